@@ -1,9 +1,11 @@
 use std::env;
 use std::io;
+use std::io::IsTerminal;
 use std::path;
 
 use anyhow::bail;
 use anyhow::Result;
+use chrono::Local;
 use clap::builder::PossibleValuesParser;
 use clap::value_parser;
 use clap::Arg;
@@ -20,15 +22,36 @@ use tokio::fs;
 use tokio::io::AsyncWriteExt;
 use yansi::Paint;
 
+use crate::application::dashboard;
 use crate::configuration::Config;
 use crate::configuration::ConfigKey;
+use crate::domain::models::Analytics;
 use crate::domain::models::BackendName;
+use crate::domain::models::BridgeName;
+use crate::domain::models::ColorMode;
+use crate::domain::models::ContextOverflow;
 use crate::domain::models::EditorName;
+use crate::domain::models::EvalPrompt;
+use crate::domain::models::EvalResult;
+use crate::domain::models::LocaleFormat;
+use crate::domain::models::ModelPrices;
 use crate::domain::models::Session;
+use crate::domain::models::SessionDiff;
+use crate::domain::models::TicketProviderName;
 use crate::domain::services::actions::help_text;
+use crate::domain::services::AuditLog;
+use crate::domain::services::BridgeService;
+use crate::domain::services::Evaluator;
+use crate::domain::services::SandboxMode;
 use crate::domain::services::Sessions;
+use crate::domain::services::Shell as ShellRunner;
 use crate::domain::services::Syntaxes;
 use crate::domain::services::Themes;
+use crate::domain::services::COMPACT_LAYOUT_HEIGHT;
+use crate::infrastructure::backends::balanced::LoadBalancing;
+use crate::infrastructure::backends::llamacpp::LlamaCppEndpoint;
+use crate::infrastructure::backends::BackendManager;
+use crate::infrastructure::bridges::BridgeManager;
 
 fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
     generate(gen, cmd, cmd.get_name().to_string(), &mut io::stdout());
@@ -38,13 +61,19 @@ fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
 fn format_session(session: &Session) -> String {
     let mut res = format!(
         "- (ID: {}) {}, Model: {}",
-        session.id, session.timestamp, session.state.backend_model,
+        session.id,
+        LocaleFormat::from_config().timestamp(&session.timestamp),
+        session.state.backend_model,
     );
 
     if !session.state.editor_language.is_empty() {
         res = format!("{res}, Lang: {}", session.state.editor_language)
     }
 
+    if !session.state.sampling.is_default() {
+        res = format!("{res}, Sampling: {}", session.state.sampling.format())
+    }
+
     if !session.state.messages.is_empty() {
         let mut line = session.state.messages[0]
             .text
@@ -82,6 +111,40 @@ async fn print_sessions_list() -> Result<()> {
     return Ok(());
 }
 
+async fn print_audit_log(limit: Option<usize>, show_path: bool) -> Result<()> {
+    let audit = AuditLog::default();
+    if show_path {
+        println!("{}", audit.path.to_string_lossy());
+        return Ok(());
+    }
+
+    let entries = audit.entries().await?;
+    let start = entries.len().saturating_sub(limit.unwrap_or(entries.len()));
+    if entries.is_empty() {
+        println!("Nothing has been run on your behalf yet.");
+    } else {
+        let lines = entries[start..]
+            .iter()
+            .map(|e| return e.format())
+            .collect::<Vec<String>>();
+        println!("{}", lines.join("\n"));
+    }
+
+    return Ok(());
+}
+
+async fn write_default_config_file(config_file_path: &path::Path) -> Result<()> {
+    if !config_file_path.parent().unwrap().exists() {
+        fs::create_dir_all(config_file_path.parent().unwrap()).await?;
+    }
+
+    let mut file = fs::File::create(config_file_path).await?;
+    file.write_all(Config::serialize_default(build()).as_bytes())
+        .await?;
+
+    return Ok(());
+}
+
 async fn create_config_file() -> Result<()> {
     let config_file_path_str = Config::default(ConfigKey::ConfigFile);
     let config_file_path = path::PathBuf::from(&config_file_path_str);
@@ -91,23 +154,37 @@ async fn create_config_file() -> Result<()> {
         ));
     }
 
-    if !config_file_path.parent().unwrap().exists() {
-        fs::create_dir_all(config_file_path.parent().unwrap()).await?;
-    }
-
-    let mut file = fs::File::create(config_file_path.clone()).await?;
-    file.write_all(Config::serialize_default(build()).as_bytes())
-        .await?;
+    write_default_config_file(&config_file_path).await?;
 
     let config_path_display = config_file_path.as_os_str().to_str().unwrap();
     println!("Created default config file at {config_path_display}");
     return Ok(());
 }
 
+/// Path of the config file in use, which is created with the defaults when
+/// it doesn't exist yet.
+pub async fn ensure_config_file() -> Result<String> {
+    let mut config_file_path_str = Config::get(ConfigKey::ConfigFile);
+    if config_file_path_str.is_empty() {
+        config_file_path_str = Config::default(ConfigKey::ConfigFile);
+    }
+    let config_file_path = path::PathBuf::from(&config_file_path_str);
+    if !config_file_path.exists() {
+        write_default_config_file(&config_file_path).await?;
+    }
+
+    return Ok(config_file_path_str);
+}
+
 async fn load_config_from_session(session_id: &str) -> Result<()> {
     let session = Sessions::default().load(session_id).await?;
     Config::set(ConfigKey::Backend, &session.state.backend_name);
     Config::set(ConfigKey::Model, &session.state.backend_model);
+    session.state.sampling.apply_to_config();
+    Config::set(
+        ConfigKey::SystemPrompt,
+        &session.state.system_prompt.unwrap_or_default(),
+    );
     Config::set(ConfigKey::SessionID, session_id);
 
     return Ok(());
@@ -263,6 +340,209 @@ fn subcommand_chat() -> Command {
         .arg(arg_model());
 }
 
+fn subcommand_fix() -> Command {
+    return Command::new("fix")
+        .about("Runs a command, and if it fails, starts a chat seeded with its error output and the source files it references.")
+        .arg(arg_backend())
+        .arg(arg_backend_health_check_timeout())
+        .arg(arg_model())
+        .arg(
+            Arg::new("command")
+                .help("Command to run, passed after `--`. For example `oatmeal fix -- cargo build`.")
+                .num_args(1..)
+                .required(true)
+                .allow_hyphen_values(true)
+                .last(true),
+        );
+}
+
+async fn load_fix_prompt(args: Vec<String>) -> Result<bool> {
+    println!("Running {}", args.join(" "));
+    let output = ShellRunner::run_args(&args).await?;
+    AuditLog::default().record_shell(&output).await?;
+    if output.success {
+        println!("The command succeeded, there's nothing to fix.");
+        return Ok(false);
+    }
+
+    let prompt = ShellRunner::fix_prompt(&output, &env::current_dir()?);
+    Config::set(ConfigKey::InitialPrompt, &prompt);
+
+    return Ok(true);
+}
+
+fn subcommand_audit() -> Command {
+    return Command::new("audit")
+        .about("Review the tools run, commands executed, and files written on your behalf.")
+        .arg(
+            Arg::new("limit")
+                .short('n')
+                .long("limit")
+                .help("Only show the most recent entries.")
+                .num_args(1)
+                .value_parser(value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("path")
+                .long("path")
+                .help("Print the audit log file path.")
+                .num_args(0)
+                .action(ArgAction::SetTrue),
+        );
+}
+
+fn subcommand_eval() -> Command {
+    return Command::new("eval")
+        .about("Runs every prompt in a JSON lines file against one or more models without the chat, writing the responses with latency and token metrics to an output file.")
+        .arg(arg_backend())
+        .arg(arg_backend_health_check_timeout())
+        .arg(
+            Arg::new("prompts")
+                .help("File with a JSON object per line, such as {\"id\": \"fizzbuzz\", \"prompt\": \"Write fizzbuzz in Rust\"}.")
+                .num_args(1)
+                .required(true),
+        )
+        .arg(
+            Arg::new("models")
+                .short('m')
+                .long("models")
+                .help("Comma separated models to evaluate. Defaults to the configured model, or the first model available from the backend.")
+                .num_args(1)
+                .value_delimiter(','),
+        )
+        .arg(
+            Arg::new("output")
+                .short('o')
+                .long("output")
+                .help("File the results are written to, as a JSON object per line.")
+                .num_args(1)
+                .default_value("eval-results.jsonl"),
+        );
+}
+
+async fn run_eval(prompts_path: &str, mut models: Vec<String>, output: &str) -> Result<()> {
+    let prompts = EvalPrompt::parse_all(&fs::read_to_string(prompts_path).await?)?;
+    if prompts.is_empty() {
+        bail!(format!("{prompts_path} doesn't contain any prompts"));
+    }
+
+    let backend =
+        BackendManager::get(BackendName::parse(Config::get(ConfigKey::Backend)).unwrap())?;
+    backend.health_check().await?;
+    if models.is_empty() {
+        let mut model = Config::get(ConfigKey::Model);
+        if model.is_empty() {
+            model = backend
+                .list_models()
+                .await?
+                .first()
+                .cloned()
+                .unwrap_or_default();
+        }
+        models.push(model);
+    }
+
+    let evaluator = Evaluator::new(backend);
+    let mut results = vec![];
+    for model in models {
+        println!("Evaluating {} prompts with {model}", prompts.len());
+        results.extend(evaluator.run(&model, &prompts).await);
+    }
+
+    Evaluator::write_results(path::Path::new(output), &results).await?;
+    println!(
+        "\n{}\n\nWrote results to {output}",
+        EvalResult::summary(&results, &LocaleFormat::from_config())
+    );
+
+    return Ok(());
+}
+
+fn subcommand_bridge() -> Command {
+    return Command::new("bridge")
+        .about("Answers messages mentioning Oatmeal in an IRC channel or Matrix room, keeping a session per room.")
+        .arg(arg_backend())
+        .arg(arg_backend_health_check_timeout())
+        .arg(arg_model())
+        .arg(
+            Arg::new("network")
+                .help("Chat network to bridge to.")
+                .num_args(1)
+                .required(true)
+                .value_parser(PossibleValuesParser::new(BridgeName::VARIANTS)),
+        );
+}
+
+fn subcommand_join() -> Command {
+    return Command::new("join")
+        .about("Joins a session shared by another Oatmeal instance with `/share`.")
+        .arg(
+            Arg::new("address")
+                .help("Address the session is shared on, such as 192.168.1.10:7878.")
+                .num_args(1)
+                .required(true),
+        )
+        .arg(
+            Arg::new("token")
+                .help("Token the host was shown when it started sharing.")
+                .num_args(1)
+                .required(true),
+        );
+}
+
+fn subcommand_stats() -> Command {
+    return Command::new("stats")
+        .about("Shows a dashboard of messages per day, models used, estimated tokens and costs, and the busiest projects from sessions saved in the last 30 days. Everything is worked out locally from the saved sessions.")
+        .arg(
+            Arg::new("all")
+                .long("all")
+                .help("Include every saved session.")
+                .num_args(0)
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("csv")
+                .long("csv")
+                .help("Write one row per session to a CSV file instead, or to stdout with -.")
+                .num_args(1),
+        );
+}
+
+/// Days of sessions `oatmeal stats` includes without `--all`.
+const STATS_DEFAULT_DAYS: i64 = 30;
+
+async fn run_stats(all: bool, csv: Option<&String>) -> Result<()> {
+    let prices = ModelPrices::parse(&Config::get(ConfigKey::ModelPrices));
+    let mut analytics = Analytics::new(&Sessions::default().load_all().await?, &prices);
+    if !all {
+        analytics =
+            analytics.since(Local::now().date_naive() - chrono::Duration::days(STATS_DEFAULT_DAYS));
+    }
+
+    if let Some(path) = csv {
+        if path == "-" {
+            print!("{}", analytics.to_csv());
+        } else {
+            fs::write(path, analytics.to_csv()).await?;
+            println!("Wrote {} sessions to {path}", analytics.sessions.len());
+        }
+        return Ok(());
+    }
+
+    if analytics.sessions.is_empty() {
+        println!("There are no saved sessions to show stats for yet.");
+        return Ok(());
+    }
+
+    let locale = LocaleFormat::from_config();
+    if !io::stdout().is_terminal() {
+        println!("{}", analytics.summary(&locale));
+        return Ok(());
+    }
+
+    return dashboard::start(&analytics, &locale);
+}
+
 fn subcommand_sessions() -> Command {
     return Command::new("sessions")
         .about("Manage past chat sessions.")
@@ -280,7 +560,30 @@ fn subcommand_sessions() -> Command {
                         .required(false),
                 ),
         )
-        .subcommand(subcommand_sessions_delete());
+        .subcommand(subcommand_sessions_delete())
+        .subcommand(
+            Command::new("diff")
+                .about("Compare two sessions prompt by prompt, showing where their answers diverged.")
+                .arg(Arg::new("left").help("Session ID").required(true))
+                .arg(Arg::new("right").help("Session ID").required(true)),
+        );
+}
+
+async fn print_sessions_diff(left_id: &str, right_id: &str) -> Result<()> {
+    let sessions = Sessions::default();
+    let left = sessions.load(left_id).await?;
+    let right = sessions.load(right_id).await?;
+
+    let diff = SessionDiff::new(&left.state.messages, &right.state.messages);
+    println!(
+        "{}",
+        diff.render(
+            &format!("{left_id} ({})", left.state.backend_model),
+            &format!("{right_id} ({})", right.state.backend_model),
+        )
+    );
+
+    return Ok(());
 }
 
 pub fn build() -> Command {
@@ -319,12 +622,18 @@ pub fn build() -> Command {
         .version(env!("CARGO_PKG_VERSION"))
         .after_help(commands_text)
         .arg_required_else_help(false)
+        .subcommand(subcommand_audit())
         .subcommand(subcommand_chat())
         .subcommand(subcommand_completions())
         .subcommand(subcommand_config())
         .subcommand(subcommand_debug())
+        .subcommand(subcommand_eval())
+        .subcommand(subcommand_bridge())
+        .subcommand(subcommand_fix())
+        .subcommand(subcommand_join())
         .subcommand(Command::new("manpages").about("Generates manpages and outputs to stdout."))
         .subcommand(subcommand_sessions())
+        .subcommand(subcommand_stats())
         .arg(arg_backend())
         .arg(arg_backend_health_check_timeout())
         .arg(arg_model())
@@ -347,6 +656,213 @@ pub fn build() -> Command {
                 .value_parser(PossibleValuesParser::new(EditorName::VARIANTS))
                 .global(true),
         )
+        .arg(
+            Arg::new(ConfigKey::CheckCommand.to_string())
+                .long(ConfigKey::CheckCommand.to_string())
+                .env("OATMEAL_CHECK_COMMAND")
+                .num_args(1)
+                .help("Linter run by `/check`, which should print diagnostics as `FILE:LINE:COLUMN: MESSAGE`. Inferred from the project when not set, such as `cargo clippy --message-format=short`.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::EmbeddingModel.to_string())
+                .long(ConfigKey::EmbeddingModel.to_string())
+                .env("OATMEAL_EMBEDDING_MODEL")
+                .num_args(1)
+                .help("Model used by `/similar` to embed messages, such as nomic-embed-text with Ollama. Defaults to the chat model with Ollama, and text-embedding-3-small with OpenAI.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::EmbeddingProvider.to_string())
+                .long(ConfigKey::EmbeddingProvider.to_string())
+                .env("OATMEAL_EMBEDDING_PROVIDER")
+                .num_args(1)
+                .help(format!("Where `/similar` embeds messages. `local` runs a model on device, downloading `embedding-model` from Hugging Face (sentence-transformers/all-MiniLM-L6-v2 unless set to a repository), and requires building with the `local-embeddings` feature. [default: {}]", Config::default(ConfigKey::EmbeddingProvider)))
+                .value_parser(PossibleValuesParser::new(["backend", "local"]))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::Seed.to_string())
+                .long(ConfigKey::Seed.to_string())
+                .env("OATMEAL_SEED")
+                .num_args(1)
+                .help("Seed for generating answers, so they can be reproduced with Ollama or an OpenAI compatible server such as llama.cpp. Can be changed in the chat with `/set seed`, and is saved with the session. Random when unset.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::Temperature.to_string())
+                .long(ConfigKey::Temperature.to_string())
+                .env("OATMEAL_TEMPERATURE")
+                .num_args(1)
+                .help("Sampling temperature for answers, from 0 to 2. Lower is more focused, higher more varied. Can be changed in the chat with `/set temperature`, and is saved with the session. Uses the backend's default when unset.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::TopP.to_string())
+                .long(ConfigKey::TopP.to_string())
+                .env("OATMEAL_TOP_P")
+                .num_args(1)
+                .help("Nucleus sampling probability for answers, such as 0.9. Can be changed in the chat with `/set top_p`, and is saved with the session. Uses the backend's default when unset.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::MaxTokens.to_string())
+                .long(ConfigKey::MaxTokens.to_string())
+                .env("OATMEAL_MAX_TOKENS")
+                .num_args(1)
+                .help("Most tokens an answer can be before it's cut off. Can be changed in the chat with `/set max_tokens`, and is saved with the session. Uses the backend's default when unset.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::StopSequences.to_string())
+                .long(ConfigKey::StopSequences.to_string())
+                .env("OATMEAL_STOP_SEQUENCES")
+                .num_args(1)
+                .help("Comma separated text that ends an answer once it's generated, such as `END,###`. Can be changed in the chat with `/set stop`, and is saved with the session.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::SystemPrompt.to_string())
+                .long(ConfigKey::SystemPrompt.to_string())
+                .env("OATMEAL_SYSTEM_PROMPT")
+                .num_args(1)
+                .help("Instructions sent with every prompt as the backend's system message, such as `Answer tersely`, or the name of one of `system-prompt-presets`. Can be changed in the chat with `/system`, and is saved with the session.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::SystemPromptPresets.to_string())
+                .long(ConfigKey::SystemPromptPresets.to_string())
+                .env("OATMEAL_SYSTEM_PROMPT_PRESETS")
+                .num_args(1)
+                .help("Named system prompts for `/system NAME`, one `NAME=PROMPT` per line. The config file can set them as a `[system-prompt-presets]` table instead.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::RewriteRules.to_string())
+                .long(ConfigKey::RewriteRules.to_string())
+                .env("OATMEAL_REWRITE_RULES")
+                .num_args(1)
+                .help("Regexes to replace in your prompts before they're sent and in answers once they're done, one `NAME=PATTERN => REPLACEMENT` per line. Prefix the pattern with `prompt:` or `response:` to only rewrite one of them. Rules run in the order they're listed, and `/rewrites` shows which ones changed the last prompt and answer. The config file can set them as a `[rewrite-rules]` table instead.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ContextWindow.to_string())
+                .long(ConfigKey::ContextWindow.to_string())
+                .env("OATMEAL_CONTEXT_WINDOW")
+                .num_args(1)
+                .help("Most tokens the model can take in at once, for showing how much of it the chat uses above the input box. Known for well known models like gpt-4o, claude, and llama3 when unset.")
+                .value_parser(value_parser!(u64))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ContextOverflow.to_string())
+                .long(ConfigKey::ContextOverflow.to_string())
+                .env("OATMEAL_CONTEXT_OVERFLOW")
+                .num_args(1)
+                .help(format!("What happens to the oldest messages once the chat outgrows `context-window`, before the next prompt is sent. `summarize` asks the backend to summarize them, `truncate` leaves them out, and `off` sends everything. They stay in the chat either way. [default: {}]", Config::default(ConfigKey::ContextOverflow)))
+                .value_parser(PossibleValuesParser::new(ContextOverflow::VARIANTS))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ContextDocumentsBudget.to_string())
+                .long(ConfigKey::ContextDocumentsBudget.to_string())
+                .env("OATMEAL_CONTEXT_DOCUMENTS_BUDGET")
+                .num_args(1)
+                .help(format!("Most tokens of documents added with `/context add` to send ahead of each prompt. Documents that would go over it are left out, in the order they were added. [default: {}]", Config::default(ConfigKey::ContextDocumentsBudget)))
+                .value_parser(value_parser!(u64))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::Fallbacks.to_string())
+                .long(ConfigKey::Fallbacks.to_string())
+                .env("OATMEAL_FALLBACKS")
+                .num_args(1)
+                .help("Comma separated `backend:model` pairs to retry a prompt with, in order, when the backend fails its health check, errors, or times out before answering, such as `openai:gpt-4o-mini`. One failing partway through an answer has the next finish it. Fallbacks answer without the chat's history, and the answer notes which model responded.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::FallbackTimeout.to_string())
+                .long(ConfigKey::FallbackTimeout.to_string())
+                .env("OATMEAL_FALLBACK_TIMEOUT")
+                .num_args(1)
+                .help(format!("Time to wait in milliseconds for a health check or the start of an answer before trying the next of `fallbacks`. [default: {}]", Config::default(ConfigKey::FallbackTimeout)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::LoadBalancing.to_string())
+                .long(ConfigKey::LoadBalancing.to_string())
+                .env("OATMEAL_LOAD_BALANCING")
+                .num_args(1)
+                .help(format!("How prompts are spread between endpoints when a backend's URL lists several, separated by commas, such as two Ollama hosts. Endpoints that can't be reached are skipped for a while. [default: {}]", Config::default(ConfigKey::LoadBalancing)))
+                .value_parser(PossibleValuesParser::new(LoadBalancing::VARIANTS))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::HealthCheckInterval.to_string())
+                .long(ConfigKey::HealthCheckInterval.to_string())
+                .env("OATMEAL_HEALTH_CHECK_INTERVAL")
+                .num_args(1)
+                .help(format!("Seconds between health checks of the backend in the background, shown as a colored indicator below the input box, and 0 turns them off. The chat says when a backend that was down is back up. [default: {}]", Config::default(ConfigKey::HealthCheckInterval)))
+                .value_parser(value_parser!(u64))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::BackendRetries.to_string())
+                .long(ConfigKey::BackendRetries.to_string())
+                .env("OATMEAL_BACKEND_RETRIES")
+                .num_args(1)
+                .help(format!("Times a prompt is sent again, waiting twice as long each time, when the backend is rate limited, has a server error, or the connection drops, before showing the error. [default: {}]", Config::default(ConfigKey::BackendRetries)))
+                .value_parser(value_parser!(u32))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::Proxy.to_string())
+                .long(ConfigKey::Proxy.to_string())
+                .env("OATMEAL_PROXY")
+                .num_args(1)
+                .help("HTTP or SOCKS proxy every request to backends and other services goes through, such as `http://proxy:8080` or `socks5://proxy:1080`. Without it, the proxy from `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY` is used. Hosts in `NO_PROXY` are reached directly either way.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::BackendHeaders.to_string())
+                .long(ConfigKey::BackendHeaders.to_string())
+                .env("OATMEAL_BACKEND_HEADERS")
+                .num_args(1)
+                .help("Extra headers sent with every request to a backend, one `BACKEND=Name: value; Name: value` per line. The config file can set them as a `[backend-headers]` table instead.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::BackendTokenCommands.to_string())
+                .long(ConfigKey::BackendTokenCommands.to_string())
+                .env("OATMEAL_BACKEND_TOKEN_COMMANDS")
+                .num_args(1)
+                .help("Commands that print a short-lived bearer token for a backend, sent in place of its API key, one `BACKEND=COMMAND` per line. The token is reused until the backend answers 401, then minted again. The config file can set them as a `[backend-token-commands]` table instead.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::FewShotFile.to_string())
+                .long(ConfigKey::FewShotFile.to_string())
+                .env("OATMEAL_FEW_SHOT_FILE")
+                .num_args(1)
+                .help("JSON lines file of example exchanges, such as {\"user\": \"...\", \"assistant\": \"...\"}, sent ahead of every new conversation without showing them in the chat. Can be changed in the chat with `/fewshot`.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ModelTools.to_string())
+                .long(ConfigKey::ModelTools.to_string())
+                .env("OATMEAL_MODEL_TOOLS")
+                .num_args(1)
+                .help("Comma separated tools the model may ask to call while answering, from `shell` and `read_file`. Every call asks for your approval before it runs, and its output is sent back to the model. Follows `tool-permissions` for `/run` and `/context`. Only works with the openai backend.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::Tee.to_string())
+                .long(ConfigKey::Tee.to_string())
+                .env("OATMEAL_TEE")
+                .num_args(1)
+                .help("File to append every prompt and answer to as they stream, as a transcript kept outside of sessions. Written as markdown when it ends in `.md`, otherwise plain text. Can be changed in the chat with `/tee`.")
+                .global(true),
+        )
         .arg(
             Arg::new(ConfigKey::Theme.to_string())
                 .short('t')
@@ -368,51 +884,608 @@ pub fn build() -> Command {
                 .global(true),
         )
         .arg(
-            Arg::new(ConfigKey::LangChainURL.to_string())
-                .long(ConfigKey::LangChainURL.to_string())
-                .env("OATMEAL_LANGCHAIN_URL")
+            Arg::new(ConfigKey::ColorMode.to_string())
+                .long(ConfigKey::ColorMode.to_string())
+                .env("OATMEAL_COLOR_MODE")
                 .num_args(1)
-                .help(format!("LangChain Serve API URL when using the LangChain backend. [default: {}]", Config::default(ConfigKey::LangChainURL)))
+                .help(format!("Colors the terminal can show. Theme colors are matched to the nearest of the 256 or 16 color palettes, and `none` draws in monochrome. `auto` goes by `NO_COLOR`, `COLORTERM` and `TERM`. [default: {}]", Config::default(ConfigKey::ColorMode)))
+                .value_parser(PossibleValuesParser::new(ColorMode::VARIANTS))
                 .global(true),
         )
         .arg(
-            Arg::new(ConfigKey::OllamaURL.to_string())
-                .long(ConfigKey::OllamaURL.to_string())
-                .env("OATMEAL_OLLAMA_URL")
+            Arg::new(ConfigKey::LowBandwidth.to_string())
+                .long(ConfigKey::LowBandwidth.to_string())
+                .env("OATMEAL_LOW_BANDWIDTH")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("true")
+                .help(format!("Redraws less for slow connections such as SSH over a high latency link. Answers are drawn a few times a second rather than as they stream in, the progress bar is replaced by a plain status, animations are off, and the screen isn't redrawn while idle. [default: {}]", Config::default(ConfigKey::LowBandwidth)))
+                .value_parser(PossibleValuesParser::new(["true", "false"]))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::Animations.to_string())
+                .long(ConfigKey::Animations.to_string())
+                .env("OATMEAL_ANIMATIONS")
                 .num_args(1)
-                .help(format!("Ollama API URL when using the Ollama backend. [default: {}]", Config::default(ConfigKey::OllamaURL)))
+                .help(format!("Animate the chat, with a spinner while waiting on the backend, a blinking cursor after answers as they stream in, and new bubbles fading in. [default: {}]", Config::default(ConfigKey::Animations)))
+                .value_parser(PossibleValuesParser::new(["true", "false"]))
                 .global(true),
         )
         .arg(
-            Arg::new(ConfigKey::OpenAiURL.to_string())
-                .long(ConfigKey::OpenAiURL.to_string())
-                .env("OATMEAL_OPENAI_URL")
+            Arg::new(ConfigKey::Layout.to_string())
+                .long(ConfigKey::Layout.to_string())
+                .env("OATMEAL_LAYOUT")
                 .num_args(1)
-                .help(format!("OpenAI API URL when using the OpenAI backend. Can be swapped to a compatible proxy. [default: {}]", Config::default(ConfigKey::OpenAiURL)))
+                .help(format!("How chat bubbles are laid out. `compact` drops their borders for a line with the author, fitting more of the chat on screen, and `auto` uses it on terminals under {COMPACT_LAYOUT_HEIGHT} rows. [default: {}]", Config::default(ConfigKey::Layout)))
+                .value_parser(PossibleValuesParser::new(["auto", "compact", "comfortable"]))
                 .global(true),
         )
         .arg(
-            Arg::new(ConfigKey::OpenAiToken.to_string())
-                .long(ConfigKey::OpenAiToken.to_string())
-                .env("OATMEAL_OPENAI_TOKEN")
+            Arg::new(ConfigKey::OutlineRatio.to_string())
+                .long(ConfigKey::OutlineRatio.to_string())
+                .env("OATMEAL_OUTLINE_RATIO")
                 .num_args(1)
-                .help("OpenAI API token when using the OpenAI backend.")
+                .help(format!("Percentage of the chat's width the outline pane takes, from 15 to 60. Resizing it with ALT+- and ALT+= saves the new size here. [default: {}]", Config::default(ConfigKey::OutlineRatio)))
                 .global(true),
         )
         .arg(
-            Arg::new(ConfigKey::ClaudeToken.to_string())
-                .long(ConfigKey::ClaudeToken.to_string())
-                .env("OATMEAL_CLAUDE_TOKEN")
+            Arg::new(ConfigKey::Locale.to_string())
+                .long(ConfigKey::Locale.to_string())
+                .env("OATMEAL_LOCALE")
                 .num_args(1)
-                .help("Anthropic's Claude API token when using the Claude backend.")
+                .help("Locale used to format numbers, dates, and durations, such as de_DE. Defaults to the locale from LC_ALL, LC_NUMERIC, or LANG.")
                 .global(true),
         )
         .arg(
-            Arg::new(ConfigKey::GeminiToken.to_string())
-                .long(ConfigKey::GeminiToken.to_string())
-                .env("OATMEAL_GEMINI_TOKEN")
+            Arg::new(ConfigKey::ModelPrices.to_string())
+                .long(ConfigKey::ModelPrices.to_string())
+                .env("OATMEAL_MODEL_PRICES")
                 .num_args(1)
-                .help("Google Gemini API token when using the Gemini backend.")
+                .help("Comma separated dollars per million tokens for each model, such as gpt-4=30,gpt-3.5-turbo=0.5, used to estimate costs in `oatmeal stats` and the chat's token usage.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ShowTokenUsage.to_string())
+                .long(ConfigKey::ShowTokenUsage.to_string())
+                .env("OATMEAL_SHOW_TOKEN_USAGE")
+                .num_args(1)
+                .help(format!("Show each message's estimated tokens and cost below it, which `/usage` toggles. [default: {}]", Config::default(ConfigKey::ShowTokenUsage)))
+                .value_parser(PossibleValuesParser::new(["true", "false"]))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::FenceCode.to_string())
+                .long(ConfigKey::FenceCode.to_string())
+                .env("OATMEAL_FENCE_CODE")
+                .num_args(1)
+                .help(format!("Wrap code pasted in to a prompt without code fences in a code block of the language it looks like. [default: {}]", Config::default(ConfigKey::FenceCode)))
+                .value_parser(PossibleValuesParser::new(["true", "false"]))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::InputMaxLines.to_string())
+                .long(ConfigKey::InputMaxLines.to_string())
+                .env("OATMEAL_INPUT_MAX_LINES")
+                .num_args(1)
+                .help(format!("Most lines the input box grows to as a prompt gets longer, after which it scrolls. It shrinks back once the prompt is sent. [default: {}]", Config::default(ConfigKey::InputMaxLines)))
+                .value_parser(value_parser!(u16).range(1..))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::SuggestReplies.to_string())
+                .long(ConfigKey::SuggestReplies.to_string())
+                .env("OATMEAL_SUGGEST_REPLIES")
+                .num_args(1)
+                .help(format!("After each answer, suggest follow-up prompts above the input box for Tab to fill in. Each suggestion costs an extra request to the backend. [default: {}]", Config::default(ConfigKey::SuggestReplies)))
+                .value_parser(PossibleValuesParser::new(["true", "false"]))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ResponseCache.to_string())
+                .long(ConfigKey::ResponseCache.to_string())
+                .env("OATMEAL_RESPONSE_CACHE")
+                .num_args(1)
+                .help(format!("Keep answers on disk, replaying the cached answer when the same prompt is sent again with the same backend, model and parameters, rather than asking the backend. Useful for demos and trying out prompt templates without spending tokens. [default: {}]", Config::default(ConfigKey::ResponseCache)))
+                .value_parser(PossibleValuesParser::new(["true", "false"]))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ModelCacheTtl.to_string())
+                .long(ConfigKey::ModelCacheTtl.to_string())
+                .env("OATMEAL_MODEL_CACHE_TTL")
+                .num_args(1)
+                .help(format!("Seconds to keep each backend's model list on disk, so starting the chat and `/modellist` don't wait on the backend. `/modelrefresh` fetches it again sooner, and 0 turns the cache off. [default: {}]", Config::default(ConfigKey::ModelCacheTtl)))
+                .value_parser(value_parser!(u64))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ProfileStartup.to_string())
+                .long(ConfigKey::ProfileStartup.to_string())
+                .env("OATMEAL_PROFILE_STARTUP")
+                .num_args(0..=1)
+                .require_equals(true)
+                .default_missing_value("true")
+                .help(format!("Print how long each phase of starting the chat took, once it exits. [default: {}]", Config::default(ConfigKey::ProfileStartup)))
+                .value_parser(PossibleValuesParser::new(["true", "false"]))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::LangChainURL.to_string())
+                .long(ConfigKey::LangChainURL.to_string())
+                .env("OATMEAL_LANGCHAIN_URL")
+                .num_args(1)
+                .help(format!("LangChain Serve API URL when using the LangChain backend. [default: {}]", Config::default(ConfigKey::LangChainURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::OllamaURL.to_string())
+                .long(ConfigKey::OllamaURL.to_string())
+                .env("OATMEAL_OLLAMA_URL")
+                .num_args(1)
+                .help(format!("Ollama API URL when using the Ollama backend. Several can be listed, separated by commas, to balance prompts between hosts with `load-balancing`. [default: {}]", Config::default(ConfigKey::OllamaURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::OllamaKeepAlive.to_string())
+                .long(ConfigKey::OllamaKeepAlive.to_string())
+                .env("OATMEAL_OLLAMA_KEEP_ALIVE")
+                .num_args(1)
+                .help("How long Ollama keeps the model loaded after a request, such as 10m, 1h, or -1 to keep it loaded indefinitely. Uses Ollama's default when unset.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::OllamaPreload.to_string())
+                .long(ConfigKey::OllamaPreload.to_string())
+                .env("OATMEAL_OLLAMA_PRELOAD")
+                .num_args(1)
+                .help(format!("Load the model into Ollama when the chat opens, so the first message isn't delayed by a cold start. [default: {}]", Config::default(ConfigKey::OllamaPreload)))
+                .value_parser(PossibleValuesParser::new(["true", "false"]))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::OpenAiURL.to_string())
+                .long(ConfigKey::OpenAiURL.to_string())
+                .env("OATMEAL_OPENAI_URL")
+                .num_args(1)
+                .help(format!("OpenAI API URL when using the OpenAI backend. Can be swapped to a compatible proxy. [default: {}]", Config::default(ConfigKey::OpenAiURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::OpenAiToken.to_string())
+                .long(ConfigKey::OpenAiToken.to_string())
+                .env("OATMEAL_OPENAI_TOKEN")
+                .num_args(1)
+                .help("OpenAI API token when using the OpenAI backend.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::OpenRouterURL.to_string())
+                .long(ConfigKey::OpenRouterURL.to_string())
+                .env("OATMEAL_OPENROUTER_URL")
+                .num_args(1)
+                .help(format!("OpenRouter API URL when using the OpenRouter backend. [default: {}]", Config::default(ConfigKey::OpenRouterURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::OpenRouterToken.to_string())
+                .long(ConfigKey::OpenRouterToken.to_string())
+                .env("OATMEAL_OPENROUTER_TOKEN")
+                .num_args(1)
+                .help("OpenRouter API key when using the OpenRouter backend.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::AzureURL.to_string())
+                .long(ConfigKey::AzureURL.to_string())
+                .env("OATMEAL_AZURE_URL")
+                .num_args(1)
+                .help("Endpoint of the Azure OpenAI resource when using the Azure backend, such as https://RESOURCE.openai.azure.com. The model is the name of a deployment on it.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::AzureToken.to_string())
+                .long(ConfigKey::AzureToken.to_string())
+                .env("OATMEAL_AZURE_TOKEN")
+                .num_args(1)
+                .help("Azure OpenAI API key when using the Azure backend.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::AzureApiVersion.to_string())
+                .long(ConfigKey::AzureApiVersion.to_string())
+                .env("OATMEAL_AZURE_API_VERSION")
+                .num_args(1)
+                .help(format!("Azure OpenAI API version requests are made with. [default: {}]", Config::default(ConfigKey::AzureApiVersion)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::AwsAccessKeyId.to_string())
+                .long(ConfigKey::AwsAccessKeyId.to_string())
+                .env("OATMEAL_AWS_ACCESS_KEY_ID")
+                .num_args(1)
+                .help("AWS access key ID when using the Bedrock backend. Falls back to AWS_ACCESS_KEY_ID.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::AwsSecretAccessKey.to_string())
+                .long(ConfigKey::AwsSecretAccessKey.to_string())
+                .env("OATMEAL_AWS_SECRET_ACCESS_KEY")
+                .num_args(1)
+                .help("AWS secret access key when using the Bedrock backend. Falls back to AWS_SECRET_ACCESS_KEY.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::AwsSessionToken.to_string())
+                .long(ConfigKey::AwsSessionToken.to_string())
+                .env("OATMEAL_AWS_SESSION_TOKEN")
+                .num_args(1)
+                .help("AWS session token for temporary credentials when using the Bedrock backend. Falls back to AWS_SESSION_TOKEN.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::AwsRegion.to_string())
+                .long(ConfigKey::AwsRegion.to_string())
+                .env("OATMEAL_AWS_REGION")
+                .num_args(1)
+                .help(format!("AWS region Bedrock models are called in. [default: {}]", Config::default(ConfigKey::AwsRegion)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::MistralURL.to_string())
+                .long(ConfigKey::MistralURL.to_string())
+                .env("OATMEAL_MISTRAL_URL")
+                .num_args(1)
+                .help(format!("Mistral API URL when using the Mistral backend. [default: {}]", Config::default(ConfigKey::MistralURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::MistralToken.to_string())
+                .long(ConfigKey::MistralToken.to_string())
+                .env("OATMEAL_MISTRAL_TOKEN")
+                .num_args(1)
+                .help("Mistral API key when using the Mistral backend.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::GroqURL.to_string())
+                .long(ConfigKey::GroqURL.to_string())
+                .env("OATMEAL_GROQ_URL")
+                .num_args(1)
+                .help(format!("Groq API URL when using the Groq backend. [default: {}]", Config::default(ConfigKey::GroqURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::GroqToken.to_string())
+                .long(ConfigKey::GroqToken.to_string())
+                .env("OATMEAL_GROQ_TOKEN")
+                .num_args(1)
+                .help("Groq API key when using the Groq backend. Falls back to GROQ_API_KEY.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::CohereURL.to_string())
+                .long(ConfigKey::CohereURL.to_string())
+                .env("OATMEAL_COHERE_URL")
+                .num_args(1)
+                .help(format!("Cohere API URL when using the Cohere backend. [default: {}]", Config::default(ConfigKey::CohereURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::CohereToken.to_string())
+                .long(ConfigKey::CohereToken.to_string())
+                .env("OATMEAL_COHERE_TOKEN")
+                .num_args(1)
+                .help("Cohere API key when using the Cohere backend.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::CoherePreamble.to_string())
+                .long(ConfigKey::CoherePreamble.to_string())
+                .env("OATMEAL_COHERE_PREAMBLE")
+                .num_args(1)
+                .help("Preamble sent with every Cohere chat request, replacing Cohere's default system message.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::TogetherURL.to_string())
+                .long(ConfigKey::TogetherURL.to_string())
+                .env("OATMEAL_TOGETHER_URL")
+                .num_args(1)
+                .help(format!("Together AI API URL when using the Together backend. [default: {}]", Config::default(ConfigKey::TogetherURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::TogetherToken.to_string())
+                .long(ConfigKey::TogetherToken.to_string())
+                .env("OATMEAL_TOGETHER_TOKEN")
+                .num_args(1)
+                .help("Together AI API key when using the Together backend. Falls back to TOGETHER_API_KEY.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::PerplexityURL.to_string())
+                .long(ConfigKey::PerplexityURL.to_string())
+                .env("OATMEAL_PERPLEXITY_URL")
+                .num_args(1)
+                .help(format!("Perplexity API URL when using the Perplexity backend. [default: {}]", Config::default(ConfigKey::PerplexityURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::PerplexityToken.to_string())
+                .long(ConfigKey::PerplexityToken.to_string())
+                .env("OATMEAL_PERPLEXITY_TOKEN")
+                .num_args(1)
+                .help("Perplexity API key when using the Perplexity backend. Falls back to PERPLEXITY_API_KEY.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::LlamaCppURL.to_string())
+                .long(ConfigKey::LlamaCppURL.to_string())
+                .env("OATMEAL_LLAMA_CPP_URL")
+                .num_args(1)
+                .help(format!("llama.cpp server URL when using the llamacpp backend. [default: {}]", Config::default(ConfigKey::LlamaCppURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::LlamaCppToken.to_string())
+                .long(ConfigKey::LlamaCppToken.to_string())
+                .env("OATMEAL_LLAMA_CPP_TOKEN")
+                .num_args(1)
+                .help("API key when the llama.cpp server was started with `--api-key`.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::LlamaCppEndpoint.to_string())
+                .long(ConfigKey::LlamaCppEndpoint.to_string())
+                .env("OATMEAL_LLAMA_CPP_ENDPOINT")
+                .num_args(1)
+                .help(format!("llama.cpp endpoint to prompt: chat applies the model's chat template, while completion continues a plain transcript, for models without one. Either reuses the server's prompt cache for the conversation. [default: {}]", Config::default(ConfigKey::LlamaCppEndpoint)))
+                .value_parser(PossibleValuesParser::new(LlamaCppEndpoint::VARIANTS))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::LmStudioURL.to_string())
+                .long(ConfigKey::LmStudioURL.to_string())
+                .env("OATMEAL_LM_STUDIO_URL")
+                .num_args(1)
+                .help(format!("LM Studio server URL when using the lmstudio backend. [default: {}]", Config::default(ConfigKey::LmStudioURL)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::CandleModelsDir.to_string())
+                .long(ConfigKey::CandleModelsDir.to_string())
+                .env("OATMEAL_CANDLE_MODELS_DIR")
+                .num_args(1)
+                .help("Directory of GGUF models for the candle backend, each with a Hugging Face tokenizer saved beside it as MODEL.tokenizer.json or tokenizer.json. Requires building with the `local-inference` feature. Answers are sampled with `temperature` 0.8 and up to 1024 `max-tokens` unless they're set. [default: oatmeal/models in your cache directory]")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ClaudeToken.to_string())
+                .long(ConfigKey::ClaudeToken.to_string())
+                .env("OATMEAL_CLAUDE_TOKEN")
+                .num_args(1)
+                .help("Anthropic's Claude API token when using the Claude backend.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::GeminiToken.to_string())
+                .long(ConfigKey::GeminiToken.to_string())
+                .env("OATMEAL_GEMINI_TOKEN")
+                .num_args(1)
+                .help("Google Gemini API token when using the Gemini backend.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::GithubToken.to_string())
+                .long(ConfigKey::GithubToken.to_string())
+                .env("OATMEAL_GITHUB_TOKEN")
+                .num_args(1)
+                .help("GitHub API token used by `/gh` when the `gh` CLI isn't available.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::AutoContinue.to_string())
+                .long(ConfigKey::AutoContinue.to_string())
+                .env("OATMEAL_AUTO_CONTINUE")
+                .num_args(1)
+                .help(format!("Continue answers that reach the model's output limit automatically, instead of offering `/continue`. [default: {}]", Config::default(ConfigKey::AutoContinue)))
+                .value_parser(PossibleValuesParser::new(["true", "false"]))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ScanContext.to_string())
+                .long(ConfigKey::ScanContext.to_string())
+                .env("OATMEAL_SCAN_CONTEXT")
+                .num_args(1)
+                .help(format!("Warn when context attached with commands such as `/gh` contains text that reads like instructions for the model. [default: {}]", Config::default(ConfigKey::ScanContext)))
+                .value_parser(PossibleValuesParser::new(["true", "false"]))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ContextHeadLines.to_string())
+                .long(ConfigKey::ContextHeadLines.to_string())
+                .env("OATMEAL_CONTEXT_HEAD_LINES")
+                .num_args(1)
+                .help(format!("Lines kept from the start of an editor selection too long to send whole. [default: {}]", Config::default(ConfigKey::ContextHeadLines)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ContextTailLines.to_string())
+                .long(ConfigKey::ContextTailLines.to_string())
+                .env("OATMEAL_CONTEXT_TAIL_LINES")
+                .num_args(1)
+                .help(format!("Lines kept from the end of an editor selection too long to send whole. [default: {}]", Config::default(ConfigKey::ContextTailLines)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::HistoryMemoryLimit.to_string())
+                .long(ConfigKey::HistoryMemoryLimit.to_string())
+                .env("OATMEAL_HISTORY_MEMORY_LIMIT")
+                .num_args(1)
+                .help(format!("Megabytes of message text kept in memory. Beyond it, the oldest messages are moved to disk and read back when needed. 0 keeps everything in memory. [default: {}]", Config::default(ConfigKey::HistoryMemoryLimit)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ToolConcurrency.to_string())
+                .long(ConfigKey::ToolConcurrency.to_string())
+                .env("OATMEAL_TOOL_CONCURRENCY")
+                .num_args(1)
+                .help(format!("Maximum number of tools, such as `/test` or `/gh`, that run at once. Others wait in a queue. [default: {}]", Config::default(ConfigKey::ToolConcurrency)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ToolCooldown.to_string())
+                .long(ConfigKey::ToolCooldown.to_string())
+                .env("OATMEAL_TOOL_COOLDOWN")
+                .num_args(1)
+                .help(format!("Time in milliseconds between repeated calls to the same tool. [default: {}]", Config::default(ConfigKey::ToolCooldown)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ToolPermissions.to_string())
+                .long(ConfigKey::ToolPermissions.to_string())
+                .env("OATMEAL_TOOL_PERMISSIONS")
+                .num_args(1)
+                .help("Whether each tool is allowed to run, asks first, or is denied, as comma separated `TOOL=allow|ask|deny` pairs such as `post=ask,review=deny`. `*` sets the default for other tools.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ToolPaths.to_string())
+                .long(ConfigKey::ToolPaths.to_string())
+                .env("OATMEAL_TOOL_PATHS")
+                .num_args(1)
+                .help("Comma separated directories that `/apply` and output redirects may write files within. Files can be written anywhere when not set.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::ToolHosts.to_string())
+                .long(ConfigKey::ToolHosts.to_string())
+                .env("OATMEAL_TOOL_HOSTS")
+                .num_args(1)
+                .help("Comma separated hosts that tools may connect to, such as `api.github.com,*.atlassian.net`. Any host is allowed when not set.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::RunSandbox.to_string())
+                .long(ConfigKey::RunSandbox.to_string())
+                .env("OATMEAL_RUN_SANDBOX")
+                .num_args(1)
+                .help(format!("Where `/run` executes code blocks: a docker or podman container with the working tree mounted read-only, a temporary copy of the working tree, which keeps its files from changing without isolating scripts from the rest of the machine, or directly in it with none. [default: {}]", Config::default(ConfigKey::RunSandbox)))
+                .value_parser(PossibleValuesParser::new(SandboxMode::VARIANTS))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::RunImage.to_string())
+                .long(ConfigKey::RunImage.to_string())
+                .env("OATMEAL_RUN_IMAGE")
+                .num_args(1)
+                .help(format!("Container image `/run` uses with the docker and podman sandboxes. [default: {}]", Config::default(ConfigKey::RunImage)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::TicketProvider.to_string())
+                .long(ConfigKey::TicketProvider.to_string())
+                .env("OATMEAL_TICKET_PROVIDER")
+                .num_args(1)
+                .help("Issue tracker used by `/ticket`.")
+                .value_parser(PossibleValuesParser::new(TicketProviderName::VARIANTS))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::JiraURL.to_string())
+                .long(ConfigKey::JiraURL.to_string())
+                .env("OATMEAL_JIRA_URL")
+                .num_args(1)
+                .help("Jira site URL when using the Jira ticket provider, such as https://example.atlassian.net.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::JiraEmail.to_string())
+                .long(ConfigKey::JiraEmail.to_string())
+                .env("OATMEAL_JIRA_EMAIL")
+                .num_args(1)
+                .help("Email of the account the Jira API token belongs to.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::JiraToken.to_string())
+                .long(ConfigKey::JiraToken.to_string())
+                .env("OATMEAL_JIRA_TOKEN")
+                .num_args(1)
+                .help("Jira API token when using the Jira ticket provider.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::LinearToken.to_string())
+                .long(ConfigKey::LinearToken.to_string())
+                .env("OATMEAL_LINEAR_TOKEN")
+                .num_args(1)
+                .help("Linear API key when using the Linear ticket provider.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::BridgeNick.to_string())
+                .long(ConfigKey::BridgeNick.to_string())
+                .env("OATMEAL_BRIDGE_NICK")
+                .num_args(1)
+                .help(format!("Name `oatmeal bridge` answers to when mentioned. [default: {}]", Config::default(ConfigKey::BridgeNick)))
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::IrcServer.to_string())
+                .long(ConfigKey::IrcServer.to_string())
+                .env("OATMEAL_IRC_SERVER")
+                .num_args(1)
+                .help("IRC server to bridge to as HOST:PORT, such as irc.libera.chat:6667. Only plain text connections are supported.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::IrcChannel.to_string())
+                .long(ConfigKey::IrcChannel.to_string())
+                .env("OATMEAL_IRC_CHANNEL")
+                .num_args(1)
+                .help("IRC channel to join when bridging, such as #rust.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::MatrixHomeserver.to_string())
+                .long(ConfigKey::MatrixHomeserver.to_string())
+                .env("OATMEAL_MATRIX_HOMESERVER")
+                .num_args(1)
+                .help("Matrix homeserver URL to bridge to, such as https://matrix.org.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::MatrixRoom.to_string())
+                .long(ConfigKey::MatrixRoom.to_string())
+                .env("OATMEAL_MATRIX_ROOM")
+                .num_args(1)
+                .help("Matrix room ID to answer in when bridging, such as !abc123:matrix.org. The account must already be joined to it.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::MatrixToken.to_string())
+                .long(ConfigKey::MatrixToken.to_string())
+                .env("OATMEAL_MATRIX_TOKEN")
+                .num_args(1)
+                .help("Matrix access token of the account used when bridging.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::SlackWebhookURL.to_string())
+                .long(ConfigKey::SlackWebhookURL.to_string())
+                .env("OATMEAL_SLACK_WEBHOOK_URL")
+                .num_args(1)
+                .help("Slack incoming webhook URL used by `/post slack`.")
+                .global(true),
+        )
+        .arg(
+            Arg::new(ConfigKey::DiscordWebhookURL.to_string())
+                .long(ConfigKey::DiscordWebhookURL.to_string())
+                .env("OATMEAL_DISCORD_WEBHOOK_URL")
+                .num_args(1)
+                .help("Discord webhook URL used by `/post discord`.")
                 .global(true),
         );
 }
@@ -449,6 +1522,14 @@ pub async fn parse() -> Result<bool> {
 
             return Ok(false);
         }
+        Some(("audit", subcmd_matches)) => {
+            print_audit_log(
+                subcmd_matches.get_one::<usize>("limit").copied(),
+                subcmd_matches.get_flag("path"),
+            )
+            .await?;
+            return Ok(false);
+        }
         Some(("chat", subcmd_matches)) => {
             Config::load(build(), vec![&matches, subcmd_matches]).await?;
         }
@@ -478,10 +1559,64 @@ pub async fn parse() -> Result<bool> {
                 }
             }
         }
+        Some(("fix", subcmd_matches)) => {
+            Config::load(build(), vec![&matches, subcmd_matches]).await?;
+            let args = subcmd_matches
+                .get_many::<String>("command")
+                .unwrap()
+                .map(|e| return e.to_string())
+                .collect::<Vec<String>>();
+
+            return load_fix_prompt(args).await;
+        }
+        Some(("eval", subcmd_matches)) => {
+            Config::load(build(), vec![&matches, subcmd_matches]).await?;
+            let models = subcmd_matches
+                .get_many::<String>("models")
+                .map(|e| return e.map(|e| return e.trim().to_string()).collect())
+                .unwrap_or_default();
+
+            run_eval(
+                subcmd_matches.get_one::<String>("prompts").unwrap(),
+                models,
+                subcmd_matches.get_one::<String>("output").unwrap(),
+            )
+            .await?;
+            return Ok(false);
+        }
+        Some(("bridge", subcmd_matches)) => {
+            Config::load(build(), vec![&matches, subcmd_matches]).await?;
+            let network = subcmd_matches.get_one::<String>("network").unwrap();
+            let bridge = BridgeManager::get(BridgeName::parse(network.to_string()).unwrap())?;
+            let backend =
+                BackendManager::get(BackendName::parse(Config::get(ConfigKey::Backend)).unwrap())?;
+            backend.health_check().await?;
+
+            BridgeService::new(bridge, backend, Sessions::default())
+                .start()
+                .await?;
+            return Ok(false);
+        }
+        Some(("join", subcmd_matches)) => {
+            Config::load(build(), vec![&matches, subcmd_matches]).await?;
+            let address = subcmd_matches.get_one::<String>("address").unwrap();
+            Config::set(ConfigKey::JoinAddress, address);
+            let token = subcmd_matches.get_one::<String>("token").unwrap();
+            Config::set(ConfigKey::JoinToken, token);
+        }
         Some(("manpages", _)) => {
             clap_mangen::Man::new(build()).render(&mut io::stdout())?;
             return Ok(false);
         }
+        Some(("stats", subcmd_matches)) => {
+            Config::load(build(), vec![&matches, subcmd_matches]).await?;
+            run_stats(
+                subcmd_matches.get_flag("all"),
+                subcmd_matches.get_one::<String>("csv"),
+            )
+            .await?;
+            return Ok(false);
+        }
         Some(("sessions", subcmd_matches)) => {
             match subcmd_matches.subcommand() {
                 Some(("dir", _)) => {
@@ -501,6 +1636,14 @@ pub async fn parse() -> Result<bool> {
                         load_config_from_session_interactive().await?;
                     }
                 }
+                Some(("diff", diff_matches)) => {
+                    print_sessions_diff(
+                        diff_matches.get_one::<String>("left").unwrap(),
+                        diff_matches.get_one::<String>("right").unwrap(),
+                    )
+                    .await?;
+                    return Ok(false);
+                }
                 Some(("delete", delete_matches)) => {
                     if let Some(session_id) = delete_matches.get_one::<String>("session-id") {
                         Sessions::default().delete(session_id).await?;
@@ -0,0 +1,168 @@
+use std::io;
+
+use anyhow::Result;
+use crossterm::event;
+use crossterm::event::KeyCode;
+use crossterm::event::KeyModifiers;
+use crossterm::terminal::disable_raw_mode;
+use crossterm::terminal::enable_raw_mode;
+use crossterm::terminal::EnterAlternateScreen;
+use crossterm::terminal::LeaveAlternateScreen;
+use ratatui::backend::CrosstermBackend;
+use ratatui::prelude::*;
+use ratatui::widgets::BarChart;
+use ratatui::widgets::Block;
+use ratatui::widgets::Borders;
+use ratatui::widgets::Paragraph;
+use ratatui::widgets::Row;
+use ratatui::widgets::Table;
+use ratatui::Terminal;
+
+use crate::domain::models::Analytics;
+use crate::domain::models::ColorMode;
+use crate::domain::models::LocaleFormat;
+use crate::domain::models::UsageTotals;
+
+/// Width of a bar in the messages per day chart, fitting a `MM-DD` label.
+const BAR_WIDTH: u16 = 5;
+
+const TABLE_WIDTHS: [Constraint; 5] = [
+    Constraint::Min(10),
+    Constraint::Length(8),
+    Constraint::Length(8),
+    Constraint::Length(10),
+    Constraint::Length(9),
+];
+
+fn format_cost(cost: Option<f64>, locale: &LocaleFormat) -> String {
+    return cost
+        .map(|e| return format!("${}", locale.number(e, 2)))
+        .unwrap_or("-".to_string());
+}
+
+fn totals_table<'a>(title: &'a str, groups: &[UsageTotals], locale: &LocaleFormat) -> Table<'a> {
+    let rows = groups
+        .iter()
+        .map(|e| {
+            return Row::new(vec![
+                e.name.to_string(),
+                locale.number(e.sessions as f64, 0),
+                locale.number(e.messages as f64, 0),
+                locale.number(e.tokens as f64, 0),
+                format_cost(e.cost, locale),
+            ]);
+        })
+        .collect::<Vec<Row>>();
+
+    return Table::new(rows)
+        .header(
+            Row::new(vec![title, "sessions", "messages", "~tokens", "cost"])
+                .style(Style::default().add_modifier(Modifier::BOLD)),
+        )
+        .widths(&TABLE_WIDTHS)
+        .block(Block::default().borders(Borders::ALL));
+}
+
+fn render(frame: &mut Frame, analytics: &Analytics, locale: &LocaleFormat) {
+    let totals = analytics.totals();
+    let models = analytics.by_model();
+    let projects = analytics.by_project();
+    let table_height = models.len().max(projects.len()) as u16 + 3;
+
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(vec![
+            Constraint::Length(3),
+            Constraint::Min(8),
+            Constraint::Length(table_height),
+        ])
+        .split(frame.size());
+
+    let header = format!(
+        "Sessions: {}   Messages: {}   Estimated tokens: {}   Cost: {}",
+        locale.number(totals.sessions as f64, 0),
+        locale.number(totals.messages as f64, 0),
+        locale.number(totals.tokens as f64, 0),
+        format_cost(totals.cost, locale),
+    );
+    frame.render_widget(
+        Paragraph::new(header).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Oatmeal stats, q to quit"),
+        ),
+        layout[0],
+    );
+
+    // Only the most recent days that fit are charted.
+    let days = analytics.messages_per_day();
+    let fits = (layout[1].width.saturating_sub(2) / (BAR_WIDTH + 1)) as usize;
+    let labels = days
+        .iter()
+        .skip(days.len().saturating_sub(fits))
+        .map(|(day, count)| return (day.get(5..).unwrap_or(day).to_string(), *count))
+        .collect::<Vec<(String, u64)>>();
+    let data = labels
+        .iter()
+        .map(|(day, count)| return (day.as_str(), *count))
+        .collect::<Vec<(&str, u64)>>();
+    frame.render_widget(
+        BarChart::default()
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Messages per day"),
+            )
+            .data(data.as_slice())
+            .bar_width(BAR_WIDTH)
+            .bar_gap(1)
+            .bar_style(Style {
+                fg: ColorMode::current().apply(Color::Rgb(138, 85, 63)),
+                ..Style::default()
+            }),
+        layout[1],
+    );
+
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(vec![Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(layout[2]);
+    frame.render_widget(totals_table("model", &models, locale), columns[0]);
+    frame.render_widget(totals_table("project", &projects, locale), columns[1]);
+}
+
+fn run<B: Backend>(
+    terminal: &mut Terminal<B>,
+    analytics: &Analytics,
+    locale: &LocaleFormat,
+) -> Result<()> {
+    loop {
+        terminal.draw(|frame| {
+            render(frame, analytics, locale);
+        })?;
+
+        if let event::Event::Key(key) = event::read()? {
+            let ctrl_c =
+                key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL);
+            if ctrl_c || key.code == KeyCode::Char('q') || key.code == KeyCode::Esc {
+                return Ok(());
+            }
+        }
+    }
+}
+
+/// Shows the `oatmeal stats` dashboard until it's closed.
+pub fn start(analytics: &Analytics, locale: &LocaleFormat) -> Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    crossterm::execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let res = run(&mut terminal, analytics, locale);
+
+    disable_raw_mode()?;
+    crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    return res;
+}
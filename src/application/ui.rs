@@ -1,4 +1,5 @@
 use std::io;
+use std::time::Instant;
 
 use anyhow::Result;
 use crossterm::cursor;
@@ -18,24 +19,40 @@ use ratatui::widgets::Scrollbar;
 use ratatui::widgets::ScrollbarOrientation;
 use ratatui::Terminal;
 use tokio::sync::mpsc;
+use tui_textarea::Input;
+use tui_textarea::Key;
 
+use crate::application::cli;
 use crate::configuration::Config;
 use crate::configuration::ConfigKey;
+use crate::domain::models::fence_pasted_code;
+use crate::domain::models::lint_input;
 use crate::domain::models::Action;
+use crate::domain::models::Animations;
 use crate::domain::models::Author;
 use crate::domain::models::BackendName;
 use crate::domain::models::BackendPrompt;
 use crate::domain::models::EditorName;
 use crate::domain::models::Event;
 use crate::domain::models::Loading;
+use crate::domain::models::LocaleFormat;
 use crate::domain::models::Message;
+use crate::domain::models::MessageType;
+use crate::domain::models::ModelTool;
+use crate::domain::models::QuitChoice;
+use crate::domain::models::QuitPrompt;
+use crate::domain::models::RecoveryAction;
+use crate::domain::models::ReplySuggestions;
+use crate::domain::models::Share;
 use crate::domain::models::SlashCommand;
 use crate::domain::models::TextArea;
+use crate::domain::models::ToolApproval;
 use crate::domain::services::events::EventsService;
 use crate::domain::services::AppState;
 use crate::domain::services::AppStateProps;
 use crate::domain::services::Bubble;
 use crate::domain::services::Sessions;
+use crate::domain::services::StartupProfile;
 use crate::infrastructure::backends::BackendManager;
 use crate::infrastructure::editors::EditorManager;
 
@@ -57,6 +74,27 @@ fn is_line_width_sufficient(line_width: u16) -> bool {
     return trimmed_line_width >= min_width;
 }
 
+/// Follow-ups above the input box, with the one Tab last filled in
+/// highlighted.
+fn suggestions_widget(suggestions: &ReplySuggestions) -> Paragraph {
+    let lines = suggestions
+        .replies
+        .iter()
+        .map(|reply| {
+            let mut style = Style::default();
+            if suggestions.selected() == Some(reply.as_str()) {
+                style = style.add_modifier(Modifier::BOLD);
+            }
+            return Line::from(vec![
+                Span::styled(" Tab ", Style::default().add_modifier(Modifier::DIM)),
+                Span::styled(reply.to_string(), style),
+            ]);
+        })
+        .collect::<Vec<Line>>();
+
+    return Paragraph::new(lines);
+}
+
 async fn start_loop<B: Backend>(
     terminal: &mut Terminal<B>,
     app_state_props: AppStateProps,
@@ -65,8 +103,45 @@ async fn start_loop<B: Backend>(
 ) -> Result<()> {
     let mut events = EventsService::new(rx);
     let mut textarea = TextArea::default();
-    let mut app_state = AppState::new(app_state_props).await?;
+    let input_max_lines = Config::get(ConfigKey::InputMaxLines).parse::<usize>()?;
     let loading = Loading::default();
+    let locale = LocaleFormat::from_config();
+    let low_bandwidth = Config::get(ConfigKey::LowBandwidth) == "true";
+    let mut animations = Animations::from_config();
+    // Quitting saves the session, unless its changes were discarded.
+    let mut save_on_quit = true;
+
+    // Draw something while waiting on the backend and editor, which can take
+    // far longer than the rest of startup.
+    terminal.draw(|frame| {
+        let size = frame.size();
+        let rect = Rect::new(
+            0,
+            size.height.saturating_sub(3),
+            size.width,
+            size.height.min(3),
+        );
+        loading.render(frame, rect, Some("Starting..."));
+    })?;
+    StartupProfile::mark("first frame");
+
+    let mut app_state = AppState::new(app_state_props).await?;
+    // Only bubbles added from here on fade in.
+    let mut messages_len = app_state.messages.len();
+    textarea.insert_str(&app_state.draft);
+    let mut initial_prompt = Config::get(ConfigKey::InitialPrompt);
+    tx.send(Action::PreloadModel())?;
+    // Resumed sessions keep the examples they were started with.
+    let few_shot_file = Config::get(ConfigKey::FewShotFile);
+    if !few_shot_file.is_empty() && Config::get(ConfigKey::SessionID).is_empty() {
+        tx.send(Action::LoadFewShot(few_shot_file))?;
+    }
+
+    let join_address = Config::get(ConfigKey::JoinAddress);
+    if !join_address.is_empty() {
+        app_state.waiting_for_backend = true;
+        tx.send(Action::JoinShare(join_address))?;
+    }
 
     #[cfg(feature = "dev")]
     {
@@ -75,6 +150,24 @@ async fn start_loop<B: Backend>(
     }
 
     loop {
+        app_state.draft = textarea.lines().join("\n");
+        let input_warnings = lint_input(&app_state.draft);
+        let context_usage = app_state.context_usage().render(&locale);
+        TextArea::set_status(
+            &mut textarea,
+            &input_warnings,
+            &context_usage,
+            app_state.backend_health,
+            &Config::get(ConfigKey::Backend),
+        );
+        let now = Instant::now();
+        if app_state.messages.len() > messages_len {
+            animations.bubble_appeared(app_state.messages.len() - 1, now);
+        }
+        messages_len = app_state.messages.len();
+        app_state.set_streaming_cursor(animations.cursor(now));
+        app_state.bubble_list.set_fading(animations.fading(now));
+        events.set_animating(animations.is_active(app_state.waiting_for_backend, now));
         terminal.draw(|frame| {
             if !is_line_width_sufficient(frame.size().width) {
                 frame.render_widget(
@@ -84,27 +177,53 @@ async fn start_loop<B: Backend>(
                 return;
             }
 
-            let textarea_len = (textarea.lines().len() + 3).try_into().unwrap();
+            let textarea_len = TextArea::height(&textarea, input_max_lines);
+            // While waiting on the backend its status takes the place of
+            // suggestions, leaving the input box free for `/stop`.
+            let mut suggestions_len = app_state.suggestions.replies.len() as u16;
+            if app_state.waiting_for_backend {
+                suggestions_len = 3;
+            }
             let layout = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints(vec![Constraint::Min(1), Constraint::Max(textarea_len)])
+                .constraints(vec![
+                    Constraint::Min(1),
+                    Constraint::Length(suggestions_len),
+                    Constraint::Max(textarea_len),
+                ])
                 .split(frame.size());
 
-            if layout[0].width as usize != app_state.last_known_width
-                || layout[0].height as usize != app_state.last_known_height
+            // The outline sits to the right, leaving the chat at least
+            // enough width to render its bubbles.
+            let mut chat_rect = layout[0];
+            let outline_width = app_state.outline.width(chat_rect.width);
+            let show_outline = app_state.outline.open
+                && is_line_width_sufficient(chat_rect.width.saturating_sub(outline_width));
+            if show_outline {
+                let columns = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(vec![Constraint::Min(1), Constraint::Length(outline_width)])
+                    .split(layout[0]);
+                chat_rect = columns[0];
+                app_state.outline.render(frame, columns[1]);
+            }
+
+            app_state.set_terminal_height(frame.size().height);
+            if chat_rect.width as usize != app_state.last_known_width
+                || chat_rect.height as usize != app_state.last_known_height
             {
-                app_state.set_rect(layout[0]);
+                app_state.set_rect(chat_rect);
             }
 
             app_state.bubble_list.render(
-                layout[0],
+                chat_rect,
                 frame.buffer_mut(),
                 app_state.scroll.position.try_into().unwrap(),
             );
 
             frame.render_stateful_widget(
                 Scrollbar::new(ScrollbarOrientation::VerticalRight),
-                layout[0].inner(&Margin {
+                chat_rect.inner(&Margin {
                     vertical: 1,
                     horizontal: 0,
                 }),
@@ -112,18 +231,86 @@ async fn start_loop<B: Backend>(
             );
 
             if app_state.waiting_for_backend {
-                loading.render(frame, layout[1]);
+                let mut status = app_state.backend_status.clone();
+                // The progress bar changes with every token, so slow
+                // connections get a status that doesn't.
+                if status.is_none() && low_bandwidth {
+                    status = Some("Answering... · CTRL+C to stop".to_string());
+                }
+                if status.is_none() {
+                    status = app_state.generation_progress().map(|e| return e.render());
+                }
+                if let Some(spinner) = animations.spinner(now) {
+                    status = Some(format!(
+                        "{spinner} {}",
+                        status.as_deref().unwrap_or("Loading...")
+                    ));
+                }
+                loading.render(frame, layout[1], status.as_deref());
             } else {
-                frame.render_widget(textarea.widget(), layout[1]);
+                frame.render_widget(suggestions_widget(&app_state.suggestions), layout[1]);
+            }
+            frame.render_widget(textarea.widget(), layout[2]);
+
+            if let Some(tool_approval) = &app_state.tool_approval {
+                tool_approval.render(frame, frame.size());
+            }
+            if let Some(quit_prompt) = &app_state.quit_prompt {
+                quit_prompt.render(frame, frame.size());
             }
         })?;
+        StartupProfile::mark("chat ready");
 
+        // Only prompts the user typed have their placeholders filled in, as
+        // ones built from tool output or files could run commands with them.
         macro_rules! send_user_message {
             ( $input_str:expr ) => {
+                send_user_message!($input_str, None::<String>, false);
+            };
+            ( $input_str:expr, $username:expr ) => {
+                send_user_message!($input_str, $username, false);
+            };
+            ( $input_str:expr, $username:expr, $typed:expr ) => {
                 let input_str = $input_str;
+                let typed: bool = $typed;
+
+                // Guests of a shared session send their prompts to the host.
+                if let Some(Share::Guest(guest)) = &app_state.share {
+                    textarea = TextArea::default();
+                    if SlashCommand::parse(&input_str)
+                        .map(|e| return e.is_quit())
+                        .unwrap_or(false)
+                    {
+                        break;
+                    }
+                    guest.send_prompt(&input_str)?;
+                    continue;
+                }
+
+                if SlashCommand::parse(&input_str)
+                    .map(|e| return e.is_quit())
+                    .unwrap_or(false)
+                {
+                    textarea = TextArea::default();
+                    app_state.draft.clear();
+                    if app_state.request_quit() {
+                        break;
+                    }
+                    continue;
+                }
 
-                let msg = Message::new(Author::User, &input_str);
+                if app_state.suggestions.clear() {
+                    tx.send(Action::CancelSuggestions())?;
+                }
+                let mut msg = Message::new(Author::User, &input_str);
+                if let Some(username) = $username {
+                    msg.set_username(&username);
+                }
+                if typed {
+                    msg.set_typed();
+                }
                 textarea = TextArea::default();
+                app_state.draft.clear();
                 app_state.add_message(msg);
 
                 let (should_break, should_continue) =
@@ -133,98 +320,316 @@ async fn start_loop<B: Backend>(
                     break;
                 }
                 if should_continue {
+                    // Slash commands such as `/lang` modify session state.
+                    app_state.save_session().await?;
                     continue;
                 }
 
+                // The chat keeps the placeholders and what `rewrite-rules`
+                // change, only the backend sees their values.
+                let mut prompt_text = input_str.to_string();
+                if SlashCommand::parse(&input_str).is_none() {
+                    if typed {
+                        match app_state.interpolate_prompt(&input_str).await {
+                            Ok(text) => prompt_text = text,
+                            Err(err) => {
+                                app_state.add_message(Message::new_with_type(
+                                    Author::Oatmeal,
+                                    MessageType::Error,
+                                    &err.to_string(),
+                                ));
+                                continue;
+                            }
+                        }
+                    }
+                    prompt_text = app_state.rewrite_prompt(&prompt_text);
+                }
+
+                app_state.pending_translation = None;
+                app_state.pending_fallback = None;
                 app_state.waiting_for_backend = true;
-                let mut prompt =
-                    BackendPrompt::new(input_str.to_string(), app_state.backend_context.clone());
+                let mut prompt = BackendPrompt::new(prompt_text, app_state.backend_context.clone());
 
                 if app_state.backend_context.is_empty() && SlashCommand::parse(&input_str).is_none()
                 {
-                    prompt.append_chat_context(&app_state.editor_context);
+                    let chat_context = app_state.chat_context();
+                    prompt.append_chat_context(&chat_context);
+                }
+                prompt.append_attached_context(&std::mem::take(&mut app_state.attached_context));
+                if SlashCommand::parse(&input_str).is_none() {
+                    prompt.tools = ModelTool::from_config()?;
+                    prompt.images = app_state.take_images();
+                    prompt.system_prompt = app_state.system_prompt();
+                    tx.send(app_state.fit_context(prompt))?;
+                } else {
+                    tx.send(Action::BackendRequest(prompt))?;
                 }
-
-                tx.send(Action::BackendRequest(prompt))?;
                 app_state.save_session().await?;
             };
         }
 
-        match events.next().await? {
+        if !initial_prompt.is_empty() {
+            let prompt = std::mem::take(&mut initial_prompt);
+            send_user_message!(&prompt);
+        }
+
+        // Steps were typed when the macro was recorded.
+        if let Some(step) = app_state.next_macro_step() {
+            send_user_message!(&step, None::<String>, true);
+        }
+
+        let event = events.next().await?;
+        if app_state.quit_prompt.is_some() && QuitPrompt::is_input(&event) {
+            match app_state.quit_prompt.as_mut().unwrap().handle(&event) {
+                Some(QuitChoice::SaveAndQuit) => {
+                    // Keeps what arrived of the answer, as stopping would.
+                    app_state.cancel_generation(&tx)?;
+                    app_state.handle_generation_cancelled();
+                    break;
+                }
+                Some(QuitChoice::Discard) => {
+                    save_on_quit = false;
+                    break;
+                }
+                Some(QuitChoice::Cancel) => {
+                    app_state.quit_prompt = None;
+                }
+                None => {}
+            }
+            continue;
+        }
+
+        if app_state.tool_approval.is_some() && ToolApproval::is_input(&event) {
+            if let Some(choice) = app_state.tool_approval.as_mut().unwrap().handle(&event) {
+                app_state.handle_tool_approval(choice, &tx)?;
+            }
+            continue;
+        }
+
+        match event {
+            Event::BackendSwitched(msg) => {
+                app_state.handle_backend_switched(msg);
+            }
             Event::BackendMessage(msg) => {
                 app_state.add_message(msg);
                 app_state.waiting_for_backend = false;
+                app_state.backend_status = None;
+            }
+            Event::BackendHealthChecked(health) => {
+                app_state.handle_backend_health(health);
+            }
+            Event::ComparisonResponse(idx, res) => {
+                app_state.handle_comparison_response(idx, res);
+            }
+            Event::ModelsRefreshed(msg) => {
+                app_state.add_message(msg);
+            }
+            Event::BackendOutputTruncated() => {
+                app_state.handle_output_truncated(&tx)?;
+            }
+            Event::BackendCancelled() => {
+                app_state.handle_generation_cancelled();
+                app_state.save_session().await?;
+            }
+            Event::BackendFallback(note) => {
+                app_state.handle_backend_fallback(note);
+            }
+            Event::BackendStatus(status) => {
+                app_state.handle_backend_status(status);
+            }
+            Event::AutoPrompt(prompt) => {
+                app_state.waiting_for_backend = false;
+                send_user_message!(&prompt);
+            }
+            Event::MacroLoaded(name, steps) => {
+                app_state.handle_macro_loaded(&name, steps);
+            }
+            Event::FewShotLoaded(path, examples) => {
+                app_state.handle_few_shot_loaded(&path, examples);
+            }
+            Event::ImageAttached(image) => {
+                app_state.handle_image_attached(image);
+            }
+            Event::ContextDocumentLoaded(document) => {
+                app_state.handle_context_document_loaded(document);
+                app_state.save_session().await?;
+            }
+            Event::SimilarFound(query, results) => {
+                app_state.handle_similar_found(&query, results);
+            }
+            Event::ExperimentCompleted(experiment) => {
+                app_state.handle_experiment_completed(experiment);
+            }
+            Event::ToolCallsRequested(calls) => {
+                app_state.handle_tool_calls_requested(calls, &tx)?;
+            }
+            Event::ModelToolFinished(output) => {
+                app_state.handle_model_tool_finished(output, &tx)?;
+            }
+            Event::ToolQueued(text) => {
+                app_state.add_message(Message::new(Author::Oatmeal, &text));
+            }
+            Event::ContextAttached(description, context) => {
+                app_state.attach_context(&description, context);
+            }
+            Event::ContextSummarized(summary) => {
+                app_state.handle_context_summarized(summary);
+            }
+            Event::ShareStarted(host) => {
+                app_state.handle_share_started(host);
+            }
+            Event::ShareJoined(guest) => {
+                app_state.handle_share_joined(guest);
+            }
+            Event::ShareParticipant(username, joined) => {
+                app_state.handle_share_participant(&username, joined);
+            }
+            Event::SharedPrompt(username, text) => {
+                if let Some(prompt) = app_state.handle_shared_prompt(&username, &text) {
+                    send_user_message!(&prompt, Some(username));
+                }
+            }
+            Event::ShareUpdate(msg) => {
+                app_state.handle_share_update(msg);
+            }
+            Event::DraftPolished(text) => {
+                app_state.handle_draft_polished();
+                textarea = TextArea::default();
+                textarea.set_yank_text(text);
+                textarea.paste();
+            }
+            Event::EditorContextSynced(editor_context) => {
+                app_state.handle_editor_context_synced(editor_context);
+            }
+            Event::EditorContextRefreshed(question, editor_context) => {
+                app_state.handle_editor_context_refreshed(&question, editor_context, &tx)?;
+                app_state.save_session().await?;
             }
             Event::BackendPromptResponse(msg) => {
                 app_state.handle_backend_response(msg.clone());
                 if msg.done {
                     app_state.save_session().await?;
+                    app_state.request_suggestions(&tx)?;
                 }
             }
+            Event::RepliesSuggested(replies) => {
+                app_state.suggestions.set(replies);
+            }
             Event::KeyboardCharInput(input) => {
-                if app_state.waiting_for_backend {
-                    continue;
-                }
-
                 // Windows submits a null event right after CTRL+C. Ignore it.
-                if input.key != tui_textarea::Key::Null {
-                    app_state.exit_warning = false;
+                if input.key != tui_textarea::Key::Null && app_state.suggestions.clear() {
+                    tx.send(Action::CancelSuggestions())?;
                 }
 
                 textarea.input(input);
             }
             Event::KeyboardCTRLC() => {
-                if app_state.waiting_for_backend {
-                    app_state.waiting_for_backend = false;
-                    tx.send(Action::BackendAbort())?;
-                } else if !app_state.exit_warning {
-                    app_state.add_message(Message::new(
-                        Author::Oatmeal,
-                        "If you wish to quit, hit CTRL+C one more time, or use /quit",
-                    ));
-                    app_state.exit_warning = true;
-                } else {
+                app_state.macro_queue.clear();
+                if app_state.cancel_generation(&tx)? {
+                    continue;
+                }
+                if app_state.request_quit() {
                     break;
                 }
             }
             Event::KeyboardCTRLO() => {
-                if app_state.waiting_for_backend {
-                    continue;
-                }
-                app_state.exit_warning = false;
                 textarea.insert_newline();
             }
             Event::KeyboardCTRLR() => {
-                let last_message = app_state
-                    .messages
-                    .iter()
-                    .filter(|msg| {
-                        return msg.author == Author::User
-                            && SlashCommand::parse(&msg.text).is_none();
-                    })
-                    .last();
-                if let Some(message) = last_message.cloned() {
-                    send_user_message!(&message.text);
+                if let Some(prompt) = app_state.last_prompt() {
+                    let typed = app_state.is_last_prompt_typed();
+                    send_user_message!(&prompt, None::<String>, typed);
                 }
             }
+            Event::KeyboardEsc() => {
+                app_state.cancel_generation(&tx)?;
+            }
             Event::KeyboardEnter() => {
+                let input_str = &textarea.lines().join("\n");
+                // Only `/stop` and `/quit` can be sent while the model is
+                // answering, anything else stays in the input box until it's
+                // done.
                 if app_state.waiting_for_backend {
+                    let command = SlashCommand::parse(input_str);
+                    if command
+                        .as_ref()
+                        .map(|e| return e.is_stop())
+                        .unwrap_or(false)
+                    {
+                        textarea = TextArea::default();
+                        app_state.cancel_generation(&tx)?;
+                    } else if command.map(|e| return e.is_quit()).unwrap_or(false) {
+                        textarea = TextArea::default();
+                        if app_state.request_quit() {
+                            break;
+                        }
+                    }
                     continue;
                 }
-                let input_str = &textarea.lines().join("\n");
                 if input_str.is_empty() {
+                    match app_state.take_recovery() {
+                        Some(RecoveryAction::Retry) => {
+                            if let Some(prompt) = app_state.last_prompt() {
+                                let typed = app_state.is_last_prompt_typed();
+                                send_user_message!(&prompt, None::<String>, typed);
+                            } else {
+                                app_state.waiting_for_backend = true;
+                                tx.send(Action::BackendHealthCheck())?;
+                            }
+                        }
+                        Some(RecoveryAction::SwitchBackend) => {
+                            app_state.waiting_for_backend = true;
+                            tx.send(Action::SwitchBackend())?;
+                        }
+                        Some(RecoveryAction::OpenConfig) => {
+                            tx.send(Action::OpenConfig(cli::ensure_config_file().await?))?;
+                        }
+                        None => {}
+                    }
                     continue;
                 }
-                send_user_message!(input_str);
+                let mut input_str = input_str.to_string();
+                if Config::get(ConfigKey::FenceCode) == "true"
+                    && SlashCommand::parse(&input_str).is_none()
+                {
+                    input_str = fence_pasted_code(&input_str);
+                }
+                app_state.record_macro_step(&input_str);
+                send_user_message!(&input_str, None::<String>, true);
             }
-            Event::KeyboardPaste(text) => {
+            Event::KeyboardTab() => {
                 if app_state.waiting_for_backend {
                     continue;
                 }
-                app_state.exit_warning = false;
+                if app_state.select_recovery() {
+                    continue;
+                }
+                if let Some(reply) = app_state
+                    .suggestions
+                    .select_next(&textarea.lines().join("\n"))
+                {
+                    textarea = TextArea::default();
+                    textarea.insert_str(reply);
+                } else {
+                    textarea.input(Input {
+                        key: Key::Tab,
+                        ..Input::default()
+                    });
+                }
+            }
+            Event::KeyboardPaste(text) => {
                 textarea.set_yank_text(text.replace('\r', "\n"));
                 textarea.paste();
             }
+            Event::UIOutlineToggle() => {
+                app_state.toggle_outline();
+            }
+            Event::UIOutlineSelect(forward) => {
+                app_state.select_outline(forward);
+            }
+            Event::UIOutlineResize(grow) => {
+                app_state.resize_outline(grow, &tx)?;
+            }
             Event::UITick() => {
                 continue;
             }
@@ -240,9 +645,18 @@ async fn start_loop<B: Backend>(
             Event::UIScrollPageUp() => {
                 app_state.scroll.up_page();
             }
+            Event::UIScrollLeft() => {
+                app_state.scroll_horizontally(false);
+            }
+            Event::UIScrollRight() => {
+                app_state.scroll_horizontally(true);
+            }
         }
     }
 
+    if save_on_quit {
+        app_state.save_draft(&textarea.lines().join("\n")).await?;
+    }
     return Ok(());
 }
 
@@ -17,27 +17,129 @@ use strum::IntoEnumIterator;
 use tokio::fs;
 
 use crate::domain::models::BackendName;
+use crate::domain::models::ConfigError;
 use crate::domain::models::EditorName;
+use crate::domain::models::ModelTool;
+use crate::domain::models::RewriteRules;
+use crate::domain::models::ToolPolicy;
 
 static CONFIG: Lazy<DashMap<String, String>> = Lazy::new(DashMap::new);
 
-#[derive(Clone, Copy, Eq, PartialEq, EnumIter, EnumVariantNames, strum::Display)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, EnumIter, EnumVariantNames, strum::Display)]
 #[strum(serialize_all = "kebab-case")]
 pub enum ConfigKey {
     Backend,
     BackendHealthCheckTimeout,
+    HealthCheckInterval,
+    BackendRetries,
+    Proxy,
+    BackendHeaders,
+    BackendTokenCommands,
+    LoadBalancing,
     Editor,
+    CheckCommand,
     Model,
+    EmbeddingModel,
+    EmbeddingProvider,
+    Seed,
+    Temperature,
+    TopP,
+    MaxTokens,
+    StopSequences,
+    SystemPrompt,
+    SystemPromptPresets,
+    RewriteRules,
+    ContextWindow,
+    ContextOverflow,
+    ContextDocumentsBudget,
+    Fallbacks,
+    FallbackTimeout,
+    FewShotFile,
+    ModelTools,
+    Tee,
     ConfigFile,
     LangChainURL,
     OllamaURL,
+    OllamaKeepAlive,
+    OllamaPreload,
     OpenAiToken,
     OpenAiURL,
+    OpenRouterToken,
+    OpenRouterURL,
+    AzureURL,
+    AzureToken,
+    AzureApiVersion,
+    AwsAccessKeyId,
+    AwsSecretAccessKey,
+    AwsSessionToken,
+    AwsRegion,
+    MistralToken,
+    MistralURL,
+    GroqToken,
+    GroqURL,
+    CohereToken,
+    CohereURL,
+    CoherePreamble,
+    TogetherToken,
+    TogetherURL,
+    PerplexityToken,
+    PerplexityURL,
+    LlamaCppURL,
+    LlamaCppToken,
+    LlamaCppEndpoint,
+    LmStudioURL,
+    CandleModelsDir,
     ClaudeToken,
     GeminiToken,
+    GithubToken,
+    AutoContinue,
+    ScanContext,
+    ContextHeadLines,
+    ContextTailLines,
+    HistoryMemoryLimit,
+    ToolConcurrency,
+    ToolCooldown,
+    ToolPermissions,
+    ToolPaths,
+    ToolHosts,
+    RunSandbox,
+    RunImage,
+    TicketProvider,
+    JiraURL,
+    JiraEmail,
+    JiraToken,
+    LinearToken,
+    BridgeNick,
+    IrcServer,
+    IrcChannel,
+    MatrixHomeserver,
+    MatrixRoom,
+    MatrixToken,
+    SlackWebhookURL,
+    DiscordWebhookURL,
     SessionID,
+    /// Prompt submitted as soon as the chat starts, such as from `oatmeal fix`.
+    InitialPrompt,
+    /// Address of a shared session to join, from `oatmeal join`.
+    JoinAddress,
+    /// Token shown by the host of the shared session to join.
+    JoinToken,
     Theme,
     ThemeFile,
+    ColorMode,
+    LowBandwidth,
+    Animations,
+    Layout,
+    OutlineRatio,
+    Locale,
+    ModelPrices,
+    ProfileStartup,
+    ShowTokenUsage,
+    FenceCode,
+    InputMaxLines,
+    SuggestReplies,
+    ResponseCache,
+    ModelCacheTtl,
     Username,
 }
 
@@ -56,6 +158,36 @@ impl Config {
         CONFIG.insert(key.to_string(), value.to_string());
     }
 
+    /// Sets the value and writes it to the config file, keeping the rest of
+    /// the file's formatting and comments.
+    pub async fn save(key: ConfigKey, value: &str) -> Result<()> {
+        Config::set(key, value);
+        let path = path::PathBuf::from(Config::get(ConfigKey::ConfigFile));
+        return Config::save_to(&path, key, value).await;
+    }
+
+    pub async fn save_to(path: &path::Path, key: ConfigKey, value: &str) -> Result<()> {
+        let mut doc = toml_edit::Document::new();
+        if path.exists() {
+            doc = fs::read_to_string(path)
+                .await?
+                .parse::<toml_edit::Document>()?;
+        }
+
+        // Numbers are written as such, like `input-max-lines = 10`.
+        doc[&key.to_string()] = match value.parse::<i64>() {
+            Ok(number) => toml_edit::value(number),
+            Err(_) => toml_edit::value(value),
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(path, doc.to_string()).await?;
+
+        return Ok(());
+    }
+
     pub fn default(key: ConfigKey) -> String {
         if key == ConfigKey::Username {
             let mut user = env::var("USER").unwrap_or_else(|_| return "".to_string());
@@ -69,6 +201,7 @@ impl Config {
         let default_backend = BackendName::Ollama.to_string();
         let default_editor = EditorName::Clipboard.to_string();
 
+        #[allow(unused_assignments)]
         let mut config_path = dirs::cache_dir().unwrap().join("oatmeal/config.toml");
 
         #[cfg(target_os = "macos")]
@@ -95,20 +228,115 @@ impl Config {
         let res = match key {
             ConfigKey::Backend => &default_backend,
             ConfigKey::BackendHealthCheckTimeout => "1000",
+            ConfigKey::HealthCheckInterval => "30",
+            ConfigKey::BackendRetries => "3",
+            ConfigKey::Proxy => "",
+            ConfigKey::BackendHeaders => "",
+            ConfigKey::BackendTokenCommands => "",
+            ConfigKey::LoadBalancing => "round-robin",
             ConfigKey::Editor => &default_editor,
+            ConfigKey::CheckCommand => "",
             ConfigKey::Model => "",
+            ConfigKey::EmbeddingModel => "",
+            ConfigKey::EmbeddingProvider => "backend",
+            ConfigKey::Seed => "",
+            ConfigKey::Temperature => "",
+            ConfigKey::TopP => "",
+            ConfigKey::MaxTokens => "",
+            ConfigKey::StopSequences => "",
+            ConfigKey::SystemPrompt => "",
+            ConfigKey::SystemPromptPresets => "",
+            ConfigKey::RewriteRules => "",
+            ConfigKey::ContextWindow => "",
+            ConfigKey::ContextOverflow => "summarize",
+            ConfigKey::ContextDocumentsBudget => "8000",
+            ConfigKey::Fallbacks => "",
+            ConfigKey::FallbackTimeout => "30000",
+            ConfigKey::FewShotFile => "",
+            ConfigKey::ModelTools => "",
+            ConfigKey::Tee => "",
             ConfigKey::LangChainURL => "http://localhost:8000",
             ConfigKey::OllamaURL => "http://localhost:11434",
+            ConfigKey::OllamaKeepAlive => "",
+            ConfigKey::OllamaPreload => "false",
             ConfigKey::OpenAiToken => "",
             ConfigKey::OpenAiURL => "https://api.openai.com",
+            ConfigKey::OpenRouterToken => "",
+            ConfigKey::OpenRouterURL => "https://openrouter.ai/api",
+            ConfigKey::AzureURL => "",
+            ConfigKey::AzureToken => "",
+            ConfigKey::AzureApiVersion => "2024-02-01",
+            ConfigKey::AwsAccessKeyId => "",
+            ConfigKey::AwsSecretAccessKey => "",
+            ConfigKey::AwsSessionToken => "",
+            ConfigKey::AwsRegion => "us-east-1",
+            ConfigKey::MistralToken => "",
+            ConfigKey::MistralURL => "https://api.mistral.ai",
+            ConfigKey::GroqToken => "",
+            ConfigKey::GroqURL => "https://api.groq.com/openai",
+            ConfigKey::CohereToken => "",
+            ConfigKey::CohereURL => "https://api.cohere.ai",
+            ConfigKey::CoherePreamble => "",
+            ConfigKey::TogetherToken => "",
+            ConfigKey::TogetherURL => "https://api.together.xyz",
+            ConfigKey::PerplexityToken => "",
+            ConfigKey::PerplexityURL => "https://api.perplexity.ai",
+            ConfigKey::LlamaCppURL => "http://localhost:8080",
+            ConfigKey::LlamaCppToken => "",
+            ConfigKey::LlamaCppEndpoint => "chat",
+            ConfigKey::LmStudioURL => "http://localhost:1234",
+            ConfigKey::CandleModelsDir => "",
             ConfigKey::ClaudeToken => "",
             ConfigKey::GeminiToken => "",
+            ConfigKey::GithubToken => "",
+            ConfigKey::AutoContinue => "false",
+            ConfigKey::ScanContext => "true",
+            ConfigKey::ContextHeadLines => "150",
+            ConfigKey::ContextTailLines => "50",
+            ConfigKey::HistoryMemoryLimit => "64",
+            ConfigKey::ToolConcurrency => "2",
+            ConfigKey::ToolCooldown => "1000",
+            ConfigKey::ToolPermissions => "",
+            ConfigKey::ToolPaths => "",
+            ConfigKey::ToolHosts => "",
+            ConfigKey::RunSandbox => "copy",
+            ConfigKey::RunImage => "python:3-slim",
+            ConfigKey::TicketProvider => "",
+            ConfigKey::JiraURL => "",
+            ConfigKey::JiraEmail => "",
+            ConfigKey::JiraToken => "",
+            ConfigKey::LinearToken => "",
+            ConfigKey::BridgeNick => "oatmeal",
+            ConfigKey::IrcServer => "",
+            ConfigKey::IrcChannel => "",
+            ConfigKey::MatrixHomeserver => "",
+            ConfigKey::MatrixRoom => "",
+            ConfigKey::MatrixToken => "",
+            ConfigKey::SlackWebhookURL => "",
+            ConfigKey::DiscordWebhookURL => "",
             ConfigKey::Theme => "base16-onedark",
             ConfigKey::ThemeFile => "",
+            ConfigKey::ColorMode => "auto",
+            ConfigKey::LowBandwidth => "false",
+            ConfigKey::Animations => "true",
+            ConfigKey::Layout => "auto",
+            ConfigKey::OutlineRatio => "30",
+            ConfigKey::Locale => "",
+            ConfigKey::ModelPrices => "",
+            ConfigKey::ProfileStartup => "false",
+            ConfigKey::ShowTokenUsage => "false",
+            ConfigKey::FenceCode => "true",
+            ConfigKey::InputMaxLines => "10",
+            ConfigKey::SuggestReplies => "false",
+            ConfigKey::ResponseCache => "false",
+            ConfigKey::ModelCacheTtl => "3600",
 
             // Special
             ConfigKey::ConfigFile => config_path.to_str().unwrap(),
             ConfigKey::SessionID => "",
+            ConfigKey::InitialPrompt => "",
+            ConfigKey::JoinAddress => "",
+            ConfigKey::JoinToken => "",
             ConfigKey::Username => "",
         };
 
@@ -160,9 +388,23 @@ impl Config {
                         if !possible_values.is_empty()
                             && !possible_values.contains(&val_str.to_string())
                         {
-                            bail!(format!("config.toml has an invalid value for key '{key}': {val_str}\nPossible values are: {}", possible_values.join(", ")));
+                            bail!(ConfigError::InvalidValue {
+                                key: key.to_string(),
+                                value: val_str.to_string(),
+                                possible_values,
+                            });
                         }
                         Config::set(key, val_str);
+                    } else if let Some(table) = val.as_table_like() {
+                        // Tables such as `[system-prompt-presets]` become a
+                        // `NAME=VALUE` line per entry.
+                        let entries = table
+                            .iter()
+                            .filter_map(|(name, value)| {
+                                return value.as_str().map(|e| return format!("{name}={e}"));
+                            })
+                            .collect::<Vec<String>>();
+                        Config::set(key, &entries.join("\n"));
                     }
                 }
             }
@@ -179,6 +421,10 @@ impl Config {
             }
         }
 
+        ToolPolicy::from_config()?;
+        ModelTool::from_config()?;
+        RewriteRules::from_config()?;
+
         tracing::debug!(
             username = Config::get(ConfigKey::Username),
             backend = Config::get(ConfigKey::Backend),
@@ -195,7 +441,12 @@ impl Config {
     pub fn serialize_default(cmd: Command) -> String {
         let toml_str = ConfigKey::iter()
             .filter_map(|key| {
-                if key == ConfigKey::SessionID || key == ConfigKey::ConfigFile {
+                if key == ConfigKey::SessionID
+                    || key == ConfigKey::ConfigFile
+                    || key == ConfigKey::InitialPrompt
+                    || key == ConfigKey::JoinAddress
+                    || key == ConfigKey::JoinToken
+                {
                     return None;
                 }
 
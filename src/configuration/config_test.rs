@@ -2,6 +2,7 @@ use anyhow::Result;
 use test_utils::insta_snapshot;
 
 use super::Config;
+use super::ConfigKey;
 use crate::application::cli;
 
 #[test]
@@ -30,3 +31,25 @@ async fn it_fails_to_loads_config_from_file() -> Result<()> {
     assert!(res.is_err());
     return Ok(());
 }
+
+#[tokio::test]
+async fn it_saves_values_to_the_config_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("config.toml");
+    tokio::fs::write(
+        &path,
+        "# My backend.\nbackend = \"ollama\"\noutline-ratio = 30\n",
+    )
+    .await?;
+
+    Config::save_to(&path, ConfigKey::OutlineRatio, "45").await?;
+    Config::save_to(&path, ConfigKey::Layout, "compact").await?;
+
+    let text = tokio::fs::read_to_string(&path).await?;
+    assert_eq!(
+        text,
+        "# My backend.\nbackend = \"ollama\"\noutline-ratio = 45\nlayout = \"compact\"\n"
+    );
+
+    return Ok(());
+}
@@ -1,11 +1,116 @@
 use super::AcceptType;
 use super::BackendPrompt;
+use super::CodeBlock;
+use super::CommandOutput;
+use super::Comparison;
+use super::DiagramKind;
 use super::EditorContext;
+use super::Experiment;
+use super::Macro;
 use super::Message;
+use super::PolishMode;
+use super::ReviewComment;
+use super::ToolInvocation;
+use super::WebhookName;
+use crate::configuration::ConfigKey;
 
 pub enum Action {
-    AcceptCodeBlock(Option<EditorContext>, String, AcceptType),
-    BackendAbort(),
+    /// The final flag skips checking the editor selection for changes
+    /// before replacing it.
+    AcceptCodeBlock(Option<EditorContext>, String, AcceptType, bool),
+    ApplyCodeBlocks(Vec<CodeBlock>),
+    /// Connects to a session shared at the address.
+    JoinShare(String),
+    /// Stops the answer being generated, keeping what arrived so far, or
+    /// whatever else the backend is doing for the chat.
+    CancelGeneration(),
+    /// Checks the backend again after its health check failed.
+    BackendHealthCheck(),
     BackendRequest(BackendPrompt),
+    /// Summarizes the transcript of the oldest messages, then sends the
+    /// prompt with the summary and the transcript of the latest messages in
+    /// place of the backend context.
+    SummarizeContext(String, String, BackendPrompt),
+    /// Stops asking for follow-ups to the last answer.
+    CancelSuggestions(),
+    /// Fetches a GitHub issue or pull request URL to attach as context.
+    FetchGitHub(String),
+    /// Fetches a ticket by its key from the configured ticket provider to
+    /// attach as context.
+    FetchTicket(String),
+    /// Looks up the man page, or `--help` output, for a command with an
+    /// optional section to attach as context.
+    FetchManPage(String, Option<String>),
+    /// Looks up the documentation of a Rust item, such as
+    /// `tokio::sync::mpsc`, to attach as context.
+    FetchRustDocs(String),
+    /// Ranks messages, keyed by their index in the session, by how similar
+    /// they are to the query using the backend's embeddings.
+    FindSimilar(String, Vec<(usize, String)>),
     CopyMessages(Vec<Message>),
+    /// Compares the chat's messages with the saved session for `/diff`.
+    DiffSession(String, Vec<Message>),
+    /// Answers each prompt variant from `/ab` with the same backend context.
+    RunExperiment(Vec<String>, String),
+    /// Sends the prompt to every model of the comparison at once for
+    /// `/compare`.
+    RunComparison(Comparison, BackendPrompt),
+    /// Logs an `/ab` comparison once its winner is picked.
+    RecordExperiment(Experiment),
+    /// Loads a macro from the config file to play, with the text to replace
+    /// `{args}` with.
+    PlayMacro(String, String),
+    /// Reads example exchanges for `/fewshot` from a file.
+    LoadFewShot(String),
+    /// Reads an image to attach with `/image`, from the clipboard without a
+    /// file.
+    LoadImage(Option<String>),
+    /// Reads a file or fetches a URL for `/context add`.
+    LoadContextDocument(String),
+    /// Rewrites a draft with the backend, outside of the chat history.
+    PolishDraft(String, PolishMode),
+    /// Loads the current model into the backend before the first prompt.
+    PreloadModel(),
+    /// Fetches the backend's models again for `/modelrefresh`, replacing the
+    /// cached ones.
+    RefreshModels(),
+    /// Posts messages to the Slack or Discord webhook.
+    PostWebhook(WebhookName, Vec<Message>),
+    PreviewCodeBlocks(Vec<CodeBlock>),
+    /// Writes a setting to the config file, such as the outline's size once
+    /// resized.
+    SaveConfig(ConfigKey, String),
+    /// Lists the action items in a conversation transcript for `/todos`,
+    /// also writing them to the output target when redirected.
+    ExtractTodos(String, Option<CommandOutput>),
+    /// Posts review comments to the numbered GitHub pull request.
+    PublishReview(Vec<ReviewComment>, String),
+    /// Runs a tool call from the model, once the user approved it.
+    RunModelTool(ToolInvocation),
+    /// Runs a code block from the model in the configured sandbox.
+    RunCodeBlock(CodeBlock),
+    /// Renders a Mermaid or Graphviz code block to an image, and opens it.
+    RenderDiagram(CodeBlock, DiagramKind),
+    /// Runs a linter with an optional command, and asks the model to fix
+    /// what it reports.
+    RunCheck(Option<String>),
+    /// Runs the project's tests with an optional command, and whether to ask
+    /// the model to fix failures.
+    RunTests(Option<String>, bool),
+    /// Opens the config file at the path with the system's default
+    /// application.
+    OpenConfig(String),
+    /// Saves a macro recorded with `/record` to the config file.
+    SaveMacro(Macro),
+    /// Shares the session with guests connecting to the address.
+    StartShare(String),
+    /// Switches to the first other backend that passes its health check.
+    SwitchBackend(),
+    /// Asks for follow-ups to the last answer in the background, with the
+    /// prompt from `ReplySuggestions::prompt`.
+    SuggestReplies(String),
+    SyncEditorContext(),
+    /// Reads the editor's current context, then asks the question with it.
+    AskWithContext(String),
+    WriteOutput(CommandOutput, Vec<Message>),
 }
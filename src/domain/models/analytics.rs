@@ -0,0 +1,279 @@
+#[cfg(test)]
+#[path = "analytics_test.rs"]
+mod tests;
+
+use std::collections::HashMap;
+
+use chrono::DateTime;
+use chrono::NaiveDate;
+
+use super::LocaleFormat;
+use super::Session;
+
+/// Sessions don't record what backends counted, so tokens are estimated at
+/// roughly four characters each.
+pub fn estimate_tokens(text: &str) -> u64 {
+    return text.chars().count().div_ceil(4) as u64;
+}
+
+/// Prices in dollars per million tokens, keyed by model, parsed from
+/// `model-prices` such as `gpt-4=30,gpt-3.5-turbo=0.5`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelPrices {
+    prices: HashMap<String, f64>,
+}
+
+impl ModelPrices {
+    /// Entries that aren't a model and a number are skipped.
+    pub fn parse(text: &str) -> ModelPrices {
+        let prices = text
+            .split(',')
+            .filter_map(|entry| {
+                let (model, price) = entry.split_once('=')?;
+                let price = price.trim().parse::<f64>().ok()?;
+                return Some((model.trim().to_string(), price));
+            })
+            .collect();
+
+        return ModelPrices { prices };
+    }
+
+    pub fn cost(&self, model: &str, tokens: u64) -> Option<f64> {
+        return self
+            .prices
+            .get(model)
+            .map(|price| return price * tokens as f64 / 1_000_000.0);
+    }
+}
+
+/// Usage of one saved session.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionUsage {
+    pub id: String,
+    /// Day the session was last saved on, as `YYYY-MM-DD`.
+    pub date: String,
+    pub project: String,
+    pub backend: String,
+    pub model: String,
+    pub messages: usize,
+    pub tokens: u64,
+    /// Only known for models with a price.
+    pub cost: Option<f64>,
+}
+
+/// Totals for a group of sessions, such as those using a model.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct UsageTotals {
+    pub name: String,
+    pub sessions: usize,
+    pub messages: usize,
+    pub tokens: u64,
+    pub cost: Option<f64>,
+}
+
+impl UsageTotals {
+    fn add(&mut self, usage: &SessionUsage) {
+        self.sessions += 1;
+        self.messages += usage.messages;
+        self.tokens += usage.tokens;
+        if let Some(cost) = usage.cost {
+            self.cost = Some(self.cost.unwrap_or(0.0) + cost);
+        }
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        return format!("\"{}\"", text.replace('"', "\"\""));
+    }
+    return text.to_string();
+}
+
+/// Usage across saved sessions for `oatmeal stats`, worked out entirely from
+/// the sessions on disk.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Analytics {
+    pub sessions: Vec<SessionUsage>,
+}
+
+impl Analytics {
+    pub fn new(sessions: &[Session], prices: &ModelPrices) -> Analytics {
+        let sessions = sessions
+            .iter()
+            .map(|session| {
+                let tokens = session
+                    .state
+                    .messages
+                    .iter()
+                    .map(|e| return estimate_tokens(&e.text))
+                    .sum();
+                let date = DateTime::parse_from_rfc3339(&session.timestamp)
+                    .map(|e| return e.date_naive().to_string())
+                    .unwrap_or_default();
+
+                return SessionUsage {
+                    id: session.id.to_string(),
+                    date,
+                    project: session.state.project.to_string(),
+                    backend: session.state.backend_name.to_string(),
+                    model: session.state.backend_model.to_string(),
+                    messages: session.state.messages.len(),
+                    tokens,
+                    cost: prices.cost(&session.state.backend_model, tokens),
+                };
+            })
+            .collect();
+
+        return Analytics { sessions };
+    }
+
+    /// Keeps only sessions saved on or after `date`.
+    pub fn since(mut self, date: NaiveDate) -> Analytics {
+        let date = date.to_string();
+        self.sessions.retain(|e| return e.date >= date);
+        return self;
+    }
+
+    pub fn totals(&self) -> UsageTotals {
+        let mut totals = UsageTotals {
+            name: "total".to_string(),
+            ..UsageTotals::default()
+        };
+        for usage in &self.sessions {
+            totals.add(usage);
+        }
+
+        return totals;
+    }
+
+    /// Messages for each day with sessions, oldest first.
+    pub fn messages_per_day(&self) -> Vec<(String, u64)> {
+        let mut days: Vec<(String, u64)> = vec![];
+        for usage in &self.sessions {
+            match days.iter_mut().find(|(day, _)| return *day == usage.date) {
+                Some((_, count)) => *count += usage.messages as u64,
+                None => days.push((usage.date.to_string(), usage.messages as u64)),
+            }
+        }
+        days.sort();
+
+        return days;
+    }
+
+    fn group_by(&self, key: impl Fn(&SessionUsage) -> String) -> Vec<UsageTotals> {
+        let mut groups: Vec<UsageTotals> = vec![];
+        for usage in &self.sessions {
+            let name = key(usage);
+            let idx = match groups.iter().position(|e| return e.name == name) {
+                Some(idx) => idx,
+                None => {
+                    groups.push(UsageTotals {
+                        name,
+                        ..UsageTotals::default()
+                    });
+                    groups.len() - 1
+                }
+            };
+            groups[idx].add(usage);
+        }
+        groups.sort_by(|a, b| return b.messages.cmp(&a.messages).then(a.name.cmp(&b.name)));
+
+        return groups;
+    }
+
+    /// Models used, busiest first.
+    pub fn by_model(&self) -> Vec<UsageTotals> {
+        return self.group_by(|e| return e.model.to_string());
+    }
+
+    /// Projects sessions were started in, busiest first. Sessions saved
+    /// before projects were recorded are grouped as "unknown".
+    pub fn by_project(&self) -> Vec<UsageTotals> {
+        return self.group_by(|e| {
+            if e.project.is_empty() {
+                return "unknown".to_string();
+            }
+            return e.project.to_string();
+        });
+    }
+
+    /// One row per session.
+    pub fn to_csv(&self) -> String {
+        let mut lines =
+            vec!["date,session,project,backend,model,messages,estimated_tokens,cost".to_string()];
+        for usage in &self.sessions {
+            lines.push(
+                [
+                    usage.date.to_string(),
+                    usage.id.to_string(),
+                    usage.project.to_string(),
+                    usage.backend.to_string(),
+                    usage.model.to_string(),
+                    usage.messages.to_string(),
+                    usage.tokens.to_string(),
+                    usage
+                        .cost
+                        .map(|e| return format!("{e:.4}"))
+                        .unwrap_or_default(),
+                ]
+                .iter()
+                .map(|e| return csv_field(e))
+                .collect::<Vec<String>>()
+                .join(","),
+            );
+        }
+
+        return lines.join("\n") + "\n";
+    }
+
+    /// Formats a group's totals as a row of a table.
+    pub fn format_totals(totals: &UsageTotals, width: usize, locale: &LocaleFormat) -> String {
+        return format!(
+            "{:width$}  {:>8}  {:>8}  {:>10}  {:>9}",
+            totals.name,
+            locale.number(totals.sessions as f64, 0),
+            locale.number(totals.messages as f64, 0),
+            locale.number(totals.tokens as f64, 0),
+            totals
+                .cost
+                .map(|e| return format!("${}", locale.number(e, 2)))
+                .unwrap_or("-".to_string()),
+        );
+    }
+
+    /// Plain text version of the dashboard, for when it isn't shown in a
+    /// terminal.
+    pub fn summary(&self, locale: &LocaleFormat) -> String {
+        let mut sections = vec![];
+        for (title, groups) in [("model", self.by_model()), ("project", self.by_project())] {
+            let width = groups
+                .iter()
+                .map(|e| return e.name.len())
+                .chain([title.len(), "total".len()])
+                .max()
+                .unwrap_or(0);
+            let mut lines = vec![format!(
+                "{title:width$}  {:>8}  {:>8}  {:>10}  {:>9}",
+                "sessions", "messages", "~tokens", "cost"
+            )];
+            lines.extend(
+                groups
+                    .iter()
+                    .map(|e| return Analytics::format_totals(e, width, locale)),
+            );
+            lines.push(Analytics::format_totals(&self.totals(), width, locale));
+            sections.push(lines.join("\n"));
+        }
+
+        let days = self
+            .messages_per_day()
+            .iter()
+            .map(|(day, count)| return format!("{day}  {}", locale.number(*count as f64, 0)))
+            .collect::<Vec<String>>();
+        if !days.is_empty() {
+            sections.push(format!("messages per day\n{}", days.join("\n")));
+        }
+
+        return sections.join("\n\n");
+    }
+}
@@ -0,0 +1,179 @@
+use std::collections::BTreeMap;
+
+use chrono::NaiveDate;
+
+use super::estimate_tokens;
+use super::Analytics;
+use super::ModelPrices;
+use super::UsageTotals;
+use crate::domain::models::Author;
+use crate::domain::models::LocaleFormat;
+use crate::domain::models::Message;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::Session;
+use crate::domain::models::State;
+
+fn session(id: &str, timestamp: &str, project: &str, model: &str, texts: &[&str]) -> Session {
+    return Session {
+        id: id.to_string(),
+        version: "1".to_string(),
+        timestamp: timestamp.to_string(),
+        state: State {
+            backend_name: "ollama".to_string(),
+            backend_model: model.to_string(),
+            backend_context: "".to_string(),
+            editor_language: "".to_string(),
+            project: project.to_string(),
+            messages: texts
+                .iter()
+                .map(|e| return Message::new(Author::User, e))
+                .collect(),
+            few_shot: vec![],
+            context_documents: vec![],
+            variables: BTreeMap::new(),
+            sampling: SamplingParams::default(),
+            system_prompt: None,
+            draft: "".to_string(),
+        },
+    };
+}
+
+fn sessions() -> Vec<Session> {
+    return vec![
+        session(
+            "a",
+            "2023-12-01T10:00:00+00:00",
+            "/code/oatmeal",
+            "llama2",
+            &["hello", "world!!!"],
+        ),
+        session("b", "2023-12-03T10:00:00+00:00", "", "gpt-4", &["12345678"]),
+        session(
+            "c",
+            "2023-12-03T12:00:00+00:00",
+            "/code/oatmeal",
+            "llama2",
+            &["one", "two", "three"],
+        ),
+    ];
+}
+
+#[test]
+fn it_estimates_tokens() {
+    assert_eq!(estimate_tokens(""), 0);
+    assert_eq!(estimate_tokens("abcd"), 1);
+    assert_eq!(estimate_tokens("abcde"), 2);
+}
+
+#[test]
+fn it_parses_model_prices() {
+    let prices = ModelPrices::parse("gpt-4=30, gpt-3.5-turbo = 0.5,broken,llama2=free");
+
+    assert_eq!(prices.cost("gpt-4", 1_000_000), Some(30.0));
+    assert_eq!(prices.cost("gpt-3.5-turbo", 2_000_000), Some(1.0));
+    assert_eq!(prices.cost("llama2", 1_000_000), None);
+    assert_eq!(ModelPrices::parse("").cost("gpt-4", 1), None);
+}
+
+#[test]
+fn it_totals_sessions() {
+    let analytics = Analytics::new(&sessions(), &ModelPrices::parse("gpt-4=1000000"));
+
+    assert_eq!(
+        analytics.totals(),
+        UsageTotals {
+            name: "total".to_string(),
+            sessions: 3,
+            messages: 6,
+            tokens: 10,
+            cost: Some(2.0),
+        }
+    );
+}
+
+#[test]
+fn it_counts_messages_per_day() {
+    let analytics = Analytics::new(&sessions(), &ModelPrices::default());
+
+    assert_eq!(
+        analytics.messages_per_day(),
+        vec![("2023-12-01".to_string(), 2), ("2023-12-03".to_string(), 4)]
+    );
+}
+
+#[test]
+fn it_groups_by_model_and_project() {
+    let analytics = Analytics::new(&sessions(), &ModelPrices::default());
+
+    let models = analytics
+        .by_model()
+        .into_iter()
+        .map(|e| return (e.name, e.sessions, e.messages))
+        .collect::<Vec<(String, usize, usize)>>();
+    assert_eq!(
+        models,
+        vec![("llama2".to_string(), 2, 5), ("gpt-4".to_string(), 1, 1)]
+    );
+
+    let projects = analytics
+        .by_project()
+        .into_iter()
+        .map(|e| return e.name)
+        .collect::<Vec<String>>();
+    assert_eq!(projects, vec!["/code/oatmeal", "unknown"]);
+}
+
+#[test]
+fn it_keeps_sessions_since_a_date() {
+    let analytics = Analytics::new(&sessions(), &ModelPrices::default())
+        .since(NaiveDate::from_ymd_opt(2023, 12, 2).unwrap());
+
+    let ids = analytics
+        .sessions
+        .iter()
+        .map(|e| return e.id.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(ids, vec!["b", "c"]);
+}
+
+#[test]
+fn it_exports_csv() {
+    let sessions = vec![
+        session(
+            "a",
+            "2023-12-01T10:00:00+00:00",
+            "/code/hello, world",
+            "gpt-4",
+            &["hello"],
+        ),
+        session("b", "2023-12-02T10:00:00+00:00", "", "llama2", &[]),
+    ];
+    let analytics = Analytics::new(&sessions, &ModelPrices::parse("gpt-4=30"));
+
+    insta::assert_snapshot!(analytics.to_csv(), @r###"
+    date,session,project,backend,model,messages,estimated_tokens,cost
+    2023-12-01,a,"/code/hello, world",ollama,gpt-4,1,2,0.0001
+    2023-12-02,b,,ollama,llama2,0,0,
+    "###);
+}
+
+#[test]
+fn it_summarizes() {
+    let analytics = Analytics::new(&sessions(), &ModelPrices::parse("gpt-4=1000000"));
+
+    insta::assert_snapshot!(analytics.summary(&LocaleFormat::default()), @r###"
+    model   sessions  messages     ~tokens       cost
+    llama2         2         5           8          -
+    gpt-4          1         1           2      $2.00
+    total          3         6          10      $2.00
+
+    project        sessions  messages     ~tokens       cost
+    /code/oatmeal         2         5           8          -
+    unknown               1         1           2      $2.00
+    total                 3         6          10      $2.00
+
+    messages per day
+    2023-12-01  2
+    2023-12-03  4
+    "###);
+}
@@ -0,0 +1,103 @@
+#[cfg(test)]
+#[path = "animation_test.rs"]
+mod tests;
+
+use std::time::Duration;
+use std::time::Instant;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// Most frames a second animations are drawn at.
+const ANIMATION_FPS: u64 = 10;
+
+/// Time between frames while anything is animating.
+pub const ANIMATION_FRAME: Duration = Duration::from_millis(1000 / ANIMATION_FPS);
+
+const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Drawn after the text of an answer as it streams in.
+const STREAMING_CURSOR: &str = "▌";
+
+/// Drawn in place of the cursor while it's blinked off, so the text doesn't
+/// rewrap.
+const STREAMING_CURSOR_OFF: &str = " ";
+
+/// Frames the streaming cursor stays shown, then hidden, for.
+const CURSOR_BLINK_FRAMES: u128 = 5;
+
+/// How long new bubbles are dimmed for before showing normally.
+const FADE_IN: Duration = Duration::from_millis(400);
+
+/// Every animation in the chat, driven by one clock so they stay in step
+/// and the UI ticks for all of them at once, at most `ANIMATION_FPS` times a
+/// second. Turned off with `animations`, or `low-bandwidth`.
+#[derive(Clone, Debug)]
+pub struct Animations {
+    enabled: bool,
+    started: Instant,
+    /// Newest bubble, and when it appeared.
+    fading: Option<(usize, Instant)>,
+}
+
+impl Animations {
+    pub fn new(enabled: bool, now: Instant) -> Animations {
+        return Animations {
+            enabled,
+            started: now,
+            fading: None,
+        };
+    }
+
+    pub fn from_config() -> Animations {
+        let enabled = Config::get(ConfigKey::Animations) == "true"
+            && Config::get(ConfigKey::LowBandwidth) != "true";
+        return Animations::new(enabled, Instant::now());
+    }
+
+    fn frame(&self, now: Instant) -> u128 {
+        return now.saturating_duration_since(self.started).as_millis()
+            / ANIMATION_FRAME.as_millis();
+    }
+
+    /// Shown ahead of the backend's status while waiting on it.
+    pub fn spinner(&self, now: Instant) -> Option<&'static str> {
+        if !self.enabled {
+            return None;
+        }
+        return Some(SPINNER_FRAMES[self.frame(now) as usize % SPINNER_FRAMES.len()]);
+    }
+
+    /// The blinking cursor after a streaming answer.
+    pub fn cursor(&self, now: Instant) -> Option<&'static str> {
+        if !self.enabled {
+            return None;
+        }
+        if (self.frame(now) / CURSOR_BLINK_FRAMES) % 2 == 1 {
+            return Some(STREAMING_CURSOR_OFF);
+        }
+        return Some(STREAMING_CURSOR);
+    }
+
+    /// Starts fading in the bubble of the message at `index`.
+    pub fn bubble_appeared(&mut self, index: usize, now: Instant) {
+        if self.enabled {
+            self.fading = Some((index, now));
+        }
+    }
+
+    /// Index of the message whose bubble is still fading in.
+    pub fn fading(&self, now: Instant) -> Option<usize> {
+        let (index, appeared) = self.fading?;
+        if now.saturating_duration_since(appeared) >= FADE_IN {
+            return None;
+        }
+        return Some(index);
+    }
+
+    /// Whether the UI should tick for the next frame, rather than waiting on
+    /// something else to change.
+    pub fn is_active(&self, waiting_for_backend: bool, now: Instant) -> bool {
+        return self.enabled && (waiting_for_backend || self.fading(now).is_some());
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use super::Animations;
+use super::ANIMATION_FRAME;
+
+#[test]
+fn it_spins_with_each_frame() {
+    let start = Instant::now();
+    let animations = Animations::new(true, start);
+
+    assert_eq!(animations.spinner(start), Some("⠋"));
+    assert_eq!(animations.spinner(start + ANIMATION_FRAME), Some("⠙"));
+    assert_eq!(animations.spinner(start + ANIMATION_FRAME * 10), Some("⠋"));
+}
+
+#[test]
+fn it_blinks_the_cursor() {
+    let start = Instant::now();
+    let animations = Animations::new(true, start);
+
+    assert_eq!(animations.cursor(start), Some("▌"));
+    assert_eq!(animations.cursor(start + ANIMATION_FRAME * 4), Some("▌"));
+    assert_eq!(animations.cursor(start + ANIMATION_FRAME * 5), Some(" "));
+    assert_eq!(animations.cursor(start + ANIMATION_FRAME * 10), Some("▌"));
+}
+
+#[test]
+fn it_fades_in_new_bubbles() {
+    let start = Instant::now();
+    let mut animations = Animations::new(true, start);
+    assert!(!animations.is_active(false, start));
+
+    animations.bubble_appeared(3, start);
+    assert_eq!(
+        animations.fading(start + Duration::from_millis(100)),
+        Some(3)
+    );
+    assert!(animations.is_active(false, start));
+    assert_eq!(animations.fading(start + Duration::from_secs(1)), None);
+    assert!(!animations.is_active(false, start + Duration::from_secs(1)));
+}
+
+#[test]
+fn it_does_nothing_when_disabled() {
+    let start = Instant::now();
+    let mut animations = Animations::new(false, start);
+    animations.bubble_appeared(0, start);
+
+    assert_eq!(animations.spinner(start), None);
+    assert_eq!(animations.cursor(start), None);
+    assert_eq!(animations.fading(start), None);
+    assert!(!animations.is_active(true, start));
+}
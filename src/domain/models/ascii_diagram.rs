@@ -0,0 +1,103 @@
+#[cfg(test)]
+#[path = "ascii_diagram_test.rs"]
+mod tests;
+
+use super::CodeBlockParser;
+use super::FenceLine;
+
+/// Code block languages models use for diagrams and tables drawn with text.
+const PLAIN_LANGUAGES: [&str; 6] = ["", "text", "txt", "plaintext", "ascii", "diagram"];
+
+/// Characters that ASCII art is drawn with, rather than written with.
+const DRAWING_CHARS: &str = "|+-=_/\\<>^*#.:~";
+
+/// How much of a line's visible text has to be drawing characters for it to
+/// look like part of a diagram.
+const MIN_DRAWING_RATIO: f32 = 0.4;
+
+fn is_box_drawing(c: char) -> bool {
+    // Box drawing, block elements, geometric shapes and arrows.
+    return matches!(c, '\u{2500}'..='\u{25FF}' | '\u{2190}'..='\u{21FF}');
+}
+
+fn is_diagram_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    if trimmed.chars().any(is_box_drawing) {
+        return true;
+    }
+
+    // Markdown tables, and the borders of ASCII ones.
+    if trimmed.starts_with('|') && trimmed.matches('|').count() >= 2 {
+        return true;
+    }
+    if trimmed.starts_with("+-") && trimmed.ends_with('+') {
+        return true;
+    }
+
+    let visible = trimmed.chars().filter(|c| return !c.is_whitespace());
+    let total = visible.clone().count();
+    if total < 3 {
+        return false;
+    }
+    let drawing = visible
+        .filter(|c| return DRAWING_CHARS.contains(*c))
+        .count();
+
+    return drawing as f32 / total as f32 >= MIN_DRAWING_RATIO;
+}
+
+/// Whether a paragraph of prose is really a diagram. Labels within diagrams
+/// don't look like drawings, so only most of its lines have to.
+fn is_diagram(paragraph: &[&str]) -> bool {
+    let drawn = paragraph
+        .iter()
+        .filter(|e| return is_diagram_line(e))
+        .count();
+    return drawn >= 2 && drawn * 2 >= paragraph.len();
+}
+
+fn end_paragraph(paragraph: &mut Vec<&str>, preserved: &mut Vec<bool>) {
+    let diagram = is_diagram(paragraph);
+    preserved.extend(paragraph.iter().map(|_| return diagram));
+    paragraph.clear();
+}
+
+/// Which lines of `text` are part of an ASCII diagram or table, and so have
+/// to keep their spacing rather than being word wrapped. Those are every
+/// line of plain text code blocks, and paragraphs of prose that are mostly
+/// drawn.
+pub fn preserved_lines(text: &str) -> Vec<bool> {
+    let mut parser = CodeBlockParser::default();
+    let mut preserved = vec![];
+    let mut paragraph: Vec<&str> = vec![];
+    let mut plain_block = false;
+
+    for line in text.lines() {
+        match parser.line(line) {
+            FenceLine::Text if !line.trim().is_empty() => {
+                paragraph.push(line);
+                continue;
+            }
+            FenceLine::Text => {
+                end_paragraph(&mut paragraph, &mut preserved);
+                preserved.push(false);
+            }
+            FenceLine::Open => {
+                end_paragraph(&mut paragraph, &mut preserved);
+                let language = parser.open_language().unwrap_or_default();
+                plain_block = PLAIN_LANGUAGES.contains(&language.as_str());
+                preserved.push(false);
+            }
+            FenceLine::Code => {
+                preserved.push(plain_block);
+            }
+            FenceLine::Close => {
+                plain_block = false;
+                preserved.push(false);
+            }
+        }
+    }
+    end_paragraph(&mut paragraph, &mut preserved);
+
+    return preserved;
+}
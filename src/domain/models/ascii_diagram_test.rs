@@ -0,0 +1,40 @@
+use super::preserved_lines;
+
+#[test]
+fn it_preserves_drawn_paragraphs() {
+    let text = r#"
+Here's how they connect:
+
++--------+      +--------+
+| Client | ---> | Server |
++--------+      +--------+
+
+The client sends a request, and | the server replies.
+"#
+    .trim();
+
+    assert_eq!(
+        preserved_lines(text),
+        vec![false, false, true, true, true, false, false]
+    );
+}
+
+#[test]
+fn it_preserves_box_drawings_and_tables() {
+    let text = "┌─────┐\n│ box │\n└─────┘\n\n| Name | Age |\n|------|-----|\n| Ada  | 36  |";
+
+    assert_eq!(
+        preserved_lines(text),
+        vec![true, true, true, false, true, true, true]
+    );
+}
+
+#[test]
+fn it_preserves_plain_code_blocks_only() {
+    let text = "```\nA    B\n```\n\n```rust\nlet a = b || c;\n```";
+
+    assert_eq!(
+        preserved_lines(text),
+        vec![false, true, false, false, false, false, false]
+    );
+}
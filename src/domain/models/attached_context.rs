@@ -0,0 +1,89 @@
+#[cfg(test)]
+#[path = "attached_context_test.rs"]
+mod tests;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// Most suspicious lines reported when warning about attached context.
+const MAX_FINDINGS: usize = 5;
+
+/// Suspicious lines are shortened to this many characters in warnings.
+const MAX_FINDING_LENGTH: usize = 100;
+
+static DELIMITER_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(r"(?i)<(/?untrusted-context)").unwrap();
+});
+
+static INSTRUCTION_RES: Lazy<Vec<Regex>> = Lazy::new(|| {
+    return [
+        r"(?i)\b(ignore|disregard|forget|override)\b.{0,30}\b(previous|prior|above|earlier|preceding|all|your|system)\b.{0,20}\b(instructions?|prompts?|rules|messages|context)\b",
+        r"(?i)\byou are now\b",
+        r"(?i)\b(new|updated|real)\s+(system\s+)?instructions\s*:",
+        r"(?i)\b(reveal|print|show|repeat|output)\b.{0,20}\b(system prompt|your instructions|hidden instructions)\b",
+        r"(?i)\bdo not (tell|inform|warn|alert) the user\b",
+        r"(?i)^\s*(system|assistant)\s*:",
+        r"<\|im_start\|>|<\|im_end\|>|\[INST\]|<<SYS>>",
+    ]
+    .iter()
+    .map(|e| return Regex::new(e).unwrap())
+    .collect();
+});
+
+/// Context fetched from outside of the conversation, such as a GitHub issue
+/// or man page, to be sent along with the next prompt. Its contents are
+/// untrusted, as anyone able to edit the source can write instructions
+/// aimed at the model.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AttachedContext {
+    /// Short description of the source, such as "ticket ENG-123".
+    pub description: String,
+    pub text: String,
+}
+
+impl AttachedContext {
+    pub fn new(description: &str, text: &str) -> AttachedContext {
+        return AttachedContext {
+            description: description.to_string(),
+            text: text.to_string(),
+        };
+    }
+
+    /// Wraps the text in an `<untrusted-context>` block naming its source.
+    /// Delimiters within the text are escaped so it can't close the block
+    /// early.
+    pub fn wrap(&self) -> String {
+        let source = self.description.replace('"', "'");
+        let text = DELIMITER_RE.replace_all(self.text.trim(), "&lt;$1");
+        return format!("<untrusted-context source=\"{source}\">\n{text}\n</untrusted-context>");
+    }
+
+    /// Finds lines that read like instructions aimed at the model rather
+    /// than content for it, such as "ignore all previous instructions".
+    /// Lines within code blocks are checked too, as payloads are often hidden
+    /// in them.
+    pub fn scan(&self) -> Vec<String> {
+        let mut findings: Vec<String> = vec![];
+        for line in self.text.lines() {
+            if !INSTRUCTION_RES.iter().any(|e| return e.is_match(line)) {
+                continue;
+            }
+
+            let mut finding = line.trim().to_string();
+            if finding.chars().count() > MAX_FINDING_LENGTH {
+                finding = format!(
+                    "{}…",
+                    finding.chars().take(MAX_FINDING_LENGTH).collect::<String>()
+                );
+            }
+            if !findings.contains(&finding) {
+                findings.push(finding);
+            }
+            if findings.len() == MAX_FINDINGS {
+                break;
+            }
+        }
+
+        return findings;
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::Local;
+use chrono::SecondsFormat;
+use serde::Deserialize;
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum AuditKind {
+    /// A slash command that runs a tool, such as `/gh`.
+    Tool,
+    /// A program executed on the user's behalf.
+    Shell,
+    /// A file written on the user's behalf.
+    File,
+}
+
+/// A record of something Oatmeal did on the user's behalf.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub kind: AuditKind,
+    /// The tool and its arguments, the command executed, or the file path.
+    pub summary: String,
+    /// The exit code of a command, or a diff of the file written.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+}
+
+impl AuditEntry {
+    pub fn new(kind: AuditKind, summary: &str, details: Option<String>) -> AuditEntry {
+        return AuditEntry {
+            timestamp: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            kind,
+            summary: summary.to_string(),
+            details,
+        };
+    }
+
+    /// Formats the entry for `oatmeal audit`, with multi-line details such as
+    /// diffs indented below it.
+    pub fn format(&self) -> String {
+        let mut res = format!("{} [{}] {}", self.timestamp, self.kind, self.summary);
+        if let Some(details) = &self.details {
+            if details.contains('\n') {
+                let indented = details
+                    .trim_end()
+                    .lines()
+                    .map(|e| return format!("    {e}"))
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                res = format!("{res}\n{indented}");
+            } else {
+                res = format!("{res} ({details})");
+            }
+        }
+
+        return res;
+    }
+}
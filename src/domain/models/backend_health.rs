@@ -0,0 +1,72 @@
+#[cfg(test)]
+#[path = "backend_health_test.rs"]
+mod tests;
+
+use std::time::Duration;
+
+use anyhow::Result;
+use ratatui::style::Color;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// Whether the backend passed its last health check, shown next to the
+/// input box while checks run in the background.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BackendHealth {
+    /// Not checked yet, such as when resuming a session.
+    #[default]
+    Unknown,
+    Online,
+    Offline,
+}
+
+impl BackendHealth {
+    pub fn from_check(res: &Result<()>) -> BackendHealth {
+        if res.is_ok() {
+            return BackendHealth::Online;
+        }
+
+        return BackendHealth::Offline;
+    }
+
+    /// Time between background health checks from `health-check-interval`,
+    /// or `None` when they're turned off.
+    pub fn interval() -> Option<Duration> {
+        return Config::get(ConfigKey::HealthCheckInterval)
+            .parse::<u64>()
+            .ok()
+            .filter(|e| return *e > 0)
+            .map(Duration::from_secs);
+    }
+
+    /// Backends that come back up after being down say so in the chat.
+    pub fn is_recovery(&self, previous: BackendHealth) -> bool {
+        return previous == BackendHealth::Offline && *self == BackendHealth::Online;
+    }
+
+    /// A symbol that tells the states apart without color, for `NO_COLOR`.
+    pub fn symbol(&self) -> &'static str {
+        return match self {
+            BackendHealth::Unknown => "◌",
+            BackendHealth::Online => "●",
+            BackendHealth::Offline => "○",
+        };
+    }
+
+    pub fn color(&self) -> Color {
+        return match self {
+            BackendHealth::Unknown => Color::DarkGray,
+            BackendHealth::Online => Color::Green,
+            BackendHealth::Offline => Color::Red,
+        };
+    }
+
+    pub fn label(&self, backend: &str) -> String {
+        if *self == BackendHealth::Offline {
+            return format!("{} {backend} offline", self.symbol());
+        }
+
+        return format!("{} {backend}", self.symbol());
+    }
+}
@@ -0,0 +1,27 @@
+use anyhow::anyhow;
+
+use super::BackendHealth;
+
+#[test]
+fn it_follows_the_health_check() {
+    assert_eq!(BackendHealth::from_check(&Ok(())), BackendHealth::Online);
+    assert_eq!(
+        BackendHealth::from_check(&Err(anyhow!("Connection refused"))),
+        BackendHealth::Offline
+    );
+}
+
+#[test]
+fn it_recovers_only_after_being_offline() {
+    assert!(BackendHealth::Online.is_recovery(BackendHealth::Offline));
+    assert!(!BackendHealth::Online.is_recovery(BackendHealth::Unknown));
+    assert!(!BackendHealth::Online.is_recovery(BackendHealth::Online));
+    assert!(!BackendHealth::Offline.is_recovery(BackendHealth::Offline));
+}
+
+#[test]
+fn it_labels_the_backend() {
+    assert_eq!(BackendHealth::Online.label("ollama"), "● ollama");
+    assert_eq!(BackendHealth::Offline.label("ollama"), "○ ollama offline");
+    assert_eq!(BackendHealth::Unknown.label("ollama"), "◌ ollama");
+}
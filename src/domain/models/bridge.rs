@@ -0,0 +1,76 @@
+#[cfg(test)]
+#[path = "bridge_test.rs"]
+mod tests;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use strum::EnumIter;
+use strum::EnumVariantNames;
+use strum::IntoEnumIterator;
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, EnumVariantNames, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum BridgeName {
+    Irc,
+    Matrix,
+}
+
+impl BridgeName {
+    pub fn parse(text: String) -> Option<BridgeName> {
+        return BridgeName::iter().find(|e| return e.to_string() == text);
+    }
+}
+
+/// A message received in a chat room.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BridgeMessage {
+    /// Room the message was sent to, such as an IRC channel or Matrix room ID.
+    pub room: String,
+    pub sender: String,
+    pub text: String,
+}
+
+impl BridgeMessage {
+    /// Returns the prompt when the message mentions `nick`, either addressed
+    /// as "nick: ..." or "nick, ...", or with "@nick" anywhere in the text.
+    pub fn prompt_for(&self, nick: &str) -> Option<String> {
+        let text = self.text.trim();
+        let lower = text.to_lowercase();
+        let nick = nick.to_lowercase();
+
+        let addressed = lower
+            .strip_prefix('@')
+            .unwrap_or(&lower)
+            .strip_prefix(&nick)
+            .filter(|rest| return rest.starts_with([':', ',']));
+        if let Some(rest) = addressed {
+            return Some(text[(text.len() - rest.len() + 1)..].trim().to_string());
+        }
+
+        let mention = format!("@{nick}");
+        if let Some(idx) = lower.find(&mention) {
+            let res = format!("{} {}", &text[..idx], &text[(idx + mention.len())..]);
+            return Some(res.split_whitespace().collect::<Vec<&str>>().join(" "));
+        }
+
+        return None;
+    }
+}
+
+#[async_trait]
+pub trait Bridge {
+    /// Returns the name of the bridge.
+    fn name(&self) -> BridgeName;
+
+    /// Connects to the chat network and joins the configured room.
+    async fn connect(&mut self) -> Result<()>;
+
+    /// Waits for the next message in the room, returning none once the
+    /// connection closes.
+    async fn next_message(&mut self) -> Result<Option<BridgeMessage>>;
+
+    /// Sends a reply to a room.
+    async fn reply(&mut self, room: &str, text: &str) -> Result<()>;
+}
+
+pub type BridgeBox = Box<dyn Bridge + Send + Sync>;
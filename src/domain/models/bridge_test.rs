@@ -0,0 +1,31 @@
+use super::BridgeMessage;
+
+fn message(text: &str) -> BridgeMessage {
+    return BridgeMessage {
+        room: "#rust".to_string(),
+        sender: "alice".to_string(),
+        text: text.to_string(),
+    };
+}
+
+#[test]
+fn it_finds_prompts_addressed_to_nick() {
+    assert_eq!(
+        message("Oatmeal: what is a lifetime?").prompt_for("oatmeal"),
+        Some("what is a lifetime?".to_string())
+    );
+    assert_eq!(
+        message("@oatmeal, explain Pin").prompt_for("oatmeal"),
+        Some("explain Pin".to_string())
+    );
+    assert_eq!(
+        message("hey @oatmeal explain Pin").prompt_for("oatmeal"),
+        Some("hey explain Pin".to_string())
+    );
+}
+
+#[test]
+fn it_ignores_messages_without_mentions() {
+    assert_eq!(message("oatmeal is tasty").prompt_for("oatmeal"), None);
+    assert_eq!(message("what is a lifetime?").prompt_for("oatmeal"), None);
+}
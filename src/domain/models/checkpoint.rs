@@ -0,0 +1,11 @@
+use super::Message;
+
+/// A snapshot of the chat taken with `/checkpoint`, which `/rollback` restores
+/// to try another direction without forking the session. Checkpoints only
+/// last as long as the chat is open.
+#[derive(Clone, Default)]
+pub struct Checkpoint {
+    pub name: String,
+    pub messages: Vec<Message>,
+    pub backend_context: String,
+}
@@ -0,0 +1,234 @@
+#[cfg(test)]
+#[path = "code_block_test.rs"]
+mod tests;
+
+/// A fenced code block extracted from a message.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CodeBlock {
+    /// Language from the opening fence, or the user's `/lang` override.
+    pub language: String,
+    pub code: String,
+    /// File the model wrote the block for, when it could be inferred.
+    pub path: Option<String>,
+}
+
+fn looks_like_path(text: &str) -> bool {
+    return !text.is_empty()
+        && text
+            .chars()
+            .all(|c| return c.is_alphanumeric() || "_-./~".contains(c))
+        && (text.contains('/') || text.contains('.'))
+        && !text.ends_with('.');
+}
+
+impl CodeBlock {
+    /// Finds a file path within a fence info string, such as ```` ```rust
+    /// title=src/main.rs ```` or ```` ```rust file="src/main.rs" ````.
+    pub fn path_from_info(info: &str) -> Option<String> {
+        for attribute in info.split_whitespace().skip(1) {
+            if let Some((key, value)) = attribute.split_once('=') {
+                if !["title", "file", "filename", "path"].contains(&key) {
+                    continue;
+                }
+
+                let value = value.trim_matches(|c| return c == '"' || c == '\'');
+                if looks_like_path(value) {
+                    return Some(value.to_string());
+                }
+            }
+        }
+
+        return None;
+    }
+
+    /// Finds a file path within prose introducing a code block, such as "In
+    /// `src/lib.rs`:" or "**src/lib.rs**:". Only lines ending with a colon
+    /// are considered.
+    pub fn path_from_prose(line: &str) -> Option<String> {
+        let trimmed = line.trim();
+        if !trimmed.ends_with(':') {
+            return None;
+        }
+
+        for delimiter in ["`", "**"] {
+            let candidates = trimmed.split(delimiter).skip(1).step_by(2);
+            for candidate in candidates {
+                if looks_like_path(candidate) {
+                    return Some(candidate.to_string());
+                }
+            }
+        }
+
+        return None;
+    }
+}
+
+/// How `CodeBlockParser` classified a line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FenceLine {
+    Text,
+    /// Opens a code block.
+    Open,
+    /// Within a code block.
+    Code,
+    /// Closes a code block.
+    Close,
+}
+
+/// A line of three or more backticks or tildes, followed by an info string
+/// such as the language.
+struct Fence {
+    marker: char,
+    len: usize,
+    indent: usize,
+    info: String,
+}
+
+impl Fence {
+    fn parse(line: &str) -> Option<Fence> {
+        let trimmed = line.trim_start();
+        let marker = trimmed.chars().next()?;
+        if marker != '`' && marker != '~' {
+            return None;
+        }
+
+        let len = trimmed.chars().take_while(|c| return *c == marker).count();
+        let info = trimmed[len..].trim();
+        // Backticks within the info string make it inline code instead.
+        if len < 3 || (marker == '`' && info.contains('`')) {
+            return None;
+        }
+
+        return Some(Fence {
+            marker,
+            len,
+            indent: line.len() - trimmed.len(),
+            info: info.to_string(),
+        });
+    }
+
+    /// Only a fence of the same character, at least as long, and without an
+    /// info string closes a block. Anything else is part of the code, so
+    /// blocks can contain other fences.
+    fn closes(&self, other: &Fence) -> bool {
+        return other.marker == self.marker && other.len >= self.len && other.info.is_empty();
+    }
+}
+
+struct OpenBlock {
+    fence: Fence,
+    path: Option<String>,
+    lines: Vec<String>,
+}
+
+impl OpenBlock {
+    fn into_codeblock(self) -> CodeBlock {
+        return CodeBlock {
+            language: self
+                .fence
+                .info
+                .split_whitespace()
+                .next()
+                .unwrap_or("")
+                .to_string(),
+            code: self.lines.join("\n"),
+            path: self.path,
+        };
+    }
+}
+
+/// Extracts fenced code blocks from text that may arrive in chunks, such as
+/// a streamed response. Chunks can split lines, and fences, anywhere.
+#[derive(Default)]
+pub struct CodeBlockParser {
+    /// Text after the last newline, waiting for the rest of its line.
+    partial: String,
+    /// Last line of prose, for inferring the path of the next block.
+    previous_line: String,
+    open: Option<OpenBlock>,
+    blocks: Vec<CodeBlock>,
+}
+
+impl CodeBlockParser {
+    /// Parses every complete line within `chunk`, holding on to any trailing
+    /// partial line until the next chunk completes it.
+    pub fn push(&mut self, chunk: &str) {
+        self.partial.push_str(chunk);
+        while let Some(idx) = self.partial.find('\n') {
+            let line = self.partial[..idx].trim_end_matches('\r').to_string();
+            self.partial.drain(..=idx);
+            self.line(&line);
+        }
+    }
+
+    /// Parses a single complete line.
+    pub fn line(&mut self, line: &str) -> FenceLine {
+        let fence = Fence::parse(line);
+        if let Some(open) = &mut self.open {
+            if fence
+                .as_ref()
+                .map(|e| return open.fence.closes(e))
+                .unwrap_or(false)
+            {
+                let open = self.open.take().unwrap();
+                self.blocks.push(open.into_codeblock());
+                return FenceLine::Close;
+            }
+
+            // Code is unindented by as much as its opening fence, such as
+            // when the block is within a list item.
+            let indent = (line.len() - line.trim_start_matches(' ').len()).min(open.fence.indent);
+            open.lines.push(line[indent..].to_string());
+            return FenceLine::Code;
+        }
+
+        if let Some(fence) = fence {
+            let path = CodeBlock::path_from_info(&fence.info)
+                .or_else(|| return CodeBlock::path_from_prose(&self.previous_line));
+            self.open = Some(OpenBlock {
+                fence,
+                path,
+                lines: vec![],
+            });
+            return FenceLine::Open;
+        }
+
+        if !line.trim().is_empty() {
+            self.previous_line = line.trim().to_string();
+        }
+        return FenceLine::Text;
+    }
+
+    /// Whether the lines parsed so far end within a code block.
+    pub fn is_open(&self) -> bool {
+        return self.open.is_some();
+    }
+
+    /// Language of the code block currently open.
+    pub fn open_language(&self) -> Option<String> {
+        return self.open.as_ref().map(|e| {
+            return e
+                .fence
+                .info
+                .split_whitespace()
+                .next()
+                .unwrap_or("")
+                .to_string();
+        });
+    }
+
+    /// Parses any remaining partial line and returns every code block. A
+    /// block still open, such as from a response that was cut off, ends with
+    /// the text.
+    pub fn finish(mut self) -> Vec<CodeBlock> {
+        if !self.partial.is_empty() {
+            let line = std::mem::take(&mut self.partial);
+            self.line(line.trim_end_matches('\r'));
+        }
+        if let Some(open) = self.open.take() {
+            self.blocks.push(open.into_codeblock());
+        }
+
+        return self.blocks;
+    }
+}
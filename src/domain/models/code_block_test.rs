@@ -0,0 +1,172 @@
+use super::CodeBlock;
+use super::CodeBlockParser;
+
+#[test]
+fn it_finds_path_from_info() {
+    assert_eq!(
+        CodeBlock::path_from_info("rust title=src/main.rs"),
+        Some("src/main.rs".to_string())
+    );
+    assert_eq!(
+        CodeBlock::path_from_info(r#"python file="scripts/run.py""#),
+        Some("scripts/run.py".to_string())
+    );
+    assert_eq!(CodeBlock::path_from_info("rust"), None);
+    assert_eq!(CodeBlock::path_from_info("rust title=Example"), None);
+}
+
+#[test]
+fn it_finds_path_from_prose() {
+    assert_eq!(
+        CodeBlock::path_from_prose("In `src/lib.rs`:"),
+        Some("src/lib.rs".to_string())
+    );
+    assert_eq!(
+        CodeBlock::path_from_prose("Update **Cargo.toml**:"),
+        Some("Cargo.toml".to_string())
+    );
+    assert_eq!(CodeBlock::path_from_prose("Call `x.len()` like so:"), None);
+    assert_eq!(
+        CodeBlock::path_from_prose("See `src/lib.rs` for more."),
+        None
+    );
+}
+
+const FIXTURES: [&str; 5] = [
+    "In `src/main.rs`:\n\n```rust\nfn main() {\n    println!(\"Hello, World!\");\n}\n```\n\nThen run it ✨\n",
+    "````markdown\nExample:\n```python\nprint(1)\n```\n````\n~~~sh\necho hi\n~~~\n",
+    "1. Install it:\n\n   ```sh\n   cargo install oatmeal\n   ```\n\n2. Done\r\n```js\r\nlet a = 1;\r\n```\r\n",
+    "Use ```inline``` code.\n```\nplain\n```python\nstill plain\n```\n",
+    "Cut off:\n```rust\nfn main() {\n    let a",
+];
+
+fn parse_chunks(chunks: &[&str]) -> Vec<CodeBlock> {
+    let mut parser = CodeBlockParser::default();
+    for chunk in chunks {
+        parser.push(chunk);
+    }
+    return parser.finish();
+}
+
+fn char_boundaries(text: &str) -> Vec<usize> {
+    return (0..=text.len())
+        .filter(|e| return text.is_char_boundary(*e))
+        .collect();
+}
+
+#[test]
+fn it_parses_code_blocks() {
+    assert_eq!(
+        parse_chunks(&[FIXTURES[0]]),
+        vec![CodeBlock {
+            language: "rust".to_string(),
+            code: "fn main() {\n    println!(\"Hello, World!\");\n}".to_string(),
+            path: Some("src/main.rs".to_string()),
+        }]
+    );
+}
+
+#[test]
+fn it_parses_nested_and_tilde_fences() {
+    assert_eq!(
+        parse_chunks(&[FIXTURES[1]]),
+        vec![
+            CodeBlock {
+                language: "markdown".to_string(),
+                code: "Example:\n```python\nprint(1)\n```".to_string(),
+                path: None,
+            },
+            CodeBlock {
+                language: "sh".to_string(),
+                code: "echo hi".to_string(),
+                path: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn it_parses_indented_fences_and_crlf() {
+    assert_eq!(
+        parse_chunks(&[FIXTURES[2]]),
+        vec![
+            CodeBlock {
+                language: "sh".to_string(),
+                code: "cargo install oatmeal".to_string(),
+                path: None,
+            },
+            CodeBlock {
+                language: "js".to_string(),
+                code: "let a = 1;".to_string(),
+                path: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn it_ignores_inline_code_and_requires_bare_closing_fence() {
+    assert_eq!(
+        parse_chunks(&[FIXTURES[3]]),
+        vec![CodeBlock {
+            language: "".to_string(),
+            code: "plain\n```python\nstill plain".to_string(),
+            path: None,
+        }]
+    );
+}
+
+#[test]
+fn it_keeps_unterminated_code_block() {
+    assert_eq!(
+        parse_chunks(&[FIXTURES[4]]),
+        vec![CodeBlock {
+            language: "rust".to_string(),
+            code: "fn main() {\n    let a".to_string(),
+            path: None,
+        }]
+    );
+}
+
+#[test]
+fn it_parses_the_same_when_split_at_any_boundary() {
+    for fixture in FIXTURES {
+        let expected = parse_chunks(&[fixture]);
+        for idx in char_boundaries(fixture) {
+            let (a, b) = fixture.split_at(idx);
+            assert_eq!(parse_chunks(&[a, b]), expected, "split at {idx}");
+        }
+    }
+}
+
+#[test]
+fn it_parses_the_same_when_split_into_random_chunks() {
+    // Small xorshift generator, so failures are reproducible without adding
+    // a dependency.
+    let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = || {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        return seed;
+    };
+
+    for fixture in FIXTURES {
+        let expected = parse_chunks(&[fixture]);
+        let boundaries = char_boundaries(fixture);
+        for _ in 0..200 {
+            let mut splits = (0..(next() % 8))
+                .map(|_| return boundaries[(next() as usize) % boundaries.len()])
+                .collect::<Vec<usize>>();
+            splits.push(0);
+            splits.push(fixture.len());
+            splits.sort();
+
+            let chunks = splits
+                .windows(2)
+                .map(|e| return &fixture[e[0]..e[1]])
+                .collect::<Vec<&str>>();
+            assert_eq!(parse_chunks(&chunks), expected, "chunks {chunks:?}");
+        }
+    }
+}
@@ -0,0 +1,173 @@
+#[cfg(test)]
+#[path = "color_mode_test.rs"]
+mod tests;
+
+use std::env;
+
+use once_cell::sync::Lazy;
+use ratatui::style::Color;
+use strum::EnumIter;
+use strum::EnumVariantNames;
+use strum::IntoEnumIterator;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// Colors the UI is drawn with, resolved once from `color-mode` as the
+/// config is loaded before the first frame.
+static CURRENT: Lazy<ColorMode> = Lazy::new(|| {
+    return ColorMode::from_config();
+});
+
+/// The 16 ANSI colors, as xterm draws them, for finding the nearest.
+const ANSI_16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Levels of each channel in the 6x6x6 cube of the 256 color palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance(first: (u8, u8, u8), second: (u8, u8, u8)) -> u32 {
+    let channel = |a: u8, b: u8| {
+        let diff = a as i32 - b as i32;
+        return (diff * diff) as u32;
+    };
+    return channel(first.0, second.0) + channel(first.1, second.1) + channel(first.2, second.2);
+}
+
+fn nearest_cube_level(value: u8) -> usize {
+    return (0..CUBE_LEVELS.len())
+        .min_by_key(|e| return (CUBE_LEVELS[*e] as i32 - value as i32).abs())
+        .unwrap();
+}
+
+/// Colour of an entry in the 256 color palette.
+fn indexed_rgb(idx: u8) -> (u8, u8, u8) {
+    return match idx {
+        0..=15 => ANSI_16[idx as usize].1,
+        16..=231 => {
+            let idx = idx - 16;
+            (
+                CUBE_LEVELS[(idx / 36) as usize],
+                CUBE_LEVELS[(idx / 6 % 6) as usize],
+                CUBE_LEVELS[(idx % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (idx - 232) * 10;
+            (level, level, level)
+        }
+    };
+}
+
+/// Nearest entry of the 256 color palette, from its color cube or gray
+/// ramp.
+fn nearest_256(rgb: (u8, u8, u8)) -> u8 {
+    let (r, g, b) = (
+        nearest_cube_level(rgb.0),
+        nearest_cube_level(rgb.1),
+        nearest_cube_level(rgb.2),
+    );
+    let cube = 16 + (36 * r + 6 * g + b) as u8;
+
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+
+    if distance(indexed_rgb(gray), rgb) < distance(indexed_rgb(cube), rgb) {
+        return gray;
+    }
+    return cube;
+}
+
+fn nearest_16(rgb: (u8, u8, u8)) -> Color {
+    return ANSI_16
+        .iter()
+        .min_by_key(|e| return distance(e.1, rgb))
+        .unwrap()
+        .0;
+}
+
+/// How many colors the terminal can show, from `color-mode`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumVariantNames, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum ColorMode {
+    /// Detected from `NO_COLOR`, `COLORTERM` and `TERM`.
+    Auto,
+    Truecolor,
+    #[strum(serialize = "256")]
+    Ansi256,
+    #[strum(serialize = "16")]
+    Ansi16,
+    /// Monochrome, for `NO_COLOR`.
+    None,
+}
+
+impl ColorMode {
+    pub fn parse(text: &str) -> Option<ColorMode> {
+        return ColorMode::iter().find(|e| return e.to_string() == text);
+    }
+
+    /// What the terminal supports going by its environment. Terminals only
+    /// claiming 256 colors are trusted with that, and anything else gets the
+    /// 16 every terminal has.
+    pub fn detect(no_color: &str, colorterm: &str, term: &str) -> ColorMode {
+        if !no_color.is_empty() || term == "dumb" {
+            return ColorMode::None;
+        }
+        if ["truecolor", "24bit"].contains(&colorterm.to_lowercase().as_str()) {
+            return ColorMode::Truecolor;
+        }
+        if term.contains("256color") {
+            return ColorMode::Ansi256;
+        }
+
+        return ColorMode::Ansi16;
+    }
+
+    pub fn from_env() -> ColorMode {
+        let var = |name: &str| return env::var(name).unwrap_or_default();
+        return ColorMode::detect(&var("NO_COLOR"), &var("COLORTERM"), &var("TERM"));
+    }
+
+    /// Truecolor when `color-mode` isn't set.
+    pub fn from_config() -> ColorMode {
+        return match ColorMode::parse(&Config::get(ConfigKey::ColorMode)) {
+            Some(ColorMode::Auto) => ColorMode::from_env(),
+            Some(mode) => mode,
+            None => ColorMode::Truecolor,
+        };
+    }
+
+    pub fn current() -> ColorMode {
+        return *CURRENT;
+    }
+
+    /// The nearest color the terminal can show, or `None` to draw without
+    /// one in monochrome.
+    pub fn apply(&self, color: Color) -> Option<Color> {
+        return match (self, color) {
+            (ColorMode::None, _) => None,
+            (ColorMode::Ansi256, Color::Rgb(r, g, b)) => {
+                Some(Color::Indexed(nearest_256((r, g, b))))
+            }
+            (ColorMode::Ansi16, Color::Rgb(r, g, b)) => Some(nearest_16((r, g, b))),
+            (ColorMode::Ansi16, Color::Indexed(idx)) => Some(nearest_16(indexed_rgb(idx))),
+            _ => Some(color),
+        };
+    }
+}
@@ -0,0 +1,54 @@
+use ratatui::style::Color;
+
+use super::ColorMode;
+
+#[test]
+fn it_detects_color_support() {
+    assert_eq!(
+        ColorMode::detect("", "truecolor", "xterm-256color"),
+        ColorMode::Truecolor
+    );
+    assert_eq!(
+        ColorMode::detect("", "", "xterm-256color"),
+        ColorMode::Ansi256
+    );
+    assert_eq!(ColorMode::detect("", "", "linux"), ColorMode::Ansi16);
+    assert_eq!(ColorMode::detect("", "", "dumb"), ColorMode::None);
+    assert_eq!(
+        ColorMode::detect("1", "truecolor", "xterm-256color"),
+        ColorMode::None
+    );
+}
+
+#[test]
+fn it_maps_to_the_nearest_256_colors() {
+    let mode = ColorMode::Ansi256;
+    assert_eq!(mode.apply(Color::Rgb(255, 0, 0)), Some(Color::Indexed(196)));
+    assert_eq!(
+        mode.apply(Color::Rgb(138, 85, 63)),
+        Some(Color::Indexed(95))
+    );
+    assert_eq!(
+        mode.apply(Color::Rgb(40, 44, 52)),
+        Some(Color::Indexed(236))
+    );
+    assert_eq!(mode.apply(Color::Red), Some(Color::Red));
+}
+
+#[test]
+fn it_maps_to_the_nearest_16_colors() {
+    let mode = ColorMode::Ansi16;
+    assert_eq!(mode.apply(Color::Rgb(250, 10, 10)), Some(Color::LightRed));
+    assert_eq!(mode.apply(Color::Rgb(138, 85, 63)), Some(Color::DarkGray));
+    assert_eq!(mode.apply(Color::Indexed(196)), Some(Color::LightRed));
+    assert_eq!(mode.apply(Color::Yellow), Some(Color::Yellow));
+}
+
+#[test]
+fn it_drops_colors_without_color_support() {
+    assert_eq!(ColorMode::None.apply(Color::Red), None);
+    assert_eq!(
+        ColorMode::Truecolor.apply(Color::Rgb(1, 2, 3)),
+        Some(Color::Rgb(1, 2, 3))
+    );
+}
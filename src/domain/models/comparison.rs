@@ -0,0 +1,96 @@
+#[cfg(test)]
+#[path = "comparison_test.rs"]
+mod tests;
+
+use super::side_by_side;
+use super::BackendName;
+use super::BackendResponse;
+
+/// A model answering a `/compare` prompt, with its answer so far.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComparedModel {
+    pub backend: BackendName,
+    pub model: String,
+    pub answer: String,
+    pub done: bool,
+}
+
+impl ComparedModel {
+    /// Reads `MODEL` for a model of `backend`, or `BACKEND:MODEL` for one of
+    /// another backend. Models with a colon that isn't after a backend name,
+    /// such as Ollama's `llama3:8b`, are of `backend`.
+    pub fn parse(spec: &str, backend: &BackendName) -> ComparedModel {
+        let (backend, model) = match spec.split_once(':') {
+            Some((name, model)) if !model.is_empty() => {
+                match BackendName::parse(name.to_string()) {
+                    Some(name) => (name, model.to_string()),
+                    None => (backend.clone(), spec.to_string()),
+                }
+            }
+            _ => (backend.clone(), spec.to_string()),
+        };
+
+        return ComparedModel {
+            backend,
+            model,
+            answer: "".to_string(),
+            done: false,
+        };
+    }
+}
+
+/// The same prompt answered by several models at once with `/compare`,
+/// streamed into columns side by side.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comparison {
+    pub models: Vec<ComparedModel>,
+}
+
+impl Comparison {
+    pub fn new(specs: &[String], backend: &BackendName) -> Comparison {
+        return Comparison {
+            models: specs
+                .iter()
+                .map(|e| return ComparedModel::parse(e, backend))
+                .collect(),
+        };
+    }
+
+    /// Adds to the answer of the model numbered `idx`.
+    pub fn append(&mut self, idx: usize, res: &BackendResponse) {
+        if let Some(model) = self.models.get_mut(idx) {
+            model.answer += &res.text;
+            model.done = model.done || res.done;
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        return self.models.iter().all(|e| return e.done);
+    }
+
+    /// Lays out the answers in columns fitting within `width` characters,
+    /// each headed by its model, and the backend when they're not all the
+    /// same one.
+    pub fn render(&self, width: usize) -> String {
+        let one_backend = self
+            .models
+            .iter()
+            .all(|e| return e.backend == self.models[0].backend);
+        let columns = self
+            .models
+            .iter()
+            .map(|e| {
+                let mut heading = e.model.to_string();
+                if !one_backend {
+                    heading = format!("{} {heading}", e.backend);
+                }
+                if !e.done {
+                    heading += " ...";
+                }
+                return (heading, e.answer.to_string());
+            })
+            .collect::<Vec<(String, String)>>();
+
+        return side_by_side(&columns, width);
+    }
+}
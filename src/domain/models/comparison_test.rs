@@ -0,0 +1,63 @@
+use super::ComparedModel;
+use super::Comparison;
+use crate::domain::models::Author;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendResponse;
+
+fn chunk(text: &str, done: bool) -> BackendResponse {
+    return BackendResponse {
+        author: Author::Model,
+        text: text.to_string(),
+        done,
+        context: None,
+    };
+}
+
+#[test]
+fn it_parses_models_of_other_backends() {
+    let model = ComparedModel::parse("llama3:8b", &BackendName::Ollama);
+    assert_eq!(model.backend, BackendName::Ollama);
+    assert_eq!(model.model, "llama3:8b");
+
+    let model = ComparedModel::parse("openai:gpt-4o", &BackendName::Ollama);
+    assert_eq!(model.backend, BackendName::OpenAI);
+    assert_eq!(model.model, "gpt-4o");
+}
+
+#[test]
+fn it_streams_answers_side_by_side() {
+    let mut comparison = Comparison::new(
+        &["llama3".to_string(), "mistral".to_string()],
+        &BackendName::Ollama,
+    );
+    comparison.append(0, &chunk("Rust is a systems ", false));
+    comparison.append(1, &chunk("Rust is safe.", true));
+    assert!(!comparison.is_done());
+    insta::assert_snapshot!(comparison.render(43), @r###"
+    llama3 ...           │ mistral
+    ──────────────────── │ ────────────────────
+    Rust is a systems    │ Rust is safe.
+    "###);
+
+    comparison.append(0, &chunk("language.", true));
+    assert!(comparison.is_done());
+    insta::assert_snapshot!(comparison.render(43), @r###"
+    llama3               │ mistral
+    ──────────────────── │ ────────────────────
+    Rust is a systems    │ Rust is safe.
+    language.            │
+    "###);
+}
+
+#[test]
+fn it_names_backends_when_they_differ() {
+    let comparison = Comparison::new(
+        &["llama3".to_string(), "openai:gpt-4o".to_string()],
+        &BackendName::Ollama,
+    );
+
+    assert_eq!(
+        comparison.render(60).lines().next(),
+        Some("ollama llama3 ...           │ openai gpt-4o ...")
+    );
+}
@@ -0,0 +1,137 @@
+#[cfg(test)]
+#[path = "context_documents_test.rs"]
+mod tests;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::estimate_tokens;
+use super::AttachedContext;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// A file or page added with `/context add`, read once and sent ahead of
+/// every prompt.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContextDocument {
+    /// Path or URL it was read from.
+    pub source: String,
+    pub text: String,
+}
+
+impl ContextDocument {
+    pub fn new(source: &str, text: &str) -> ContextDocument {
+        return ContextDocument {
+            source: source.to_string(),
+            text: text.to_string(),
+        };
+    }
+
+    pub fn tokens(&self) -> u64 {
+        return estimate_tokens(&self.text);
+    }
+}
+
+/// Documents kept with the session by `/context`, in the order they were
+/// added. Only those fitting the token budget are sent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContextDocuments {
+    pub documents: Vec<ContextDocument>,
+}
+
+impl ContextDocuments {
+    pub fn new(documents: Vec<ContextDocument>) -> ContextDocuments {
+        return ContextDocuments { documents };
+    }
+
+    pub fn budget_from_config() -> u64 {
+        return Config::get(ConfigKey::ContextDocumentsBudget)
+            .parse::<u64>()
+            .unwrap_or(0);
+    }
+
+    /// Adds the document, replacing one read from the same source so adding
+    /// it again refreshes it. Returns its number.
+    pub fn add(&mut self, document: ContextDocument) -> usize {
+        if let Some(idx) = self
+            .documents
+            .iter()
+            .position(|e| return e.source == document.source)
+        {
+            self.documents[idx] = document;
+            return idx + 1;
+        }
+
+        self.documents.push(document);
+        return self.documents.len();
+    }
+
+    /// Removes a document by its number in `/context list`, starting at 1.
+    pub fn remove(&mut self, number: usize) -> Option<ContextDocument> {
+        if number == 0 || number > self.documents.len() {
+            return None;
+        }
+        return Some(self.documents.remove(number - 1));
+    }
+
+    /// Whether each document is sent, in order, leaving out those that would
+    /// take the total past `budget`.
+    pub fn included(&self, budget: u64) -> Vec<bool> {
+        let mut used = 0;
+        return self
+            .documents
+            .iter()
+            .map(|e| {
+                if used + e.tokens() > budget {
+                    return false;
+                }
+                used += e.tokens();
+                return true;
+            })
+            .collect();
+    }
+
+    /// The documents within `budget`, wrapped as untrusted context.
+    pub fn to_context(&self, budget: u64) -> Vec<AttachedContext> {
+        return self
+            .documents
+            .iter()
+            .zip(self.included(budget))
+            .filter(|(_, included)| return *included)
+            .map(|(e, _)| return AttachedContext::new(&e.source, &e.text))
+            .collect();
+    }
+
+    /// Numbered list of the documents with their size, marking those left
+    /// out for being over `budget`.
+    pub fn format(&self, budget: u64) -> String {
+        let included = self.included(budget);
+        let used = self
+            .documents
+            .iter()
+            .zip(&included)
+            .filter(|(_, included)| return **included)
+            .map(|(e, _)| return e.tokens())
+            .sum::<u64>();
+
+        let mut lines = self
+            .documents
+            .iter()
+            .zip(&included)
+            .enumerate()
+            .map(|(idx, (e, included))| {
+                let mut line = format!("{}. {} (~{} tokens)", idx + 1, e.source, e.tokens());
+                if !included {
+                    line += ", over the budget so not sent";
+                }
+                return line;
+            })
+            .collect::<Vec<String>>();
+        lines.push("".to_string());
+        lines.push(format!(
+            "Sending ~{used} of the {budget} tokens allowed by `context-documents-budget` ahead of each prompt."
+        ));
+
+        return lines.join("\n");
+    }
+}
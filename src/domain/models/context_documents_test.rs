@@ -0,0 +1,50 @@
+use super::ContextDocument;
+use super::ContextDocuments;
+
+fn documents() -> ContextDocuments {
+    return ContextDocuments::new(vec![
+        ContextDocument::new("README.md", &"a".repeat(400)),
+        ContextDocument::new("https://example.com/spec", &"b".repeat(800)),
+        ContextDocument::new("notes.txt", &"c".repeat(40)),
+    ]);
+}
+
+#[test]
+fn it_replaces_documents_from_the_same_source() {
+    let mut documents = documents();
+
+    assert_eq!(
+        documents.add(ContextDocument::new("notes.txt", "Updated")),
+        3
+    );
+    assert_eq!(documents.add(ContextDocument::new("todo.md", "Todo")), 4);
+    assert_eq!(documents.documents[2].text, "Updated");
+}
+
+#[test]
+fn it_removes_documents_by_number() {
+    let mut documents = documents();
+
+    assert_eq!(documents.remove(0), None);
+    assert_eq!(documents.remove(4), None);
+    assert_eq!(documents.remove(1).unwrap().source, "README.md");
+    assert_eq!(documents.documents.len(), 2);
+}
+
+#[test]
+fn it_leaves_out_documents_over_the_budget() {
+    let documents = documents();
+
+    assert_eq!(documents.included(150), vec![true, false, true]);
+    let context = documents.to_context(150);
+    assert_eq!(context.len(), 2);
+    assert_eq!(context[1].description, "notes.txt");
+
+    insta::assert_snapshot!(documents.format(150), @r###"
+    1. README.md (~100 tokens)
+    2. https://example.com/spec (~200 tokens), over the budget so not sent
+    3. notes.txt (~10 tokens)
+
+    Sending ~110 of the 150 tokens allowed by `context-documents-budget` ahead of each prompt.
+    "###);
+}
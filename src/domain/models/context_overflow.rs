@@ -0,0 +1,136 @@
+#[cfg(test)]
+#[path = "context_overflow_test.rs"]
+mod tests;
+
+use strum::EnumIter;
+use strum::EnumVariantNames;
+use strum::IntoEnumIterator;
+
+use super::Author;
+use super::Message;
+use super::SlashCommand;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// Share of the context window the chat may fill, leaving the rest for the
+/// answer.
+const CONTEXT_BUDGET_PERCENT: u64 = 75;
+
+/// What happens to the oldest messages once the chat outgrows the model's
+/// context window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumVariantNames, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum ContextOverflow {
+    /// Replaced by a summary the backend writes of them.
+    Summarize,
+    /// Left out of what's sent.
+    Truncate,
+    /// Sent anyway, leaving the backend to cope or fail.
+    Off,
+}
+
+impl ContextOverflow {
+    pub fn parse(text: &str) -> Option<ContextOverflow> {
+        return ContextOverflow::iter().find(|e| return e.to_string() == text);
+    }
+
+    pub fn from_config() -> ContextOverflow {
+        return ContextOverflow::parse(&Config::get(ConfigKey::ContextOverflow))
+            .unwrap_or(ContextOverflow::Summarize);
+    }
+}
+
+/// Where the chat is split to fit the context window, with the oldest
+/// exchanges before `start` and the latest ones kept as they are from it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContextSplit {
+    pub start: usize,
+    /// Prompts and answers before `start`, as a transcript.
+    pub dropped: String,
+    /// Prompts and answers from `start`, as a transcript.
+    pub kept: String,
+    pub dropped_count: usize,
+}
+
+fn is_sent(message: &Message) -> bool {
+    return match message.author {
+        Author::User => SlashCommand::parse(&message.text).is_none(),
+        Author::Model => true,
+        Author::Oatmeal => false,
+    };
+}
+
+/// Prompts and answers sent to the backend, as a transcript for a prompt
+/// about the conversation.
+pub fn transcript(messages: &[Message]) -> String {
+    return messages
+        .iter()
+        .filter(|e| return is_sent(e))
+        .map(|e| {
+            let role = if e.author == Author::User {
+                "User"
+            } else {
+                "Assistant"
+            };
+            return format!("{role}: {}", e.text.trim());
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n");
+}
+
+/// Finds how many of the latest exchanges from `messages[from..]` fit in the
+/// window along with a prompt of `prompt_tokens`, when they don't all fit.
+/// The kept part always starts with a prompt.
+pub fn split_context(
+    messages: &[Message],
+    from: usize,
+    prompt_tokens: u64,
+    window: u64,
+) -> Option<ContextSplit> {
+    let budget = (window * CONTEXT_BUDGET_PERCENT / 100).saturating_sub(prompt_tokens);
+    let history = &messages[from.min(messages.len())..];
+    let total = history
+        .iter()
+        .filter(|e| return is_sent(e))
+        .map(|e| return e.tokens())
+        .sum::<u64>();
+    if total <= budget {
+        return None;
+    }
+
+    let mut start = history.len();
+    let mut used = 0;
+    for (idx, message) in history.iter().enumerate().rev() {
+        if !is_sent(message) {
+            continue;
+        }
+        used += message.tokens();
+        if used > budget {
+            break;
+        }
+        if message.author == Author::User {
+            start = idx;
+        }
+    }
+
+    let (dropped, kept) = history.split_at(start);
+    let dropped_count = dropped.iter().filter(|e| return is_sent(e)).count();
+    if dropped_count == 0 {
+        return None;
+    }
+
+    return Some(ContextSplit {
+        start: from + start,
+        dropped: transcript(dropped),
+        kept: transcript(kept),
+        dropped_count,
+    });
+}
+
+/// Asks the backend to summarize the oldest exchanges of the chat, to send
+/// in their place.
+pub fn summary_prompt(transcript: &str) -> String {
+    return format!(
+        "Summarize this conversation in a few short paragraphs, keeping the facts, decisions, code identifiers, and open questions a continuation of it would need. Reply with only the summary.\n\n{transcript}"
+    );
+}
@@ -0,0 +1,75 @@
+use super::split_context;
+use super::summary_prompt;
+use super::ContextOverflow;
+use crate::domain::models::Author;
+use crate::domain::models::Message;
+
+fn chat() -> Vec<Message> {
+    return vec![
+        Message::new(Author::User, &"a".repeat(400)),
+        Message::new(Author::Model, &"b".repeat(400)),
+        Message::new(Author::User, "/help"),
+        Message::new(Author::Oatmeal, "Help text"),
+        Message::new(Author::User, &"c".repeat(200)),
+        Message::new(Author::Model, &"d".repeat(200)),
+    ];
+}
+
+#[test]
+fn it_parses_strategies() {
+    assert_eq!(
+        ContextOverflow::parse("summarize"),
+        Some(ContextOverflow::Summarize)
+    );
+    assert_eq!(
+        ContextOverflow::parse("truncate"),
+        Some(ContextOverflow::Truncate)
+    );
+    assert_eq!(ContextOverflow::parse("drop"), None);
+}
+
+#[test]
+fn it_keeps_everything_that_fits() {
+    assert_eq!(split_context(&chat(), 0, 10, 1000), None);
+}
+
+#[test]
+fn it_splits_off_the_oldest_exchanges() {
+    let split = split_context(&chat(), 0, 10, 200).unwrap();
+
+    assert_eq!(split.start, 4);
+    assert_eq!(split.dropped_count, 2);
+    assert_eq!(
+        split.dropped,
+        format!(
+            "User: {}\n\nAssistant: {}",
+            "a".repeat(400),
+            "b".repeat(400)
+        )
+    );
+    assert_eq!(
+        split.kept,
+        format!(
+            "User: {}\n\nAssistant: {}",
+            "c".repeat(200),
+            "d".repeat(200)
+        )
+    );
+}
+
+#[test]
+fn it_starts_what_is_kept_with_a_prompt() {
+    let split = split_context(&chat(), 0, 10, 100).unwrap();
+
+    assert_eq!(split.start, 6);
+    assert_eq!(split.dropped_count, 4);
+    assert_eq!(split.kept, "");
+}
+
+#[test]
+fn it_splits_after_what_was_already_left_out() {
+    assert_eq!(split_context(&chat(), 2, 10, 200), None);
+
+    let prompt = summary_prompt("User: Hi");
+    assert!(prompt.ends_with("\n\nUser: Hi"));
+}
@@ -0,0 +1,53 @@
+#[cfg(test)]
+#[path = "diagram_test.rs"]
+mod tests;
+
+/// Languages of code blocks `/render` can turn into an image.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DiagramKind {
+    Mermaid,
+    Graphviz,
+}
+
+impl DiagramKind {
+    pub fn from_language(language: &str) -> Option<DiagramKind> {
+        return match language.to_lowercase().as_str() {
+            "mermaid" | "mmd" => Some(DiagramKind::Mermaid),
+            "dot" | "graphviz" | "gv" => Some(DiagramKind::Graphviz),
+            _ => None,
+        };
+    }
+
+    /// Program that renders the diagram.
+    pub fn program(&self) -> &'static str {
+        return match self {
+            DiagramKind::Mermaid => "mmdc",
+            DiagramKind::Graphviz => "dot",
+        };
+    }
+
+    /// Arguments to render `input` into a PNG at `output`.
+    pub fn args(&self, input: &str, output: &str) -> Vec<String> {
+        let args = match self {
+            DiagramKind::Mermaid => vec!["-i", input, "-o", output],
+            DiagramKind::Graphviz => vec!["-Tpng", input, "-o", output],
+        };
+
+        return args.iter().map(|e| return e.to_string()).collect();
+    }
+
+    pub fn extension(&self) -> &'static str {
+        return match self {
+            DiagramKind::Mermaid => "mmd",
+            DiagramKind::Graphviz => "dot",
+        };
+    }
+
+    /// What to do when the program isn't installed.
+    pub fn install_hint(&self) -> &'static str {
+        return match self {
+            DiagramKind::Mermaid => "Rendering Mermaid diagrams needs `mmdc`, which isn't installed. Install it with `npm install -g @mermaid-js/mermaid-cli`, or paste the code block into https://mermaid.live instead.",
+            DiagramKind::Graphviz => "Rendering Graphviz diagrams needs `dot`, which isn't installed. Install Graphviz, such as with `brew install graphviz` or `apt install graphviz`, or paste the code block into https://dreampuf.github.io/GraphvizOnline instead.",
+        };
+    }
+}
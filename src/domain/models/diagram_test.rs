@@ -0,0 +1,26 @@
+use super::DiagramKind;
+
+#[test]
+fn it_recognizes_diagram_languages() {
+    assert_eq!(
+        DiagramKind::from_language("Mermaid"),
+        Some(DiagramKind::Mermaid)
+    );
+    assert_eq!(
+        DiagramKind::from_language("dot"),
+        Some(DiagramKind::Graphviz)
+    );
+    assert_eq!(DiagramKind::from_language("rust"), None);
+}
+
+#[test]
+fn it_builds_render_args() {
+    assert_eq!(
+        DiagramKind::Graphviz.args("graph.dot", "graph.png"),
+        vec!["-Tpng", "graph.dot", "-o", "graph.png"]
+    );
+    assert_eq!(
+        DiagramKind::Mermaid.args("chart.mmd", "chart.png"),
+        vec!["-i", "chart.mmd", "-o", "chart.png"]
+    );
+}
@@ -51,6 +51,55 @@ pub struct EditorContext {
 }
 
 impl EditorContext {
+    /// Whether the file, as the editor has it, still has the captured code
+    /// over the same lines, so replacing them won't clobber edits made since.
+    /// Moving the cursor or the selection doesn't count as a change. Contexts
+    /// without an end line have nothing to replace, so they always match.
+    pub fn matches_file(&self, contents: &str) -> bool {
+        let end_line = match self.end_line {
+            Some(end_line) => end_line,
+            None => return true,
+        };
+
+        let start = self.start_line.max(0) as usize;
+        let end = (end_line.max(0) as usize).max(start);
+        let lines = contents
+            .lines()
+            .skip(start)
+            .take(end - start)
+            .collect::<Vec<&str>>();
+        return lines == self.code.lines().collect::<Vec<&str>>();
+    }
+
+    /// Keeps the first `head_lines` and last `tail_lines` of the code,
+    /// replacing those in between with a marker so huge selections fit
+    /// within the model's context. Also returns the number of lines
+    /// removed, which is zero when the code already fits.
+    pub fn truncate(&self, head_lines: usize, tail_lines: usize) -> (EditorContext, usize) {
+        let lines = self.code.lines().collect::<Vec<&str>>();
+        if lines.len() <= head_lines + tail_lines {
+            return (self.clone(), 0);
+        }
+
+        let omitted = lines.len() - head_lines - tail_lines;
+        let marker = format!("... {omitted} lines truncated ...");
+        let code = lines[..head_lines]
+            .iter()
+            .chain([&marker.as_str()])
+            .chain(lines[lines.len() - tail_lines..].iter())
+            .copied()
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        return (
+            EditorContext {
+                code,
+                ..self.clone()
+            },
+            omitted,
+        );
+    }
+
     pub fn format(&self) -> String {
         let file_path = &self.file_path;
         let language = &self.language;
@@ -87,6 +136,11 @@ pub trait Editor {
     /// selected lines, and full code blocks.
     async fn get_context(&self) -> Result<Option<EditorContext>>;
 
+    /// Returns the contents of `file_path` as it's open in the editor,
+    /// including unsaved edits, so code blocks aren't sent over lines that
+    /// changed since they were shared.
+    async fn get_buffer(&self, file_path: &str) -> Result<String>;
+
     /// If required, clear_context is called when Oatmeal exits to do any
     /// necessary cleanup in the editor.
     async fn clear_context(&self) -> Result<()>;
@@ -44,3 +44,77 @@ fn it_renders_with_code() {
     ```
     "###);
 }
+
+#[test]
+fn it_matches_the_captured_lines_of_the_file() {
+    let context = EditorContext {
+        file_path: "file.rs".to_string(),
+        language: "rust".to_string(),
+        code: "let x = 5;\nlet y = 6;".to_string(),
+        start_line: 1,
+        end_line: Some(3),
+    };
+
+    assert!(context.matches_file("fn main() {\nlet x = 5;\nlet y = 6;\n}\n"));
+    assert!(context.matches_file("fn main() {\r\nlet x = 5;\r\nlet y = 6;\r\n}\r\n"));
+    assert!(!context.matches_file("fn main() {\nlet x = 7;\nlet y = 6;\n}\n"));
+    assert!(!context.matches_file("// Moved down\nfn main() {\nlet x = 5;\nlet y = 6;\n}\n"));
+    assert!(!context.matches_file("fn main() {\nlet x = 5;"));
+
+    let context = EditorContext {
+        end_line: None,
+        ..context
+    };
+    assert!(context.matches_file(""));
+}
+
+#[test]
+fn it_keeps_code_that_fits() {
+    let context = EditorContext {
+        code: "a\nb\nc".to_string(),
+        ..EditorContext::default()
+    };
+
+    let (truncated, omitted) = context.truncate(2, 1);
+    assert_eq!(truncated.code, "a\nb\nc");
+    assert_eq!(omitted, 0);
+}
+
+#[test]
+fn it_truncates_the_middle_of_code() {
+    let context = EditorContext {
+        file_path: "file.rs".to_string(),
+        code: "a\nb\nc\nd\ne\nf".to_string(),
+        start_line: 10,
+        end_line: Some(15),
+        ..EditorContext::default()
+    };
+
+    let (truncated, omitted) = context.truncate(2, 1);
+    assert_eq!(omitted, 3);
+    assert_eq!(truncated.file_path, "file.rs");
+    assert_eq!(truncated.end_line, Some(15));
+    insta::assert_snapshot!(truncated.code, @r###"
+    a
+    b
+    ... 3 lines truncated ...
+    f
+    "###);
+}
+
+#[test]
+fn it_truncates_to_only_a_head_or_tail() {
+    let context = EditorContext {
+        code: "a\nb\nc\nd".to_string(),
+        ..EditorContext::default()
+    };
+
+    assert_eq!(
+        context.truncate(1, 0).0.code,
+        "a\n... 3 lines truncated ..."
+    );
+    assert_eq!(
+        context.truncate(0, 1).0.code,
+        "... 3 lines truncated ...\nd"
+    );
+}
@@ -0,0 +1,451 @@
+#[cfg(test)]
+#[path = "error_test.rs"]
+mod tests;
+
+use std::fmt;
+use std::io;
+
+use super::BackendName;
+use super::EditorName;
+use crate::configuration::ConfigKey;
+
+/// The setting holding where the backend is served from, for backends that
+/// can be self-hosted or proxied.
+pub fn url_key(backend: &BackendName) -> Option<ConfigKey> {
+    return match backend {
+        BackendName::LangChain => Some(ConfigKey::LangChainURL),
+        BackendName::Ollama => Some(ConfigKey::OllamaURL),
+        BackendName::OpenAI => Some(ConfigKey::OpenAiURL),
+        BackendName::OpenRouter => Some(ConfigKey::OpenRouterURL),
+        BackendName::Azure => Some(ConfigKey::AzureURL),
+        BackendName::Mistral => Some(ConfigKey::MistralURL),
+        BackendName::Groq => Some(ConfigKey::GroqURL),
+        BackendName::Cohere => Some(ConfigKey::CohereURL),
+        BackendName::Together => Some(ConfigKey::TogetherURL),
+        BackendName::Perplexity => Some(ConfigKey::PerplexityURL),
+        BackendName::LlamaCpp => Some(ConfigKey::LlamaCppURL),
+        BackendName::LMStudio => Some(ConfigKey::LmStudioURL),
+        BackendName::Claude | BackendName::Gemini | BackendName::Bedrock | BackendName::Candle => {
+            None
+        }
+    };
+}
+
+/// The setting holding the backend's API key, for backends that need one.
+pub fn token_key(backend: &BackendName) -> Option<ConfigKey> {
+    return match backend {
+        BackendName::OpenAI => Some(ConfigKey::OpenAiToken),
+        BackendName::Claude => Some(ConfigKey::ClaudeToken),
+        BackendName::Gemini => Some(ConfigKey::GeminiToken),
+        BackendName::OpenRouter => Some(ConfigKey::OpenRouterToken),
+        BackendName::Azure => Some(ConfigKey::AzureToken),
+        BackendName::Bedrock => Some(ConfigKey::AwsAccessKeyId),
+        BackendName::Mistral => Some(ConfigKey::MistralToken),
+        BackendName::Groq => Some(ConfigKey::GroqToken),
+        BackendName::Cohere => Some(ConfigKey::CohereToken),
+        BackendName::Together => Some(ConfigKey::TogetherToken),
+        BackendName::Perplexity => Some(ConfigKey::PerplexityToken),
+        BackendName::LlamaCpp => Some(ConfigKey::LlamaCppToken),
+        BackendName::LangChain
+        | BackendName::Ollama
+        | BackendName::LMStudio
+        | BackendName::Candle => None,
+    };
+}
+
+/// Whether a failed request is worth sending again, as the backend was rate
+/// limiting or had a server error, or the connection to it was lost.
+pub fn is_transient(err: &anyhow::Error) -> bool {
+    for cause in err.chain() {
+        if let Some(e) = cause.downcast_ref::<BackendError>() {
+            return e.is_transient();
+        }
+        if let Some(e) = cause.downcast_ref::<io::Error>() {
+            return matches!(
+                e.kind(),
+                io::ErrorKind::ConnectionReset
+                    | io::ErrorKind::ConnectionAborted
+                    | io::ErrorKind::BrokenPipe
+                    | io::ErrorKind::TimedOut
+                    | io::ErrorKind::UnexpectedEof
+            );
+        }
+    }
+
+    return false;
+}
+
+/// Something the user can pick from an error message to recover from it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecoveryAction {
+    /// Sends the last prompt again, or checks the backend again when nothing
+    /// was sent yet.
+    Retry,
+    /// Switches to the first other backend that passes its health check.
+    SwitchBackend,
+    /// Opens the config file, creating it with the defaults when missing.
+    OpenConfig,
+}
+
+impl RecoveryAction {
+    pub fn label(&self) -> &'static str {
+        return match self {
+            RecoveryAction::Retry => "Retry",
+            RecoveryAction::SwitchBackend => "Switch backend",
+            RecoveryAction::OpenConfig => "Open config",
+        };
+    }
+}
+
+/// Failures talking to a backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BackendError {
+    /// Nothing answered at the backend's URL.
+    Unreachable { backend: BackendName, url: String },
+    /// The backend rejected the API token.
+    InvalidToken { backend: BackendName },
+    /// The backend doesn't serve the model.
+    ModelMissing { backend: BackendName, model: String },
+    /// Too many requests were sent in too short a time.
+    RateLimited { backend: BackendName },
+    /// The backend is running, but has no model loaded to answer with.
+    NoModelLoaded { backend: BackendName },
+    /// Any other unsuccessful response.
+    Status { backend: BackendName, status: u16 },
+}
+
+impl BackendError {
+    /// Classifies an unsuccessful response. A 404 only means the model is
+    /// missing for requests made with one.
+    pub fn from_status(backend: BackendName, status: u16, model: &str) -> BackendError {
+        return match status {
+            401 | 403 => BackendError::InvalidToken { backend },
+            404 if !model.is_empty() => {
+                BackendError::ModelMissing {
+                    backend,
+                    model: model.to_string(),
+                }
+            }
+            429 => BackendError::RateLimited { backend },
+            _ => BackendError::Status { backend, status },
+        };
+    }
+
+    /// Whether the same request may succeed when sent again shortly.
+    pub fn is_transient(&self) -> bool {
+        return match self {
+            BackendError::RateLimited { .. } => true,
+            BackendError::Status { status, .. } => (500..=599).contains(status),
+            _ => false,
+        };
+    }
+
+    fn hint(&self) -> Option<String> {
+        return match self {
+            BackendError::Unreachable { backend, .. } => match url_key(backend) {
+                Some(key) => Some(format!(
+                    "Make sure {backend} is running, or set `{key}` to where it's listening."
+                )),
+                None => Some("Check your network connection, then try again.".to_string()),
+            },
+            BackendError::InvalidToken { backend } => match token_key(backend) {
+                Some(key) => Some(format!(
+                    "Check that `{key}` is set to a valid API key in your config, or pass it with `--{key}`."
+                )),
+                None => Some(format!("Check the credentials {backend} is configured with.")),
+            },
+            BackendError::ModelMissing { .. } => Some(
+                "You can use `/modellist` to view all available models, and `/model NAME` to switch models."
+                    .to_string(),
+            ),
+            BackendError::RateLimited { .. } => {
+                Some("Wait a moment before sending your message again.".to_string())
+            }
+            BackendError::NoModelLoaded { backend } => match backend {
+                BackendName::LMStudio => Some(
+                    "Load a model in LM Studio, or from a terminal with `lms load MODEL`, then retry."
+                        .to_string(),
+                ),
+                _ => Some(format!("Load a model in {backend}, then retry.")),
+            },
+            BackendError::Status { .. } => None,
+        };
+    }
+
+    fn actions(&self) -> Vec<RecoveryAction> {
+        return match self {
+            BackendError::Unreachable { .. } | BackendError::InvalidToken { .. } => {
+                vec![
+                    RecoveryAction::Retry,
+                    RecoveryAction::SwitchBackend,
+                    RecoveryAction::OpenConfig,
+                ]
+            }
+            BackendError::ModelMissing { .. } => {
+                vec![RecoveryAction::SwitchBackend, RecoveryAction::OpenConfig]
+            }
+            BackendError::RateLimited { .. } => vec![RecoveryAction::Retry],
+            BackendError::NoModelLoaded { .. } | BackendError::Status { .. } => {
+                vec![RecoveryAction::Retry, RecoveryAction::SwitchBackend]
+            }
+        };
+    }
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            BackendError::Unreachable { backend, url } => {
+                write!(f, "Backend {backend} isn't reachable at {url}")
+            }
+            BackendError::InvalidToken { backend } => {
+                write!(f, "Backend {backend} rejected the API key")
+            }
+            BackendError::ModelMissing { backend, model } => {
+                write!(f, "Model {model} doesn't exist for backend {backend}")
+            }
+            BackendError::RateLimited { backend } => {
+                write!(f, "Backend {backend} is rate limiting requests")
+            }
+            BackendError::NoModelLoaded { backend } => {
+                write!(f, "Backend {backend} doesn't have a model loaded")
+            }
+            BackendError::Status { backend, status } => {
+                write!(f, "Backend {backend} responded with status {status}")
+            }
+        };
+    }
+}
+
+impl std::error::Error for BackendError {}
+
+impl From<&BackendError> for ErrorReport {
+    fn from(err: &BackendError) -> ErrorReport {
+        return ErrorReport {
+            message: err.to_string(),
+            hint: err.hint(),
+            actions: err.actions(),
+        };
+    }
+}
+
+/// Failures talking to an editor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditorError {
+    /// Oatmeal isn't running somewhere the editor can be reached from.
+    Unavailable { editor: EditorName, reason: String },
+    /// The editor was reached, but the request failed.
+    RequestFailed { editor: EditorName, reason: String },
+}
+
+impl EditorError {
+    fn hint(&self) -> Option<String> {
+        return match self {
+            EditorError::Unavailable { editor, .. } => {
+                Some(format!(
+                    "Start Oatmeal from within {editor}, or pick another editor with `--editor`."
+                ))
+            }
+            EditorError::RequestFailed { editor, .. } => {
+                Some(format!(
+                    "Make sure the Oatmeal plugin for {editor} is installed and up to date."
+                ))
+            }
+        };
+    }
+
+    fn actions(&self) -> Vec<RecoveryAction> {
+        return match self {
+            EditorError::Unavailable { .. } => vec![RecoveryAction::OpenConfig],
+            EditorError::RequestFailed { .. } => vec![],
+        };
+    }
+}
+
+impl fmt::Display for EditorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            EditorError::Unavailable { editor, reason } => {
+                write!(f, "Editor {editor} isn't available: {reason}")
+            }
+            EditorError::RequestFailed { editor, reason } => {
+                write!(f, "Editor {editor} failed to respond: {reason}")
+            }
+        };
+    }
+}
+
+impl std::error::Error for EditorError {}
+
+impl From<&EditorError> for ErrorReport {
+    fn from(err: &EditorError) -> ErrorReport {
+        return ErrorReport {
+            message: err.to_string(),
+            hint: err.hint(),
+            actions: err.actions(),
+        };
+    }
+}
+
+/// Failures loading a saved session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SessionError {
+    NotFound {
+        id: String,
+    },
+    /// The session file exists but couldn't be read.
+    Corrupt {
+        id: String,
+        reason: String,
+    },
+}
+
+impl SessionError {
+    fn hint(&self) -> Option<String> {
+        return match self {
+            SessionError::NotFound { .. } => {
+                Some("Run `oatmeal sessions list` to view the ids of saved sessions.".to_string())
+            }
+            SessionError::Corrupt { id, .. } => Some(format!(
+                "The session may have been edited by hand, or saved by a newer version of Oatmeal. You can remove it with `oatmeal sessions delete --id {id}`."
+            )),
+        };
+    }
+
+    fn actions(&self) -> Vec<RecoveryAction> {
+        return vec![];
+    }
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            SessionError::NotFound { id } => write!(f, "No session found for id {id}"),
+            SessionError::Corrupt { id, reason } => {
+                write!(f, "Session {id} couldn't be read: {reason}")
+            }
+        };
+    }
+}
+
+impl std::error::Error for SessionError {}
+
+impl From<&SessionError> for ErrorReport {
+    fn from(err: &SessionError) -> ErrorReport {
+        return ErrorReport {
+            message: err.to_string(),
+            hint: err.hint(),
+            actions: err.actions(),
+        };
+    }
+}
+
+/// Failures in the user's configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    /// A key required by the chosen backend or service has no value.
+    Missing { key: ConfigKey },
+    /// A key is set to something other than one of its possible values.
+    InvalidValue {
+        key: String,
+        value: String,
+        possible_values: Vec<String>,
+    },
+}
+
+impl ConfigError {
+    fn hint(&self) -> Option<String> {
+        return match self {
+            ConfigError::Missing { key } => {
+                Some(format!(
+                    "Set `{key}` in your config.toml, or pass it with `--{key}`."
+                ))
+            }
+            ConfigError::InvalidValue {
+                possible_values, ..
+            } => {
+                Some(format!(
+                    "Possible values are: {}",
+                    possible_values.join(", ")
+                ))
+            }
+        };
+    }
+
+    fn actions(&self) -> Vec<RecoveryAction> {
+        return vec![RecoveryAction::OpenConfig];
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            ConfigError::Missing { key } => write!(f, "`{key}` is not defined"),
+            ConfigError::InvalidValue { key, value, .. } => {
+                write!(
+                    f,
+                    "config.toml has an invalid value for key '{key}': {value}"
+                )
+            }
+        };
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl From<&ConfigError> for ErrorReport {
+    fn from(err: &ConfigError) -> ErrorReport {
+        return ErrorReport {
+            message: err.to_string(),
+            hint: err.hint(),
+            actions: err.actions(),
+        };
+    }
+}
+
+/// A typed error explained for the user: what went wrong, how they can fix
+/// it, and the actions offered to do so.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorReport {
+    pub message: String,
+    pub hint: Option<String>,
+    pub actions: Vec<RecoveryAction>,
+}
+
+impl ErrorReport {
+    /// Finds the first typed error in the chain. Others aren't something the
+    /// user can act on, so are left to be shown as they are.
+    pub fn from_error(err: &anyhow::Error) -> Option<ErrorReport> {
+        for cause in err.chain() {
+            if let Some(e) = cause.downcast_ref::<BackendError>() {
+                return Some(e.into());
+            }
+            if let Some(e) = cause.downcast_ref::<EditorError>() {
+                return Some(e.into());
+            }
+            if let Some(e) = cause.downcast_ref::<SessionError>() {
+                return Some(e.into());
+            }
+            if let Some(e) = cause.downcast_ref::<ConfigError>() {
+                return Some(e.into());
+            }
+        }
+
+        return None;
+    }
+
+    /// Renders typed errors with their hint, and any other error as it is.
+    pub fn render_error(err: &anyhow::Error) -> String {
+        return match ErrorReport::from_error(err) {
+            Some(report) => report.render(),
+            None => format!("Error: {err}"),
+        };
+    }
+
+    pub fn render(&self) -> String {
+        return match &self.hint {
+            Some(hint) => format!("{}.\n\n{hint}", self.message),
+            None => format!("{}.", self.message),
+        };
+    }
+}
@@ -0,0 +1,144 @@
+use std::io;
+
+use anyhow::anyhow;
+use anyhow::Context;
+
+use super::is_transient;
+use super::BackendError;
+use super::ConfigError;
+use super::ErrorReport;
+use super::RecoveryAction;
+use super::SessionError;
+use crate::configuration::ConfigKey;
+use crate::domain::models::BackendName;
+
+#[test]
+fn it_classifies_status_codes() {
+    assert_eq!(
+        BackendError::from_status(BackendName::OpenAI, 401, "gpt-4"),
+        BackendError::InvalidToken {
+            backend: BackendName::OpenAI
+        }
+    );
+    assert_eq!(
+        BackendError::from_status(BackendName::Ollama, 404, "llama2"),
+        BackendError::ModelMissing {
+            backend: BackendName::Ollama,
+            model: "llama2".to_string()
+        }
+    );
+    assert_eq!(
+        BackendError::from_status(BackendName::Ollama, 404, ""),
+        BackendError::Status {
+            backend: BackendName::Ollama,
+            status: 404
+        }
+    );
+    assert_eq!(
+        BackendError::from_status(BackendName::Claude, 429, "claude"),
+        BackendError::RateLimited {
+            backend: BackendName::Claude
+        }
+    );
+}
+
+#[test]
+fn it_renders_backend_errors_with_a_hint() {
+    let err = anyhow!(BackendError::Unreachable {
+        backend: BackendName::Ollama,
+        url: "http://localhost:11434".to_string(),
+    });
+
+    assert_eq!(
+        ErrorReport::from_error(&err).unwrap().render(),
+        "Backend ollama isn't reachable at http://localhost:11434.\n\nMake sure ollama is running, or set `ollama-url` to where it's listening."
+    );
+}
+
+#[test]
+fn it_renders_token_errors_with_the_config_key() {
+    let err = anyhow!(BackendError::InvalidToken {
+        backend: BackendName::OpenAI,
+    });
+
+    assert_eq!(
+        ErrorReport::from_error(&err).unwrap().hint.unwrap(),
+        "Check that `open-ai-token` is set to a valid API key in your config, or pass it with `--open-ai-token`."
+    );
+}
+
+#[test]
+fn it_retries_transient_errors() {
+    let err = anyhow::Error::new(BackendError::from_status(BackendName::OpenAI, 503, "gpt-4"));
+    assert!(is_transient(&err.context("Failed to stream")));
+
+    let err = anyhow::Error::new(BackendError::from_status(
+        BackendName::Claude,
+        429,
+        "claude",
+    ));
+    assert!(is_transient(&err));
+
+    let err = anyhow::Error::new(io::Error::from(io::ErrorKind::ConnectionReset));
+    assert!(is_transient(&err));
+
+    let err = anyhow::Error::new(BackendError::from_status(BackendName::OpenAI, 401, "gpt-4"));
+    assert!(!is_transient(&err));
+    assert!(!is_transient(&anyhow!("Something else broke")));
+}
+
+#[test]
+fn it_finds_typed_errors_within_context() {
+    let err = Err::<(), _>(SessionError::NotFound {
+        id: "abc".to_string(),
+    })
+    .context("Failed to open session")
+    .unwrap_err();
+
+    let report = ErrorReport::from_error(&err).unwrap();
+    assert_eq!(report.message, "No session found for id abc");
+}
+
+#[test]
+fn it_renders_config_errors() {
+    let report = ErrorReport::from(&ConfigError::Missing {
+        key: ConfigKey::ClaudeToken,
+    });
+
+    assert_eq!(
+        report.render(),
+        "`claude-token` is not defined.\n\nSet `claude-token` in your config.toml, or pass it with `--claude-token`."
+    );
+}
+
+#[test]
+fn it_leaves_untyped_errors_alone() {
+    let err = anyhow!("Something else broke");
+
+    assert_eq!(ErrorReport::from_error(&err), None);
+    assert_eq!(
+        ErrorReport::render_error(&err),
+        "Error: Something else broke"
+    );
+}
+
+#[test]
+fn it_offers_recovery_actions() {
+    let report = ErrorReport::from(&BackendError::Unreachable {
+        backend: BackendName::Ollama,
+        url: "http://localhost:11434".to_string(),
+    });
+    assert_eq!(
+        report.actions,
+        vec![
+            RecoveryAction::Retry,
+            RecoveryAction::SwitchBackend,
+            RecoveryAction::OpenConfig
+        ]
+    );
+
+    let report = ErrorReport::from(&SessionError::NotFound {
+        id: "abc".to_string(),
+    });
+    assert!(report.actions.is_empty());
+}
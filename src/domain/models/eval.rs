@@ -0,0 +1,131 @@
+#[cfg(test)]
+#[path = "eval_test.rs"]
+mod tests;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::LocaleFormat;
+
+/// A prompt to evaluate, read from a line of a `oatmeal eval` prompts file.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvalPrompt {
+    /// Identifies the prompt in the results, defaulting to its line number.
+    #[serde(default)]
+    pub id: String,
+    pub prompt: String,
+}
+
+impl EvalPrompt {
+    /// Parses one JSON object per line, such as `{"id": "fizzbuzz", "prompt":
+    /// "Write fizzbuzz in Rust"}`. Blank lines are skipped.
+    pub fn parse_all(text: &str) -> Result<Vec<EvalPrompt>> {
+        let mut prompts = vec![];
+        for (idx, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let mut prompt = serde_json::from_str::<EvalPrompt>(line)
+                .map_err(|err| return anyhow!("Line {} is not a valid prompt: {err}", idx + 1))?;
+            if prompt.id.is_empty() {
+                prompt.id = (idx + 1).to_string();
+            }
+            prompts.push(prompt);
+        }
+
+        return Ok(prompts);
+    }
+}
+
+/// A model's response to an evaluated prompt, and how long it took.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct EvalResult {
+    pub id: String,
+    pub model: String,
+    pub prompt: String,
+    pub response: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Milliseconds until the first part of the response arrived.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_token_ms: Option<u64>,
+    /// Milliseconds until the response finished.
+    pub latency_ms: u64,
+    /// Number of streamed chunks, which backends such as Ollama and OpenAI
+    /// send one token at a time.
+    pub tokens: usize,
+}
+
+impl EvalResult {
+    pub fn tokens_per_second(&self) -> f64 {
+        if self.latency_ms == 0 {
+            return 0.0;
+        }
+        return self.tokens as f64 / (self.latency_ms as f64 / 1000.0);
+    }
+
+    /// Formats a table of each model's prompt count, errors, and average
+    /// latencies and throughput.
+    pub fn summary(results: &[EvalResult], locale: &LocaleFormat) -> String {
+        let mut models: Vec<&str> = vec![];
+        for result in results {
+            if !models.contains(&result.model.as_str()) {
+                models.push(&result.model);
+            }
+        }
+
+        let width = models
+            .iter()
+            .map(|e| return e.len())
+            .chain(["model".len()])
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec![format!(
+            "{:width$}  {:>7}  {:>6}  {:>11}  {:>12}  {:>8}",
+            "model", "prompts", "errors", "avg latency", "avg first ms", "tokens/s"
+        )];
+        for model in models {
+            let model_results = results
+                .iter()
+                .filter(|e| return e.model == model)
+                .collect::<Vec<&EvalResult>>();
+            let succeeded = model_results
+                .iter()
+                .filter(|e| return e.error.is_none())
+                .collect::<Vec<&&EvalResult>>();
+            let count = succeeded.len().max(1) as f64;
+
+            let latency = succeeded
+                .iter()
+                .map(|e| return e.latency_ms as f64)
+                .sum::<f64>()
+                / count;
+            let first_token = succeeded
+                .iter()
+                .filter_map(|e| return e.first_token_ms)
+                .map(|e| return e as f64)
+                .sum::<f64>()
+                / count;
+            let throughput = succeeded
+                .iter()
+                .map(|e| return e.tokens_per_second())
+                .sum::<f64>()
+                / count;
+
+            lines.push(format!(
+                "{model:width$}  {:>7}  {:>6}  {:>11}  {:>12}  {:>8}",
+                locale.number(model_results.len() as f64, 0),
+                locale.number((model_results.len() - succeeded.len()) as f64, 0),
+                locale.duration(latency as u64),
+                locale.number(first_token, 0),
+                locale.number(throughput, 1)
+            ));
+        }
+
+        return lines.join("\n");
+    }
+}
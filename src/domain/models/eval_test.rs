@@ -0,0 +1,67 @@
+use anyhow::Result;
+
+use super::EvalPrompt;
+use super::EvalResult;
+use super::LocaleFormat;
+
+#[test]
+fn it_parses_prompts() -> Result<()> {
+    let prompts = EvalPrompt::parse_all(
+        r#"{"id": "fizzbuzz", "prompt": "Write fizzbuzz in Rust"}
+
+{"prompt": "Explain ownership"}
+"#,
+    )?;
+
+    assert_eq!(
+        prompts,
+        vec![
+            EvalPrompt {
+                id: "fizzbuzz".to_string(),
+                prompt: "Write fizzbuzz in Rust".to_string(),
+            },
+            EvalPrompt {
+                id: "3".to_string(),
+                prompt: "Explain ownership".to_string(),
+            },
+        ]
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn it_reports_the_line_of_invalid_prompts() {
+    let err = EvalPrompt::parse_all("{\"prompt\": \"Hi\"}\n{\"id\": \"missing\"}").unwrap_err();
+    assert!(err.to_string().starts_with("Line 2 is not a valid prompt"));
+}
+
+#[test]
+fn it_summarizes_results_per_model() {
+    let result = EvalResult {
+        model: "llama2".to_string(),
+        first_token_ms: Some(100),
+        latency_ms: 2000,
+        tokens: 50,
+        ..EvalResult::default()
+    };
+    let results = vec![
+        result.clone(),
+        EvalResult {
+            latency_ms: 1000,
+            first_token_ms: Some(300),
+            ..result.clone()
+        },
+        EvalResult {
+            model: "codellama:7b".to_string(),
+            error: Some("connection refused".to_string()),
+            ..result
+        },
+    ];
+
+    insta::assert_snapshot!(EvalResult::summary(&results, &LocaleFormat::default()), @r###"
+    model         prompts  errors  avg latency  avg first ms  tokens/s
+    llama2              2       0        1.5 s           200      37.5
+    codellama:7b        1       1         0 ms             0       0.0
+    "###);
+}
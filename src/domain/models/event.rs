@@ -1,20 +1,108 @@
 use tui_textarea::Input;
 
+use super::BackendHealth;
 use super::BackendResponse;
+use super::ContextDocument;
+use super::EditorContext;
+use super::Experiment;
+use super::FewShotExample;
+use super::ImageAttachment;
 use super::Message;
+use super::ShareGuest;
+use super::ShareHost;
+use super::ShareMessage;
+use super::SimilarMessage;
+use super::ToolInvocation;
+use super::ToolOutput;
 
 pub enum Event {
     BackendMessage(Message),
+    /// Result of a background health check of the backend.
+    BackendHealthChecked(BackendHealth),
+    /// Switched to another backend, with a message saying which one.
+    BackendSwitched(Message),
+    /// Says how `/modelrefresh` went, which runs alongside anything else.
+    ModelsRefreshed(Message),
     BackendPromptResponse(BackendResponse),
+    /// What the backend is doing before it starts responding, such as
+    /// loading the model, shown in place of the input box.
+    BackendStatus(String),
+    /// The last response stopped because it reached the model's output
+    /// limit, sent after its final `done` response.
+    BackendOutputTruncated(),
+    /// The answer being generated was stopped with `CancelGeneration`, sent
+    /// after the last of it that arrived.
+    BackendCancelled(),
+    /// The backend in use failed, so a fallback is answering instead, with
+    /// a note saying which one and why.
+    BackendFallback(String),
+    /// Context fetched for the next prompt, with a short description of what
+    /// it contains.
+    ContextAttached(String, String),
+    /// An image read for `/image`, to send with the next prompt.
+    ImageAttached(ImageAttachment),
+    /// A document read for `/context add`.
+    ContextDocumentLoaded(ContextDocument),
+    /// Summary of the oldest messages, sent in their place as the chat
+    /// outgrew the context window. Empty when the backend couldn't write
+    /// one, leaving them out instead.
+    ContextSummarized(String),
+    /// Tools the model asked to call, sent after the final `done` response
+    /// that asked for them.
+    ToolCallsRequested(Vec<ToolInvocation>),
+    /// A tool call from the model finished, or failed, with what to send
+    /// back to it.
+    ModelToolFinished(ToolOutput),
+    /// A tool is waiting for its turn to run, with a description of what
+    /// it's waiting on.
+    ToolQueued(String),
+    /// Messages ranked by similarity to a `/similar` query, best match first.
+    SimilarFound(String, Vec<SimilarMessage>),
+    /// Answers to every prompt variant from `/ab`, ready to compare.
+    ExperimentCompleted(Experiment),
+    /// Part of the answer from the model numbered by `/compare`.
+    ComparisonResponse(usize, BackendResponse),
+    /// Submits a prompt to the model as though the user typed it.
+    AutoPrompt(String),
+    /// Steps of a macro from `/play`, with its name, to send one at a time.
+    MacroLoaded(String, Vec<String>),
+    /// Examples read from a file for `/fewshot`, with its path.
+    FewShotLoaded(String, Vec<FewShotExample>),
+    EditorContextSynced(Option<EditorContext>),
+    /// The editor's current context, read for a `/withcontext` question.
+    EditorContextRefreshed(String, Option<EditorContext>),
+    /// Follow-ups to the last answer, from `suggest-replies`.
+    RepliesSuggested(Vec<String>),
+    /// A draft rewritten by `/polish`, to be placed back in the input box.
+    DraftPolished(String),
+    /// This session is now shared with guests through the host.
+    ShareStarted(ShareHost),
+    /// Connected to a session shared by another instance.
+    ShareJoined(ShareGuest),
+    /// A guest joined, when true, or left a shared session.
+    ShareParticipant(String, bool),
+    /// A prompt sent by a guest, with their username.
+    SharedPrompt(String, String),
+    /// An update from the host of a shared session.
+    ShareUpdate(ShareMessage),
     KeyboardCharInput(Input),
     KeyboardCTRLC(),
     KeyboardCTRLO(),
     KeyboardCTRLR(),
     KeyboardEnter(),
+    KeyboardEsc(),
     KeyboardPaste(String),
+    KeyboardTab(),
     UITick(),
+    UIOutlineToggle(),
+    /// Selects the next outline entry, or the previous one when false.
+    UIOutlineSelect(bool),
+    /// Widens the outline pane, or narrows it when false.
+    UIOutlineResize(bool),
     UIScrollDown(),
     UIScrollUp(),
     UIScrollPageDown(),
     UIScrollPageUp(),
+    UIScrollLeft(),
+    UIScrollRight(),
 }
@@ -0,0 +1,129 @@
+#[cfg(test)]
+#[path = "experiment_test.rs"]
+mod tests;
+
+use chrono::Local;
+use chrono::SecondsFormat;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Narrowest a column is wrapped to when comparing answers side by side.
+const MIN_COLUMN_WIDTH: usize = 20;
+
+/// A prompt compared with `/ab`, and the model's answer to it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Variant {
+    pub prompt: String,
+    pub answer: String,
+    /// Backend context after answering, so the chat can continue from the
+    /// winner.
+    #[serde(skip)]
+    pub backend_context: String,
+}
+
+/// Prompt variants answered with the same context by `/ab`, logged for later
+/// review once a winner is picked.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Experiment {
+    pub timestamp: String,
+    pub model: String,
+    pub variants: Vec<Variant>,
+    /// Number of the winning variant, starting from 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub winner: Option<usize>,
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = vec![];
+    for line in text.lines() {
+        let mut current = String::new();
+        for word in line.split(' ') {
+            let word_len = word.chars().count();
+            let current_len = current.chars().count();
+            if current_len > 0 && current_len + 1 + word_len > width {
+                lines.push(std::mem::take(&mut current));
+            } else if current_len > 0 {
+                current.push(' ');
+            }
+
+            // Hard wrap words, such as URLs, too long for a column.
+            let mut chars = word.chars().collect::<Vec<char>>();
+            while chars.len() > width {
+                lines.push(chars.drain(..width).collect());
+            }
+            current.push_str(&chars.into_iter().collect::<String>());
+        }
+        lines.push(current);
+    }
+
+    return lines;
+}
+
+/// Lays out `(heading, text)` columns fitting within `width` characters, with
+/// a rule under each heading.
+pub fn side_by_side(columns: &[(String, String)], width: usize) -> String {
+    if columns.is_empty() {
+        return "".to_string();
+    }
+
+    let separator = " │ ";
+    let gaps = separator.len() * (columns.len() - 1);
+    let column_width = (width.saturating_sub(gaps) / columns.len()).max(MIN_COLUMN_WIDTH);
+
+    let columns = columns
+        .iter()
+        .map(|(heading, text)| {
+            let mut lines = wrap(heading, column_width);
+            lines.push("─".repeat(column_width));
+            lines.extend(wrap(text.trim(), column_width));
+            return lines;
+        })
+        .collect::<Vec<Vec<String>>>();
+
+    let height = columns.iter().map(|e| return e.len()).max().unwrap_or(0);
+    return (0..height)
+        .map(|row| {
+            return columns
+                .iter()
+                .map(|column| {
+                    let cell = column.get(row).map(|e| return e.as_str()).unwrap_or("");
+                    let padding = column_width.saturating_sub(cell.chars().count());
+                    return format!("{cell}{}", " ".repeat(padding));
+                })
+                .collect::<Vec<String>>()
+                .join(separator)
+                .trim_end()
+                .to_string();
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+}
+
+impl Experiment {
+    pub fn new(model: &str, variants: Vec<Variant>) -> Experiment {
+        return Experiment {
+            timestamp: Local::now().to_rfc3339_opts(SecondsFormat::Secs, false),
+            model: model.to_string(),
+            variants,
+            winner: None,
+        };
+    }
+
+    /// Lays out the answers in columns fitting within `width` characters,
+    /// each headed by its number and prompt.
+    pub fn side_by_side(&self, width: usize) -> String {
+        let columns = self
+            .variants
+            .iter()
+            .enumerate()
+            .map(|(idx, variant)| {
+                return (
+                    format!("({}) {}", idx + 1, variant.prompt),
+                    variant.answer.to_string(),
+                );
+            })
+            .collect::<Vec<(String, String)>>();
+
+        return side_by_side(&columns, width);
+    }
+}
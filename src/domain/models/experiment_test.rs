@@ -0,0 +1,59 @@
+use super::Experiment;
+use super::Variant;
+
+fn experiment() -> Experiment {
+    return Experiment::new(
+        "llama2",
+        vec![
+            Variant {
+                prompt: "Be brief".to_string(),
+                answer: "Rust is a systems programming language.".to_string(),
+                ..Variant::default()
+            },
+            Variant {
+                prompt: "Be detailed".to_string(),
+                answer: "Rust is a language focused on safety.\n\nIt has no garbage collector."
+                    .to_string(),
+                ..Variant::default()
+            },
+        ],
+    );
+}
+
+#[test]
+fn it_lays_out_answers_side_by_side() {
+    insta::assert_snapshot!(experiment().side_by_side(43), @r###"
+    (1) Be brief         │ (2) Be detailed
+    ──────────────────── │ ────────────────────
+    Rust is a systems    │ Rust is a language
+    programming          │ focused on safety.
+    language.            │
+                         │ It has no garbage
+                         │ collector.
+    "###);
+}
+
+#[test]
+fn it_hard_wraps_long_words() {
+    let mut experiment = experiment();
+    experiment.variants[0].answer = "https://www.rust-lang.org/learn".to_string();
+    let res = experiment.side_by_side(0);
+
+    assert!(res
+        .lines()
+        .nth(2)
+        .unwrap()
+        .starts_with("https://www.rust-lan │"));
+    assert!(res.lines().nth(3).unwrap().starts_with("g.org/learn "));
+}
+
+#[test]
+fn it_serializes_without_backend_context() {
+    let mut experiment = experiment();
+    experiment.variants[0].backend_context = "[1,2,3]".to_string();
+    experiment.winner = Some(1);
+
+    let json = serde_json::to_string(&experiment).unwrap();
+    assert!(!json.contains("[1,2,3]"));
+    assert!(json.contains(r#""winner":1"#));
+}
@@ -0,0 +1,61 @@
+#[cfg(test)]
+#[path = "fallback_test.rs"]
+mod tests;
+
+use std::fmt;
+
+use anyhow::bail;
+use anyhow::Result;
+use strum::IntoEnumIterator;
+
+use super::BackendName;
+use super::ConfigError;
+use crate::configuration::ConfigKey;
+
+/// A backend and model to retry a prompt with when the one in use fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Fallback {
+    pub backend: BackendName,
+    pub model: String,
+}
+
+impl Fallback {
+    /// Parses comma separated `backend:model` pairs, in the order they should
+    /// be tried. Only the first colon separates the two, as Ollama tags such
+    /// as `llama3:8b` have one too.
+    pub fn parse_all(text: &str) -> Result<Vec<Fallback>> {
+        let mut fallbacks = vec![];
+        for entry in text
+            .split(',')
+            .map(|e| return e.trim())
+            .filter(|e| return !e.is_empty())
+        {
+            let (backend, model) = entry.split_once(':').unwrap_or((entry, ""));
+            let backend = match BackendName::parse(backend.trim().to_string()) {
+                Some(backend) if !model.trim().is_empty() => backend,
+                _ => {
+                    bail!(ConfigError::InvalidValue {
+                        key: ConfigKey::Fallbacks.to_string(),
+                        value: entry.to_string(),
+                        possible_values: BackendName::iter()
+                            .map(|e| return format!("{e}:MODEL"))
+                            .collect(),
+                    });
+                }
+            };
+
+            fallbacks.push(Fallback {
+                backend,
+                model: model.trim().to_string(),
+            });
+        }
+
+        return Ok(fallbacks);
+    }
+}
+
+impl fmt::Display for Fallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return write!(f, "{} {}", self.backend, self.model);
+    }
+}
@@ -0,0 +1,39 @@
+use super::Fallback;
+use crate::domain::models::BackendName;
+use crate::domain::models::ConfigError;
+
+#[test]
+fn it_parses_fallbacks_in_order() {
+    let fallbacks = Fallback::parse_all("ollama:llama3:8b, openai:gpt-4o-mini,").unwrap();
+
+    assert_eq!(
+        fallbacks,
+        vec![
+            Fallback {
+                backend: BackendName::Ollama,
+                model: "llama3:8b".to_string(),
+            },
+            Fallback {
+                backend: BackendName::OpenAI,
+                model: "gpt-4o-mini".to_string(),
+            },
+        ]
+    );
+    assert_eq!(fallbacks[1].to_string(), "openai gpt-4o-mini");
+}
+
+#[test]
+fn it_parses_no_fallbacks() {
+    assert_eq!(Fallback::parse_all("").unwrap(), vec![]);
+}
+
+#[test]
+fn it_rejects_fallbacks_without_a_known_backend_and_model() {
+    for text in ["nope:gpt-4o-mini", "openai", "openai:"] {
+        let err = Fallback::parse_all(text).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<ConfigError>(),
+            Some(ConfigError::InvalidValue { .. })
+        ));
+    }
+}
@@ -0,0 +1,44 @@
+#[cfg(test)]
+#[path = "few_shot_test.rs"]
+mod tests;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// An example exchange sent ahead of the conversation, showing the model how
+/// it should answer. Examples are never shown in the chat.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FewShotExample {
+    pub user: String,
+    pub assistant: String,
+}
+
+impl FewShotExample {
+    /// Parses one JSON object per line, such as `{"user": "Rename x",
+    /// "assistant": "Renamed x to count."}`. Blank lines are skipped.
+    pub fn parse_all(text: &str) -> Result<Vec<FewShotExample>> {
+        let mut examples = vec![];
+        for (idx, line) in text.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let example = serde_json::from_str::<FewShotExample>(line).map_err(|err| {
+                return anyhow!("Line {} is not a valid example: {err}", idx + 1);
+            })?;
+            examples.push(example);
+        }
+
+        return Ok(examples);
+    }
+
+    pub fn format(&self) -> String {
+        return format!(
+            "<example>\nUser: {}\nAssistant: {}\n</example>",
+            self.user.trim(),
+            self.assistant.trim()
+        );
+    }
+}
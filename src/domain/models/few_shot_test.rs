@@ -0,0 +1,26 @@
+use super::FewShotExample;
+
+#[test]
+fn it_parses_examples() {
+    let text = r#"{"user": "Rename x", "assistant": "Renamed `x` to `count`."}
+
+{"user": "Add docs", "assistant": "Added a doc comment."}"#;
+
+    let examples = FewShotExample::parse_all(text).unwrap();
+
+    assert_eq!(examples.len(), 2);
+    assert_eq!(examples[1].user, "Add docs");
+    assert_eq!(
+        examples[0].format(),
+        "<example>\nUser: Rename x\nAssistant: Renamed `x` to `count`.\n</example>"
+    );
+}
+
+#[test]
+fn it_fails_on_invalid_lines() {
+    let err = FewShotExample::parse_all("{\"user\": \"Hi\"}").unwrap_err();
+
+    assert!(err
+        .to_string()
+        .starts_with("Line 1 is not a valid example: missing field `assistant`"));
+}
@@ -0,0 +1,62 @@
+#[cfg(test)]
+#[path = "generation_progress_test.rs"]
+mod tests;
+
+/// How many of the latest answers the expected length is averaged over.
+const MOVING_AVERAGE_WINDOW: usize = 10;
+
+/// Width of the bar drawn in place of the input box.
+const BAR_WIDTH: usize = 10;
+
+/// How far along an answer being streamed is, judged against the length of
+/// the answers before it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GenerationProgress {
+    pub tokens: u64,
+    /// Average length of the latest answers, when there were any.
+    pub expected: Option<u64>,
+}
+
+impl GenerationProgress {
+    pub fn new(tokens: u64, past_answer_tokens: &[u64]) -> GenerationProgress {
+        let recent = &past_answer_tokens[past_answer_tokens
+            .len()
+            .saturating_sub(MOVING_AVERAGE_WINDOW)..];
+        let mut expected = None;
+        if !recent.is_empty() {
+            expected = Some((recent.iter().sum::<u64>() / recent.len() as u64).max(1));
+        }
+
+        return GenerationProgress { tokens, expected };
+    }
+
+    /// Stays below 100% while the answer is still streaming, as the estimate
+    /// is only a guess.
+    pub fn percent(&self) -> Option<u64> {
+        return self
+            .expected
+            .map(|expected| return (self.tokens * 100 / expected).min(99));
+    }
+
+    pub fn render(&self) -> String {
+        let hint = "CTRL+C to stop";
+        let (expected, percent) = match (self.expected, self.percent()) {
+            (Some(expected), Some(percent)) => (expected, percent),
+            _ => return format!("~{} tokens so far · {hint}", self.tokens),
+        };
+
+        let filled = (percent as usize * BAR_WIDTH).div_ceil(100);
+        let bar = format!("{}{}", "▰".repeat(filled), "▱".repeat(BAR_WIDTH - filled));
+        if self.tokens > expected {
+            return format!(
+                "{bar} ~{} tokens, longer than the usual ~{expected} · {hint}",
+                self.tokens
+            );
+        }
+
+        return format!(
+            "{bar} {percent}% · ~{} of ~{expected} tokens · {hint}",
+            self.tokens
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use super::GenerationProgress;
+
+#[test]
+fn it_averages_the_latest_answers() {
+    let mut past = vec![10_000; 5];
+    past.extend(vec![100; 10]);
+    let progress = GenerationProgress::new(40, &past);
+
+    assert_eq!(progress.expected, Some(100));
+    assert_eq!(progress.percent(), Some(40));
+    assert_eq!(
+        progress.render(),
+        "▰▰▰▰▱▱▱▱▱▱ 40% · ~40 of ~100 tokens · CTRL+C to stop"
+    );
+}
+
+#[test]
+fn it_renders_answers_longer_than_usual() {
+    let progress = GenerationProgress::new(250, &[100, 200]);
+
+    assert_eq!(progress.percent(), Some(99));
+    assert_eq!(
+        progress.render(),
+        "▰▰▰▰▰▰▰▰▰▰ ~250 tokens, longer than the usual ~150 · CTRL+C to stop"
+    );
+}
+
+#[test]
+fn it_renders_the_first_answer() {
+    let progress = GenerationProgress::new(12, &[]);
+
+    assert_eq!(progress.percent(), None);
+    assert_eq!(progress.render(), "~12 tokens so far · CTRL+C to stop");
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+#[path = "image_attachment_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use base64::engine::general_purpose::STANDARD as b64;
+use base64::Engine;
+
+/// Largest image that can be attached, as backends reject bigger ones.
+const MAX_IMAGE_BYTES: usize = 20_000_000;
+
+/// Media type of the image, told apart by the bytes it starts with.
+fn media_type(bytes: &[u8]) -> Option<&'static str> {
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some("image/png");
+    }
+    if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some("image/jpeg");
+    }
+    if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+        return Some("image/gif");
+    }
+    if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+
+    return None;
+}
+
+/// An image attached with `/image`, sent with the next prompt for vision
+/// models to look at.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ImageAttachment {
+    /// Path it was read from, or `clipboard`.
+    pub source: String,
+    pub media_type: String,
+    /// The image, base64 encoded.
+    pub data: String,
+    /// Bytes before encoding.
+    pub size: usize,
+}
+
+impl ImageAttachment {
+    /// Fails for anything other than a PNG, JPEG, GIF or WebP image, or one
+    /// over `MAX_IMAGE_BYTES`.
+    pub fn from_bytes(source: &str, bytes: &[u8]) -> Result<ImageAttachment> {
+        let media_type = match media_type(bytes) {
+            Some(media_type) => media_type,
+            None => {
+                bail!(format!(
+                    "{source} isn't an image, only PNG, JPEG, GIF and WebP images can be attached."
+                ))
+            }
+        };
+        if bytes.len() > MAX_IMAGE_BYTES {
+            bail!(format!(
+                "{source} is too big to attach, images can be up to {} MB.",
+                MAX_IMAGE_BYTES / 1_000_000
+            ));
+        }
+
+        return Ok(ImageAttachment {
+            source: source.to_string(),
+            media_type: media_type.to_string(),
+            data: b64.encode(bytes),
+            size: bytes.len(),
+        });
+    }
+
+    pub fn data_url(&self) -> String {
+        return format!("data:{};base64,{}", self.media_type, self.data);
+    }
+
+    /// Shown in the chat in place of the image, which terminals can't draw.
+    pub fn placeholder(&self) -> String {
+        return format!(
+            "[Image: {}, {}, {} KB]",
+            self.source,
+            self.media_type,
+            self.size.div_ceil(1000)
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use super::ImageAttachment;
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+#[test]
+fn it_detects_the_media_type() {
+    let image = ImageAttachment::from_bytes("screenshot.png", PNG).unwrap();
+    assert_eq!(image.media_type, "image/png");
+    assert_eq!(image.data, "iVBORw0KGgoAAAANSUhEUg==");
+    assert_eq!(
+        image.data_url(),
+        "data:image/png;base64,iVBORw0KGgoAAAANSUhEUg=="
+    );
+
+    let jpeg = ImageAttachment::from_bytes("photo.jpg", &[0xFF, 0xD8, 0xFF, 0xE0]).unwrap();
+    assert_eq!(jpeg.media_type, "image/jpeg");
+    let webp = ImageAttachment::from_bytes("clipboard", b"RIFF\0\0\0\0WEBPVP8 ").unwrap();
+    assert_eq!(webp.media_type, "image/webp");
+}
+
+#[test]
+fn it_rejects_files_that_arent_images() {
+    let err = ImageAttachment::from_bytes("notes.txt", b"Hello").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "notes.txt isn't an image, only PNG, JPEG, GIF and WebP images can be attached."
+    );
+}
+
+#[test]
+fn it_formats_a_placeholder() {
+    let image = ImageAttachment::from_bytes("screenshot.png", PNG).unwrap();
+    assert_eq!(
+        image.placeholder(),
+        "[Image: screenshot.png, image/png, 1 KB]"
+    );
+}
@@ -0,0 +1,138 @@
+#[cfg(test)]
+#[path = "input_lint_test.rs"]
+mod tests;
+
+use std::fmt;
+
+use super::SlashCommand;
+use super::SLASH_COMMANDS;
+
+/// Typos further than this from every command aren't given a suggestion.
+const MAX_COMMAND_DISTANCE: usize = 2;
+
+/// Something that looks off about the prompt being typed. These are only
+/// shown as hints, submitting is never blocked by them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InputWarning {
+    UnclosedFence,
+    UnbalancedQuotes,
+    UnknownCommand {
+        command: String,
+        suggestion: Option<String>,
+    },
+}
+
+impl fmt::Display for InputWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            InputWarning::UnclosedFence => write!(f, "Unclosed code fence"),
+            InputWarning::UnbalancedQuotes => write!(f, "Unbalanced quotes"),
+            InputWarning::UnknownCommand {
+                command,
+                suggestion: Some(suggestion),
+            } => write!(f, "Unknown command {command}, did you mean {suggestion}?"),
+            InputWarning::UnknownCommand {
+                command,
+                suggestion: None,
+            } => write!(f, "Unknown command {command}, it will be sent as a prompt"),
+        };
+    }
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut previous = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    return previous[b.len()];
+}
+
+/// Slash command the user most likely meant to type.
+fn closest_command(command: &str) -> Option<String> {
+    return SLASH_COMMANDS
+        .iter()
+        .map(|e| return (edit_distance(command, e), e))
+        .filter(|(distance, _)| return *distance <= MAX_COMMAND_DISTANCE)
+        .min_by_key(|(distance, _)| return *distance)
+        .map(|(_, e)| return e.to_string());
+}
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    return line.starts_with("```") || line.starts_with("~~~");
+}
+
+/// Counts double quotes outside of code, where they're expected to pair up.
+/// Single quotes double as apostrophes, so only count in slash commands.
+fn has_unbalanced_quotes(text: &str, is_command: bool) -> bool {
+    let mut double = 0;
+    let mut single = 0;
+    let mut opening = 0;
+    let mut closing = 0;
+    let mut in_fence = false;
+    for line in text.lines() {
+        if is_fence(line) {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+
+        let mut in_code = false;
+        let mut escaped = false;
+        for c in line.chars() {
+            if escaped {
+                escaped = false;
+                continue;
+            }
+            match c {
+                '\\' if is_command => escaped = true,
+                '`' => in_code = !in_code,
+                _ if in_code => {}
+                '"' => double += 1,
+                '\'' => single += 1,
+                '“' => opening += 1,
+                '”' => closing += 1,
+                _ => {}
+            }
+        }
+    }
+
+    return double % 2 == 1 || opening != closing || (is_command && single % 2 == 1);
+}
+
+/// Checks the prompt being typed for mistakes worth pointing out before it's
+/// sent.
+pub fn lint_input(text: &str) -> Vec<InputWarning> {
+    let mut warnings: Vec<InputWarning> = vec![];
+    if text.trim().is_empty() {
+        return warnings;
+    }
+
+    let command = text.split_whitespace().next().unwrap_or("");
+    let is_command = command.starts_with('/') && command.len() > 1 && !command[1..].contains('/');
+    if is_command && SlashCommand::parse(command).is_none() {
+        warnings.push(InputWarning::UnknownCommand {
+            command: command.to_string(),
+            suggestion: closest_command(command),
+        });
+    }
+
+    if text.lines().filter(|e| return is_fence(e)).count() % 2 == 1 {
+        warnings.push(InputWarning::UnclosedFence);
+    }
+
+    if has_unbalanced_quotes(text, is_command) {
+        warnings.push(InputWarning::UnbalancedQuotes);
+    }
+
+    return warnings;
+}
@@ -0,0 +1,62 @@
+use super::lint_input;
+use super::InputWarning;
+
+#[test]
+fn it_accepts_clean_prompts() {
+    assert_eq!(lint_input(""), vec![]);
+    assert_eq!(
+        lint_input("Why doesn't \"this\" compile?\n```rust\nlet c = '\"';\n```"),
+        vec![]
+    );
+    assert_eq!(lint_input("/modellist --filter \"gpt 4\""), vec![]);
+    assert_eq!(lint_input("/usr/bin is on my PATH"), vec![]);
+}
+
+#[test]
+fn it_warns_about_unclosed_fences() {
+    assert_eq!(
+        lint_input("Explain this:\n```rust\nfn main() {}"),
+        vec![InputWarning::UnclosedFence]
+    );
+}
+
+#[test]
+fn it_warns_about_unbalanced_quotes() {
+    assert_eq!(
+        lint_input("What does \"borrow mean?"),
+        vec![InputWarning::UnbalancedQuotes]
+    );
+    assert_eq!(
+        lint_input("What does “borrow mean?"),
+        vec![InputWarning::UnbalancedQuotes]
+    );
+    assert_eq!(
+        lint_input("/similar 'borrow"),
+        vec![InputWarning::UnbalancedQuotes]
+    );
+    assert_eq!(lint_input("Isn't `\"` a quote?"), vec![]);
+}
+
+#[test]
+fn it_suggests_misspelled_commands() {
+    let warnings = lint_input("/modellst --page 2");
+    assert_eq!(
+        warnings,
+        vec![InputWarning::UnknownCommand {
+            command: "/modellst".to_string(),
+            suggestion: Some("/modellist".to_string()),
+        }]
+    );
+    assert_eq!(
+        warnings[0].to_string(),
+        "Unknown command /modellst, did you mean /modellist?"
+    );
+
+    assert_eq!(
+        lint_input("/frobnicate"),
+        vec![InputWarning::UnknownCommand {
+            command: "/frobnicate".to_string(),
+            suggestion: None,
+        }]
+    );
+}
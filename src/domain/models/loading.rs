@@ -11,9 +11,11 @@ use ratatui::Frame;
 pub struct Loading {}
 
 impl Loading {
-    pub fn render(&self, frame: &mut Frame, rect: Rect) {
+    /// Renders the status reported by the backend, or `Loading...` when
+    /// there isn't one.
+    pub fn render(&self, frame: &mut Frame, rect: Rect, status: Option<&str>) {
         frame.render_widget(
-            Paragraph::new("Loading...")
+            Paragraph::new(status.unwrap_or("Loading..."))
                 .block(
                     Block::default()
                         .borders(Borders::ALL)
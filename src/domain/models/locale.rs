@@ -0,0 +1,149 @@
+#[cfg(test)]
+#[path = "locale_test.rs"]
+mod tests;
+
+use std::env;
+use std::fmt;
+
+use chrono::DateTime;
+use chrono::Locale;
+use chrono::TimeZone;
+use pure_rust_locales::locale_match;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// Formats numbers, dates, and durations for display in the user's locale.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LocaleFormat {
+    locale: Locale,
+}
+
+impl Default for LocaleFormat {
+    fn default() -> LocaleFormat {
+        return LocaleFormat {
+            locale: Locale::en_US,
+        };
+    }
+}
+
+impl LocaleFormat {
+    /// Parses a locale such as `de_DE`, `de-DE`, or `de_DE.UTF-8` as found in
+    /// `LANG`. "C" and "POSIX" use the POSIX locale.
+    pub fn parse(text: &str) -> Option<LocaleFormat> {
+        let name = text
+            .split(['.', '@'])
+            .next()
+            .unwrap_or("")
+            .trim()
+            .replace('-', "_");
+        if name == "C" {
+            return Some(LocaleFormat {
+                locale: Locale::POSIX,
+            });
+        }
+
+        return Locale::try_from(name.as_str())
+            .ok()
+            .map(|locale| return LocaleFormat { locale });
+    }
+
+    /// Uses the `locale` option, falling back to `LC_ALL`, `LC_NUMERIC`,
+    /// and `LANG` from the environment, then to en_US.
+    pub fn from_config() -> LocaleFormat {
+        let configured = Config::get(ConfigKey::Locale);
+        let candidates = [
+            Some(configured),
+            env::var("LC_ALL").ok(),
+            env::var("LC_NUMERIC").ok(),
+            env::var("LANG").ok(),
+        ];
+
+        return candidates
+            .into_iter()
+            .flatten()
+            .filter(|e| return !e.is_empty())
+            .find_map(|e| return LocaleFormat::parse(&e))
+            .unwrap_or_default();
+    }
+
+    /// Formats `value` with `decimals` digits after the decimal point, and
+    /// the integer part grouped by thousands.
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        let decimal_point = locale_match!(self.locale => LC_NUMERIC::DECIMAL_POINT);
+        let thousands_sep = locale_match!(self.locale => LC_NUMERIC::THOUSANDS_SEP);
+        let grouping = locale_match!(self.locale => LC_NUMERIC::GROUPING);
+
+        let formatted = format!("{:.decimals$}", value.abs());
+        let (integer, fraction) = formatted.split_once('.').unwrap_or((&formatted, ""));
+
+        // Only the first group size is used, which is the same for every
+        // group in nearly every locale.
+        let group = grouping.first().copied().unwrap_or(0);
+        let mut grouped = integer.to_string();
+        if group > 0 && !thousands_sep.is_empty() {
+            let digits = integer.chars().collect::<Vec<char>>();
+            grouped = digits
+                .rchunks(group as usize)
+                .rev()
+                .map(|e| return e.iter().collect::<String>())
+                .collect::<Vec<String>>()
+                .join(thousands_sep);
+        }
+
+        let sign = if value < 0.0 && formatted.chars().any(|e| return e != '0' && e != '.') {
+            "-"
+        } else {
+            ""
+        };
+        if fraction.is_empty() {
+            return format!("{sign}{grouped}");
+        }
+        return format!("{sign}{grouped}{decimal_point}{fraction}");
+    }
+
+    /// Formats the date and time in the locale's preferred style, keeping
+    /// the time zone it's in.
+    pub fn datetime<Tz: TimeZone>(&self, datetime: &DateTime<Tz>) -> String
+    where
+        Tz::Offset: fmt::Display,
+    {
+        // The locale's formats are expanded here, as chrono stops at the
+        // `%r` that en_US and others use for their `%X`.
+        let date_format = locale_match!(self.locale => LC_TIME::D_FMT);
+        let mut time_format = locale_match!(self.locale => LC_TIME::T_FMT);
+        if time_format == "%r" {
+            time_format = locale_match!(self.locale => LC_TIME::T_FMT_AMPM);
+        }
+
+        return datetime
+            .format_localized(&format!("{date_format} {time_format}"), self.locale)
+            .to_string();
+    }
+
+    /// Parses an RFC 3339 timestamp, such as from a session file, and
+    /// formats it with `datetime`. Anything else is returned as is.
+    pub fn timestamp(&self, timestamp: &str) -> String {
+        return DateTime::parse_from_rfc3339(timestamp)
+            .map(|e| return self.datetime(&e))
+            .unwrap_or_else(|_| return timestamp.to_string());
+    }
+
+    /// Formats milliseconds as milliseconds, seconds, or minutes and
+    /// seconds, depending on how long they are.
+    pub fn duration(&self, ms: u64) -> String {
+        if ms < 1000 {
+            return format!("{} ms", self.number(ms as f64, 0));
+        }
+        if ms < 60_000 {
+            return format!("{} s", self.number(ms as f64 / 1000.0, 1));
+        }
+
+        let seconds = ms / 1000;
+        return format!(
+            "{} min {} s",
+            self.number((seconds / 60) as f64, 0),
+            seconds % 60
+        );
+    }
+}
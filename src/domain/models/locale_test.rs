@@ -0,0 +1,64 @@
+use chrono::DateTime;
+
+use super::LocaleFormat;
+
+#[test]
+fn it_parses_locales_from_the_environment() {
+    let de = LocaleFormat::parse("de_DE").unwrap();
+    assert_eq!(LocaleFormat::parse("de_DE.UTF-8"), Some(de));
+    assert_eq!(LocaleFormat::parse("de-DE"), Some(de));
+    assert!(LocaleFormat::parse("C").is_some());
+    assert!(LocaleFormat::parse("POSIX").is_some());
+    assert!(LocaleFormat::parse("klingon").is_none());
+}
+
+#[test]
+fn it_formats_numbers() {
+    let en = LocaleFormat::default();
+    assert_eq!(en.number(1234567.891, 2), "1,234,567.89");
+    assert_eq!(en.number(999.0, 0), "999");
+    assert_eq!(en.number(-1234.5, 1), "-1,234.5");
+    assert_eq!(en.number(-0.01, 1), "0.0");
+
+    let de = LocaleFormat::parse("de_DE").unwrap();
+    assert_eq!(de.number(1234567.891, 2), "1.234.567,89");
+
+    let posix = LocaleFormat::parse("C").unwrap();
+    assert_eq!(posix.number(1234567.891, 2), "1234567.89");
+}
+
+#[test]
+fn it_formats_datetimes_in_their_time_zone() {
+    let datetime = DateTime::parse_from_rfc3339("2024-06-04T14:38:31-07:00").unwrap();
+
+    assert_eq!(
+        LocaleFormat::default().datetime(&datetime),
+        "06/04/2024 02:38:31 PM"
+    );
+    assert_eq!(
+        LocaleFormat::parse("fr_FR").unwrap().datetime(&datetime),
+        "04/06/2024 14:38:31"
+    );
+}
+
+#[test]
+fn it_formats_timestamps() {
+    let de = LocaleFormat::parse("de_DE").unwrap();
+
+    assert_eq!(
+        de.timestamp("2023-11-28T10:00:00+00:00"),
+        "28.11.2023 10:00:00"
+    );
+    assert_eq!(de.timestamp("yesterday"), "yesterday");
+}
+
+#[test]
+fn it_formats_durations() {
+    let en = LocaleFormat::default();
+    assert_eq!(en.duration(350), "350 ms");
+    assert_eq!(en.duration(1500), "1.5 s");
+    assert_eq!(en.duration(125_000), "2 min 5 s");
+
+    let de = LocaleFormat::parse("de_DE").unwrap();
+    assert_eq!(de.duration(1500), "1,5 s");
+}
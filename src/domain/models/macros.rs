@@ -0,0 +1,46 @@
+#[cfg(test)]
+#[path = "macros_test.rs"]
+mod tests;
+
+use super::SlashCommand;
+
+/// Placeholder in a step replaced by the text given after `/play NAME`.
+pub const MACRO_ARGS_PLACEHOLDER: &str = "{args}";
+
+/// A named list of prompts and slash commands, recorded with `/record` and
+/// replayed in order with `/play`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Macro {
+    pub name: String,
+    pub steps: Vec<String>,
+}
+
+impl Macro {
+    pub fn new(name: &str) -> Macro {
+        return Macro {
+            name: name.to_string(),
+            steps: vec![],
+        };
+    }
+
+    /// Macro commands are left out, so playing a macro never records or
+    /// plays another one.
+    pub fn record(&mut self, input: &str) {
+        if let Some(command) = SlashCommand::parse(input) {
+            if command.is_record() || command.is_stop_macro() || command.is_play() {
+                return;
+            }
+        }
+
+        self.steps.push(input.to_string());
+    }
+
+    /// Steps to send for `/play`, with any `{args}` filled in.
+    pub fn expand(&self, args: &str) -> Vec<String> {
+        return self
+            .steps
+            .iter()
+            .map(|e| return e.replace(MACRO_ARGS_PLACEHOLDER, args))
+            .collect();
+    }
+}
@@ -0,0 +1,35 @@
+use super::Macro;
+
+#[test]
+fn it_records_steps_without_macro_commands() {
+    let mut recorded = Macro::new("review");
+    recorded.record("/record other");
+    recorded.record("/gh {args}");
+    recorded.record("/play review");
+    recorded.record("Review this pull request.");
+    recorded.record("/stopmacro");
+
+    assert_eq!(
+        recorded.steps,
+        vec![
+            "/gh {args}".to_string(),
+            "Review this pull request.".to_string()
+        ]
+    );
+}
+
+#[test]
+fn it_expands_args() {
+    let recorded = Macro {
+        name: "review".to_string(),
+        steps: vec!["/gh {args}".to_string(), "Summarize it.".to_string()],
+    };
+
+    assert_eq!(
+        recorded.expand("https://github.com/dustinblackman/oatmeal/pull/1"),
+        vec![
+            "/gh https://github.com/dustinblackman/oatmeal/pull/1".to_string(),
+            "Summarize it.".to_string()
+        ]
+    );
+}
@@ -1,10 +1,17 @@
 #[cfg(test)]
 #[path = "message_test.rs"]
 mod tests;
+
+use std::collections::BTreeMap;
+
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::estimate_tokens;
 use super::Author;
+use super::CodeBlock;
+use super::CodeBlockParser;
+use super::RecoveryAction;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MessageType {
@@ -12,11 +19,57 @@ pub enum MessageType {
     Error,
 }
 
+/// Where a message's text was written within the history archive.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ArchivedText {
+    pub offset: u64,
+    pub len: usize,
+}
+
 #[derive(Clone, Serialize, Deserialize)]
 pub struct Message {
     pub author: Author,
     pub text: String,
     mtype: MessageType,
+    /// Languages set with `/lang`, keyed by the index of the code block
+    /// within this message.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    codeblock_languages: BTreeMap<usize, String>,
+    /// Name displayed instead of the author's, such as a participant in a
+    /// shared session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    username: Option<String>,
+    /// Seed the backend generated the message with, so it can be
+    /// reproduced.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    /// Tokens counted for the text with the model's tokenizer, or sent for
+    /// the message when they aren't its text, such as context attached with
+    /// `/gh`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tokens: Option<u64>,
+    /// Placeholders for the images sent with the message, shown below its
+    /// text as terminals can't draw them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    /// Copy of the text on disk, once the history outgrew its memory limit.
+    #[serde(skip)]
+    archived: Option<ArchivedText>,
+    /// Whether the text was dropped from memory, leaving only the copy in
+    /// the archive.
+    #[serde(skip)]
+    evicted: bool,
+    /// Whether the user typed the prompt, so its `{{...}}` placeholders are
+    /// filled in. Prompts built from tool output or files aren't, nor are
+    /// ones restored with a saved session.
+    #[serde(skip)]
+    typed: bool,
+    /// Actions offered to recover from an error, chosen with TAB.
+    #[serde(skip)]
+    recovery: Vec<RecoveryAction>,
+    /// Index of the chosen recovery action, which ENTER runs.
+    #[serde(skip)]
+    selected_recovery: Option<usize>,
 }
 
 impl Message {
@@ -25,6 +78,16 @@ impl Message {
             author: author.clone(),
             text: text.to_string().replace('\t', "  "),
             mtype: MessageType::Normal,
+            codeblock_languages: BTreeMap::new(),
+            username: None,
+            seed: None,
+            tokens: None,
+            images: vec![],
+            archived: None,
+            evicted: false,
+            typed: false,
+            recovery: vec![],
+            selected_recovery: None,
         };
     }
 
@@ -33,40 +96,215 @@ impl Message {
             author: author.clone(),
             text: text.to_string().replace('\t', "  "),
             mtype,
+            codeblock_languages: BTreeMap::new(),
+            username: None,
+            seed: None,
+            tokens: None,
+            images: vec![],
+            archived: None,
+            evicted: false,
+            typed: false,
+            recovery: vec![],
+            selected_recovery: None,
         };
     }
 
+    /// An error offering actions to recover from it.
+    pub fn new_error(text: &str, actions: Vec<RecoveryAction>) -> Message {
+        let mut message = Message::new_with_type(Author::Oatmeal, MessageType::Error, text);
+        message.set_recovery(actions);
+        return message;
+    }
+
     pub fn message_type(&self) -> MessageType {
         return self.mtype;
     }
 
+    /// Name displayed above the message.
+    pub fn author_name(&self) -> String {
+        if let Some(username) = &self.username {
+            return username.to_string();
+        }
+        return self.author.to_string();
+    }
+
+    pub fn username(&self) -> Option<&str> {
+        return self.username.as_deref();
+    }
+
+    pub fn set_username(&mut self, username: &str) {
+        self.username = Some(username.to_string());
+    }
+
+    pub fn set_seed(&mut self, seed: Option<u64>) {
+        self.seed = seed;
+    }
+
+    pub fn images(&self) -> &[String] {
+        return &self.images;
+    }
+
+    pub fn set_images(&mut self, images: Vec<String>) {
+        self.images = images;
+    }
+
+    pub fn set_tokens(&mut self, tokens: u64) {
+        self.tokens = Some(tokens);
+    }
+
+    pub fn is_counted(&self) -> bool {
+        return self.tokens.is_some();
+    }
+
+    /// Estimated tokens the message costs, which is nothing for messages from
+    /// Oatmeal unless they stand for something sent to the backend.
+    pub fn tokens(&self) -> u64 {
+        if let Some(tokens) = self.tokens {
+            return tokens;
+        }
+        if self.author == Author::Oatmeal {
+            return 0;
+        }
+        return estimate_tokens(&self.text);
+    }
+
+    /// Name displayed in the message's border, including the seed when one
+    /// was set.
+    pub fn title(&self) -> String {
+        if let Some(seed) = self.seed {
+            return format!("{} (seed {seed})", self.author_name());
+        }
+        return self.author_name();
+    }
+
+    pub fn set_recovery(&mut self, actions: Vec<RecoveryAction>) {
+        self.recovery = actions;
+        self.selected_recovery = None;
+    }
+
+    pub fn recovery(&self) -> &[RecoveryAction] {
+        return &self.recovery;
+    }
+
+    pub fn selected_recovery(&self) -> Option<RecoveryAction> {
+        return self.selected_recovery.map(|idx| return self.recovery[idx]);
+    }
+
+    /// Chooses the next recovery action, going back to none after the last.
+    /// Returns false when there are none to choose from.
+    pub fn select_next_recovery(&mut self) -> bool {
+        if self.recovery.is_empty() {
+            return false;
+        }
+
+        self.selected_recovery = match self.selected_recovery {
+            None => Some(0),
+            Some(idx) if idx + 1 < self.recovery.len() => Some(idx + 1),
+            Some(_) => None,
+        };
+        return true;
+    }
+
+    pub fn archived(&self) -> Option<ArchivedText> {
+        return self.archived;
+    }
+
+    pub fn set_typed(&mut self) {
+        self.typed = true;
+    }
+
+    pub fn is_typed(&self) -> bool {
+        return self.typed;
+    }
+
+    pub fn is_evicted(&self) -> bool {
+        return self.evicted;
+    }
+
+    /// Drops the text from memory, leaving only the `archived` copy.
+    pub fn evict(&mut self, archived: ArchivedText) {
+        self.archived = Some(archived);
+        self.evicted = true;
+        self.text = String::new();
+    }
+
+    /// Puts text read back from the archive in place. The archived copy is
+    /// kept, so the message can be evicted again without rewriting it.
+    pub fn restore(&mut self, text: String) {
+        self.evicted = false;
+        self.text = text;
+    }
+
+    /// Any count of its tokens is dropped, as it no longer matches the text.
     pub fn append(&mut self, text: &str) {
+        self.tokens = None;
         self.text += &text.replace('\t', "  ");
     }
 
-    pub fn codeblocks(&self) -> Vec<String> {
-        let mut codeblocks: Vec<String> = vec![];
-        let mut current_codeblock: Vec<&str> = vec![];
-        let mut in_codeblock = false;
-
-        for line in self.text.split('\n') {
-            let trimmed = line.trim();
-            if trimmed.starts_with("```") {
-                if in_codeblock {
-                    codeblocks.push(current_codeblock.join("\n"));
-                    current_codeblock = vec![];
-                    in_codeblock = false
-                } else {
-                    in_codeblock = true;
-                }
-                continue;
-            }
+    /// Joins a continuation that was appended at byte offset `seam`. When
+    /// the text was cut off inside a code block and the continuation opens
+    /// it again, the repeated fence is dropped so the block stays whole.
+    pub fn join_continuation(&mut self, seam: usize) {
+        let (before, after) = self.text.split_at(seam);
+        let mut parser = CodeBlockParser::default();
+        parser.push(&format!("{before}\n"));
+        let trimmed = after.trim_start();
+        if !parser.is_open() || !trimmed.starts_with("```") {
+            return;
+        }
+
+        let rest = trimmed.split_once('\n').map(|e| return e.1).unwrap_or("");
+        self.text = format!("{before}{rest}");
+    }
+
+    /// Overrides the language of the code block at `index`, where `index` is
+    /// the position of the block within this message.
+    pub fn set_codeblock_language(&mut self, index: usize, language: &str) {
+        self.codeblock_languages.insert(index, language.to_string());
+    }
+
+    /// Sets the language of code blocks the model didn't label, leaving
+    /// labelled blocks and `/lang` overrides as they are.
+    pub fn set_default_codeblock_language(&mut self, language: &str) {
+        if language.is_empty() {
+            return;
+        }
 
-            if in_codeblock {
-                current_codeblock.push(line);
-            }
+        let untagged = self
+            .codeblocks()
+            .iter()
+            .enumerate()
+            .filter(|(_, e)| return e.language.is_empty())
+            .map(|(idx, _)| return idx)
+            .collect::<Vec<usize>>();
+        for idx in untagged {
+            self.set_codeblock_language(idx, language);
         }
+    }
 
-        return codeblocks;
+    /// Returns the language override for the code block at `index`, if one
+    /// was set.
+    pub fn codeblock_language(&self, index: usize) -> Option<&str> {
+        return self
+            .codeblock_languages
+            .get(&index)
+            .map(|e| return e.as_str());
+    }
+
+    pub fn codeblocks(&self) -> Vec<CodeBlock> {
+        let mut parser = CodeBlockParser::default();
+        parser.push(&self.text);
+
+        return parser
+            .finish()
+            .into_iter()
+            .enumerate()
+            .map(|(idx, mut codeblock)| {
+                if let Some(language) = self.codeblock_language(idx) {
+                    codeblock.language = language.to_string();
+                }
+                return codeblock;
+            })
+            .collect();
     }
 }
@@ -3,6 +3,7 @@ use test_utils::codeblock_fixture;
 use super::Author;
 use super::Message;
 use super::MessageType;
+use crate::domain::models::RecoveryAction;
 
 #[test]
 fn it_executes_new() {
@@ -22,6 +23,14 @@ fn it_executes_new_replacing_tabs() {
     assert_eq!(msg.mtype, MessageType::Normal);
 }
 
+#[test]
+fn it_uses_username_as_author_name() {
+    let mut msg = Message::new(Author::Oatmeal, "Hi there!");
+    msg.set_username("alice");
+    assert_eq!(msg.author_name(), "alice");
+    assert_eq!(msg.username(), Some("alice"));
+}
+
 #[test]
 fn it_executes_new_with_type() {
     let msg = Message::new_with_type(Author::Oatmeal, MessageType::Error, "It broke!");
@@ -66,7 +75,7 @@ fn it_executes_codeblocks() {
     let codeblocks = msg.codeblocks();
 
     assert_eq!(codeblocks.len(), 4);
-    insta::assert_snapshot!(codeblocks[0], @r###"
+    insta::assert_snapshot!(codeblocks[0].code, @r###"
     fn print_numbers() {
         for i in 0..=0 {
             println!("{i}");
@@ -74,7 +83,7 @@ fn it_executes_codeblocks() {
     }
     "###);
 
-    insta::assert_snapshot!(codeblocks[1], @r###"
+    insta::assert_snapshot!(codeblocks[1].code, @r###"
     // Hello World.
 
     // This is a really long line that pushes the boundaries of 50 characters across the screen, resulting in a code comment block where the line is wrapped to the next line. Cool right?
@@ -87,10 +96,151 @@ fn it_executes_codeblocks() {
     }
     "###);
 
-    insta::assert_snapshot!(codeblocks[2], @"abc123");
+    insta::assert_snapshot!(codeblocks[2].code, @"abc123");
 
-    insta::assert_snapshot!(codeblocks[3], @r###"
+    insta::assert_snapshot!(codeblocks[3].code, @r###"
     for i in range(11):
         print(i)
     "###);
 }
+
+#[test]
+fn it_executes_codeblocks_with_languages() {
+    let msg = Message::new(Author::Oatmeal, codeblock_fixture());
+    let languages = msg
+        .codeblocks()
+        .into_iter()
+        .map(|e| return e.language)
+        .collect::<Vec<String>>();
+
+    assert_eq!(languages, vec!["rust", "javascript", "", "python"]);
+}
+
+#[test]
+fn it_overrides_codeblock_language() {
+    let mut msg = Message::new(Author::Oatmeal, codeblock_fixture());
+    msg.set_codeblock_language(2, "text");
+
+    assert_eq!(msg.codeblock_language(2), Some("text"));
+    assert_eq!(msg.codeblock_language(1), None);
+    assert_eq!(msg.codeblocks()[2].language, "text");
+}
+
+#[test]
+fn it_sets_the_language_of_unlabelled_codeblocks() {
+    let mut msg = Message::new(
+        Author::Model,
+        "```\nfn main() {}\n```\n\n```toml\n[package]\n```\n\n```\nlet x = 1;\n```",
+    );
+    msg.set_codeblock_language(2, "text");
+    msg.set_default_codeblock_language("rust");
+
+    let languages = msg
+        .codeblocks()
+        .into_iter()
+        .map(|e| return e.language)
+        .collect::<Vec<String>>();
+    assert_eq!(languages, vec!["rust", "toml", "text"]);
+}
+
+#[test]
+fn it_infers_codeblock_paths() {
+    let msg = Message::new(
+        Author::Model,
+        r#"
+```rust title=src/main.rs
+fn main() {}
+```
+
+In `src/lib.rs`:
+
+```rust
+pub fn lib() {}
+```
+
+Then run it.
+
+```sh
+cargo run
+```
+"#,
+    );
+    let paths = msg
+        .codeblocks()
+        .into_iter()
+        .map(|e| return e.path)
+        .collect::<Vec<Option<String>>>();
+
+    assert_eq!(
+        paths,
+        vec![
+            Some("src/main.rs".to_string()),
+            Some("src/lib.rs".to_string()),
+            None
+        ]
+    );
+}
+
+#[test]
+fn it_joins_continuations_inside_code_blocks() {
+    let mut msg = Message::new(Author::Model, "Here:\n\n```rust\nfn main() {\n");
+    let seam = msg.text.len();
+    msg.append("\n```rust\n    println!(\"hi\");\n}\n```");
+    msg.join_continuation(seam);
+
+    assert_eq!(
+        msg.text,
+        "Here:\n\n```rust\nfn main() {\n    println!(\"hi\");\n}\n```"
+    );
+    assert_eq!(msg.codeblocks().len(), 1);
+}
+
+#[test]
+fn it_keeps_continuations_outside_code_blocks() {
+    let mut msg = Message::new(Author::Model, "```sh\nls\n```\nThen ");
+    let seam = msg.text.len();
+    msg.append("run:\n```sh\npwd\n```");
+    msg.join_continuation(seam);
+
+    assert_eq!(msg.text, "```sh\nls\n```\nThen run:\n```sh\npwd\n```");
+}
+
+#[test]
+fn it_includes_seed_in_title() {
+    let mut msg = Message::new(Author::Oatmeal, "Hi there!");
+    assert_eq!(msg.title(), "Oatmeal");
+
+    msg.set_seed(Some(42));
+    assert_eq!(msg.title(), "Oatmeal (seed 42)");
+
+    let json = serde_json::to_string(&msg).unwrap();
+    let msg = serde_json::from_str::<Message>(&json).unwrap();
+    assert_eq!(msg.title(), "Oatmeal (seed 42)");
+}
+
+#[test]
+fn it_cycles_recovery_actions() {
+    let mut msg = Message::new_with_type(Author::Oatmeal, MessageType::Error, "Oh no");
+    assert!(!msg.select_next_recovery());
+
+    msg.set_recovery(vec![RecoveryAction::Retry, RecoveryAction::OpenConfig]);
+    assert_eq!(msg.selected_recovery(), None);
+
+    assert!(msg.select_next_recovery());
+    assert_eq!(msg.selected_recovery(), Some(RecoveryAction::Retry));
+    assert!(msg.select_next_recovery());
+    assert_eq!(msg.selected_recovery(), Some(RecoveryAction::OpenConfig));
+    assert!(msg.select_next_recovery());
+    assert_eq!(msg.selected_recovery(), None);
+}
+
+#[test]
+fn it_estimates_tokens() {
+    assert_eq!(Message::new(Author::User, "abcdefgh").tokens(), 2);
+    assert_eq!(Message::new(Author::Model, "abcde").tokens(), 2);
+    assert_eq!(Message::new(Author::Oatmeal, "Hi there!").tokens(), 0);
+
+    let mut msg = Message::new(Author::Oatmeal, "Attached ticket ENG-123.");
+    msg.set_tokens(500);
+    assert_eq!(msg.tokens(), 500);
+}
@@ -1,21 +1,111 @@
 mod action;
+mod analytics;
+mod animation;
+mod ascii_diagram;
+mod attached_context;
+mod audit;
 mod author;
 mod backend;
+mod backend_health;
+mod bridge;
+mod checkpoint;
+mod code_block;
+mod color_mode;
+mod comparison;
+mod context_documents;
+mod context_overflow;
+mod diagram;
 mod editor;
+mod error;
+mod eval;
 mod event;
+mod experiment;
+mod fallback;
+mod few_shot;
+mod generation_progress;
+mod image_attachment;
+mod input_lint;
 mod loading;
+mod locale;
+mod macros;
 mod message;
+mod model_list;
+mod model_tool;
+mod pasted_code;
+mod permissions;
+mod polish;
+mod prompt_variables;
+mod quit_prompt;
+mod review_comment;
+mod rewrite_rule;
+mod sampling;
 mod session;
+mod session_diff;
+mod share;
+mod similarity;
 mod slash_commands;
+mod suggestions;
+mod system_prompt;
 mod textarea;
+mod ticket;
+mod todos;
+mod tokenizer;
+mod tool_approval;
+mod translation;
+mod webhook;
 
 pub use action::*;
+pub use analytics::*;
+pub use animation::*;
+pub use ascii_diagram::*;
+pub use attached_context::*;
+pub use audit::*;
 pub use author::*;
 pub use backend::*;
+pub use backend_health::*;
+pub use bridge::*;
+pub use checkpoint::*;
+pub use code_block::*;
+pub use color_mode::*;
+pub use comparison::*;
+pub use context_documents::*;
+pub use context_overflow::*;
+pub use diagram::*;
 pub use editor::*;
+pub use error::*;
+pub use eval::*;
 pub use event::*;
+pub use experiment::*;
+pub use fallback::*;
+pub use few_shot::*;
+pub use generation_progress::*;
+pub use image_attachment::*;
+pub use input_lint::*;
 pub use loading::*;
+pub use locale::*;
+pub use macros::*;
 pub use message::*;
+pub use model_list::*;
+pub use model_tool::*;
+pub use pasted_code::*;
+pub use permissions::*;
+pub use polish::*;
+pub use prompt_variables::*;
+pub use quit_prompt::*;
+pub use review_comment::*;
+pub use rewrite_rule::*;
+pub use sampling::*;
 pub use session::*;
+pub use session_diff::*;
+pub use share::*;
+pub use similarity::*;
 pub use slash_commands::*;
+pub use suggestions::*;
+pub use system_prompt::*;
 pub use textarea::*;
+pub use ticket::*;
+pub use todos::*;
+pub use tokenizer::*;
+pub use tool_approval::*;
+pub use translation::*;
+pub use webhook::*;
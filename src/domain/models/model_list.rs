@@ -0,0 +1,80 @@
+#[cfg(test)]
+#[path = "model_list_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+
+/// Models shown per page of `/modellist`, as catalogs like OpenRouter's run in
+/// to the hundreds.
+pub const MODEL_LIST_PAGE_SIZE: usize = 50;
+
+/// Models a backend serves, numbered the way `/model` accepts them.
+pub struct ModelList {
+    models: Vec<String>,
+}
+
+impl ModelList {
+    pub fn new(mut models: Vec<String>) -> ModelList {
+        models.sort();
+        return ModelList { models };
+    }
+
+    /// Formats a page of the models with names containing `filter`. Numbers
+    /// are kept as-is when filtering so they still work with `/model`.
+    pub fn render(&self, filter: &str, page: usize) -> Result<String> {
+        let filter = filter.to_lowercase();
+        let matches = self
+            .models
+            .iter()
+            .enumerate()
+            .filter(|(_, model)| return model.to_lowercase().contains(&filter))
+            .collect::<Vec<(usize, &String)>>();
+
+        if matches.is_empty() {
+            if filter.is_empty() {
+                return Ok("The backend doesn't have any models.".to_string());
+            }
+            return Ok(format!("No models match `{filter}`."));
+        }
+
+        let pages = matches.len().div_ceil(MODEL_LIST_PAGE_SIZE);
+        if page == 0 || page > pages {
+            bail!(format!(
+                "There's no page {page} of models, the last page is {pages}."
+            ));
+        }
+
+        let start = (page - 1) * MODEL_LIST_PAGE_SIZE;
+        let end = (start + MODEL_LIST_PAGE_SIZE).min(matches.len());
+        let mut res = matches[start..end]
+            .iter()
+            .map(|(idx, model)| {
+                let n = idx + 1;
+                return format!("- ({n}) {model}");
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        if pages > 1 {
+            res = format!(
+                "{res}\n\nPage {page} of {pages}, showing {} to {end} of {} models.",
+                start + 1,
+                matches.len()
+            );
+            if page < pages && filter.is_empty() {
+                res = format!(
+                    "{res} Run `/modellist --page {}` for more, or narrow them down with `--filter TEXT`.",
+                    page + 1
+                );
+            } else if page < pages {
+                res = format!(
+                    "{res} Run `/modellist --filter \"{filter}\" --page {}` for more.",
+                    page + 1
+                );
+            }
+        }
+
+        return Ok(res);
+    }
+}
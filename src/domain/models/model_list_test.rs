@@ -0,0 +1,67 @@
+use super::ModelList;
+use super::MODEL_LIST_PAGE_SIZE;
+
+fn catalog() -> ModelList {
+    let models = (1..=120)
+        .map(|e| {
+            let vendor = if e % 2 == 0 { "openai" } else { "meta-llama" };
+            return format!("{vendor}/model-{e:03}");
+        })
+        .collect::<Vec<String>>();
+
+    return ModelList::new(models);
+}
+
+#[test]
+fn it_lists_short_lists_on_one_page() {
+    let models = ModelList::new(vec!["b".to_string(), "a".to_string()]);
+
+    insta::assert_snapshot!(models.render("", 1).unwrap(), @r###"
+    - (1) a
+    - (2) b
+    "###);
+}
+
+#[test]
+fn it_pages_long_lists() {
+    let res = catalog().render("", 1).unwrap();
+    let lines = res.lines().collect::<Vec<&str>>();
+
+    assert_eq!(lines[0], "- (1) meta-llama/model-001");
+    assert_eq!(
+        lines[MODEL_LIST_PAGE_SIZE - 1],
+        "- (50) meta-llama/model-099"
+    );
+    assert_eq!(
+        lines.last().unwrap(),
+        &"Page 1 of 3, showing 1 to 50 of 120 models. Run `/modellist --page 2` for more, or narrow them down with `--filter TEXT`."
+    );
+
+    let res = catalog().render("", 3).unwrap();
+    assert_eq!(res.lines().count(), 22);
+    assert!(res.ends_with("Page 3 of 3, showing 101 to 120 of 120 models."));
+}
+
+#[test]
+fn it_keeps_indexes_when_filtering() {
+    let res = catalog().render("OpenAI", 2).unwrap();
+    let lines = res.lines().collect::<Vec<&str>>();
+
+    assert_eq!(lines[0], "- (111) openai/model-102");
+    assert_eq!(
+        lines.last().unwrap(),
+        &"Page 2 of 2, showing 51 to 60 of 60 models."
+    );
+}
+
+#[test]
+fn it_reports_missing_pages_and_models() {
+    assert_eq!(
+        catalog().render("", 4).unwrap_err().to_string(),
+        "There's no page 4 of models, the last page is 3."
+    );
+    assert_eq!(
+        catalog().render("mistral", 1).unwrap(),
+        "No models match `mistral`."
+    );
+}
@@ -0,0 +1,178 @@
+#[cfg(test)]
+#[path = "model_tool_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use strum::EnumIter;
+use strum::IntoEnumIterator;
+
+use super::ConfigError;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// Most characters of a tool's output sent back to the model.
+const TOOL_OUTPUT_MAX_CHARS: usize = 16000;
+
+/// A tool the model can ask to call, from `model-tools`. Every call waits for
+/// the user to approve it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum ModelTool {
+    Shell,
+    ReadFile,
+}
+
+impl ModelTool {
+    pub fn parse(text: &str) -> Option<ModelTool> {
+        return ModelTool::iter().find(|e| return e.to_string() == text);
+    }
+
+    /// Parses comma separated tool names.
+    pub fn parse_all(text: &str) -> Result<Vec<ModelTool>> {
+        let mut tools = vec![];
+        for name in text
+            .split(',')
+            .map(|e| return e.trim())
+            .filter(|e| return !e.is_empty())
+        {
+            match ModelTool::parse(name) {
+                Some(tool) if !tools.contains(&tool) => tools.push(tool),
+                Some(_) => {}
+                None => {
+                    bail!(ConfigError::InvalidValue {
+                        key: ConfigKey::ModelTools.to_string(),
+                        value: name.to_string(),
+                        possible_values: ModelTool::iter().map(|e| return e.to_string()).collect(),
+                    });
+                }
+            }
+        }
+
+        return Ok(tools);
+    }
+
+    pub fn from_config() -> Result<Vec<ModelTool>> {
+        return ModelTool::parse_all(&Config::get(ConfigKey::ModelTools));
+    }
+
+    pub fn description(&self) -> &'static str {
+        return match self {
+            ModelTool::Shell => {
+                "Runs a shell command in the user's project directory, returning its output."
+            }
+            ModelTool::ReadFile => "Reads a file from the user's project, returning its contents.",
+        };
+    }
+
+    /// The one argument the tool takes.
+    fn argument_name(&self) -> &'static str {
+        return match self {
+            ModelTool::Shell => "command",
+            ModelTool::ReadFile => "path",
+        };
+    }
+
+    /// JSON schema of the arguments.
+    pub fn parameters(&self) -> serde_json::Value {
+        let description = match self {
+            ModelTool::Shell => "The command to run.",
+            ModelTool::ReadFile => "Path of the file, relative to the project directory.",
+        };
+
+        return serde_json::json!({
+            "type": "object",
+            "properties": {
+                self.argument_name(): {
+                    "type": "string",
+                    "description": description,
+                },
+            },
+            "required": [self.argument_name()],
+        });
+    }
+
+    /// Tool in `tool-permissions` that decides whether it may run.
+    pub fn permission_tool(&self) -> &'static str {
+        return match self {
+            ModelTool::Shell => "run",
+            ModelTool::ReadFile => "context",
+        };
+    }
+}
+
+/// A call to a tool the model asked for, with its arguments as JSON.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolInvocation {
+    /// Given by the backend, to match the output with the call.
+    pub id: String,
+    pub name: String,
+    pub arguments: String,
+}
+
+impl ToolInvocation {
+    pub fn new(id: &str, name: &str, arguments: &str) -> ToolInvocation {
+        return ToolInvocation {
+            id: id.to_string(),
+            name: name.to_string(),
+            arguments: arguments.to_string(),
+        };
+    }
+
+    /// `None` when the model asked for a tool that doesn't exist.
+    pub fn tool(&self) -> Option<ModelTool> {
+        return ModelTool::parse(&self.name);
+    }
+
+    /// The tool's argument, such as the command for `shell`. `None` when
+    /// it's missing, or the arguments aren't valid JSON.
+    pub fn argument(&self) -> Option<String> {
+        let tool = self.tool()?;
+        let arguments = serde_json::from_str::<serde_json::Value>(&self.arguments).ok()?;
+        return arguments
+            .get(tool.argument_name())
+            .and_then(|e| return e.as_str())
+            .map(|e| return e.to_string());
+    }
+
+    /// What the call would do, for the user to approve.
+    pub fn summary(&self) -> String {
+        return match (self.tool(), self.argument()) {
+            (Some(ModelTool::Shell), Some(command)) => format!("Run `{command}`"),
+            (Some(ModelTool::ReadFile), Some(path)) => format!("Read `{path}`"),
+            _ => format!("Call `{}` with {}", self.name, self.arguments),
+        };
+    }
+}
+
+/// What a tool call returned, to send back to the model.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ToolOutput {
+    /// Of the call it answers.
+    pub id: String,
+    pub name: String,
+    pub output: String,
+}
+
+impl ToolOutput {
+    /// Cuts the output off at `TOOL_OUTPUT_MAX_CHARS`, saying so.
+    pub fn new(invocation: &ToolInvocation, output: &str) -> ToolOutput {
+        let mut output = output.to_string();
+        if let Some((idx, _)) = output.char_indices().nth(TOOL_OUTPUT_MAX_CHARS) {
+            output.truncate(idx);
+            output += &format!("\n\n[Output cut off after {TOOL_OUTPUT_MAX_CHARS} characters]");
+        }
+
+        return ToolOutput {
+            id: invocation.id.to_string(),
+            name: invocation.name.to_string(),
+            output,
+        };
+    }
+
+    pub fn declined(invocation: &ToolInvocation) -> ToolOutput {
+        return ToolOutput::new(invocation, "The user declined to run this tool call.");
+    }
+}
@@ -0,0 +1,59 @@
+use super::ModelTool;
+use super::ToolInvocation;
+use super::ToolOutput;
+use crate::domain::models::ConfigError;
+
+#[test]
+fn it_parses_tools() {
+    assert_eq!(
+        ModelTool::parse_all("shell, read_file,shell,").unwrap(),
+        vec![ModelTool::Shell, ModelTool::ReadFile]
+    );
+    assert_eq!(ModelTool::parse_all("").unwrap(), vec![]);
+
+    let err = ModelTool::parse_all("shell,browser").unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<ConfigError>(),
+        Some(ConfigError::InvalidValue { .. })
+    ));
+}
+
+#[test]
+fn it_describes_the_arguments() {
+    assert_eq!(
+        ModelTool::ReadFile.parameters()["required"],
+        serde_json::json!(["path"])
+    );
+}
+
+#[test]
+fn it_summarizes_invocations() {
+    let shell = ToolInvocation::new("call_1", "shell", r#"{"command":"ls -la"}"#);
+    assert_eq!(shell.argument(), Some("ls -la".to_string()));
+    assert_eq!(shell.summary(), "Run `ls -la`");
+
+    let read_file = ToolInvocation::new("call_2", "read_file", r#"{"path":"src/main.rs"}"#);
+    assert_eq!(read_file.summary(), "Read `src/main.rs`");
+
+    let invalid = ToolInvocation::new("call_3", "read_file", "{\"pa");
+    assert_eq!(invalid.argument(), None);
+    assert_eq!(invalid.summary(), "Call `read_file` with {\"pa");
+
+    let unknown = ToolInvocation::new("call_4", "browse", "{}");
+    assert_eq!(unknown.tool(), None);
+    assert_eq!(unknown.summary(), "Call `browse` with {}");
+}
+
+#[test]
+fn it_cuts_off_long_output() {
+    let invocation = ToolInvocation::new("call_1", "shell", "{}");
+
+    let output = ToolOutput::new(&invocation, &"a".repeat(20000));
+    assert_eq!(output.id, "call_1");
+    assert!(output.output.starts_with(&"a".repeat(16000)));
+    assert!(output
+        .output
+        .ends_with("\n\n[Output cut off after 16000 characters]"));
+
+    assert_eq!(ToolOutput::new(&invocation, "short").output, "short");
+}
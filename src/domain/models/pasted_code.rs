@@ -0,0 +1,293 @@
+#[cfg(test)]
+#[path = "pasted_code_test.rs"]
+mod tests;
+
+/// Fewest lines of code in a row that are fenced, so a prompt that mentions a
+/// single statement isn't mistaken for pasted code.
+const MIN_CODE_LINES: usize = 3;
+
+/// Hints that code is in a language. Keywords are matched against the first
+/// word of each line, and patterns anywhere in it.
+struct LanguageHints {
+    language: &'static str,
+    keywords: &'static [&'static str],
+    patterns: &'static [&'static str],
+}
+
+/// Ties go to whichever language is listed first.
+const LANGUAGES: &[LanguageHints] = &[
+    LanguageHints {
+        language: "rust",
+        keywords: &[
+            "fn", "impl", "pub", "mod", "struct", "enum", "trait", "use", "let",
+        ],
+        patterns: &[
+            "let mut ",
+            "&mut ",
+            "println!",
+            "#[derive",
+            "Vec<",
+            "Option<",
+            ".unwrap()",
+            "::new(",
+        ],
+    },
+    LanguageHints {
+        language: "typescript",
+        keywords: &["interface", "type", "export", "import"],
+        patterns: &[": string", ": number", ": boolean", "<T>", "readonly "],
+    },
+    LanguageHints {
+        language: "javascript",
+        keywords: &["function", "const", "let", "var", "export", "import"],
+        patterns: &[
+            "=> ",
+            "===",
+            "console.log",
+            "require(",
+            "document.",
+            "async ",
+        ],
+    },
+    LanguageHints {
+        language: "python",
+        keywords: &[
+            "def", "import", "from", "elif", "class", "with", "except", "try:", "print",
+        ],
+        patterns: &[
+            "self.", "__init__", "None", "True", "False", "lambda ", "):",
+        ],
+    },
+    LanguageHints {
+        language: "go",
+        keywords: &["func", "package", "import", "defer", "go"],
+        patterns: &[":= ", "fmt.", "err != nil", "chan "],
+    },
+    LanguageHints {
+        language: "java",
+        keywords: &["public", "private", "protected", "import", "package"],
+        patterns: &[
+            "public class ",
+            "static void",
+            "System.out",
+            "@Override",
+            "String[]",
+        ],
+    },
+    LanguageHints {
+        language: "cpp",
+        keywords: &["#include", "template", "namespace", "using"],
+        patterns: &["std::", "cout <<", "nullptr", "<iostream>"],
+    },
+    LanguageHints {
+        language: "c",
+        keywords: &["#include", "#define", "int", "void", "struct", "typedef"],
+        patterns: &["printf(", "malloc(", "int main(", "NULL"],
+    },
+    LanguageHints {
+        language: "ruby",
+        keywords: &["def", "end", "require", "puts", "elsif", "module", "class"],
+        patterns: &[" do |", "attr_accessor", ".each"],
+    },
+    LanguageHints {
+        language: "bash",
+        keywords: &["echo", "fi", "then", "done", "export", "if", "for", "cd"],
+        patterns: &["$(", "${", " && ", " | ", "; then", "; do"],
+    },
+    LanguageHints {
+        language: "sql",
+        keywords: &[
+            "SELECT", "INSERT", "UPDATE", "DELETE", "CREATE", "FROM", "WHERE",
+        ],
+        patterns: &[" FROM ", " WHERE ", " JOIN ", "INTO "],
+    },
+    LanguageHints {
+        language: "html",
+        keywords: &[],
+        patterns: &["<div", "</", "<html", "<body", "class=\"", "<script"],
+    },
+];
+
+/// Keywords that start a line of code in any language, without one of the
+/// trailing punctuation marks of code.
+const CODE_KEYWORDS: &[&str] = &[
+    "fn",
+    "impl",
+    "pub",
+    "mod",
+    "struct",
+    "enum",
+    "trait",
+    "use",
+    "let",
+    "const",
+    "var",
+    "function",
+    "interface",
+    "type",
+    "export",
+    "import",
+    "from",
+    "def",
+    "class",
+    "return",
+    "if",
+    "elif",
+    "else",
+    "for",
+    "while",
+    "try",
+    "except",
+    "finally",
+    "with",
+    "func",
+    "package",
+    "defer",
+    "public",
+    "private",
+    "protected",
+    "#include",
+    "#define",
+    "template",
+    "namespace",
+    "end",
+    "require",
+    "puts",
+    "print",
+    "echo",
+    "fi",
+    "then",
+    "done",
+    "SELECT",
+    "INSERT",
+    "UPDATE",
+    "DELETE",
+    "CREATE",
+    "FROM",
+    "WHERE",
+];
+
+fn first_word(line: &str) -> &str {
+    let line = line.trim_start();
+    let end = line
+        .find(|e: char| return !(e.is_alphanumeric() || e == '_' || e == '#'))
+        .unwrap_or(line.len());
+    return &line[..end];
+}
+
+fn is_code_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return false;
+    }
+    if line.starts_with([' ', '\t']) {
+        return true;
+    }
+    if trimmed.ends_with(['{', '}', ';', '(', '[', ',', ')', ']']) {
+        return true;
+    }
+    if trimmed.starts_with(['}', '<', '@']) || trimmed.starts_with("//") {
+        return true;
+    }
+    if [" = ", "==", "=>", "->", "::"]
+        .iter()
+        .any(|e| return trimmed.contains(e))
+    {
+        return true;
+    }
+
+    return CODE_KEYWORDS.contains(&first_word(trimmed)) && !trimmed.ends_with(['.', '?', '!']);
+}
+
+/// Guesses the language of code from the keywords and punctuation it uses,
+/// favouring a shebang when there is one.
+pub fn detect_language(code: &str) -> Option<&'static str> {
+    let first_line = code.trim_start().lines().next().unwrap_or_default();
+    if let Some(interpreter) = first_line.strip_prefix("#!") {
+        for (name, language) in [
+            ("python", "python"),
+            ("node", "javascript"),
+            ("ruby", "ruby"),
+            ("bash", "bash"),
+            ("sh", "bash"),
+        ] {
+            if interpreter.contains(name) {
+                return Some(language);
+            }
+        }
+    }
+
+    let trimmed = code.trim();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return Some("json");
+    }
+
+    let mut best: Option<(&'static str, usize)> = None;
+    for hints in LANGUAGES {
+        let score = code
+            .lines()
+            .map(|line| {
+                let keyword = hints.keywords.contains(&first_word(line)) as usize;
+                let patterns = hints
+                    .patterns
+                    .iter()
+                    .filter(|e| return line.contains(*e))
+                    .count();
+                return keyword + patterns;
+            })
+            .sum::<usize>();
+        if score > best.map(|e| return e.1).unwrap_or(0) {
+            best = Some((hints.language, score));
+        }
+    }
+
+    return best.map(|e| return e.0);
+}
+
+/// Wraps code pasted in to a prompt without fences in a fenced code block of
+/// the language it looks like, so the model and the chat both treat it as
+/// code. Prompts that already have a fence are left as they are.
+pub fn fence_pasted_code(text: &str) -> String {
+    if text.contains("```") {
+        return text.to_string();
+    }
+
+    let lines = text.lines().collect::<Vec<&str>>();
+    let mut res: Vec<String> = vec![];
+    let mut idx = 0;
+    while idx < lines.len() {
+        if !is_code_line(lines[idx]) {
+            res.push(lines[idx].to_string());
+            idx += 1;
+            continue;
+        }
+
+        // Blank lines belong to the run of code only if more code follows.
+        let start = idx;
+        let mut end = idx + 1;
+        let mut next = idx + 1;
+        while next < lines.len() {
+            if is_code_line(lines[next]) {
+                end = next + 1;
+            } else if !lines[next].trim().is_empty() {
+                break;
+            }
+            next += 1;
+        }
+
+        let code = &lines[start..end];
+        if code.iter().filter(|e| return !e.trim().is_empty()).count() < MIN_CODE_LINES {
+            res.extend(code.iter().map(|e| return e.to_string()));
+        } else {
+            let code = code.join("\n");
+            res.push(format!("```{}", detect_language(&code).unwrap_or_default()));
+            res.push(code);
+            res.push("```".to_string());
+        }
+        idx = end;
+    }
+
+    return res.join("\n");
+}
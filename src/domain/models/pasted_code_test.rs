@@ -0,0 +1,68 @@
+use super::detect_language;
+use super::fence_pasted_code;
+
+#[test]
+fn it_detects_languages() {
+    let samples = [
+        (
+            "rust",
+            "use std::io;\n\nfn main() {\n    let mut input = String::new();\n    println!(\"{input}\");\n}",
+        ),
+        (
+            "python",
+            "import os\n\ndef main():\n    if os.path.exists(\"a\"):\n        print(\"yes\")",
+        ),
+        (
+            "javascript",
+            "const fs = require(\"fs\");\nfunction read() {\n  console.log(fs.readFileSync(\"a\"));\n}",
+        ),
+        (
+            "typescript",
+            "interface User {\n  name: string;\n  age: number;\n}",
+        ),
+        (
+            "go",
+            "package main\n\nfunc main() {\n\tx, err := run()\n\tif err != nil {\n\t\tfmt.Println(x)\n\t}\n}",
+        ),
+        ("bash", "#!/usr/bin/env bash\nset -e\nls"),
+        ("json", "{\n  \"name\": \"oatmeal\",\n  \"version\": 1\n}"),
+        ("sql", "SELECT name\nFROM users\nWHERE id = 1;"),
+    ];
+
+    for (language, code) in samples {
+        assert_eq!(detect_language(code), Some(language), "{code}");
+    }
+    assert_eq!(detect_language("hello world"), None);
+}
+
+#[test]
+fn it_fences_pasted_code() {
+    let prompt = "Why does this panic?\n\nfn main() {\n    let v: Vec<i32> = vec![];\n    v[0];\n}\n\nIt's on stable.";
+
+    insta::assert_snapshot!(fence_pasted_code(prompt), @r###"
+    Why does this panic?
+
+    ```rust
+    fn main() {
+        let v: Vec<i32> = vec![];
+        v[0];
+    }
+    ```
+
+    It's on stable.
+    "###);
+}
+
+#[test]
+fn it_leaves_prose_and_fenced_code_alone() {
+    let prompts = [
+        "Write a haiku about oatmeal.\nMake it rhyme.\nAnd keep it short.",
+        "What does `let x = 5;` do?",
+        "Explain this:\n```\nfn main() {\n    todo!();\n}\n```",
+        "fn a() {}\nfn b() {}",
+    ];
+
+    for prompt in prompts {
+        assert_eq!(fence_pasted_code(prompt), prompt);
+    }
+}
@@ -0,0 +1,191 @@
+#[cfg(test)]
+#[path = "permissions_test.rs"]
+mod tests;
+
+use std::collections::HashMap;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use anyhow::Result;
+use strum::EnumIter;
+use strum::IntoEnumIterator;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// Commands that run programs, reach the network, or write files, and can be
+/// restricted with `tool-permissions`. `env` covers prompt placeholders that
+/// read environment variables.
+pub const TOOLS: [&str; 13] = [
+    "apply", "check", "context", "docs", "env", "gh", "man", "post", "render", "review", "run",
+    "test", "ticket",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum Permission {
+    Allow,
+    Ask,
+    Deny,
+}
+
+impl Permission {
+    pub fn parse(text: &str) -> Option<Permission> {
+        return Permission::iter().find(|e| return e.to_string() == text);
+    }
+}
+
+fn split_list(text: &str) -> Vec<String> {
+    return text
+        .split(',')
+        .map(|e| return e.trim().to_string())
+        .filter(|e| return !e.is_empty())
+        .collect();
+}
+
+/// Resolves `.` and `..` without touching the filesystem, as files being
+/// written may not exist yet.
+fn normalize(path: &Path) -> PathBuf {
+    let mut res = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                res.pop();
+            }
+            Component::CurDir => {}
+            component => res.push(component),
+        }
+    }
+
+    return res;
+}
+
+/// What tools are allowed to do, from `tool-permissions`, `tool-paths`, and
+/// `tool-hosts`. Anything not configured is allowed.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ToolPolicy {
+    permissions: HashMap<String, Permission>,
+    /// Directories files may be written within. Empty allows anywhere.
+    pub paths: Vec<String>,
+    /// Hosts tools may connect to, where `*.example.com` matches any
+    /// subdomain. Empty allows any host.
+    pub hosts: Vec<String>,
+}
+
+impl ToolPolicy {
+    /// Parses permissions written as `TOOL=PERMISSION` pairs separated by
+    /// commas, such as `post=ask,review=deny,*=allow`, where `*` sets the
+    /// default for every other tool.
+    pub fn parse(permissions: &str, paths: &str, hosts: &str) -> Result<ToolPolicy> {
+        let mut policy = ToolPolicy {
+            permissions: HashMap::new(),
+            paths: split_list(paths),
+            hosts: split_list(hosts),
+        };
+
+        for entry in split_list(permissions) {
+            let (tool, permission) = entry.split_once('=').unwrap_or((&entry, ""));
+            let tool = tool.trim().trim_start_matches('/');
+            if tool != "*" && !TOOLS.contains(&tool) {
+                bail!(format!(
+                    "tool-permissions has an unknown tool '{tool}'\nPossible tools are: {}",
+                    TOOLS.join(", ")
+                ));
+            }
+
+            if let Some(permission) = Permission::parse(permission.trim()) {
+                policy.permissions.insert(tool.to_string(), permission);
+            } else {
+                bail!(format!(
+                    "tool-permissions has an invalid permission for '{tool}': {permission}\nPossible values are: allow, ask, deny"
+                ));
+            }
+        }
+
+        return Ok(policy);
+    }
+
+    pub fn from_config() -> Result<ToolPolicy> {
+        return ToolPolicy::parse(
+            &Config::get(ConfigKey::ToolPermissions),
+            &Config::get(ConfigKey::ToolPaths),
+            &Config::get(ConfigKey::ToolHosts),
+        );
+    }
+
+    pub fn permission(&self, tool: &str) -> Permission {
+        return self
+            .permissions
+            .get(tool)
+            .or(self.permissions.get("*"))
+            .copied()
+            .unwrap_or(Permission::Allow);
+    }
+
+    /// Returns true if a file may be written at `path`, resolved against
+    /// `cwd` when relative.
+    pub fn allows_path(&self, path: &Path, cwd: &Path) -> bool {
+        if self.paths.is_empty() {
+            return true;
+        }
+
+        let path = normalize(&cwd.join(path));
+        return self
+            .paths
+            .iter()
+            .any(|e| return path.starts_with(normalize(&cwd.join(e))));
+    }
+
+    pub fn allows_host(&self, host: &str) -> bool {
+        if self.hosts.is_empty() {
+            return true;
+        }
+
+        return self.hosts.iter().any(|e| {
+            if let Some(domain) = e.strip_prefix("*.") {
+                return host == domain || host.ends_with(&format!(".{domain}"));
+            }
+            return e == host;
+        });
+    }
+
+    /// Describes the policy for `/perms`.
+    pub fn format(&self) -> String {
+        let tools = TOOLS
+            .iter()
+            .map(|e| {
+                let name = if *e == "env" {
+                    "{{env:NAME}}".to_string()
+                } else {
+                    format!("/{e}")
+                };
+                return format!("- {name}: {}", self.permission(e));
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let paths = if self.paths.is_empty() {
+            "- Anywhere".to_string()
+        } else {
+            self.paths
+                .iter()
+                .map(|e| return format!("- {e}"))
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+
+        let hosts = if self.hosts.is_empty() {
+            "- Any host".to_string()
+        } else {
+            self.hosts
+                .iter()
+                .map(|e| return format!("- {e}"))
+                .collect::<Vec<String>>()
+                .join("\n")
+        };
+
+        return format!("Tools:\n{tools}\n\nFiles can be written to:\n{paths}\n\nTools can connect to:\n{hosts}\n\nChange these with `tool-permissions`, `tool-paths`, and `tool-hosts` in your config.");
+    }
+}
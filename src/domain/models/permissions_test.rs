@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use anyhow::Result;
+
+use super::Permission;
+use super::ToolPolicy;
+
+#[test]
+fn it_allows_everything_by_default() -> Result<()> {
+    let policy = ToolPolicy::parse("", "", "")?;
+    assert_eq!(policy.permission("post"), Permission::Allow);
+    assert!(policy.allows_path(Path::new("/etc/passwd"), Path::new("/home/me")));
+    assert!(policy.allows_host("example.com"));
+
+    return Ok(());
+}
+
+#[test]
+fn it_parses_permissions() -> Result<()> {
+    let policy = ToolPolicy::parse("post=ask, /review=deny,*=deny,test=allow", "", "")?;
+    assert_eq!(policy.permission("post"), Permission::Ask);
+    assert_eq!(policy.permission("review"), Permission::Deny);
+    assert_eq!(policy.permission("test"), Permission::Allow);
+    assert_eq!(policy.permission("gh"), Permission::Deny);
+
+    return Ok(());
+}
+
+#[test]
+fn it_rejects_invalid_permissions() {
+    insta::assert_snapshot!(ToolPolicy::parse("rm=deny", "", "").unwrap_err().to_string(), @r###"
+    tool-permissions has an unknown tool 'rm'
+    Possible tools are: apply, check, context, docs, env, gh, man, post, render, review, run, test, ticket
+    "###);
+
+    insta::assert_snapshot!(ToolPolicy::parse("post=maybe", "", "").unwrap_err().to_string(), @r###"
+    tool-permissions has an invalid permission for 'post': maybe
+    Possible values are: allow, ask, deny
+    "###);
+}
+
+#[test]
+fn it_restricts_paths() -> Result<()> {
+    let policy = ToolPolicy::parse("", "src, /tmp/out", "")?;
+    let cwd = Path::new("/home/me/project");
+    assert!(policy.allows_path(Path::new("src/main.rs"), cwd));
+    assert!(policy.allows_path(Path::new("/tmp/out/report.md"), cwd));
+    assert!(!policy.allows_path(Path::new("Cargo.toml"), cwd));
+    assert!(!policy.allows_path(Path::new("src/../../.bashrc"), cwd));
+
+    return Ok(());
+}
+
+#[test]
+fn it_restricts_hosts() -> Result<()> {
+    let policy = ToolPolicy::parse("", "", "api.github.com,*.atlassian.net")?;
+    assert!(policy.allows_host("api.github.com"));
+    assert!(policy.allows_host("example.atlassian.net"));
+    assert!(!policy.allows_host("github.com"));
+    assert!(!policy.allows_host("evilatlassian.net"));
+
+    return Ok(());
+}
+
+#[test]
+fn it_formats() -> Result<()> {
+    let policy = ToolPolicy::parse("post=ask,review=deny", "src", "")?;
+    insta::assert_snapshot!(policy.format(), @r###"
+    Tools:
+    - /apply: allow
+    - /check: allow
+    - /context: allow
+    - /docs: allow
+    - {{env:NAME}}: allow
+    - /gh: allow
+    - /man: allow
+    - /post: ask
+    - /render: allow
+    - /review: deny
+    - /run: allow
+    - /test: allow
+    - /ticket: allow
+
+    Files can be written to:
+    - src
+
+    Tools can connect to:
+    - Any host
+
+    Change these with `tool-permissions`, `tool-paths`, and `tool-hosts` in your config.
+    "###);
+
+    return Ok(());
+}
@@ -0,0 +1,60 @@
+#[cfg(test)]
+#[path = "polish_test.rs"]
+mod tests;
+
+use strum::EnumIter;
+use strum::EnumVariantNames;
+use strum::IntoEnumIterator;
+
+/// How `/polish` rewrites a draft.
+#[derive(Clone, Debug, Default, PartialEq, Eq, EnumIter, EnumVariantNames, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum PolishMode {
+    #[default]
+    Grammar,
+    Terse,
+    Formal,
+}
+
+impl PolishMode {
+    pub fn parse(text: &str) -> Option<PolishMode> {
+        return PolishMode::iter().find(|e| return e.to_string() == text);
+    }
+
+    /// Splits the text following `/polish` in to the mode and the draft. The
+    /// draft is kept as typed, including quotes and new lines.
+    pub fn split(text: &str) -> (PolishMode, String) {
+        let text = text.trim_start();
+        let rest = text
+            .split_once(char::is_whitespace)
+            .map(|e| return e.1)
+            .unwrap_or("")
+            .trim_start();
+
+        if let Some((first, draft)) = rest.split_once(char::is_whitespace) {
+            if let Some(mode) = PolishMode::parse(first) {
+                return (mode, draft.trim().to_string());
+            }
+        } else if let Some(mode) = PolishMode::parse(rest) {
+            return (mode, "".to_string());
+        }
+
+        return (PolishMode::default(), rest.trim().to_string());
+    }
+
+    pub fn prompt(&self, draft: &str) -> String {
+        let instruction = match self {
+            PolishMode::Grammar => {
+                "Fix the grammar and spelling of the following message without changing its meaning or tone."
+            }
+            PolishMode::Terse => {
+                "Rewrite the following message to be as short as possible while keeping its meaning."
+            }
+            PolishMode::Formal => "Rewrite the following message in a formal, professional tone.",
+        };
+
+        return format!(
+            "{instruction} Only reply with the rewritten message, without quotes or commentary.\n\n{draft}"
+        );
+    }
+}
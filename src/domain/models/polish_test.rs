@@ -0,0 +1,32 @@
+use super::PolishMode;
+
+#[test]
+fn it_splits_mode_and_draft() {
+    assert_eq!(
+        PolishMode::split("/polish terse I'd really like to know\nwhy this fails"),
+        (
+            PolishMode::Terse,
+            "I'd really like to know\nwhy this fails".to_string()
+        )
+    );
+    assert_eq!(
+        PolishMode::split("/polish  their going to the store"),
+        (PolishMode::Grammar, "their going to the store".to_string())
+    );
+    assert_eq!(
+        PolishMode::split("/polish formal"),
+        (PolishMode::Formal, "".to_string())
+    );
+    assert_eq!(
+        PolishMode::split("/polish"),
+        (PolishMode::Grammar, "".to_string())
+    );
+}
+
+#[test]
+fn it_builds_prompt() {
+    assert_eq!(
+        PolishMode::Formal.prompt("hey whats up"),
+        "Rewrite the following message in a formal, professional tone. Only reply with the rewritten message, without quotes or commentary.\n\nhey whats up"
+    );
+}
@@ -0,0 +1,186 @@
+#[cfg(test)]
+#[path = "prompt_variables_test.rs"]
+mod tests;
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use anyhow::bail;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+const NAME_PATTERN: &str = r"[A-Za-z_][A-Za-z0-9_.-]*";
+
+/// Placeholders are variable names, or `env:` and `sh:` followed by what
+/// they read. Anything else in braces, such as a Helm template, is left
+/// alone, and `\{{` is sent as `{{` for braces that would be a placeholder.
+static PLACEHOLDER_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(&format!(
+        r"(?P<escape>\\\{{\{{)|\{{\{{\s*(?:(?P<source>env|sh):\s*(?P<value>.+?)|(?P<name>{NAME_PATTERN}))\s*\}}\}}"
+    ))
+    .unwrap();
+});
+
+static NAME_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(&format!("^{NAME_PATTERN}$")).unwrap();
+});
+
+/// Where a `{{...}}` placeholder takes its value from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Placeholder {
+    /// `{{name}}`, set with `/set var`, falling back to the environment
+    /// variable of the same name when `env` is allowed.
+    Variable(String),
+    /// `{{env:NAME}}`, always read from the environment.
+    Env(String),
+    /// `{{sh:COMMAND}}`, replaced by what the command prints.
+    Command(String),
+}
+
+/// Variables set with `/set var NAME=VALUE`, kept with the session and
+/// filled into prompts just before they're sent.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PromptVariables {
+    pub values: BTreeMap<String, String>,
+}
+
+impl PromptVariables {
+    pub fn new(values: BTreeMap<String, String>) -> PromptVariables {
+        return PromptVariables { values };
+    }
+
+    pub fn is_valid_name(name: &str) -> bool {
+        return NAME_RE.is_match(name);
+    }
+
+    /// Sets the variable from `NAME=VALUE`, or removes it when the value is
+    /// empty. Returns the name.
+    pub fn assign(&mut self, assignment: &str) -> Result<String> {
+        let (name, value) = match assignment.split_once('=') {
+            Some((name, value)) => (name.trim(), value.trim()),
+            None => bail!("Variables are set as NAME=VALUE"),
+        };
+        if !PromptVariables::is_valid_name(name) {
+            bail!(format!(
+                "`{name}` isn't a valid variable name, use letters, numbers, `_`, `.`, and `-`"
+            ));
+        }
+
+        if value.is_empty() {
+            self.values.remove(name);
+        } else {
+            self.values.insert(name.to_string(), value.to_string());
+        }
+
+        return Ok(name.to_string());
+    }
+
+    /// Lists the variables for `/set var`.
+    pub fn format(&self) -> String {
+        if self.values.is_empty() {
+            return "No variables are set. Set one with `/set var NAME=VALUE`, and use it in prompts as `{{NAME}}`.".to_string();
+        }
+
+        let list = self
+            .values
+            .iter()
+            .map(|(name, value)| return format!("- {name}: {value}"))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        return format!("Variables:\n{list}");
+    }
+
+    fn placeholder(caps: &regex::Captures) -> Option<Placeholder> {
+        if caps.name("escape").is_some() {
+            return None;
+        }
+        if let Some(name) = caps.name("name") {
+            return Some(Placeholder::Variable(name.as_str().to_string()));
+        }
+
+        let value = caps["value"].to_string();
+        return match &caps["source"] {
+            "env" => Some(Placeholder::Env(value)),
+            _ => Some(Placeholder::Command(value)),
+        };
+    }
+
+    pub fn placeholders(text: &str) -> Vec<Placeholder> {
+        return PLACEHOLDER_RE
+            .captures_iter(text)
+            .filter_map(|caps| return PromptVariables::placeholder(&caps))
+            .collect();
+    }
+
+    /// True when a placeholder always reads the environment, as
+    /// `{{env:NAME}}`.
+    pub fn reads_env(&self, text: &str) -> bool {
+        return PromptVariables::placeholders(text)
+            .iter()
+            .any(|e| return matches!(e, Placeholder::Env(_)));
+    }
+
+    /// Commands the text runs, in order and without repeats, so each only
+    /// runs once.
+    pub fn commands(text: &str) -> Vec<String> {
+        let mut commands: Vec<String> = vec![];
+        for placeholder in PromptVariables::placeholders(text) {
+            if let Placeholder::Command(command) = placeholder {
+                if !commands.contains(&command) {
+                    commands.push(command);
+                }
+            }
+        }
+
+        return commands;
+    }
+
+    /// Replaces each placeholder, with `outputs` holding what each of the
+    /// text's commands printed. Variables that aren't set are left as they
+    /// are, as they're likely template syntax meant for the model, and only
+    /// fall back to the environment when `read_env` is set. Fails on the
+    /// first `env:` or `sh:` placeholder without a value, so a prompt is
+    /// never sent half filled in.
+    pub fn interpolate(
+        &self,
+        text: &str,
+        outputs: &HashMap<String, String>,
+        read_env: bool,
+    ) -> Result<String> {
+        let mut res = String::new();
+        let mut last = 0;
+        for caps in PLACEHOLDER_RE.captures_iter(text) {
+            let found = caps.get(0).unwrap();
+            let replacement = match PromptVariables::placeholder(&caps) {
+                None => "{{".to_string(),
+                Some(Placeholder::Env(name)) => {
+                    match std::env::var(&name) {
+                        Ok(env) => env,
+                        Err(_) => bail!(format!("Environment variable `{name}` isn't set")),
+                    }
+                }
+                Some(Placeholder::Command(command)) => {
+                    match outputs.get(&command) {
+                        Some(output) => output.trim_end().to_string(),
+                        None => bail!(format!("Command `{command}` wasn't run")),
+                    }
+                }
+                Some(Placeholder::Variable(name)) => {
+                    let value = self.values.get(&name).cloned().or_else(|| {
+                        return read_env.then(|| return std::env::var(&name).ok())?;
+                    });
+                    value.unwrap_or_else(|| return found.as_str().to_string())
+                }
+            };
+
+            res += &text[last..found.start()];
+            res += &replacement;
+            last = found.end();
+        }
+        res += &text[last..];
+
+        return Ok(res);
+    }
+}
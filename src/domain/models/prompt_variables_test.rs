@@ -0,0 +1,105 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use super::Placeholder;
+use super::PromptVariables;
+
+fn variables(pairs: &[(&str, &str)]) -> PromptVariables {
+    return PromptVariables::new(
+        pairs
+            .iter()
+            .map(|(name, value)| return (name.to_string(), value.to_string()))
+            .collect::<BTreeMap<String, String>>(),
+    );
+}
+
+#[test]
+fn it_finds_placeholders() {
+    let text = "Review {{ lang }} for {{env:USER}}: {{sh: git diff --stat}} {{sh:git diff --stat}}";
+
+    assert_eq!(
+        PromptVariables::placeholders(text),
+        vec![
+            Placeholder::Variable("lang".to_string()),
+            Placeholder::Env("USER".to_string()),
+            Placeholder::Command("git diff --stat".to_string()),
+            Placeholder::Command("git diff --stat".to_string()),
+        ]
+    );
+    assert_eq!(
+        PromptVariables::commands(text),
+        vec!["git diff --stat".to_string()]
+    );
+}
+
+#[test]
+fn it_interpolates_variables_env_and_commands() {
+    std::env::set_var("OATMEAL_TEST_PROMPT_VAR", "from env");
+    let vars = variables(&[("lang", "Rust")]);
+    let outputs = HashMap::from([("echo hi".to_string(), "hi\n".to_string())]);
+
+    let res = vars
+        .interpolate(
+            "{{lang}}, {{env:OATMEAL_TEST_PROMPT_VAR}}, {{OATMEAL_TEST_PROMPT_VAR}}, {{sh:echo hi}}!",
+            &outputs,
+            true,
+        )
+        .unwrap();
+    assert_eq!(res, "Rust, from env, from env, hi!");
+
+    let res = vars
+        .interpolate("{{lang}}, {{OATMEAL_TEST_PROMPT_VAR}}", &outputs, false)
+        .unwrap();
+    assert_eq!(res, "Rust, {{OATMEAL_TEST_PROMPT_VAR}}");
+}
+
+#[test]
+fn it_leaves_template_syntax_alone() {
+    let text = "Why does {{ .Values.image }} fail in Helm, when {% if user %}{{ user.name | upper }}{% endif %} works in Jinja and {{#each items}}{{this}}{{/each}} in Handlebars?";
+    assert_eq!(
+        PromptVariables::placeholders(text),
+        vec![Placeholder::Variable("this".to_string())]
+    );
+    assert!(!PromptVariables::default().reads_env(text));
+
+    let res = PromptVariables::default()
+        .interpolate(text, &HashMap::new(), false)
+        .unwrap();
+    assert_eq!(res, text);
+}
+
+#[test]
+fn it_escapes_placeholders() {
+    let text = r"Fill {{lang}} with \{{lang}}, or \{{sh:date}}";
+    assert_eq!(
+        PromptVariables::placeholders(text),
+        vec![Placeholder::Variable("lang".to_string())]
+    );
+
+    let res = variables(&[("lang", "Rust")])
+        .interpolate(text, &HashMap::new(), false)
+        .unwrap();
+    assert_eq!(res, "Fill Rust with {{lang}}, or {{sh:date}}");
+}
+
+#[test]
+fn it_fails_on_env_placeholders_that_arent_set() {
+    let err = PromptVariables::default()
+        .interpolate("Hello {{env:OATMEAL_UNSET_NAME}}", &HashMap::new(), true)
+        .unwrap_err();
+
+    insta::assert_snapshot!(err.to_string(), @"Environment variable `OATMEAL_UNSET_NAME` isn't set");
+}
+
+#[test]
+fn it_assigns_and_removes_variables() {
+    let mut vars = PromptVariables::default();
+    assert_eq!(vars.assign("lang = Rust 2021").unwrap(), "lang");
+    assert_eq!(vars.values.get("lang").unwrap(), "Rust 2021");
+
+    vars.assign("lang=").unwrap();
+    assert!(vars.values.is_empty());
+
+    assert!(vars.assign("not valid=1").is_err());
+    assert!(vars.assign("novalue").is_err());
+}
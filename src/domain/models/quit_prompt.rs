@@ -0,0 +1,177 @@
+#[cfg(test)]
+#[path = "quit_prompt_test.rs"]
+mod tests;
+
+use ratatui::prelude::Alignment;
+use ratatui::prelude::Line;
+use ratatui::prelude::Modifier;
+use ratatui::prelude::Rect;
+use ratatui::prelude::Span;
+use ratatui::prelude::Style;
+use ratatui::widgets::Block;
+use ratatui::widgets::BorderType;
+use ratatui::widgets::Borders;
+use ratatui::widgets::Clear;
+use ratatui::widgets::Padding;
+use ratatui::widgets::Paragraph;
+use ratatui::widgets::Wrap;
+use ratatui::Frame;
+use strum::EnumIter;
+use strum::IntoEnumIterator;
+use tui_textarea::Key;
+
+use super::Event;
+
+/// Widest the quit prompt gets, however wide the terminal is.
+const QUIT_PROMPT_WIDTH: u16 = 60;
+
+/// What to do about quitting with something left to lose.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+pub enum QuitChoice {
+    SaveAndQuit,
+    Discard,
+    Cancel,
+}
+
+impl QuitChoice {
+    pub fn label(&self) -> &'static str {
+        return match self {
+            QuitChoice::SaveAndQuit => "Save and quit",
+            QuitChoice::Discard => "Discard",
+            QuitChoice::Cancel => "Cancel",
+        };
+    }
+
+    /// Picked by the first letter of its label.
+    fn for_char(char: char) -> Option<QuitChoice> {
+        return QuitChoice::iter().find(|e| {
+            return e
+                .label()
+                .to_lowercase()
+                .starts_with(char.to_ascii_lowercase());
+        });
+    }
+}
+
+/// Asks what to do before quitting with unsaved changes to the session or an
+/// answer still being generated, drawn over the chat.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QuitPrompt {
+    /// What would be lost, one per line.
+    pub reasons: Vec<String>,
+    pub selected: QuitChoice,
+}
+
+impl QuitPrompt {
+    pub fn new(reasons: Vec<String>) -> QuitPrompt {
+        return QuitPrompt {
+            reasons,
+            selected: QuitChoice::SaveAndQuit,
+        };
+    }
+
+    /// Keyboard events go to the prompt while it's open, rather than the
+    /// input box.
+    pub fn is_input(event: &Event) -> bool {
+        return matches!(
+            event,
+            Event::KeyboardCharInput(_)
+                | Event::KeyboardCTRLC()
+                | Event::KeyboardCTRLO()
+                | Event::KeyboardCTRLR()
+                | Event::KeyboardEnter()
+                | Event::KeyboardEsc()
+                | Event::KeyboardPaste(_)
+                | Event::KeyboardTab()
+        );
+    }
+
+    /// Moves the selection with Tab and the arrow keys. Returns the choice
+    /// once made, with Enter or CTRL+C for the one selected, Esc to cancel, or
+    /// the first letter of one.
+    pub fn handle(&mut self, event: &Event) -> Option<QuitChoice> {
+        let choices = QuitChoice::iter().collect::<Vec<QuitChoice>>();
+        let idx = choices
+            .iter()
+            .position(|e| return e == &self.selected)
+            .unwrap();
+        match event {
+            Event::KeyboardEnter() | Event::KeyboardCTRLC() => return Some(self.selected),
+            Event::KeyboardEsc() => return Some(QuitChoice::Cancel),
+            Event::KeyboardTab() => {
+                self.selected = choices[(idx + 1) % choices.len()];
+            }
+            Event::KeyboardCharInput(input) => {
+                match input.key {
+                    Key::Right | Key::Down => {
+                        self.selected = choices[(idx + 1) % choices.len()];
+                    }
+                    Key::Left | Key::Up => {
+                        self.selected = choices[(idx + choices.len() - 1) % choices.len()];
+                    }
+                    Key::Char(char) if !input.ctrl && !input.alt => {
+                        return QuitChoice::for_char(char);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        return None;
+    }
+
+    /// Renders the prompt in the middle of `rect`, over whatever is there.
+    pub fn render(&self, frame: &mut Frame, rect: Rect) {
+        let width = rect.width.min(QUIT_PROMPT_WIDTH);
+        // Reasons wrap within the borders and padding.
+        let inner_width = width.saturating_sub(4).max(1) as usize;
+        let reason_lines = self
+            .reasons
+            .iter()
+            .map(|e| return e.chars().count().div_ceil(inner_width).max(1))
+            .sum::<usize>();
+
+        let mut lines = self
+            .reasons
+            .iter()
+            .map(|e| return Line::from(e.to_string()))
+            .collect::<Vec<Line>>();
+        lines.push(Line::from(""));
+
+        let mut choices = vec![];
+        for choice in QuitChoice::iter() {
+            let mut style = Style::default();
+            if choice == self.selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            choices.push(Span::styled(format!(" {} ", choice.label()), style));
+            choices.push(Span::raw(" "));
+        }
+        choices.pop();
+        lines.push(Line::from(choices));
+
+        let height = rect.height.min(reason_lines as u16 + 4);
+        let area = Rect::new(
+            rect.x + (rect.width - width) / 2,
+            rect.y + (rect.height - height) / 2,
+            width,
+            height,
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(
+                    Block::default()
+                        .title(" Quit Oatmeal? ")
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Double)
+                        .padding(Padding::new(1, 1, 0, 0)),
+                )
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
+            area,
+        );
+    }
+}
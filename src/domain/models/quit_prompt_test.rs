@@ -0,0 +1,65 @@
+use tui_textarea::Input;
+use tui_textarea::Key;
+
+use super::QuitChoice;
+use super::QuitPrompt;
+use crate::domain::models::Event;
+
+fn key(key: Key) -> Event {
+    return Event::KeyboardCharInput(Input {
+        key,
+        ..Input::default()
+    });
+}
+
+#[test]
+fn it_saves_and_quits_by_default() {
+    let mut prompt = QuitPrompt::new(vec!["Unsaved".to_string()]);
+
+    assert_eq!(
+        prompt.handle(&Event::KeyboardEnter()),
+        Some(QuitChoice::SaveAndQuit)
+    );
+    assert_eq!(
+        prompt.handle(&Event::KeyboardCTRLC()),
+        Some(QuitChoice::SaveAndQuit)
+    );
+}
+
+#[test]
+fn it_moves_the_selection() {
+    let mut prompt = QuitPrompt::new(vec![]);
+
+    assert_eq!(prompt.handle(&Event::KeyboardTab()), None);
+    assert_eq!(prompt.selected, QuitChoice::Discard);
+    assert_eq!(prompt.handle(&key(Key::Right)), None);
+    assert_eq!(prompt.selected, QuitChoice::Cancel);
+    assert_eq!(prompt.handle(&key(Key::Right)), None);
+    assert_eq!(prompt.selected, QuitChoice::SaveAndQuit);
+    assert_eq!(prompt.handle(&key(Key::Left)), None);
+    assert_eq!(prompt.selected, QuitChoice::Cancel);
+    assert_eq!(
+        prompt.handle(&Event::KeyboardEnter()),
+        Some(QuitChoice::Cancel)
+    );
+}
+
+#[test]
+fn it_chooses_by_first_letter() {
+    let mut prompt = QuitPrompt::new(vec![]);
+
+    assert_eq!(
+        prompt.handle(&key(Key::Char('d'))),
+        Some(QuitChoice::Discard)
+    );
+    assert_eq!(
+        prompt.handle(&key(Key::Char('S'))),
+        Some(QuitChoice::SaveAndQuit)
+    );
+    assert_eq!(prompt.handle(&key(Key::Char('x'))), None);
+    assert_eq!(
+        prompt.handle(&Event::KeyboardEsc()),
+        Some(QuitChoice::Cancel)
+    );
+    assert!(!QuitPrompt::is_input(&Event::UITick()));
+}
@@ -0,0 +1,102 @@
+#[cfg(test)]
+#[path = "review_comment_test.rs"]
+mod tests;
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::json;
+
+static REVIEW_COMMENT_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(r"^\s*(?:[-*+]|\d+[.)])?\s*(?:\*\*|`)*(?P<path>[\w./-]+\.\w+):(?P<line>\d+)(?:-\d+)?(?:\*\*|`)*\s*(?::|-|–|—)\s*(?P<body>\S.*)$").unwrap();
+});
+
+/// A comment from a model's code review, tied to a line of a file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReviewComment {
+    pub path: String,
+    pub line: usize,
+    pub body: String,
+}
+
+impl ReviewComment {
+    /// Extracts comments written as `FILE:LINE: COMMENT`, optionally as a
+    /// list item and with the location wrapped in backticks or bold, such as
+    /// "- `src/main.rs:10`: Missing error handling." Lines within code
+    /// blocks are ignored.
+    pub fn parse_all(text: &str) -> Vec<ReviewComment> {
+        let mut comments = vec![];
+        let mut in_codeblock = false;
+        for line in text.lines() {
+            if line.trim().starts_with("```") {
+                in_codeblock = !in_codeblock;
+                continue;
+            }
+            if in_codeblock {
+                continue;
+            }
+
+            if let Some(captures) = REVIEW_COMMENT_RE.captures(line) {
+                if let Ok(line_number) = captures["line"].parse::<usize>() {
+                    comments.push(ReviewComment {
+                        path: captures["path"].to_string(),
+                        line: line_number,
+                        body: captures["body"].trim().to_string(),
+                    });
+                }
+            }
+        }
+
+        return comments;
+    }
+
+    /// Serializes comments in reviewdog's diagnostic JSON format (rdjson).
+    pub fn to_rdjson(comments: &[ReviewComment]) -> Result<String> {
+        let diagnostics = comments
+            .iter()
+            .map(|e| {
+                return json!({
+                    "message": e.body,
+                    "location": {
+                        "path": e.path,
+                        "range": {
+                            "start": { "line": e.line }
+                        }
+                    },
+                    "severity": "INFO"
+                });
+            })
+            .collect::<Vec<serde_json::Value>>();
+
+        let res = json!({
+            "source": { "name": "oatmeal" },
+            "diagnostics": diagnostics
+        });
+
+        return Ok(serde_json::to_string_pretty(&res)?);
+    }
+
+    /// Serializes comments as the request body for GitHub's create review
+    /// endpoint.
+    pub fn to_github_review(comments: &[ReviewComment]) -> Result<String> {
+        let review_comments = comments
+            .iter()
+            .map(|e| {
+                return json!({
+                    "path": e.path,
+                    "line": e.line,
+                    "side": "RIGHT",
+                    "body": e.body
+                });
+            })
+            .collect::<Vec<serde_json::Value>>();
+
+        let res = json!({
+            "event": "COMMENT",
+            "body": "Review from Oatmeal.",
+            "comments": review_comments
+        });
+
+        return Ok(serde_json::to_string(&res)?);
+    }
+}
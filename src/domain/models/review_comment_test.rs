@@ -0,0 +1,87 @@
+use anyhow::Result;
+
+use super::ReviewComment;
+
+#[test]
+fn it_parses_review_comments() {
+    let text = r#"
+Here's my review.
+
+- `src/main.rs:10`: Missing error handling.
+- **src/lib.rs:3-5** - This could be a constant.
+src/app.rs:42: Typo in the variable name.
+
+```rust
+// src/ignored.rs:1: not a comment
+```
+
+Overall looks good!
+"#;
+
+    assert_eq!(
+        ReviewComment::parse_all(text),
+        vec![
+            ReviewComment {
+                path: "src/main.rs".to_string(),
+                line: 10,
+                body: "Missing error handling.".to_string(),
+            },
+            ReviewComment {
+                path: "src/lib.rs".to_string(),
+                line: 3,
+                body: "This could be a constant.".to_string(),
+            },
+            ReviewComment {
+                path: "src/app.rs".to_string(),
+                line: 42,
+                body: "Typo in the variable name.".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn it_serializes_to_rdjson() -> Result<()> {
+    let comments = vec![ReviewComment {
+        path: "src/main.rs".to_string(),
+        line: 10,
+        body: "Missing error handling.".to_string(),
+    }];
+
+    insta::assert_snapshot!(ReviewComment::to_rdjson(&comments)?, @r###"
+    {
+      "diagnostics": [
+        {
+          "location": {
+            "path": "src/main.rs",
+            "range": {
+              "start": {
+                "line": 10
+              }
+            }
+          },
+          "message": "Missing error handling.",
+          "severity": "INFO"
+        }
+      ],
+      "source": {
+        "name": "oatmeal"
+      }
+    }
+    "###);
+
+    return Ok(());
+}
+
+#[test]
+fn it_serializes_to_github_review() -> Result<()> {
+    let comments = vec![ReviewComment {
+        path: "src/main.rs".to_string(),
+        line: 10,
+        body: "Missing error handling.".to_string(),
+    }];
+
+    insta::assert_snapshot!(ReviewComment::to_github_review(&comments)?, @r###"{"body":"Review from Oatmeal.","comments":[{"body":"Missing error handling.","line":10,"path":"src/main.rs","side":"RIGHT"}],"event":"COMMENT"}"###);
+
+    return Ok(());
+}
@@ -0,0 +1,166 @@
+#[cfg(test)]
+#[path = "rewrite_rule_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use regex::Regex;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// Which messages a rewrite rule changes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum RewriteTarget {
+    Prompt,
+    Response,
+    Both,
+}
+
+/// A regex replaced in prompts before they're sent, or in answers once
+/// they're done, from `rewrite-rules`.
+#[derive(Clone, Debug)]
+pub struct RewriteRule {
+    pub name: String,
+    pub target: RewriteTarget,
+    pub pattern: Regex,
+    /// Can refer to the pattern's groups, such as `$1`.
+    pub replacement: String,
+}
+
+impl RewriteRule {
+    /// Reads `NAME=[prompt:|response:]PATTERN => REPLACEMENT`, changing
+    /// both prompts and answers without a prefix.
+    pub fn parse(line: &str) -> Result<RewriteRule> {
+        let (name, rule) = match line.split_once('=') {
+            Some((name, rule)) if !name.trim().is_empty() => (name.trim(), rule.trim()),
+            _ => bail!("Rewrite rules are set as NAME=PATTERN => REPLACEMENT, got `{line}`"),
+        };
+
+        let (target, rule) = if let Some(rule) = rule.strip_prefix("prompt:") {
+            (RewriteTarget::Prompt, rule)
+        } else if let Some(rule) = rule.strip_prefix("response:") {
+            (RewriteTarget::Response, rule)
+        } else {
+            (RewriteTarget::Both, rule)
+        };
+
+        let (pattern, replacement) = match rule.split_once(" =>") {
+            Some((pattern, replacement)) if !pattern.trim().is_empty() => {
+                (pattern.trim(), replacement.trim())
+            }
+            _ => bail!("The rewrite rule `{name}` is missing a PATTERN => REPLACEMENT"),
+        };
+        let pattern = match Regex::new(pattern) {
+            Ok(pattern) => pattern,
+            Err(err) => bail!("The rewrite rule `{name}` isn't a valid regex: {err}"),
+        };
+
+        return Ok(RewriteRule {
+            name: name.to_string(),
+            target,
+            pattern,
+            replacement: replacement.to_string(),
+        });
+    }
+
+    pub fn rewrites(&self, target: RewriteTarget) -> bool {
+        return self.target == RewriteTarget::Both || self.target == target;
+    }
+}
+
+/// A rule that changed a message, and how many times it matched.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AppliedRewrite {
+    pub name: String,
+    pub target: RewriteTarget,
+    pub count: usize,
+}
+
+/// The rules from `rewrite-rules`, applied one after another in the order
+/// they're listed, each to what the one before it left.
+#[derive(Clone, Debug, Default)]
+pub struct RewriteRules {
+    pub rules: Vec<RewriteRule>,
+}
+
+impl RewriteRules {
+    /// Reads one rule per line, skipping blank lines.
+    pub fn parse(text: &str) -> Result<RewriteRules> {
+        let rules = text
+            .lines()
+            .filter(|e| return !e.trim().is_empty())
+            .map(RewriteRule::parse)
+            .collect::<Result<Vec<RewriteRule>>>()?;
+
+        return Ok(RewriteRules { rules });
+    }
+
+    pub fn from_config() -> Result<RewriteRules> {
+        return RewriteRules::parse(&Config::get(ConfigKey::RewriteRules));
+    }
+
+    /// Rewrites a prompt or answer, along with the rules that changed it.
+    pub fn apply(&self, target: RewriteTarget, text: &str) -> (String, Vec<AppliedRewrite>) {
+        let mut text = text.to_string();
+        let mut applied = vec![];
+        for rule in self.rules.iter().filter(|e| return e.rewrites(target)) {
+            let count = rule.pattern.find_iter(&text).count();
+            if count == 0 {
+                continue;
+            }
+
+            tracing::debug!(rule = rule.name, %target, count, "Applied rewrite rule");
+            text = rule
+                .pattern
+                .replace_all(&text, rule.replacement.as_str())
+                .to_string();
+            applied.push(AppliedRewrite {
+                name: rule.name.to_string(),
+                target,
+                count,
+            });
+        }
+
+        return (text, applied);
+    }
+
+    /// Lists the rules in the order they run for `/rewrites`, with the ones
+    /// that changed the last prompt and answer.
+    pub fn format(&self, applied: &[AppliedRewrite]) -> String {
+        if self.rules.is_empty() {
+            return "No rewrite rules are set. Add them to `rewrite-rules` as `NAME=PATTERN => REPLACEMENT`, prefixing the pattern with `prompt:` or `response:` to only rewrite one of them.".to_string();
+        }
+
+        let rules = self
+            .rules
+            .iter()
+            .enumerate()
+            .map(|(idx, rule)| {
+                return format!(
+                    "{}. {} ({}): `{}` => `{}`",
+                    idx + 1,
+                    rule.name,
+                    rule.target,
+                    rule.pattern.as_str(),
+                    rule.replacement
+                );
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        if applied.is_empty() {
+            return format!("Rewrite rules, in the order they run:\n\n{rules}\n\nNone of them changed the last prompt or answer.");
+        }
+
+        let applied = applied
+            .iter()
+            .map(|e| {
+                let times = if e.count == 1 { "time" } else { "times" };
+                return format!("- {} changed the {} {} {times}", e.name, e.target, e.count);
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+        return format!("Rewrite rules, in the order they run:\n\n{rules}\n\nApplied to the last prompt and answer:\n\n{applied}");
+    }
+}
@@ -0,0 +1,87 @@
+use anyhow::Result;
+
+use super::AppliedRewrite;
+use super::RewriteRule;
+use super::RewriteRules;
+use super::RewriteTarget;
+
+#[test]
+fn it_parses_rules() -> Result<()> {
+    let rule = RewriteRule::parse(r"disclaimer=response:(?i)as an AI language model,?\s* =>")?;
+    assert_eq!(rule.name, "disclaimer");
+    assert_eq!(rule.target, RewriteTarget::Response);
+    assert_eq!(rule.pattern.as_str(), r"(?i)as an AI language model,?\s*");
+    assert_eq!(rule.replacement, "");
+
+    let rule = RewriteRule::parse(r"brb=\bbrb\b => be right back")?;
+    assert_eq!(rule.target, RewriteTarget::Both);
+    assert_eq!(rule.replacement, "be right back");
+
+    insta::assert_snapshot!(RewriteRule::parse("brb").unwrap_err().to_string(), @"Rewrite rules are set as NAME=PATTERN => REPLACEMENT, got `brb`");
+    insta::assert_snapshot!(RewriteRule::parse("brb=prompt:brb").unwrap_err().to_string(), @"The rewrite rule `brb` is missing a PATTERN => REPLACEMENT");
+    assert!(RewriteRule::parse("brb=(brb => be right back").is_err());
+
+    return Ok(());
+}
+
+#[test]
+fn it_applies_rules_in_order() -> Result<()> {
+    let rules = RewriteRules::parse(
+        r"
+tbh=prompt:\btbh\b => to be honest
+honest=(\w+) honest => $1 candid
+disclaimer=response:As an AI language model,\s* =>
+",
+    )?;
+
+    let (text, applied) = rules.apply(RewriteTarget::Prompt, "tbh, tbh I like it");
+    assert_eq!(text, "to be candid, to be candid I like it");
+    assert_eq!(
+        applied,
+        vec![
+            AppliedRewrite {
+                name: "tbh".to_string(),
+                target: RewriteTarget::Prompt,
+                count: 2,
+            },
+            AppliedRewrite {
+                name: "honest".to_string(),
+                target: RewriteTarget::Prompt,
+                count: 2,
+            },
+        ]
+    );
+
+    let (text, applied) = rules.apply(
+        RewriteTarget::Response,
+        "As an AI language model, tbh is slang.",
+    );
+    assert_eq!(text, "tbh is slang.");
+    assert_eq!(applied.len(), 1);
+
+    return Ok(());
+}
+
+#[test]
+fn it_formats_rules_with_the_ones_applied() -> Result<()> {
+    assert!(RewriteRules::default()
+        .format(&[])
+        .starts_with("No rewrite rules are set."));
+
+    let rules = RewriteRules::parse(r"brb=prompt:\bbrb\b => be right back")?;
+    insta::assert_snapshot!(rules.format(&[AppliedRewrite {
+        name: "brb".to_string(),
+        target: RewriteTarget::Prompt,
+        count: 1,
+    }]), @r###"
+    Rewrite rules, in the order they run:
+
+    1. brb (prompt): `\bbrb\b` => `be right back`
+
+    Applied to the last prompt and answer:
+
+    - brb changed the prompt 1 time
+    "###);
+
+    return Ok(());
+}
@@ -0,0 +1,173 @@
+#[cfg(test)]
+#[path = "sampling_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use strum::EnumIter;
+use strum::EnumVariantNames;
+use strum::IntoEnumIterator;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// A sampling parameter that `/set` can change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumVariantNames, strum::Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum SamplingParam {
+    Temperature,
+    TopP,
+    MaxTokens,
+    /// Comma separated text that ends an answer once generated.
+    Stop,
+}
+
+impl SamplingParam {
+    pub fn parse(text: &str) -> Option<SamplingParam> {
+        return SamplingParam::iter().find(|e| return e.to_string() == text);
+    }
+
+    pub fn config_key(&self) -> ConfigKey {
+        return match self {
+            SamplingParam::Temperature => ConfigKey::Temperature,
+            SamplingParam::TopP => ConfigKey::TopP,
+            SamplingParam::MaxTokens => ConfigKey::MaxTokens,
+            SamplingParam::Stop => ConfigKey::StopSequences,
+        };
+    }
+
+    /// Checks `value` is within the range backends accept.
+    pub fn validate(&self, value: &str) -> Result<()> {
+        let valid = match self {
+            SamplingParam::Temperature => {
+                value
+                    .parse::<f64>()
+                    .map(|e| return (0.0..=2.0).contains(&e))
+                    .unwrap_or(false)
+            }
+            SamplingParam::TopP => {
+                value
+                    .parse::<f64>()
+                    .map(|e| return e > 0.0 && e <= 1.0)
+                    .unwrap_or(false)
+            }
+            SamplingParam::MaxTokens => value.parse::<u32>().map(|e| return e > 0).unwrap_or(false),
+            SamplingParam::Stop => value.split(',').all(|e| return !e.is_empty()),
+        };
+        if !valid {
+            bail!(format!(
+                "{value} isn't a valid {self}, it must be {}",
+                self.range()
+            ));
+        }
+
+        return Ok(());
+    }
+
+    fn range(&self) -> &'static str {
+        return match self {
+            SamplingParam::Temperature => "between 0 and 2",
+            SamplingParam::TopP => "above 0 and at most 1",
+            SamplingParam::MaxTokens => "a whole number above 0",
+            SamplingParam::Stop => "comma separated text, without empty entries",
+        };
+    }
+}
+
+/// How backends generate answers, saved with the session. Anything left
+/// unset uses the backend's own default.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SamplingParams {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stop: Vec<String>,
+}
+
+impl SamplingParams {
+    pub fn from_config() -> SamplingParams {
+        return SamplingParams {
+            temperature: Config::get(ConfigKey::Temperature).parse::<f64>().ok(),
+            top_p: Config::get(ConfigKey::TopP).parse::<f64>().ok(),
+            max_tokens: Config::get(ConfigKey::MaxTokens).parse::<u32>().ok(),
+            seed: Config::get(ConfigKey::Seed).parse::<u64>().ok(),
+            stop: Config::get(ConfigKey::StopSequences)
+                .split(',')
+                .filter(|e| return !e.is_empty())
+                .map(|e| return e.to_string())
+                .collect(),
+        };
+    }
+
+    /// Stop sequences for backends' requests, leaving them out when none are
+    /// set.
+    pub fn stop_sequences(&self) -> Option<Vec<String>> {
+        return Some(self.stop.clone()).filter(|e| return !e.is_empty());
+    }
+
+    /// Sets the config for each parameter, so a resumed session samples the
+    /// same way it did.
+    pub fn apply_to_config(&self) {
+        let values = [
+            (
+                ConfigKey::Temperature,
+                self.temperature.map(|e| return e.to_string()),
+            ),
+            (ConfigKey::TopP, self.top_p.map(|e| return e.to_string())),
+            (
+                ConfigKey::MaxTokens,
+                self.max_tokens.map(|e| return e.to_string()),
+            ),
+            (ConfigKey::Seed, self.seed.map(|e| return e.to_string())),
+            (ConfigKey::StopSequences, Some(self.stop.join(","))),
+        ];
+        for (key, value) in values {
+            Config::set(key, &value.unwrap_or_default());
+        }
+    }
+
+    pub fn is_default(&self) -> bool {
+        return self == &SamplingParams::default();
+    }
+
+    /// Lists the parameters that are set, such as `temperature 0.2, top_p
+    /// 0.9`.
+    pub fn format(&self) -> String {
+        let values = [
+            (
+                SamplingParam::Temperature,
+                self.temperature.map(|e| return e.to_string()),
+            ),
+            (
+                SamplingParam::TopP,
+                self.top_p.map(|e| return e.to_string()),
+            ),
+            (
+                SamplingParam::MaxTokens,
+                self.max_tokens.map(|e| return e.to_string()),
+            ),
+            (
+                SamplingParam::Stop,
+                self.stop_sequences().map(|e| return e.join(",")),
+            ),
+        ];
+
+        let mut params = values
+            .into_iter()
+            .filter_map(|(param, value)| return Some(format!("{param} {}", value?)))
+            .collect::<Vec<String>>();
+        if let Some(seed) = self.seed {
+            params.push(format!("seed {seed}"));
+        }
+
+        return params.join(", ");
+    }
+}
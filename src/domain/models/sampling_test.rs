@@ -0,0 +1,70 @@
+use super::SamplingParam;
+use super::SamplingParams;
+use crate::configuration::ConfigKey;
+
+#[test]
+fn it_parses_sampling_params() {
+    assert_eq!(SamplingParam::parse("top_p"), Some(SamplingParam::TopP));
+    assert_eq!(SamplingParam::parse("top-p"), None);
+    assert_eq!(SamplingParam::TopP.config_key(), ConfigKey::TopP);
+}
+
+#[test]
+fn it_validates_sampling_values() {
+    assert!(SamplingParam::Temperature.validate("0").is_ok());
+    assert!(SamplingParam::Temperature.validate("2").is_ok());
+    assert!(SamplingParam::TopP.validate("1").is_ok());
+    assert!(SamplingParam::MaxTokens.validate("256").is_ok());
+
+    assert_eq!(
+        SamplingParam::Temperature
+            .validate("2.5")
+            .unwrap_err()
+            .to_string(),
+        "2.5 isn't a valid temperature, it must be between 0 and 2"
+    );
+    assert!(SamplingParam::TopP.validate("0").is_err());
+    assert!(SamplingParam::MaxTokens.validate("0").is_err());
+    assert!(SamplingParam::MaxTokens.validate("1.5").is_err());
+    assert!(SamplingParam::Stop.validate("END,###").is_ok());
+    assert!(SamplingParam::Stop.validate("END,").is_err());
+}
+
+#[test]
+fn it_formats_sampling_params() {
+    let params = SamplingParams {
+        temperature: Some(0.2),
+        max_tokens: Some(512),
+        ..SamplingParams::default()
+    };
+    assert_eq!(params.format(), "temperature 0.2, max_tokens 512");
+
+    let params = SamplingParams {
+        seed: Some(42),
+        stop: vec!["END".to_string(), "###".to_string()],
+        ..SamplingParams::default()
+    };
+    assert_eq!(params.format(), "stop END,###, seed 42");
+    assert_eq!(
+        params.stop_sequences(),
+        Some(vec!["END".to_string(), "###".to_string()])
+    );
+    assert_eq!(SamplingParams::default().stop_sequences(), None);
+    assert!(!params.is_default());
+    assert!(SamplingParams::default().is_default());
+    assert_eq!(SamplingParams::default().format(), "");
+}
+
+#[test]
+fn it_serializes_only_set_params() {
+    let params = SamplingParams {
+        top_p: Some(0.9),
+        ..SamplingParams::default()
+    };
+    let yaml = serde_yaml::to_string(&params).unwrap();
+    assert_eq!(yaml, "top_p: 0.9\n");
+    assert_eq!(
+        serde_yaml::from_str::<SamplingParams>(&yaml).unwrap(),
+        params
+    );
+}
@@ -1,21 +1,50 @@
+use std::collections::BTreeMap;
+
 use serde::Deserialize;
 use serde::Serialize;
 
+use super::ContextDocument;
+use super::FewShotExample;
 use super::Message;
+use super::SamplingParams;
 
+/// Generic over the messages so sessions can be saved without copying them.
 #[derive(Serialize, Deserialize)]
-pub struct State {
+pub struct State<M = Vec<Message>> {
     pub backend_name: String,
     pub backend_model: String,
     pub backend_context: String,
     pub editor_language: String,
-    pub messages: Vec<Message>,
+    /// Directory Oatmeal was started in, for grouping `oatmeal stats`.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub project: String,
+    pub messages: M,
+    /// Examples from `/fewshot`, sent ahead of the conversation.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub few_shot: Vec<FewShotExample>,
+    /// Documents added with `/context add`, sent ahead of every prompt.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub context_documents: Vec<ContextDocument>,
+    /// Variables set with `/set var`, filled into prompts.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub variables: BTreeMap<String, String>,
+    /// Sampling parameters answers were generated with, restored when the
+    /// session is opened.
+    #[serde(default, skip_serializing_if = "SamplingParams::is_default")]
+    pub sampling: SamplingParams,
+    /// System prompt from `/system`, restored when the session is opened.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Prompt left unsent in the input box, put back when the session is
+    /// opened.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub draft: String,
 }
 
 #[derive(Serialize, Deserialize)]
-pub struct Session {
+pub struct Session<M = Vec<Message>> {
     pub id: String,
     pub version: String,
     pub timestamp: String,
-    pub state: State,
+    pub state: State<M>,
 }
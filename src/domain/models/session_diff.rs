@@ -0,0 +1,210 @@
+#[cfg(test)]
+#[path = "session_diff_test.rs"]
+mod tests;
+
+use similar::capture_diff_slices;
+use similar::Algorithm;
+use similar::DiffOp;
+use similar::TextDiff;
+
+use super::Author;
+use super::Message;
+use super::SlashCommand;
+
+/// Longest a prompt is shown as in the heading of a turn.
+const MAX_HEADING_CHARS: usize = 60;
+
+/// A prompt from the user, and everything the model answered to it.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Turn {
+    pub prompt: String,
+    pub answer: String,
+}
+
+impl Turn {
+    /// Groups messages in to turns. Slash commands and messages from Oatmeal
+    /// aren't part of the conversation, so are skipped.
+    pub fn from_messages(messages: &[Message]) -> Vec<Turn> {
+        let mut turns: Vec<Turn> = vec![];
+        for message in messages {
+            match message.author {
+                Author::User => {
+                    if SlashCommand::parse(&message.text).is_none() {
+                        turns.push(Turn {
+                            prompt: message.text.trim().to_string(),
+                            ..Turn::default()
+                        });
+                    }
+                }
+                Author::Model => {
+                    if let Some(turn) = turns.last_mut() {
+                        if !turn.answer.is_empty() {
+                            turn.answer += "\n\n";
+                        }
+                        turn.answer += message.text.trim();
+                    }
+                }
+                Author::Oatmeal => {}
+            }
+        }
+
+        return turns;
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TurnDiff {
+    /// Both sessions asked the prompt and got the same answer.
+    Same(Turn),
+    /// Both sessions asked the prompt, and the answers differ.
+    Diverged {
+        prompt: String,
+        left: String,
+        right: String,
+    },
+    OnlyLeft(Turn),
+    OnlyRight(Turn),
+}
+
+fn with_newline(text: &str) -> String {
+    if text.ends_with('\n') {
+        return text.to_string();
+    }
+    return format!("{text}\n");
+}
+
+fn heading(prompt: &str) -> String {
+    let line = prompt.lines().next().unwrap_or_default();
+    if line.chars().count() > MAX_HEADING_CHARS || prompt.lines().count() > 1 {
+        return format!(
+            "{}...",
+            line.chars().take(MAX_HEADING_CHARS).collect::<String>()
+        );
+    }
+    return line.to_string();
+}
+
+/// Two conversations aligned by their prompts, such as after running the
+/// same workflow against two models, to see where the answers diverged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SessionDiff {
+    pub turns: Vec<TurnDiff>,
+}
+
+impl SessionDiff {
+    pub fn new(left: &[Message], right: &[Message]) -> SessionDiff {
+        let left = Turn::from_messages(left);
+        let right = Turn::from_messages(right);
+        let left_prompts = left.iter().map(|e| return &e.prompt).collect::<Vec<_>>();
+        let right_prompts = right.iter().map(|e| return &e.prompt).collect::<Vec<_>>();
+
+        let mut turns = vec![];
+        for op in capture_diff_slices(Algorithm::Myers, &left_prompts, &right_prompts) {
+            match op {
+                DiffOp::Equal {
+                    old_index,
+                    new_index,
+                    len,
+                } => {
+                    for (a, b) in left[old_index..old_index + len]
+                        .iter()
+                        .zip(&right[new_index..new_index + len])
+                    {
+                        if a.answer == b.answer {
+                            turns.push(TurnDiff::Same(a.clone()));
+                        } else {
+                            turns.push(TurnDiff::Diverged {
+                                prompt: a.prompt.to_string(),
+                                left: a.answer.to_string(),
+                                right: b.answer.to_string(),
+                            });
+                        }
+                    }
+                }
+                DiffOp::Delete {
+                    old_index, old_len, ..
+                } => {
+                    for turn in &left[old_index..old_index + old_len] {
+                        turns.push(TurnDiff::OnlyLeft(turn.clone()));
+                    }
+                }
+                DiffOp::Insert {
+                    new_index, new_len, ..
+                } => {
+                    for turn in &right[new_index..new_index + new_len] {
+                        turns.push(TurnDiff::OnlyRight(turn.clone()));
+                    }
+                }
+                DiffOp::Replace {
+                    old_index,
+                    old_len,
+                    new_index,
+                    new_len,
+                } => {
+                    for turn in &left[old_index..old_index + old_len] {
+                        turns.push(TurnDiff::OnlyLeft(turn.clone()));
+                    }
+                    for turn in &right[new_index..new_index + new_len] {
+                        turns.push(TurnDiff::OnlyRight(turn.clone()));
+                    }
+                }
+            }
+        }
+
+        return SessionDiff { turns };
+    }
+
+    /// Formats each turn under a heading of its prompt, with diverged answers
+    /// as a unified diff from the left session to the right.
+    pub fn render(&self, left_name: &str, right_name: &str) -> String {
+        let shared = self
+            .turns
+            .iter()
+            .filter(|e| return matches!(e, TurnDiff::Same(_) | TurnDiff::Diverged { .. }))
+            .count();
+        let diverged = self
+            .turns
+            .iter()
+            .filter(|e| return matches!(e, TurnDiff::Diverged { .. }))
+            .count();
+
+        let mut sections = vec![format!(
+            "Comparing {left_name} with {right_name}: {diverged} of {shared} shared prompts were answered differently."
+        )];
+        for (idx, turn) in self.turns.iter().enumerate() {
+            let section = match turn {
+                TurnDiff::Same(turn) => {
+                    format!("{}. {}\nAnswers match.", idx + 1, heading(&turn.prompt))
+                }
+                TurnDiff::Diverged {
+                    prompt,
+                    left,
+                    right,
+                } => {
+                    let diff = TextDiff::from_lines(&with_newline(left), &with_newline(right))
+                        .unified_diff()
+                        .header(left_name, right_name)
+                        .to_string();
+                    format!("{}. {}\n```diff\n{diff}```", idx + 1, heading(prompt))
+                }
+                TurnDiff::OnlyLeft(turn) => {
+                    format!(
+                        "{}. {}\nOnly in {left_name}.",
+                        idx + 1,
+                        heading(&turn.prompt)
+                    )
+                }
+                TurnDiff::OnlyRight(turn) => {
+                    format!(
+                        "{}. {}\nOnly in {right_name}.",
+                        idx + 1,
+                        heading(&turn.prompt)
+                    )
+                }
+            };
+            sections.push(section);
+        }
+
+        return sections.join("\n\n");
+    }
+}
@@ -0,0 +1,112 @@
+use super::SessionDiff;
+use super::Turn;
+use super::TurnDiff;
+use crate::domain::models::Author;
+use crate::domain::models::Message;
+
+fn messages(turns: &[(&str, &str)]) -> Vec<Message> {
+    return turns
+        .iter()
+        .flat_map(|(prompt, answer)| {
+            return vec![
+                Message::new(Author::User, prompt),
+                Message::new(Author::Model, answer),
+            ];
+        })
+        .collect();
+}
+
+#[test]
+fn it_groups_messages_in_to_turns() {
+    let messages = vec![
+        Message::new(Author::Oatmeal, "Hey, it looks like you're new here."),
+        Message::new(Author::User, "Write fizzbuzz"),
+        Message::new(Author::Model, "Here's fizzbuzz."),
+        Message::new(Author::Model, "It prints numbers."),
+        Message::new(Author::User, "/copy"),
+        Message::new(Author::Oatmeal, "Copied chat log to clipboard."),
+        Message::new(Author::User, "Now in Rust"),
+    ];
+
+    assert_eq!(
+        Turn::from_messages(&messages),
+        vec![
+            Turn {
+                prompt: "Write fizzbuzz".to_string(),
+                answer: "Here's fizzbuzz.\n\nIt prints numbers.".to_string(),
+            },
+            Turn {
+                prompt: "Now in Rust".to_string(),
+                answer: "".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn it_aligns_sessions_by_prompt() {
+    let left = messages(&[("one", "1"), ("two", "2"), ("three", "3")]);
+    let right = messages(&[("one", "1"), ("three", "III"), ("four", "4")]);
+
+    let diff = SessionDiff::new(&left, &right);
+
+    assert_eq!(
+        diff.turns,
+        vec![
+            TurnDiff::Same(Turn {
+                prompt: "one".to_string(),
+                answer: "1".to_string(),
+            }),
+            TurnDiff::OnlyLeft(Turn {
+                prompt: "two".to_string(),
+                answer: "2".to_string(),
+            }),
+            TurnDiff::Diverged {
+                prompt: "three".to_string(),
+                left: "3".to_string(),
+                right: "III".to_string(),
+            },
+            TurnDiff::OnlyRight(Turn {
+                prompt: "four".to_string(),
+                answer: "4".to_string(),
+            }),
+        ]
+    );
+}
+
+#[test]
+fn it_renders() {
+    let left = messages(&[
+        ("Write fizzbuzz", "fn main() {\n    fizzbuzz();\n}"),
+        ("Explain it", "It loops."),
+    ]);
+    let right = messages(&[
+        ("Write fizzbuzz", "fn main() {\n    fizz_buzz();\n}"),
+        ("Explain it", "It loops."),
+        (
+            "Add tests for every number from one to a hundred, and for zero",
+            "Done.",
+        ),
+    ]);
+
+    insta::assert_snapshot!(SessionDiff::new(&left, &right).render("a (llama2)", "b (gpt-4)"), @r###"
+    Comparing a (llama2) with b (gpt-4): 1 of 2 shared prompts were answered differently.
+
+    1. Write fizzbuzz
+    ```diff
+    --- a (llama2)
+    +++ b (gpt-4)
+    @@ -1,3 +1,3 @@
+     fn main() {
+    -    fizzbuzz();
+    +    fizz_buzz();
+     }
+    ```
+
+    2. Explain it
+    Answers match.
+
+    3. Add tests for every number from one to a hundred, and for ze...
+    Only in b (gpt-4).
+    "###);
+}
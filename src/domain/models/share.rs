@@ -0,0 +1,298 @@
+#[cfg(test)]
+#[path = "share_test.rs"]
+mod tests;
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::net::TcpListener;
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use super::Author;
+use super::Event;
+use super::Message;
+use super::MessageType;
+
+/// Messages exchanged between the host and guests of a shared session, sent
+/// as one JSON object per line.
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ShareMessage {
+    /// Sent by a guest as soon as it connects, with the token shown to the
+    /// host.
+    Join { username: String, token: String },
+    /// Sent by the host before closing the connection of a guest that didn't
+    /// join with the right token.
+    Refused { reason: String },
+    /// A prompt from a guest, submitted by the host when it's their turn.
+    Prompt { text: String },
+    /// The full conversation, sent by the host when guests join and after
+    /// every change.
+    Sync {
+        messages: Vec<Message>,
+        waiting_for_backend: bool,
+    },
+    /// Text streamed from the model while it's answering.
+    Append { author: Author, text: String },
+}
+
+impl ShareMessage {
+    pub fn encode(&self) -> Result<String> {
+        return Ok(format!("{}\n", serde_json::to_string(self)?));
+    }
+
+    pub fn decode(line: &str) -> Result<ShareMessage> {
+        return Ok(serde_json::from_str(line)?);
+    }
+}
+
+/// Address `/share` listens on when one isn't provided.
+pub const DEFAULT_SHARE_ADDRESS: &str = "127.0.0.1:7878";
+
+/// Hosts the current session for guests connecting over TCP.
+#[derive(Clone)]
+pub struct ShareHost {
+    /// Address the host is listening on.
+    pub addr: String,
+    /// Secret guests join with, so only those the host shares it with can.
+    pub token: String,
+    updates: broadcast::Sender<String>,
+    /// Last encoded `Sync` message, sent to guests as they join.
+    latest: Arc<Mutex<String>>,
+}
+
+impl ShareHost {
+    /// Starts listening for guests. Guest prompts and arrivals are sent to
+    /// the UI as events.
+    pub async fn start(addr: &str, tx: mpsc::UnboundedSender<Event>) -> Result<ShareHost> {
+        let listener = TcpListener::bind(addr).await?;
+        let (updates, _) = broadcast::channel::<String>(1024);
+        let initial = ShareMessage::Sync {
+            messages: vec![],
+            waiting_for_backend: false,
+        };
+
+        let host = ShareHost {
+            addr: listener.local_addr()?.to_string(),
+            token: Uuid::new_v4().simple().to_string(),
+            updates,
+            latest: Arc::new(Mutex::new(initial.encode()?)),
+        };
+
+        let accept_host = host.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let guest_host = accept_host.clone();
+                let guest_tx = tx.clone();
+                tokio::spawn(async move {
+                    if let Err(err) = guest_host.handle_guest(stream, &guest_tx).await {
+                        tracing::debug!(error = ?err, "Shared session guest disconnected");
+                    }
+                });
+            }
+        });
+
+        return Ok(host);
+    }
+
+    /// Whether only this machine can reach the address the host listens on.
+    pub fn is_loopback(&self) -> bool {
+        return self
+            .addr
+            .parse::<SocketAddr>()
+            .map(|e| return e.ip().is_loopback())
+            .unwrap_or(false);
+    }
+
+    async fn handle_guest(
+        &self,
+        stream: TcpStream,
+        tx: &mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+
+        // Guests see and send nothing until they join with the token.
+        let join = lines
+            .next_line()
+            .await?
+            .map(|e| return ShareMessage::decode(&e));
+        let username = match join {
+            Some(Ok(ShareMessage::Join { username, token })) if token == self.token => username,
+            _ => {
+                let refused = ShareMessage::Refused {
+                    reason: "The join token doesn't match the one shown to the host.".to_string(),
+                };
+                writer.write_all(refused.encode()?.as_bytes()).await?;
+                return Ok(());
+            }
+        };
+        tx.send(Event::ShareParticipant(username.to_string(), true))?;
+
+        let mut updates = self.updates.subscribe();
+        let latest = self.latest.lock().unwrap().to_string();
+        writer.write_all(latest.as_bytes()).await?;
+
+        loop {
+            tokio::select! {
+                line = lines.next_line() => {
+                    let line = line?;
+                    if line.is_none() {
+                        break;
+                    }
+
+                    if let Ok(ShareMessage::Prompt { text }) = ShareMessage::decode(&line.unwrap()) {
+                        tx.send(Event::SharedPrompt(username.to_string(), text))?;
+                    }
+                }
+                update = updates.recv() => {
+                    match update {
+                        Ok(update) => {
+                            writer.write_all(update.as_bytes()).await?;
+                        }
+                        // Catch up with the latest state after falling behind.
+                        Err(broadcast::error::RecvError::Lagged(_)) => {
+                            let latest = self.latest.lock().unwrap().to_string();
+                            writer.write_all(latest.as_bytes()).await?;
+                        }
+                        Err(broadcast::error::RecvError::Closed) => {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        tx.send(Event::ShareParticipant(username, false))?;
+
+        return Ok(());
+    }
+
+    fn broadcast(&self, msg: ShareMessage) {
+        match msg.encode() {
+            Ok(line) => {
+                if let ShareMessage::Sync { .. } = msg {
+                    *self.latest.lock().unwrap() = line.to_string();
+                }
+                // Sending only fails when no guests are connected.
+                let _ = self.updates.send(line);
+            }
+            Err(err) => {
+                tracing::error!(error = ?err, "Failed to encode shared session message");
+            }
+        }
+    }
+
+    /// Sends the full conversation to all guests. Messages are stamped with
+    /// the host's names so guests don't display their own.
+    pub fn publish(&self, messages: &[Message], waiting_for_backend: bool) {
+        let messages = messages
+            .iter()
+            .cloned()
+            .map(|mut e| {
+                if e.username().is_none() && e.author != Author::Oatmeal {
+                    e.set_username(&e.author.to_string());
+                }
+                return e;
+            })
+            .collect();
+
+        self.broadcast(ShareMessage::Sync {
+            messages,
+            waiting_for_backend,
+        });
+    }
+
+    /// Sends text streamed from the model to all guests.
+    pub fn publish_append(&self, author: Author, text: &str) {
+        self.broadcast(ShareMessage::Append {
+            author,
+            text: text.to_string(),
+        });
+    }
+}
+
+/// A connection to a session shared by another instance.
+#[derive(Clone)]
+pub struct ShareGuest {
+    /// Address of the host.
+    pub addr: String,
+    prompts: mpsc::UnboundedSender<String>,
+}
+
+impl ShareGuest {
+    /// Connects to a host with the token it showed, forwarding its updates
+    /// to the UI as events.
+    pub async fn connect(
+        addr: &str,
+        token: &str,
+        username: &str,
+        tx: mpsc::UnboundedSender<Event>,
+    ) -> Result<ShareGuest> {
+        let stream = TcpStream::connect(addr).await?;
+        let (reader, mut writer) = stream.into_split();
+        let join = ShareMessage::Join {
+            username: username.to_string(),
+            token: token.to_string(),
+        };
+        writer.write_all(join.encode()?.as_bytes()).await?;
+
+        let (prompts, mut prompts_rx) = mpsc::unbounded_channel::<String>();
+        tokio::spawn(async move {
+            while let Some(line) = prompts_rx.recv().await {
+                if writer.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Ok(msg) = ShareMessage::decode(&line) {
+                    if tx.send(Event::ShareUpdate(msg)).is_err() {
+                        return;
+                    }
+                }
+            }
+
+            let _ = tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                "Disconnected from the shared session.",
+            )));
+        });
+
+        return Ok(ShareGuest {
+            addr: addr.to_string(),
+            prompts,
+        });
+    }
+
+    /// Asks the host to submit a prompt.
+    pub fn send_prompt(&self, text: &str) -> Result<()> {
+        let msg = ShareMessage::Prompt {
+            text: text.to_string(),
+        };
+        self.prompts.send(msg.encode()?)?;
+
+        return Ok(());
+    }
+}
+
+/// Which side of a shared session this instance is on.
+#[derive(Clone)]
+pub enum Share {
+    Host(ShareHost),
+    Guest(ShareGuest),
+}
@@ -0,0 +1,111 @@
+use anyhow::bail;
+use anyhow::Result;
+use tokio::sync::mpsc;
+
+use super::ShareGuest;
+use super::ShareHost;
+use super::ShareMessage;
+use crate::domain::models::Author;
+use crate::domain::models::Event;
+use crate::domain::models::Message;
+
+#[test]
+fn it_encodes_and_decodes() -> Result<()> {
+    let msg = ShareMessage::Append {
+        author: Author::Model,
+        text: "Hello".to_string(),
+    };
+
+    let line = msg.encode()?;
+    assert_eq!(
+        line,
+        "{\"type\":\"append\",\"author\":\"Model\",\"text\":\"Hello\"}\n"
+    );
+
+    match ShareMessage::decode(line.trim())? {
+        ShareMessage::Append { author, text } => {
+            assert_eq!(author, Author::Model);
+            assert_eq!(text, "Hello");
+        }
+        _ => bail!("Wrong enum"),
+    }
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_shares_a_session() -> Result<()> {
+    let (host_tx, mut host_rx) = mpsc::unbounded_channel::<Event>();
+    let (guest_tx, mut guest_rx) = mpsc::unbounded_channel::<Event>();
+
+    let host = ShareHost::start("127.0.0.1:0", host_tx).await?;
+    assert!(host.is_loopback());
+    let guest = ShareGuest::connect(&host.addr, &host.token, "alice", guest_tx).await?;
+
+    match host_rx.recv().await.unwrap() {
+        Event::ShareParticipant(username, joined) => {
+            assert_eq!(username, "alice");
+            assert!(joined);
+        }
+        _ => bail!("Wrong enum"),
+    }
+
+    // The initial state is sent as soon as the guest connects.
+    match guest_rx.recv().await.unwrap() {
+        Event::ShareUpdate(ShareMessage::Sync { messages, .. }) => {
+            assert!(messages.is_empty());
+        }
+        _ => bail!("Wrong enum"),
+    }
+
+    guest.send_prompt("Why is this failing?")?;
+    match host_rx.recv().await.unwrap() {
+        Event::SharedPrompt(username, text) => {
+            assert_eq!(username, "alice");
+            assert_eq!(text, "Why is this failing?");
+        }
+        _ => bail!("Wrong enum"),
+    }
+
+    host.publish(&[Message::new(Author::Oatmeal, "Hello")], true);
+    match guest_rx.recv().await.unwrap() {
+        Event::ShareUpdate(ShareMessage::Sync {
+            messages,
+            waiting_for_backend,
+        }) => {
+            assert_eq!(messages[0].text, "Hello");
+            assert!(waiting_for_backend);
+        }
+        _ => bail!("Wrong enum"),
+    }
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_refuses_guests_with_the_wrong_token() -> Result<()> {
+    let (host_tx, mut host_rx) = mpsc::unbounded_channel::<Event>();
+    let (guest_tx, mut guest_rx) = mpsc::unbounded_channel::<Event>();
+
+    let host = ShareHost::start("127.0.0.1:0", host_tx).await?;
+    let guest = ShareGuest::connect(&host.addr, "guess", "mallory", guest_tx).await?;
+
+    match guest_rx.recv().await.unwrap() {
+        Event::ShareUpdate(ShareMessage::Refused { reason }) => {
+            insta::assert_snapshot!(reason, @"The join token doesn't match the one shown to the host.");
+        }
+        _ => bail!("Wrong enum"),
+    }
+
+    guest.send_prompt("Run this")?;
+    host.publish(&[Message::new(Author::Oatmeal, "Secret")], false);
+    match guest_rx.recv().await.unwrap() {
+        Event::BackendMessage(msg) => {
+            assert_eq!(msg.text, "Disconnected from the shared session.");
+        }
+        _ => bail!("Wrong enum"),
+    }
+    assert!(host_rx.try_recv().is_err());
+
+    return Ok(());
+}
@@ -0,0 +1,41 @@
+#[cfg(test)]
+#[path = "similarity_test.rs"]
+mod tests;
+
+/// A message from the session ranked against a `/similar` query.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimilarMessage {
+    /// Index of the message within the session.
+    pub index: usize,
+    pub score: f32,
+}
+
+fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot = a.iter().zip(b).map(|(x, y)| return x * y).sum::<f32>();
+    let norm_a = a.iter().map(|e| return e * e).sum::<f32>().sqrt();
+    let norm_b = b.iter().map(|e| return e * e).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        return 0.0;
+    }
+
+    return dot / (norm_a * norm_b);
+}
+
+impl SimilarMessage {
+    /// Ranks message embeddings, keyed by message index, by their cosine
+    /// similarity to the query embedding, best match first.
+    pub fn rank(query: &[f32], embeddings: &[(usize, Vec<f32>)]) -> Vec<SimilarMessage> {
+        let mut res = embeddings
+            .iter()
+            .map(|(index, embedding)| {
+                return SimilarMessage {
+                    index: *index,
+                    score: cosine_similarity(query, embedding),
+                };
+            })
+            .collect::<Vec<SimilarMessage>>();
+
+        res.sort_by(|a, b| return b.score.total_cmp(&a.score));
+        return res;
+    }
+}
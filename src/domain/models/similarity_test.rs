@@ -0,0 +1,22 @@
+use super::SimilarMessage;
+
+#[test]
+fn it_ranks_by_cosine_similarity() {
+    let embeddings = vec![
+        (0, vec![0.0, 1.0]),
+        (2, vec![1.0, 0.1]),
+        (3, vec![0.7, 0.7]),
+    ];
+
+    let res = SimilarMessage::rank(&[1.0, 0.0], &embeddings);
+    let indexes = res.iter().map(|e| return e.index).collect::<Vec<usize>>();
+    assert_eq!(indexes, vec![2, 3, 0]);
+    assert!(res[0].score > 0.99);
+    assert_eq!(res[2].score, 0.0);
+}
+
+#[test]
+fn it_scores_empty_embeddings_as_zero() {
+    let res = SimilarMessage::rank(&[0.0, 0.0], &[(1, vec![1.0, 0.0])]);
+    assert_eq!(res[0].score, 0.0);
+}
@@ -2,40 +2,673 @@
 #[path = "slash_commands_test.rs"]
 mod tests;
 
+use std::collections::HashMap;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use super::SamplingParam;
+use super::WebhookName;
+
+/// Full names of every slash command, without their short aliases.
+pub const SLASH_COMMANDS: &[&str] = &[
+    "/ab",
+    "/append",
+    "/apply",
+    "/applyall",
+    "/check",
+    "/checkpoint",
+    "/compare",
+    "/context",
+    "/continue",
+    "/copy",
+    "/diff",
+    "/docs",
+    "/exit",
+    "/fewshot",
+    "/fork",
+    "/gh",
+    "/help",
+    "/image",
+    "/lang",
+    "/man",
+    "/model",
+    "/modellist",
+    "/modelrefresh",
+    "/perms",
+    "/play",
+    "/polish",
+    "/post",
+    "/quit",
+    "/record",
+    "/render",
+    "/replace",
+    "/review",
+    "/rewrites",
+    "/rollback",
+    "/run",
+    "/set",
+    "/share",
+    "/similar",
+    "/stats",
+    "/stop",
+    "/stopmacro",
+    "/sync",
+    "/system",
+    "/tee",
+    "/test",
+    "/ticket",
+    "/todos",
+    "/translate",
+    "/usage",
+    "/withcontext",
+];
+
+struct Token {
+    text: String,
+    quoted: bool,
+    /// Byte offset of the token in the trimmed input.
+    start: usize,
+}
+
+/// Splits a command string in to tokens on whitespace, keeping single or
+/// double quoted sections together. Backslashes escape the following
+/// character. An unterminated quote consumes the rest of the input.
+fn tokenize(text: &str) -> Vec<Token> {
+    let mut tokens: Vec<Token> = vec![];
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut quoted = false;
+    let mut quote: Option<char> = None;
+    let mut start = 0;
+    let mut chars = text.trim().char_indices();
+
+    while let Some((idx, c)) = chars.next() {
+        if !in_token && !c.is_whitespace() {
+            start = idx;
+        }
+
+        if c == '\\' {
+            if let Some((_, escaped)) = chars.next() {
+                current.push(escaped);
+                in_token = true;
+            }
+            continue;
+        }
+
+        if let Some(q) = quote {
+            if c == q {
+                quote = None;
+            } else {
+                current.push(c);
+            }
+            continue;
+        }
+
+        if c == '"' || c == '\'' {
+            quote = Some(c);
+            in_token = true;
+            quoted = true;
+            continue;
+        }
+
+        if c.is_whitespace() {
+            if in_token {
+                tokens.push(Token {
+                    text: current,
+                    quoted,
+                    start,
+                });
+                current = String::new();
+                in_token = false;
+                quoted = false;
+            }
+            continue;
+        }
+
+        current.push(c);
+        in_token = true;
+    }
+
+    if in_token {
+        tokens.push(Token {
+            text: current,
+            quoted,
+            start,
+        });
+    }
+
+    return tokens;
+}
+
+/// Where the result of a command is sent instead of the chat window.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommandOutput {
+    /// `> path` overwrites a file, `>> path` appends to it.
+    File { path: String, append: bool },
+    /// `| clipboard`
+    Clipboard,
+}
+
 pub struct SlashCommand {
     command: String,
+    /// Positional arguments, in the order they were provided.
     pub args: Vec<String>,
+    /// Named values passed as `--key value`, `--key=value`, or `key=value`.
+    pub options: HashMap<String, String>,
+    /// Flags passed as `--flag` without a value.
+    pub flags: Vec<String>,
+    /// Redirect target provided with a trailing `> FILE`, `>> FILE`, or `|
+    /// clipboard`.
+    pub output: Option<CommandOutput>,
+    output_error: Option<String>,
+    text: String,
 }
 
 impl SlashCommand {
     pub fn parse(text: &str) -> Option<SlashCommand> {
-        let mut args = text
-            .trim()
-            .split(' ')
-            .map(|e| return e.to_string())
-            .collect::<Vec<String>>();
-        let prefix = args[0].to_string();
-        args.remove(0);
-
-        let cmd = SlashCommand {
-            command: prefix,
-            args,
+        let text = text.trim();
+        let tokens = tokenize(text);
+        if tokens.is_empty() {
+            return None;
+        }
+
+        let mut cmd = SlashCommand {
+            command: tokens[0].text.to_string(),
+            args: vec![],
+            options: HashMap::new(),
+            flags: vec![],
+            output: None,
+            output_error: None,
+            text: "".to_string(),
         };
-        if cmd.is_quit()
-            || cmd.is_model_list()
-            || cmd.is_model_set()
-            || cmd.is_append_code_block()
-            || cmd.is_replace_code_block()
-            || cmd.is_copy_code_block()
-            || cmd.is_copy_chat()
-            || cmd.is_help()
+
+        if !cmd.is_quit()
+            && !cmd.is_model_list()
+            && !cmd.is_model_refresh()
+            && !cmd.is_rewrites()
+            && !cmd.is_model_set()
+            && !cmd.is_append_code_block()
+            && !cmd.is_replace_code_block()
+            && !cmd.is_copy_code_block()
+            && !cmd.is_copy_chat()
+            && !cmd.is_help()
+            && !cmd.is_lang()
+            && !cmd.is_apply_code_block()
+            && !cmd.is_apply_all()
+            && !cmd.is_sync()
+            && !cmd.is_with_context()
+            && !cmd.is_test()
+            && !cmd.is_check()
+            && !cmd.is_review()
+            && !cmd.is_github()
+            && !cmd.is_ticket()
+            && !cmd.is_man()
+            && !cmd.is_docs()
+            && !cmd.is_translate()
+            && !cmd.is_polish()
+            && !cmd.is_share()
+            && !cmd.is_post()
+            && !cmd.is_perms()
+            && !cmd.is_run()
+            && !cmd.is_render()
+            && !cmd.is_stats()
+            && !cmd.is_similar()
+            && !cmd.is_continue()
+            && !cmd.is_set()
+            && !cmd.is_ab()
+            && !cmd.is_compare()
+            && !cmd.is_diff()
+            && !cmd.is_usage()
+            && !cmd.is_checkpoint()
+            && !cmd.is_rollback()
+            && !cmd.is_record()
+            && !cmd.is_stop_macro()
+            && !cmd.is_stop()
+            && !cmd.is_play()
+            && !cmd.is_few_shot()
+            && !cmd.is_fork()
+            && !cmd.is_tee()
+            && !cmd.is_system()
+            && !cmd.is_context()
+            && !cmd.is_image()
+            && !cmd.is_todos()
+        {
+            return None;
+        }
+
+        let mut iter = tokens.into_iter().skip(1).peekable();
+        while let Some(Token {
+            text: token,
+            quoted,
+            start,
+        }) = iter.next()
         {
-            return Some(cmd);
+            // Everything after the leading flags is a command line or free
+            // text, kept as it was typed.
+            if cmd.takes_text()
+                && token
+                    .strip_prefix("--")
+                    .map_or(true, |name| return !cmd.text_flags().contains(&name))
+            {
+                let rest: Vec<String> = iter.by_ref().map(|e| return e.text).collect();
+                cmd.text = if quoted && rest.is_empty() {
+                    token.to_string()
+                } else {
+                    text[start..].to_string()
+                };
+                cmd.args.push(token);
+                cmd.args.extend(rest);
+                break;
+            }
+
+            if !quoted && [">", ">>", "|"].contains(&token.as_str()) {
+                cmd.parse_output(&token, iter.by_ref().map(|e| return e.text).collect());
+                break;
+            }
+
+            if let Some(name) = token.strip_prefix("--") {
+                if let Some((key, value)) = name.split_once('=') {
+                    cmd.options.insert(key.to_string(), value.to_string());
+                    continue;
+                }
+
+                if cmd.value_options().contains(&name) {
+                    if let Some(next) = iter.next_if(|next| {
+                        return !next.text.starts_with("--")
+                            && (next.quoted || ![">", ">>", "|"].contains(&next.text.as_str()));
+                    }) {
+                        cmd.options.insert(name.to_string(), next.text);
+                        continue;
+                    }
+                }
+
+                cmd.flags.push(name.to_string());
+                continue;
+            }
+
+            if let Some((key, value)) = token.split_once('=') {
+                if !key.is_empty() {
+                    cmd.options.insert(key.to_string(), value.to_string());
+                    continue;
+                }
+            }
+
+            cmd.args.push(token);
+        }
+
+        return Some(cmd);
+    }
+
+    fn parse_output(&mut self, operator: &str, rest: Vec<String>) {
+        if rest.len() != 1 {
+            self.output_error = Some(format!(
+                "Expected a single target after `{operator}`, e.g. `{operator} {}`",
+                if operator == "|" {
+                    "clipboard"
+                } else {
+                    "output.md"
+                }
+            ));
+            return;
+        }
+
+        let target = rest[0].to_string();
+        if operator == "|" {
+            if target != "clipboard" {
+                self.output_error = Some(format!(
+                    "Unsupported output target `{target}`, only `| clipboard` is supported"
+                ));
+                return;
+            }
+            self.output = Some(CommandOutput::Clipboard);
+            return;
+        }
+
+        self.output = Some(CommandOutput::File {
+            path: target,
+            append: operator == ">>",
+        });
+    }
+
+    /// Options the command reads a value for when given as `--name VALUE`. Any
+    /// other `--name` is a flag, even when an argument follows it.
+    fn value_options(&self) -> &'static [&'static str] {
+        if self.is_model_list() {
+            return &["filter", "page"];
+        }
+        if self.is_translate() {
+            return &["last"];
+        }
+        if self.is_ab() {
+            return &["pick"];
+        }
+
+        return &[];
+    }
+
+    /// Commands whose arguments after any leading flags are a command line or
+    /// free text, which is read with `text` rather than as options.
+    fn takes_text(&self) -> bool {
+        return self.is_test()
+            || self.is_check()
+            || self.is_with_context()
+            || self.is_system()
+            || self.is_similar();
+    }
+
+    /// Flags a command that takes text reads before it. Any other `--name` is
+    /// the start of the text.
+    fn text_flags(&self) -> &'static [&'static str] {
+        if self.is_test() {
+            return &["fix"];
+        }
+
+        return &[];
+    }
+
+    /// Commands that produce a result which can be redirected with `>` or `|`.
+    pub fn supports_output(&self) -> bool {
+        return self.is_model_list()
+            || self.is_stats()
+            || self.is_help()
+            || self.is_append_code_block()
+            || self.is_replace_code_block()
+            || self.is_copy_code_block()
+            || self.is_copy_chat()
+            || self.is_review()
+            || self.is_todos();
+    }
+
+    /// Name the command is restricted by in `tool-permissions`, for commands
+    /// that run programs, reach the network, or write files.
+    pub fn tool_name(&self) -> Option<&'static str> {
+        if self.is_apply_code_block() || self.is_apply_all() {
+            return Some("apply");
+        }
+        if self.is_check() {
+            return Some("check");
+        }
+        if self.is_context() && self.args.first().map(|e| return e.as_str()) == Some("add") {
+            return Some("context");
+        }
+        if self.is_docs() {
+            return Some("docs");
+        }
+        if self.is_github() {
+            return Some("gh");
+        }
+        if self.is_man() {
+            return Some("man");
+        }
+        if self.is_post() {
+            return Some("post");
+        }
+        if self.is_review() {
+            return Some("review");
+        }
+        if self.is_run() {
+            return Some("run");
+        }
+        if self.is_render() {
+            return Some("render");
+        }
+        if self.is_test() {
+            return Some("test");
+        }
+        if self.is_ticket() {
+            return Some("ticket");
         }
 
         return None;
     }
 
+    /// Returns the value of a named option, if provided.
+    pub fn option(&self, name: &str) -> Option<&str> {
+        return self.options.get(name).map(|e| return e.as_str());
+    }
+
+    /// Returns everything after the command name and its leading flags as it
+    /// was typed, for commands that take a command line or free text. A single
+    /// quoted argument is returned without its quotes.
+    pub fn text(&self) -> &str {
+        return &self.text;
+    }
+
+    /// Returns true if a valueless `--flag` was provided.
+    pub fn has_flag(&self, name: &str) -> bool {
+        return self.flags.iter().any(|e| return e == name);
+    }
+
+    /// Usage string displayed to the user when a command is called with
+    /// invalid arguments.
+    pub fn usage(&self) -> &'static str {
+        if self.is_quit() {
+            return "/quit";
+        }
+        if self.is_model_list() {
+            return "/modellist [--filter TEXT] [--page N]";
+        }
+        if self.is_model_refresh() {
+            return "/modelrefresh";
+        }
+        if self.is_rewrites() {
+            return "/rewrites";
+        }
+        if self.is_model_set() {
+            return "/model [MODEL_NAME,MODEL_INDEX]";
+        }
+        if self.is_append_code_block() {
+            return "/append [CODE_BLOCK_NUMBER?] [--all]";
+        }
+        if self.is_replace_code_block() {
+            return "/replace [CODE_BLOCK_NUMBER?] [--all] [--force]";
+        }
+        if self.is_copy_chat() || self.is_copy_code_block() {
+            return "/copy [CODE_BLOCK_NUMBER?] [--all]";
+        }
+        if self.is_stats() {
+            return "/stats";
+        }
+        if self.is_similar() {
+            return "/similar [TEXT]";
+        }
+        if self.is_continue() {
+            return "/continue";
+        }
+        if self.is_ab() {
+            return "/ab [VARIANT_ONE] [VARIANT_TWO] [--pick 1,2]";
+        }
+        if self.is_compare() {
+            return "/compare [MODEL_ONE] [MODEL_TWO] [PROMPT]";
+        }
+        if self.is_diff() {
+            return "/diff [SESSION_ID]";
+        }
+        if self.is_usage() {
+            return "/usage";
+        }
+        if self.is_checkpoint() {
+            return "/checkpoint [NAME]";
+        }
+        if self.is_rollback() {
+            return "/rollback [NAME]";
+        }
+        if self.is_record() {
+            return "/record [NAME]";
+        }
+        if self.is_stop_macro() {
+            return "/stopmacro";
+        }
+        if self.is_stop() {
+            return "/stop";
+        }
+        if self.is_play() {
+            return "/play [NAME] [ARGS?]";
+        }
+        if self.is_few_shot() {
+            return "/fewshot [FILE?]";
+        }
+        if self.is_image() {
+            return "/image [FILE?]";
+        }
+        if self.is_todos() {
+            return "/todos";
+        }
+        if self.is_tee() {
+            return "/tee [FILE?]";
+        }
+        if self.is_system() {
+            return "/system [PROMPT,PRESET,default?]";
+        }
+        if self.is_context() {
+            return "/context add [FILE_OR_URL], /context list, or /context rm [NUMBER]";
+        }
+        if self.is_fork() {
+            return "/fork";
+        }
+        if self.is_set() {
+            return "/set seed [NUMBER,random], /set [temperature,top_p,max_tokens,stop] [VALUE,default] or /set var [NAME=VALUE?]";
+        }
+        if self.is_help() {
+            return "/help";
+        }
+        if self.is_lang() {
+            return "/lang [CODE_BLOCK_NUMBER] [LANGUAGE]";
+        }
+        if self.is_apply_code_block() {
+            return "/apply [CODE_BLOCK_NUMBER?] [--all]";
+        }
+        if self.is_apply_all() {
+            return "/applyall [--yes]";
+        }
+        if self.is_sync() {
+            return "/sync";
+        }
+        if self.is_with_context() {
+            return "/withcontext [QUESTION]";
+        }
+        if self.is_test() {
+            return "/test [--fix] [COMMAND?]";
+        }
+        if self.is_check() {
+            return "/check [COMMAND?]";
+        }
+        if self.is_run() {
+            return "/run [CODE_BLOCK_NUMBER?] [--allow]";
+        }
+        if self.is_render() {
+            return "/render [CODE_BLOCK_NUMBER?]";
+        }
+        if self.is_github() {
+            return "/gh [ISSUE_OR_PR_URL]";
+        }
+        if self.is_ticket() {
+            return "/ticket [TICKET_KEY]";
+        }
+        if self.is_man() {
+            return "/man [SECTION?] [COMMAND]";
+        }
+        if self.is_docs() {
+            return "/docs [CRATE::PATH]";
+        }
+        if self.is_translate() {
+            return "/translate [LANGUAGE] [--last N]";
+        }
+        if self.is_polish() {
+            return "/polish [grammar,terse,formal?] [DRAFT]";
+        }
+        if self.is_share() {
+            return "/share [ADDRESS?]";
+        }
+        if self.is_post() {
+            return "/post [slack,discord] [--all]";
+        }
+        if self.is_perms() {
+            return "/perms";
+        }
+        if self.is_review() {
+            return "/review [rdjson,github] [PR_NUMBER?]";
+        }
+
+        return "";
+    }
+
+    /// Validates the provided arguments against what the command supports.
+    pub fn validate(&self) -> Result<()> {
+        let expects_no_args = self.is_quit()
+            || self.is_model_list()
+            || self.is_model_refresh()
+            || self.is_rewrites()
+            || self.is_stats()
+            || self.is_usage()
+            || self.is_continue()
+            || self.is_help()
+            || self.is_apply_all()
+            || self.is_stop_macro()
+            || self.is_stop()
+            || self.is_sync()
+            || self.is_fork()
+            || self.is_perms();
+        let is_invalid = (expects_no_args && !self.args.is_empty())
+            || (self.is_model_set() && self.args.len() != 1)
+            || (self.is_lang() && self.args.len() != 2)
+            || (self.is_github() && self.args.len() != 1)
+            || (self.is_ticket() && self.args.len() != 1)
+            || (self.is_man() && !(1..=2).contains(&self.args.len()))
+            || (self.is_docs() && self.args.len() != 1)
+            || (self.is_diff() && self.args.len() != 1)
+            || (self.is_checkpoint() && self.args.len() != 1)
+            || (self.is_rollback() && self.args.len() != 1)
+            || (self.is_record() && self.args.len() != 1)
+            || (self.is_play() && self.args.is_empty())
+            || (self.is_few_shot() && self.args.len() > 1)
+            || (self.is_image() && self.args.len() > 1)
+            || (self.is_todos() && !self.args.is_empty())
+            || (self.is_tee() && self.args.len() > 1)
+            || (self.is_run() && self.args.len() > 1)
+            || (self.is_render() && self.args.len() > 1)
+            || (self.is_similar() && self.args.is_empty())
+            || (self.is_with_context() && self.args.is_empty())
+            || (self.is_set() && !self.is_valid_set_args())
+            || (self.is_ab() && !self.is_valid_ab_args())
+            || (self.is_compare() && self.args.len() < 3)
+            || (self.is_share() && self.args.len() > 1)
+            || (self.is_post()
+                && (self.args.len() != 1
+                    || WebhookName::parse(self.args[0].to_string()).is_none()))
+            || (self.is_translate()
+                && (self.args.is_empty()
+                    || self
+                        .option("last")
+                        .map(|e| return e.parse::<usize>().map(|e| return e == 0).unwrap_or(true))
+                        .unwrap_or(false)))
+            || (self.is_review() && !self.is_valid_review_args())
+            || (self.is_context() && !self.is_valid_context_args())
+            || (self.is_model_list()
+                && self
+                    .option("page")
+                    .map(|e| return e.parse::<usize>().map(|e| return e == 0).unwrap_or(true))
+                    .unwrap_or(false));
+
+        if is_invalid {
+            bail!(format!("Usage: {}", self.usage()));
+        }
+
+        if let Some(err) = &self.output_error {
+            bail!(err.to_string());
+        }
+
+        if self.output.is_some() && !self.supports_output() {
+            bail!(format!(
+                "{} does not support sending its output to a file or clipboard",
+                self.command
+            ));
+        }
+
+        return Ok(());
+    }
+
     pub fn is_quit(&self) -> bool {
         return ["/q", "/quit", "/exit"].contains(&self.command.as_str());
     }
@@ -44,6 +677,14 @@ impl SlashCommand {
         return ["/ml", "/modellist", "/modelist"].contains(&self.command.as_str());
     }
 
+    pub fn is_model_refresh(&self) -> bool {
+        return ["/modelrefresh"].contains(&self.command.as_str());
+    }
+
+    pub fn is_rewrites(&self) -> bool {
+        return ["/rewrites"].contains(&self.command.as_str());
+    }
+
     pub fn is_model_set(&self) -> bool {
         return ["/m", "/model"].contains(&self.command.as_str());
     }
@@ -56,15 +697,268 @@ impl SlashCommand {
         return ["/r", "/replace"].contains(&self.command.as_str());
     }
 
+    pub fn is_apply_code_block(&self) -> bool {
+        return ["/apply"].contains(&self.command.as_str());
+    }
+
+    pub fn is_apply_all(&self) -> bool {
+        return ["/applyall"].contains(&self.command.as_str());
+    }
+
     pub fn is_copy_code_block(&self) -> bool {
-        return ["/c", "/copy"].contains(&self.command.as_str()) && !self.args.is_empty();
+        return ["/c", "/copy"].contains(&self.command.as_str())
+            && (!self.args.is_empty() || self.has_flag("all"));
     }
 
     pub fn is_copy_chat(&self) -> bool {
-        return ["/c", "/copy"].contains(&self.command.as_str()) && self.args.is_empty();
+        return ["/c", "/copy"].contains(&self.command.as_str()) && !self.is_copy_code_block();
     }
 
     pub fn is_help(&self) -> bool {
         return ["/h", "/help"].contains(&self.command.as_str());
     }
+
+    pub fn is_sync(&self) -> bool {
+        return ["/sync"].contains(&self.command.as_str());
+    }
+
+    pub fn is_with_context(&self) -> bool {
+        return ["/wc", "/withcontext"].contains(&self.command.as_str());
+    }
+
+    pub fn is_test(&self) -> bool {
+        return ["/t", "/test"].contains(&self.command.as_str());
+    }
+
+    pub fn is_run(&self) -> bool {
+        return ["/run"].contains(&self.command.as_str());
+    }
+
+    pub fn is_render(&self) -> bool {
+        return ["/render"].contains(&self.command.as_str());
+    }
+
+    pub fn is_check(&self) -> bool {
+        return ["/check"].contains(&self.command.as_str());
+    }
+
+    fn is_valid_review_args(&self) -> bool {
+        return match self
+            .args
+            .iter()
+            .map(|e| return e.as_str())
+            .collect::<Vec<&str>>()[..]
+        {
+            ["rdjson"] => true,
+            ["github", pr] => pr.parse::<usize>().is_ok(),
+            _ => false,
+        };
+    }
+
+    fn is_valid_context_args(&self) -> bool {
+        return match self
+            .args
+            .iter()
+            .map(|e| return e.as_str())
+            .collect::<Vec<&str>>()[..]
+        {
+            ["add", _] | ["list"] => true,
+            ["rm", number] => {
+                number
+                    .parse::<usize>()
+                    .map(|e| return e > 0)
+                    .unwrap_or(false)
+            }
+            _ => false,
+        };
+    }
+
+    fn is_valid_ab_args(&self) -> bool {
+        if let Some(pick) = self.option("pick") {
+            return self.args.is_empty() && ["1", "2"].contains(&pick);
+        }
+        return self.args.len() == 2;
+    }
+
+    fn is_valid_set_args(&self) -> bool {
+        return match self
+            .args
+            .iter()
+            .map(|e| return e.as_str())
+            .collect::<Vec<&str>>()[..]
+        {
+            ["seed", "random"] => true,
+            ["seed", seed] => seed.parse::<u64>().is_ok(),
+            ["var"] => self.options.len() <= 1,
+            ["var", ..] => self.options.len() == 1,
+            [param, value] => {
+                match SamplingParam::parse(param) {
+                    Some(param) => value == "default" || param.validate(value).is_ok(),
+                    None => false,
+                }
+            }
+            _ => false,
+        };
+    }
+
+    pub fn is_github(&self) -> bool {
+        return ["/gh"].contains(&self.command.as_str());
+    }
+
+    pub fn is_ticket(&self) -> bool {
+        return ["/ticket"].contains(&self.command.as_str());
+    }
+
+    pub fn is_man(&self) -> bool {
+        return ["/man"].contains(&self.command.as_str());
+    }
+
+    pub fn is_docs(&self) -> bool {
+        return ["/docs"].contains(&self.command.as_str());
+    }
+
+    pub fn is_translate(&self) -> bool {
+        return ["/translate"].contains(&self.command.as_str());
+    }
+
+    pub fn is_polish(&self) -> bool {
+        return ["/polish"].contains(&self.command.as_str());
+    }
+
+    pub fn is_share(&self) -> bool {
+        return ["/share"].contains(&self.command.as_str());
+    }
+
+    pub fn is_post(&self) -> bool {
+        return ["/post"].contains(&self.command.as_str());
+    }
+
+    pub fn is_stats(&self) -> bool {
+        return ["/stats"].contains(&self.command.as_str());
+    }
+
+    pub fn is_continue(&self) -> bool {
+        return ["/continue"].contains(&self.command.as_str());
+    }
+
+    pub fn is_ab(&self) -> bool {
+        return ["/ab"].contains(&self.command.as_str());
+    }
+
+    pub fn is_compare(&self) -> bool {
+        return ["/compare"].contains(&self.command.as_str());
+    }
+
+    pub fn is_diff(&self) -> bool {
+        return ["/diff"].contains(&self.command.as_str());
+    }
+
+    pub fn is_usage(&self) -> bool {
+        return ["/usage"].contains(&self.command.as_str());
+    }
+
+    pub fn is_checkpoint(&self) -> bool {
+        return ["/checkpoint"].contains(&self.command.as_str());
+    }
+
+    pub fn is_rollback(&self) -> bool {
+        return ["/rollback"].contains(&self.command.as_str());
+    }
+
+    pub fn is_record(&self) -> bool {
+        return ["/record"].contains(&self.command.as_str());
+    }
+
+    pub fn is_stop(&self) -> bool {
+        return ["/stop"].contains(&self.command.as_str());
+    }
+
+    pub fn is_stop_macro(&self) -> bool {
+        return ["/stopmacro"].contains(&self.command.as_str());
+    }
+
+    pub fn is_play(&self) -> bool {
+        return ["/play"].contains(&self.command.as_str());
+    }
+
+    pub fn is_few_shot(&self) -> bool {
+        return ["/fewshot"].contains(&self.command.as_str());
+    }
+
+    pub fn is_image(&self) -> bool {
+        return ["/image"].contains(&self.command.as_str());
+    }
+
+    pub fn is_todos(&self) -> bool {
+        return ["/todos"].contains(&self.command.as_str());
+    }
+
+    pub fn is_fork(&self) -> bool {
+        return ["/fork"].contains(&self.command.as_str());
+    }
+
+    pub fn is_tee(&self) -> bool {
+        return ["/tee"].contains(&self.command.as_str());
+    }
+
+    pub fn is_system(&self) -> bool {
+        return ["/system"].contains(&self.command.as_str());
+    }
+
+    pub fn is_context(&self) -> bool {
+        return ["/context"].contains(&self.command.as_str());
+    }
+
+    pub fn is_set(&self) -> bool {
+        return ["/set"].contains(&self.command.as_str());
+    }
+
+    pub fn is_set_var(&self) -> bool {
+        return self.is_set()
+            && self
+                .args
+                .first()
+                .map(|e| return e == "var")
+                .unwrap_or(false);
+    }
+
+    /// The sampling parameter `/set` changes, and its value, or `None` for
+    /// the backend's default.
+    pub fn set_sampling(&self) -> Option<(SamplingParam, Option<String>)> {
+        if !self.is_set() || self.args.len() != 2 {
+            return None;
+        }
+
+        let param = SamplingParam::parse(&self.args[0])?;
+        let value = Some(self.args[1].to_string()).filter(|e| return e != "default");
+        return Some((param, value));
+    }
+
+    /// `NAME=VALUE` from `/set var`, empty when listing the variables. The
+    /// value may span several words, which are parsed as args.
+    pub fn set_var_assignment(&self) -> String {
+        return match self.options.iter().next() {
+            Some((name, value)) => {
+                let words = [&[value.to_string()], &self.args[1..]].concat();
+                format!("{name}={}", words.join(" ").trim())
+            }
+            None => "".to_string(),
+        };
+    }
+
+    pub fn is_similar(&self) -> bool {
+        return ["/similar"].contains(&self.command.as_str());
+    }
+
+    pub fn is_perms(&self) -> bool {
+        return ["/perms"].contains(&self.command.as_str());
+    }
+
+    pub fn is_review(&self) -> bool {
+        return ["/review"].contains(&self.command.as_str());
+    }
+
+    pub fn is_lang(&self) -> bool {
+        return ["/l", "/lang"].contains(&self.command.as_str());
+    }
 }
@@ -1,4 +1,7 @@
+use super::CommandOutput;
+use super::SamplingParam;
 use super::SlashCommand;
+use super::SLASH_COMMANDS;
 
 #[test]
 fn it_parse_empty_string() {
@@ -165,3 +168,557 @@ fn it_is_not_copy_code() {
     let cmd = SlashCommand::parse("/copy").unwrap();
     assert!(!cmd.is_copy_code_block());
 }
+
+#[test]
+fn it_parses_positional_args() {
+    let cmd = SlashCommand::parse("/copy 1 2..3").unwrap();
+    assert_eq!(cmd.args, vec!["1".to_string(), "2..3".to_string()]);
+}
+
+#[test]
+fn it_parses_quoted_args() {
+    let cmd = SlashCommand::parse(r#"/model "my model" 'single quoted'"#).unwrap();
+    assert_eq!(
+        cmd.args,
+        vec!["my model".to_string(), "single quoted".to_string()]
+    );
+}
+
+#[test]
+fn it_parses_escaped_quotes() {
+    let cmd = SlashCommand::parse(r#"/model "say \"hi\"""#).unwrap();
+    assert_eq!(cmd.args, vec![r#"say "hi""#.to_string()]);
+}
+
+#[test]
+fn it_parses_unterminated_quotes() {
+    let cmd = SlashCommand::parse(r#"/model "my model"#).unwrap();
+    assert_eq!(cmd.args, vec!["my model".to_string()]);
+}
+
+#[test]
+fn it_parses_flag_with_value() {
+    let cmd = SlashCommand::parse(r#"/translate --last 2 "Brazilian Portuguese""#).unwrap();
+    assert_eq!(cmd.option("last"), Some("2"));
+    assert_eq!(cmd.args, vec!["Brazilian Portuguese".to_string()]);
+}
+
+#[test]
+fn it_does_not_take_values_for_boolean_flags() {
+    let cmd = SlashCommand::parse("/run --allow 2").unwrap();
+    assert!(cmd.has_flag("allow"));
+    assert_eq!(cmd.args, vec!["2".to_string()]);
+    assert!(cmd.options.is_empty());
+
+    let cmd = SlashCommand::parse("/replace --force 1").unwrap();
+    assert!(cmd.has_flag("force"));
+    assert_eq!(cmd.args, vec!["1".to_string()]);
+
+    let cmd = SlashCommand::parse("/test --fix cargo test").unwrap();
+    assert!(cmd.has_flag("fix"));
+    assert_eq!(cmd.args, vec!["cargo".to_string(), "test".to_string()]);
+}
+
+#[test]
+fn it_keeps_command_lines_as_typed() {
+    let cmd =
+        SlashCommand::parse(r#"/test --fix cargo test --release -- "a b" 2>&1 | tee log"#).unwrap();
+    assert!(cmd.has_flag("fix"));
+    assert_eq!(
+        cmd.text(),
+        r#"cargo test --release -- "a b" 2>&1 | tee log"#
+    );
+    assert!(cmd.options.is_empty());
+    assert_eq!(cmd.output, None);
+
+    let cmd = SlashCommand::parse(r#"/test "make test""#).unwrap();
+    assert_eq!(cmd.text(), "make test");
+
+    let cmd = SlashCommand::parse("/test --fix").unwrap();
+    assert_eq!(cmd.text(), "");
+}
+
+#[test]
+fn it_parses_flag_with_equals() {
+    let cmd = SlashCommand::parse("/copy --format=md").unwrap();
+    assert_eq!(cmd.option("format"), Some("md"));
+    assert!(cmd.args.is_empty());
+}
+
+#[test]
+fn it_parses_boolean_flags() {
+    let cmd = SlashCommand::parse("/copy --all --force").unwrap();
+    assert!(cmd.has_flag("all"));
+    assert!(cmd.has_flag("force"));
+    assert!(!cmd.has_flag("other"));
+    assert!(cmd.options.is_empty());
+}
+
+#[test]
+fn it_parses_key_value_pairs() {
+    let cmd = SlashCommand::parse(r#"/copy lang=rust title="a b""#).unwrap();
+    assert_eq!(cmd.option("lang"), Some("rust"));
+    assert_eq!(cmd.option("title"), Some("a b"));
+    assert!(cmd.args.is_empty());
+}
+
+#[test]
+fn it_ignores_repeated_whitespace() {
+    let cmd = SlashCommand::parse("  /model    llama2  ").unwrap();
+    assert!(cmd.is_model_set());
+    assert_eq!(cmd.args, vec!["llama2".to_string()]);
+}
+
+#[test]
+fn it_validates_model_set_args() {
+    let cmd = SlashCommand::parse("/model").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /model [MODEL_NAME,MODEL_INDEX]");
+
+    let cmd = SlashCommand::parse("/model llama2").unwrap();
+    assert!(cmd.validate().is_ok());
+}
+
+#[test]
+fn it_validates_commands_without_args() {
+    let cmd = SlashCommand::parse("/help me").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /help");
+
+    let cmd = SlashCommand::parse("/help").unwrap();
+    assert!(cmd.validate().is_ok());
+}
+
+#[test]
+fn it_is_copy_code_with_all_flag() {
+    let cmd = SlashCommand::parse("/copy --all").unwrap();
+    assert!(cmd.is_copy_code_block());
+    assert!(!cmd.is_copy_chat());
+}
+
+#[test]
+fn it_parses_file_output() {
+    let cmd = SlashCommand::parse("/copy 2 > main.rs").unwrap();
+    assert_eq!(cmd.args, vec!["2".to_string()]);
+    assert_eq!(
+        cmd.output,
+        Some(CommandOutput::File {
+            path: "main.rs".to_string(),
+            append: false
+        })
+    );
+    assert!(cmd.validate().is_ok());
+}
+
+#[test]
+fn it_parses_appending_file_output() {
+    let cmd = SlashCommand::parse(r#"/help >> "my notes.md""#).unwrap();
+    assert!(cmd.args.is_empty());
+    assert_eq!(
+        cmd.output,
+        Some(CommandOutput::File {
+            path: "my notes.md".to_string(),
+            append: true
+        })
+    );
+    assert!(cmd.validate().is_ok());
+}
+
+#[test]
+fn it_parses_clipboard_output() {
+    let cmd = SlashCommand::parse("/modellist --filter llama | clipboard").unwrap();
+    assert_eq!(cmd.option("filter"), Some("llama"));
+    assert_eq!(cmd.output, Some(CommandOutput::Clipboard));
+    assert!(cmd.validate().is_ok());
+}
+
+#[test]
+fn it_ignores_quoted_output_operators() {
+    let cmd = SlashCommand::parse(r#"/model ">""#).unwrap();
+    assert_eq!(cmd.args, vec![">".to_string()]);
+    assert!(cmd.output.is_none());
+}
+
+#[test]
+fn it_validates_missing_output_target() {
+    let cmd = SlashCommand::parse("/copy 1 >").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Expected a single target after `>`, e.g. `> output.md`");
+}
+
+#[test]
+fn it_validates_unsupported_output_target() {
+    let cmd = SlashCommand::parse("/copy 1 | less").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Unsupported output target `less`, only `| clipboard` is supported");
+}
+
+#[test]
+fn it_validates_commands_without_output_support() {
+    let cmd = SlashCommand::parse("/quit > out.txt").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"/quit does not support sending its output to a file or clipboard");
+}
+
+#[test]
+fn it_is_lang() {
+    let cmd = SlashCommand::parse("/lang 2 rust").unwrap();
+    assert!(cmd.is_lang());
+    assert_eq!(cmd.args, vec!["2".to_string(), "rust".to_string()]);
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/l 2").unwrap();
+    assert!(cmd.is_lang());
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /lang [CODE_BLOCK_NUMBER] [LANGUAGE]");
+}
+
+#[test]
+fn it_is_stats() {
+    let cmd = SlashCommand::parse("/stats").unwrap();
+    assert!(cmd.is_stats());
+    assert!(cmd.supports_output());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/stats llama2").unwrap();
+    assert!(cmd.validate().is_err());
+}
+
+#[test]
+fn it_is_continue() {
+    let cmd = SlashCommand::parse("/continue").unwrap();
+    assert!(cmd.is_continue());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/continue please").unwrap();
+    assert!(cmd.validate().is_err());
+}
+
+#[test]
+fn it_is_similar() {
+    let cmd = SlashCommand::parse("/similar retry logic").unwrap();
+    assert!(cmd.is_similar());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/similar").unwrap();
+    assert!(cmd.validate().is_err());
+}
+
+#[test]
+fn it_is_with_context() {
+    let cmd = SlashCommand::parse("/withcontext why does this panic?").unwrap();
+    assert!(cmd.is_with_context());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/wc why does this panic?").unwrap();
+    assert!(cmd.is_with_context());
+
+    let cmd = SlashCommand::parse("/withcontext").unwrap();
+    assert!(cmd.validate().is_err());
+}
+
+#[test]
+fn it_is_post() {
+    let cmd = SlashCommand::parse("/post slack --all").unwrap();
+    assert!(cmd.is_post());
+    assert!(cmd.has_flag("all"));
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/post teams").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /post [slack,discord] [--all]");
+}
+
+#[test]
+fn it_is_set() {
+    let cmd = SlashCommand::parse("/set seed 42").unwrap();
+    assert!(cmd.is_set());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/set seed random").unwrap();
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/set seed -1").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /set seed [NUMBER,random], /set [temperature,top_p,max_tokens,stop] [VALUE,default] or /set var [NAME=VALUE?]");
+
+    let cmd = SlashCommand::parse("/set colour 1").unwrap();
+    assert!(cmd.validate().is_err());
+}
+
+#[test]
+fn it_validates_set_sampling_commands() {
+    let cmd = SlashCommand::parse("/set temperature 0.2").unwrap();
+    assert!(cmd.validate().is_ok());
+    assert_eq!(
+        cmd.set_sampling(),
+        Some((SamplingParam::Temperature, Some("0.2".to_string())))
+    );
+
+    let cmd = SlashCommand::parse("/set max_tokens default").unwrap();
+    assert!(cmd.validate().is_ok());
+    assert_eq!(cmd.set_sampling(), Some((SamplingParam::MaxTokens, None)));
+
+    assert!(SlashCommand::parse("/set temperature 3")
+        .unwrap()
+        .validate()
+        .is_err());
+    assert!(SlashCommand::parse("/set top_p 0")
+        .unwrap()
+        .validate()
+        .is_err());
+    assert!(SlashCommand::parse("/set seed 42")
+        .unwrap()
+        .set_sampling()
+        .is_none());
+
+    assert!(SlashCommand::parse("/set system_prompt Answer in French")
+        .unwrap()
+        .validate()
+        .is_err());
+    assert!(SlashCommand::parse("/set stop END ###")
+        .unwrap()
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn it_validates_set_var_commands() {
+    let cmd = SlashCommand::parse("/set var lang=Rust 2021").unwrap();
+    assert!(cmd.is_set_var());
+    assert!(cmd.validate().is_ok());
+    assert_eq!(cmd.set_var_assignment(), "lang=Rust 2021");
+
+    let cmd = SlashCommand::parse("/set var").unwrap();
+    assert!(cmd.is_set_var());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/set var lang").unwrap();
+    assert!(cmd.validate().is_err());
+
+    let cmd = SlashCommand::parse("/set seed 1").unwrap();
+    assert!(!cmd.is_set_var());
+}
+
+#[test]
+fn it_is_ab() {
+    let cmd = SlashCommand::parse(r#"/ab "Explain briefly" "Explain in depth""#).unwrap();
+    assert!(cmd.is_ab());
+    assert_eq!(cmd.args, vec!["Explain briefly", "Explain in depth"]);
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/ab --pick 2").unwrap();
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/ab --pick 3").unwrap();
+    assert!(cmd.validate().is_err());
+
+    let cmd = SlashCommand::parse(r#"/ab "Only one""#).unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /ab [VARIANT_ONE] [VARIANT_TWO] [--pick 1,2]");
+}
+
+#[test]
+fn it_is_compare() {
+    let cmd = SlashCommand::parse("/compare llama3 openai:gpt-4o Explain borrowing").unwrap();
+    assert!(cmd.is_compare());
+    assert_eq!(
+        cmd.args,
+        vec!["llama3", "openai:gpt-4o", "Explain", "borrowing"]
+    );
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/compare llama3 mistral").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /compare [MODEL_ONE] [MODEL_TWO] [PROMPT]");
+}
+
+#[test]
+fn it_is_diff() {
+    let cmd = SlashCommand::parse("/diff 1a2b3c4d-5e6f").unwrap();
+    assert!(cmd.is_diff());
+    assert_eq!(cmd.args, vec!["1a2b3c4d-5e6f"]);
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/diff").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /diff [SESSION_ID]");
+}
+
+#[test]
+fn it_is_checkpoint_and_rollback() {
+    let cmd = SlashCommand::parse("/checkpoint before-refactor").unwrap();
+    assert!(cmd.is_checkpoint());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/rollback before-refactor").unwrap();
+    assert!(cmd.is_rollback());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/checkpoint").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /checkpoint [NAME]");
+
+    let cmd = SlashCommand::parse("/rollback one two").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /rollback [NAME]");
+}
+
+#[test]
+fn it_is_usage() {
+    let cmd = SlashCommand::parse("/usage").unwrap();
+    assert!(cmd.is_usage());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/usage on").unwrap();
+    assert!(cmd.validate().is_err());
+}
+
+#[test]
+fn it_validates_model_list_pages() {
+    let cmd = SlashCommand::parse("/modellist --filter llama --page 2").unwrap();
+    assert_eq!(cmd.option("page"), Some("2"));
+    assert!(cmd.validate().is_ok());
+
+    for text in ["/modellist --page 0", "/modellist --page next"] {
+        let cmd = SlashCommand::parse(text).unwrap();
+        assert_eq!(
+            cmd.validate().unwrap_err().to_string(),
+            "Usage: /modellist [--filter TEXT] [--page N]"
+        );
+    }
+}
+
+#[test]
+fn it_parses_every_listed_command() {
+    for command in SLASH_COMMANDS {
+        assert!(SlashCommand::parse(command).is_some(), "{command}");
+    }
+}
+
+#[test]
+fn it_validates_macro_commands() {
+    assert!(SlashCommand::parse("/record").unwrap().validate().is_err());
+    assert!(SlashCommand::parse("/record review")
+        .unwrap()
+        .validate()
+        .is_ok());
+    assert!(SlashCommand::parse("/stopmacro now")
+        .unwrap()
+        .validate()
+        .is_err());
+    assert!(SlashCommand::parse("/play").unwrap().validate().is_err());
+
+    let cmd = SlashCommand::parse("/play review 42 --fast").unwrap();
+    assert!(cmd.validate().is_ok());
+    assert_eq!(cmd.args, vec!["review", "42"]);
+}
+
+#[test]
+fn it_validates_render_commands() {
+    let cmd = SlashCommand::parse("/render 2").unwrap();
+    assert!(cmd.is_render());
+    assert_eq!(cmd.tool_name(), Some("render"));
+    assert!(cmd.validate().is_ok());
+    assert!(SlashCommand::parse("/render 1 2")
+        .unwrap()
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn it_validates_stop_commands() {
+    let cmd = SlashCommand::parse("/stop").unwrap();
+    assert!(cmd.is_stop());
+    assert!(!cmd.is_stop_macro());
+    assert!(cmd.validate().is_ok());
+    assert!(SlashCommand::parse("/stop now")
+        .unwrap()
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn it_validates_tee_commands() {
+    let cmd = SlashCommand::parse("/tee chat.md").unwrap();
+    assert!(cmd.is_tee());
+    assert!(cmd.validate().is_ok());
+    assert!(SlashCommand::parse("/tee").unwrap().validate().is_ok());
+    assert!(SlashCommand::parse("/tee a.md b.md")
+        .unwrap()
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn it_validates_few_shot_commands() {
+    assert!(SlashCommand::parse("/fewshot").unwrap().validate().is_ok());
+    assert!(SlashCommand::parse("/fewshot examples.jsonl")
+        .unwrap()
+        .validate()
+        .is_ok());
+    assert!(SlashCommand::parse("/fewshot one.jsonl two.jsonl")
+        .unwrap()
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn it_validates_image_commands() {
+    assert!(SlashCommand::parse("/image").unwrap().validate().is_ok());
+    assert!(SlashCommand::parse("/image \"my screenshot.png\"")
+        .unwrap()
+        .validate()
+        .is_ok());
+    assert!(SlashCommand::parse("/image one.png two.png")
+        .unwrap()
+        .validate()
+        .is_err());
+}
+
+#[test]
+fn it_validates_model_refresh_commands() {
+    let cmd = SlashCommand::parse("/modelrefresh").unwrap();
+    assert!(cmd.is_model_refresh());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/modelrefresh ollama").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /modelrefresh");
+}
+
+#[test]
+fn it_validates_rewrites_commands() {
+    let cmd = SlashCommand::parse("/rewrites").unwrap();
+    assert!(cmd.is_rewrites());
+    assert!(cmd.validate().is_ok());
+
+    let cmd = SlashCommand::parse("/rewrites clear").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /rewrites");
+}
+
+#[test]
+fn it_validates_todos_commands() {
+    let cmd = SlashCommand::parse("/todos > todos.md").unwrap();
+    assert!(cmd.is_todos());
+    assert!(cmd.supports_output());
+    assert!(cmd.validate().is_ok());
+    assert!(SlashCommand::parse("/todos | clipboard")
+        .unwrap()
+        .validate()
+        .is_ok());
+
+    let cmd = SlashCommand::parse("/todos everything").unwrap();
+    insta::assert_snapshot!(cmd.validate().unwrap_err().to_string(), @"Usage: /todos");
+}
+
+#[test]
+fn it_validates_context_commands() {
+    let cmd = SlashCommand::parse("/context add https://example.com/spec").unwrap();
+    assert!(cmd.is_context());
+    assert!(cmd.validate().is_ok());
+    assert_eq!(cmd.tool_name(), Some("context"));
+
+    let cmd = SlashCommand::parse("/context rm 2").unwrap();
+    assert!(cmd.validate().is_ok());
+    assert_eq!(cmd.tool_name(), None);
+
+    assert!(SlashCommand::parse("/context list")
+        .unwrap()
+        .validate()
+        .is_ok());
+    for invalid in [
+        "/context",
+        "/context add",
+        "/context rm 0",
+        "/context rm README.md",
+    ] {
+        assert!(SlashCommand::parse(invalid).unwrap().validate().is_err());
+    }
+}
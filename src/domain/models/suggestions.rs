@@ -0,0 +1,115 @@
+#[cfg(test)]
+#[path = "suggestions_test.rs"]
+mod tests;
+
+/// Most follow-ups suggested after an answer.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Only the end of long answers is sent, keeping the request cheap.
+const MAX_ANSWER_CHARS: usize = 4000;
+
+/// Lines longer than this are commentary rather than a follow-up.
+const MAX_SUGGESTION_CHARS: usize = 200;
+
+/// Follow-up prompts suggested after an answer with `suggest-replies`, shown
+/// above the input box for Tab to fill in.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplySuggestions {
+    /// Suggestions were requested and haven't arrived yet.
+    pub pending: bool,
+    pub replies: Vec<String>,
+    selected: Option<usize>,
+}
+
+impl ReplySuggestions {
+    /// Asks for follow-ups to the last prompt and its answer.
+    pub fn prompt(question: &str, answer: &str) -> String {
+        let skip = answer.chars().count().saturating_sub(MAX_ANSWER_CHARS);
+        let answer = answer.chars().skip(skip).collect::<String>();
+
+        return format!(
+            "Suggest {MAX_SUGGESTIONS} short follow-up prompts the user might send next in the conversation below, written as the user. Reply with one prompt per line, without numbering, quotes or commentary.\n\nUser: {question}\n\nAssistant: {answer}"
+        );
+    }
+
+    /// Reads suggestions from the backend's reply, dropping list markers and
+    /// any lines introducing them.
+    pub fn parse(text: &str) -> Vec<String> {
+        let mut replies: Vec<String> = vec![];
+        for line in text.lines() {
+            let mut line = line.trim().trim_start_matches(['-', '*', '•']).trim_start();
+            let digits = line
+                .chars()
+                .take_while(|e| return e.is_ascii_digit())
+                .count();
+            if digits > 0 && line[digits..].starts_with(['.', ')']) {
+                line = line[digits + 1..].trim_start();
+            }
+            let line = line
+                .trim_matches(|e| return ['"', '\'', '`'].contains(&e))
+                .trim();
+
+            if line.is_empty()
+                || line.ends_with(':')
+                || line.chars().count() > MAX_SUGGESTION_CHARS
+                || replies.iter().any(|e| return e == line)
+            {
+                continue;
+            }
+            replies.push(line.to_string());
+            if replies.len() == MAX_SUGGESTIONS {
+                break;
+            }
+        }
+
+        return replies;
+    }
+
+    pub fn request(&mut self) {
+        self.clear();
+        self.pending = true;
+    }
+
+    /// Shows the suggestions, unless they were cancelled while on their way.
+    pub fn set(&mut self, replies: Vec<String>) {
+        if !self.pending {
+            return;
+        }
+        self.pending = false;
+        self.replies = replies;
+        self.selected = None;
+    }
+
+    /// Returns true when suggestions were shown or on their way, so a pending
+    /// request should be cancelled.
+    pub fn clear(&mut self) -> bool {
+        let had_suggestions = self.pending || !self.replies.is_empty();
+        self.pending = false;
+        self.replies = vec![];
+        self.selected = None;
+        return had_suggestions;
+    }
+
+    pub fn selected(&self) -> Option<&str> {
+        return self
+            .selected
+            .and_then(|idx| return self.replies.get(idx))
+            .map(|e| return e.as_str());
+    }
+
+    /// Picks the next suggestion for Tab to fill in, as long as the input is
+    /// empty or still holds the previous pick.
+    pub fn select_next(&mut self, input: &str) -> Option<&str> {
+        if self.replies.is_empty() || !(input.is_empty() || self.selected() == Some(input)) {
+            return None;
+        }
+
+        let next = match self.selected {
+            Some(idx) => (idx + 1) % self.replies.len(),
+            None => 0,
+        };
+        self.selected = Some(next);
+
+        return self.selected();
+    }
+}
@@ -0,0 +1,53 @@
+use super::ReplySuggestions;
+
+#[test]
+fn it_limits_the_answer_in_the_prompt() {
+    let answer = format!("{}end", "a".repeat(5000));
+    let prompt = ReplySuggestions::prompt("Why?", &answer);
+
+    assert!(prompt.contains("User: Why?"));
+    assert!(prompt.ends_with(&format!("Assistant: {}end", "a".repeat(3997))));
+}
+
+#[test]
+fn it_parses_suggestions() {
+    let text = r#"Here are some follow-ups:
+1. How do I test this?
+2) "Can it run async?"
+- Can it run async?
+* `What about Windows?`
+
+Show me the error handling."#;
+
+    assert_eq!(
+        ReplySuggestions::parse(text),
+        vec![
+            "How do I test this?".to_string(),
+            "Can it run async?".to_string(),
+            "What about Windows?".to_string(),
+        ]
+    );
+}
+
+#[test]
+fn it_ignores_suggestions_after_clearing() {
+    let mut suggestions = ReplySuggestions::default();
+    suggestions.request();
+    assert!(suggestions.clear());
+    assert!(!suggestions.clear());
+
+    suggestions.set(vec!["Why?".to_string()]);
+    assert!(suggestions.replies.is_empty());
+}
+
+#[test]
+fn it_cycles_through_suggestions() {
+    let mut suggestions = ReplySuggestions::default();
+    suggestions.request();
+    suggestions.set(vec!["One?".to_string(), "Two?".to_string()]);
+
+    assert_eq!(suggestions.select_next(""), Some("One?"));
+    assert_eq!(suggestions.select_next("One?"), Some("Two?"));
+    assert_eq!(suggestions.select_next("Two?"), Some("One?"));
+    assert_eq!(suggestions.select_next("One? And more"), None);
+}
@@ -0,0 +1,59 @@
+#[cfg(test)]
+#[path = "system_prompt_test.rs"]
+mod tests;
+
+use std::collections::BTreeMap;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// Named system prompts `/system` can switch to, from
+/// `system-prompt-presets`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SystemPromptPresets {
+    presets: BTreeMap<String, String>,
+}
+
+impl SystemPromptPresets {
+    /// Reads one `NAME=PROMPT` preset per line, skipping lines without a
+    /// name or prompt.
+    pub fn parse(text: &str) -> SystemPromptPresets {
+        let presets = text
+            .lines()
+            .filter_map(|line| {
+                let (name, prompt) = line.split_once('=')?;
+                let (name, prompt) = (name.trim(), prompt.trim());
+                if name.is_empty() || prompt.is_empty() {
+                    return None;
+                }
+                return Some((name.to_string(), prompt.to_string()));
+            })
+            .collect();
+
+        return SystemPromptPresets { presets };
+    }
+
+    pub fn from_config() -> SystemPromptPresets {
+        return SystemPromptPresets::parse(&Config::get(ConfigKey::SystemPromptPresets));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        return self.presets.get(name).map(|e| return e.as_str());
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        return self.presets.keys().map(|e| return e.as_str()).collect();
+    }
+
+    /// The system prompt from `system-prompt`, which may name one of the
+    /// presets, or `None` when there isn't one.
+    pub fn system_prompt_from_config() -> Option<String> {
+        let system_prompt = Config::get(ConfigKey::SystemPrompt);
+        let system_prompt = SystemPromptPresets::from_config()
+            .get(&system_prompt)
+            .map(|e| return e.to_string())
+            .unwrap_or(system_prompt);
+
+        return Some(system_prompt.trim().to_string()).filter(|e| return !e.is_empty());
+    }
+}
@@ -0,0 +1,23 @@
+use super::SystemPromptPresets;
+
+#[test]
+fn it_parses_presets() {
+    let presets = SystemPromptPresets::parse(
+        "terse = Answer in one sentence.\nreviewer=Review code for bugs, then style.\nempty=\n=No name",
+    );
+
+    assert_eq!(presets.names(), vec!["reviewer", "terse"]);
+    assert_eq!(presets.get("terse"), Some("Answer in one sentence."));
+    assert_eq!(
+        presets.get("reviewer"),
+        Some("Review code for bugs, then style.")
+    );
+    assert_eq!(presets.get("empty"), None);
+}
+
+#[test]
+fn it_keeps_equals_signs_in_prompts() {
+    let presets = SystemPromptPresets::parse("math=Write x = y as x == y");
+
+    assert_eq!(presets.get("math"), Some("Write x = y as x == y"));
+}
@@ -1,21 +1,106 @@
+#[cfg(test)]
+#[path = "textarea_test.rs"]
+mod tests;
+
+use ratatui::layout::Alignment;
+use ratatui::style::Color;
+use ratatui::style::Style;
+use ratatui::text::Line;
+use ratatui::text::Span;
+use ratatui::widgets::block::Position;
+use ratatui::widgets::block::Title;
 use ratatui::widgets::Block;
 use ratatui::widgets::BorderType;
 use ratatui::widgets::Borders;
 use ratatui::widgets::Padding;
 
+use super::BackendHealth;
+use super::ColorMode;
+use super::InputWarning;
+
 pub struct TextArea {}
 
+fn block<'a>(
+    warnings: &[InputWarning],
+    context_usage: &str,
+    health: &str,
+    color: Color,
+) -> Block<'a> {
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Double)
+        .title("Enter prompt")
+        .padding(Padding::new(1, 1, 0, 0));
+
+    if !health.is_empty() {
+        let style = Style {
+            fg: ColorMode::current().apply(color),
+            ..Style::default()
+        };
+        block = block.title(
+            Title::from(Line::from(Span::styled(format!(" {health} "), style)))
+                .position(Position::Bottom)
+                .alignment(Alignment::Left),
+        );
+    }
+
+    if !context_usage.is_empty() {
+        block = block.title(Title::from(format!(" {context_usage} ")).alignment(Alignment::Right));
+    }
+
+    if !warnings.is_empty() {
+        let text = warnings
+            .iter()
+            .map(|e| return e.to_string())
+            .collect::<Vec<String>>()
+            .join(", ");
+        block = block
+            .border_style(Style {
+                fg: ColorMode::current().apply(Color::Yellow),
+                ..Style::default()
+            })
+            .title(
+                Title::from(format!(" ⚠ {text} "))
+                    .position(Position::Bottom)
+                    .alignment(Alignment::Right),
+            );
+    }
+
+    return block;
+}
+
 impl<'a> TextArea {
     pub fn default() -> tui_textarea::TextArea<'a> {
         let mut textarea = tui_textarea::TextArea::default();
-        textarea.set_block(
-            Block::default()
-                .borders(Borders::ALL)
-                .border_type(BorderType::Double)
-                .title("Enter prompt")
-                .padding(Padding::new(1, 1, 0, 0)),
-        );
+        textarea.set_block(block(&[], "", "", Color::Reset));
 
         return textarea;
     }
+
+    /// Rows the input box takes, growing with the draft up to `max_lines` of
+    /// it, after which it scrolls within the box.
+    pub fn height(textarea: &tui_textarea::TextArea<'a>, max_lines: usize) -> u16 {
+        let lines = textarea.lines().len().clamp(1, max_lines.max(1));
+        // Borders, and a spare line below the last.
+        return (lines + 3).try_into().unwrap_or(u16::MAX);
+    }
+
+    /// Highlights the border and lists the warnings along its bottom, leaving
+    /// the prompt free to be sent anyway. How much of the context window the
+    /// chat uses is shown along its top, and whether the backend is up at
+    /// the bottom left.
+    pub fn set_status(
+        textarea: &mut tui_textarea::TextArea<'a>,
+        warnings: &[InputWarning],
+        context_usage: &str,
+        health: BackendHealth,
+        backend: &str,
+    ) {
+        textarea.set_block(block(
+            warnings,
+            context_usage,
+            &health.label(backend),
+            health.color(),
+        ));
+    }
 }
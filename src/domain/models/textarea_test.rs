@@ -0,0 +1,13 @@
+use super::TextArea;
+
+#[test]
+fn it_grows_with_the_draft_up_to_the_max_lines() {
+    let mut textarea = TextArea::default();
+    assert_eq!(TextArea::height(&textarea, 5), 4);
+
+    textarea.insert_str("one\ntwo\nthree");
+    assert_eq!(TextArea::height(&textarea, 5), 6);
+
+    textarea.insert_str("\nfour\nfive\nsix\nseven");
+    assert_eq!(TextArea::height(&textarea, 5), 8);
+}
@@ -0,0 +1,120 @@
+#[cfg(test)]
+#[path = "ticket_test.rs"]
+mod tests;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use strum::EnumIter;
+use strum::EnumVariantNames;
+use strum::IntoEnumIterator;
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, EnumVariantNames, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum TicketProviderName {
+    Jira,
+    Linear,
+}
+
+impl TicketProviderName {
+    pub fn parse(text: String) -> Option<TicketProviderName> {
+        return TicketProviderName::iter().find(|e| return e.to_string() == text);
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Ticket {
+    pub key: String,
+    pub title: String,
+    pub description: String,
+    pub url: String,
+}
+
+fn is_heading(line: &str) -> bool {
+    let trimmed = line.trim();
+    return trimmed.starts_with('#')
+        || (trimmed.len() > 3
+            && trimmed.starts_with('h')
+            && trimmed[1..2].parse::<u8>().is_ok()
+            && trimmed[2..].starts_with(". "));
+}
+
+impl Ticket {
+    /// Line range of the acceptance criteria section within the description,
+    /// starting at its Markdown or Jira heading.
+    fn acceptance_criteria_range(&self) -> Option<(usize, usize)> {
+        let lines = self.description.lines().collect::<Vec<&str>>();
+        let start = lines.iter().position(|line| {
+            return line.to_lowercase().contains("acceptance criteria")
+                && (is_heading(line)
+                    || line.trim().starts_with('*')
+                    || line.trim().ends_with(':'));
+        })?;
+
+        let length = lines[(start + 1)..]
+            .iter()
+            .take_while(|line| return !is_heading(line))
+            .count();
+
+        return Some((start, start + 1 + length));
+    }
+
+    /// Returns the acceptance criteria section of the description, if it has
+    /// one.
+    pub fn acceptance_criteria(&self) -> Option<String> {
+        let (start, end) = self.acceptance_criteria_range()?;
+        let section = self
+            .description
+            .lines()
+            .skip(start + 1)
+            .take(end - start - 1)
+            .collect::<Vec<&str>>()
+            .join("\n");
+
+        if section.trim().is_empty() {
+            return None;
+        }
+
+        return Some(section.trim().to_string());
+    }
+
+    /// Formats the ticket as context for a prompt, with the acceptance
+    /// criteria pulled out ahead of the rest of the description.
+    pub fn format(&self) -> String {
+        let mut res = format!("Ticket {}: {}", self.key, self.title);
+        if !self.url.is_empty() {
+            res = format!("{res}\n{}", self.url);
+        }
+
+        let mut description = self.description.to_string();
+        if let Some(criteria) = self.acceptance_criteria() {
+            res = format!("{res}\n\nAcceptance criteria:\n\n{criteria}");
+
+            let (start, end) = self.acceptance_criteria_range().unwrap();
+            description = self
+                .description
+                .lines()
+                .enumerate()
+                .filter(|(idx, _)| return *idx < start || *idx >= end)
+                .map(|(_, line)| return line)
+                .collect::<Vec<&str>>()
+                .join("\n");
+        }
+
+        if !description.trim().is_empty() {
+            res = format!("{res}\n\nDescription:\n\n{}", description.trim());
+        }
+
+        return res;
+    }
+}
+
+#[async_trait]
+pub trait TicketProvider {
+    /// Returns the name of the ticket provider.
+    fn name(&self) -> TicketProviderName;
+
+    /// Fetches a ticket by its key, such as `ENG-123`.
+    async fn get_ticket(&self, key: &str) -> Result<Ticket>;
+}
+
+pub type TicketProviderBox = Box<dyn TicketProvider + Send + Sync>;
@@ -0,0 +1,40 @@
+use super::Ticket;
+
+#[test]
+fn it_finds_markdown_acceptance_criteria() {
+    let ticket = Ticket {
+        key: "ENG-1".to_string(),
+        title: "Add login".to_string(),
+        description: "Users need to log in.\n\n## Acceptance Criteria\n\n- Email login\n- Password reset\n\n## Notes\n\nLater.".to_string(),
+        url: "https://linear.app/team/issue/ENG-1".to_string(),
+    };
+
+    insta::assert_snapshot!(ticket.format(), @r###"
+    Ticket ENG-1: Add login
+    https://linear.app/team/issue/ENG-1
+
+    Acceptance criteria:
+
+    - Email login
+    - Password reset
+
+    Description:
+
+    Users need to log in.
+
+    ## Notes
+
+    Later.
+    "###);
+}
+
+#[test]
+fn it_finds_jira_acceptance_criteria() {
+    let ticket = Ticket {
+        description: "h2. Acceptance criteria\n* Works\nh2. Other".to_string(),
+        ..Ticket::default()
+    };
+
+    assert_eq!(ticket.acceptance_criteria(), Some("* Works".to_string()));
+    assert_eq!(Ticket::default().acceptance_criteria(), None);
+}
@@ -0,0 +1,45 @@
+#[cfg(test)]
+#[path = "todos_test.rs"]
+mod tests;
+
+/// Asks for the action items in a conversation for `/todos`.
+pub fn todos_prompt(transcript: &str) -> String {
+    return format!(
+        "List the action items from this conversation: tasks someone agreed or still needs to do, such as follow-ups, fixes and open questions to answer. Reply with one `- [ ] ` line per item and nothing else, or `NONE` when there aren't any.\n\n{transcript}"
+    );
+}
+
+/// Action items in a reply to `todos_prompt`, taken from its list lines.
+/// Anything that isn't a list item, such as an introduction, is skipped.
+pub fn parse_todos(text: &str) -> Vec<String> {
+    let mut todos = vec![];
+    for line in text.lines().map(|e| return e.trim()) {
+        let item = match line
+            .strip_prefix("- ")
+            .or_else(|| return line.strip_prefix("* "))
+        {
+            Some(item) => item.trim_start(),
+            None => continue,
+        };
+        let item = ["[ ]", "[x]", "[X]"]
+            .iter()
+            .find_map(|e| return item.strip_prefix(e))
+            .unwrap_or(item)
+            .trim();
+
+        if !item.is_empty() && !todos.iter().any(|e: &String| return e == item) {
+            todos.push(item.to_string());
+        }
+    }
+
+    return todos;
+}
+
+/// Markdown checklist of the action items, to show or export.
+pub fn todos_checklist(todos: &[String]) -> String {
+    return todos
+        .iter()
+        .map(|e| return format!("- [ ] {e}"))
+        .collect::<Vec<String>>()
+        .join("\n");
+}
@@ -0,0 +1,28 @@
+use super::parse_todos;
+use super::todos_checklist;
+
+#[test]
+fn it_parses_todos() {
+    let todos = parse_todos(
+        "Here are the action items:\n\n- [ ] Add a test for the parser\n* [x] Bump the version\n-   Ask about the release date\n- [ ] Add a test for the parser\n",
+    );
+    assert_eq!(
+        todos,
+        vec![
+            "Add a test for the parser".to_string(),
+            "Bump the version".to_string(),
+            "Ask about the release date".to_string(),
+        ]
+    );
+
+    assert!(parse_todos("NONE").is_empty());
+}
+
+#[test]
+fn it_formats_a_checklist() {
+    let todos = vec!["Fix the build".to_string(), "Reply to Sam".to_string()];
+    assert_eq!(
+        todos_checklist(&todos),
+        "- [ ] Fix the build\n- [ ] Reply to Sam"
+    );
+}
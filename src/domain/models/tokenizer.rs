@@ -0,0 +1,128 @@
+#[cfg(test)]
+#[path = "tokenizer_test.rs"]
+mod tests;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::estimate_tokens;
+use super::BackendName;
+use super::LocaleFormat;
+
+/// Splits text the way tiktoken does before encoding each piece: words with
+/// their leading space, numbers of up to three digits, runs of punctuation,
+/// and whitespace.
+static PIECES_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(
+        r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+",
+    )
+    .unwrap();
+});
+
+/// Letters a word piece of OpenAI's vocabularies tends to cover, past which
+/// it's split.
+const BPE_WORD_CHARS: usize = 6;
+
+/// Context windows of well known models, matched by the start of their name.
+/// More specific names come first.
+const CONTEXT_WINDOWS: &[(&str, u64)] = &[
+    ("gpt-4o", 128000),
+    ("gpt-4-turbo", 128000),
+    ("gpt-4-32k", 32768),
+    ("gpt-4", 8192),
+    ("gpt-3.5-turbo", 16385),
+    ("o1", 128000),
+    ("claude", 200000),
+    ("gemini-1.5-pro", 2097152),
+    ("gemini-1.5", 1048576),
+    ("gemini", 32768),
+    ("command-r", 128000),
+    ("mistral-large", 128000),
+    ("mixtral", 32768),
+    ("mistral", 32768),
+    ("codellama", 16384),
+    ("llama3.1", 131072),
+    ("llama3", 8192),
+    ("llama2", 4096),
+];
+
+/// How tokens are counted for a model. Only OpenAI's models are counted
+/// piece by piece, others are estimated from their length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tokenizer {
+    /// Approximates tiktoken, without its vocabulary.
+    Bpe,
+    /// Roughly four characters a token.
+    Heuristic,
+}
+
+impl Tokenizer {
+    pub fn for_model(backend: &str, model: &str) -> Tokenizer {
+        let model = model.rsplit('/').next().unwrap_or_default();
+        let openai = matches!(
+            BackendName::parse(backend.to_string()),
+            Some(BackendName::OpenAI | BackendName::Azure)
+        );
+        if openai || model.starts_with("gpt-") {
+            return Tokenizer::Bpe;
+        }
+
+        return Tokenizer::Heuristic;
+    }
+
+    pub fn count(&self, text: &str) -> u64 {
+        if *self == Tokenizer::Heuristic {
+            return estimate_tokens(text);
+        }
+
+        return PIECES_RE
+            .find_iter(text)
+            .map(|piece| {
+                let piece = piece.as_str();
+                let letters = piece.trim_start().chars().count();
+                if piece.chars().any(|e| return e.is_alphabetic()) {
+                    return letters.div_ceil(BPE_WORD_CHARS).max(1) as u64;
+                }
+                if piece.trim().is_empty() || piece.chars().all(|e| return e.is_numeric()) {
+                    return 1;
+                }
+                return letters.div_ceil(2).max(1) as u64;
+            })
+            .sum();
+    }
+}
+
+/// Tokens the model can take in at once, when it's a well known one.
+pub fn context_window(model: &str) -> Option<u64> {
+    let model = model.rsplit('/').next().unwrap_or_default().to_lowercase();
+    return CONTEXT_WINDOWS
+        .iter()
+        .find(|(prefix, _)| return model.starts_with(prefix))
+        .map(|(_, window)| return *window);
+}
+
+/// How much of the model's context window the chat takes up. Tokens are
+/// only estimated, as backends don't report them as answers stream.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ContextUsage {
+    pub tokens: u64,
+    /// Unknown for models that aren't well known, unless `context-window`
+    /// is set.
+    pub window: Option<u64>,
+}
+
+impl ContextUsage {
+    pub fn render(&self, locale: &LocaleFormat) -> String {
+        let tokens = locale.number(self.tokens as f64, 0);
+        return match self.window {
+            Some(window) if window > 0 => {
+                format!(
+                    "~{tokens} / {} tokens ({}%)",
+                    locale.number(window as f64, 0),
+                    self.tokens * 100 / window
+                )
+            }
+            _ => format!("~{tokens} tokens"),
+        };
+    }
+}
@@ -0,0 +1,53 @@
+use super::context_window;
+use super::ContextUsage;
+use super::Tokenizer;
+use crate::domain::models::LocaleFormat;
+
+#[test]
+fn it_picks_a_tokenizer_for_the_model() {
+    assert_eq!(Tokenizer::for_model("openai", "gpt-4"), Tokenizer::Bpe);
+    assert_eq!(
+        Tokenizer::for_model("openrouter", "openai/gpt-4o"),
+        Tokenizer::Bpe
+    );
+    assert_eq!(
+        Tokenizer::for_model("ollama", "codellama:latest"),
+        Tokenizer::Heuristic
+    );
+}
+
+#[test]
+fn it_counts_tokens_piece_by_piece() {
+    let tokenizer = Tokenizer::Bpe;
+    assert_eq!(tokenizer.count(""), 0);
+    assert_eq!(tokenizer.count("Hello world"), 2);
+    assert_eq!(tokenizer.count("It's 2024!"), 6);
+    assert_eq!(tokenizer.count("internationalization"), 4);
+
+    assert_eq!(Tokenizer::Heuristic.count("Hello world"), 3);
+}
+
+#[test]
+fn it_knows_the_context_window_of_well_known_models() {
+    assert_eq!(context_window("gpt-4o-mini"), Some(128000));
+    assert_eq!(context_window("gpt-4"), Some(8192));
+    assert_eq!(context_window("claude-3-opus-20240229"), Some(200000));
+    assert_eq!(context_window("meta-llama/llama3.1:8b"), Some(131072));
+    assert_eq!(context_window("phi"), None);
+}
+
+#[test]
+fn it_renders_context_usage() {
+    let locale = LocaleFormat::default();
+    let usage = ContextUsage {
+        tokens: 2048,
+        window: Some(8192),
+    };
+    assert_eq!(usage.render(&locale), "~2,048 / 8,192 tokens (25%)");
+
+    let usage = ContextUsage {
+        tokens: 12,
+        window: None,
+    };
+    assert_eq!(usage.render(&locale), "~12 tokens");
+}
@@ -0,0 +1,164 @@
+#[cfg(test)]
+#[path = "tool_approval_test.rs"]
+mod tests;
+
+use ratatui::prelude::Alignment;
+use ratatui::prelude::Line;
+use ratatui::prelude::Modifier;
+use ratatui::prelude::Rect;
+use ratatui::prelude::Span;
+use ratatui::prelude::Style;
+use ratatui::widgets::Block;
+use ratatui::widgets::BorderType;
+use ratatui::widgets::Borders;
+use ratatui::widgets::Clear;
+use ratatui::widgets::Padding;
+use ratatui::widgets::Paragraph;
+use ratatui::widgets::Wrap;
+use ratatui::Frame;
+use strum::EnumIter;
+use strum::IntoEnumIterator;
+use tui_textarea::Key;
+
+use super::Event;
+use super::QuitPrompt;
+use super::ToolInvocation;
+
+/// Widest the approval prompt gets, however wide the terminal is.
+const TOOL_APPROVAL_WIDTH: u16 = 70;
+
+/// Whether to run a tool call the model asked for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter)]
+pub enum ToolApprovalChoice {
+    Run,
+    Skip,
+}
+
+impl ToolApprovalChoice {
+    pub fn label(&self) -> &'static str {
+        return match self {
+            ToolApprovalChoice::Run => "Run",
+            ToolApprovalChoice::Skip => "Skip",
+        };
+    }
+
+    /// Picked by the first letter of its label.
+    fn for_char(char: char) -> Option<ToolApprovalChoice> {
+        return ToolApprovalChoice::iter().find(|e| {
+            return e
+                .label()
+                .to_lowercase()
+                .starts_with(char.to_ascii_lowercase());
+        });
+    }
+}
+
+/// Asks before running a tool call from the model, drawn over the chat.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ToolApproval {
+    pub invocation: ToolInvocation,
+    pub selected: ToolApprovalChoice,
+}
+
+impl ToolApproval {
+    /// Skipping is selected to start with, so a stray Enter doesn't run
+    /// anything.
+    pub fn new(invocation: ToolInvocation) -> ToolApproval {
+        return ToolApproval {
+            invocation,
+            selected: ToolApprovalChoice::Skip,
+        };
+    }
+
+    /// Keyboard events go to the prompt while it's open, rather than the
+    /// input box.
+    pub fn is_input(event: &Event) -> bool {
+        return QuitPrompt::is_input(event);
+    }
+
+    /// Moves the selection with Tab and the arrow keys. Returns the choice
+    /// once made, with Enter for the one selected, Esc or CTRL+C to skip, or
+    /// the first letter of one.
+    pub fn handle(&mut self, event: &Event) -> Option<ToolApprovalChoice> {
+        let choices = ToolApprovalChoice::iter().collect::<Vec<ToolApprovalChoice>>();
+        let idx = choices
+            .iter()
+            .position(|e| return e == &self.selected)
+            .unwrap();
+        match event {
+            Event::KeyboardEnter() => return Some(self.selected),
+            Event::KeyboardEsc() | Event::KeyboardCTRLC() => return Some(ToolApprovalChoice::Skip),
+            Event::KeyboardTab() => {
+                self.selected = choices[(idx + 1) % choices.len()];
+            }
+            Event::KeyboardCharInput(input) => {
+                match input.key {
+                    Key::Right | Key::Down => {
+                        self.selected = choices[(idx + 1) % choices.len()];
+                    }
+                    Key::Left | Key::Up => {
+                        self.selected = choices[(idx + choices.len() - 1) % choices.len()];
+                    }
+                    Key::Char(char) if !input.ctrl && !input.alt => {
+                        return ToolApprovalChoice::for_char(char);
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+
+        return None;
+    }
+
+    /// Renders the prompt in the middle of `rect`, over whatever is there.
+    pub fn render(&self, frame: &mut Frame, rect: Rect) {
+        let width = rect.width.min(TOOL_APPROVAL_WIDTH);
+        let summary = self.invocation.summary();
+        // The summary wraps within the borders and padding.
+        let inner_width = width.saturating_sub(4).max(1) as usize;
+        let summary_lines = summary
+            .lines()
+            .map(|e| return e.chars().count().div_ceil(inner_width).max(1))
+            .sum::<usize>();
+
+        let mut lines = vec![Line::from("The model wants to:"), Line::from("")];
+        lines.extend(summary.lines().map(|e| return Line::from(e.to_string())));
+        lines.push(Line::from(""));
+
+        let mut choices = vec![];
+        for choice in ToolApprovalChoice::iter() {
+            let mut style = Style::default();
+            if choice == self.selected {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            choices.push(Span::styled(format!(" {} ", choice.label()), style));
+            choices.push(Span::raw(" "));
+        }
+        choices.pop();
+        lines.push(Line::from(choices));
+
+        let height = rect.height.min(summary_lines as u16 + 6);
+        let area = Rect::new(
+            rect.x + (rect.width - width) / 2,
+            rect.y + (rect.height - height) / 2,
+            width,
+            height,
+        );
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines)
+                .block(
+                    Block::default()
+                        .title(format!(" Run {}? ", self.invocation.name))
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Double)
+                        .padding(Padding::new(1, 1, 0, 0)),
+                )
+                .alignment(Alignment::Center)
+                .wrap(Wrap { trim: true }),
+            area,
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use tui_textarea::Input;
+use tui_textarea::Key;
+
+use super::ToolApproval;
+use super::ToolApprovalChoice;
+use crate::domain::models::Event;
+use crate::domain::models::ToolInvocation;
+
+fn approval() -> ToolApproval {
+    return ToolApproval::new(ToolInvocation::new(
+        "call_1",
+        "shell",
+        r#"{"command":"ls"}"#,
+    ));
+}
+
+#[test]
+fn it_skips_by_default() {
+    let mut approval = approval();
+
+    assert_eq!(
+        approval.handle(&Event::KeyboardEnter()),
+        Some(ToolApprovalChoice::Skip)
+    );
+    assert_eq!(
+        approval.handle(&Event::KeyboardCTRLC()),
+        Some(ToolApprovalChoice::Skip)
+    );
+}
+
+#[test]
+fn it_runs_once_chosen() {
+    let mut approval = approval();
+
+    assert_eq!(approval.handle(&Event::KeyboardTab()), None);
+    assert_eq!(approval.selected, ToolApprovalChoice::Run);
+    assert_eq!(
+        approval.handle(&Event::KeyboardEnter()),
+        Some(ToolApprovalChoice::Run)
+    );
+    assert_eq!(
+        approval.handle(&Event::KeyboardEsc()),
+        Some(ToolApprovalChoice::Skip)
+    );
+    assert_eq!(
+        approval.handle(&Event::KeyboardCharInput(Input {
+            key: Key::Char('r'),
+            ..Input::default()
+        })),
+        Some(ToolApprovalChoice::Run)
+    );
+}
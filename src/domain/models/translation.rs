@@ -0,0 +1,73 @@
+#[cfg(test)]
+#[path = "translation_test.rs"]
+mod tests;
+
+/// A pending `/translate` request. Code blocks are swapped for placeholders
+/// before the text is sent to the backend, and restored once the translation
+/// completes so code is never altered.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Translation {
+    /// Prompt sent to the backend.
+    pub prompt: String,
+    /// Code blocks removed from the text, in order, including their fences.
+    codeblocks: Vec<String>,
+    /// Context of the chat before the translation, restored once it completes
+    /// so the translation doesn't become part of the conversation.
+    pub backend_context: String,
+}
+
+fn placeholder(index: usize) -> String {
+    return format!("[[CODE_BLOCK_{}]]", index + 1);
+}
+
+impl Translation {
+    pub fn new(text: &str, language: &str, backend_context: &str) -> Translation {
+        let mut codeblocks: Vec<String> = vec![];
+        let mut lines: Vec<String> = vec![];
+        let mut current: Option<Vec<&str>> = None;
+        for line in text.split('\n') {
+            if line.trim().starts_with("```") {
+                if let Some(mut block) = current.take() {
+                    block.push(line);
+                    lines.push(placeholder(codeblocks.len()));
+                    codeblocks.push(block.join("\n"));
+                } else {
+                    current = Some(vec![line]);
+                }
+                continue;
+            }
+
+            if let Some(block) = current.as_mut() {
+                block.push(line);
+                continue;
+            }
+            lines.push(line.to_string());
+        }
+
+        // An unterminated block is kept as is.
+        if let Some(block) = current {
+            lines.extend(block.iter().map(|e| return e.to_string()));
+        }
+
+        let prompt = format!(
+            "Translate the following text to {language}. Keep the Markdown formatting, and keep placeholders such as [[CODE_BLOCK_1]] exactly as they are. Only reply with the translation.\n\n{}",
+            lines.join("\n")
+        );
+
+        return Translation {
+            prompt,
+            codeblocks,
+            backend_context: backend_context.to_string(),
+        };
+    }
+
+    /// Puts the original code blocks back in to the translated text.
+    pub fn restore(&self, text: &str) -> String {
+        let mut res = text.to_string();
+        for (idx, codeblock) in self.codeblocks.iter().enumerate() {
+            res = res.replace(&placeholder(idx), codeblock);
+        }
+
+        return res;
+    }
+}
@@ -0,0 +1,31 @@
+use super::Translation;
+
+#[test]
+fn it_masks_codeblocks() {
+    let text = "Use this function:\n\n```rust\n// Adds numbers\nfn add() {}\n```\n\nIt adds.";
+    let translation = Translation::new(text, "French", "ctx");
+
+    assert_eq!(
+        translation.prompt,
+        "Translate the following text to French. Keep the Markdown formatting, and keep placeholders such as [[CODE_BLOCK_1]] exactly as they are. Only reply with the translation.\n\nUse this function:\n\n[[CODE_BLOCK_1]]\n\nIt adds."
+    );
+    assert_eq!(translation.backend_context, "ctx");
+}
+
+#[test]
+fn it_restores_codeblocks() {
+    let text = "First:\n```sh\necho 1\n```\nSecond:\n```\necho 2\n```";
+    let translation = Translation::new(text, "German", "");
+
+    assert_eq!(
+        translation.restore("Erstens:\n[[CODE_BLOCK_1]]\nZweitens:\n[[CODE_BLOCK_2]]"),
+        "Erstens:\n```sh\necho 1\n```\nZweitens:\n```\necho 2\n```"
+    );
+}
+
+#[test]
+fn it_keeps_unterminated_codeblocks() {
+    let translation = Translation::new("Hi\n```rust\nfn main()", "Spanish", "");
+    assert!(translation.prompt.ends_with("Hi\n```rust\nfn main()"));
+    assert_eq!(translation.restore("Hola"), "Hola");
+}
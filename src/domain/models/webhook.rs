@@ -0,0 +1,47 @@
+#[cfg(test)]
+#[path = "webhook_test.rs"]
+mod tests;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use strum::EnumIter;
+use strum::EnumVariantNames;
+use strum::IntoEnumIterator;
+
+use super::Author;
+use super::Message;
+
+#[derive(Clone, Debug, PartialEq, Eq, EnumIter, EnumVariantNames, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum WebhookName {
+    Discord,
+    Slack,
+}
+
+impl WebhookName {
+    pub fn parse(text: String) -> Option<WebhookName> {
+        return WebhookName::iter().find(|e| return e.to_string() == text);
+    }
+}
+
+/// Formats messages as Markdown, with each author's name in bold above what
+/// they wrote. Messages from Oatmeal itself are skipped.
+pub fn conversation_markdown(messages: &[Message]) -> String {
+    return messages
+        .iter()
+        .filter(|e| return e.author != Author::Oatmeal)
+        .map(|e| return format!("**{}:**\n{}", e.author_name(), e.text.trim()))
+        .collect::<Vec<String>>()
+        .join("\n\n");
+}
+
+#[async_trait]
+pub trait Webhook {
+    /// Returns the name of the webhook service.
+    fn name(&self) -> WebhookName;
+
+    /// Posts the messages to the configured webhook.
+    async fn post(&self, messages: &[Message]) -> Result<()>;
+}
+
+pub type WebhookBox = Box<dyn Webhook + Send + Sync>;
@@ -0,0 +1,22 @@
+use super::conversation_markdown;
+use crate::domain::models::Author;
+use crate::domain::models::Message;
+
+#[test]
+fn it_formats_conversation() {
+    let mut question = Message::new(Author::User, "What's 1 + 1?");
+    question.set_username("alice");
+    let messages = vec![
+        Message::new(Author::Oatmeal, "Hey, it looks like you're new here!"),
+        question,
+        Message::new(Author::Model, "It's 2.\n"),
+    ];
+
+    let mut answer = messages[2].clone();
+    answer.set_username("llama");
+    assert_eq!(
+        conversation_markdown(&[messages[1].clone(), answer]),
+        "**alice:**\nWhat's 1 + 1?\n\n**llama:**\nIt's 2."
+    );
+    assert!(!conversation_markdown(&messages).contains("new here"));
+}
@@ -1,79 +1,275 @@
+use std::collections::hash_map::DefaultHasher;
+use std::env;
+use std::future::Future;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path::Path;
+use std::process::Stdio;
 use std::sync::Arc;
+use std::time::Duration;
 
+use anyhow::bail;
 use anyhow::Result;
+use similar::TextDiff;
+use strum::IntoEnumIterator;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::process;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
 
 use super::clipboard::ClipboardService;
+use super::AuditLog;
+use super::CachedResponse;
+use super::ExperimentLog;
+use super::MacroStore;
+use super::ModelCache;
+use super::ResponseCache;
+use super::Sandbox;
+use super::SandboxMode;
+use super::Scheduler;
+use super::Sessions;
+use super::Shell;
+use super::ShellOutput;
 use crate::configuration::Config;
 use crate::configuration::ConfigKey;
+use crate::domain::models::is_transient;
+use crate::domain::models::parse_todos;
+use crate::domain::models::summary_prompt;
+use crate::domain::models::todos_checklist;
+use crate::domain::models::todos_prompt;
 use crate::domain::models::AcceptType;
 use crate::domain::models::Action;
+use crate::domain::models::AuditEntry;
+use crate::domain::models::AuditKind;
 use crate::domain::models::Author;
 use crate::domain::models::BackendBox;
+use crate::domain::models::BackendHealth;
+use crate::domain::models::BackendName;
 use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::CodeBlock;
+use crate::domain::models::CommandOutput;
+use crate::domain::models::Comparison;
+use crate::domain::models::ContextDocument;
+use crate::domain::models::DiagramKind;
 use crate::domain::models::EditorContext;
 use crate::domain::models::EditorName;
+use crate::domain::models::ErrorReport;
 use crate::domain::models::Event;
+use crate::domain::models::Experiment;
+use crate::domain::models::Fallback;
+use crate::domain::models::FewShotExample;
+use crate::domain::models::ImageAttachment;
+use crate::domain::models::LocaleFormat;
+use crate::domain::models::Macro;
 use crate::domain::models::Message;
 use crate::domain::models::MessageType;
+use crate::domain::models::ModelList;
+use crate::domain::models::ModelTool;
+use crate::domain::models::Permission;
+use crate::domain::models::PolishMode;
+use crate::domain::models::RecoveryAction;
+use crate::domain::models::ReplySuggestions;
+use crate::domain::models::ReviewComment;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::SessionDiff;
+use crate::domain::models::ShareGuest;
+use crate::domain::models::ShareHost;
+use crate::domain::models::SimilarMessage;
 use crate::domain::models::SlashCommand;
+use crate::domain::models::StreamInterrupted;
+use crate::domain::models::SystemPromptPresets;
+use crate::domain::models::TicketProviderName;
+use crate::domain::models::ToolInvocation;
+use crate::domain::models::ToolOutput;
+use crate::domain::models::ToolPolicy;
+use crate::domain::models::Variant;
+use crate::domain::models::WebhookName;
+use crate::infrastructure::backends::BackendManager;
+use crate::infrastructure::context::docs::DocsPath;
+use crate::infrastructure::context::docs::RustDocs;
+use crate::infrastructure::context::document::Document;
+use crate::infrastructure::context::github::GitHub;
+use crate::infrastructure::context::github::GitHubReference;
+use crate::infrastructure::context::man::ManPage;
 use crate::infrastructure::editors::EditorManager;
+use crate::infrastructure::embeddings::local::LocalEmbeddings;
+use crate::infrastructure::tickets::TicketProviderManager;
+use crate::infrastructure::webhooks::WebhookManager;
 
 pub fn help_text() -> String {
     let text = r#"
 COMMANDS:
-- /modellist (/ml) - Lists all available models from the backend.
+- /modellist (/ml) [--filter TEXT] [--page N] - Lists all available models from the backend, optionally filtered to names containing `TEXT`. Long lists are split in to pages of 50.
+- /modelrefresh - Fetches the model list from the backend again in the background, rather than waiting for the cached one to expire.
+- /rewrites - Lists the `rewrite-rules` in the order they run, and which ones changed the last prompt and answer.
 - /model (/model) [MODEL_NAME,MODEL_INDEX] - Sets the specified model as the active model. You can pass either the model name, or the index from `/modellist`.
+- /continue - Continues the last answer where it stopped, such as when it reached the model's output limit, appending to the same message. Answers that hit the limit are continued automatically with `auto-continue`.
+- /stats - Shows the active backend and model, and how much VRAM and RAM the model is using when the backend reports it, such as with Ollama.
+- /usage - Shows or hides the estimated tokens of each message below it, and their cost for models given a price with `model-prices`.
+- /ab [VARIANT_ONE] [VARIANT_TWO] [--pick 1,2] - Sends two variants of a prompt with the same context and shows the answers side by side. Pick the winner with `--pick` to continue the chat from its answer, logging the comparison to `experiments.jsonl` in the cache directory for later review.
+- /compare [MODEL_ONE] [MODEL_TWO] [PROMPT] - Sends the prompt to two models at once, without the chat's history, and streams their answers side by side. Prefix a model with its backend, such as `openai:gpt-4o`, to compare models of different backends.
+- /diff [SESSION_ID] - Compares this chat with a saved session, prompt by prompt, and shows where their answers diverged as a diff. Handy after running the same workflow against two models.
+- /checkpoint [NAME] - Saves the chat and the model's context as a named checkpoint, to come back to with `/rollback`.
+- /rollback [NAME] - Restores the chat and the model's context to a checkpoint, to try another direction without starting a new session.
+- /record [NAME] - Starts recording the prompts and commands you send as a macro, until `/stopmacro`.
+- /stop - Stops the answer being generated, the same as Esc. Can be typed while the model is answering.
+- /stopmacro - Stops recording and saves the macro under `[macros]` in your config file, where steps can also be edited by hand.
+- /play [NAME] [ARGS?] - Sends each step of a macro in turn, waiting for every answer before the next step. `{args}` in a step is replaced with `ARGS`.
+- /fork - Continues the chat as a new session, leaving the current one as it was. Run it when the session is read-only because it's already open in another Oatmeal.
+- /fewshot [FILE?] - Sends example exchanges from a JSON lines file of `{"user": "...", "assistant": "..."}` ahead of the conversation, without showing them. Clears them without a file.
+- /context add [FILE_OR_URL], /context list, or /context rm [NUMBER] - Keeps a file or web page with the session and sends it ahead of your next prompt, as untrusted reference material, and again whenever the backend's context starts over, such as when it's trimmed to fit the context window. Documents are sent in the order they were added, leaving out any that would go over `context-documents-budget`. Adding the same source again refreshes it.
+- /set seed [NUMBER,random] - Sets the seed used to generate answers, so they can be reproduced with Ollama or an OpenAI compatible server such as llama.cpp. Answers show the seed they were generated with, and it's saved with the session.
+- /set [temperature,top_p,max_tokens,stop] [VALUE,default] - Sets how answers are generated, from a temperature between 0 and 2, a top_p above 0 and at most 1, the most tokens an answer can have, or comma separated stop sequences that end an answer. Saved with the session, and `default` goes back to the backend's own.
+- /set var [NAME=VALUE?] - Sets a variable for this session, used in prompts as `{{NAME}}`, which is left as it is when it isn't set. Prompts can also use `{{env:NAME}}` for environment variables, which `tool-permissions` controls with `env`, and `{{sh:COMMAND}}` for a command's output, which it controls with `/run`. Write `\{{` to send `{{` as it is. Only prompts you type are filled in, so those built from command output or files, such as by `/test --fix`, are sent as they are, and guests of a shared session can't use placeholders. Lists the variables without a value, and removes one given an empty value.
+- /similar [TEXT] - Finds the messages in this session most similar in meaning to `TEXT` using the backend's embeddings, and jumps to the best match. Uses `embedding-model` when set.
 - /append (/a) [CODE_BLOCK_NUMBER?] - Appends code blocks to an editor. See Code Actions for more details.
 - /replace (/r) [CODE_BLOCK_NUMBER?] - Replaces selections with code blocks in an editor. See Code Actions for more details.
+- /system [PROMPT,PRESET,default?] - Sets the system prompt sent with every prompt, to PROMPT or one of the `system-prompt-presets` by name. Saved with the session, and `default` clears it. Shows the system prompt and presets without an argument.
+- /tee [FILE?] - Appends every prompt and answer to FILE as they stream, as markdown when it ends in `.md` and plain text otherwise. Stops writing the transcript without a file.
+- /test (/t) [--fix] [COMMAND?] - Runs the project's tests, posting a report of any failures. `COMMAND` is run as it's typed, and is inferred as `cargo test`, `npm test`, or `pytest` when not provided. With `--fix` before the command, failures are sent to the model to fix.
+- /run [CODE_BLOCK_NUMBER?] [--allow] - Runs a shell, Python, JavaScript, or Ruby code block, the last one by default, and posts its output. Runs in a container, or a temporary copy of your working tree, depending on `run-sandbox`, listing any files it changed without applying them. The copy only keeps your files from changing, it doesn't isolate the script from the rest of your machine. When `run-sandbox` is `none`, add `--allow` to confirm running it in your working tree.
+- /render [CODE_BLOCK_NUMBER?] - Renders a Mermaid or Graphviz code block, the last one by default, to an image with `mmdc` or `dot`, and opens it with your system's image viewer.
+- /check [COMMAND?] - Runs a linter such as `cargo clippy`, and asks the model to fix the problems it reports. `COMMAND` is run as it's typed. Uses `check-command` from your config, or is inferred from the project, when `COMMAND` isn't provided.
+- /gh [ISSUE_OR_PR_URL] - Attaches the title, description, and recent comments of a GitHub issue or pull request to your next message. Uses the `gh` CLI when installed, otherwise the GitHub API with `github-token`.
+- /ticket [TICKET_KEY] - Attaches the description and acceptance criteria of a Jira or Linear ticket to your next message. Requires `ticket-provider` and its API token.
+- /man [SECTION?] [COMMAND] - Attaches the installed man page for a command to your next message, falling back to its `--help` output, so answers match the version you have.
+- /docs [CRATE::PATH] - Attaches the documentation for a Rust crate, module, or item to your next message. Uses local `cargo doc` output when available, otherwise docs.rs at the version in `Cargo.lock`.
+- /translate [LANGUAGE] [--last N] - Translates the last response, or the last `N` responses, leaving code blocks untouched.
+- /todos - Lists the action items from the conversation as a checklist. Send them to a markdown file with `/todos > todos.md`, or copy them with `/todos | clipboard`.
+- /polish [grammar,terse,formal?] [DRAFT] - Rewrites your draft to fix its grammar, make it terse, or make it formal, and puts the result back in the input box for review before sending.
+- /share [ADDRESS?] - Shares this session with others, who join with `oatmeal join ADDRESS TOKEN` using the token it shows. Everyone sees the same conversation and takes turns prompting. Listens on `127.0.0.1:7878` unless an address such as `0.0.0.0:7878` is provided, which anyone on your network can connect to.
+- /post [slack,discord] [--all] - Posts the last answer, or the whole conversation with `--all`, to a Slack or Discord channel as Markdown. Requires `slack-webhook-url` or `discord-webhook-url`.
+- /perms - Shows which tools are allowed to run, which directories they may write to, and which hosts they may connect to. A tool set to `ask` in `tool-permissions` runs once `--allow` is added to its command.
+- /review [rdjson,github] [PR_NUMBER?] - Exports `FILE:LINE: COMMENT` review comments from the last answer, either as reviewdog JSON or posted as a review to a GitHub pull request with `gh`.
+- /sync - Re-reads the current selection from your editor, for when it changed after starting Oatmeal.
+- /withcontext (/wc) [QUESTION] - Re-reads the current selection from your editor and asks `QUESTION` with it, so follow-up questions see the latest code.
 - /copy (/c) [CODE_BLOCK_NUMBER?] - Copies the entire chat history to your clipboard. When a `CODE_BLOCK_NUMBER` is used, only the specified copy blocks are copied to clipboard. See Code Actions for more details.
-- /quit /exit (/q) - Exit Oatmeal.
+- /quit /exit (/q) - Exit Oatmeal, asking first when the session has unsaved changes or the model is answering. Can be typed while the model is answering.
 - /help (/h) - Provides this help menu.
+- /image [FILE?] - Attaches a PNG, JPEG, GIF or WebP image to your next prompt, for models that can see images with the openai, claude, gemini or ollama backends. Reads the image from the clipboard without a file, using `pngpaste` on macOS or `wl-paste` or `xclip` on Linux. The chat shows a placeholder in place of the image.
+
+Commands that return a result can send it somewhere other than the chat window by ending with `> FILE` (overwrite), `>> FILE` (append), or `| clipboard`. For example `/copy 2 > main.rs` or `/modellist | clipboard`.
 
 HOTKEYS:
 - Up arrow - Scroll up.
 - Down arrow - Scroll down.
 - CTRL+U - Page up.
 - CTRL+D - Page down.
-- CTRL+C - Interrupt waiting for prompt response if in progress, otherwise exit.
+- ALT+Left / ALT+Right - Scroll diagrams and tables too wide for their bubble sideways.
+- CTRL+C - Stops the answer being generated if in progress, otherwise exit. When the session has unsaved changes, asks whether to save and quit, discard them, or cancel first.
+- Esc - Stops the answer being generated, keeping what arrived so far. Hit it again to stop waiting on the backend.
 - CTRL+O - Insert a line break at the cursor position.
 - CTRL+R - Resubmit your last message to the backend.
+- CTRL+T - Show or hide the outline of your prompts, and the headings of long answers.
+- CTRL+N / CTRL+P - Select the next or previous outline entry, scrolling the chat to it.
+- ALT+= / ALT+- - Widen or narrow the outline while it's open. Its size is saved to the config file for later sessions.
+- TAB - Choose an action offered by an error, such as retrying or switching backends, then ENTER to run it.
 
 CODE ACTIONS:
 When working with models that provide code, and using an editor integration, Oatmeal has the capabilities to read selected code from an editor, and submit model provided code back in to an editor. Each code block provided by a model is indexed with a (NUMBER) at the beginning of the block to make it easily identifiable.
 
-- /append (/a) [CODE_BLOCK_NUMBER?] will append one-to-many model provided code blocks to the open file in your editor.
-- /replace (/r) [CODE_BLOCK_NUMBER?] - will replace selected code in your editor with one-to-many model provided code blocks.
+- /append (/a) [CODE_BLOCK_NUMBER?] will append one-to-many model provided code blocks to the open file in your editor. Without a number, it appends the last block in the language of that file, when there is one.
+- /replace (/r) [CODE_BLOCK_NUMBER?] - will replace selected code in your editor with one-to-many model provided code blocks. If the selected lines were edited since they were shared with Oatmeal, nothing is replaced until you run `/sync`, or pass `--force`.
 - /copy (/c) [CODE_BLOCK_NUMBER?] - Copies the entire chat history to your clipboard. When a `CODE_BLOCK_NUMBER` is used it will append one-to-many model provided code blocks to your clipboard, no matter the editor integration.
+- /apply [CODE_BLOCK_NUMBER?] - Writes code blocks directly to the file the model labeled them with, such as ```` ```rust title=src/main.rs ```` or "In `src/main.rs`:", without going through your editor. Blocks with an inferred file show it beside their number.
+- /applyall [--yes] - Shows a combined diff of every code block in the last answer against the files they were labeled with. Run again with `--yes` to write them all at once.
+- /lang (/l) [CODE_BLOCK_NUMBER] [LANGUAGE] - Overrides the language of a mislabeled code block, used for syntax highlighting. The override is saved with the session. Blocks the model didn't label take the language of the file shared from your editor.
 
 The `CODE_BLOCK_NUMBER` allows you to select several code blocks to send back to your editor at once. The parameter can be set as follows:
 - `1` - Selects the first code block
 - `1,3,5` - Selects code blocks 1, 3, and 5.
 - `2..5`- Selects an inclusive range of code blocks between 2 and 5.
 - None - Selects the last provided code block.
+- `--all` - Selects every code block in the conversation.
+
+Arguments containing spaces can be wrapped in double or single quotes.
         "#;
 
     return text.trim().to_string();
 }
 
-async fn model_list(backend: &BackendBox, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
-    let mut models = backend.list_models().await?;
-    models.sort();
+async fn model_list(
+    backend: &BackendBox,
+    tx: &mpsc::UnboundedSender<Event>,
+    command: &SlashCommand,
+) -> Result<()> {
+    let models = ModelList::new(ModelCache::default().list_models(backend).await?);
+    let page = command
+        .option("page")
+        .and_then(|e| return e.parse::<usize>().ok())
+        .unwrap_or(1);
+
+    let res = match models.render(command.option("filter").unwrap_or(""), page) {
+        Ok(res) => res,
+        Err(err) => {
+            let msg = Message::new_with_type(Author::Oatmeal, MessageType::Error, &err.to_string());
+            tx.send(Event::BackendMessage(msg))?;
+            return Ok(());
+        }
+    };
+
+    send_command_result(command, Message::new(Author::Oatmeal, &res), tx).await?;
+
+    return Ok(());
+}
+
+async fn stats(
+    backend: &BackendBox,
+    tx: &mpsc::UnboundedSender<Event>,
+    command: &SlashCommand,
+) -> Result<()> {
+    let mut res = format!(
+        "- Backend: {}\n- Model: {}",
+        backend.name(),
+        Config::get(ConfigKey::Model)
+    );
+
+    match backend.model_usage().await {
+        Ok(Some(usage)) => res = format!("{res}\n{}", usage.format(&LocaleFormat::from_config())),
+        Ok(None) => res = format!("{res}\n- Memory: not reported, or the model isn't loaded"),
+        Err(err) => {
+            tracing::warn!(error = ?err, "Failed to get model usage");
+            res = format!("{res}\n- Memory: unavailable ({err})");
+        }
+    }
+
+    send_command_result(command, Message::new(Author::Oatmeal, &res), tx).await?;
+
+    return Ok(());
+}
+
+async fn find_similar(
+    backend: &BackendBox,
+    query: &str,
+    candidates: Vec<(usize, String)>,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let mut texts = vec![query.to_string()];
+    texts.extend(candidates.iter().map(|(_, text)| return text.to_string()));
+
+    let embeddings = if Config::get(ConfigKey::EmbeddingProvider) == "local" {
+        LocalEmbeddings::default().embed(&texts).await?
+    } else {
+        backend.embed(&texts).await?
+    };
+    if embeddings.len() != texts.len() {
+        bail!("The backend returned the wrong number of embeddings");
+    }
 
-    let res = models
+    let keyed = candidates
         .iter()
-        .enumerate()
-        .map(|(idx, model)| {
-            let n = idx + 1;
-            return format!("- ({n}) {model}");
-        })
-        .collect::<Vec<String>>();
+        .zip(embeddings[1..].iter())
+        .map(|((index, _), embedding)| return (*index, embedding.clone()))
+        .collect::<Vec<(usize, Vec<f32>)>>();
 
-    tx.send(Event::BackendMessage(Message::new(
-        Author::Oatmeal,
-        res.join("\n").as_str(),
-    )))?;
+    tx.send(Event::SimilarFound(
+        query.to_string(),
+        SimilarMessage::rank(&embeddings[0], &keyed),
+    ))?;
 
     return Ok(());
 }
@@ -81,10 +277,9 @@ async fn model_list(backend: &BackendBox, tx: &mpsc::UnboundedSender<Event>) ->
 async fn model_set(
     backend: &BackendBox,
     tx: &mpsc::UnboundedSender<Event>,
-    text: &str,
+    command: &SlashCommand,
 ) -> Result<()> {
-    let mut model_name = text.split(' ').last().unwrap().to_string();
-    if SlashCommand::parse(&model_name).is_some() {
+    if command.args.is_empty() {
         let msg = Message::new_with_type(
             Author::Oatmeal,
             MessageType::Error,
@@ -93,8 +288,9 @@ async fn model_set(
         tx.send(Event::BackendMessage(msg))?;
         return Ok(());
     }
+    let mut model_name = command.args[0].to_string();
 
-    let mut models = backend.list_models().await?;
+    let mut models = ModelCache::default().list_models(backend).await?;
     models.sort();
 
     if let Ok(idx) = model_name.parse::<usize>() {
@@ -137,6 +333,7 @@ async fn accept_codeblock(
     context: Option<EditorContext>,
     codeblock: String,
     accept_type: AcceptType,
+    force: bool,
     tx: &mpsc::UnboundedSender<Event>,
 ) -> Result<()> {
     let editor_name = EditorName::parse(Config::get(ConfigKey::Editor)).unwrap();
@@ -145,6 +342,37 @@ async fn accept_codeblock(
 
     if editor_name == EditorName::Clipboard || editor_name == EditorName::None {
         context_mut = Some(EditorContext::default());
+    } else if accept_type == AcceptType::Replace && !force {
+        // Don't clobber edits made after the selection was shared with Oatmeal.
+        if let Some(captured) = context_mut.as_ref().filter(|e| return e.end_line.is_some()) {
+            let contents = match editor.get_buffer(&captured.file_path).await {
+                Ok(contents) => contents,
+                Err(err) => {
+                    tx.send(Event::BackendMessage(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        &format!(
+                            "I couldn't read {} from your editor to check the selected lines weren't edited since they were shared with me, so I didn't replace them. Run `/replace --force` to replace them anyway.\n\n{err}",
+                            captured.file_path
+                        ),
+                    )))?;
+
+                    return Ok(());
+                }
+            };
+            if !captured.matches_file(&contents) {
+                tx.send(Event::BackendMessage(Message::new_with_type(
+                    Author::Oatmeal,
+                    MessageType::Error,
+                    &format!(
+                        "The selected lines of {} were edited since they were shared with me, so I didn't replace them. Run `/sync` to use the current selection, or `/replace --force` to replace the original lines anyway.",
+                        captured.file_path
+                    ),
+                )))?;
+
+                return Ok(());
+            }
+        }
     }
 
     if let Some(editor_context) = context_mut {
@@ -156,7 +384,7 @@ async fn accept_codeblock(
             tx.send(Event::BackendMessage(Message::new_with_type(
                 Author::Oatmeal,
                 MessageType::Error,
-                &format!("Failed to commuicate with editor:\n\n{err}"),
+                &format!("Failed to communicate with editor:\n\n{err}"),
             )))?;
         }
     }
@@ -171,123 +399,2232 @@ async fn accept_codeblock(
     return Ok(());
 }
 
-fn copy_messages(messages: Vec<Message>, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
-    let mut payload = messages[0].text.to_string();
-    if messages.len() > 1 {
-        payload = messages
+/// Writes each code block to the file path inferred from the model's answer,
+/// replacing the file's contents.
+async fn apply_codeblocks(
+    codeblocks: Vec<CodeBlock>,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let mut applied = vec![];
+    for codeblock in codeblocks {
+        let path = codeblock.path.unwrap_or_default();
+        let mut res = check_path(&path);
+        if let Some(parent) = Path::new(&path).parent() {
+            if res.is_ok() && !parent.as_os_str().is_empty() {
+                res = fs::create_dir_all(parent)
+                    .await
+                    .map_err(anyhow::Error::from);
+            }
+        }
+        if res.is_ok() {
+            res = write_file(&path, false, &codeblock.code).await;
+        }
+
+        if let Err(err) = res {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Failed to write code block to {path}:\n\n{err}"),
+            )))?;
+
+            return Ok(());
+        }
+
+        applied.push(format!("- {path}"));
+    }
+
+    tx.send(Event::BackendMessage(Message::new(
+        Author::Oatmeal,
+        &format!("Applied code blocks to:\n{}", applied.join("\n")),
+    )))?;
+
+    return Ok(());
+}
+
+/// Displays a combined diff of what `apply_codeblocks` would write, so the
+/// user can confirm before any files are touched.
+async fn preview_codeblocks(
+    codeblocks: Vec<CodeBlock>,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let mut diffs = vec![];
+    for codeblock in codeblocks {
+        let path = codeblock.path.unwrap_or_default();
+        let current = fs::read_to_string(&path).await.unwrap_or_default();
+
+        let mut code = codeblock.code.to_string();
+        if !code.ends_with('\n') {
+            code += "\n";
+        }
+
+        let diff = TextDiff::from_lines(&current, &code)
+            .unified_diff()
+            .header(&path, &path)
+            .to_string();
+
+        if diff.is_empty() {
+            diffs.push(format!("--- {path}\n+++ {path}\n(no changes)\n"));
+        } else {
+            diffs.push(diff);
+        }
+    }
+
+    tx.send(Event::BackendMessage(Message::new(
+        Author::Oatmeal,
+        &format!(
+            "The following changes will be made.\n\n```diff\n{}```\n\nRun `/applyall --yes` to write them.",
+            diffs.join("")
+        ),
+    )))?;
+
+    return Ok(());
+}
+
+async fn run_tests(
+    command: Option<String>,
+    fix: bool,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let command = command.or_else(|| return Shell::infer_test_command(&env::current_dir().ok()?));
+    if command.is_none() {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            "I couldn't work out how to run the tests for this project. Pass the command to use, for example `/test \"make test\"`.",
+        )))?;
+
+        return Ok(());
+    }
+
+    let res = run_shell(&command.unwrap()).await;
+    if let Err(err) = res {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("Failed to run tests:\n\n{err}"),
+        )))?;
+
+        return Ok(());
+    }
+
+    let output = res.unwrap();
+    let cmd = &output.command;
+    if output.success {
+        tx.send(Event::BackendMessage(Message::new(
+            Author::Oatmeal,
+            &format!("`{cmd}` passed."),
+        )))?;
+
+        return Ok(());
+    }
+
+    let exit_code = output
+        .exit_code
+        .map(|e| return e.to_string())
+        .unwrap_or("none".to_string());
+    let report = format!(
+        "`{cmd}` failed with exit code {exit_code}:\n\n```\n{}\n```",
+        Shell::condense_test_output(&output.output())
+    );
+
+    if fix {
+        tx.send(Event::AutoPrompt(format!(
+            "{report}\n\nPlease fix the failing tests."
+        )))?;
+    } else {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("{report}\n\nRun `/test --fix` to ask the model to fix them."),
+        )))?;
+    }
+
+    return Ok(());
+}
+
+/// Lines of output kept when reporting the result of `/run`.
+const MAX_RUN_OUTPUT_LINES: usize = 100;
+
+async fn run_codeblock(codeblock: CodeBlock, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let sandbox = Sandbox::default();
+    let res = match sandbox.run(&codeblock).await {
+        Ok(res) => res,
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Failed to run the code block:\n\n{err}"),
+            )))?;
+
+            return Ok(());
+        }
+    };
+    if let Err(err) = AuditLog::default().record_shell(&res.output).await {
+        tracing::error!(error = ?err, "Failed to write to the audit log");
+    }
+
+    let location = match sandbox.mode {
+        SandboxMode::None => "your working tree".to_string(),
+        SandboxMode::Copy => "a copy of your working tree".to_string(),
+        mode => format!("a {mode} container"),
+    };
+    let exit_code = res
+        .output
+        .exit_code
+        .map(|e| return e.to_string())
+        .unwrap_or("none".to_string());
+
+    let output = res.output.output();
+    let lines = output.lines().collect::<Vec<&str>>();
+    let start = lines.len().saturating_sub(MAX_RUN_OUTPUT_LINES);
+    let mut text =
+        format!("Ran the code block in {location}, and it exited with code {exit_code}.");
+    if !lines.is_empty() {
+        text = format!("{text}\n\n```\n{}\n```", lines[start..].join("\n"));
+    }
+    if !res.changes.is_empty() {
+        let changes = res
+            .changes
             .iter()
-            .map(|message| {
-                return format!("{}: {}", message.author.to_string(), message.text);
-            })
+            .map(|e| return format!("- {e}"))
             .collect::<Vec<String>>()
-            .join("\n\n");
+            .join("\n");
+        text = format!("{text}\n\nIt changed these files in the sandbox, which weren't applied to your working tree:\n{changes}");
     }
 
-    if let Err(err) = ClipboardService::set(payload) {
+    let message = if res.output.success {
+        Message::new(Author::Oatmeal, &text)
+    } else {
+        Message::new_with_type(Author::Oatmeal, MessageType::Error, &text)
+    };
+    tx.send(Event::BackendMessage(message))?;
+
+    return Ok(());
+}
+
+async fn run_check(command: Option<String>, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let mut command = command;
+    if command.is_none() && !Config::get(ConfigKey::CheckCommand).is_empty() {
+        command = Some(Config::get(ConfigKey::CheckCommand));
+    }
+    let command = command.or_else(|| return Shell::infer_check_command(&env::current_dir().ok()?));
+    if command.is_none() {
         tx.send(Event::BackendMessage(Message::new_with_type(
             Author::Oatmeal,
             MessageType::Error,
-            &format!("Failed to copy to clipboard:\n\n{err}"),
+            "I couldn't work out which linter to run for this project. Pass the command to use, for example `/check \"make lint\"`, or set `check-command` in your config.",
         )))?;
 
         return Ok(());
     }
-    tx.send(Event::BackendMessage(Message::new(
-        Author::Oatmeal,
-        "Copied chat log to clipboard.",
+
+    let res = run_shell(&command.unwrap()).await;
+    if let Err(err) = res {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("Failed to run linter:\n\n{err}"),
+        )))?;
+
+        return Ok(());
+    }
+
+    let output = res.unwrap();
+    let cmd = &output.command;
+    let diagnostics = Shell::parse_diagnostics(&output.output());
+    if diagnostics.is_empty() {
+        if output.success {
+            tx.send(Event::BackendMessage(Message::new(
+                Author::Oatmeal,
+                &format!("`{cmd}` found no problems."),
+            )))?;
+        } else {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!(
+                    "`{cmd}` failed, but I couldn't find any diagnostics in its output:\n\n```\n{}\n```",
+                    Shell::condense_test_output(&output.output())
+                ),
+            )))?;
+        }
+
+        return Ok(());
+    }
+
+    let mut context = vec![];
+    for diagnostic in diagnostics.iter() {
+        context.push(diagnostic.format());
+        if let Ok(source) = fs::read_to_string(&diagnostic.file).await {
+            if let Some(line) = source.lines().nth(diagnostic.line.saturating_sub(1)) {
+                context.push(format!("{:>5} | {line}", diagnostic.line));
+            }
+        }
+    }
+
+    tx.send(Event::AutoPrompt(format!(
+        "`{cmd}` reported {} problems:\n\n```\n{}\n```\n\nPlease fix them. Label each code block with the file it belongs to, for example ```` ```rust title=src/main.rs ````.",
+        diagnostics.len(),
+        context.join("\n")
     )))?;
 
     return Ok(());
 }
 
-fn worker_error(err: anyhow::Error, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
-    tx.send(Event::BackendMessage(Message::new_with_type(
-        Author::Oatmeal,
-        MessageType::Error,
-        &format!("The backend failed with the following error: {:?}", err),
-    )))?;
+async fn fetch_github(url: &str, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let reference = GitHubReference::parse(url);
+    if reference.is_none() {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("{url} isn't a GitHub issue or pull request. Use a URL such as `https://github.com/OWNER/REPO/issues/1`, or `OWNER/REPO#1`."),
+        )))?;
+
+        return Ok(());
+    }
+
+    let reference = reference.unwrap();
+    match GitHub::default().fetch(&reference).await {
+        Ok(context) => {
+            tx.send(Event::ContextAttached(
+                format!(
+                    "{}/{}#{}",
+                    reference.owner, reference.repo, reference.number
+                ),
+                context,
+            ))?;
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Failed to fetch from GitHub:\n\n{err}"),
+            )))?;
+        }
+    }
 
     return Ok(());
 }
 
-async fn completions(
-    backend: &BackendBox,
-    prompt: BackendPrompt,
-    tx: &mpsc::UnboundedSender<Event>,
-) -> Result<()> {
-    if Config::get(ConfigKey::Model).is_empty() {
-        let models = backend.list_models().await?;
-        Config::set(ConfigKey::Model, &models[0]);
+async fn fetch_ticket(key: &str, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let provider_name = TicketProviderName::parse(Config::get(ConfigKey::TicketProvider));
+    if provider_name.is_none() {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            "No ticket provider is configured. Set `ticket-provider` to `jira` or `linear` along with its API token.",
+        )))?;
+
+        return Ok(());
     }
 
-    backend.get_completion(prompt, tx).await?;
+    let provider = TicketProviderManager::get(provider_name.unwrap())?;
+    match provider.get_ticket(key).await {
+        Ok(ticket) => {
+            tx.send(Event::ContextAttached(
+                format!("ticket {}", ticket.key),
+                ticket.format(),
+            ))?;
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!(
+                    "Failed to fetch ticket {key} from {}:\n\n{err}",
+                    provider.name()
+                ),
+            )))?;
+        }
+    }
 
     return Ok(());
 }
 
-fn help(tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+async fn post_webhook(
+    name: WebhookName,
+    messages: Vec<Message>,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let webhook = WebhookManager::get(name)?;
+    if let Err(err) = webhook.post(&messages).await {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("Failed to post to {}:\n\n{err}", webhook.name()),
+        )))?;
+
+        return Ok(());
+    }
+
+    let noun = if messages.len() == 1 {
+        "message"
+    } else {
+        "messages"
+    };
     tx.send(Event::BackendMessage(Message::new(
         Author::Oatmeal,
-        &help_text(),
+        &format!("Posted {} {noun} to {}.", messages.len(), webhook.name()),
     )))?;
 
     return Ok(());
 }
 
-pub struct ActionsService {}
+async fn fetch_man_page(
+    topic: &str,
+    section: Option<String>,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    match ManPage::lookup(topic, section.as_deref()).await {
+        Ok(context) => {
+            tx.send(Event::ContextAttached(
+                format!("the documentation for {topic}"),
+                context,
+            ))?;
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &err.to_string(),
+            )))?;
+        }
+    }
 
-impl ActionsService {
-    pub async fn start(
-        backend: BackendBox,
-        tx: mpsc::UnboundedSender<Event>,
-        rx: &mut mpsc::UnboundedReceiver<Action>,
-    ) -> Result<()> {
-        let backend_arc = Arc::new(backend);
+    return Ok(());
+}
 
-        // Lazy default.
-        let mut worker: JoinHandle<Result<()>> = tokio::spawn(async {
-            return Ok(());
-        });
+async fn fetch_rust_docs(path: &str, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let docs_path = DocsPath::parse(path);
+    if docs_path.is_none() {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!(
+                "{path} isn't a Rust item path. Use a path such as `tokio::sync::mpsc::channel`."
+            ),
+        )))?;
 
-        loop {
-            let event = rx.recv().await;
-            if event.is_none() {
-                continue;
-            }
+        return Ok(());
+    }
 
-            let worker_tx = tx.clone();
-            match event.unwrap() {
-                Action::AcceptCodeBlock(context, codeblock, accept_type) => {
-                    accept_codeblock(context, codeblock, accept_type, &tx).await?;
-                }
-                Action::CopyMessages(messages) => {
-                    copy_messages(messages, &tx)?;
-                }
-                Action::BackendAbort() => {
-                    worker.abort();
-                }
-                Action::BackendRequest(prompt) => {
-                    if let Some(command) = SlashCommand::parse(&prompt.text) {
-                        if command.is_model_list() {
-                            model_list(&backend_arc, &tx).await?;
-                            continue;
+    match RustDocs::default().lookup(&docs_path.unwrap()).await {
+        Ok(context) => {
+            tx.send(Event::ContextAttached(
+                format!("the documentation for {path}"),
+                context,
+            ))?;
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &err.to_string(),
+            )))?;
+        }
+    }
+
+    return Ok(());
+}
+
+async fn start_share(addr: &str, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    match ShareHost::start(addr, tx.clone()).await {
+        Ok(host) => {
+            tx.send(Event::ShareStarted(host))?;
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Failed to share the session on {addr}:\n\n{err}"),
+            )))?;
+        }
+    }
+
+    return Ok(());
+}
+
+async fn join_share(addr: &str, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    match ShareGuest::connect(
+        addr,
+        &Config::get(ConfigKey::JoinToken),
+        &Config::get(ConfigKey::Username),
+        tx.clone(),
+    )
+    .await
+    {
+        Ok(guest) => {
+            tx.send(Event::ShareJoined(guest))?;
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Failed to join the session shared on {addr}:\n\n{err}"),
+            )))?;
+        }
+    }
+
+    return Ok(());
+}
+
+/// Posts review comments to a pull request for the repository in the current
+/// directory using the GitHub CLI.
+async fn publish_review(
+    comments: Vec<ReviewComment>,
+    pull_request: &str,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let payload = ReviewComment::to_github_review(&comments)?;
+    let payload_path = env::temp_dir().join("oatmeal-review.json");
+    fs::write(&payload_path, &payload).await?;
+
+    let args = [
+        "gh".to_string(),
+        "api".to_string(),
+        format!("repos/{{owner}}/{{repo}}/pulls/{pull_request}/reviews"),
+        "--method".to_string(),
+        "POST".to_string(),
+        "--input".to_string(),
+        payload_path.to_string_lossy().to_string(),
+    ];
+
+    let res = run_shell_args(&args).await;
+    let error = match res {
+        Ok(output) if output.success => None,
+        Ok(output) => Some(output.output()),
+        Err(err) => Some(err.to_string()),
+    };
+
+    if let Some(err) = error {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("Failed to post the review with `gh`:\n\n{err}"),
+        )))?;
+
+        return Ok(());
+    }
+
+    tx.send(Event::BackendMessage(Message::new(
+        Author::Oatmeal,
+        &format!(
+            "Posted {} review comments to pull request #{pull_request}.",
+            comments.len()
+        ),
+    )))?;
+
+    return Ok(());
+}
+
+async fn sync_editor_context(
+    question: Option<String>,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let editor_name = EditorName::parse(Config::get(ConfigKey::Editor)).unwrap();
+    let editor = EditorManager::get(editor_name)?;
+
+    match editor.get_context().await {
+        Ok(editor_context) => {
+            if let Some(question) = question {
+                tx.send(Event::EditorContextRefreshed(question, editor_context))?;
+            } else {
+                tx.send(Event::EditorContextSynced(editor_context))?;
+            }
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Failed to communicate with editor:\n\n{err}"),
+            )))?;
+        }
+    }
+
+    return Ok(());
+}
+
+fn format_messages(messages: &[Message]) -> String {
+    if messages.len() == 1 {
+        return messages[0].text.to_string();
+    }
+
+    return messages
+        .iter()
+        .map(|message| {
+            return format!("{}: {}", message.author.to_string(), message.text);
+        })
+        .collect::<Vec<String>>()
+        .join("\n\n");
+}
+
+fn copy_messages(messages: Vec<Message>, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let payload = format_messages(&messages);
+
+    if let Err(err) = ClipboardService::set(payload) {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("Failed to copy to clipboard:\n\n{err}"),
+        )))?;
+
+        return Ok(());
+    }
+    tx.send(Event::BackendMessage(Message::new(
+        Author::Oatmeal,
+        "Copied chat log to clipboard.",
+    )))?;
+
+    return Ok(());
+}
+
+async fn diff_session(
+    id: &str,
+    messages: Vec<Message>,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let session = match Sessions::default().load(id).await {
+        Ok(session) => session,
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &ErrorReport::render_error(&err),
+            )))?;
+            return Ok(());
+        }
+    };
+
+    let diff = SessionDiff::new(&messages, &session.state.messages);
+    tx.send(Event::BackendMessage(Message::new(
+        Author::Oatmeal,
+        &diff.render(
+            "this chat",
+            &format!("session {id} ({})", session.state.backend_model),
+        ),
+    )))?;
+
+    return Ok(());
+}
+
+async fn save_macro(recorded: Macro, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let store = MacroStore::default();
+    if let Err(err) = store.save(&recorded).await {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("Failed to save macro `{}`:\n\n{err}", recorded.name),
+        )))?;
+
+        return Ok(());
+    }
+
+    tx.send(Event::BackendMessage(Message::new(
+        Author::Oatmeal,
+        &format!(
+            "Saved macro `{name}` with {count} steps to {path}. Replay it with `/play {name}`.",
+            name = recorded.name,
+            count = recorded.steps.len(),
+            path = store.path.to_string_lossy()
+        ),
+    )))?;
+
+    return Ok(());
+}
+
+async fn play_macro(name: &str, args: &str, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let res = MacroStore::default().get(name).await;
+    let recorded = match res {
+        Ok(Some(recorded)) if !recorded.steps.is_empty() => recorded,
+        Ok(_) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("There's no macro named `{name}`. Record one with `/record {name}`."),
+            )))?;
+            return Ok(());
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Failed to read macros from the config file:\n\n{err}"),
+            )))?;
+            return Ok(());
+        }
+    };
+
+    tx.send(Event::MacroLoaded(name.to_string(), recorded.expand(args)))?;
+
+    return Ok(());
+}
+
+async fn load_few_shot(path: &str, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let res = fs::read_to_string(path)
+        .await
+        .map_err(anyhow::Error::from)
+        .and_then(|text| return FewShotExample::parse_all(&text));
+    match res {
+        Ok(examples) => {
+            tx.send(Event::FewShotLoaded(path.to_string(), examples))?;
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Failed to read few-shot examples from {path}:\n\n{err}"),
+            )))?;
+        }
+    }
+
+    return Ok(());
+}
+
+async fn load_context_document(source: &str, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    match Document::read(source).await {
+        Ok(text) => {
+            tx.send(Event::ContextDocumentLoaded(ContextDocument::new(
+                source, &text,
+            )))?;
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Failed to read {source}:\n\n{err}"),
+            )))?;
+        }
+    }
+
+    return Ok(());
+}
+
+async fn load_image(path: Option<String>, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let source = path.clone().unwrap_or("clipboard".to_string());
+    let res = match &path {
+        Some(path) => fs::read(path).await.map_err(anyhow::Error::from),
+        None => ClipboardService::image().await,
+    }
+    .and_then(|bytes| return ImageAttachment::from_bytes(&source, &bytes));
+
+    match res {
+        Ok(image) => {
+            tx.send(Event::ImageAttached(image))?;
+        }
+        Err(err) => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Failed to attach an image from {source}:\n\n{err}"),
+            )))?;
+        }
+    }
+
+    return Ok(());
+}
+
+/// Runs a tool call from the model, sending back what it output, or why it
+/// couldn't run, for the model to carry on with.
+async fn run_model_tool(
+    invocation: ToolInvocation,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let output = match model_tool_output(&invocation).await {
+        Ok(output) => output,
+        Err(err) => format!("The tool call failed: {err}"),
+    };
+    tx.send(Event::ModelToolFinished(ToolOutput::new(
+        &invocation,
+        &output,
+    )))?;
+
+    return Ok(());
+}
+
+async fn model_tool_output(invocation: &ToolInvocation) -> Result<String> {
+    let tool = match invocation.tool() {
+        Some(tool) if ModelTool::from_config()?.contains(&tool) => tool,
+        _ => {
+            bail!(format!(
+                "`{}` isn't one of the tools in `model-tools`.",
+                invocation.name
+            ))
+        }
+    };
+    let argument = match invocation.argument() {
+        Some(argument) => argument,
+        None => {
+            bail!(format!(
+                "`{}` was called with invalid arguments: {}",
+                invocation.name, invocation.arguments
+            ))
+        }
+    };
+
+    match tool {
+        ModelTool::Shell => {
+            let output = run_shell(&argument).await?;
+            if output.success {
+                return Ok(output.output());
+            }
+            let exit_code = output
+                .exit_code
+                .map(|e| return e.to_string())
+                .unwrap_or("unknown".to_string());
+            return Ok(format!("{}\n\nExit code: {exit_code}", output.output()));
+        }
+        ModelTool::ReadFile => {
+            check_path(&argument)?;
+            return Ok(fs::read_to_string(&argument).await?);
+        }
+    }
+}
+
+/// Writes a file on the user's behalf, recording a diff of the change in the
+/// audit log.
+async fn write_file(path: &str, append: bool, payload: &str) -> Result<()> {
+    check_path(path)?;
+    let current = fs::read_to_string(path).await.unwrap_or_default();
+
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(path)
+        .await?;
+
+    let mut text = payload.to_string();
+    if !text.ends_with('\n') {
+        text += "\n";
+    }
+    file.write_all(text.as_bytes()).await?;
+
+    let updated = if append {
+        format!("{current}{text}")
+    } else {
+        text
+    };
+    let diff = TextDiff::from_lines(&current, &updated)
+        .unified_diff()
+        .header(path, path)
+        .to_string();
+    audit(
+        AuditKind::File,
+        path,
+        Some(diff).filter(|e| return !e.is_empty()),
+    )
+    .await;
+
+    return Ok(());
+}
+
+async fn write_output(
+    output: CommandOutput,
+    messages: Vec<Message>,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let payload = format_messages(&messages);
+
+    let (res, success_msg) = match output {
+        CommandOutput::Clipboard => {
+            (
+                ClipboardService::set(payload),
+                "Copied output to clipboard.".to_string(),
+            )
+        }
+        CommandOutput::File { path, append } => {
+            (
+                write_file(&path, append, &payload).await,
+                format!("Wrote output to {path}."),
+            )
+        }
+    };
+
+    if let Err(err) = res {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("Failed to write command output:\n\n{err}"),
+        )))?;
+
+        return Ok(());
+    }
+
+    tx.send(Event::BackendMessage(Message::new(
+        Author::Oatmeal,
+        &success_msg,
+    )))?;
+
+    return Ok(());
+}
+
+/// Sends the result of a command to the chat window, or to the output target
+/// the user redirected it to.
+async fn send_command_result(
+    command: &SlashCommand,
+    message: Message,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    if let Some(output) = command.output.clone() {
+        return write_output(output, vec![message], tx).await;
+    }
+
+    tx.send(Event::BackendMessage(message))?;
+    return Ok(());
+}
+
+fn worker_error(err: anyhow::Error, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let msg = match ErrorReport::from_error(&err) {
+        Some(report) => Message::new_error(&report.render(), report.actions),
+        None => {
+            Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("The backend failed with the following error: {:?}", err),
+            )
+        }
+    };
+    tx.send(Event::BackendMessage(msg))?;
+
+    return Ok(());
+}
+
+async fn backend_health_check(
+    backend: &BackendBox,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let backend_name = backend.name();
+    let res = backend.health_check().await;
+    tx.send(Event::BackendHealthChecked(BackendHealth::from_check(&res)))?;
+    let msg = match res {
+        Ok(()) => {
+            Message::new(
+                Author::Oatmeal,
+                &format!("Backend {backend_name} is ready now, what can I do for you?"),
+            )
+        }
+        Err(err) => {
+            let text = format!(
+                "Backend {backend_name} still isn't ready.\n\n{}",
+                ErrorReport::render_error(&err)
+            );
+            let actions = ErrorReport::from_error(&err)
+                .map(|e| return e.actions)
+                .unwrap_or_default();
+            Message::new_error(&text, actions)
+        }
+    };
+    tx.send(Event::BackendMessage(msg))?;
+
+    return Ok(());
+}
+
+/// Resolves at the next background health check, or never when they're
+/// turned off.
+async fn next_health_check(health_checks: &mut Option<time::Interval>) {
+    match health_checks {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending::<()>().await,
+    }
+}
+
+/// Health checks the backend in the background for the indicator below the
+/// input box, giving up after `backend-health-check-timeout`.
+async fn monitor_health(backend: &BackendBox, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let timeout = Config::get(ConfigKey::BackendHealthCheckTimeout)
+        .parse::<u64>()
+        .unwrap_or(1000);
+    let res = healthy(backend, Duration::from_millis(timeout)).await;
+    if let Err(err) = &res {
+        tracing::debug!(error = ?err, "Background health check failed");
+    }
+    tx.send(Event::BackendHealthChecked(BackendHealth::from_check(&res)))?;
+
+    return Ok(());
+}
+
+/// Health checks every other backend in turn, switching to the first one
+/// that passes, using its first model.
+async fn switch_backend(
+    current: &BackendName,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<Option<BackendBox>> {
+    for backend_name in BackendName::iter().filter(|e| return e != current) {
+        let backend = BackendManager::get(backend_name.clone())?;
+        if backend.health_check().await.is_err() {
+            continue;
+        }
+        let models = match backend.list_models().await {
+            Ok(models) if !models.is_empty() => models,
+            _ => continue,
+        };
+
+        Config::set(ConfigKey::Backend, &backend_name.to_string());
+        Config::set(ConfigKey::Model, &models[0]);
+        tx.send(Event::BackendSwitched(Message::new(
+            Author::Oatmeal,
+            &format!(
+                "Switched to backend {backend_name}, using model {}. You can use `/model NAME` to switch models.",
+                models[0]
+            ),
+        )))?;
+
+        return Ok(Some(backend));
+    }
+
+    tx.send(Event::BackendMessage(Message::new_error(
+        &format!("None of the other backends are ready either, so I'm sticking with {current}."),
+        vec![RecoveryAction::Retry, RecoveryAction::OpenConfig],
+    )))?;
+
+    return Ok(None);
+}
+
+/// Opens the file with the system's default application.
+fn open_with_system(path: &str) -> std::io::Result<process::Child> {
+    let (program, args) = if cfg!(target_os = "macos") {
+        ("open", vec![])
+    } else if cfg!(target_os = "windows") {
+        ("cmd", vec!["/C", "start", ""])
+    } else {
+        ("xdg-open", vec![])
+    };
+
+    return process::Command::new(program)
+        .args(args)
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+}
+
+/// Renders the diagram to a PNG in the cache directory, named after its
+/// code so rendering it again reuses the file. The image is opened in the
+/// system's viewer, as the chat can't draw images within the terminal.
+async fn render_diagram(
+    codeblock: CodeBlock,
+    kind: DiagramKind,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let mut hasher = DefaultHasher::new();
+    codeblock.code.hash(&mut hasher);
+    let name = format!("diagram-{:x}", hasher.finish());
+
+    let dir = dirs::cache_dir().unwrap().join("oatmeal/diagrams");
+    fs::create_dir_all(&dir).await?;
+    let input = dir.join(format!("{name}.{}", kind.extension()));
+    let output = dir.join(format!("{name}.png"));
+    fs::write(&input, &codeblock.code).await?;
+
+    let output_path = output.to_string_lossy().to_string();
+    let res = process::Command::new(kind.program())
+        .args(kind.args(&input.to_string_lossy(), &output_path))
+        .output()
+        .await;
+
+    let rendered = match res {
+        Ok(rendered) => rendered,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                kind.install_hint(),
+            )))?;
+            return Ok(());
+        }
+        Err(err) => return Err(err.into()),
+    };
+    if !rendered.status.success() {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!(
+                "`{}` couldn't render the diagram:\n\n```\n{}\n```",
+                kind.program(),
+                String::from_utf8_lossy(&rendered.stderr).trim()
+            ),
+        )))?;
+        return Ok(());
+    }
+
+    let msg = match open_with_system(&output_path) {
+        Ok(_) => Message::new(
+            Author::Oatmeal,
+            &format!("Rendered the diagram to {output_path}, and opened it."),
+        ),
+        Err(err) => Message::new(
+            Author::Oatmeal,
+            &format!("Rendered the diagram to {output_path}, but couldn't open it, so open it with your image viewer of choice.\n\nError: {err}"),
+        ),
+    };
+    tx.send(Event::BackendMessage(msg))?;
+
+    return Ok(());
+}
+
+/// Opens the config file with the system's default application, as the
+/// chat's terminal can't be shared with an editor.
+async fn open_config(path: &str, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    let res = open_with_system(path);
+
+    let msg = match res {
+        Ok(_) => Message::new(
+            Author::Oatmeal,
+            &format!("Opened {path}. Changes take effect the next time you start Oatmeal."),
+        ),
+        Err(err) => Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("I couldn't open {path}, you can edit it with your editor of choice instead.\n\nError: {err}"),
+        ),
+    };
+    tx.send(Event::BackendMessage(msg))?;
+
+    return Ok(());
+}
+
+fn url_host(url: &str) -> Vec<String> {
+    return reqwest::Url::parse(url)
+        .ok()
+        .and_then(|e| return e.host_str().map(|e| return e.to_string()))
+        .into_iter()
+        .collect();
+}
+
+/// Hosts `/ticket` connects to for the configured provider.
+fn ticket_hosts() -> Vec<String> {
+    return match TicketProviderName::parse(Config::get(ConfigKey::TicketProvider)) {
+        Some(TicketProviderName::Jira) => url_host(&Config::get(ConfigKey::JiraURL)),
+        Some(TicketProviderName::Linear) => vec!["api.linear.app".to_string()],
+        None => vec![],
+    };
+}
+
+/// Hosts `/post` connects to for the webhook.
+fn webhook_hosts(name: &WebhookName) -> Vec<String> {
+    return match name {
+        WebhookName::Discord => url_host(&Config::get(ConfigKey::DiscordWebhookURL)),
+        WebhookName::Slack => url_host(&Config::get(ConfigKey::SlackWebhookURL)),
+    };
+}
+
+/// Fails when `path` is outside of the directories allowed by `tool-paths`.
+fn check_path(path: &str) -> Result<()> {
+    if !ToolPolicy::from_config()?.allows_path(Path::new(path), &env::current_dir()?) {
+        bail!(format!(
+            "{path} is outside of the directories allowed by `tool-paths`, see `/perms`."
+        ));
+    }
+
+    return Ok(());
+}
+
+/// A tool about to be run on the user's behalf.
+struct ToolCall {
+    tool: &'static str,
+    args: String,
+    /// Hosts the tool connects to.
+    hosts: Vec<String>,
+}
+
+impl ToolCall {
+    fn new(tool: &'static str, args: &str, hosts: Vec<String>) -> ToolCall {
+        return ToolCall {
+            tool,
+            args: args.trim().to_string(),
+            hosts,
+        };
+    }
+
+    fn summary(&self) -> String {
+        return format!("/{} {}", self.tool, self.args).trim().to_string();
+    }
+
+    /// Fails when the tool is denied, or connects to a host not allowed, by
+    /// the tool policy.
+    fn check(&self) -> Result<()> {
+        let tool = self.tool;
+        let policy = ToolPolicy::from_config()?;
+        if policy.permission(tool) == Permission::Deny {
+            bail!(format!(
+                "`/{tool}` is denied by `tool-permissions`, see `/perms`."
+            ));
+        }
+
+        if let Some(host) = self.hosts.iter().find(|e| return !policy.allows_host(e)) {
+            bail!(format!(
+                "`/{tool}` connects to {host}, which isn't allowed by `tool-hosts`, see `/perms`."
+            ));
+        }
+
+        return Ok(());
+    }
+}
+
+/// Records an entry in the audit log. Failing to do so is logged rather than
+/// stopping what's being recorded.
+async fn audit(kind: AuditKind, summary: &str, details: Option<String>) {
+    if let Err(err) = AuditLog::default()
+        .record(&AuditEntry::new(kind, summary, details))
+        .await
+    {
+        tracing::error!(error = ?err, "Failed to write to the audit log");
+    }
+}
+
+async fn run_shell(command: &str) -> Result<ShellOutput> {
+    let output = Shell::run(command).await?;
+    if let Err(err) = AuditLog::default().record_shell(&output).await {
+        tracing::error!(error = ?err, "Failed to write to the audit log");
+    }
+
+    return Ok(output);
+}
+
+async fn run_shell_args(args: &[String]) -> Result<ShellOutput> {
+    let output = Shell::run_args(args).await?;
+    if let Err(err) = AuditLog::default().record_shell(&output).await {
+        tracing::error!(error = ?err, "Failed to write to the audit log");
+    }
+
+    return Ok(output);
+}
+
+/// Runs a tool in the background once the scheduler gives it a turn, letting
+/// the user know when it has to wait. Tools are checked against the tool
+/// policy first, and recorded in the audit log as they start.
+fn spawn_tool<F>(
+    scheduler: &Scheduler,
+    call: ToolCall,
+    tx: &mpsc::UnboundedSender<Event>,
+    run: F,
+) -> Result<()>
+where
+    F: Future<Output = Result<()>> + Send + 'static,
+{
+    if let Err(err) = call.check() {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &err.to_string(),
+        )))?;
+
+        return Ok(());
+    }
+
+    let tool = call.tool;
+    let ticket = scheduler.schedule(tool);
+    if ticket.is_queued() {
+        let mut reasons = vec![];
+        if ticket.ahead > 0 {
+            let noun = if ticket.ahead == 1 { "call" } else { "calls" };
+            reasons.push(format!("{} other tool {noun}", ticket.ahead));
+        }
+        if !ticket.delay.is_zero() {
+            reasons.push(format!("a {:.1}s cooldown", ticket.delay.as_secs_f32()));
+        }
+        tx.send(Event::ToolQueued(format!(
+            "Queued `/{tool}` behind {}.",
+            reasons.join(" and ")
+        )))?;
+    }
+
+    let tx = tx.clone();
+    tokio::spawn(async move {
+        let _permit = ticket.wait().await;
+        audit(AuditKind::Tool, &call.summary(), None).await;
+        if let Err(err) = run.await {
+            tx.send(Event::BackendMessage(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("`/{tool}` failed with the following error: {:?}", err),
+            )))?;
+        }
+        return Ok::<(), anyhow::Error>(());
+    });
+
+    return Ok(());
+}
+
+/// Delay before the first retry of a failed or interrupted request, doubled
+/// for each one after.
+const RETRY_BASE_DELAY_MS: u64 = 1000;
+
+/// Longest wait between retries, however many there have been.
+const RETRY_MAX_DELAY_MS: u64 = 30000;
+
+/// Marks where a response was continued after its stream dropped.
+const STREAM_SEAM_MARKER: &str = "\n\n*[Connection dropped, continued from here]*\n\n";
+
+/// Marks the end of a response that couldn't be continued.
+const STREAM_INCOMPLETE_MARKER: &str = "\n\n*[Connection dropped, this answer is incomplete]*";
+
+/// Marks where a fallback picked up an answer the backend in use stopped
+/// partway through.
+const FALLBACK_SEAM_MARKER: &str = "\n\n*[Continued by a fallback from here]*\n\n";
+
+async fn completions(
+    backend: &BackendBox,
+    prompt: BackendPrompt,
+    tx: &mpsc::UnboundedSender<Event>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    if Config::get(ConfigKey::Model).is_empty() {
+        let models = ModelCache::default().list_models(backend).await?;
+        Config::set(ConfigKey::Model, &models[0]);
+    }
+
+    // Requests that fail for a reason that may pass are sent again. When the
+    // stream drops partway through, the answer so far is kept and the
+    // backend is asked to continue it, with a marker at the seam.
+    let retries = Config::get(ConfigKey::BackendRetries)
+        .parse::<u32>()
+        .unwrap_or(0);
+    let original_prompt = prompt.text.to_string();
+    let mut text = original_prompt.to_string();
+    let mut partial = "".to_string();
+    for attempt in 1..=retries + 1 {
+        // Tools and images go with every attempt, only the text changes.
+        let mut request = prompt.clone();
+        request.text = text.to_string();
+        let err = match backend
+            .get_cancellable_completion(request, tx, cancel.clone())
+            .await
+        {
+            Ok(()) => return Ok(()),
+            Err(err) => err,
+        };
+
+        if let Some(interrupted) = err.downcast_ref::<StreamInterrupted>() {
+            partial += &interrupted.text;
+            let seam = if attempt > retries {
+                STREAM_INCOMPLETE_MARKER
+            } else {
+                STREAM_SEAM_MARKER
+            };
+            tx.send(Event::BackendPromptResponse(BackendResponse {
+                author: Author::Model,
+                text: seam.to_string(),
+                done: false,
+                context: None,
+            }))?;
+            text = continuation_prompt(&original_prompt, &partial);
+        } else if !is_transient(&err) {
+            return Err(err);
+        }
+
+        if attempt > retries {
+            return Err(err);
+        }
+
+        tracing::warn!(attempt, error = ?err, "Backend request failed, retrying it");
+        tx.send(Event::BackendStatus(format!(
+            "Retrying {attempt}/{retries}..."
+        )))?;
+        time::sleep(retry_delay(attempt)).await;
+    }
+
+    return Ok(());
+}
+
+/// Waits twice as long before each retry as the one before, up to a limit.
+fn retry_delay(attempt: u32) -> Duration {
+    let delay = RETRY_BASE_DELAY_MS.saturating_mul(2u64.saturating_pow(attempt - 1));
+    return Duration::from_millis(delay.min(RETRY_MAX_DELAY_MS));
+}
+
+/// Answers the prompt with the backend in use, moving on to each of the
+/// configured fallbacks in turn when it fails its health check, errors, or
+/// times out before any of the answer arrives. One failing partway through
+/// has the next finish the answer from where it stopped. Fallbacks get the
+/// prompt without the chat's context, as it belongs to the backend and model
+/// in use.
+async fn routed_completions(
+    backend: &BackendBox,
+    prompt: BackendPrompt,
+    tx: &mpsc::UnboundedSender<Event>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let fallbacks = Fallback::parse_all(&Config::get(ConfigKey::Fallbacks))?;
+    if fallbacks.is_empty() {
+        return completions(backend, prompt, tx, cancel).await;
+    }
+
+    let timeout = Duration::from_millis(Config::get(ConfigKey::FallbackTimeout).parse::<u64>()?);
+    let primary = format!("{} {}", backend.name(), prompt.model());
+    let text = prompt.text.to_string();
+    let original = prompt.clone();
+    let (err, mut partial, reason) = match healthy(backend, timeout).await {
+        Err(err) => {
+            let reason = format!("{primary} failed its health check: {err}");
+            (err, "".to_string(), reason)
+        }
+        Ok(()) => {
+            match try_completions(backend, prompt, timeout, tx, cancel).await? {
+                None => return Ok(()),
+                Some((err, partial)) if partial.is_empty() => {
+                    let reason = format!("{primary} failed: {err}");
+                    (err, partial, reason)
+                }
+                Some((err, partial)) => {
+                    let reason = format!("{primary} failed partway through: {err}");
+                    (err, partial, reason)
+                }
+            }
+        }
+    };
+    tracing::warn!(error = ?err, "{primary} failed, trying fallbacks");
+
+    for fallback in fallbacks {
+        let fallback_backend = BackendManager::get(fallback.backend.clone())?;
+        if let Err(fallback_err) = healthy(&fallback_backend, timeout).await {
+            tracing::warn!(error = ?fallback_err, "Fallback {fallback} failed its health check");
+            continue;
+        }
+
+        let mut fallback_text = text.to_string();
+        if partial.is_empty() {
+            tx.send(Event::BackendFallback(format!(
+                "Answered by {fallback}, as {reason}"
+            )))?;
+        } else {
+            tx.send(Event::BackendFallback(format!(
+                "Finished by {fallback}, as {reason}"
+            )))?;
+            tx.send(Event::BackendPromptResponse(BackendResponse {
+                author: Author::Model,
+                text: FALLBACK_SEAM_MARKER.to_string(),
+                done: false,
+                context: None,
+            }))?;
+            fallback_text =
+                continuation_prompt(&text, partial.trim_end_matches(STREAM_INCOMPLETE_MARKER));
+        }
+
+        // The fallback's context belongs to another backend, and its model
+        // is set on the prompt, as the task can be dropped at any await.
+        let mut request = original.clone();
+        request.text = fallback_text;
+        request.backend_context = "".to_string();
+        request.model = Some(fallback.model.clone());
+        match try_completions(&fallback_backend, request, timeout, tx, cancel).await? {
+            Some((fallback_err, fallback_partial)) => {
+                tracing::warn!(error = ?fallback_err, "Fallback {fallback} failed");
+                partial += &fallback_partial;
+            }
+            None => return Ok(()),
+        }
+    }
+
+    return Err(err);
+}
+
+/// Checks the backend is reachable before a prompt is routed to it, giving up
+/// after the fallback timeout.
+async fn healthy(backend: &BackendBox, timeout: Duration) -> Result<()> {
+    return match time::timeout(timeout, backend.health_check()).await {
+        Ok(res) => res,
+        Err(_) => {
+            Err(anyhow::anyhow!(
+                "No health check response within {}ms",
+                timeout.as_millis()
+            ))
+        }
+    };
+}
+
+/// Replays the answer to an identical request when `response-cache` is on,
+/// otherwise caching the answer once it's complete. Answers from a fallback,
+/// or that end in tool calls, aren't cached.
+async fn cached_completions(
+    backend: &BackendBox,
+    prompt: BackendPrompt,
+    tx: &mpsc::UnboundedSender<Event>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    if !ResponseCache::is_enabled() {
+        return routed_completions(backend, prompt, tx, cancel).await;
+    }
+
+    let cache = ResponseCache::default();
+    let key = ResponseCache::key(
+        &backend.name(),
+        &prompt.model(),
+        &SamplingParams::from_config(),
+        &prompt,
+    );
+    match cache.get(&key).await {
+        Ok(Some(cached)) => {
+            tx.send(Event::BackendPromptResponse(BackendResponse {
+                author: Author::Model,
+                text: cached.text,
+                done: true,
+                context: cached.context,
+            }))?;
+            return Ok(());
+        }
+        Ok(None) => {}
+        Err(err) => tracing::warn!(error = ?err, "Failed to read the response cache"),
+    }
+
+    let (completion_tx, mut completion_rx) = mpsc::unbounded_channel::<Event>();
+    let request = async move {
+        return routed_completions(backend, prompt, &completion_tx, cancel).await;
+    };
+    tokio::pin!(request);
+
+    let mut response = CachedResponse::default();
+    let mut cacheable = true;
+    let mut forward = |event: Event| -> Result<()> {
+        match &event {
+            Event::BackendPromptResponse(msg) => {
+                response.text += &msg.text;
+                if msg.context.is_some() {
+                    response.context = msg.context.clone();
+                }
+            }
+            Event::BackendFallback(_) | Event::ToolCallsRequested(_) => cacheable = false,
+            _ => {}
+        }
+        tx.send(event)?;
+        return Ok(());
+    };
+    loop {
+        tokio::select! {
+            res = &mut request => {
+                while let Ok(event) = completion_rx.try_recv() {
+                    forward(event)?;
+                }
+                res?;
+                break;
+            }
+            Some(event) = completion_rx.recv() => {
+                forward(event)?;
+            }
+        }
+    }
+
+    if cacheable && !response.text.trim().is_empty() {
+        if let Err(err) = cache.save(&key, &response).await {
+            tracing::warn!(error = ?err, "Failed to save to the response cache");
+        }
+    }
+
+    return Ok(());
+}
+
+/// Forwards a completion to `tx`, giving up when nothing of the answer
+/// arrives within the timeout. Returns the error when the backend failed,
+/// along with what arrived of the answer before it did, so another can be
+/// tried or finish it.
+async fn try_completions(
+    backend: &BackendBox,
+    prompt: BackendPrompt,
+    timeout: Duration,
+    tx: &mpsc::UnboundedSender<Event>,
+    cancel: &CancellationToken,
+) -> Result<Option<(anyhow::Error, String)>> {
+    let (completion_tx, mut completion_rx) = mpsc::unbounded_channel::<Event>();
+    let request = async move {
+        return completions(backend, prompt, &completion_tx, cancel).await;
+    };
+    tokio::pin!(request);
+    let deadline = time::sleep(timeout);
+    tokio::pin!(deadline);
+
+    let mut answering = false;
+    let mut partial = "".to_string();
+    loop {
+        tokio::select! {
+            res = &mut request => {
+                while let Ok(event) = completion_rx.try_recv() {
+                    if let Event::BackendPromptResponse(msg) = &event {
+                        partial += &msg.text;
+                    }
+                    tx.send(event)?;
+                }
+                return match res {
+                    Ok(()) => Ok(None),
+                    Err(err) => Ok(Some((err, partial))),
+                };
+            }
+            Some(event) = completion_rx.recv() => {
+                if let Event::BackendPromptResponse(msg) = &event {
+                    answering = true;
+                    partial += &msg.text;
+                }
+                tx.send(event)?;
+            }
+            _ = &mut deadline, if !answering => {
+                return Ok(Some((
+                    anyhow::anyhow!("No answer within {}ms", timeout.as_millis()),
+                    partial,
+                )));
+            }
+        }
+    }
+}
+
+/// Asks the backend to pick up an answer that was cut off, from the prompt
+/// it was answering and the text received before the stream dropped.
+fn continuation_prompt(original_prompt: &str, partial: &str) -> String {
+    return format!(
+        "{original_prompt}\n\nYour answer to this was cut off by a network error. This is what you wrote so far:\n\n{partial}\n\nContinue exactly where it stops, without repeating any of it or mentioning the interruption."
+    );
+}
+
+/// Rewrites a draft without adding it to the chat, collecting the streamed
+/// response before sending it back to the input box.
+async fn polish_draft(
+    backend: &BackendBox,
+    draft: &str,
+    mode: PolishMode,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let (completion_tx, mut completion_rx) = mpsc::unbounded_channel::<Event>();
+    let prompt = BackendPrompt::new(mode.prompt(draft), "".to_string());
+    completions(backend, prompt, &completion_tx, &CancellationToken::new()).await?;
+
+    let mut text = "".to_string();
+    while let Ok(event) = completion_rx.try_recv() {
+        match event {
+            Event::BackendPromptResponse(msg) => {
+                text += &msg.text;
+            }
+            event => {
+                tx.send(event)?;
+            }
+        }
+    }
+
+    if text.trim().is_empty() {
+        tx.send(Event::BackendMessage(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            "The backend didn't return a rewritten draft.",
+        )))?;
+        return Ok(());
+    }
+
+    tx.send(Event::DraftPolished(text.trim().to_string()))?;
+    return Ok(());
+}
+
+/// Lists the action items in the conversation with the backend, outside of
+/// the chat history, showing them as a checklist and writing them to the
+/// output target when `/todos` was redirected.
+async fn extract_todos(
+    backend: &BackendBox,
+    transcript: &str,
+    output: Option<CommandOutput>,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let (completion_tx, mut completion_rx) = mpsc::unbounded_channel::<Event>();
+    let prompt = BackendPrompt::new(todos_prompt(transcript), "".to_string());
+    completions(backend, prompt, &completion_tx, &CancellationToken::new()).await?;
+
+    let mut text = "".to_string();
+    while let Ok(event) = completion_rx.try_recv() {
+        match event {
+            Event::BackendPromptResponse(msg) => {
+                text += &msg.text;
+            }
+            event => {
+                tx.send(event)?;
+            }
+        }
+    }
+
+    let todos = parse_todos(&text);
+    if todos.is_empty() {
+        tx.send(Event::BackendMessage(Message::new(
+            Author::Oatmeal,
+            "I couldn't find any action items in the conversation.",
+        )))?;
+        return Ok(());
+    }
+
+    let checklist = todos_checklist(&todos);
+    tx.send(Event::BackendMessage(Message::new(
+        Author::Oatmeal,
+        &format!("Action items from the conversation:\n\n{checklist}"),
+    )))?;
+
+    if let Some(output) = output {
+        write_output(output, vec![Message::new(Author::Model, &checklist)], tx).await?;
+    }
+
+    return Ok(());
+}
+
+/// Answers the prompt in place of the oldest messages, summarizing their
+/// transcript first. When no summary comes back they're left out instead, as
+/// sending them all would overflow the context window.
+async fn summarized_completions(
+    backend: &BackendBox,
+    dropped: &str,
+    kept: &str,
+    mut prompt: BackendPrompt,
+    tx: &mpsc::UnboundedSender<Event>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    tx.send(Event::BackendStatus(
+        "Summarizing earlier messages...".to_string(),
+    ))?;
+    let (completion_tx, mut completion_rx) = mpsc::unbounded_channel::<Event>();
+    let request = BackendPrompt::new(summary_prompt(dropped), "".to_string());
+    if let Err(err) = completions(backend, request, &completion_tx, cancel).await {
+        tracing::warn!(error = ?err, "Failed to summarize earlier messages");
+    }
+
+    let mut summary = "".to_string();
+    while let Ok(event) = completion_rx.try_recv() {
+        if let Event::BackendPromptResponse(msg) = event {
+            summary += &msg.text;
+        }
+    }
+    let summary = summary.trim().to_string();
+
+    prompt.prepend_history(
+        Some(&summary)
+            .filter(|e| return !e.is_empty())
+            .map(|e| return e.as_str()),
+        kept,
+    );
+    tx.send(Event::ContextSummarized(summary))?;
+    return routed_completions(backend, prompt, tx, cancel).await;
+}
+
+/// Asks for follow-ups outside of the chat history. Failures are only
+/// logged, as nobody asked for them.
+async fn suggest_replies(
+    backend: &BackendBox,
+    prompt: String,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let (completion_tx, mut completion_rx) = mpsc::unbounded_channel::<Event>();
+    completions(
+        backend,
+        BackendPrompt::new(prompt, "".to_string()),
+        &completion_tx,
+        &CancellationToken::new(),
+    )
+    .await?;
+
+    let mut text = "".to_string();
+    while let Ok(event) = completion_rx.try_recv() {
+        if let Event::BackendPromptResponse(msg) = event {
+            text += &msg.text;
+        }
+    }
+
+    tx.send(Event::RepliesSuggested(ReplySuggestions::parse(&text)))?;
+    return Ok(());
+}
+
+/// Streams the answer of the model numbered `idx` into its `/compare`
+/// column, ending it with the error when the model fails.
+async fn compare_model(
+    idx: usize,
+    backend: &BackendBox,
+    prompt: BackendPrompt,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let (completion_tx, mut completion_rx) = mpsc::unbounded_channel::<Event>();
+    let answer = async move {
+        let res = completions(backend, prompt, &completion_tx, &CancellationToken::new()).await;
+        drop(completion_tx);
+        return res;
+    };
+    let forward = async {
+        while let Some(event) = completion_rx.recv().await {
+            if let Event::BackendPromptResponse(msg) = event {
+                tx.send(Event::ComparisonResponse(idx, msg))?;
+            }
+        }
+        return Ok::<(), anyhow::Error>(());
+    };
+
+    let (res, forwarded) = tokio::join!(answer, forward);
+    forwarded?;
+    let text = match res {
+        Ok(()) => "".to_string(),
+        Err(err) => format!("\n\nFailed to answer: {err}"),
+    };
+    tx.send(Event::ComparisonResponse(
+        idx,
+        BackendResponse {
+            author: Author::Model,
+            text,
+            done: true,
+            context: None,
+        },
+    ))?;
+
+    return Ok(());
+}
+
+/// Sends the prompt to every model of the comparison at once, each with the
+/// backend it's from.
+async fn run_comparison(
+    backend: &BackendBox,
+    comparison: Comparison,
+    prompt: BackendPrompt,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let mut backends = vec![];
+    for model in &comparison.models {
+        if model.backend == backend.name() {
+            backends.push(None);
+        } else {
+            backends.push(Some(BackendManager::get(model.backend.clone())?));
+        }
+    }
+
+    let answers =
+        comparison
+            .models
+            .iter()
+            .zip(&backends)
+            .enumerate()
+            .map(|(idx, (model, other))| {
+                let mut request = prompt.clone();
+                request.model = Some(model.model.to_string());
+                return compare_model(idx, other.as_ref().unwrap_or(backend), request, tx);
+            });
+    for res in futures::future::join_all(answers).await {
+        res?;
+    }
+
+    return Ok(());
+}
+
+/// Answers each prompt variant in turn from the same backend context,
+/// collecting the streamed responses rather than adding them to the chat.
+async fn run_experiment(
+    backend: &BackendBox,
+    prompts: Vec<String>,
+    backend_context: &str,
+    tx: &mpsc::UnboundedSender<Event>,
+) -> Result<()> {
+    let mut variants = vec![];
+    for prompt in prompts {
+        let (completion_tx, mut completion_rx) = mpsc::unbounded_channel::<Event>();
+        let mut request = BackendPrompt::new(prompt.to_string(), backend_context.to_string());
+        request.system_prompt = SystemPromptPresets::system_prompt_from_config();
+        completions(backend, request, &completion_tx, &CancellationToken::new()).await?;
+
+        let mut variant = Variant {
+            prompt,
+            ..Variant::default()
+        };
+        while let Ok(event) = completion_rx.try_recv() {
+            match event {
+                Event::BackendPromptResponse(msg) => {
+                    variant.answer += &msg.text;
+                    if let Some(context) = msg.context {
+                        variant.backend_context = context;
+                    }
+                }
+                Event::BackendStatus(status) => {
+                    tx.send(Event::BackendStatus(status))?;
+                }
+                _ => {}
+            }
+        }
+        variants.push(variant);
+    }
+
+    tx.send(Event::ExperimentCompleted(Experiment::new(
+        &Config::get(ConfigKey::Model),
+        variants,
+    )))?;
+    return Ok(());
+}
+
+async fn help(command: &SlashCommand, tx: &mpsc::UnboundedSender<Event>) -> Result<()> {
+    send_command_result(command, Message::new(Author::Oatmeal, &help_text()), tx).await?;
+    return Ok(());
+}
+
+/// Runs work for the chat on the worker until it's done, or `cancel` is
+/// cancelled, after which the UI is told with `BackendCancelled`.
+fn spawn_cancellable(
+    cancel: &CancellationToken,
+    tx: &mpsc::UnboundedSender<Event>,
+    work: impl Future<Output = Result<()>> + Send + 'static,
+) -> JoinHandle<Result<()>> {
+    let cancel = cancel.clone();
+    let tx = tx.clone();
+    return tokio::spawn(async move {
+        tokio::select! {
+            res = work => {
+                if let Err(err) = res {
+                    worker_error(err, &tx)?;
+                }
+            }
+            _ = cancel.cancelled() => {
+                tx.send(Event::BackendCancelled())?;
+            }
+        }
+        return Ok(());
+    });
+}
+
+pub struct ActionsService {}
+
+impl ActionsService {
+    pub async fn start(
+        backend: BackendBox,
+        tx: mpsc::UnboundedSender<Event>,
+        rx: &mut mpsc::UnboundedReceiver<Action>,
+    ) -> Result<()> {
+        let mut backend_arc = Arc::new(backend);
+        let scheduler = Scheduler::default();
+
+        // Lazy default.
+        let mut worker: JoinHandle<Result<()>> = tokio::spawn(async {
+            return Ok(());
+        });
+        // Cancelled to stop the worker with `CancelGeneration`.
+        let mut generation = CancellationToken::new();
+        // Suggestions have their own worker so they never hold up, or
+        // abort, a prompt.
+        let mut suggestions_worker: JoinHandle<()> = tokio::spawn(async {});
+        // The backend was checked at startup, so the first check waits.
+        let mut health_checks = BackendHealth::interval().map(|period| {
+            let mut interval = time::interval_at(time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(time::MissedTickBehavior::Delay);
+            return interval;
+        });
+
+        loop {
+            let event = tokio::select! {
+                event = rx.recv() => event,
+                _ = next_health_check(&mut health_checks) => {
+                    let backend_worker = backend_arc.clone();
+                    let worker_tx = tx.clone();
+                    tokio::spawn(async move {
+                        return monitor_health(&backend_worker, &worker_tx).await;
+                    });
+                    continue;
+                }
+            };
+            if event.is_none() {
+                continue;
+            }
+
+            let worker_tx = tx.clone();
+            match event.unwrap() {
+                Action::AcceptCodeBlock(context, codeblock, accept_type, force) => {
+                    accept_codeblock(context, codeblock, accept_type, force, &tx).await?;
+                }
+                Action::ApplyCodeBlocks(codeblocks) => {
+                    apply_codeblocks(codeblocks, &tx).await?;
+                }
+                Action::DiffSession(id, messages) => {
+                    diff_session(&id, messages, &tx).await?;
+                }
+                Action::CopyMessages(messages) => {
+                    copy_messages(messages, &tx)?;
+                }
+                Action::PreviewCodeBlocks(codeblocks) => {
+                    preview_codeblocks(codeblocks, &tx).await?;
+                }
+                Action::FetchGitHub(url) => {
+                    let call = ToolCall::new("gh", &url, vec!["api.github.com".to_string()]);
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return fetch_github(&url, &worker_tx).await;
+                    })?;
+                }
+                Action::FetchTicket(key) => {
+                    let call = ToolCall::new("ticket", &key, ticket_hosts());
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return fetch_ticket(&key, &worker_tx).await;
+                    })?;
+                }
+                Action::FetchManPage(topic, section) => {
+                    let args = format!("{} {topic}", section.clone().unwrap_or_default());
+                    let call = ToolCall::new("man", &args, vec![]);
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return fetch_man_page(&topic, section, &worker_tx).await;
+                    })?;
+                }
+                Action::FetchRustDocs(path) => {
+                    let call = ToolCall::new("docs", &path, vec!["docs.rs".to_string()]);
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return fetch_rust_docs(&path, &worker_tx).await;
+                    })?;
+                }
+                Action::PostWebhook(name, messages) => {
+                    let call = ToolCall::new("post", &name.to_string(), webhook_hosts(&name));
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return post_webhook(name, messages, &worker_tx).await;
+                    })?;
+                }
+                Action::PublishReview(comments, pull_request) => {
+                    let call = ToolCall::new(
+                        "review",
+                        &format!("github {pull_request}"),
+                        vec!["api.github.com".to_string()],
+                    );
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return publish_review(comments, &pull_request, &worker_tx).await;
+                    })?;
+                }
+                Action::FindSimilar(query, candidates) => {
+                    let backend_worker = backend_arc.clone();
+                    generation = CancellationToken::new();
+                    worker = spawn_cancellable(&generation, &tx, async move {
+                        return find_similar(&backend_worker, &query, candidates, &worker_tx).await;
+                    });
+                }
+                Action::RunExperiment(prompts, backend_context) => {
+                    let backend_worker = backend_arc.clone();
+                    generation = CancellationToken::new();
+                    worker = spawn_cancellable(&generation, &tx, async move {
+                        return run_experiment(
+                            &backend_worker,
+                            prompts,
+                            &backend_context,
+                            &worker_tx,
+                        )
+                        .await;
+                    });
+                }
+                Action::RunComparison(comparison, prompt) => {
+                    let backend_worker = backend_arc.clone();
+                    generation = CancellationToken::new();
+                    worker = spawn_cancellable(&generation, &tx, async move {
+                        return run_comparison(&backend_worker, comparison, prompt, &worker_tx)
+                            .await;
+                    });
+                }
+                Action::RecordExperiment(experiment) => {
+                    if let Err(err) = ExperimentLog::default().record(&experiment).await {
+                        tracing::warn!(error = ?err, "Failed to record experiment");
+                    }
+                }
+                Action::SaveMacro(recorded) => {
+                    save_macro(recorded, &tx).await?;
+                }
+                Action::PlayMacro(name, args) => {
+                    play_macro(&name, &args, &tx).await?;
+                }
+                Action::LoadFewShot(path) => {
+                    load_few_shot(&path, &tx).await?;
+                }
+                Action::LoadImage(path) => {
+                    load_image(path, &tx).await?;
+                }
+                Action::LoadContextDocument(source) => {
+                    let call = ToolCall::new("context", &source, url_host(&source));
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return load_context_document(&source, &worker_tx).await;
+                    })?;
+                }
+                Action::RefreshModels() => {
+                    let backend_worker = backend_arc.clone();
+                    tokio::spawn(async move {
+                        let msg = match ModelCache::default().refresh(&backend_worker).await {
+                            Ok(models) => {
+                                Message::new(
+                                    Author::Oatmeal,
+                                    &format!(
+                                        "Refreshed the model list from {}, it has {} models.",
+                                        backend_worker.name(),
+                                        models.len()
+                                    ),
+                                )
+                            }
+                            Err(err) => {
+                                Message::new_with_type(
+                                    Author::Oatmeal,
+                                    MessageType::Error,
+                                    &format!("Failed to refresh the model list: {err}"),
+                                )
+                            }
+                        };
+                        if let Err(err) = worker_tx.send(Event::ModelsRefreshed(msg)) {
+                            tracing::warn!(error = ?err, "Failed to report refreshed models");
+                        }
+                    });
+                }
+                Action::PreloadModel() => {
+                    let backend_worker = backend_arc.clone();
+                    tokio::spawn(async move {
+                        if let Err(err) = backend_worker.preload().await {
+                            tracing::warn!(error = ?err, "Failed to preload model");
+                        }
+                    });
+                }
+                Action::RunModelTool(invocation) => {
+                    let tool = invocation
+                        .tool()
+                        .map(|e| return e.permission_tool())
+                        .unwrap_or("run");
+                    let call =
+                        ToolCall::new(tool, &invocation.argument().unwrap_or_default(), vec![]);
+                    // The model waits on an answer either way.
+                    if let Err(err) = call.check() {
+                        tx.send(Event::ModelToolFinished(ToolOutput::new(
+                            &invocation,
+                            &err.to_string(),
+                        )))?;
+                        continue;
+                    }
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return run_model_tool(invocation, &worker_tx).await;
+                    })?;
+                }
+                Action::RunCodeBlock(codeblock) => {
+                    let call = ToolCall::new("run", &codeblock.language, vec![]);
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return run_codeblock(codeblock, &worker_tx).await;
+                    })?;
+                }
+                Action::RenderDiagram(codeblock, kind) => {
+                    let call = ToolCall::new("render", &codeblock.language, vec![]);
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return render_diagram(codeblock, kind, &worker_tx).await;
+                    })?;
+                }
+                Action::RunCheck(command) => {
+                    let call = ToolCall::new("check", &command.clone().unwrap_or_default(), vec![]);
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return run_check(command, &worker_tx).await;
+                    })?;
+                }
+                Action::RunTests(command, fix) => {
+                    let mut args = command.clone().unwrap_or_default();
+                    if fix {
+                        args += " --fix";
+                    }
+                    let call = ToolCall::new("test", &args, vec![]);
+                    spawn_tool(&scheduler, call, &tx, async move {
+                        return run_tests(command, fix, &worker_tx).await;
+                    })?;
+                }
+                Action::StartShare(addr) => {
+                    start_share(&addr, &tx).await?;
+                }
+                Action::JoinShare(addr) => {
+                    join_share(&addr, &tx).await?;
+                }
+                Action::SyncEditorContext() => {
+                    sync_editor_context(None, &tx).await?;
+                }
+                Action::AskWithContext(question) => {
+                    sync_editor_context(Some(question), &tx).await?;
+                }
+                Action::WriteOutput(output, messages) => {
+                    write_output(output, messages, &tx).await?;
+                }
+                Action::CancelGeneration() => {
+                    generation.cancel();
+                    // Nothing is left to say it stopped when the worker
+                    // already finished, such as while a tool runs.
+                    if worker.is_finished() {
+                        tx.send(Event::BackendCancelled())?;
+                    }
+                }
+                Action::BackendHealthCheck() => {
+                    backend_health_check(&backend_arc, &tx).await?;
+                }
+                Action::SwitchBackend() => {
+                    if let Some(backend) = switch_backend(&backend_arc.name(), &tx).await? {
+                        backend_arc = Arc::new(backend);
+                    }
+                }
+                Action::OpenConfig(path) => {
+                    open_config(&path, &tx).await?;
+                }
+                Action::SuggestReplies(prompt) => {
+                    suggestions_worker.abort();
+                    let backend_worker = backend_arc.clone();
+                    suggestions_worker = tokio::spawn(async move {
+                        if let Err(err) = suggest_replies(&backend_worker, prompt, &worker_tx).await
+                        {
+                            tracing::warn!(error = ?err, "Failed to suggest replies");
+                        }
+                    });
+                }
+                Action::CancelSuggestions() => {
+                    suggestions_worker.abort();
+                }
+                Action::PolishDraft(draft, mode) => {
+                    suggestions_worker.abort();
+                    let backend_worker = backend_arc.clone();
+                    generation = CancellationToken::new();
+                    worker = spawn_cancellable(&generation, &tx, async move {
+                        return polish_draft(&backend_worker, &draft, mode, &worker_tx).await;
+                    });
+                }
+                Action::SaveConfig(key, value) => {
+                    if let Err(err) = Config::save(key, &value).await {
+                        tx.send(Event::BackendMessage(Message::new_with_type(
+                            Author::Oatmeal,
+                            MessageType::Error,
+                            &format!("Failed to save {key} to the config file:\n\n{err}"),
+                        )))?;
+                    }
+                }
+                Action::ExtractTodos(transcript, output) => {
+                    suggestions_worker.abort();
+                    let backend_worker = backend_arc.clone();
+                    generation = CancellationToken::new();
+                    worker = spawn_cancellable(&generation, &tx, async move {
+                        return extract_todos(&backend_worker, &transcript, output, &worker_tx)
+                            .await;
+                    });
+                }
+                Action::BackendRequest(prompt) => {
+                    suggestions_worker.abort();
+                    if let Some(command) = SlashCommand::parse(&prompt.text) {
+                        if command.is_model_list() {
+                            model_list(&backend_arc, &tx, &command).await?;
+                            continue;
                         }
                         if command.is_model_set() {
-                            model_set(&backend_arc, &tx, &prompt.text).await?;
+                            model_set(&backend_arc, &tx, &command).await?;
+                            continue;
+                        }
+                        if command.is_stats() {
+                            stats(&backend_arc, &tx, &command).await?;
                             continue;
                         }
                         if command.is_help() {
-                            help(&tx)?;
+                            help(&command, &tx).await?;
                             continue;
                         }
                     }
 
                     let backend_worker = backend_arc.clone();
-                    worker = tokio::spawn(async move {
-                        if let Err(err) = completions(&backend_worker, prompt, &worker_tx).await {
-                            worker_error(err, &worker_tx)?;
-                        }
-                        return Ok(());
+                    generation = CancellationToken::new();
+                    let cancel = generation.clone();
+                    worker = spawn_cancellable(&generation, &tx, async move {
+                        return cached_completions(&backend_worker, prompt, &worker_tx, &cancel)
+                            .await;
+                    });
+                }
+                Action::SummarizeContext(dropped, kept, prompt) => {
+                    suggestions_worker.abort();
+                    let backend_worker = backend_arc.clone();
+                    generation = CancellationToken::new();
+                    let cancel = generation.clone();
+                    worker = spawn_cancellable(&generation, &tx, async move {
+                        return summarized_completions(
+                            &backend_worker,
+                            &dropped,
+                            &kept,
+                            prompt,
+                            &worker_tx,
+                            &cancel,
+                        )
+                        .await;
                     });
                 }
             }
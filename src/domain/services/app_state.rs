@@ -1,30 +1,118 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path;
+use std::time::Duration;
+
 use anyhow::anyhow;
 use anyhow::Result;
 use ratatui::prelude::Rect;
 use tokio::sync::mpsc;
 
+use super::Bubble;
 use super::BubbleList;
 use super::CodeBlocks;
+use super::MessageArchive;
+use super::ModelCache;
+use super::Outline;
 use super::Scroll;
+use super::SessionLock;
 use super::Sessions;
+use super::Shell;
+use super::StartupProfile;
+use super::Tee;
 use super::Themes;
+use super::COMPACT_LAYOUT_HEIGHT;
 use crate::configuration::Config;
 use crate::configuration::ConfigKey;
+use crate::domain::models::context_window;
+use crate::domain::models::estimate_tokens;
+use crate::domain::models::split_context;
+use crate::domain::models::transcript;
 use crate::domain::models::AcceptType;
 use crate::domain::models::Action;
+use crate::domain::models::AppliedRewrite;
+use crate::domain::models::AttachedContext;
 use crate::domain::models::Author;
 use crate::domain::models::BackendBox;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendHealth;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
 use crate::domain::models::BackendResponse;
+use crate::domain::models::Checkpoint;
+use crate::domain::models::Comparison;
+use crate::domain::models::ContextDocument;
+use crate::domain::models::ContextDocuments;
+use crate::domain::models::ContextOverflow;
+use crate::domain::models::ContextUsage;
+use crate::domain::models::DiagramKind;
 use crate::domain::models::EditorBox;
 use crate::domain::models::EditorContext;
+use crate::domain::models::ErrorReport;
+use crate::domain::models::Experiment;
+use crate::domain::models::FewShotExample;
+use crate::domain::models::GenerationProgress;
+use crate::domain::models::ImageAttachment;
+use crate::domain::models::Macro;
 use crate::domain::models::Message;
 use crate::domain::models::MessageType;
+use crate::domain::models::ModelPrices;
+use crate::domain::models::ModelTool;
+use crate::domain::models::Permission;
+use crate::domain::models::PolishMode;
+use crate::domain::models::PromptVariables;
+use crate::domain::models::QuitPrompt;
+use crate::domain::models::RecoveryAction;
+use crate::domain::models::ReplySuggestions;
+use crate::domain::models::ReviewComment;
+use crate::domain::models::RewriteRules;
+use crate::domain::models::RewriteTarget;
+use crate::domain::models::SamplingParam;
+use crate::domain::models::Share;
+use crate::domain::models::ShareGuest;
+use crate::domain::models::ShareHost;
+use crate::domain::models::ShareMessage;
+use crate::domain::models::SimilarMessage;
 use crate::domain::models::SlashCommand;
+use crate::domain::models::SystemPromptPresets;
+use crate::domain::models::Tokenizer;
+use crate::domain::models::ToolApproval;
+use crate::domain::models::ToolApprovalChoice;
+use crate::domain::models::ToolInvocation;
+use crate::domain::models::ToolOutput;
+use crate::domain::models::ToolPolicy;
+use crate::domain::models::Translation;
+use crate::domain::models::WebhookName;
+use crate::domain::models::DEFAULT_SHARE_ADDRESS;
 
 #[cfg(test)]
 #[path = "app_state_test.rs"]
 mod tests;
 
+/// Characters of each message embedded by `/similar`, keeping requests
+/// within the context of embedding models.
+const MAX_SIMILAR_CHARS: usize = 2000;
+
+/// Number of matches listed by `/similar`.
+const MAX_SIMILAR_RESULTS: usize = 3;
+
+/// How long a `{{sh:COMMAND}}` placeholder may run before the prompt is
+/// abandoned.
+const PROMPT_COMMAND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Shown while waiting on the worker to stop an answer.
+const STOPPING_STATUS: &str = "Stopping...";
+
+/// Marks the end of an answer stopped with Esc or `/stop`.
+const STOPPED_MARKER: &str = "\n\n*[Stopped, this answer is incomplete]*";
+
+/// Prompt sent by `/continue` to pick up an answer that hit the output
+/// limit.
+const CONTINUE_PROMPT: &str = "Your last answer was cut off by the output limit. Continue it exactly where it stops, without repeating any of it.";
+
 pub struct AppStateProps {
     pub backend: BackendBox,
     pub editor: EditorBox,
@@ -36,17 +124,98 @@ pub struct AppStateProps {
 }
 
 pub struct AppState<'a> {
+    pub archive: MessageArchive,
+    /// Context attached with commands such as `/gh`, sent with the next
+    /// prompt.
+    pub attached_context: Vec<AttachedContext>,
+    /// Images attached with `/image`, sent with the next prompt.
+    pub attached_images: Vec<ImageAttachment>,
     pub backend_context: String,
+    /// From the last health check, at startup or in the background.
+    pub backend_health: BackendHealth,
+    /// Shown while waiting on the backend before its response starts.
+    pub backend_status: Option<String>,
     pub bubble_list: BubbleList<'a>,
+    /// Snapshots taken with `/checkpoint`.
+    pub checkpoints: Vec<Checkpoint>,
     pub codeblocks: CodeBlocks,
+    /// Documents added with `/context add`, sent ahead of every prompt.
+    pub context_documents: ContextDocuments,
+    /// Whether the backend context already has `context_documents`.
+    pub context_documents_sent: bool,
+    /// First message still in the backend's context, once older ones were
+    /// summarized or left out to fit the context window.
+    pub context_start: usize,
+    /// What older messages were summarized as, sent ahead of the rest.
+    pub context_summary: Option<String>,
+    /// What's in the input box, saved with the session so it isn't lost on
+    /// quitting.
+    pub draft: String,
     pub editor_context: Option<EditorContext>,
-    pub exit_warning: bool,
+    /// Example exchanges sent ahead of each conversation, from
+    /// `few-shot-file` or `/fewshot`.
+    pub few_shot: Vec<FewShotExample>,
+    /// Whether the backend context already starts with `few_shot`.
+    pub few_shot_sent: bool,
     pub last_known_height: usize,
     pub last_known_width: usize,
+    /// Steps of the macro being played, sent once the backend is free.
+    pub macro_queue: VecDeque<String>,
     pub messages: Vec<Message>,
+    pub outline: Outline,
+    /// Estimated length of each answer this run, to judge how far along the
+    /// next one is.
+    pub past_answer_tokens: Vec<u64>,
+    /// Where `/continue` started appending to the last answer.
+    pub pending_continuation: Option<usize>,
+    /// Answers from `/ab` waiting for a winner to be picked.
+    pub pending_experiment: Option<Experiment>,
+    /// Answers streaming in for `/compare`, with the index of the message
+    /// showing them.
+    pub pending_comparison: Option<(usize, Comparison)>,
+    /// Rules from `rewrite-rules` that changed the last prompt and answer,
+    /// for `/rewrites`.
+    pub applied_rewrites: Vec<AppliedRewrite>,
+    /// Which fallback is answering the last prompt, and why.
+    pub pending_fallback: Option<String>,
+    /// How older messages were made to fit the context window for the last
+    /// prompt.
+    pub pending_context_note: Option<String>,
+    pub pending_translation: Option<Translation>,
+    /// Tool calls from the model still to approve, in the order it asked.
+    pub pending_tool_calls: VecDeque<ToolInvocation>,
+    /// Whether the next response answers the tool outputs, in a message of
+    /// its own after them.
+    pub pending_tool_answer: bool,
+    /// Asks what to do about unsaved changes or the answer being generated
+    /// before quitting, while it's open.
+    pub quit_prompt: Option<QuitPrompt>,
+    /// Macro being recorded with `/record`.
+    pub recording_macro: Option<Macro>,
+    /// Fingerprint of what the session was last saved or opened with, to
+    /// tell when quitting would lose changes.
+    saved_fingerprint: u64,
     pub scroll: Scroll,
     pub session_id: String,
+    /// `None` when the session is already open in another Oatmeal, leaving
+    /// this one read-only until it's forked.
+    pub session_lock: Option<SessionLock>,
     pub sessions_service: Sessions,
+    pub share: Option<Share>,
+    /// Follow-ups offered after the last answer, from `suggest-replies`.
+    pub suggestions: ReplySuggestions,
+    /// Transcript prompts and answers are appended to, from `tee` or `/tee`.
+    pub tee: Option<Tee>,
+    /// Asks before running the next of `pending_tool_calls`, while it's
+    /// open.
+    pub tool_approval: Option<ToolApproval>,
+    /// What the model's tool calls returned so far, sent back once all of
+    /// them are done.
+    pub tool_outputs: Vec<ToolOutput>,
+    /// Which tools may run, from `tool-permissions`.
+    pub tool_policy: ToolPolicy,
+    /// Set with `/set var`, for `{{NAME}}` placeholders in prompts.
+    pub variables: PromptVariables,
     pub waiting_for_backend: bool,
 }
 
@@ -61,47 +230,116 @@ impl<'a> AppState<'a> {
 
     async fn init(props: AppStateProps) -> Result<AppState<'a>> {
         let mut model_name = props.model_name.to_string();
-        let theme = Themes::get(&props.theme_name, &props.theme_file)?;
+        let theme = Themes::load_in_background(&props.theme_name, &props.theme_file);
+
+        let mut messages = vec![];
+        let backend_name = props.backend.name();
+        let health_check = props.backend.health_check().await;
+        let backend_health = BackendHealth::from_check(&health_check);
+        if let Err(err) = health_check {
+            // Any failed health check can be retried, or worked around with
+            // another backend.
+            let actions = ErrorReport::from_error(&err)
+                .map(|e| return e.actions)
+                .unwrap_or_else(|| {
+                    return vec![
+                        RecoveryAction::Retry,
+                        RecoveryAction::SwitchBackend,
+                        RecoveryAction::OpenConfig,
+                    ];
+                });
+            // Without a model loaded the backend is fine otherwise, so only
+            // say how to load one.
+            let no_model_loaded = err.chain().find_map(|e| {
+                return match e.downcast_ref::<BackendError>() {
+                    Some(e @ BackendError::NoModelLoaded { .. }) => ErrorReport::from(e).hint,
+                    _ => None,
+                };
+            });
+            let text = match no_model_loaded {
+                Some(hint) => format!("Hey, backend {backend_name} is running, but it doesn't have a model loaded for me to talk to yet.\n\n{hint}"),
+                None => format!("Hey, it looks like backend {backend_name} isn't ready, I can't talk to it. You should double check that before we start talking, otherwise I may crash.\n\n{}", ErrorReport::render_error(&err)),
+            };
+            messages.push(Message::new_error(&text, actions));
+        } else {
+            let models = ModelCache::default().list_models(&props.backend).await?;
+            if model_name.is_empty() {
+                model_name = models[0].to_string();
+                // TODO refactor this out later.
+                Config::set(ConfigKey::Model, &model_name);
+            } else if !models.contains(&model_name.to_string()) {
+                let report = ErrorReport::from(&BackendError::ModelMissing {
+                    backend: backend_name.clone(),
+                    model: model_name.to_string(),
+                });
+                messages.push(Message::new_error(&report.render(), report.actions));
+            }
+        }
+        if !ModelTool::from_config()?.is_empty() && !props.backend.supports_tools() {
+            messages.push(Message::new(
+                Author::Oatmeal,
+                &format!("Backend {backend_name} doesn't support tool calls, so `model-tools` is ignored."),
+            ));
+        }
+        StartupProfile::mark("backend checked");
+
+        let theme = theme.await??;
+        StartupProfile::mark("theme loaded");
+
+        let session_id = Sessions::create_id();
+        let session_lock = props.sessions_service.lock(&session_id)?;
 
         let mut app_state = AppState {
+            archive: MessageArchive::default(),
+            attached_context: vec![],
+            attached_images: vec![],
             backend_context: "".to_string(),
+            backend_health,
+            backend_status: None,
             bubble_list: BubbleList::new(theme),
+            checkpoints: vec![],
             codeblocks: CodeBlocks::default(),
+            context_documents: ContextDocuments::default(),
+            context_documents_sent: false,
+            context_start: 0,
+            context_summary: None,
+            draft: "".to_string(),
             editor_context: None,
-            exit_warning: false,
+            few_shot: vec![],
+            few_shot_sent: false,
             last_known_height: 0,
             last_known_width: 0,
-            messages: vec![],
+            macro_queue: VecDeque::new(),
+            messages,
+            outline: Outline::from_config(),
+            past_answer_tokens: vec![],
+            pending_continuation: None,
+            pending_experiment: None,
+            pending_comparison: None,
+            applied_rewrites: vec![],
+            pending_fallback: None,
+            pending_context_note: None,
+            pending_translation: None,
+            pending_tool_calls: VecDeque::new(),
+            pending_tool_answer: false,
+            quit_prompt: None,
+            recording_macro: None,
+            suggestions: ReplySuggestions::default(),
+            saved_fingerprint: 0,
             scroll: Scroll::default(),
-            session_id: Sessions::create_id(),
+            session_id,
+            session_lock,
             sessions_service: props.sessions_service,
+            share: None,
+            tee: Tee::from_config()?,
+            tool_approval: None,
+            tool_outputs: vec![],
+            tool_policy: ToolPolicy::from_config()?,
+            variables: PromptVariables::default(),
             waiting_for_backend: false,
         };
-
-        let backend_name = props.backend.name();
-        if let Err(err) = props.backend.health_check().await {
-            app_state
-                .messages
-                .push(Message::new_with_type(
-                    Author::Oatmeal,
-                    MessageType::Error,
-                    &format!("Hey, it looks like backend {backend_name} isn't running, I can't connect to it. You should double check that before we start talking, otherwise I may crash.\n\nError: {err}"),
-                ));
-        } else {
-            let models = props.backend.list_models().await?;
-            if model_name.is_empty() {
-                model_name = models[0].to_string();
-                // TODO refactor this out later.
-                Config::set(ConfigKey::Model, &model_name);
-            } else if !models.contains(&model_name.to_string()) {
-                app_state
-                .messages
-                .push(Message::new_with_type(
-                    Author::Oatmeal,
-                    MessageType::Error,
-                    format!("Model {model_name} doesn't exist for backend {backend_name}. You can use `/modellist` to view all available models, and `/model NAME` to switch models.").as_str(),
-                ));
-            }
+        if Config::get(ConfigKey::ShowTokenUsage) == "true" {
+            app_state.toggle_usage();
         }
 
         // Fallback to the default intro message when there's no editor context.
@@ -111,38 +349,89 @@ impl<'a> AppState<'a> {
                 "Hey there! What can I do for you?",
             ));
         }
+        StartupProfile::mark("editor context loaded");
 
+        app_state.saved_fingerprint = app_state.fingerprint();
         return Ok(app_state);
     }
 
     async fn from_session(props: AppStateProps) -> Result<AppState<'a>> {
         let session_id = props.session_id.clone().unwrap().to_string();
+        let theme = Themes::load_in_background(&props.theme_name, &props.theme_file);
         let session = props.sessions_service.load(&session_id).await?;
-        let theme = Themes::get(&props.theme_name, &props.theme_file)?;
+        let session_lock = props.sessions_service.lock(&session_id)?;
+        let theme = theme.await??;
+        StartupProfile::mark("theme loaded");
 
         let mut app_state = AppState {
+            archive: MessageArchive::default(),
+            attached_context: vec![],
+            attached_images: vec![],
             backend_context: session.state.backend_context,
+            backend_health: BackendHealth::Unknown,
+            backend_status: None,
             bubble_list: BubbleList::new(theme),
+            checkpoints: vec![],
             codeblocks: CodeBlocks::default(),
+            context_documents: ContextDocuments::new(session.state.context_documents),
+            context_documents_sent: true,
+            context_start: 0,
+            context_summary: None,
+            draft: session.state.draft,
             editor_context: None,
-            exit_warning: false,
+            few_shot: session.state.few_shot,
+            few_shot_sent: true,
             last_known_height: 0,
             last_known_width: 0,
+            macro_queue: VecDeque::new(),
             messages: session.state.messages,
+            outline: Outline::from_config(),
+            past_answer_tokens: vec![],
+            pending_continuation: None,
+            pending_experiment: None,
+            pending_comparison: None,
+            applied_rewrites: vec![],
+            pending_fallback: None,
+            pending_context_note: None,
+            pending_translation: None,
+            pending_tool_calls: VecDeque::new(),
+            pending_tool_answer: false,
+            quit_prompt: None,
+            recording_macro: None,
+            suggestions: ReplySuggestions::default(),
+            saved_fingerprint: 0,
             scroll: Scroll::default(),
             session_id,
+            session_lock,
             sessions_service: props.sessions_service,
+            share: None,
+            tee: Tee::from_config()?,
+            tool_approval: None,
+            tool_outputs: vec![],
+            tool_policy: ToolPolicy::from_config()?,
+            variables: PromptVariables::new(session.state.variables),
             waiting_for_backend: false,
         };
+        if Config::get(ConfigKey::ShowTokenUsage) == "true" {
+            app_state.toggle_usage();
+        }
 
         app_state
             .codeblocks
             .replace_from_messages(&app_state.messages);
 
+        if app_state.session_lock.is_none() {
+            app_state.messages.push(Message::new(
+                Author::Oatmeal,
+                &format!("Session {} is already open in another Oatmeal, so this one is read-only to keep from saving over its changes. Run `/fork` to carry on here as a new session.", app_state.session_id),
+            ));
+        }
+
         if props.editor.health_check().await.is_ok() {
             app_state.editor_context = props.editor.get_context().await?;
         }
 
+        app_state.saved_fingerprint = app_state.fingerprint();
         return Ok(app_state);
     }
 
@@ -154,7 +443,7 @@ impl<'a> AppState<'a> {
                 .push(Message::new_with_type(
                     Author::Oatmeal,
                     MessageType::Error,
-                    &format!("Whoops, it looks like editor {editor_name} isn't setup properly. You should double check that before we start talking, otherwise I may crash.\n\nError: {err}"),
+                    &format!("Whoops, it looks like editor {editor_name} isn't setup properly. You should double check that before we start talking, otherwise I may crash.\n\n{}", ErrorReport::render_error(&err)),
                 ));
 
             return Ok(());
@@ -177,23 +466,75 @@ impl<'a> AppState<'a> {
         }
     }
 
+    /// Statuses arriving after the response started, or was aborted, are
+    /// stale and ignored.
+    pub fn handle_backend_status(&mut self, status: String) {
+        if self.waiting_for_backend {
+            self.backend_status = Some(status);
+        }
+    }
+
     pub fn handle_backend_response(&mut self, msg: BackendResponse) {
+        self.backend_status = None;
+        if let Some(Share::Host(host)) = &self.share {
+            host.publish_append(msg.author.clone(), &msg.text);
+        }
+
+        let answers_tools = std::mem::take(&mut self.pending_tool_answer);
         let last_message = self.messages.last_mut().unwrap();
-        if last_message.author != Author::User {
+        if last_message.author != Author::User && !answers_tools {
             last_message.append(&msg.text);
         } else {
-            self.messages.push(Message::new(msg.author, &msg.text));
+            let mut message = Message::new(msg.author, &msg.text);
+            message.set_seed(Config::get(ConfigKey::Seed).parse::<u64>().ok());
+            self.messages.push(message);
         }
+        let name = self.messages.last().unwrap().author_name();
+        self.write_tee(|tee| return tee.answer(&name, &msg.text, msg.done));
 
         self.sync_dependants();
 
         if msg.done {
             self.waiting_for_backend = false;
-            if let Some(ctx) = msg.context {
-                self.backend_context = ctx;
+            if let Some(last) = self.messages.last() {
+                if last.author == Author::Model {
+                    self.past_answer_tokens.push(last.tokens());
+                }
+            }
+            if let Some(seam) = self.pending_continuation.take() {
+                self.messages.last_mut().unwrap().join_continuation(seam);
+                self.bubble_list.clear_cache();
+                self.sync_dependants();
+            }
+            // A fallback's context belongs to another backend or model, so
+            // the one in use keeps its own.
+            let fallback = self.pending_fallback.take();
+            if let Some(note) = &fallback {
+                let last_message = self.messages.last_mut().unwrap();
+                last_message.text = format!("{}\n\n*[{note}]*", last_message.text.trim_end());
+                self.bubble_list.clear_cache();
+                self.sync_dependants();
             }
+            if let Some(note) = self.pending_context_note.take() {
+                let last_message = self.messages.last_mut().unwrap();
+                last_message.text = format!("{}\n\n*[{note}]*", last_message.text.trim_end());
+                self.bubble_list.clear_cache();
+                self.sync_dependants();
+            }
+            if let Some(translation) = self.pending_translation.take() {
+                let last_message = self.messages.last_mut().unwrap();
+                last_message.text = translation.restore(&last_message.text);
+                self.backend_context = translation.backend_context;
+                self.bubble_list.clear_cache();
+                self.sync_dependants();
+            } else if let Some(ctx) = msg.context {
+                if fallback.is_none() {
+                    self.backend_context = ctx;
+                }
+            }
+            self.rewrite_answer();
 
-            if self.backend_context.is_empty() {
+            if self.backend_context.is_empty() && fallback.is_none() {
                 self.add_message(Message::new_with_type(
                     Author::Oatmeal,
                     MessageType::Error,
@@ -202,7 +543,39 @@ impl<'a> AppState<'a> {
                 self.sync_dependants();
             }
 
-            self.codeblocks.replace_from_messages(&self.messages);
+            // Blocks the model didn't label are most likely in the language
+            // of the file being edited.
+            if let Some(context) = &self.editor_context {
+                let language = context.language.to_string();
+                if let Some(last) = self
+                    .messages
+                    .iter_mut()
+                    .rev()
+                    .find(|e| return e.author == Author::Model)
+                {
+                    last.set_default_codeblock_language(&language);
+                    self.bubble_list.clear_cache();
+                }
+            }
+
+            self.codeblocks
+                .replace_from_messages(&self.archive.restore_all(&self.messages));
+
+            let review_comments = self
+                .messages
+                .iter()
+                .rev()
+                .find(|e| return e.author == Author::Model)
+                .map(|e| return ReviewComment::parse_all(&e.text))
+                .unwrap_or_default();
+            if !review_comments.is_empty() {
+                self.add_message(Message::new(
+                    Author::Oatmeal,
+                    &format!("Found {} review comments. Export them with `/review rdjson > review.json`, or `/review github PR_NUMBER` to post them to a pull request.", review_comments.len()),
+                ));
+            }
+
+            self.publish_share();
         }
     }
 
@@ -215,16 +588,56 @@ impl<'a> AppState<'a> {
         let mut should_continue = false;
 
         if let Some(command) = SlashCommand::parse(input_str) {
+            if let Err(err) = command.validate() {
+                self.add_message(Message::new_with_type(
+                    Author::Oatmeal,
+                    MessageType::Error,
+                    &err.to_string(),
+                ));
+
+                return Ok((should_break, true));
+            }
+
+            if let Some(tool) = command.tool_name() {
+                let permission = self.tool_policy.permission(tool);
+                if permission == Permission::Deny {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        &format!("`/{tool}` is denied by `tool-permissions`, see `/perms`."),
+                    ));
+
+                    return Ok((should_break, true));
+                }
+
+                if permission == Permission::Ask && !command.has_flag("allow") {
+                    self.add_message(Message::new(
+                        Author::Oatmeal,
+                        &format!("`/{tool}` is set to ask before running. Add `--allow` to the command to run it this time."),
+                    ));
+
+                    return Ok((should_break, true));
+                }
+            }
+
             if command.is_quit() {
                 should_break = true;
             }
 
+            if command.is_perms() {
+                should_continue = true;
+                self.add_message(Message::new(Author::Oatmeal, &self.tool_policy.format()));
+            }
+
             if command.is_append_code_block()
                 || command.is_replace_code_block()
                 || command.is_copy_code_block()
             {
                 should_continue = true;
-                let codeblocks_res = self.codeblocks.blocks_from_slash_commands(&command);
+                let codeblocks_res = match self.default_append_codeblock(&command) {
+                    Some(code) => Ok(code),
+                    None => self.codeblocks.blocks_from_slash_commands(&command),
+                };
                 if let Err(err) = codeblocks_res.as_ref() {
                     self.add_message(Message::new_with_type(
                         Author::Oatmeal,
@@ -238,6 +651,16 @@ impl<'a> AppState<'a> {
                     return Ok((should_break, should_continue));
                 }
 
+                if let Some(output) = command.output.clone() {
+                    tx.send(Action::WriteOutput(
+                        output,
+                        vec![Message::new(Author::Model, &codeblocks_res.unwrap())],
+                    ))?;
+
+                    self.waiting_for_backend = true;
+                    return Ok((should_break, should_continue));
+                }
+
                 if command.is_copy_code_block() {
                     tx.send(Action::CopyMessages(vec![Message::new(
                         Author::Model,
@@ -257,59 +680,2273 @@ impl<'a> AppState<'a> {
                     self.editor_context.clone(),
                     codeblocks_res.unwrap(),
                     accept_type,
+                    command.has_flag("force"),
                 ))?;
             }
 
             if command.is_copy_chat() {
                 should_continue = true;
-                tx.send(Action::CopyMessages(self.messages.clone()))?;
+                if let Some(output) = command.output.clone() {
+                    let messages = self.archive.restore_all(&self.messages).to_vec();
+                    tx.send(Action::WriteOutput(output, messages))?;
+                } else {
+                    let messages = self.archive.restore_all(&self.messages).to_vec();
+                    tx.send(Action::CopyMessages(messages))?;
+                }
                 self.waiting_for_backend = true;
             }
 
-            // Reset backend context on model switch.
-            if command.is_model_set() {
-                self.backend_context = "".to_string();
+            if command.is_apply_code_block() {
+                should_continue = true;
+                let codeblocks_res = self.codeblocks.selected_from_slash_commands(&command);
+                if let Err(err) = codeblocks_res.as_ref() {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        &format!(
+                            "There was an error trying to parse your command:\n\n{:?}",
+                            err
+                        ),
+                    ));
+
+                    return Ok((should_break, should_continue));
+                }
+
+                let codeblocks = codeblocks_res.unwrap();
+                if codeblocks.is_empty() || codeblocks.iter().any(|e| return e.path.is_none()) {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        "I couldn't work out which file a selected code block belongs to. Use `/append` or `/replace` to send it to your editor instead.",
+                    ));
+
+                    return Ok((should_break, should_continue));
+                }
+
+                tx.send(Action::ApplyCodeBlocks(codeblocks))?;
+                self.waiting_for_backend = true;
             }
-        }
 
-        return Ok((should_break, should_continue));
-    }
+            if command.is_run() {
+                should_continue = true;
+                let codeblocks_res = self.codeblocks.selected_from_slash_commands(&command);
+                if let Err(err) = codeblocks_res.as_ref() {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        &format!(
+                            "There was an error trying to parse your command:\n\n{:?}",
+                            err
+                        ),
+                    ));
 
-    pub fn set_rect(&mut self, rect: Rect) {
-        self.last_known_width = rect.width.into();
-        self.last_known_height = rect.height.into();
-        self.sync_dependants();
-    }
+                    return Ok((should_break, should_continue));
+                }
 
-    pub fn add_message(&mut self, message: Message) {
-        self.messages.push(message);
-        self.sync_dependants();
-        self.scroll.last();
-    }
+                let codeblock = codeblocks_res.unwrap().first().cloned();
+                if codeblock.is_none() {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        "There are no code blocks to run yet.",
+                    ));
 
-    fn sync_dependants(&mut self) {
-        self.bubble_list
-            .set_messages(&self.messages, self.last_known_width);
+                    return Ok((should_break, should_continue));
+                }
 
-        let scrollbar_at_bottom = self.scroll.is_position_at_last();
-        self.scroll
-            .set_state(self.bubble_list.len(), self.last_known_height);
+                if Config::get(ConfigKey::RunSandbox) == "none" && !command.has_flag("allow") {
+                    self.add_message(Message::new(
+                        Author::Oatmeal,
+                        "`run-sandbox` is `none`, so the code block would run directly in your working tree. Add `--allow` to the command to run it anyway.",
+                    ));
 
-        if self.waiting_for_backend && scrollbar_at_bottom {
-            self.scroll.last();
-        }
-    }
+                    return Ok((should_break, should_continue));
+                }
 
-    pub async fn save_session(&self) -> Result<()> {
-        self.sessions_service
-            .save(
-                &self.session_id,
-                &self.backend_context,
-                &self.editor_context,
-                &self.messages,
-            )
-            .await?;
+                tx.send(Action::RunCodeBlock(codeblock.unwrap()))?;
+                self.waiting_for_backend = true;
+            }
 
-        return Ok(());
+            if command.is_render() {
+                should_continue = true;
+                let codeblock = match self.codeblocks.selected_from_slash_commands(&command) {
+                    Ok(codeblocks) => codeblocks.first().cloned(),
+                    Err(err) => {
+                        self.add_message(Message::new_with_type(
+                            Author::Oatmeal,
+                            MessageType::Error,
+                            &format!(
+                                "There was an error trying to parse your command:\n\n{:?}",
+                                err
+                            ),
+                        ));
+
+                        return Ok((should_break, should_continue));
+                    }
+                };
+
+                let codeblock = match codeblock {
+                    Some(codeblock) => codeblock,
+                    None => {
+                        self.add_message(Message::new_with_type(
+                            Author::Oatmeal,
+                            MessageType::Error,
+                            "There are no code blocks to render yet.",
+                        ));
+
+                        return Ok((should_break, should_continue));
+                    }
+                };
+
+                match DiagramKind::from_language(&codeblock.language) {
+                    Some(kind) => {
+                        tx.send(Action::RenderDiagram(codeblock, kind))?;
+                        self.waiting_for_backend = true;
+                    }
+                    None => {
+                        self.add_message(Message::new_with_type(
+                            Author::Oatmeal,
+                            MessageType::Error,
+                            &format!("Only `mermaid` and `dot` code blocks can be rendered, this one is `{}`.", codeblock.language),
+                        ));
+                    }
+                }
+            }
+
+            if command.is_apply_all() {
+                should_continue = true;
+                let codeblocks = self
+                    .messages
+                    .iter()
+                    .rev()
+                    .find(|e| return e.author == Author::Model)
+                    .map(|e| return e.codeblocks())
+                    .unwrap_or_default();
+
+                let missing = codeblocks
+                    .iter()
+                    .filter(|e| return e.path.is_none())
+                    .count();
+                if codeblocks.is_empty() || missing > 0 {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        &format!(
+                            "The last answer has {} code blocks, and I couldn't work out which file {missing} of them belong to. Use `/apply`, `/append`, or `/replace` to pick blocks individually instead.",
+                            codeblocks.len()
+                        ),
+                    ));
+
+                    return Ok((should_break, should_continue));
+                }
+
+                if command.has_flag("yes") {
+                    tx.send(Action::ApplyCodeBlocks(codeblocks))?;
+                } else {
+                    tx.send(Action::PreviewCodeBlocks(codeblocks))?;
+                }
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_test() {
+                should_continue = true;
+                let mut test_command = None;
+                if !command.text().is_empty() {
+                    test_command = Some(command.text().to_string());
+                }
+
+                tx.send(Action::RunTests(test_command, command.has_flag("fix")))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_check() {
+                should_continue = true;
+                let mut check_command = None;
+                if !command.text().is_empty() {
+                    check_command = Some(command.text().to_string());
+                }
+
+                tx.send(Action::RunCheck(check_command))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_sync() {
+                should_continue = true;
+                tx.send(Action::SyncEditorContext())?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_with_context() {
+                should_continue = true;
+                tx.send(Action::AskWithContext(command.text().to_string()))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_github() {
+                should_continue = true;
+                tx.send(Action::FetchGitHub(command.args[0].to_string()))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_ticket() {
+                should_continue = true;
+                tx.send(Action::FetchTicket(command.args[0].to_string()))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_man() {
+                should_continue = true;
+                let mut args = command.args.iter().rev();
+                let topic = args.next().unwrap().to_string();
+                let section = args.next().map(|e| return e.to_string());
+                tx.send(Action::FetchManPage(topic, section))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_docs() {
+                should_continue = true;
+                tx.send(Action::FetchRustDocs(command.args[0].to_string()))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_translate() {
+                should_continue = true;
+                let count = command
+                    .option("last")
+                    .map(|e| return e.parse::<usize>().unwrap())
+                    .unwrap_or(1);
+                let mut responses = self
+                    .messages
+                    .iter()
+                    .rev()
+                    .filter(|e| return e.author == Author::Model)
+                    .take(count)
+                    .map(|e| return e.text.to_string())
+                    .collect::<Vec<String>>();
+                responses.reverse();
+
+                if responses.is_empty() {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        "There's no response to translate yet.",
+                    ));
+
+                    return Ok((should_break, should_continue));
+                }
+
+                let translation = Translation::new(
+                    &responses.join("\n\n"),
+                    &command.args.join(" "),
+                    &self.backend_context,
+                );
+                tx.send(Action::BackendRequest(BackendPrompt::new(
+                    translation.prompt.to_string(),
+                    "".to_string(),
+                )))?;
+                self.pending_translation = Some(translation);
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_continue() {
+                should_continue = true;
+                self.continue_answer(tx)?;
+            }
+
+            if command.is_ab() {
+                should_continue = true;
+                self.handle_ab(&command, tx)?;
+            }
+
+            if command.is_compare() {
+                should_continue = true;
+                self.handle_compare(&command, tx)?;
+            }
+
+            if command.is_set_var() {
+                should_continue = true;
+                self.set_variable(&command.set_var_assignment());
+            } else if let Some((param, value)) = command.set_sampling() {
+                should_continue = true;
+                self.set_sampling(param, value);
+            } else if command.is_set() {
+                should_continue = true;
+                let seed = &command.args[1];
+                if seed == "random" {
+                    Config::set(ConfigKey::Seed, "");
+                    self.add_message(Message::new(
+                        Author::Oatmeal,
+                        "Answers will now use a random seed.",
+                    ));
+                } else {
+                    Config::set(ConfigKey::Seed, seed);
+                    self.add_message(Message::new(
+                        Author::Oatmeal,
+                        &format!(
+                            "Answers will now use seed {seed}, with backends that support it."
+                        ),
+                    ));
+                }
+            }
+
+            if command.is_usage() {
+                should_continue = true;
+                self.toggle_usage();
+            }
+
+            if command.is_checkpoint() {
+                should_continue = true;
+                self.save_checkpoint(&command.args[0]);
+            }
+
+            if command.is_rollback() {
+                should_continue = true;
+                self.rollback(&command.args[0]);
+            }
+
+            if command.is_record() {
+                should_continue = true;
+                self.start_recording(&command.args[0]);
+            }
+
+            if command.is_stop() {
+                should_continue = true;
+                if !self.cancel_generation(tx)? {
+                    self.add_message(Message::new(
+                        Author::Oatmeal,
+                        "There's no answer being generated to stop.",
+                    ));
+                }
+            }
+
+            if command.is_stop_macro() {
+                should_continue = true;
+                if let Some(recorded) = self.stop_recording() {
+                    tx.send(Action::SaveMacro(recorded))?;
+                    self.waiting_for_backend = true;
+                }
+            }
+
+            if command.is_fork() {
+                should_continue = true;
+                self.fork_session()?;
+            }
+
+            if command.is_image() {
+                should_continue = true;
+                let backend = Config::get(ConfigKey::Backend);
+                if BackendName::parse(backend.to_string())
+                    .map(|e| return e.supports_images())
+                    .unwrap_or(false)
+                {
+                    tx.send(Action::LoadImage(command.args.first().cloned()))?;
+                    self.waiting_for_backend = true;
+                } else {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        &format!("Backend {backend} can't send images, switch to openai, claude, gemini or ollama to attach them."),
+                    ));
+                }
+            }
+
+            if command.is_few_shot() {
+                should_continue = true;
+                if command.args.is_empty() {
+                    self.few_shot = vec![];
+                    self.add_message(Message::new(
+                        Author::Oatmeal,
+                        "Cleared the few-shot examples, they won't be sent with new conversations.",
+                    ));
+                } else {
+                    tx.send(Action::LoadFewShot(command.args[0].to_string()))?;
+                    self.waiting_for_backend = true;
+                }
+            }
+
+            if command.is_system() {
+                should_continue = true;
+                self.set_system_prompt(command.text());
+            }
+
+            if command.is_context() {
+                should_continue = true;
+                self.handle_context_command(&command, tx)?;
+            }
+
+            if command.is_tee() {
+                should_continue = true;
+                if command.args.is_empty() {
+                    let text = match self.tee.take() {
+                        Some(tee) => {
+                            format!("Stopped writing the transcript to {}.", tee.path.display())
+                        }
+                        None => {
+                            "There's no transcript being written, start one with `/tee FILE`."
+                                .to_string()
+                        }
+                    };
+                    self.add_message(Message::new(Author::Oatmeal, &text));
+                } else {
+                    let path = command.args[0].to_string();
+                    match Tee::open(path::PathBuf::from(&path)) {
+                        Ok(tee) => {
+                            self.tee = Some(tee);
+                            self.add_message(Message::new(
+                                Author::Oatmeal,
+                                &format!("Writing every prompt and answer to {path} from now on."),
+                            ));
+                        }
+                        Err(err) => {
+                            self.add_message(Message::new_with_type(
+                                Author::Oatmeal,
+                                MessageType::Error,
+                                &format!("Couldn't open {path} for the transcript: {err}"),
+                            ));
+                        }
+                    }
+                }
+            }
+
+            if command.is_play() {
+                should_continue = true;
+                tx.send(Action::PlayMacro(
+                    command.args[0].to_string(),
+                    command.args[1..].join(" "),
+                ))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_diff() {
+                should_continue = true;
+                let messages = self.archive.restore_all(&self.messages).to_vec();
+                tx.send(Action::DiffSession(command.args[0].to_string(), messages))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_similar() {
+                should_continue = true;
+                // The last message is the `/similar` command itself.
+                let messages = self.archive.restore_all(&self.messages);
+                let candidates = messages[..messages.len().saturating_sub(1)]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, e)| {
+                        return e.author != Author::Oatmeal
+                            && SlashCommand::parse(&e.text).is_none();
+                    })
+                    .map(|(idx, e)| return (idx, e.text.chars().take(MAX_SIMILAR_CHARS).collect()))
+                    .collect::<Vec<(usize, String)>>();
+
+                if candidates.is_empty() {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        "There are no messages in this session to search yet.",
+                    ));
+
+                    return Ok((should_break, should_continue));
+                }
+
+                tx.send(Action::FindSimilar(command.text().to_string(), candidates))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_polish() {
+                should_continue = true;
+                let (mode, draft) = PolishMode::split(input_str);
+                if draft.is_empty() {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        &format!("Usage: {}", command.usage()),
+                    ));
+
+                    return Ok((should_break, should_continue));
+                }
+
+                tx.send(Action::PolishDraft(draft, mode))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_rewrites() {
+                should_continue = true;
+                let rules = RewriteRules::from_config().unwrap_or_default();
+                self.add_message(Message::new(
+                    Author::Oatmeal,
+                    &rules.format(&self.applied_rewrites),
+                ));
+            }
+
+            if command.is_model_refresh() {
+                should_continue = true;
+                tx.send(Action::RefreshModels())?;
+                self.add_message(Message::new(
+                    Author::Oatmeal,
+                    &format!(
+                        "Fetching the model list from {} in the background.",
+                        Config::get(ConfigKey::Backend)
+                    ),
+                ));
+            }
+
+            if command.is_todos() {
+                should_continue = true;
+                let messages = self.archive.restore_all(&self.messages);
+                let transcript = transcript(&messages);
+                if transcript.is_empty() {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        "There's no conversation yet to find action items in.",
+                    ));
+
+                    return Ok((should_break, should_continue));
+                }
+
+                tx.send(Action::ExtractTodos(transcript, command.output.clone()))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_share() {
+                should_continue = true;
+                let addr = command
+                    .args
+                    .first()
+                    .map(|e| return e.to_string())
+                    .unwrap_or(DEFAULT_SHARE_ADDRESS.to_string());
+                tx.send(Action::StartShare(addr))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_post() {
+                should_continue = true;
+                let messages = if command.has_flag("all") {
+                    self.archive
+                        .restore_all(&self.messages)
+                        .iter()
+                        .filter(|e| return e.author != Author::Oatmeal)
+                        .cloned()
+                        .collect::<Vec<Message>>()
+                } else {
+                    self.messages
+                        .iter()
+                        .rev()
+                        .find(|e| return e.author == Author::Model)
+                        .cloned()
+                        .into_iter()
+                        .collect::<Vec<Message>>()
+                };
+
+                if messages.is_empty() {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        "There's nothing to post yet.",
+                    ));
+
+                    return Ok((should_break, should_continue));
+                }
+
+                let name = WebhookName::parse(command.args[0].to_string()).unwrap();
+                tx.send(Action::PostWebhook(name, messages))?;
+                self.waiting_for_backend = true;
+            }
+
+            if command.is_review() {
+                should_continue = true;
+                let comments = self
+                    .messages
+                    .iter()
+                    .rev()
+                    .find(|e| return e.author == Author::Model)
+                    .map(|e| return ReviewComment::parse_all(&e.text))
+                    .unwrap_or_default();
+
+                if comments.is_empty() {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        "I couldn't find any review comments in the last answer. Ask for comments formatted as `FILE:LINE: COMMENT`.",
+                    ));
+
+                    return Ok((should_break, should_continue));
+                }
+
+                if command.args[0] == "github" {
+                    tx.send(Action::PublishReview(comments, command.args[1].to_string()))?;
+                    self.waiting_for_backend = true;
+                    return Ok((should_break, should_continue));
+                }
+
+                let rdjson = ReviewComment::to_rdjson(&comments)?;
+                if let Some(output) = command.output.clone() {
+                    tx.send(Action::WriteOutput(
+                        output,
+                        vec![Message::new(Author::Oatmeal, &rdjson)],
+                    ))?;
+                    self.waiting_for_backend = true;
+                } else {
+                    self.add_message(Message::new(
+                        Author::Oatmeal,
+                        &format!("```json\n{rdjson}\n```"),
+                    ));
+                }
+            }
+
+            if command.is_lang() {
+                should_continue = true;
+                if let Err(err) = self.set_codeblock_language(&command) {
+                    self.add_message(Message::new_with_type(
+                        Author::Oatmeal,
+                        MessageType::Error,
+                        &format!(
+                            "There was an error trying to parse your command:\n\n{:?}",
+                            err
+                        ),
+                    ));
+                }
+            }
+
+            // Reset backend context on model switch.
+            if command.is_model_set() {
+                self.backend_context = "".to_string();
+                self.reset_context_window(self.messages.len());
+            }
+        }
+
+        return Ok((should_break, should_continue));
+    }
+
+    /// Without a code block number, `/append` sends the last block in the
+    /// editor's language rather than the last block of all.
+    fn default_append_codeblock(&self, command: &SlashCommand) -> Option<String> {
+        if !command.is_append_code_block() || !command.args.is_empty() || command.has_flag("all") {
+            return None;
+        }
+
+        let language = &self.editor_context.as_ref()?.language;
+        return self
+            .codeblocks
+            .last_in_language(language)
+            .map(|e| return e.code.to_string());
+    }
+
+    fn set_codeblock_language(&mut self, command: &SlashCommand) -> Result<()> {
+        let index = self.codeblocks.validate_index(&command.args[0])?;
+        let language = command.args[1].to_string();
+
+        let counts = self
+            .archive
+            .restore_all(&self.messages)
+            .iter()
+            .map(|e| return e.codeblocks().len())
+            .collect::<Vec<usize>>();
+
+        let mut offset = 0;
+        let mut found = false;
+        for (message, count) in self.messages.iter_mut().zip(counts) {
+            if index <= offset + count {
+                message.set_codeblock_language(index - offset - 1, &language);
+                found = true;
+                break;
+            }
+            offset += count;
+        }
+
+        if !found {
+            return Err(anyhow!(format!("Code block index {index} is not valid")));
+        }
+
+        self.codeblocks
+            .replace_from_messages(&self.archive.restore_all(&self.messages));
+        self.bubble_list.clear_cache();
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!("Code block ({index}) will now be treated as `{language}`."),
+        ));
+
+        return Ok(());
+    }
+
+    pub fn attach_context(&mut self, description: &str, context: String) {
+        self.waiting_for_backend = false;
+        let ctx = AttachedContext::new(description, &context);
+        let findings = if Config::get(ConfigKey::ScanContext) != "false" {
+            ctx.scan()
+        } else {
+            vec![]
+        };
+        let mut message = Message::new(
+            Author::Oatmeal,
+            &format!("Attached {description}. It will be sent along with your next message."),
+        );
+        message.set_tokens(estimate_tokens(&ctx.text));
+        self.attached_context.push(ctx);
+        self.add_message(message);
+        self.warn_untrusted(description, &findings);
+    }
+
+    /// Warns about lines in fetched context that read like instructions for
+    /// the model.
+    fn warn_untrusted(&mut self, description: &str, findings: &[String]) {
+        if findings.is_empty() {
+            return;
+        }
+
+        let lines = findings
+            .iter()
+            .map(|e| return format!("- {e}"))
+            .collect::<Vec<String>>()
+            .join("\n");
+        self.add_message(Message::new_with_type(
+            Author::Oatmeal,
+            MessageType::Error,
+            &format!("Heads up, {description} contains text that reads like instructions for the model, which could be an attempt to hijack its answer:\n\n{lines}\n\nIt's marked as untrusted when sent, but double check the answer before acting on it."),
+        ));
+    }
+
+    pub fn handle_backend_fallback(&mut self, note: String) {
+        self.pending_fallback = Some(note);
+    }
+
+    pub fn handle_draft_polished(&mut self) {
+        self.waiting_for_backend = false;
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            "Your draft has been rewritten in the input box. Edit it, or hit enter to send it.",
+        ));
+    }
+
+    /// Offers `/continue` for an answer that hit the model's output limit, or
+    /// continues it straight away with `auto-continue`.
+    /// Asks the worker to stop the answer being generated. Asking again
+    /// before it has stopped gives up waiting on it. Returns false when
+    /// there isn't an answer to stop.
+    pub fn cancel_generation(&mut self, tx: &mpsc::UnboundedSender<Action>) -> Result<bool> {
+        if !self.waiting_for_backend {
+            return Ok(false);
+        }
+        if self.backend_status.as_deref() == Some(STOPPING_STATUS) {
+            self.handle_generation_cancelled();
+            return Ok(true);
+        }
+
+        self.macro_queue.clear();
+        self.backend_status = Some(STOPPING_STATUS.to_string());
+        tx.send(Action::CancelGeneration())?;
+        return Ok(true);
+    }
+
+    /// Keeps what arrived of the stopped answer, marked as incomplete. Its
+    /// context never arrived, so the backend won't remember it.
+    pub fn handle_generation_cancelled(&mut self) {
+        if !self.waiting_for_backend {
+            return;
+        }
+        self.waiting_for_backend = false;
+        self.backend_status = None;
+        self.pending_fallback = None;
+        self.pending_tool_answer = false;
+        self.pending_context_note = None;
+        if let Some(translation) = self.pending_translation.take() {
+            self.backend_context = translation.backend_context;
+        }
+        // The answers so far stay side by side.
+        if self.pending_comparison.take().is_some() {
+            self.add_message(Message::new(Author::Oatmeal, "Stopped the comparison."));
+            return;
+        }
+
+        let answering = self
+            .messages
+            .last()
+            .map(|e| return e.author == Author::Model)
+            .unwrap_or(false);
+        if !answering {
+            self.pending_continuation = None;
+            self.add_message(Message::new(
+                Author::Oatmeal,
+                "Stopped before the model started answering.",
+            ));
+            return;
+        }
+
+        let last_message = self.messages.last_mut().unwrap();
+        if let Some(seam) = self.pending_continuation.take() {
+            last_message.join_continuation(seam);
+        }
+        last_message.text = format!("{}{STOPPED_MARKER}", last_message.text.trim_end());
+        let name = last_message.author_name();
+        self.write_tee(|tee| return tee.answer(&name, STOPPED_MARKER, true));
+
+        self.bubble_list.clear_cache();
+        self.sync_dependants();
+        self.codeblocks
+            .replace_from_messages(&self.archive.restore_all(&self.messages));
+        self.publish_share();
+    }
+
+    pub fn handle_output_truncated(&mut self, tx: &mpsc::UnboundedSender<Action>) -> Result<()> {
+        if Config::get(ConfigKey::AutoContinue) == "true" {
+            return self.continue_answer(tx);
+        }
+
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            "That answer reached the model's output limit. Run `/continue` to finish it.",
+        ));
+
+        return Ok(());
+    }
+
+    /// Asks the backend to continue the last answer, appending to it rather
+    /// than starting a new message. Only notices from Oatmeal and the
+    /// `/continue` command may follow the answer, which are removed.
+    pub fn continue_answer(&mut self, tx: &mpsc::UnboundedSender<Action>) -> Result<()> {
+        let answer_idx = self
+            .messages
+            .iter()
+            .rposition(|e| return e.author == Author::Model)
+            .filter(|idx| {
+                return self.messages[idx + 1..].iter().all(|e| {
+                    return e.author == Author::Oatmeal
+                        || SlashCommand::parse(&e.text)
+                            .map(|e| return e.is_continue())
+                            .unwrap_or(false);
+                });
+            });
+
+        let answer_idx = match answer_idx {
+            Some(idx) => idx,
+            None => {
+                self.add_message(Message::new_with_type(
+                    Author::Oatmeal,
+                    MessageType::Error,
+                    "There's no answer to continue, `/continue` only works right after one.",
+                ));
+                return Ok(());
+            }
+        };
+
+        self.messages.truncate(answer_idx + 1);
+        self.pending_continuation = Some(self.messages[answer_idx].text.len());
+        self.pending_translation = None;
+        self.bubble_list.clear_cache();
+        self.sync_dependants();
+
+        let mut prompt =
+            BackendPrompt::new(CONTINUE_PROMPT.to_string(), self.backend_context.clone());
+        prompt.system_prompt = self.system_prompt();
+        tx.send(Action::BackendRequest(prompt))?;
+        self.waiting_for_backend = true;
+
+        return Ok(());
+    }
+
+    /// Asks the user to approve each tool call the model asked for. An
+    /// answer with nothing but the calls is left out of the chat.
+    pub fn handle_tool_calls_requested(
+        &mut self,
+        calls: Vec<ToolInvocation>,
+        tx: &mpsc::UnboundedSender<Action>,
+    ) -> Result<()> {
+        if let Some(last) = self.messages.last() {
+            if last.author == Author::Model && last.text.trim().is_empty() {
+                self.messages.pop();
+                self.sync_dependants();
+            }
+        }
+
+        self.pending_tool_calls.extend(calls);
+        return self.next_tool_call(tx);
+    }
+
+    /// Runs or skips the tool call being approved, moving on to the next.
+    pub fn handle_tool_approval(
+        &mut self,
+        choice: ToolApprovalChoice,
+        tx: &mpsc::UnboundedSender<Action>,
+    ) -> Result<()> {
+        self.tool_approval = None;
+        let invocation = match self.pending_tool_calls.pop_front() {
+            Some(invocation) => invocation,
+            None => return Ok(()),
+        };
+
+        if choice == ToolApprovalChoice::Run {
+            self.waiting_for_backend = true;
+            tx.send(Action::RunModelTool(invocation))?;
+            return Ok(());
+        }
+
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!("Skipped the model's tool call: {}", invocation.summary()),
+        ));
+        self.tool_outputs.push(ToolOutput::declined(&invocation));
+        return self.next_tool_call(tx);
+    }
+
+    pub fn handle_model_tool_finished(
+        &mut self,
+        output: ToolOutput,
+        tx: &mpsc::UnboundedSender<Action>,
+    ) -> Result<()> {
+        self.waiting_for_backend = false;
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!(
+                "Output of `{}`:\n\n```\n{}\n```",
+                output.name,
+                output.output.trim_end()
+            ),
+        ));
+        self.tool_outputs.push(output);
+        return self.next_tool_call(tx);
+    }
+
+    /// Opens the approval for the next tool call, or sends the outputs back
+    /// to the model once every call is done.
+    fn next_tool_call(&mut self, tx: &mpsc::UnboundedSender<Action>) -> Result<()> {
+        if let Some(invocation) = self.pending_tool_calls.front() {
+            self.tool_approval = Some(ToolApproval::new(invocation.clone()));
+            return Ok(());
+        }
+        if self.tool_outputs.is_empty() {
+            return Ok(());
+        }
+
+        let mut prompt = BackendPrompt::new("".to_string(), self.backend_context.clone());
+        prompt.tools = ModelTool::from_config().unwrap_or_default();
+        prompt.tool_outputs = std::mem::take(&mut self.tool_outputs);
+        prompt.system_prompt = self.system_prompt();
+        self.pending_tool_answer = true;
+        self.waiting_for_backend = true;
+        tx.send(Action::BackendRequest(prompt))?;
+
+        return Ok(());
+    }
+
+    /// Snapshots the chat as it was before the `/checkpoint` command, replacing
+    /// any checkpoint with the same name.
+    fn save_checkpoint(&mut self, name: &str) {
+        let mut messages = self.messages.clone();
+        if let Some(last) = messages.last() {
+            if last.author == Author::User && SlashCommand::parse(&last.text).is_some() {
+                messages.pop();
+            }
+        }
+
+        let count = messages.len();
+        self.checkpoints.retain(|e| return e.name != name);
+        self.checkpoints.push(Checkpoint {
+            name: name.to_string(),
+            messages,
+            backend_context: self.backend_context.to_string(),
+        });
+
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!(
+                "Saved checkpoint {name} with {count} messages. Run `/rollback {name}` to return to it."
+            ),
+        ));
+    }
+
+    /// Restores the chat and backend context from a checkpoint. Later
+    /// checkpoints are kept, so it's possible to roll forward again.
+    fn rollback(&mut self, name: &str) {
+        if self.waiting_for_backend {
+            self.add_message(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                "Wait for the current answer to finish before rolling back.",
+            ));
+            return;
+        }
+
+        let checkpoint = match self.checkpoints.iter().find(|e| return e.name == name) {
+            Some(checkpoint) => checkpoint.clone(),
+            None => {
+                let names = self
+                    .checkpoints
+                    .iter()
+                    .map(|e| return e.name.to_string())
+                    .collect::<Vec<String>>();
+                let hint = if names.is_empty() {
+                    "Save one first with `/checkpoint NAME`.".to_string()
+                } else {
+                    format!("Saved checkpoints are: {}", names.join(", "))
+                };
+                self.add_message(Message::new_with_type(
+                    Author::Oatmeal,
+                    MessageType::Error,
+                    &format!("There's no checkpoint named {name}. {hint}"),
+                ));
+                return;
+            }
+        };
+
+        self.messages = checkpoint.messages;
+        self.backend_context = checkpoint.backend_context;
+        self.reset_context_window(0);
+        self.pending_continuation = None;
+        self.pending_experiment = None;
+        self.pending_translation = None;
+        self.codeblocks
+            .replace_from_messages(&self.archive.restore_all(&self.messages));
+        self.bubble_list.clear_cache();
+
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!("Rolled back to checkpoint {name}."),
+        ));
+    }
+
+    /// Lists the closest matches to a `/similar` query, and scrolls to the
+    /// best one.
+    pub fn handle_similar_found(&mut self, query: &str, results: Vec<SimilarMessage>) {
+        self.waiting_for_backend = false;
+        let matches = results
+            .iter()
+            .take(MAX_SIMILAR_RESULTS)
+            .filter_map(|result| {
+                let mut message = self.messages.get(result.index)?.clone();
+                self.archive.restore(&mut message);
+                let mut preview = message.text.lines().next().unwrap_or("").to_string();
+                if preview.chars().count() > 80 {
+                    preview = format!("{}...", preview.chars().take(80).collect::<String>());
+                }
+                return Some(format!(
+                    "- {:.2} {}: {preview}",
+                    result.score,
+                    message.author_name()
+                ));
+            })
+            .collect::<Vec<String>>();
+
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!(
+                "Closest messages to \"{query}\", jumping to the first:\n\n{}",
+                matches.join("\n")
+            ),
+        ));
+
+        if let Some(best) = results.first() {
+            self.scroll.to(self.bubble_list.line_offset(best.index));
+        }
+    }
+
+    fn handle_ab(
+        &mut self,
+        command: &SlashCommand,
+        tx: &mpsc::UnboundedSender<Action>,
+    ) -> Result<()> {
+        let pick = match command.option("pick") {
+            Some(pick) => pick.parse::<usize>()?,
+            None => {
+                tx.send(Action::RunExperiment(
+                    command.args.to_vec(),
+                    self.backend_context.to_string(),
+                ))?;
+                self.waiting_for_backend = true;
+                return Ok(());
+            }
+        };
+
+        let mut experiment = match self.pending_experiment.take() {
+            Some(experiment) => experiment,
+            None => {
+                self.add_message(Message::new_with_type(
+                    Author::Oatmeal,
+                    MessageType::Error,
+                    "There's no comparison to pick a winner for, start one with `/ab`.",
+                ));
+                return Ok(());
+            }
+        };
+
+        // Carry on the chat from the winning answer, as though it was the
+        // only prompt sent.
+        let winner = experiment.variants[pick - 1].clone();
+        experiment.winner = Some(pick);
+        self.backend_context = winner.backend_context;
+        self.add_message(Message::new(Author::User, &winner.prompt));
+        self.add_message(Message::new(Author::Model, &winner.answer));
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!("Variant ({pick}) won, continuing the chat from its answer."),
+        ));
+        tx.send(Action::RecordExperiment(experiment))?;
+
+        return Ok(());
+    }
+
+    /// Adds the message the answers stream in to, as the prompt is sent to
+    /// both models without the chat's history.
+    fn handle_compare(
+        &mut self,
+        command: &SlashCommand,
+        tx: &mpsc::UnboundedSender<Action>,
+    ) -> Result<()> {
+        let backend = BackendName::parse(Config::get(ConfigKey::Backend)).unwrap();
+        let comparison = Comparison::new(&command.args[..2], &backend);
+        let mut prompt = BackendPrompt::new(command.args[2..].join(" "), "".to_string());
+        prompt.prepend_context_documents(&self.context_documents_for_prompt());
+        prompt.system_prompt = self.system_prompt();
+
+        let width = Bubble::max_text_width(self.last_known_width);
+        self.add_message(Message::new(Author::Oatmeal, &comparison.render(width)));
+        self.pending_comparison = Some((self.messages.len() - 1, comparison.clone()));
+        tx.send(Action::RunComparison(comparison, prompt))?;
+        self.waiting_for_backend = true;
+
+        return Ok(());
+    }
+
+    /// Rewrites the prompt with the `rewrite-rules` for prompts, starting
+    /// over the rules `/rewrites` shows as applied.
+    pub fn rewrite_prompt(&mut self, text: &str) -> String {
+        let rules = RewriteRules::from_config().unwrap_or_default();
+        let (text, applied) = rules.apply(RewriteTarget::Prompt, text);
+        self.applied_rewrites = applied;
+
+        return text;
+    }
+
+    /// Rewrites the finished answer with the `rewrite-rules` for answers.
+    /// It streams in as the model wrote it, as a pattern can span chunks.
+    fn rewrite_answer(&mut self) {
+        let last_message = self.messages.last_mut().unwrap();
+        if last_message.author != Author::Model {
+            return;
+        }
+
+        let rules = RewriteRules::from_config().unwrap_or_default();
+        let (text, applied) = rules.apply(RewriteTarget::Response, &last_message.text);
+        if applied.is_empty() {
+            return;
+        }
+        last_message.text = text;
+        self.applied_rewrites.extend(applied);
+        self.bubble_list.clear_cache();
+        self.sync_dependants();
+    }
+
+    /// Says when a backend that was down is back up, so prompts can be sent
+    /// again without restarting.
+    pub fn handle_backend_health(&mut self, health: BackendHealth) {
+        let previous = std::mem::replace(&mut self.backend_health, health);
+        if health.is_recovery(previous) {
+            self.add_message(Message::new(
+                Author::Oatmeal,
+                &format!(
+                    "Backend {} is back up, what can I do for you?",
+                    Config::get(ConfigKey::Backend)
+                ),
+            ));
+        }
+    }
+
+    /// Streams part of a `/compare` answer into its column.
+    pub fn handle_comparison_response(&mut self, idx: usize, res: BackendResponse) {
+        let (message_idx, comparison) = match self.pending_comparison.as_mut() {
+            Some(pending) => pending,
+            None => return,
+        };
+        comparison.append(idx, &res);
+
+        let width = Bubble::max_text_width(self.last_known_width);
+        let message_idx = *message_idx;
+        let done = comparison.is_done();
+        if let Some(message) = self.messages.get_mut(message_idx) {
+            message.text = comparison.render(width);
+        }
+        if done {
+            self.pending_comparison = None;
+            self.waiting_for_backend = false;
+            self.backend_status = None;
+        }
+
+        self.bubble_list.invalidate(message_idx);
+        self.sync_dependants();
+    }
+
+    pub fn handle_experiment_completed(&mut self, experiment: Experiment) {
+        self.waiting_for_backend = false;
+        self.backend_status = None;
+        let width = Bubble::max_text_width(self.last_known_width);
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!(
+                "{}\n\nPick the winner with `/ab --pick 1` or `/ab --pick 2`.",
+                experiment.side_by_side(width)
+            ),
+        ));
+        self.pending_experiment = Some(experiment);
+    }
+
+    pub fn handle_share_started(&mut self, host: ShareHost) {
+        self.waiting_for_backend = false;
+        let mut text = format!(
+            "Sharing this session on {addr}. Others can join with `oatmeal join {addr} {token}`.",
+            addr = host.addr,
+            token = host.token
+        );
+        if !host.is_loopback() {
+            text += " Anyone who can reach this address can try to join, so only give the token to people you trust.";
+        }
+        self.add_message(Message::new(Author::Oatmeal, &text));
+        self.share = Some(Share::Host(host));
+        self.publish_share();
+    }
+
+    pub fn handle_share_joined(&mut self, guest: ShareGuest) {
+        self.waiting_for_backend = false;
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!(
+                "Joined the session shared on {}. Your prompts are sent by the host when it's your turn.",
+                guest.addr
+            ),
+        ));
+        self.share = Some(Share::Guest(guest));
+    }
+
+    pub fn handle_share_participant(&mut self, username: &str, joined: bool) {
+        let action = if joined { "joined" } else { "left" };
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!("{username} {action} the session."),
+        ));
+    }
+
+    /// Returns the prompt to submit for a guest, or explains to everyone why
+    /// it can't be submitted.
+    pub fn handle_shared_prompt(&mut self, username: &str, text: &str) -> Option<String> {
+        let mut error: Option<String> = None;
+        if self.waiting_for_backend {
+            error = Some(format!(
+                "{username} tried to send a prompt while the model is answering. Wait for your turn!"
+            ));
+        } else if SlashCommand::parse(text).is_some() {
+            error = Some(format!(
+                "Only the host can run slash commands, sorry {username}."
+            ));
+        } else if !PromptVariables::placeholders(text).is_empty() {
+            // They'd run commands and read the environment on the host.
+            error = Some(format!(
+                "Only the host can use `{{{{...}}}}` placeholders, sorry {username}."
+            ));
+        }
+
+        if let Some(error) = error {
+            self.add_message(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &error,
+            ));
+            return None;
+        }
+
+        return Some(text.to_string());
+    }
+
+    /// Applies an update from the host of a shared session.
+    pub fn handle_share_update(&mut self, msg: ShareMessage) {
+        match msg {
+            ShareMessage::Sync {
+                messages,
+                waiting_for_backend,
+            } => {
+                self.messages = messages;
+                self.waiting_for_backend = waiting_for_backend;
+                self.codeblocks
+                    .replace_from_messages(&self.archive.restore_all(&self.messages));
+                self.bubble_list.clear_cache();
+                self.sync_dependants();
+                self.scroll.last();
+            }
+            ShareMessage::Append { author, text } => {
+                self.waiting_for_backend = true;
+                match self.messages.last_mut() {
+                    Some(last_message) if last_message.author != Author::User => {
+                        last_message.append(&text);
+                    }
+                    _ => {
+                        self.messages.push(Message::new(author, &text));
+                    }
+                }
+                self.sync_dependants();
+            }
+            ShareMessage::Refused { reason } => {
+                self.add_message(Message::new_with_type(
+                    Author::Oatmeal,
+                    MessageType::Error,
+                    &format!("The host refused to let you join the session. {reason}"),
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    fn publish_share(&self) {
+        if let Some(Share::Host(host)) = &self.share {
+            host.publish(
+                &self.archive.restore_all(&self.messages),
+                self.waiting_for_backend,
+            );
+        }
+    }
+
+    /// Editor context to send with the first message, truncated to the
+    /// configured head and tail lines. Lets the user know when lines were
+    /// left out.
+    pub fn chat_context(&mut self) -> Option<EditorContext> {
+        let context = self.editor_context.as_ref()?;
+        let lines = |key: ConfigKey| {
+            return Config::get(key)
+                .parse::<usize>()
+                .unwrap_or_else(|_| return Config::default(key).parse::<usize>().unwrap());
+        };
+
+        let (truncated, omitted) = context.truncate(
+            lines(ConfigKey::ContextHeadLines),
+            lines(ConfigKey::ContextTailLines),
+        );
+        if omitted > 0 {
+            let total = context.code.lines().count();
+            self.add_message(Message::new(
+                Author::Oatmeal,
+                &format!(
+                    "Your selection in {} is {total} lines, so {omitted} lines from its middle were left out to fit the model's context. Change how many are kept with the `context-head-lines` and `context-tail-lines` options.",
+                    context.file_path
+                ),
+            ));
+        }
+
+        return Some(truncated);
+    }
+
+    pub fn handle_editor_context_synced(&mut self, editor_context: Option<EditorContext>) {
+        self.waiting_for_backend = false;
+        if let Some(ctx) = editor_context.as_ref() {
+            self.add_message(Message::new(
+                Author::Oatmeal,
+                &format!(
+                    "Re-synced with your editor, replacements will now target:\n\n{}",
+                    ctx.format()
+                ),
+            ));
+        } else {
+            self.add_message(Message::new(
+                Author::Oatmeal,
+                "Re-synced with your editor, there's no selection to replace.",
+            ));
+        }
+        self.editor_context = editor_context;
+    }
+
+    /// Asks a `/withcontext` question with the editor's current context,
+    /// which replacements then target too.
+    pub fn handle_editor_context_refreshed(
+        &mut self,
+        question: &str,
+        editor_context: Option<EditorContext>,
+        tx: &mpsc::UnboundedSender<Action>,
+    ) -> Result<()> {
+        if editor_context.is_none() {
+            self.waiting_for_backend = false;
+            self.add_message(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                "There's no selection in your editor to ask about.",
+            ));
+
+            return Ok(());
+        }
+
+        self.editor_context = editor_context;
+        self.pending_translation = None;
+        let mut prompt = BackendPrompt::new(question.to_string(), self.backend_context.clone());
+        prompt.append_chat_context(&self.chat_context());
+        prompt.append_attached_context(&std::mem::take(&mut self.attached_context));
+        prompt.prepend_context_documents(&self.take_context_documents());
+        prompt.prepend_few_shot(&self.take_few_shot());
+        prompt.system_prompt = self.system_prompt();
+        tx.send(Action::BackendRequest(prompt))?;
+
+        return Ok(());
+    }
+
+    pub fn set_rect(&mut self, rect: Rect) {
+        self.last_known_width = rect.width.into();
+        self.last_known_height = rect.height.into();
+        self.sync_dependants();
+    }
+
+    /// Uses the compact layout on short terminals, unless `layout` forces
+    /// one or the other.
+    pub fn set_terminal_height(&mut self, height: u16) {
+        let compact = match Config::get(ConfigKey::Layout).as_str() {
+            "compact" => true,
+            "comfortable" => false,
+            _ => height < COMPACT_LAYOUT_HEIGHT,
+        };
+        if self.bubble_list.set_compact(compact) {
+            self.sync_dependants();
+        }
+    }
+
+    pub fn add_message(&mut self, message: Message) {
+        if message.author == Author::User && SlashCommand::parse(&message.text).is_none() {
+            let (name, text) = (message.author_name(), message.text.to_string());
+            self.write_tee(|tee| return tee.prompt(&name, &text));
+        }
+        self.messages.push(message);
+        self.sync_dependants();
+        self.scroll.last();
+        self.publish_share();
+    }
+
+    /// Writes to the transcript, stopping it when the file can no longer be
+    /// written to.
+    fn write_tee(&mut self, write: impl FnOnce(&mut Tee) -> Result<()>) {
+        let Some(tee) = &mut self.tee else {
+            return;
+        };
+        if let Err(err) = write(tee) {
+            let path = tee.path.display().to_string();
+            self.tee = None;
+            self.add_message(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &format!("Stopped writing the transcript to {path}: {err}"),
+            ));
+        }
+    }
+
+    /// Text of the last prompt sent to the backend, skipping slash commands.
+    pub fn last_prompt(&self) -> Option<String> {
+        return self.last_prompt_message().map(|e| return e.text);
+    }
+
+    /// Whether the user typed the last prompt, so sending it again fills in
+    /// its placeholders.
+    pub fn is_last_prompt_typed(&self) -> bool {
+        return self
+            .last_prompt_message()
+            .map(|e| return e.is_typed())
+            .unwrap_or(false);
+    }
+
+    fn last_prompt_message(&self) -> Option<Message> {
+        let mut message = self
+            .messages
+            .iter()
+            .filter(|msg| {
+                return msg.author == Author::User && SlashCommand::parse(&msg.text).is_none();
+            })
+            .last()
+            .cloned()?;
+        self.archive.restore(&mut message);
+
+        return Some(message);
+    }
+
+    /// Asks for follow-ups to the last answer when `suggest-replies` is on,
+    /// once the backend is done with it.
+    pub fn request_suggestions(&mut self, tx: &mpsc::UnboundedSender<Action>) -> Result<()> {
+        if Config::get(ConfigKey::SuggestReplies) != "true" || self.waiting_for_backend {
+            return Ok(());
+        }
+
+        let answer = match self.messages.last() {
+            Some(message) if message.author == Author::Model => message.text.to_string(),
+            _ => return Ok(()),
+        };
+        let question = match self.last_prompt() {
+            Some(question) => question,
+            None => return Ok(()),
+        };
+
+        self.suggestions.request();
+        tx.send(Action::SuggestReplies(ReplySuggestions::prompt(
+            &question, &answer,
+        )))?;
+
+        return Ok(());
+    }
+
+    /// Chooses the next recovery action offered by the last message. Returns
+    /// false when it doesn't offer any.
+    pub fn select_recovery(&mut self) -> bool {
+        let idx = match self.messages.len().checked_sub(1) {
+            Some(idx) => idx,
+            None => return false,
+        };
+        if !self.messages[idx].select_next_recovery() {
+            return false;
+        }
+
+        self.bubble_list.invalidate(idx);
+        self.sync_dependants();
+        return true;
+    }
+
+    /// Takes the recovery action chosen on the last message, removing its
+    /// actions so they only run once.
+    pub fn take_recovery(&mut self) -> Option<RecoveryAction> {
+        let idx = self.messages.len().checked_sub(1)?;
+        let action = self.messages[idx].selected_recovery()?;
+        self.messages[idx].set_recovery(vec![]);
+
+        self.bubble_list.invalidate(idx);
+        self.sync_dependants();
+        return Some(action);
+    }
+
+    /// The backend context from the previous backend means nothing to the
+    /// new one, so the conversation starts over with it.
+    pub fn handle_backend_switched(&mut self, message: Message) {
+        self.backend_context = "".to_string();
+        self.reset_context_window(self.messages.len());
+        self.waiting_for_backend = false;
+        self.backend_status = None;
+        self.add_message(message);
+    }
+
+    fn start_recording(&mut self, name: &str) {
+        if let Some(recording) = &self.recording_macro {
+            let text = format!(
+                "Already recording macro `{}`, send `/stopmacro` to save it first.",
+                recording.name
+            );
+            self.add_message(Message::new_with_type(
+                Author::Oatmeal,
+                MessageType::Error,
+                &text,
+            ));
+            return;
+        }
+
+        self.recording_macro = Some(Macro::new(name));
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!("Recording macro `{name}`. Send the prompts and commands to save, then `/stopmacro`."),
+        ));
+    }
+
+    /// Returns the recorded macro to save, when it has any steps.
+    fn stop_recording(&mut self) -> Option<Macro> {
+        let recorded = match self.recording_macro.take() {
+            Some(recorded) => recorded,
+            None => {
+                self.add_message(Message::new_with_type(
+                    Author::Oatmeal,
+                    MessageType::Error,
+                    "There's no macro being recorded, start one with `/record NAME`.",
+                ));
+                return None;
+            }
+        };
+
+        if recorded.steps.is_empty() {
+            self.add_message(Message::new(
+                Author::Oatmeal,
+                &format!(
+                    "Nothing was recorded for macro `{}`, so it wasn't saved.",
+                    recorded.name
+                ),
+            ));
+            return None;
+        }
+
+        return Some(recorded);
+    }
+
+    /// Adds a prompt or command typed by the user to the macro being
+    /// recorded.
+    pub fn record_macro_step(&mut self, input: &str) {
+        if let Some(recording) = self.recording_macro.as_mut() {
+            recording.record(input);
+        }
+    }
+
+    /// Sets a variable from `NAME=VALUE`, or lists them all when empty.
+    fn set_variable(&mut self, assignment: &str) {
+        if assignment.is_empty() {
+            self.add_message(Message::new(Author::Oatmeal, &self.variables.format()));
+            return;
+        }
+
+        match self.variables.assign(assignment) {
+            Ok(name) => {
+                let text = match self.variables.values.get(&name) {
+                    Some(value) => {
+                        format!("Set `{name}` to `{value}`, use it in prompts as `{{{{{name}}}}}`.")
+                    }
+                    None => format!("Removed the variable `{name}`."),
+                };
+                self.add_message(Message::new(Author::Oatmeal, &text));
+            }
+            Err(err) => {
+                self.add_message(Message::new_with_type(
+                    Author::Oatmeal,
+                    MessageType::Error,
+                    &err.to_string(),
+                ));
+            }
+        }
+    }
+
+    /// Fills in the prompt's `{{...}}` placeholders just before it's sent.
+    /// Commands run as `/run` would, so they follow its permission, and
+    /// environment variables follow `env`'s.
+    pub async fn interpolate_prompt(&self, text: &str) -> Result<String> {
+        if self.variables.reads_env(text) && self.tool_policy.permission("env") != Permission::Allow
+        {
+            return Err(anyhow!(
+                "`{{{{env:...}}}}` reads environment variables, which needs `env` allowed by `tool-permissions`, see `/perms`."
+            ));
+        }
+
+        let commands = PromptVariables::commands(text);
+        if !commands.is_empty() && self.tool_policy.permission("run") != Permission::Allow {
+            return Err(anyhow!(
+                "`{{{{sh:...}}}}` runs commands, which needs `/run` allowed by `tool-permissions`, see `/perms`."
+            ));
+        }
+
+        let mut outputs = HashMap::new();
+        for command in commands {
+            let output =
+                match tokio::time::timeout(PROMPT_COMMAND_TIMEOUT, Shell::run(&command)).await {
+                    Ok(output) => output?,
+                    Err(_) => return Err(anyhow!("Command `{command}` took too long to run")),
+                };
+            if !output.success {
+                return Err(anyhow!(
+                    "Command `{command}` failed:\n\n```\n{}\n```",
+                    output.output()
+                ));
+            }
+            outputs.insert(command, output.stdout);
+        }
+
+        return self.variables.interpolate(
+            text,
+            &outputs,
+            self.tool_policy.permission("env") == Permission::Allow,
+        );
+    }
+
+    pub fn handle_few_shot_loaded(&mut self, path: &str, examples: Vec<FewShotExample>) {
+        self.waiting_for_backend = false;
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!(
+                "Loaded {} few-shot examples from {path}, they'll be sent ahead of your next prompt.",
+                examples.len()
+            ),
+        ));
+        self.few_shot = examples;
+        self.few_shot_sent = false;
+    }
+
+    pub fn handle_image_attached(&mut self, image: ImageAttachment) {
+        self.waiting_for_backend = false;
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!(
+                "Attached {}, it will be sent with your next prompt for models that can see images.",
+                image.placeholder()
+            ),
+        ));
+        self.attached_images.push(image);
+    }
+
+    /// Images to send with the prompt, shown as placeholders in its message.
+    pub fn take_images(&mut self) -> Vec<ImageAttachment> {
+        let images = std::mem::take(&mut self.attached_images);
+        if images.is_empty() {
+            return images;
+        }
+
+        if let Some(message) = self
+            .messages
+            .iter_mut()
+            .rev()
+            .find(|e| return e.author == Author::User)
+        {
+            message.set_images(images.iter().map(|e| return e.placeholder()).collect());
+            self.bubble_list.clear_cache();
+            self.sync_dependants();
+        }
+
+        return images;
+    }
+
+    /// Lists, adds, or removes the documents sent ahead of every prompt.
+    fn handle_context_command(
+        &mut self,
+        command: &SlashCommand,
+        tx: &mpsc::UnboundedSender<Action>,
+    ) -> Result<()> {
+        match command.args[0].as_str() {
+            "add" => {
+                tx.send(Action::LoadContextDocument(command.args[1].to_string()))?;
+                self.waiting_for_backend = true;
+            }
+            "rm" => {
+                let number = command.args[1].parse::<usize>()?;
+                match self.context_documents.remove(number) {
+                    Some(document) => {
+                        self.add_message(Message::new(
+                            Author::Oatmeal,
+                            &format!(
+                                "Removed {}, it won't be sent with your prompts anymore.",
+                                document.source
+                            ),
+                        ));
+                    }
+                    None => {
+                        self.add_message(Message::new_with_type(
+                            Author::Oatmeal,
+                            MessageType::Error,
+                            &format!(
+                                "There's no document {number}, see `/context list` for their numbers."
+                            ),
+                        ));
+                    }
+                }
+            }
+            _ => {
+                let text = if self.context_documents.documents.is_empty() {
+                    "There are no documents yet, add a file or web page with `/context add FILE_OR_URL`.".to_string()
+                } else {
+                    self.context_documents
+                        .format(ContextDocuments::budget_from_config())
+                };
+                self.add_message(Message::new(Author::Oatmeal, &text));
+            }
+        }
+
+        return Ok(());
+    }
+
+    pub fn handle_context_document_loaded(&mut self, document: ContextDocument) {
+        self.waiting_for_backend = false;
+        let budget = ContextDocuments::budget_from_config();
+        let source = document.source.to_string();
+        let tokens = document.tokens();
+        let findings = if Config::get(ConfigKey::ScanContext) != "false" {
+            AttachedContext::new(&document.source, &document.text).scan()
+        } else {
+            vec![]
+        };
+        let number = self.context_documents.add(document);
+        self.context_documents_sent = false;
+
+        let mut text = format!("Added {source} as document {number}, it will be sent ahead of every prompt. See them with `/context list`.");
+        if !self.context_documents.included(budget)[number - 1] {
+            text = format!("Added {source} as document {number}, but its ~{tokens} tokens don't fit in what's left of `context-documents-budget`, so it won't be sent until others are removed with `/context rm NUMBER`.");
+        }
+        let mut message = Message::new(Author::Oatmeal, &text);
+        message.set_tokens(tokens);
+        self.add_message(message);
+
+        self.warn_untrusted(&format!("document {number}"), &findings);
+    }
+
+    /// The documents from `/context` that fit the token budget, to put ahead
+    /// of the next prompt.
+    pub fn context_documents_for_prompt(&self) -> Vec<AttachedContext> {
+        return self
+            .context_documents
+            .to_context(ContextDocuments::budget_from_config());
+    }
+
+    /// The documents from `/context` to put ahead of the next prompt: at the
+    /// start of each conversation, and once after one is added. Backends
+    /// keep them in their context, so they aren't sent with every prompt.
+    pub fn take_context_documents(&mut self) -> Vec<AttachedContext> {
+        if self.context_documents_sent && !self.backend_context.is_empty() {
+            return vec![];
+        }
+
+        self.context_documents_sent = true;
+        return self.context_documents_for_prompt();
+    }
+
+    /// Examples to put ahead of the next prompt: at the start of each
+    /// conversation, and once after `/fewshot` loads new ones.
+    pub fn take_few_shot(&mut self) -> Vec<FewShotExample> {
+        if self.few_shot_sent && !self.backend_context.is_empty() {
+            return vec![];
+        }
+
+        self.few_shot_sent = true;
+        return self.few_shot.clone();
+    }
+
+    /// The system prompt to send with the next prompt. Backends keep it out
+    /// of their context, so it's sent with every prompt.
+    pub fn system_prompt(&self) -> Option<String> {
+        return SystemPromptPresets::system_prompt_from_config();
+    }
+
+    /// Sets the system prompt from `/system`, to a preset when given one of
+    /// their names. Shows the one in use without a prompt.
+    pub fn set_system_prompt(&mut self, text: &str) {
+        let presets = SystemPromptPresets::from_config();
+        if text.is_empty() {
+            let current = Config::get(ConfigKey::SystemPrompt);
+            let mut lines = vec![if current.is_empty() {
+                "There's no system prompt, set one with `/system PROMPT`.".to_string()
+            } else {
+                format!("The system prompt is:\n\n{current}")
+            }];
+            if !presets.names().is_empty() {
+                lines.push(format!(
+                    "Presets: {}",
+                    presets
+                        .names()
+                        .iter()
+                        .map(|e| return format!("`{e}`"))
+                        .collect::<Vec<String>>()
+                        .join(", ")
+                ));
+            }
+            self.add_message(Message::new(Author::Oatmeal, &lines.join("\n\n")));
+            return;
+        }
+        let msg = if text == "default" {
+            Config::set(ConfigKey::SystemPrompt, "");
+            "Answers will no longer follow a system prompt.".to_string()
+        } else if let Some(preset) = presets.get(text) {
+            Config::set(ConfigKey::SystemPrompt, preset);
+            format!("Answers will now follow the `{text}` system prompt.")
+        } else {
+            Config::set(ConfigKey::SystemPrompt, text);
+            "Answers will now follow the system prompt.".to_string()
+        };
+        self.add_message(Message::new(Author::Oatmeal, &msg));
+    }
+
+    pub fn handle_macro_loaded(&mut self, name: &str, steps: Vec<String>) {
+        self.waiting_for_backend = false;
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!("Playing macro `{name}`, {} steps.", steps.len()),
+        ));
+        self.macro_queue = steps.into();
+    }
+
+    /// Next step of the macro being played, once the backend is done with
+    /// the last one. Playing stops at the first error.
+    pub fn next_macro_step(&mut self) -> Option<String> {
+        if self.waiting_for_backend || self.macro_queue.is_empty() {
+            return None;
+        }
+
+        let failed = self
+            .messages
+            .last()
+            .map(|e| return e.message_type() == MessageType::Error)
+            .unwrap_or(false);
+        if failed {
+            let count = self.macro_queue.len();
+            self.macro_queue.clear();
+            self.add_message(Message::new(
+                Author::Oatmeal,
+                &format!("Stopped playing the macro, skipping its last {count} steps."),
+            ));
+            return None;
+        }
+
+        return self.macro_queue.pop_front();
+    }
+
+    /// Progress of the answer being streamed, once its first chunk arrived.
+    pub fn generation_progress(&self) -> Option<GenerationProgress> {
+        if !self.waiting_for_backend {
+            return None;
+        }
+
+        let last = self.messages.last()?;
+        if last.author != Author::Model {
+            return None;
+        }
+
+        return Some(GenerationProgress::new(
+            last.tokens(),
+            &self.past_answer_tokens,
+        ));
+    }
+
+    /// Counts the tokens of prompts and answers with the model's tokenizer
+    /// once they're done streaming, so the counts outlive their text being
+    /// archived.
+    fn count_tokens(&mut self) {
+        let tokenizer = Tokenizer::for_model(
+            &Config::get(ConfigKey::Backend),
+            &Config::get(ConfigKey::Model),
+        );
+        let streaming = usize::from(self.waiting_for_backend);
+        let end = self.messages.len().saturating_sub(streaming);
+        for message in self.messages[..end].iter_mut() {
+            if message.author != Author::Oatmeal && !message.is_evicted() && !message.is_counted() {
+                message.set_tokens(tokenizer.count(&message.text));
+            }
+        }
+    }
+
+    /// Tokens the chat takes up of the model's context window, leaving out
+    /// slash commands as they're never sent.
+    pub fn context_usage(&self) -> ContextUsage {
+        let summary_tokens = self
+            .context_summary
+            .as_ref()
+            .map(|e| return estimate_tokens(e))
+            .unwrap_or(0);
+        let tokens = self.messages[self.context_start.min(self.messages.len())..]
+            .iter()
+            .filter(|e| {
+                return e.author != Author::User || SlashCommand::parse(&e.text).is_none();
+            })
+            .map(|e| return e.tokens())
+            .sum::<u64>()
+            + summary_tokens
+            + self
+                .few_shot
+                .iter()
+                .map(|e| return estimate_tokens(&e.user) + estimate_tokens(&e.assistant))
+                .sum::<u64>();
+
+        let model = Config::get(ConfigKey::Model);
+        let window = Config::get(ConfigKey::ContextWindow)
+            .parse::<u64>()
+            .ok()
+            .or_else(|| return context_window(&model));
+
+        return ContextUsage { tokens, window };
+    }
+
+    /// Starts counting what's in the backend's context over from
+    /// `messages[start..]`.
+    fn reset_context_window(&mut self, start: usize) {
+        self.context_start = start;
+        self.context_summary = None;
+    }
+
+    /// Makes room for the prompt when the chat outgrew the model's context
+    /// window, following `context-overflow`. The oldest messages stay in the
+    /// chat, but are summarized or left out of what's sent. Examples from
+    /// `/fewshot` go ahead of the prompt too, whenever the backend's context
+    /// starts over.
+    pub fn fit_context(&mut self, mut prompt: BackendPrompt) -> Action {
+        let strategy = ContextOverflow::from_config();
+        let model = Config::get(ConfigKey::Model);
+        let split = match self.context_usage().window {
+            Some(window) if strategy != ContextOverflow::Off => {
+                let tokenizer = Tokenizer::for_model(&Config::get(ConfigKey::Backend), &model);
+                let summary_tokens = self
+                    .context_summary
+                    .as_ref()
+                    .map(|e| return estimate_tokens(e))
+                    .unwrap_or(0);
+                let few_shot_tokens = self
+                    .few_shot
+                    .iter()
+                    .map(|e| return estimate_tokens(&e.user) + estimate_tokens(&e.assistant))
+                    .sum::<u64>();
+                let document_tokens = self
+                    .context_documents_for_prompt()
+                    .iter()
+                    .map(|e| return estimate_tokens(&e.wrap()))
+                    .sum::<u64>();
+                // The prompt was already added to the chat.
+                let history = &self.messages[..self.messages.len().saturating_sub(1)];
+                split_context(
+                    history,
+                    self.context_start,
+                    tokenizer.count(&prompt.text)
+                        + summary_tokens
+                        + few_shot_tokens
+                        + document_tokens,
+                    window,
+                )
+            }
+            _ => None,
+        };
+        let split = match split {
+            Some(split) => split,
+            None => {
+                prompt.prepend_context_documents(&self.take_context_documents());
+                prompt.prepend_few_shot(&self.take_few_shot());
+                return Action::BackendRequest(prompt);
+            }
+        };
+
+        // The history is carried over to a fresh backend context, which
+        // needs the documents and examples again.
+        self.context_documents_sent = true;
+        prompt.prepend_context_documents(&self.context_documents_for_prompt());
+        self.few_shot_sent = true;
+        prompt.prepend_few_shot(&self.few_shot);
+
+        let count = split.dropped_count;
+        self.context_start = split.start;
+        if strategy == ContextOverflow::Truncate {
+            self.context_summary = None;
+            self.pending_context_note = Some(format!(
+                "Left out the {count} oldest messages to fit {model}'s context window"
+            ));
+            prompt.prepend_history(None, &split.kept);
+            return Action::BackendRequest(prompt);
+        }
+
+        self.pending_context_note = Some(format!(
+            "Summarized the {count} oldest messages to fit {model}'s context window"
+        ));
+        let mut dropped = split.dropped;
+        if let Some(summary) = self.context_summary.take() {
+            dropped = format!("Summary of what came before:\n\n{summary}\n\n{dropped}");
+        }
+        return Action::SummarizeContext(dropped, split.kept, prompt);
+    }
+
+    pub fn handle_context_summarized(&mut self, summary: String) {
+        if summary.trim().is_empty() {
+            self.pending_context_note = self.pending_context_note.take().map(|e| {
+                return e.replacen("Summarized", "Couldn't summarize", 1)
+                    + ", so they were left out";
+            });
+            return;
+        }
+        self.context_summary = Some(summary);
+    }
+
+    /// Shows or hides the estimated tokens and cost of each message.
+    pub fn toggle_usage(&mut self) {
+        if self.bubble_list.shows_usage() {
+            self.bubble_list.set_usage(None);
+        } else {
+            self.bubble_list
+                .set_usage(Some(ModelPrices::parse(&Config::get(
+                    ConfigKey::ModelPrices,
+                ))));
+        }
+        self.sync_dependants();
+    }
+
+    /// Scrolls diagrams too wide for their bubble sideways.
+    pub fn scroll_horizontally(&mut self, forward: bool) {
+        if self.bubble_list.scroll_horizontally(forward) {
+            self.sync_dependants();
+        }
+    }
+
+    /// Opens or closes the outline pane.
+    pub fn toggle_outline(&mut self) {
+        self.outline
+            .toggle(&self.archive.restore_all(&self.messages));
+    }
+
+    /// Widens the outline pane, or narrows it when `grow` is false, saving
+    /// its new size to the config file for later sessions.
+    pub fn resize_outline(&mut self, grow: bool, tx: &mpsc::UnboundedSender<Action>) -> Result<()> {
+        if !self.outline.open || !self.outline.resize(grow) {
+            return Ok(());
+        }
+
+        tx.send(Action::SaveConfig(
+            ConfigKey::OutlineRatio,
+            self.outline.ratio.to_string(),
+        ))?;
+        return Ok(());
+    }
+
+    /// Moves the outline selection, scrolling to the selected entry.
+    pub fn select_outline(&mut self, forward: bool) {
+        if !self.outline.open {
+            return;
+        }
+
+        if let Some(entry) = self.outline.select(forward) {
+            // Bubbles start with a border line, and wrapping can only push
+            // the entry further down, so this lands at or above it.
+            let mut position = self.bubble_list.line_offset(entry.message_index);
+            if entry.line > 0 {
+                position += entry.line + 1;
+            }
+            self.scroll.to(position);
+        }
+    }
+
+    /// Draws `cursor` after the answer while it streams in, rebuilding its
+    /// bubble as the cursor blinks.
+    pub fn set_streaming_cursor(&mut self, cursor: Option<&'static str>) {
+        let streaming = self.waiting_for_backend
+            && self
+                .messages
+                .last()
+                .map(|e| return e.author == Author::Model)
+                .unwrap_or(false);
+        if self
+            .bubble_list
+            .set_cursor(if streaming { cursor } else { None })
+        {
+            self.sync_dependants();
+        }
+    }
+
+    fn sync_dependants(&mut self) {
+        // Bubbles not rendered at this width yet need their text back.
+        for (idx, message) in self.messages.iter_mut().enumerate() {
+            if message.is_evicted() && !self.bubble_list.is_rendered(idx, self.last_known_width) {
+                self.archive.restore(message);
+            }
+        }
+        self.count_tokens();
+        self.bubble_list
+            .set_messages(&self.messages, self.last_known_width);
+        if self.outline.open {
+            self.outline.set_messages(&self.messages);
+        }
+        self.archive
+            .enforce_limit(&mut self.messages, &mut self.backend_context);
+
+        let scrollbar_at_bottom = self.scroll.is_position_at_last();
+        self.scroll
+            .set_state(self.bubble_list.len(), self.last_known_height);
+
+        if self.waiting_for_backend && scrollbar_at_bottom {
+            self.scroll.last();
+        }
+    }
+
+    /// Changes how answers are sampled from now on. The parameters are saved
+    /// with the session.
+    pub fn set_sampling(&mut self, param: SamplingParam, value: Option<String>) {
+        Config::set(param.config_key(), value.as_deref().unwrap_or_default());
+        let text = match value {
+            Some(value) => {
+                format!("Answers will now use {param} {value}, with backends that support it.")
+            }
+            None => format!("Answers will now use the backend's default {param}."),
+        };
+        self.add_message(Message::new(Author::Oatmeal, &text));
+    }
+
+    /// Continues the chat as a new session, which this Oatmeal holds the lock
+    /// for.
+    pub fn fork_session(&mut self) -> Result<()> {
+        let session_id = Sessions::create_id();
+        let session_lock = self.sessions_service.lock(&session_id)?;
+        let previous = std::mem::replace(&mut self.session_id, session_id);
+        self.session_lock = session_lock;
+
+        self.add_message(Message::new(
+            Author::Oatmeal,
+            &format!(
+                "Forked session {previous} as {}, which this chat is saved to from now on.",
+                self.session_id
+            ),
+        ));
+
+        return Ok(());
+    }
+
+    /// Does nothing while the session is read-only.
+    pub async fn save_session(&mut self) -> Result<()> {
+        if self.session_lock.is_none() {
+            return Ok(());
+        }
+
+        self.sessions_service
+            .save(
+                &self.session_id,
+                &self.backend_context,
+                &self.editor_context,
+                &self.archive.restored(&self.messages),
+                &self.few_shot,
+                &self.context_documents.documents,
+                &self.variables,
+                &self.draft,
+            )
+            .await?;
+        self.saved_fingerprint = self.fingerprint();
+
+        return Ok(());
+    }
+
+    /// Hashes what saving the session writes, leaving out what's saved from
+    /// the config. Evicted messages are told apart by where they're archived,
+    /// as their text can't change while it's on disk.
+    fn fingerprint(&self) -> u64 {
+        let messages = self
+            .messages
+            .iter()
+            .map(|e| return (e.archived().map(|archived| return archived.offset), e))
+            .collect::<Vec<_>>();
+        let state = serde_json::to_string(&(
+            &self.backend_context,
+            messages,
+            &self.few_shot,
+            &self.context_documents.documents,
+            &self.variables.values,
+            &self.draft,
+        ))
+        .unwrap_or_default();
+
+        let mut hasher = DefaultHasher::new();
+        state.hash(&mut hasher);
+        return hasher.finish();
+    }
+
+    /// Whether quitting now would lose anything since the session was last
+    /// saved.
+    pub fn has_unsaved_changes(&self) -> bool {
+        return self.session_lock.is_some() && self.fingerprint() != self.saved_fingerprint;
+    }
+
+    /// Quits right away when there's nothing to lose, returning true.
+    /// Otherwise opens the quit prompt to ask first.
+    pub fn request_quit(&mut self) -> bool {
+        let mut reasons = vec![];
+        if self.waiting_for_backend {
+            reasons.push("An answer is still being generated.".to_string());
+        }
+        if self.has_unsaved_changes() {
+            reasons.push(format!(
+                "Session {} has changes that aren't saved yet.",
+                self.session_id
+            ));
+        }
+        if reasons.is_empty() {
+            return true;
+        }
+
+        self.quit_prompt = Some(QuitPrompt::new(reasons));
+        return false;
+    }
+
+    /// Saves what's left in the input box on quitting. Chats that never got
+    /// a prompt aren't saved just for being left empty.
+    pub async fn save_draft(&mut self, draft: &str) -> Result<()> {
+        let prompted = self
+            .messages
+            .iter()
+            .any(|e| return e.author == Author::User);
+        if draft.is_empty() && !prompted {
+            return Ok(());
+        }
+
+        self.draft = draft.to_string();
+        return self.save_session().await;
     }
 }
@@ -0,0 +1,79 @@
+#[cfg(test)]
+#[path = "audit_test.rs"]
+mod tests;
+
+use std::path;
+
+use anyhow::Result;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use super::ShellOutput;
+use crate::domain::models::AuditEntry;
+use crate::domain::models::AuditKind;
+
+/// Append-only log of tools run, commands executed, and files written on the
+/// user's behalf, stored as one JSON entry per line.
+pub struct AuditLog {
+    pub path: path::PathBuf,
+}
+
+impl Default for AuditLog {
+    fn default() -> AuditLog {
+        let path = dirs::cache_dir().unwrap().join("oatmeal/audit.jsonl");
+
+        return AuditLog::new(path);
+    }
+}
+
+impl AuditLog {
+    pub fn new(path: path::PathBuf) -> AuditLog {
+        return AuditLog { path };
+    }
+
+    pub async fn record(&self, entry: &AuditEntry) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+
+        let line = format!("{}\n", serde_json::to_string(entry)?);
+        file.write_all(line.as_bytes()).await?;
+
+        return Ok(());
+    }
+
+    pub async fn record_shell(&self, output: &ShellOutput) -> Result<()> {
+        let exit_code = output
+            .exit_code
+            .map(|e| return e.to_string())
+            .unwrap_or("none".to_string());
+
+        return self
+            .record(&AuditEntry::new(
+                AuditKind::Shell,
+                &output.command,
+                Some(format!("exit code {exit_code}")),
+            ))
+            .await;
+    }
+
+    /// Returns every entry, oldest first. Lines that can't be parsed are
+    /// skipped.
+    pub async fn entries(&self) -> Result<Vec<AuditEntry>> {
+        if !self.path.exists() {
+            return Ok(vec![]);
+        }
+
+        let text = fs::read_to_string(&self.path).await?;
+        return Ok(text
+            .lines()
+            .filter_map(|e| return serde_json::from_str::<AuditEntry>(e).ok())
+            .collect());
+    }
+}
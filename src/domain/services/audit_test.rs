@@ -0,0 +1,75 @@
+use anyhow::Result;
+
+use super::AuditLog;
+use crate::domain::models::AuditEntry;
+use crate::domain::models::AuditKind;
+use crate::domain::services::ShellOutput;
+
+#[tokio::test]
+async fn it_appends_entries() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let audit = AuditLog::new(dir.path().join("nested/audit.jsonl"));
+    assert!(audit.entries().await?.is_empty());
+
+    audit
+        .record(&AuditEntry::new(
+            AuditKind::Tool,
+            "/gh dustinblackman/oatmeal#3",
+            None,
+        ))
+        .await?;
+    audit
+        .record_shell(&ShellOutput {
+            command: "cargo test".to_string(),
+            success: false,
+            exit_code: Some(101),
+            stdout: "".to_string(),
+            stderr: "".to_string(),
+        })
+        .await?;
+
+    let entries = audit.entries().await?;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].kind, AuditKind::Tool);
+    assert_eq!(entries[0].summary, "/gh dustinblackman/oatmeal#3");
+    assert_eq!(entries[1].kind, AuditKind::Shell);
+    assert_eq!(entries[1].details, Some("exit code 101".to_string()));
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_skips_invalid_lines() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("audit.jsonl");
+    tokio::fs::write(&path, "not json\n").await?;
+
+    let audit = AuditLog::new(path);
+    audit
+        .record(&AuditEntry::new(AuditKind::File, "src/main.rs", None))
+        .await?;
+    assert_eq!(audit.entries().await?.len(), 1);
+
+    return Ok(());
+}
+
+#[test]
+fn it_formats_entries() {
+    let mut entry = AuditEntry::new(
+        AuditKind::File,
+        "src/main.rs",
+        Some("--- src/main.rs\n+++ src/main.rs\n+fn main() {}\n".to_string()),
+    );
+    entry.timestamp = "2023-11-28T10:00:00+00:00".to_string();
+    insta::assert_snapshot!(entry.format(), @r###"
+    2023-11-28T10:00:00+00:00 [file] src/main.rs
+        --- src/main.rs
+        +++ src/main.rs
+        +fn main() {}
+    "###);
+
+    entry.kind = AuditKind::Shell;
+    entry.summary = "cargo test".to_string();
+    entry.details = Some("exit code 0".to_string());
+    insta::assert_snapshot!(entry.format(), @"2023-11-28T10:00:00+00:00 [shell] cargo test (exit code 0)");
+}
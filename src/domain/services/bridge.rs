@@ -0,0 +1,142 @@
+#[cfg(test)]
+#[path = "bridge_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use tokio::sync::mpsc;
+
+use super::Sessions;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Author;
+use crate::domain::models::BackendBox;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BridgeBox;
+use crate::domain::models::BridgeMessage;
+use crate::domain::models::Event;
+use crate::domain::models::Message;
+use crate::domain::models::PromptVariables;
+
+/// Answers messages mentioning the bot in a chat room, keeping a session per
+/// room so conversations carry on between questions.
+pub struct BridgeService {
+    bridge: BridgeBox,
+    backend: BackendBox,
+    sessions: Sessions,
+    nick: String,
+}
+
+impl BridgeService {
+    pub fn new(bridge: BridgeBox, backend: BackendBox, sessions: Sessions) -> BridgeService {
+        return BridgeService {
+            bridge,
+            backend,
+            sessions,
+            nick: Config::get(ConfigKey::BridgeNick),
+        };
+    }
+
+    /// Session ID used for a room, such as `irc-_rust` for `#rust`.
+    pub fn session_id(&self, room: &str) -> String {
+        let room = room
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' {
+                    return c;
+                }
+                return '_';
+            })
+            .collect::<String>();
+
+        return format!("{}-{room}", self.bridge.name());
+    }
+
+    pub async fn start(&mut self) -> Result<()> {
+        if Config::get(ConfigKey::Model).is_empty() {
+            let models = self.backend.list_models().await?;
+            match models.first() {
+                Some(model) => Config::set(ConfigKey::Model, model),
+                None => {
+                    bail!(
+                        "{} returned no models, pick one with `--model`",
+                        self.backend.name()
+                    )
+                }
+            }
+        }
+
+        self.bridge.connect().await?;
+        println!(
+            "Connected to {}, answering messages mentioning {}",
+            self.bridge.name(),
+            self.nick
+        );
+
+        while let Some(msg) = self.bridge.next_message().await? {
+            if let Some(prompt) = msg.prompt_for(&self.nick) {
+                if let Err(err) = self.answer(&msg, &prompt).await {
+                    tracing::error!(error = ?err, "Failed to answer bridge message");
+                    self.bridge
+                        .reply(&msg.room, &format!("Sorry, I failed to answer: {err}"))
+                        .await?;
+                }
+            }
+        }
+
+        return Ok(());
+    }
+
+    async fn answer(&mut self, msg: &BridgeMessage, prompt: &str) -> Result<()> {
+        let session_id = self.session_id(&msg.room);
+        let (mut backend_context, mut messages) = match self.sessions.load(&session_id).await {
+            Ok(session) => (session.state.backend_context, session.state.messages),
+            Err(_) => ("".to_string(), vec![]),
+        };
+
+        let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+        self.backend
+            .get_completion(
+                BackendPrompt::new(prompt.to_string(), backend_context.to_string()),
+                &tx,
+            )
+            .await?;
+
+        let mut text = "".to_string();
+        while let Ok(event) = rx.try_recv() {
+            match event {
+                Event::BackendPromptResponse(res) => {
+                    text += &res.text;
+                    if let Some(context) = res.context {
+                        backend_context = context;
+                    }
+                }
+                Event::BackendMessage(err) => {
+                    bail!(err.text);
+                }
+                _ => {}
+            }
+        }
+
+        self.bridge.reply(&msg.room, text.trim()).await?;
+
+        let mut user_message = Message::new(Author::User, prompt);
+        user_message.set_username(&msg.sender);
+        messages.push(user_message);
+        messages.push(Message::new(Author::Model, text.trim()));
+        self.sessions
+            .save(
+                &session_id,
+                &backend_context,
+                &None,
+                &messages,
+                &[],
+                &[],
+                &PromptVariables::default(),
+                "",
+            )
+            .await?;
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use super::BridgeService;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Bridge;
+use crate::domain::models::BridgeMessage;
+use crate::domain::models::BridgeName;
+use crate::domain::models::Event;
+use crate::domain::services::Sessions;
+
+struct TestBridge {
+    incoming: VecDeque<BridgeMessage>,
+    replies: Arc<Mutex<Vec<(String, String)>>>,
+}
+
+#[async_trait]
+impl Bridge for TestBridge {
+    fn name(&self) -> BridgeName {
+        return BridgeName::Irc;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn connect(&mut self) -> Result<()> {
+        return Ok(());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn next_message(&mut self) -> Result<Option<BridgeMessage>> {
+        return Ok(self.incoming.pop_front());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn reply(&mut self, room: &str, text: &str) -> Result<()> {
+        self.replies
+            .lock()
+            .unwrap()
+            .push((room.to_string(), text.to_string()));
+        return Ok(());
+    }
+}
+
+struct TestBackend {}
+
+#[async_trait]
+impl Backend for TestBackend {
+    fn name(&self) -> BackendName {
+        return BackendName::Ollama;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        return Ok(());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        return Ok(vec!["model".to_string()]);
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        tx.send(Event::BackendPromptResponse(BackendResponse {
+            author: Author::Model,
+            text: format!("You asked: {}", prompt.text),
+            done: true,
+            context: Some(format!("{}+1", prompt.backend_context)),
+        }))?;
+        return Ok(());
+    }
+}
+
+fn message(text: &str) -> BridgeMessage {
+    return BridgeMessage {
+        room: "#rust".to_string(),
+        sender: "alice".to_string(),
+        text: text.to_string(),
+    };
+}
+
+#[tokio::test]
+async fn it_answers_mentions_with_a_session_per_room() -> Result<()> {
+    let sessions_dir = tempfile::tempdir()?;
+    let replies = Arc::new(Mutex::new(vec![]));
+    let bridge = TestBridge {
+        incoming: VecDeque::from(vec![
+            message("oatmeal: first"),
+            message("not for the bot"),
+            message("oatmeal: second"),
+        ]),
+        replies: replies.clone(),
+    };
+
+    let mut service = BridgeService::new(
+        Box::new(bridge),
+        Box::new(TestBackend {}),
+        Sessions::new(sessions_dir.path().to_path_buf()),
+    );
+    service.nick = "oatmeal".to_string();
+    service.start().await?;
+
+    assert_eq!(
+        *replies.lock().unwrap(),
+        vec![
+            ("#rust".to_string(), "You asked: first".to_string()),
+            ("#rust".to_string(), "You asked: second".to_string()),
+        ]
+    );
+
+    let session = Sessions::new(sessions_dir.path().to_path_buf())
+        .load(&service.session_id("#rust"))
+        .await?;
+    assert_eq!(session.id, "irc-_rust");
+    assert_eq!(session.state.backend_context, "+1+1");
+    assert_eq!(session.state.messages.len(), 4);
+    assert_eq!(session.state.messages[0].author_name(), "alice");
+
+    return Ok(());
+}
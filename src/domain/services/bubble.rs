@@ -3,6 +3,7 @@
 mod tests;
 
 use ratatui::style::Color;
+use ratatui::style::Modifier;
 use ratatui::style::Style;
 use ratatui::text::Line;
 use ratatui::text::Span;
@@ -11,9 +12,14 @@ use syntect::highlighting::Theme;
 
 use super::Syntaxes;
 use super::SYNTAX_SET;
+use crate::domain::models::preserved_lines;
 use crate::domain::models::Author;
+use crate::domain::models::CodeBlockParser;
+use crate::domain::models::ColorMode;
+use crate::domain::models::FenceLine;
 use crate::domain::models::Message;
 use crate::domain::models::MessageType;
+use crate::domain::models::RecoveryAction;
 
 #[derive(PartialEq, Eq)]
 pub enum BubbleAlignment {
@@ -26,6 +32,15 @@ pub struct Bubble<'a> {
     message: &'a Message,
     window_max_width: usize,
     codeblock_counter: usize,
+    /// Token usage shown in the bottom border.
+    usage: Option<String>,
+    /// Columns that diagrams too wide for the bubble are scrolled by.
+    horizontal_offset: usize,
+    /// Columns the widest diagram is cut off by, as of the last `as_lines`.
+    overflow: usize,
+    /// Drops the border box for a line with the author above the text, to
+    /// fit more of the chat on short terminals.
+    compact: bool,
 }
 
 pub struct BubbleConfig {
@@ -64,9 +79,32 @@ impl<'a> Bubble<'_> {
             message,
             window_max_width,
             codeblock_counter,
+            usage: None,
+            horizontal_offset: 0,
+            overflow: 0,
+            compact: false,
         };
     }
 
+    pub fn with_usage(mut self, usage: Option<String>) -> Self {
+        self.usage = usage;
+        return self;
+    }
+
+    pub fn with_horizontal_offset(mut self, horizontal_offset: usize) -> Self {
+        self.horizontal_offset = horizontal_offset;
+        return self;
+    }
+
+    pub fn with_compact(mut self, compact: bool) -> Self {
+        self.compact = compact;
+        return self;
+    }
+
+    pub fn overflow(&self) -> usize {
+        return self.overflow;
+    }
+
     pub fn style_config() -> BubbleConfig {
         return BubbleConfig {
             // Unicode character border + padding.
@@ -78,39 +116,77 @@ impl<'a> Bubble<'_> {
         };
     }
 
+    pub fn compact_style_config() -> BubbleConfig {
+        return BubbleConfig {
+            // Left bar + padding.
+            bubble_padding: 4,
+            // left border + left padding + (text, not counted) + scrollbar.
+            border_elements_length: 3,
+            outer_padding_percentage: 0.02,
+        };
+    }
+
+    fn config(&self) -> BubbleConfig {
+        if self.compact {
+            return Bubble::compact_style_config();
+        }
+        return Bubble::style_config();
+    }
+
     pub fn as_lines(&mut self, theme: &Theme) -> Vec<Line<'a>> {
         // Lazy default
         let mut highlight = HighlightLines::new(Syntaxes::get("text"), theme);
-        let mut in_codeblock = false;
+        let mut parser = CodeBlockParser::default();
+        let mut message_codeblock_idx = 0;
+        let message_codeblocks = self.message.codeblocks();
         let mut lines: Vec<Line> = vec![];
 
         let max_line_length = self.get_max_line_length();
+        let preserved = preserved_lines(&self.message.text);
 
-        for line in self.message.text.lines() {
+        for (line_idx, line) in self.message.text.lines().enumerate() {
             let mut spans = vec![];
 
-            if line.trim().starts_with("```") {
-                let lang = line.trim().replace("```", "");
-                let syntax = Syntaxes::get(&lang);
-                if !in_codeblock {
-                    highlight = HighlightLines::new(syntax, theme);
-                    in_codeblock = true;
-
-                    self.codeblock_counter += 1;
-                    spans = vec![
-                        Span::from(line.to_owned()),
-                        Span::styled(
-                            format!(" ({})", self.codeblock_counter),
-                            Style {
-                                fg: Some(Color::White),
-                                ..Style::default()
-                            },
-                        ),
-                    ];
-                } else {
-                    in_codeblock = false;
+            let kind = parser.line(line);
+            if preserved.get(line_idx) == Some(&true) {
+                lines.push(self.preserved_line(line, max_line_length));
+                continue;
+            }
+
+            if kind == FenceLine::Open {
+                let mut lang = parser.open_language().unwrap_or_default();
+                let mut fence_line = line.to_owned();
+                if let Some(lang_override) = self.message.codeblock_language(message_codeblock_idx)
+                {
+                    let indent = &line[..(line.len() - line.trim_start().len())];
+                    lang = lang_override.to_string();
+                    fence_line = format!("{indent}```{lang}");
                 }
-            } else if in_codeblock {
+
+                let mut path_hint = "".to_string();
+                if let Some(path) = message_codeblocks
+                    .get(message_codeblock_idx)
+                    .and_then(|e| return e.path.as_ref())
+                {
+                    path_hint = format!(" {path}, /apply {} to write", self.codeblock_counter + 1);
+                }
+                message_codeblock_idx += 1;
+
+                let syntax = Syntaxes::get(&lang);
+                highlight = HighlightLines::new(syntax, theme);
+
+                self.codeblock_counter += 1;
+                spans = vec![
+                    Span::from(fence_line),
+                    Span::styled(
+                        format!(" ({}){path_hint}", self.codeblock_counter),
+                        Style {
+                            fg: ColorMode::current().apply(Color::White),
+                            ..Style::default()
+                        },
+                    ),
+                ];
+            } else if kind == FenceLine::Code {
                 // Highlighting doesn't work accurately unless each line is postfixed with '\n',
                 // especially when dealing with multi-line code comments.
                 let line_nl = format!("{line}\n");
@@ -173,18 +249,101 @@ impl<'a> Bubble<'_> {
             lines.push(self.spans_to_line(split_spans, max_line_length));
         }
 
+        if !self.message.images().is_empty() {
+            lines.push(self.spans_to_line(vec![], max_line_length));
+            for placeholder in self.message.images() {
+                lines.push(self.spans_to_line(
+                    vec![Span::styled(
+                        placeholder.to_string(),
+                        Style::default().add_modifier(Modifier::ITALIC),
+                    )],
+                    max_line_length,
+                ));
+            }
+        }
+
+        if !self.message.recovery().is_empty() {
+            lines.push(self.spans_to_line(vec![], max_line_length));
+            lines.extend(self.recovery_lines(max_line_length));
+        }
+
         return self.wrap_lines_in_buddle(lines, max_line_length);
     }
 
+    /// A line of a diagram, kept as is rather than wrapped. When it's too
+    /// wide, only the part scrolled to is shown, with arrows at the sides it
+    /// continues past.
+    fn preserved_line(&mut self, line: &str, max_line_length: usize) -> Line<'a> {
+        let width = line.chars().count();
+        if width <= max_line_length {
+            return self.spans_to_line(vec![Span::from(line.to_owned())], max_line_length);
+        }
+
+        let overflow = width - max_line_length;
+        self.overflow = self.overflow.max(overflow);
+        let offset = self.horizontal_offset.min(overflow);
+
+        let mut visible: Vec<char> = line.chars().skip(offset).take(max_line_length).collect();
+        if offset > 0 {
+            visible[0] = '‹';
+        }
+        if offset < overflow {
+            visible[max_line_length - 1] = '›';
+        }
+
+        return self.spans_to_line(
+            vec![Span::from(visible.into_iter().collect::<String>())],
+            max_line_length,
+        );
+    }
+
+    fn recovery_label(action: &RecoveryAction) -> String {
+        return format!("[ {} ]", action.label());
+    }
+
+    /// Recovery actions as buttons, with the chosen one highlighted. Buttons
+    /// wrap on to another line when they don't fit.
+    fn recovery_lines(&self, max_line_length: usize) -> Vec<Line<'a>> {
+        let selected = self.message.selected_recovery();
+        let mut lines = vec![];
+        let mut spans: Vec<Span<'a>> = vec![];
+        let mut line_len = 0;
+
+        for action in self.message.recovery() {
+            let label = Bubble::recovery_label(action);
+            if line_len > 0 && line_len + 1 + label.len() > max_line_length {
+                lines.push(self.spans_to_line(std::mem::take(&mut spans), max_line_length));
+                line_len = 0;
+            }
+            if line_len > 0 {
+                spans.push(Span::from(" "));
+                line_len += 1;
+            }
+
+            let mut style = Style::default();
+            if selected == Some(*action) {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            line_len += label.len();
+            spans.push(Span::styled(label, style));
+        }
+        lines.push(self.spans_to_line(spans, max_line_length));
+
+        return lines;
+    }
+
     fn spans_to_line(&self, mut spans: Vec<Span<'a>>, max_line_length: usize) -> Line<'a> {
-        let line_str_len: usize = spans.iter().map(|e| return e.content.len()).sum();
+        let line_str_len: usize = spans.iter().map(|e| return e.content.chars().count()).sum();
         let fill = repeat_from_subtractions(" ", vec![max_line_length, line_str_len]);
-        let formatted_line_length =
-            line_str_len + fill.len() + Bubble::style_config().bubble_padding;
+        let formatted_line_length = line_str_len + fill.len() + self.config().bubble_padding;
 
         let mut wrapped_spans = vec![self.highlight_span("│ ".to_string())];
         wrapped_spans.append(&mut spans);
-        wrapped_spans.push(self.highlight_span(format!("{fill} │")));
+        if self.compact {
+            wrapped_spans.push(Span::from(fill));
+        } else {
+            wrapped_spans.push(self.highlight_span(format!("{fill} │")));
+        }
 
         let outer_bubble_padding =
             repeat_from_subtractions(" ", vec![self.window_max_width, formatted_line_length]);
@@ -200,44 +359,101 @@ impl<'a> Bubble<'_> {
         return Line::from(line_spans);
     }
 
-    fn get_max_line_length(&self) -> usize {
-        let style_config = Bubble::style_config();
+    /// Widest a line of text can be within a bubble before it's wrapped.
+    pub fn max_text_width(window_max_width: usize) -> usize {
+        return Bubble::text_width(&Bubble::style_config(), window_max_width);
+    }
+
+    fn text_width(style_config: &BubbleConfig, window_max_width: usize) -> usize {
         // Add a minimum 4% of padding on the side.
-        let min_bubble_padding_length = ((self.window_max_width as f32
-            * style_config.outer_padding_percentage)
-            .ceil()) as usize;
+        let min_bubble_padding_length =
+            ((window_max_width as f32 * style_config.outer_padding_percentage).ceil()) as usize;
 
         // Border elements + minimum bubble padding.
         let line_border_width = style_config.border_elements_length + min_bubble_padding_length;
 
+        return window_max_width.saturating_sub(line_border_width);
+    }
+
+    fn get_max_line_length(&self) -> usize {
+        let max_text_width = Bubble::text_width(&self.config(), self.window_max_width);
         let mut max_line_length = self
             .message
             .text
             .lines()
             .map(|line| {
-                return line.len();
+                return line.chars().count();
             })
             .max()
-            .unwrap();
+            .unwrap_or(0);
 
-        if max_line_length > (self.window_max_width - line_border_width) {
-            max_line_length = self.window_max_width - line_border_width;
+        let recovery_len = self
+            .message
+            .recovery()
+            .iter()
+            .map(|e| return Bubble::recovery_label(e).len() + 1)
+            .sum::<usize>()
+            .saturating_sub(1);
+        if max_line_length < recovery_len {
+            max_line_length = recovery_len;
         }
 
-        let username = &self.message.author.to_string();
+        let images_len = self
+            .message
+            .images()
+            .iter()
+            .map(|e| return e.chars().count())
+            .max()
+            .unwrap_or(0);
+        if max_line_length < images_len {
+            max_line_length = images_len;
+        }
+
+        if max_line_length > max_text_width {
+            max_line_length = max_text_width;
+        }
+
+        let username = &self.message.title();
         if max_line_length < username.len() {
             max_line_length = username.len();
         }
 
+        let usage_len = self
+            .usage
+            .as_ref()
+            .map(|e| return e.chars().count())
+            .unwrap_or(0);
+        if max_line_length < usage_len {
+            max_line_length = usage_len;
+        }
+
+        // The author line spans the left bar too.
+        let header_len = (username.len() + usage_len).saturating_sub(2);
+        if self.compact && max_line_length < header_len {
+            max_line_length = header_len;
+        }
+
         return max_line_length;
     }
 
     fn wrap_lines_in_buddle(&self, lines: Vec<Line<'a>>, max_line_length: usize) -> Vec<Line<'a>> {
+        if self.compact {
+            return self.prefix_lines_with_author(lines, max_line_length);
+        }
+
         // Add 2 for the vertical bars.
         let inner_bar = ["─"].repeat(max_line_length + 2).join("");
         let top_left_border = "╭";
         let mut top_bar = format!("{top_left_border}{inner_bar}╮");
-        let bottom_bar = format!("╰{inner_bar}╯");
+        let mut bottom_bar = format!("╰{inner_bar}╯");
+        if let Some(usage) = &self.usage {
+            let bottom_replace = ["─"].repeat(usage.chars().count()).join("");
+            bottom_bar = bottom_bar.replacen(
+                format!("╰{bottom_replace}").as_str(),
+                format!("╰{usage}").as_str(),
+                1,
+            );
+        }
         let bar_bubble_padding = repeat_from_subtractions(
             " ",
             vec![
@@ -247,7 +463,7 @@ impl<'a> Bubble<'_> {
             ],
         );
 
-        let username = &self.message.author.to_string();
+        let username = &self.message.title();
 
         if self.alignment == BubbleAlignment::Left {
             let top_replace = ["─"].repeat(username.len()).join("");
@@ -274,12 +490,45 @@ impl<'a> Bubble<'_> {
         }
     }
 
+    /// A single line with the author, and token usage when shown, in place
+    /// of the border box.
+    fn prefix_lines_with_author(
+        &self,
+        lines: Vec<Line<'a>>,
+        max_line_length: usize,
+    ) -> Vec<Line<'a>> {
+        let mut header = self.message.title();
+        if let Some(usage) = &self.usage {
+            header += usage.trim_end();
+        }
+        // Add 2 for the left bar and its padding.
+        let fill = repeat_from_subtractions(" ", vec![max_line_length + 2, header.chars().count()]);
+        let bar_bubble_padding = repeat_from_subtractions(
+            " ",
+            vec![
+                self.window_max_width,
+                max_line_length,
+                self.config().bubble_padding,
+            ],
+        );
+
+        let header_line = if self.alignment == BubbleAlignment::Left {
+            self.highlight_line(format!("{header}{fill}{bar_bubble_padding}"))
+        } else {
+            self.highlight_line(format!("{bar_bubble_padding}{header}{fill}"))
+        };
+
+        let mut res = vec![header_line];
+        res.extend(lines);
+        return res;
+    }
+
     fn highlight_span(&self, text: String) -> Span<'a> {
         if self.message.message_type() == MessageType::Error {
             return Span::styled(
                 text,
                 Style {
-                    fg: Some(Color::Red),
+                    fg: ColorMode::current().apply(Color::Red),
                     ..Style::default()
                 },
             );
@@ -287,7 +536,7 @@ impl<'a> Bubble<'_> {
             return Span::styled(
                 text,
                 Style {
-                    fg: Some(Color::Rgb(138, 85, 63)), // Brown
+                    fg: ColorMode::current().apply(Color::Rgb(138, 85, 63)), // Brown
                     ..Style::default()
                 },
             );
@@ -1,22 +1,39 @@
 use std::collections::HashMap;
 
 use ratatui::prelude::Buffer;
+use ratatui::prelude::Modifier;
 use ratatui::prelude::Rect;
+use ratatui::prelude::Style;
 use ratatui::text::Line;
 use syntect::highlighting::Theme;
 
 use super::Bubble;
 use super::BubbleAlignment;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
 use crate::domain::models::Author;
+use crate::domain::models::LocaleFormat;
 use crate::domain::models::Message;
+use crate::domain::models::ModelPrices;
 
 #[cfg(test)]
 #[path = "bubble_list_test.rs"]
 mod tests;
 
+/// Columns a diagram moves by for each sideways scroll.
+const HORIZONTAL_SCROLL_STEP: usize = 8;
+
+/// Terminals with fewer rows than this use the compact layout when `layout`
+/// is `auto`.
+pub const COMPACT_LAYOUT_HEIGHT: u16 = 30;
+
 struct BubbleCacheEntry<'a> {
     codeblocks_count: usize,
     text_len: usize,
+    /// Columns the widest diagram within the bubble is cut off by.
+    overflow: usize,
+    /// Drawn after the text, while the answer streams in.
+    cursor: Option<&'static str>,
     lines: Vec<Line<'a>>,
 }
 
@@ -25,6 +42,16 @@ pub struct BubbleList<'a> {
     line_width: usize,
     lines_len: usize,
     theme: Theme,
+    /// Prices to annotate bubbles with their token usage, when shown.
+    usage: Option<ModelPrices>,
+    locale: LocaleFormat,
+    /// Columns that diagrams too wide for their bubble are scrolled by.
+    horizontal_offset: usize,
+    compact: bool,
+    /// Drawn after the last message's text.
+    cursor: Option<&'static str>,
+    /// Message whose bubble is dimmed as it fades in.
+    fading: Option<usize>,
 }
 
 impl<'a> BubbleList<'a> {
@@ -34,9 +61,70 @@ impl<'a> BubbleList<'a> {
             line_width: 0,
             lines_len: 0,
             theme,
+            usage: None,
+            locale: LocaleFormat::default(),
+            horizontal_offset: 0,
+            compact: false,
+            cursor: None,
+            fading: None,
         };
     }
 
+    /// Shows or hides each bubble's estimated tokens, and their cost for the
+    /// current model when it has a price.
+    pub fn set_usage(&mut self, usage: Option<ModelPrices>) {
+        self.usage = usage;
+        self.locale = LocaleFormat::from_config();
+        self.cache.clear();
+    }
+
+    /// Switches between bordered bubbles and the compact layout. Returns
+    /// whether it changed, in which case bubbles are rebuilt on the next
+    /// `set_messages`.
+    pub fn set_compact(&mut self, compact: bool) -> bool {
+        if self.compact == compact {
+            return false;
+        }
+
+        self.compact = compact;
+        self.cache.clear();
+        return true;
+    }
+
+    /// Draws `cursor` after the last message on the next `set_messages`.
+    /// Returns whether it changed.
+    pub fn set_cursor(&mut self, cursor: Option<&'static str>) -> bool {
+        if self.cursor == cursor {
+            return false;
+        }
+
+        self.cursor = cursor;
+        return true;
+    }
+
+    /// Dims the bubble of the message at `index` when rendering it.
+    pub fn set_fading(&mut self, fading: Option<usize>) {
+        self.fading = fading;
+    }
+
+    pub fn shows_usage(&self) -> bool {
+        return self.usage.is_some();
+    }
+
+    fn usage_label(&self, message: &Message) -> Option<String> {
+        let prices = self.usage.as_ref()?;
+        let tokens = message.tokens();
+        if tokens == 0 {
+            return None;
+        }
+
+        let mut label = format!("~{} tokens", self.locale.number(tokens as f64, 0));
+        if let Some(cost) = prices.cost(&Config::get(ConfigKey::Model), tokens) {
+            label += &format!(", ${}", self.locale.number(cost, 4));
+        }
+        return Some(format!(" {label} "));
+    }
+
     pub fn set_messages(&mut self, messages: &[Message], line_width: usize) {
         if self.line_width != line_width {
             self.cache.clear();
@@ -48,9 +136,16 @@ impl<'a> BubbleList<'a> {
             .iter()
             .enumerate()
             .map(|(idx, message)| {
+                let mut cursor = None;
+                if idx == messages.len() - 1 {
+                    cursor = self.cursor;
+                }
                 if self.cache.contains_key(&idx) {
                     let cache_entry = self.cache.get(&idx).unwrap();
-                    if idx < (messages.len() - 1) || message.text.len() == cache_entry.text_len {
+                    if cache_entry.cursor == cursor
+                        && (idx < (messages.len() - 1)
+                            || message.text.len() == cache_entry.text_len)
+                    {
                         total_codeblock_counter += cache_entry.codeblocks_count;
                         return cache_entry.lines.len();
                     }
@@ -61,8 +156,21 @@ impl<'a> BubbleList<'a> {
                     align = BubbleAlignment::Right;
                 }
 
-                let bubble_lines = Bubble::new(message, align, line_width, total_codeblock_counter)
-                    .as_lines(&self.theme);
+                let with_cursor;
+                let drawn = match cursor {
+                    Some(cursor) => {
+                        let mut drawn = message.clone();
+                        drawn.text += cursor;
+                        with_cursor = drawn;
+                        &with_cursor
+                    }
+                    None => message,
+                };
+                let mut bubble = Bubble::new(drawn, align, line_width, total_codeblock_counter)
+                    .with_usage(self.usage_label(message))
+                    .with_horizontal_offset(self.horizontal_offset)
+                    .with_compact(self.compact);
+                let bubble_lines = bubble.as_lines(&self.theme);
                 let bubble_line_len = bubble_lines.len();
 
                 let codeblocks_count = message.codeblocks().len();
@@ -73,6 +181,8 @@ impl<'a> BubbleList<'a> {
                     BubbleCacheEntry {
                         codeblocks_count,
                         text_len: message.text.len(),
+                        overflow: bubble.overflow(),
+                        cursor,
                         lines: bubble_lines,
                     },
                 );
@@ -82,10 +192,67 @@ impl<'a> BubbleList<'a> {
             .sum();
     }
 
+    /// Scrolls diagrams too wide for their bubble sideways, up to the end of
+    /// the widest one. Returns whether anything moved, in which case bubbles
+    /// are rebuilt on the next `set_messages`.
+    pub fn scroll_horizontally(&mut self, forward: bool) -> bool {
+        let max_offset = self
+            .cache
+            .values()
+            .map(|e| return e.overflow)
+            .max()
+            .unwrap_or(0);
+
+        let offset = if forward {
+            self.horizontal_offset
+                .saturating_add(HORIZONTAL_SCROLL_STEP)
+        } else {
+            self.horizontal_offset
+                .saturating_sub(HORIZONTAL_SCROLL_STEP)
+        }
+        .min(max_offset);
+        if offset == self.horizontal_offset {
+            return false;
+        }
+
+        self.horizontal_offset = offset;
+        self.cache.clear();
+        return true;
+    }
+
+    /// Drops all rendered bubbles, forcing them to be rebuilt on the next
+    /// `set_messages`. Required when a message changes in a way that doesn't
+    /// affect its text, such as a code block language override.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Drops the rendered bubble of the message at `index`, such as when its
+    /// chosen recovery action changes.
+    pub fn invalidate(&mut self, index: usize) {
+        self.cache.remove(&index);
+    }
+
+    /// Whether the message at `index` has a bubble rendered for
+    /// `line_width`, so its text isn't needed to render it again.
+    pub fn is_rendered(&self, index: usize, line_width: usize) -> bool {
+        return self.line_width == line_width && self.cache.contains_key(&index);
+    }
+
     pub fn len(&self) -> usize {
         return self.lines_len;
     }
 
+    /// Line the message at `index` starts on, as of the last `set_messages`.
+    pub fn line_offset(&self, index: usize) -> usize {
+        return self
+            .cache
+            .iter()
+            .filter(|(idx, _)| return **idx < index)
+            .map(|(_, entry)| return entry.lines.len())
+            .sum();
+    }
+
     pub fn render(&self, rect: Rect, buf: &mut Buffer, scroll_index: u16) {
         let mut cache_keys: Vec<usize> = self.cache.keys().cloned().collect();
         cache_keys.sort();
@@ -106,6 +273,12 @@ impl<'a> BubbleList<'a> {
                 }
 
                 buf.set_line(0, line_idx - scroll_index, line, rect.width);
+                if self.fading == Some(cache_key) {
+                    buf.set_style(
+                        Rect::new(0, line_idx - scroll_index, rect.width, 1),
+                        Style::default().add_modifier(Modifier::DIM),
+                    );
+                }
                 line_idx += 1;
             }
 
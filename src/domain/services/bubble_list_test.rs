@@ -2,8 +2,12 @@ use anyhow::Result;
 use test_utils::codeblock_fixture;
 
 use super::BubbleList;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
 use crate::domain::models::Author;
+use crate::domain::models::LocaleFormat;
 use crate::domain::models::Message;
+use crate::domain::models::ModelPrices;
 use crate::domain::services::Themes;
 
 #[test]
@@ -44,3 +48,68 @@ fn it_returns_correct_length() -> Result<()> {
     assert_eq!(bubble_list.len(), 50);
     return Ok(());
 }
+
+#[test]
+fn it_labels_token_usage() -> Result<()> {
+    Config::set(ConfigKey::Model, "model-1");
+    let theme = Themes::get("base16-seti", "")?;
+    let mut bubble_list = BubbleList::new(theme);
+    assert_eq!(
+        bubble_list.usage_label(&Message::new(Author::User, "abcdefgh")),
+        None
+    );
+
+    bubble_list.set_usage(Some(ModelPrices::parse("model-1=1000000")));
+    bubble_list.locale = LocaleFormat::default();
+    assert!(bubble_list.shows_usage());
+
+    assert_eq!(
+        bubble_list.usage_label(&Message::new(Author::User, "abcdefgh")),
+        Some(" ~2 tokens, $2.0000 ".to_string())
+    );
+    assert_eq!(
+        bubble_list.usage_label(&Message::new(Author::Oatmeal, "Hi there!")),
+        None
+    );
+
+    let mut attached = Message::new(Author::Oatmeal, "Attached the man page for ls.");
+    attached.set_tokens(1200);
+    bubble_list.set_usage(Some(ModelPrices::default()));
+    bubble_list.locale = LocaleFormat::default();
+    assert_eq!(
+        bubble_list.usage_label(&attached),
+        Some(" ~1,200 tokens ".to_string())
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn it_draws_the_streaming_cursor_after_the_last_message() -> Result<()> {
+    let theme = Themes::get("base16-seti", "")?;
+    let messages = vec![
+        Message::new(Author::User, "Hi there!"),
+        Message::new(Author::Model, "Hello"),
+    ];
+
+    let mut bubble_list = BubbleList::new(theme);
+    assert!(bubble_list.set_cursor(Some("▌")));
+    assert!(!bubble_list.set_cursor(Some("▌")));
+    bubble_list.set_messages(&messages, 50);
+
+    let rendered = |bubble_list: &BubbleList, idx: usize| {
+        return bubble_list.cache[&idx]
+            .lines
+            .iter()
+            .flat_map(|e| return e.spans.iter().map(|e| return e.content.to_string()))
+            .collect::<String>();
+    };
+    assert!(rendered(&bubble_list, 1).contains("Hello▌"));
+    assert!(!rendered(&bubble_list, 0).contains('▌'));
+
+    bubble_list.set_cursor(None);
+    bubble_list.set_messages(&messages, 50);
+    assert!(!rendered(&bubble_list, 1).contains('▌'));
+
+    return Ok(());
+}
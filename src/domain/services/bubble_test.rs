@@ -8,6 +8,8 @@ use crate::configuration::Config;
 use crate::configuration::ConfigKey;
 use crate::domain::models::Author;
 use crate::domain::models::Message;
+use crate::domain::models::MessageType;
+use crate::domain::models::RecoveryAction;
 use crate::domain::services::Themes;
 
 fn create_lines(
@@ -15,13 +17,20 @@ fn create_lines(
     alignment: BubbleAlignment,
     codeblock_count: usize,
     text: &str,
+) -> Result<String> {
+    return render_lines(&Message::new(author, text), alignment, codeblock_count);
+}
+
+fn render_lines(
+    message: &Message,
+    alignment: BubbleAlignment,
+    codeblock_count: usize,
 ) -> Result<String> {
     Config::set(ConfigKey::Username, "testuser");
     Config::set(ConfigKey::Model, "model-1");
 
-    let message = Message::new(author, text);
     let theme = Themes::get("base16-seti", "")?;
-    let lines = Bubble::new(&message, alignment, 50, codeblock_count).as_lines(&theme);
+    let lines = Bubble::new(message, alignment, 50, codeblock_count).as_lines(&theme);
     let lines_str = lines
         .iter()
         .map(|line| {
@@ -130,3 +139,150 @@ fn it_creates_author_user_text() -> Result<()> {
 
     return Ok(());
 }
+
+#[test]
+fn it_creates_recovery_actions() -> Result<()> {
+    let mut message = Message::new_with_type(Author::Oatmeal, MessageType::Error, "Oh no!");
+    message.set_recovery(vec![
+        RecoveryAction::Retry,
+        RecoveryAction::SwitchBackend,
+        RecoveryAction::OpenConfig,
+    ]);
+    message.select_next_recovery();
+
+    let lines_str = render_lines(&message, BubbleAlignment::Left, 0)?;
+    insta::assert_snapshot!(lines_str, @r###"
+    ╭Oatmeal──────────────────────────────────────╮
+    │ Oh no!                                      │
+    │                                             │
+    │ [ Retry ] [ Switch backend ]                │
+    │ [ Open config ]                             │
+    ╰─────────────────────────────────────────────╯
+    "###);
+
+    return Ok(());
+}
+
+#[test]
+fn it_shows_placeholders_for_images() -> Result<()> {
+    let mut message = Message::new(Author::Oatmeal, "What's this?");
+    message.set_images(vec!["[Image: cat.png, image/png, 12 KB]".to_string()]);
+
+    let lines_str = render_lines(&message, BubbleAlignment::Left, 0)?;
+    insta::assert_snapshot!(lines_str, @r###"
+    ╭Oatmeal─────────────────────────────╮        
+    │ What's this?                       │        
+    │                                    │        
+    │ [Image: cat.png, image/png, 12 KB] │        
+    ╰────────────────────────────────────╯        
+    "###);
+
+    return Ok(());
+}
+
+#[test]
+fn it_shows_token_usage_in_the_bottom_border() -> Result<()> {
+    Config::set(ConfigKey::Username, "testuser");
+    let message = Message::new(Author::User, "Hi");
+
+    let theme = Themes::get("base16-seti", "")?;
+    let lines = Bubble::new(&message, BubbleAlignment::Left, 50, 0)
+        .with_usage(Some(" ~1 tokens, $0.0001 ".to_string()))
+        .as_lines(&theme);
+    let lines_str = lines
+        .iter()
+        .map(|line| {
+            return line
+                .spans
+                .iter()
+                .map(|span| return span.content.to_string())
+                .collect::<String>();
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    insta::assert_snapshot!(lines_str, @r###"
+    ╭testuser──────────────╮                      
+    │ Hi                   │                      
+    ╰ ~1 tokens, $0.0001 ──╯                      
+    "###);
+
+    return Ok(());
+}
+
+#[test]
+fn it_preserves_diagrams_scrolling_them_sideways() -> Result<()> {
+    let text = r#"
++----------+      +----------+      +----------+
+| Client   | ---> | Server   | ---> | Database |
++----------+      +----------+      +----------+
+"#
+    .trim();
+    let message = Message::new(Author::Model, text);
+
+    let theme = Themes::get("base16-seti", "")?;
+    Config::set(ConfigKey::Model, "model-1");
+    let mut bubble = Bubble::new(&message, BubbleAlignment::Left, 50, 0).with_horizontal_offset(8);
+    let lines_str = bubble
+        .as_lines(&theme)
+        .iter()
+        .map(|line| {
+            return line
+                .spans
+                .iter()
+                .map(|span| return span.content.to_string())
+                .collect::<String>();
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    assert_eq!(bubble.overflow(), 5);
+
+    insta::assert_snapshot!(lines_str, @r###"
+    ╭model-1──────────────────────────────────────╮
+    │ ‹-----+      +----------+      +----------+ │
+    │ ‹nt   | ---> | Server   | ---> | Database | │
+    │ ‹-----+      +----------+      +----------+ │
+    ╰─────────────────────────────────────────────╯
+    "###);
+
+    return Ok(());
+}
+
+#[test]
+fn it_creates_compact_bubbles() -> Result<()> {
+    Config::set(ConfigKey::Username, "testuser");
+    Config::set(ConfigKey::Model, "model-1");
+    let theme = Themes::get("base16-seti", "")?;
+    let render = |message: &Message, alignment: BubbleAlignment| {
+        return Bubble::new(message, alignment, 50, 0)
+            .with_usage(Some(" ~2 tokens ".to_string()))
+            .with_compact(true)
+            .as_lines(&theme)
+            .iter()
+            .map(|line| {
+                return line
+                    .spans
+                    .iter()
+                    .map(|span| return span.content.to_string())
+                    .collect::<String>();
+            })
+            .collect::<Vec<String>>()
+            .join("\n");
+    };
+
+    let answer = Message::new(Author::Model, "Hello there, how can I help?\n\nAsk away.");
+    insta::assert_snapshot!(render(&answer, BubbleAlignment::Left), @r###"
+    model-1 ~2 tokens                               
+    │ Hello there, how can I help?                  
+    │                                               
+    │ Ask away.                                     
+    "###);
+
+    let prompt = Message::new(Author::User, "Hi");
+    let prompt_lines = render(&prompt, BubbleAlignment::Right);
+    insta_snapshot(|| {
+        insta::assert_toml_snapshot!(prompt_lines);
+    });
+
+    return Ok(());
+}
@@ -1,6 +1,10 @@
+use std::env;
+
 use anyhow::anyhow;
+use anyhow::bail;
 use anyhow::Result;
 use once_cell::sync::OnceCell;
+use tokio::process::Command;
 use tokio::sync::mpsc;
 
 static SENDER: OnceCell<mpsc::UnboundedSender<String>> = OnceCell::new();
@@ -40,4 +44,36 @@ impl ClipboardService {
 
         return Err(anyhow!("Clipboard service is not initialized."));
     }
+
+    /// Reads an image copied to the clipboard, as PNG. The clipboard is read
+    /// with `pngpaste` on macOS, and `wl-paste` or `xclip` on Linux.
+    pub async fn image() -> Result<Vec<u8>> {
+        let args = if cfg!(target_os = "macos") {
+            vec!["pngpaste", "-"]
+        } else if cfg!(windows) {
+            bail!("Attaching an image from the clipboard isn't supported on Windows, save it to a file for `/image FILE` instead.");
+        } else if env::var("WAYLAND_DISPLAY").is_ok() {
+            vec!["wl-paste", "--type", "image/png"]
+        } else {
+            vec![
+                "xclip",
+                "-selection",
+                "clipboard",
+                "-target",
+                "image/png",
+                "-out",
+            ]
+        };
+
+        let output = Command::new(args[0])
+            .args(&args[1..])
+            .output()
+            .await
+            .map_err(|err| return anyhow!("Failed to run `{}`, is it installed? {err}", args[0]))?;
+        if !output.status.success() || output.stdout.is_empty() {
+            bail!("There's no image in the clipboard.");
+        }
+
+        return Ok(output.stdout);
+    }
 }
@@ -1,8 +1,10 @@
 use anyhow::anyhow;
 use anyhow::Result;
 
+use crate::domain::models::CodeBlock;
 use crate::domain::models::Message;
 use crate::domain::models::SlashCommand;
+use crate::domain::services::Syntaxes;
 
 #[cfg(test)]
 #[path = "code_blocks_test.rs"]
@@ -10,7 +12,7 @@ mod tests;
 
 #[derive(Default)]
 pub struct CodeBlocks {
-    codeblocks: Vec<String>,
+    codeblocks: Vec<CodeBlock>,
 }
 
 impl CodeBlocks {
@@ -23,9 +25,36 @@ impl CodeBlocks {
             .collect();
     }
 
+    /// The last code block in `language`, which `/append` defaults to when
+    /// the editor has a file in that language open.
+    pub fn last_in_language(&self, language: &str) -> Option<&CodeBlock> {
+        return self
+            .codeblocks
+            .iter()
+            .rev()
+            .find(|e| return Syntaxes::same_language(&e.language, language));
+    }
+
     pub fn blocks_from_slash_commands(&self, command: &SlashCommand) -> Result<String> {
+        let res = self
+            .selected_from_slash_commands(command)?
+            .iter()
+            .map(|e| return e.code.to_string())
+            .collect::<Vec<String>>()
+            .join("\n\n");
+
+        return Ok(res);
+    }
+
+    /// Returns the code blocks selected by a command's `CODE_BLOCK_NUMBER`
+    /// arguments, or `--all`.
+    pub fn selected_from_slash_commands(&self, command: &SlashCommand) -> Result<Vec<CodeBlock>> {
         if self.codeblocks.is_empty() {
-            return Ok("".to_string());
+            return Ok(vec![]);
+        }
+
+        if command.has_flag("all") {
+            return Ok(self.codeblocks.clone());
         }
 
         let args = command
@@ -36,7 +65,7 @@ impl CodeBlocks {
             .collect::<Vec<String>>();
 
         if args.is_empty() {
-            return Ok(self.codeblocks.last().unwrap().to_string());
+            return Ok(vec![self.codeblocks.last().unwrap().clone()]);
         }
 
         let mut indexes = vec![];
@@ -66,14 +95,13 @@ impl CodeBlocks {
 
         let res = indexes
             .iter()
-            .map(|idx| return self.codeblocks[*idx].to_string())
-            .collect::<Vec<String>>()
-            .join("\n\n");
+            .map(|idx| return self.codeblocks[*idx].clone())
+            .collect::<Vec<CodeBlock>>();
 
         return Ok(res);
     }
 
-    fn validate_index(&self, entry: &str) -> Result<usize> {
+    pub fn validate_index(&self, entry: &str) -> Result<usize> {
         let res = entry.parse::<usize>()?;
         if res == 0 {
             return Err(anyhow!(format!("Code block index 0 is not valid")));
@@ -51,6 +51,24 @@ fn it_provides_last_codeblock() {
     "###);
 }
 
+#[test]
+fn it_provides_last_codeblock_in_language() {
+    let mut codeblocks = CodeBlocks::default();
+    codeblocks.replace_from_messages(&[Message::new(Author::Model, codeblock_fixture())]);
+
+    assert!(codeblocks
+        .last_in_language("rs")
+        .unwrap()
+        .code
+        .starts_with("fn print_numbers()"));
+    assert_eq!(
+        codeblocks.last_in_language("JavaScript").unwrap().language,
+        "javascript"
+    );
+    assert_eq!(codeblocks.last_in_language("go"), None);
+    assert_eq!(codeblocks.last_in_language(""), None);
+}
+
 #[test]
 fn it_provides_first_second_codeblock() {
     let res = from_slash_command("/a 1,2").unwrap();
@@ -104,3 +122,11 @@ fn it_throws_an_error_on_invalid_index() {
     let res = from_slash_command("/a 1010101").unwrap_err().to_string();
     insta::assert_snapshot!(res, @"Code block index 1010101 is not valid");
 }
+
+#[test]
+fn it_provides_all_codeblocks() {
+    let res = from_slash_command("/a --all").unwrap();
+    assert!(res.contains("abc123"));
+    assert!(res.starts_with("fn print_numbers()"));
+    assert!(res.ends_with("print(i)"));
+}
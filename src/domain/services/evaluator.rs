@@ -0,0 +1,104 @@
+#[cfg(test)]
+#[path = "evaluator_test.rs"]
+mod tests;
+
+use std::path;
+use std::time::Instant;
+
+use anyhow::Result;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::BackendBox;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::EvalPrompt;
+use crate::domain::models::EvalResult;
+use crate::domain::models::Event;
+
+/// Runs prompts against models without the chat UI, for `oatmeal eval`.
+pub struct Evaluator {
+    backend: BackendBox,
+}
+
+impl Evaluator {
+    pub fn new(backend: BackendBox) -> Evaluator {
+        return Evaluator { backend };
+    }
+
+    /// Sends each prompt on its own, without context from the others, and
+    /// times the response. Failures are recorded on the result rather than
+    /// stopping the run.
+    pub async fn run(&self, model: &str, prompts: &[EvalPrompt]) -> Vec<EvalResult> {
+        Config::set(ConfigKey::Model, model);
+
+        let mut results = vec![];
+        for prompt in prompts {
+            results.push(self.run_prompt(model, prompt).await);
+        }
+
+        return results;
+    }
+
+    async fn run_prompt(&self, model: &str, prompt: &EvalPrompt) -> EvalResult {
+        let mut result = EvalResult {
+            id: prompt.id.to_string(),
+            model: model.to_string(),
+            prompt: prompt.prompt.to_string(),
+            ..EvalResult::default()
+        };
+
+        let start = Instant::now();
+        let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+        let request = async move {
+            let backend_prompt = BackendPrompt::new(prompt.prompt.to_string(), "".to_string());
+            return self.backend.get_completion(backend_prompt, &tx).await;
+        };
+        let collect = async {
+            let mut response = "".to_string();
+            let mut tokens = 0;
+            let mut first_token_ms = None;
+            while let Some(event) = rx.recv().await {
+                if let Event::BackendPromptResponse(msg) = event {
+                    if msg.text.is_empty() {
+                        continue;
+                    }
+                    if first_token_ms.is_none() {
+                        first_token_ms = Some(start.elapsed().as_millis() as u64);
+                    }
+                    response += &msg.text;
+                    tokens += 1;
+                }
+            }
+            return (response, tokens, first_token_ms);
+        };
+
+        let (res, (response, tokens, first_token_ms)) = tokio::join!(request, collect);
+        result.latency_ms = start.elapsed().as_millis() as u64;
+        result.response = response;
+        result.tokens = tokens;
+        result.first_token_ms = first_token_ms;
+        if let Err(err) = res {
+            result.error = Some(err.to_string());
+        }
+
+        return result;
+    }
+
+    /// Writes results as one JSON object per line.
+    pub async fn write_results(path: &path::Path, results: &[EvalResult]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = fs::File::create(path).await?;
+        for result in results {
+            let line = format!("{}\n", serde_json::to_string(result)?);
+            file.write_all(line.as_bytes()).await?;
+        }
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,112 @@
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use super::Evaluator;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::EvalPrompt;
+use crate::domain::models::EvalResult;
+use crate::domain::models::Event;
+
+struct TestBackend {}
+
+#[async_trait]
+impl Backend for TestBackend {
+    fn name(&self) -> BackendName {
+        return BackendName::Ollama;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        return Ok(());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        return Ok(vec!["model-1".to_string()]);
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        if prompt.text == "fail" {
+            bail!("The model is not available");
+        }
+
+        for word in ["Hello", " there"] {
+            tx.send(Event::BackendPromptResponse(BackendResponse {
+                author: Author::Model,
+                text: word.to_string(),
+                done: false,
+                context: None,
+            }))?;
+        }
+        tx.send(Event::BackendPromptResponse(BackendResponse {
+            author: Author::Model,
+            text: "".to_string(),
+            done: true,
+            context: Some("[]".to_string()),
+        }))?;
+        return Ok(());
+    }
+}
+
+fn prompt(id: &str, text: &str) -> EvalPrompt {
+    return EvalPrompt {
+        id: id.to_string(),
+        prompt: text.to_string(),
+    };
+}
+
+#[tokio::test]
+async fn it_runs_prompts() -> Result<()> {
+    let evaluator = Evaluator::new(Box::new(TestBackend {}));
+    let results = evaluator
+        .run(
+            "model-1",
+            &[prompt("greet", "Hi"), prompt("broken", "fail")],
+        )
+        .await;
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].id, "greet");
+    assert_eq!(results[0].model, "model-1");
+    assert_eq!(results[0].response, "Hello there");
+    assert_eq!(results[0].tokens, 2);
+    assert!(results[0].first_token_ms.is_some());
+    assert_eq!(results[0].error, None);
+
+    assert_eq!(results[1].response, "");
+    assert_eq!(
+        results[1].error,
+        Some("The model is not available".to_string())
+    );
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_writes_results() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("nested/results.jsonl");
+    let results = vec![EvalResult {
+        id: "greet".to_string(),
+        model: "model-1".to_string(),
+        response: "Hello".to_string(),
+        ..EvalResult::default()
+    }];
+
+    Evaluator::write_results(&path, &results).await?;
+    let text = tokio::fs::read_to_string(&path).await?;
+    assert_eq!(serde_json::from_str::<EvalResult>(text.trim())?, results[0]);
+
+    return Ok(());
+}
@@ -1,3 +1,7 @@
+#[cfg(test)]
+#[path = "events_test.rs"]
+mod tests;
+
 use anyhow::Result;
 use crossterm::event::Event as CrosstermEvent;
 use crossterm::event::EventStream;
@@ -8,11 +12,50 @@ use tokio::time;
 use tui_textarea::Input;
 use tui_textarea::Key;
 
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::BackendResponse;
 use crate::domain::models::Event;
+use crate::domain::models::ANIMATION_FRAME;
+
+/// Time between ticks while nothing is animating, redrawing the screen.
+const IDLE_TICK: time::Duration = time::Duration::from_millis(500);
+
+/// How long answers stream in before being drawn with `low-bandwidth`.
+const LOW_BANDWIDTH_BATCH_DELAY: time::Duration = time::Duration::from_millis(250);
+
+/// Joins chunks of an answer that queued up while the UI was drawing, so fast
+/// backends redraw once per batch rather than once per token. Returns the
+/// first event that couldn't be joined, to be handled next.
+fn batch_responses(
+    mut res: BackendResponse,
+    events: &mut mpsc::UnboundedReceiver<Event>,
+) -> (BackendResponse, Option<Event>) {
+    while !res.done {
+        match events.try_recv() {
+            Ok(Event::BackendPromptResponse(next)) if next.author == res.author => {
+                res.text += &next.text;
+                res.done = next.done;
+                res.context = next.context;
+            }
+            Ok(event) => return (res, Some(event)),
+            Err(_) => break,
+        }
+    }
+
+    return (res, None);
+}
 
 pub struct EventsService {
     crossterm_events: EventStream,
     events: mpsc::UnboundedReceiver<Event>,
+    /// Received while batching responses, returned by the next call.
+    pending: Option<Event>,
+    /// Waits for more of an answer before drawing it, and skips the ticks
+    /// that redraw the screen while idle.
+    low_bandwidth: bool,
+    /// Ticks for the next animation frame rather than idling.
+    animating: bool,
 }
 
 impl EventsService {
@@ -20,9 +63,28 @@ impl EventsService {
         return EventsService {
             crossterm_events: EventStream::new(),
             events,
+            pending: None,
+            low_bandwidth: Config::get(ConfigKey::LowBandwidth) == "true",
+            animating: false,
         };
     }
 
+    /// Ticks every frame while something animates, so one ticker draws
+    /// every animation.
+    pub fn set_animating(&mut self, animating: bool) {
+        self.animating = animating;
+    }
+
+    fn tick(&self) -> Option<time::Duration> {
+        if self.animating {
+            return Some(ANIMATION_FRAME);
+        }
+        if self.low_bandwidth {
+            return None;
+        }
+        return Some(IDLE_TICK);
+    }
+
     fn handle_crossterm(&self, event: CrosstermEvent) -> Option<Event> {
         match event {
             CrosstermEvent::Paste(text) => {
@@ -61,6 +123,38 @@ impl EventsService {
                     } => {
                         return Some(Event::UIScrollUp());
                     }
+                    Input {
+                        key: Key::Left,
+                        alt: true,
+                        ctrl: false,
+                        ..
+                    } => {
+                        return Some(Event::UIScrollLeft());
+                    }
+                    Input {
+                        key: Key::Right,
+                        alt: true,
+                        ctrl: false,
+                        ..
+                    } => {
+                        return Some(Event::UIScrollRight());
+                    }
+                    Input {
+                        key: Key::Char('='),
+                        alt: true,
+                        ctrl: false,
+                        ..
+                    } => {
+                        return Some(Event::UIOutlineResize(true));
+                    }
+                    Input {
+                        key: Key::Char('-'),
+                        alt: true,
+                        ctrl: false,
+                        ..
+                    } => {
+                        return Some(Event::UIOutlineResize(false));
+                    }
                     Input {
                         key: Key::PageDown, ..
                     } => {
@@ -106,11 +200,38 @@ impl EventsService {
                     } => {
                         return Some(Event::KeyboardCTRLR());
                     }
+                    Input {
+                        key: Key::Char('t'),
+                        ctrl: true,
+                        ..
+                    } => {
+                        return Some(Event::UIOutlineToggle());
+                    }
+                    Input {
+                        key: Key::Char('n'),
+                        ctrl: true,
+                        ..
+                    } => {
+                        return Some(Event::UIOutlineSelect(true));
+                    }
+                    Input {
+                        key: Key::Char('p'),
+                        ctrl: true,
+                        ..
+                    } => {
+                        return Some(Event::UIOutlineSelect(false));
+                    }
                     Input {
                         key: Key::Enter, ..
                     } => {
                         return Some(Event::KeyboardEnter());
                     }
+                    Input { key: Key::Tab, .. } => {
+                        return Some(Event::KeyboardTab());
+                    }
+                    Input { key: Key::Esc, .. } => {
+                        return Some(Event::KeyboardEsc());
+                    }
                     input => {
                         return Some(Event::KeyboardCharInput(input));
                     }
@@ -121,6 +242,11 @@ impl EventsService {
     }
 
     pub async fn next(&mut self) -> Result<Event> {
+        if let Some(event) = self.pending.take() {
+            return Ok(event);
+        }
+
+        let tick = self.tick();
         loop {
             let evt = tokio::select! {
                 event = self.events.recv() => event,
@@ -129,9 +255,17 @@ impl EventsService {
                     Some(Err(_)) => None,
                     None => None
                 },
-                _ = time::sleep(time::Duration::from_millis(500)) => Some(Event::UITick())
+                _ = time::sleep(tick.unwrap_or_default()), if tick.is_some() => Some(Event::UITick())
             };
 
+            if let Some(Event::BackendPromptResponse(res)) = evt {
+                if self.low_bandwidth && !res.done {
+                    time::sleep(LOW_BANDWIDTH_BATCH_DELAY).await;
+                }
+                let (res, pending) = batch_responses(res, &mut self.events);
+                self.pending = pending;
+                return Ok(Event::BackendPromptResponse(res));
+            }
             if let Some(event) = evt {
                 return Ok(event);
             }
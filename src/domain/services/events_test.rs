@@ -0,0 +1,52 @@
+use anyhow::bail;
+use anyhow::Result;
+use tokio::sync::mpsc;
+
+use super::batch_responses;
+use crate::domain::models::Author;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Event;
+
+fn chunk(text: &str, done: bool) -> BackendResponse {
+    return BackendResponse {
+        author: Author::Model,
+        text: text.to_string(),
+        done,
+        context: done.then(|| return "ctx".to_string()),
+    };
+}
+
+#[test]
+fn it_batches_queued_chunks() -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    tx.send(Event::BackendPromptResponse(chunk("lo ", false)))?;
+    tx.send(Event::BackendPromptResponse(chunk("world", false)))?;
+    tx.send(Event::BackendPromptResponse(chunk("", true)))?;
+    tx.send(Event::BackendOutputTruncated())?;
+
+    let (res, pending) = batch_responses(chunk("Hel", false), &mut rx);
+    assert_eq!(res.text, "Hello world");
+    assert!(res.done);
+    assert_eq!(res.context, Some("ctx".to_string()));
+    assert!(pending.is_none());
+    assert!(matches!(rx.try_recv(), Ok(Event::BackendOutputTruncated())));
+
+    return Ok(());
+}
+
+#[test]
+fn it_stops_batching_at_other_events() -> Result<()> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    tx.send(Event::BackendStatus("Loading".to_string()))?;
+    tx.send(Event::BackendPromptResponse(chunk("world", false)))?;
+
+    let (res, pending) = batch_responses(chunk("Hello ", false), &mut rx);
+    assert_eq!(res.text, "Hello ");
+    assert!(!res.done);
+    match pending {
+        Some(Event::BackendStatus(status)) => assert_eq!(status, "Loading"),
+        _ => bail!("Expected the status to be pending"),
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,48 @@
+#[cfg(test)]
+#[path = "experiments_test.rs"]
+mod tests;
+
+use std::path;
+
+use anyhow::Result;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::domain::models::Experiment;
+
+/// Append-only log of `/ab` comparisons and their winners, stored as one
+/// JSON entry per line for reviewing prompts later.
+pub struct ExperimentLog {
+    pub path: path::PathBuf,
+}
+
+impl Default for ExperimentLog {
+    fn default() -> ExperimentLog {
+        let path = dirs::cache_dir().unwrap().join("oatmeal/experiments.jsonl");
+
+        return ExperimentLog::new(path);
+    }
+}
+
+impl ExperimentLog {
+    pub fn new(path: path::PathBuf) -> ExperimentLog {
+        return ExperimentLog { path };
+    }
+
+    pub async fn record(&self, experiment: &Experiment) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+
+        let line = format!("{}\n", serde_json::to_string(experiment)?);
+        file.write_all(line.as_bytes()).await?;
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::Result;
+
+use super::ExperimentLog;
+use crate::domain::models::Experiment;
+use crate::domain::models::Variant;
+
+#[tokio::test]
+async fn it_appends_experiments() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let log = ExperimentLog::new(dir.path().join("nested/experiments.jsonl"));
+
+    let mut experiment = Experiment::new(
+        "llama2",
+        vec![
+            Variant {
+                prompt: "Be brief".to_string(),
+                answer: "Short".to_string(),
+                ..Variant::default()
+            },
+            Variant {
+                prompt: "Be detailed".to_string(),
+                answer: "Long".to_string(),
+                ..Variant::default()
+            },
+        ],
+    );
+    experiment.winner = Some(2);
+    log.record(&experiment).await?;
+    log.record(&experiment).await?;
+
+    let text = tokio::fs::read_to_string(&log.path).await?;
+    let entries = text
+        .lines()
+        .map(|e| return serde_json::from_str::<Experiment>(e))
+        .collect::<Result<Vec<Experiment>, serde_json::Error>>()?;
+    assert_eq!(entries, vec![experiment.clone(), experiment]);
+
+    return Ok(());
+}
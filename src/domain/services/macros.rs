@@ -0,0 +1,81 @@
+#[cfg(test)]
+#[path = "macros_test.rs"]
+mod tests;
+
+use std::path;
+
+use anyhow::Result;
+use tokio::fs;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Macro;
+
+/// Table in the config file macros are kept under, as `name = ["step", ...]`.
+const MACROS_TABLE: &str = "macros";
+
+/// Macros saved in the config file, so they can be played in any session or
+/// written by hand.
+pub struct MacroStore {
+    pub path: path::PathBuf,
+}
+
+impl Default for MacroStore {
+    fn default() -> MacroStore {
+        return MacroStore::new(path::PathBuf::from(Config::get(ConfigKey::ConfigFile)));
+    }
+}
+
+impl MacroStore {
+    pub fn new(path: path::PathBuf) -> MacroStore {
+        return MacroStore { path };
+    }
+
+    async fn document(&self) -> Result<toml_edit::Document> {
+        if !self.path.exists() {
+            return Ok(toml_edit::Document::new());
+        }
+
+        let toml_str = fs::read_to_string(&self.path).await?;
+        return Ok(toml_str.parse::<toml_edit::Document>()?);
+    }
+
+    pub async fn get(&self, name: &str) -> Result<Option<Macro>> {
+        let doc = self.document().await?;
+        let steps = match doc
+            .get(MACROS_TABLE)
+            .and_then(|e| return e.get(name))
+            .and_then(|e| return e.as_array())
+        {
+            Some(steps) => steps,
+            None => return Ok(None),
+        };
+
+        return Ok(Some(Macro {
+            name: name.to_string(),
+            steps: steps
+                .iter()
+                .filter_map(|e| return e.as_str())
+                .map(|e| return e.to_string())
+                .collect(),
+        }));
+    }
+
+    /// Adds the macro to the config file, replacing one with the same name
+    /// while keeping the rest of the file's formatting and comments.
+    pub async fn save(&self, recorded: &Macro) -> Result<()> {
+        let mut doc = self.document().await?;
+        if !doc.contains_table(MACROS_TABLE) {
+            doc[MACROS_TABLE] = toml_edit::table();
+        }
+        doc[MACROS_TABLE][&recorded.name] =
+            toml_edit::value(recorded.steps.iter().collect::<toml_edit::Array>());
+
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::write(&self.path, doc.to_string()).await?;
+
+        return Ok(());
+    }
+}
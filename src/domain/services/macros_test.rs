@@ -0,0 +1,44 @@
+use anyhow::Result;
+
+use super::MacroStore;
+use crate::domain::models::Macro;
+
+#[tokio::test]
+async fn it_saves_macros_to_the_config_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("config.toml");
+    tokio::fs::write(&path, "# My backend.\nbackend = \"ollama\"\n").await?;
+
+    let store = MacroStore::new(path.to_path_buf());
+    let mut recorded = Macro::new("review");
+    recorded.steps = vec!["/gh {args}".to_string(), "Summarize it.".to_string()];
+    store.save(&recorded).await?;
+
+    recorded.steps.pop();
+    store.save(&recorded).await?;
+
+    let text = tokio::fs::read_to_string(&path).await?;
+    assert_eq!(
+        text,
+        "# My backend.\nbackend = \"ollama\"\n\n[macros]\nreview = [\"/gh {args}\"]\n"
+    );
+    assert_eq!(store.get("review").await?, Some(recorded));
+    assert_eq!(store.get("missing").await?, None);
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_creates_the_config_file() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let store = MacroStore::new(dir.path().join("nested/config.toml"));
+    let recorded = Macro {
+        name: "hi".to_string(),
+        steps: vec!["Say hi".to_string()],
+    };
+    store.save(&recorded).await?;
+
+    assert_eq!(store.get("hi").await?, Some(recorded));
+
+    return Ok(());
+}
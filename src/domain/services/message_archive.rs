@@ -0,0 +1,299 @@
+#[cfg(test)]
+#[path = "message_archive_test.rs"]
+mod tests;
+
+use std::borrow::Cow;
+use std::fs;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path;
+
+use anyhow::Result;
+use serde::ser::SerializeSeq;
+use serde::Serialize;
+use serde::Serializer;
+use serde_json::Value;
+
+use super::Sessions;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::ArchivedText;
+use crate::domain::models::Message;
+
+/// Latest messages are never evicted, as answers still streaming in or being
+/// continued are changed in place.
+const RECENT_MESSAGES: usize = 10;
+
+/// Holds the text of old messages on disk once a session's history outgrows
+/// `history-memory-limit`, while their rendered bubbles stay in memory. Reads
+/// and writes are blocking, as they happen while rendering.
+pub struct MessageArchive {
+    path: path::PathBuf,
+    len: u64,
+    /// Bytes of message text kept in memory, where zero keeps everything.
+    memory_limit: usize,
+}
+
+impl Default for MessageArchive {
+    fn default() -> MessageArchive {
+        let path = dirs::cache_dir()
+            .unwrap()
+            .join(format!("oatmeal/archive/{}", Sessions::create_id()));
+        let megabytes = Config::get(ConfigKey::HistoryMemoryLimit)
+            .parse::<usize>()
+            .unwrap_or_else(|_| {
+                return Config::default(ConfigKey::HistoryMemoryLimit)
+                    .parse::<usize>()
+                    .unwrap();
+            });
+
+        return MessageArchive::new(path, megabytes * 1_000_000);
+    }
+}
+
+impl Drop for MessageArchive {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+impl MessageArchive {
+    pub fn new(path: path::PathBuf, memory_limit: usize) -> MessageArchive {
+        return MessageArchive {
+            path,
+            len: 0,
+            memory_limit,
+        };
+    }
+
+    pub fn store(&mut self, text: &str) -> Result<ArchivedText> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        file.write_all(text.as_bytes())?;
+
+        let archived = ArchivedText {
+            offset: self.len,
+            len: text.len(),
+        };
+        self.len += text.len() as u64;
+
+        return Ok(archived);
+    }
+
+    pub fn load(&self, archived: &ArchivedText) -> Result<String> {
+        let mut file = fs::File::open(&self.path)?;
+        file.seek(SeekFrom::Start(archived.offset))?;
+
+        let mut bytes = vec![0; archived.len];
+        file.read_exact(&mut bytes)?;
+
+        return Ok(String::from_utf8(bytes)?);
+    }
+
+    /// Restores the text of an evicted message in place.
+    pub fn restore(&self, message: &mut Message) {
+        if !message.is_evicted() {
+            return;
+        }
+
+        let text = match self.load(&message.archived().unwrap()) {
+            Ok(text) => text,
+            Err(err) => {
+                tracing::error!(error = ?err, "Failed to restore an archived message");
+                format!("This message could not be restored from the archive: {err}")
+            }
+        };
+        message.restore(text);
+    }
+
+    /// Returns the messages with their text, reading evicted messages back
+    /// from the archive. Borrows them as they are when none were evicted.
+    pub fn restore_all<'m>(&self, messages: &'m [Message]) -> Cow<'m, [Message]> {
+        if !messages.iter().any(|e| return e.is_evicted()) {
+            return Cow::Borrowed(messages);
+        }
+
+        return Cow::Owned(
+            messages
+                .iter()
+                .map(|message| {
+                    let mut message = message.clone();
+                    self.restore(&mut message);
+                    return message;
+                })
+                .collect(),
+        );
+    }
+
+    /// Serializes the messages with their text, reading evicted messages back
+    /// from the archive one at a time as they're written out.
+    pub fn restored<'a>(&'a self, messages: &'a [Message]) -> RestoredMessages<'a> {
+        return RestoredMessages {
+            archive: self,
+            messages,
+        };
+    }
+
+    /// Evicts the oldest messages until the text kept in memory fits within
+    /// the limit, and drops the oldest turns from the backend context once it
+    /// alone outgrows it.
+    pub fn enforce_limit(&mut self, messages: &mut [Message], backend_context: &mut String) {
+        if self.memory_limit == 0 {
+            return;
+        }
+
+        trim_context(backend_context, self.memory_limit);
+
+        let mut in_memory = messages.iter().map(|e| return e.text.len()).sum::<usize>();
+        let end = messages.len().saturating_sub(RECENT_MESSAGES);
+        for message in messages[..end].iter_mut() {
+            if in_memory <= self.memory_limit {
+                break;
+            }
+            if message.text.is_empty() {
+                continue;
+            }
+
+            let archived = match message.archived() {
+                Some(archived) => archived,
+                None => {
+                    match self.store(&message.text) {
+                        Ok(archived) => archived,
+                        Err(err) => {
+                            tracing::error!(error = ?err, "Failed to archive a message");
+                            return;
+                        }
+                    }
+                }
+            };
+
+            in_memory -= message.text.len();
+            message.evict(archived);
+        }
+    }
+}
+
+/// Messages as saved with the session, from [`MessageArchive::restored`].
+pub struct RestoredMessages<'a> {
+    archive: &'a MessageArchive,
+    messages: &'a [Message],
+}
+
+impl Serialize for RestoredMessages<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.messages.len()))?;
+        for message in self.messages {
+            if !message.is_evicted() {
+                seq.serialize_element(message)?;
+                continue;
+            }
+
+            let mut message = message.clone();
+            self.archive.restore(&mut message);
+            seq.serialize_element(&message)?;
+        }
+
+        return seq.end();
+    }
+}
+
+/// Keeps the context starting on a prompt, as backends expect. Contexts in a
+/// shape it doesn't know are left as they are.
+fn trim_context(context: &mut String, limit: usize) {
+    if context.len() <= limit {
+        return;
+    }
+
+    let mut value = match serde_json::from_str::<Value>(context) {
+        Ok(value) => value,
+        Err(_) => return,
+    };
+    let overhead = context.len();
+    let trimmed = match &mut value {
+        Value::Array(entries) => trim_entries(entries, limit),
+        Value::Object(object) => {
+            match object.get_mut("messages") {
+                Some(Value::Array(entries)) => trim_entries(entries, limit),
+                _ => {
+                    match object.get_mut("prompt") {
+                        Some(Value::String(transcript)) => {
+                            let overhead = overhead - transcript.len();
+                            trim_transcript(transcript, limit.saturating_sub(overhead))
+                        }
+                        _ => false,
+                    }
+                }
+            }
+        }
+        _ => false,
+    };
+    if !trimmed {
+        return;
+    }
+
+    match serde_json::to_string(&value) {
+        Ok(value) => *context = value,
+        Err(err) => tracing::error!(error = ?err, "Failed to trim the backend context"),
+    }
+}
+
+/// Drops the oldest messages, or tokens, until the rest fit within the limit,
+/// along with any answer left ahead of the first prompt kept.
+fn trim_entries(entries: &mut Vec<Value>, limit: usize) -> bool {
+    let sizes = entries
+        .iter()
+        .map(|e| return e.to_string().len() + 1)
+        .collect::<Vec<usize>>();
+    let mut len = sizes.iter().sum::<usize>();
+    let mut drop = 0;
+    while drop < entries.len() && len > limit {
+        len -= sizes[drop];
+        drop += 1;
+    }
+    while drop < entries.len() && !is_prompt(&entries[drop]) {
+        drop += 1;
+    }
+    if drop == 0 {
+        return false;
+    }
+
+    entries.drain(..drop);
+    return true;
+}
+
+fn is_prompt(entry: &Value) -> bool {
+    return match entry.get("role") {
+        Some(Value::String(role)) => role.eq_ignore_ascii_case("user"),
+        Some(_) => false,
+        None => true,
+    };
+}
+
+/// Cuts a plain transcript at the first prompt that leaves it within the
+/// limit.
+fn trim_transcript(transcript: &mut String, limit: usize) -> bool {
+    let start = transcript.len().saturating_sub(limit);
+    if start == 0 {
+        return false;
+    }
+
+    let cut = transcript
+        .match_indices("User: ")
+        .map(|(idx, _)| return idx)
+        .find(|idx| return *idx >= start && transcript[..*idx].ends_with('\n'));
+    return match cut {
+        Some(cut) => {
+            transcript.replace_range(..cut, "");
+            true
+        }
+        None => false,
+    };
+}
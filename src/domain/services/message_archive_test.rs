@@ -0,0 +1,168 @@
+use std::borrow::Cow;
+
+use anyhow::Result;
+
+use super::MessageArchive;
+use crate::domain::models::Author;
+use crate::domain::models::Message;
+
+fn archive(memory_limit: usize) -> MessageArchive {
+    let path = tempfile::tempdir().unwrap().into_path().join("archive");
+    return MessageArchive::new(path, memory_limit);
+}
+
+fn messages(count: usize) -> Vec<Message> {
+    return (0..count)
+        .map(|e| return Message::new(Author::User, &format!("Message {e:02}")))
+        .collect();
+}
+
+#[test]
+fn it_stores_and_loads_text() -> Result<()> {
+    let mut archive = archive(0);
+    let first = archive.store("Hello")?;
+    let second = archive.store("World 🌍")?;
+
+    assert_eq!(archive.load(&second)?, "World 🌍");
+    assert_eq!(archive.load(&first)?, "Hello");
+
+    return Ok(());
+}
+
+#[test]
+fn it_evicts_the_oldest_messages_over_the_limit() {
+    let mut archive = archive(100);
+    let mut messages = messages(15);
+
+    archive.enforce_limit(&mut messages, &mut String::new());
+
+    let evicted = messages
+        .iter()
+        .map(|e| return e.is_evicted())
+        .collect::<Vec<bool>>();
+    assert_eq!(evicted, [vec![true; 5], vec![false; 10]].concat());
+    assert_eq!(messages[0].text, "");
+}
+
+#[test]
+fn it_keeps_recent_messages_in_memory() {
+    let mut archive = archive(1);
+    let mut messages = messages(10);
+
+    archive.enforce_limit(&mut messages, &mut String::new());
+
+    assert!(messages.iter().all(|e| return !e.is_evicted()));
+}
+
+#[test]
+fn it_keeps_everything_without_a_limit() {
+    let mut archive = archive(0);
+    let mut messages = messages(100);
+
+    archive.enforce_limit(&mut messages, &mut String::new());
+
+    assert!(messages.iter().all(|e| return !e.is_evicted()));
+}
+
+#[test]
+fn it_restores_evicted_messages() {
+    let mut archive = archive(100);
+    let mut messages = messages(15);
+
+    assert!(matches!(archive.restore_all(&messages), Cow::Borrowed(_)));
+    archive.enforce_limit(&mut messages, &mut String::new());
+
+    let restored = archive.restore_all(&messages);
+    assert!(matches!(restored, Cow::Owned(_)));
+    assert_eq!(restored[0].text, "Message 00");
+    assert_eq!(restored[14].text, "Message 14");
+
+    archive.restore(&mut messages[0]);
+    assert!(!messages[0].is_evicted());
+    assert_eq!(messages[0].text, "Message 00");
+}
+
+#[test]
+fn it_evicts_restored_messages_without_rewriting_them() {
+    let mut archive = archive(100);
+    let mut messages = messages(15);
+    archive.enforce_limit(&mut messages, &mut String::new());
+    let archived_len = archive.len;
+
+    archive.restore(&mut messages[0]);
+    archive.enforce_limit(&mut messages, &mut String::new());
+
+    assert!(messages[0].is_evicted());
+    assert_eq!(archive.len, archived_len);
+}
+
+#[test]
+fn it_stays_within_the_limit_as_the_chat_grows() {
+    let mut archive = archive(1000);
+    let mut messages = vec![];
+    let mut context = vec![];
+    for idx in 0..200 {
+        let (author, role) = if idx % 2 == 0 {
+            (Author::User, "user")
+        } else {
+            (Author::Model, "assistant")
+        };
+        let text = format!("Message {idx:03} {}", "text ".repeat(10));
+        messages.push(Message::new(author, &text));
+        context.push(serde_json::json!({ "role": role, "content": text }));
+
+        let mut backend_context = serde_json::to_string(&context).unwrap();
+        archive.enforce_limit(&mut messages, &mut backend_context);
+        context = serde_json::from_str(&backend_context).unwrap();
+
+        let in_memory = messages.iter().map(|e| return e.text.len()).sum::<usize>();
+        assert!(in_memory <= 1000, "{in_memory} bytes of messages in memory");
+        assert!(
+            backend_context.len() <= 1000,
+            "{} bytes of context",
+            backend_context.len()
+        );
+    }
+
+    assert_eq!(messages.len(), 200);
+    assert_eq!(context[0]["role"], "user");
+    assert_eq!(context.last().unwrap()["content"], messages[199].text);
+}
+
+#[test]
+fn it_trims_contexts_to_start_on_a_prompt() {
+    let mut chat = archive(80);
+    let mut context = r#"[{"content":"one","role":"user"},{"content":"two","role":"assistant"},{"content":"three","role":"user"},{"content":"four","role":"assistant"}]"#.to_string();
+    chat.enforce_limit(&mut messages(0), &mut context);
+    assert_eq!(
+        context,
+        r#"[{"content":"three","role":"user"},{"content":"four","role":"assistant"}]"#
+    );
+
+    let mut transcript = archive(90);
+    let mut context = r#"{"id_slot":1,"prompt":"User: one\nAssistant: two\nUser: three\nAssistant: four\nUser: five\nAssistant: six"}"#.to_string();
+    transcript.enforce_limit(&mut messages(0), &mut context);
+    assert_eq!(
+        context,
+        r#"{"id_slot":1,"prompt":"User: three\nAssistant: four\nUser: five\nAssistant: six"}"#
+    );
+
+    let mut context = "not json, but far longer than the limit of the archive".repeat(2);
+    let before = context.clone();
+    transcript.enforce_limit(&mut messages(0), &mut context);
+    assert_eq!(context, before);
+}
+
+#[test]
+fn it_serializes_evicted_messages_with_their_text() -> Result<()> {
+    let mut archive = archive(100);
+    let mut messages = messages(15);
+    let before = serde_json::to_string(&messages)?;
+
+    archive.enforce_limit(&mut messages, &mut String::new());
+
+    assert!(messages[0].is_evicted());
+    assert_eq!(serde_json::to_string(&archive.restored(&messages))?, before);
+
+    return Ok(());
+}
@@ -1,20 +1,48 @@
 pub mod actions;
 mod app_state;
+mod audit;
+mod bridge;
 mod bubble;
 mod bubble_list;
 pub mod clipboard;
 mod code_blocks;
+mod evaluator;
 pub mod events;
+mod experiments;
+mod macros;
+mod message_archive;
+mod model_cache;
+mod outline;
+mod response_cache;
+mod sandbox;
+mod scheduler;
 mod scroll;
 mod sessions;
+mod shell;
+mod startup_profile;
 mod syntaxes;
+mod tee;
 mod themes;
 
 pub use app_state::*;
+pub use audit::*;
+pub use bridge::*;
 pub use bubble::*;
 pub use bubble_list::*;
 pub use code_blocks::*;
+pub use evaluator::*;
+pub use experiments::*;
+pub use macros::*;
+pub use message_archive::*;
+pub use model_cache::*;
+pub use outline::*;
+pub use response_cache::*;
+pub use sandbox::*;
+pub use scheduler::*;
 pub use scroll::*;
 pub use sessions::*;
+pub use shell::*;
+pub use startup_profile::*;
 pub use syntaxes::*;
+pub use tee::*;
 pub use themes::*;
@@ -0,0 +1,143 @@
+#[cfg(test)]
+#[path = "model_cache_test.rs"]
+mod tests;
+
+use std::path;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use anyhow::Result;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::fs;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::url_key;
+use crate::domain::models::BackendBox;
+use crate::domain::models::BackendName;
+
+/// A backend's models as last fetched.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct CachedModels {
+    /// URL of the backend they were fetched from, as a backend at another
+    /// URL can have other models.
+    url: String,
+    /// Seconds since the Unix epoch.
+    fetched_at: u64,
+    models: Vec<String>,
+}
+
+/// Model lists stored on disk, one JSON file per backend, so starting the
+/// chat and `/modellist` don't wait on the backend until they're older than
+/// `model-cache-ttl`.
+pub struct ModelCache {
+    pub dir: path::PathBuf,
+    pub ttl: Duration,
+}
+
+impl Default for ModelCache {
+    fn default() -> ModelCache {
+        let ttl = Config::get(ConfigKey::ModelCacheTtl)
+            .parse::<u64>()
+            .unwrap_or(0);
+        return ModelCache::new(
+            dirs::cache_dir().unwrap().join("oatmeal/models"),
+            Duration::from_secs(ttl),
+        );
+    }
+}
+
+fn unix_seconds(time: SystemTime) -> u64 {
+    return time
+        .duration_since(UNIX_EPOCH)
+        .map(|e| return e.as_secs())
+        .unwrap_or(0);
+}
+
+fn backend_url(backend: &BackendName) -> String {
+    return url_key(backend).map(Config::get).unwrap_or_default();
+}
+
+impl ModelCache {
+    pub fn new(dir: path::PathBuf, ttl: Duration) -> ModelCache {
+        return ModelCache { dir, ttl };
+    }
+
+    fn path(&self, backend: &BackendName) -> path::PathBuf {
+        return self.dir.join(format!("{backend}.json"));
+    }
+
+    /// The backend's models, unless they were fetched from another URL or
+    /// longer than `ttl` before `now`.
+    async fn get_at(
+        &self,
+        backend: &BackendName,
+        url: &str,
+        now: SystemTime,
+    ) -> Result<Option<Vec<String>>> {
+        let path = self.path(backend);
+        if self.ttl.is_zero() || !path.exists() {
+            return Ok(None);
+        }
+
+        let cached = serde_json::from_str::<CachedModels>(&fs::read_to_string(path).await?)?;
+        let age = unix_seconds(now).saturating_sub(cached.fetched_at);
+        if cached.url != url || age >= self.ttl.as_secs() {
+            return Ok(None);
+        }
+
+        return Ok(Some(cached.models));
+    }
+
+    async fn save_at(
+        &self,
+        backend: &BackendName,
+        url: &str,
+        models: &[String],
+        now: SystemTime,
+    ) -> Result<()> {
+        if self.ttl.is_zero() {
+            return Ok(());
+        }
+
+        let cached = CachedModels {
+            url: url.to_string(),
+            fetched_at: unix_seconds(now),
+            models: models.to_vec(),
+        };
+        fs::create_dir_all(&self.dir).await?;
+        fs::write(self.path(backend), serde_json::to_string(&cached)?).await?;
+
+        return Ok(());
+    }
+
+    /// The backend's models from the cache, fetching them when they aren't
+    /// cached yet or have expired.
+    pub async fn list_models(&self, backend: &BackendBox) -> Result<Vec<String>> {
+        let name = backend.name();
+        let url = backend_url(&name);
+        match self.get_at(&name, &url, SystemTime::now()).await {
+            Ok(Some(models)) => return Ok(models),
+            Ok(None) => {}
+            Err(err) => tracing::warn!(error = ?err, "Failed to read the model cache"),
+        }
+
+        return self.refresh(backend).await;
+    }
+
+    /// Fetches the backend's models, replacing the cached ones.
+    pub async fn refresh(&self, backend: &BackendBox) -> Result<Vec<String>> {
+        let name = backend.name();
+        let models = backend.list_models().await?;
+        if let Err(err) = self
+            .save_at(&name, &backend_url(&name), &models, SystemTime::now())
+            .await
+        {
+            tracing::warn!(error = ?err, "Failed to save the model cache");
+        }
+
+        return Ok(models);
+    }
+}
@@ -0,0 +1,61 @@
+use std::time::Duration;
+use std::time::SystemTime;
+
+use anyhow::Result;
+
+use super::ModelCache;
+use crate::domain::models::BackendName;
+
+fn models() -> Vec<String> {
+    return vec!["llama2".to_string(), "mistral".to_string()];
+}
+
+#[tokio::test]
+async fn it_caches_models_until_they_expire() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let cache = ModelCache::new(dir.path().to_path_buf(), Duration::from_secs(60));
+    let url = "http://localhost:11434";
+    let now = SystemTime::now();
+
+    assert_eq!(cache.get_at(&BackendName::Ollama, url, now).await?, None);
+    cache
+        .save_at(&BackendName::Ollama, url, &models(), now)
+        .await?;
+
+    assert_eq!(
+        cache
+            .get_at(&BackendName::Ollama, url, now + Duration::from_secs(59))
+            .await?,
+        Some(models())
+    );
+    assert_eq!(
+        cache
+            .get_at(&BackendName::Ollama, url, now + Duration::from_secs(60))
+            .await?,
+        None
+    );
+    assert_eq!(
+        cache
+            .get_at(&BackendName::Ollama, "http://gpu:11434", now)
+            .await?,
+        None
+    );
+    assert_eq!(cache.get_at(&BackendName::OpenAI, url, now).await?, None);
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_skips_the_cache_without_a_ttl() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let cache = ModelCache::new(dir.path().to_path_buf(), Duration::ZERO);
+    let now = SystemTime::now();
+
+    cache
+        .save_at(&BackendName::Ollama, "", &models(), now)
+        .await?;
+    assert_eq!(cache.get_at(&BackendName::Ollama, "", now).await?, None);
+    assert!(!dir.path().join("ollama.json").exists());
+
+    return Ok(());
+}
@@ -0,0 +1,239 @@
+#[cfg(test)]
+#[path = "outline_test.rs"]
+mod tests;
+
+use ratatui::prelude::Rect;
+use ratatui::style::Modifier;
+use ratatui::style::Style;
+use ratatui::widgets::Block;
+use ratatui::widgets::BorderType;
+use ratatui::widgets::Borders;
+use ratatui::widgets::List;
+use ratatui::widgets::ListItem;
+use ratatui::widgets::ListState;
+use ratatui::Frame;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Author;
+use crate::domain::models::Message;
+use crate::domain::models::SlashCommand;
+
+/// Answers need at least this many lines before their headings are listed.
+const MIN_HEADING_LINES: usize = 20;
+
+/// Share of the chat's width the outline pane takes, as a percentage, when
+/// `outline-ratio` isn't a number.
+const DEFAULT_OUTLINE_RATIO: u16 = 30;
+
+const MIN_OUTLINE_RATIO: u16 = 15;
+
+const MAX_OUTLINE_RATIO: u16 = 60;
+
+/// How much one keypress resizes the outline pane by.
+const OUTLINE_RATIO_STEP: u16 = 5;
+
+/// Narrowest the outline pane gets, including its borders, however narrow
+/// the terminal is.
+const MIN_OUTLINE_WIDTH: u16 = 20;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutlineEntry {
+    /// Index of the message within the session.
+    pub message_index: usize,
+    /// Line within the message the entry starts on.
+    pub line: usize,
+    /// 0 for prompts, and 1 or more for headings within answers.
+    pub depth: usize,
+    pub title: String,
+}
+
+/// A table of contents for the conversation, listing user prompts and the
+/// headings of long answers.
+pub struct Outline {
+    pub open: bool,
+    /// Share of the chat's width the pane takes, as a percentage.
+    pub ratio: u16,
+    entries: Vec<OutlineEntry>,
+    selected: Option<usize>,
+}
+
+impl Default for Outline {
+    fn default() -> Outline {
+        return Outline::with_ratio(DEFAULT_OUTLINE_RATIO);
+    }
+}
+
+impl Outline {
+    pub fn with_ratio(ratio: u16) -> Outline {
+        return Outline {
+            open: false,
+            ratio: ratio.clamp(MIN_OUTLINE_RATIO, MAX_OUTLINE_RATIO),
+            entries: vec![],
+            selected: None,
+        };
+    }
+
+    /// Sized by `outline-ratio`, as last resized.
+    pub fn from_config() -> Outline {
+        let ratio = Config::get(ConfigKey::OutlineRatio)
+            .parse::<u16>()
+            .unwrap_or(DEFAULT_OUTLINE_RATIO);
+        return Outline::with_ratio(ratio);
+    }
+
+    /// Width of the pane beside a chat `width` columns wide.
+    pub fn width(&self, width: u16) -> u16 {
+        return ((width as u32 * self.ratio as u32 / 100) as u16).max(MIN_OUTLINE_WIDTH);
+    }
+
+    /// Widens the pane, or narrows it when `grow` is false. Returns whether
+    /// it changed size, as it stops at its smallest and largest.
+    pub fn resize(&mut self, grow: bool) -> bool {
+        let ratio = if grow {
+            self.ratio + OUTLINE_RATIO_STEP
+        } else {
+            self.ratio.saturating_sub(OUTLINE_RATIO_STEP)
+        }
+        .clamp(MIN_OUTLINE_RATIO, MAX_OUTLINE_RATIO);
+
+        let changed = ratio != self.ratio;
+        self.ratio = ratio;
+        return changed;
+    }
+
+    pub fn entries_from_messages(messages: &[Message]) -> Vec<OutlineEntry> {
+        let mut entries = vec![];
+        for (message_index, message) in messages.iter().enumerate() {
+            if message.author == Author::User {
+                if SlashCommand::parse(&message.text).is_some() {
+                    continue;
+                }
+
+                let title = message.text.lines().find(|e| return !e.trim().is_empty());
+                if let Some(title) = title {
+                    entries.push(OutlineEntry {
+                        message_index,
+                        line: 0,
+                        depth: 0,
+                        title: title.trim().to_string(),
+                    });
+                }
+                continue;
+            }
+
+            if message.author != Author::Model || message.text.lines().count() < MIN_HEADING_LINES {
+                continue;
+            }
+
+            let mut in_codeblock = false;
+            for (line, text) in message.text.lines().enumerate() {
+                let text = text.trim();
+                if text.starts_with("```") {
+                    in_codeblock = !in_codeblock;
+                    continue;
+                }
+                if in_codeblock || !text.starts_with('#') {
+                    continue;
+                }
+
+                let depth = text.chars().take_while(|c| return *c == '#').count();
+                let title = text[depth..].trim();
+                if depth > 6 || title.is_empty() {
+                    continue;
+                }
+
+                entries.push(OutlineEntry {
+                    message_index,
+                    line,
+                    depth,
+                    title: title.to_string(),
+                });
+            }
+        }
+
+        return entries;
+    }
+
+    pub fn toggle(&mut self, messages: &[Message]) {
+        self.open = !self.open;
+        if self.open {
+            self.set_messages(messages);
+        }
+    }
+
+    /// Keeps the entries of messages evicted to the archive as they were, as
+    /// their text can't change while it's on disk.
+    pub fn set_messages(&mut self, messages: &[Message]) {
+        let mut entries = self
+            .entries
+            .drain(..)
+            .filter(|e| {
+                return messages
+                    .get(e.message_index)
+                    .map(|message| return message.is_evicted())
+                    .unwrap_or(false);
+            })
+            .chain(Outline::entries_from_messages(messages))
+            .collect::<Vec<OutlineEntry>>();
+        entries.sort_by_key(|e| return e.message_index);
+        self.entries = entries;
+        if self.entries.is_empty() {
+            self.selected = None;
+        } else if let Some(selected) = self.selected {
+            self.selected = Some(selected.min(self.entries.len() - 1));
+        }
+    }
+
+    /// Moves the selection down, or up when `forward` is false, returning
+    /// the newly selected entry.
+    pub fn select(&mut self, forward: bool) -> Option<&OutlineEntry> {
+        if self.entries.is_empty() {
+            return None;
+        }
+
+        let last = self.entries.len() - 1;
+        let selected = match (self.selected, forward) {
+            (None, true) => 0,
+            (None, false) => last,
+            (Some(idx), true) => (idx + 1).min(last),
+            (Some(idx), false) => idx.saturating_sub(1),
+        };
+
+        self.selected = Some(selected);
+        return self.entries.get(selected);
+    }
+
+    pub fn render(&self, frame: &mut Frame, rect: Rect) {
+        let width = rect.width.saturating_sub(3) as usize;
+        let items = self
+            .entries
+            .iter()
+            .map(|e| {
+                let mut title = format!("{}{}", "  ".repeat(e.depth), e.title);
+                if title.chars().count() > width {
+                    title = format!(
+                        "{}…",
+                        title
+                            .chars()
+                            .take(width.saturating_sub(1))
+                            .collect::<String>()
+                    );
+                }
+                return ListItem::new(title);
+            })
+            .collect::<Vec<ListItem>>();
+
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .title("Outline")
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded),
+            )
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+
+        let mut state = ListState::default().with_selected(self.selected);
+        frame.render_stateful_widget(list, rect, &mut state);
+    }
+}
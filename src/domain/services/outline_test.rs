@@ -0,0 +1,109 @@
+use super::Outline;
+use super::OutlineEntry;
+use crate::domain::models::ArchivedText;
+use crate::domain::models::Author;
+use crate::domain::models::Message;
+
+fn long_answer() -> String {
+    let mut lines = vec!["# Setup", "Install it."];
+    lines.extend(["More detail."; 10]);
+    lines.extend(["```sh", "# not a heading", "```", "## Usage"]);
+    lines.extend(["Run it."; 10]);
+    return lines.join("\n");
+}
+
+#[test]
+fn it_lists_prompts_and_headings() {
+    let messages = vec![
+        Message::new(Author::Oatmeal, "Hey, it looks like you're starting fresh."),
+        Message::new(Author::User, "\nHow do I install this?\nThanks"),
+        Message::new(Author::Model, &long_answer()),
+        Message::new(Author::User, "/copy"),
+        Message::new(Author::User, "And a short one?"),
+        Message::new(Author::Model, "# Short\nNot listed."),
+    ];
+
+    assert_eq!(
+        Outline::entries_from_messages(&messages),
+        vec![
+            OutlineEntry {
+                message_index: 1,
+                line: 0,
+                depth: 0,
+                title: "How do I install this?".to_string(),
+            },
+            OutlineEntry {
+                message_index: 2,
+                line: 0,
+                depth: 1,
+                title: "Setup".to_string(),
+            },
+            OutlineEntry {
+                message_index: 2,
+                line: 15,
+                depth: 2,
+                title: "Usage".to_string(),
+            },
+            OutlineEntry {
+                message_index: 4,
+                line: 0,
+                depth: 0,
+                title: "And a short one?".to_string(),
+            },
+        ]
+    );
+}
+
+#[test]
+fn it_moves_the_selection_within_bounds() {
+    let messages = vec![
+        Message::new(Author::User, "First"),
+        Message::new(Author::User, "Second"),
+    ];
+
+    let mut outline = Outline::default();
+    outline.toggle(&messages);
+    assert!(outline.open);
+
+    assert_eq!(outline.select(false).unwrap().title, "Second");
+    assert_eq!(outline.select(false).unwrap().title, "First");
+    assert_eq!(outline.select(false).unwrap().title, "First");
+    assert_eq!(outline.select(true).unwrap().title, "Second");
+    assert_eq!(outline.select(true).unwrap().title, "Second");
+
+    outline.set_messages(&[]);
+    assert!(outline.select(true).is_none());
+}
+
+#[test]
+fn it_keeps_the_entries_of_evicted_messages() {
+    let mut messages = vec![
+        Message::new(Author::User, "First"),
+        Message::new(Author::User, "Second"),
+    ];
+
+    let mut outline = Outline::default();
+    outline.toggle(&messages);
+    messages[0].evict(ArchivedText { offset: 0, len: 5 });
+    messages.push(Message::new(Author::User, "Third"));
+    outline.set_messages(&messages);
+
+    assert_eq!(outline.select(true).unwrap().title, "First");
+    assert_eq!(outline.select(true).unwrap().title, "Second");
+    assert_eq!(outline.select(true).unwrap().title, "Third");
+}
+
+#[test]
+fn it_resizes_within_bounds() {
+    let mut outline = Outline::with_ratio(50);
+    assert_eq!(outline.width(100), 50);
+    assert!(outline.resize(true));
+    assert!(outline.resize(true));
+    assert!(!outline.resize(true));
+    assert_eq!(outline.ratio, 60);
+
+    let mut outline = Outline::with_ratio(0);
+    assert_eq!(outline.ratio, 15);
+    assert!(!outline.resize(false));
+    assert_eq!(outline.width(100), 20);
+}
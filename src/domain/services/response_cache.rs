@@ -0,0 +1,102 @@
+#[cfg(test)]
+#[path = "response_cache_test.rs"]
+mod tests;
+
+use std::path;
+
+use anyhow::Result;
+use ring::digest;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::fs;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::SamplingParams;
+
+/// An answer kept by the response cache, with the backend context to carry
+/// on the conversation from it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+}
+
+/// Answers stored on disk with `response-cache`, one JSON file per request,
+/// so sending an identical request replays its answer instead of asking the
+/// backend again.
+pub struct ResponseCache {
+    pub dir: path::PathBuf,
+}
+
+impl Default for ResponseCache {
+    fn default() -> ResponseCache {
+        return ResponseCache::new(dirs::cache_dir().unwrap().join("oatmeal/responses"));
+    }
+}
+
+impl ResponseCache {
+    pub fn new(dir: path::PathBuf) -> ResponseCache {
+        return ResponseCache { dir };
+    }
+
+    pub fn is_enabled() -> bool {
+        return Config::get(ConfigKey::ResponseCache) == "true";
+    }
+
+    /// Identifies a request by everything that shapes its answer: the
+    /// backend, model, sampling parameters, and the prompt with its system
+    /// prompt, context, tools and images.
+    pub fn key(
+        backend: &BackendName,
+        model: &str,
+        params: &SamplingParams,
+        prompt: &BackendPrompt,
+    ) -> String {
+        let request = serde_json::json!({
+            "backend": backend.to_string(),
+            "model": model,
+            "params": params,
+            "text": prompt.text,
+            "system_prompt": prompt.system_prompt,
+            "backend_context": prompt.backend_context,
+            "tools": prompt.tools.iter().map(|e| return e.to_string()).collect::<Vec<String>>(),
+            "tool_outputs": prompt
+                .tool_outputs
+                .iter()
+                .map(|e| return [&e.id, &e.name, &e.output])
+                .collect::<Vec<[&String; 3]>>(),
+            "images": prompt.images.iter().map(|e| return &e.data).collect::<Vec<&String>>(),
+        });
+
+        return digest::digest(&digest::SHA256, request.to_string().as_bytes())
+            .as_ref()
+            .iter()
+            .map(|e| return format!("{e:02x}"))
+            .collect();
+    }
+
+    fn path(&self, key: &str) -> path::PathBuf {
+        return self.dir.join(format!("{key}.json"));
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<CachedResponse>> {
+        let path = self.path(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let json = fs::read_to_string(path).await?;
+        return Ok(Some(serde_json::from_str(&json)?));
+    }
+
+    pub async fn save(&self, key: &str, response: &CachedResponse) -> Result<()> {
+        fs::create_dir_all(&self.dir).await?;
+        fs::write(self.path(key), serde_json::to_string(response)?).await?;
+
+        return Ok(());
+    }
+}
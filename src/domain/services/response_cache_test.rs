@@ -0,0 +1,53 @@
+use anyhow::Result;
+
+use super::CachedResponse;
+use super::ResponseCache;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::SamplingParams;
+
+#[test]
+fn it_keys_requests_by_what_shapes_the_answer() {
+    let prompt = BackendPrompt::new("Hello".to_string(), "".to_string());
+    let params = SamplingParams::default();
+    let key = ResponseCache::key(&BackendName::Ollama, "llama2", &params, &prompt);
+    assert_eq!(key.len(), 64);
+    assert_eq!(
+        key,
+        ResponseCache::key(&BackendName::Ollama, "llama2", &params, &prompt)
+    );
+
+    assert_ne!(
+        key,
+        ResponseCache::key(&BackendName::Ollama, "mistral", &params, &prompt)
+    );
+    let warmer = SamplingParams {
+        temperature: Some(1.5),
+        ..SamplingParams::default()
+    };
+    assert_ne!(
+        key,
+        ResponseCache::key(&BackendName::Ollama, "llama2", &warmer, &prompt)
+    );
+    let followup = BackendPrompt::new("Hello".to_string(), "[1,2,3]".to_string());
+    assert_ne!(
+        key,
+        ResponseCache::key(&BackendName::Ollama, "llama2", &params, &followup)
+    );
+}
+
+#[tokio::test]
+async fn it_saves_responses() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let cache = ResponseCache::new(dir.path().join("responses"));
+    assert_eq!(cache.get("abc").await?, None);
+
+    let response = CachedResponse {
+        text: "Hi there!".to_string(),
+        context: Some("[1,2,3]".to_string()),
+    };
+    cache.save("abc", &response).await?;
+    assert_eq!(cache.get("abc").await?, Some(response));
+
+    return Ok(());
+}
@@ -0,0 +1,239 @@
+#[cfg(test)]
+#[path = "sandbox_test.rs"]
+mod tests;
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::Hash;
+use std::hash::Hasher;
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use anyhow::Result;
+use strum::EnumIter;
+use strum::EnumVariantNames;
+use strum::IntoEnumIterator;
+use uuid::Uuid;
+
+use super::Shell;
+use super::ShellOutput;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::CodeBlock;
+
+/// Directories skipped when copying the working tree, as they're large and
+/// rebuilt by tooling.
+const SKIPPED_DIRS: [&str; 4] = [".git", "node_modules", "target", ".venv"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumVariantNames, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum SandboxMode {
+    /// Runs directly in the working tree.
+    None,
+    /// Runs in a temporary copy of the working tree, so changes to its files
+    /// aren't applied. This isn't isolation, as scripts can still reach
+    /// anything else the user can, including the network.
+    Copy,
+    /// Runs in a container with the working tree mounted read-only.
+    Docker,
+    Podman,
+}
+
+impl SandboxMode {
+    pub fn parse(text: String) -> Option<SandboxMode> {
+        return SandboxMode::iter().find(|e| return e.to_string() == text);
+    }
+}
+
+pub struct SandboxOutput {
+    pub output: ShellOutput,
+    /// Files created, modified, or deleted within a copied working tree,
+    /// which aren't applied to the real one.
+    pub changes: Vec<String>,
+}
+
+/// Returns the program that runs a code block's language, and the extension
+/// its script is saved with.
+pub fn interpreter(language: &str) -> Option<(&'static str, &'static str)> {
+    return match language.to_lowercase().as_str() {
+        "sh" | "shell" => Some(("sh", "sh")),
+        "bash" | "zsh" => Some(("bash", "sh")),
+        "python" | "py" | "python3" => Some(("python3", "py")),
+        "javascript" | "js" | "node" => Some(("node", "js")),
+        "ruby" | "rb" => Some(("ruby", "rb")),
+        _ => None,
+    };
+}
+
+fn snapshot(root: &Path, dir: &Path, res: &mut HashMap<String, u64>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_symlink() {
+            continue;
+        }
+
+        let path = entry.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if path.is_dir() {
+            if !SKIPPED_DIRS.contains(&name.as_ref()) {
+                snapshot(root, &path, res)?;
+            }
+            continue;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        fs::read(&path)?.hash(&mut hasher);
+        let relative = path.strip_prefix(root)?.to_string_lossy().to_string();
+        res.insert(relative, hasher.finish());
+    }
+
+    return Ok(());
+}
+
+fn snapshot_tree(tree: &Path) -> Result<HashMap<String, u64>> {
+    let mut res = HashMap::new();
+    snapshot(tree, tree, &mut res)?;
+    return Ok(res);
+}
+
+fn copy_tree(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        if entry.file_type()?.is_symlink() {
+            continue;
+        }
+
+        let path = entry.path();
+        let name = path.file_name().unwrap_or_default();
+        if path.is_dir() {
+            if !SKIPPED_DIRS.contains(&name.to_string_lossy().as_ref()) {
+                copy_tree(&path, &to.join(name))?;
+            }
+            continue;
+        }
+        fs::copy(&path, to.join(name))?;
+    }
+
+    return Ok(());
+}
+
+/// Runs code blocks from the model. Only the container modes isolate
+/// generated scripts, while the copy mode just keeps them from changing the
+/// working tree.
+pub struct Sandbox {
+    pub mode: SandboxMode,
+    /// Container image used by the Docker and Podman modes.
+    pub image: String,
+    /// The working tree scripts are run against.
+    pub dir: PathBuf,
+}
+
+impl Default for Sandbox {
+    fn default() -> Sandbox {
+        return Sandbox {
+            mode: SandboxMode::parse(Config::get(ConfigKey::RunSandbox))
+                .unwrap_or(SandboxMode::Copy),
+            image: Config::get(ConfigKey::RunImage),
+            dir: std::env::current_dir().unwrap_or(PathBuf::from(".")),
+        };
+    }
+}
+
+impl Sandbox {
+    pub async fn run(&self, codeblock: &CodeBlock) -> Result<SandboxOutput> {
+        let (program, extension) = match interpreter(&codeblock.language) {
+            Some(res) => res,
+            None => bail!(format!(
+                "I don't know how to run `{}` code blocks. Supported languages are sh, bash, python, javascript, and ruby.",
+                codeblock.language
+            )),
+        };
+
+        let scratch = std::env::temp_dir().join(format!("oatmeal-run-{}", Uuid::new_v4()));
+        tokio::fs::create_dir_all(&scratch).await?;
+        let script = scratch.join(format!("script.{extension}"));
+        tokio::fs::write(&script, &codeblock.code).await?;
+
+        let res = self.run_script(program, &script, &scratch).await;
+        tokio::fs::remove_dir_all(&scratch).await?;
+
+        return res;
+    }
+
+    async fn run_script(
+        &self,
+        program: &str,
+        script: &Path,
+        scratch: &Path,
+    ) -> Result<SandboxOutput> {
+        let script_path = script.to_string_lossy().to_string();
+        match self.mode {
+            SandboxMode::None => {
+                let output =
+                    Shell::run_args_in(&[program.to_string(), script_path], &self.dir).await?;
+                return Ok(SandboxOutput {
+                    output,
+                    changes: vec![],
+                });
+            }
+            SandboxMode::Copy => {
+                // Large working trees are walked on a blocking thread, so they
+                // don't hold up the runtime.
+                let tree = scratch.join("tree");
+                let (dir, copy) = (self.dir.to_path_buf(), tree.to_path_buf());
+                let before = tokio::task::spawn_blocking(move || {
+                    copy_tree(&dir, &copy)?;
+                    return snapshot_tree(&copy);
+                })
+                .await??;
+
+                let output = Shell::run_args_in(&[program.to_string(), script_path], &tree).await?;
+
+                let copy = tree.to_path_buf();
+                let after =
+                    tokio::task::spawn_blocking(move || return snapshot_tree(&copy)).await??;
+                let mut changes = after
+                    .iter()
+                    .filter(|(path, hash)| return before.get(*path) != Some(hash))
+                    .map(|(path, _)| return path.to_string())
+                    .chain(
+                        before
+                            .keys()
+                            .filter(|e| return !after.contains_key(*e))
+                            .map(|e| return format!("{e} (deleted)")),
+                    )
+                    .collect::<Vec<String>>();
+                changes.sort();
+
+                return Ok(SandboxOutput { output, changes });
+            }
+            SandboxMode::Docker | SandboxMode::Podman => {
+                let file_name = script.file_name().unwrap().to_string_lossy();
+                let args = [
+                    self.mode.to_string(),
+                    "run".to_string(),
+                    "--rm".to_string(),
+                    "--network".to_string(),
+                    "none".to_string(),
+                    "-v".to_string(),
+                    format!("{}:/work:ro", self.dir.to_string_lossy()),
+                    "-v".to_string(),
+                    format!("{}:/oatmeal:ro", scratch.to_string_lossy()),
+                    "-w".to_string(),
+                    "/work".to_string(),
+                    self.image.to_string(),
+                    program.to_string(),
+                    format!("/oatmeal/{file_name}"),
+                ];
+                let output = Shell::run_args(&args).await?;
+                return Ok(SandboxOutput {
+                    output,
+                    changes: vec![],
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+
+use super::interpreter;
+use super::Sandbox;
+use super::SandboxMode;
+use crate::domain::models::CodeBlock;
+
+fn sh(code: &str) -> CodeBlock {
+    return CodeBlock {
+        language: "sh".to_string(),
+        code: code.to_string(),
+        path: None,
+    };
+}
+
+#[test]
+fn it_finds_interpreters() {
+    assert_eq!(interpreter("Python"), Some(("python3", "py")));
+    assert_eq!(interpreter("bash"), Some(("bash", "sh")));
+    assert_eq!(interpreter("rust"), None);
+}
+
+#[tokio::test]
+async fn it_runs_in_a_copy_of_the_working_tree() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("keep.txt"), "original")?;
+    std::fs::write(dir.path().join("remove.txt"), "original")?;
+
+    let sandbox = Sandbox {
+        mode: SandboxMode::Copy,
+        image: "".to_string(),
+        dir: dir.path().to_path_buf(),
+    };
+    let res = sandbox
+        .run(&sh(
+            "echo changed > keep.txt\nrm remove.txt\ntouch new.txt\necho done",
+        ))
+        .await?;
+
+    assert!(res.output.success);
+    assert_eq!(res.output.stdout.trim(), "done");
+    assert_eq!(
+        res.changes,
+        vec![
+            "keep.txt".to_string(),
+            "new.txt".to_string(),
+            "remove.txt (deleted)".to_string(),
+        ]
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("keep.txt"))?,
+        "original"
+    );
+    assert!(dir.path().join("remove.txt").exists());
+    assert!(!dir.path().join("new.txt").exists());
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_runs_in_the_working_tree_without_a_sandbox() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let sandbox = Sandbox {
+        mode: SandboxMode::None,
+        image: "".to_string(),
+        dir: dir.path().to_path_buf(),
+    };
+    let res = sandbox.run(&sh("touch new.txt")).await?;
+
+    assert!(res.output.success);
+    assert!(dir.path().join("new.txt").exists());
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_rejects_unknown_languages() {
+    let sandbox = Sandbox {
+        mode: SandboxMode::Copy,
+        image: "".to_string(),
+        dir: std::env::temp_dir(),
+    };
+    let res = sandbox
+        .run(&CodeBlock {
+            language: "rust".to_string(),
+            code: "fn main() {}".to_string(),
+            path: None,
+        })
+        .await;
+
+    assert!(res.is_err());
+}
@@ -0,0 +1,116 @@
+#[cfg(test)]
+#[path = "scheduler_test.rs"]
+mod tests;
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+fn config_number(key: ConfigKey) -> u64 {
+    return Config::get(key)
+        .parse::<u64>()
+        .unwrap_or_else(|_| return Config::default(key).parse::<u64>().unwrap());
+}
+
+/// A reserved turn to run a tool, returned by `Scheduler::schedule`.
+pub struct Ticket {
+    /// Time until the tool's cooldown has passed.
+    pub delay: Duration,
+    /// Tool calls running or waiting when this one was scheduled.
+    pub ahead: usize,
+    start: Instant,
+    permits: Arc<Semaphore>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Ticket {
+    /// Returns true when the tool can't start straight away.
+    pub fn is_queued(&self) -> bool {
+        return !self.delay.is_zero() || self.permits.available_permits() == 0;
+    }
+
+    /// Waits for the cooldown and a free slot. The tool may run until the
+    /// returned permit is dropped.
+    pub async fn wait(self) -> SchedulerPermit {
+        tokio::time::sleep_until(self.start).await;
+        let permit = self.permits.clone().acquire_owned().await.unwrap();
+
+        return SchedulerPermit {
+            _permit: permit,
+            in_flight: self.in_flight.clone(),
+        };
+    }
+}
+
+pub struct SchedulerPermit {
+    _permit: OwnedSemaphorePermit,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for SchedulerPermit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Bounds how many tools, such as `/test` or `/gh`, run at once, and spaces
+/// out repeated calls to the same tool. Keeps anything issuing commands in a
+/// loop from hammering APIs or the local machine.
+#[derive(Clone)]
+pub struct Scheduler {
+    permits: Arc<Semaphore>,
+    cooldown: Duration,
+    next_start: Arc<Mutex<HashMap<String, Instant>>>,
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Default for Scheduler {
+    fn default() -> Scheduler {
+        return Scheduler::new(
+            config_number(ConfigKey::ToolConcurrency) as usize,
+            Duration::from_millis(config_number(ConfigKey::ToolCooldown)),
+        );
+    }
+}
+
+impl Scheduler {
+    pub fn new(max_concurrent: usize, cooldown: Duration) -> Scheduler {
+        return Scheduler {
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            cooldown,
+            next_start: Arc::new(Mutex::new(HashMap::new())),
+            in_flight: Arc::new(AtomicUsize::new(0)),
+        };
+    }
+
+    /// Reserves the next turn for a tool, no sooner than the cooldown after
+    /// its previous call started.
+    pub fn schedule(&self, tool: &str) -> Ticket {
+        let now = Instant::now();
+        let mut next_start = self.next_start.lock().unwrap();
+        let start = next_start
+            .get(tool)
+            .copied()
+            .filter(|e| return *e > now)
+            .unwrap_or(now);
+        next_start.insert(tool.to_string(), start + self.cooldown);
+
+        return Ticket {
+            delay: start - now,
+            ahead: self.in_flight.fetch_add(1, Ordering::SeqCst),
+            start,
+            permits: self.permits.clone(),
+            in_flight: self.in_flight.clone(),
+        };
+    }
+}
@@ -0,0 +1,56 @@
+use std::time::Duration;
+
+use super::Scheduler;
+
+#[tokio::test(start_paused = true)]
+async fn it_runs_first_call_immediately() {
+    let scheduler = Scheduler::new(2, Duration::from_secs(1));
+    let ticket = scheduler.schedule("/gh");
+    assert!(ticket.delay.is_zero());
+    assert_eq!(ticket.ahead, 0);
+    assert!(!ticket.is_queued());
+
+    let permit = ticket.wait().await;
+    drop(permit);
+    assert_eq!(scheduler.schedule("/man").ahead, 0);
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_applies_cooldown_per_tool() {
+    let scheduler = Scheduler::new(4, Duration::from_secs(1));
+    drop(scheduler.schedule("/gh").wait().await);
+
+    let second = scheduler.schedule("/gh");
+    let third = scheduler.schedule("/gh");
+    assert_eq!(second.delay, Duration::from_secs(1));
+    assert_eq!(third.delay, Duration::from_secs(2));
+    assert!(second.is_queued());
+
+    let other = scheduler.schedule("/man");
+    assert!(other.delay.is_zero());
+
+    let start = tokio::time::Instant::now();
+    drop(third.wait().await);
+    assert_eq!(start.elapsed(), Duration::from_secs(2));
+    drop(second);
+    drop(other);
+}
+
+#[tokio::test(start_paused = true)]
+async fn it_bounds_concurrent_calls() {
+    let scheduler = Scheduler::new(1, Duration::ZERO);
+    let running = scheduler.schedule("/test").wait().await;
+
+    let ticket = scheduler.schedule("/check");
+    assert_eq!(ticket.ahead, 1);
+    assert!(ticket.is_queued());
+
+    let waiting = tokio::spawn(async move {
+        drop(ticket.wait().await);
+    });
+    tokio::time::sleep(Duration::from_secs(5)).await;
+    assert!(!waiting.is_finished());
+
+    drop(running);
+    waiting.await.unwrap();
+}
@@ -51,6 +51,13 @@ impl Scroll {
         return self.position == self.get_position_as_if_last();
     }
 
+    /// Scrolls so the line at `position` is at the top of the viewport, or
+    /// as close as the end of the list allows.
+    pub fn to(&mut self, position: usize) {
+        self.position = position.min(self.get_position_as_if_last());
+        self.scrollbar_state = self.scrollbar_state.position(self.position);
+    }
+
     pub fn last(&mut self) {
         self.position = self.get_position_as_if_last();
         self.scrollbar_state.last();
@@ -1,3 +1,8 @@
+#[cfg(test)]
+#[path = "sessions_test.rs"]
+mod tests;
+
+use std::env;
 use std::path;
 
 use anyhow::bail;
@@ -5,6 +10,7 @@ use anyhow::Result;
 use chrono::DateTime;
 use chrono::Local;
 use chrono::SecondsFormat;
+use serde::Serialize;
 use tokio::fs;
 use tokio::io::AsyncWriteExt;
 use uuid::Uuid;
@@ -12,15 +18,59 @@ use uuid::Uuid;
 use crate::configuration::Config;
 use crate::configuration::ConfigKey;
 use crate::domain::models::Author;
+use crate::domain::models::ContextDocument;
 use crate::domain::models::EditorContext;
+use crate::domain::models::FewShotExample;
 use crate::domain::models::Message;
+use crate::domain::models::PromptVariables;
+use crate::domain::models::SamplingParams;
 use crate::domain::models::Session;
+use crate::domain::models::SessionError;
 use crate::domain::models::State;
 
 pub struct Sessions {
     pub cache_dir: path::PathBuf,
 }
 
+/// Held for as long as a session is open, so another Oatmeal opening the
+/// same session knows not to save over it. The lock is released when this
+/// is dropped, or when Oatmeal exits for any reason.
+pub struct SessionLock {
+    _file: std::fs::File,
+}
+
+/// Takes an advisory lock on `file` without waiting for it, returning false
+/// when another process holds it.
+#[cfg(unix)]
+fn try_lock(file: &std::fs::File) -> Result<bool> {
+    use std::os::unix::io::AsRawFd;
+
+    // SAFETY: the descriptor belongs to `file`, which outlives the call.
+    let res = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) };
+    if res == 0 {
+        return Ok(true);
+    }
+
+    let err = std::io::Error::last_os_error();
+    if err.kind() == std::io::ErrorKind::WouldBlock {
+        return Ok(false);
+    }
+    return Err(err.into());
+}
+
+/// Sessions aren't locked on other platforms yet.
+#[cfg(not(unix))]
+fn try_lock(_file: &std::fs::File) -> Result<bool> {
+    return Ok(true);
+}
+
+fn is_session_file(path: &path::Path) -> bool {
+    return path
+        .extension()
+        .map(|e| return e == "yaml")
+        .unwrap_or(false);
+}
+
 impl Default for Sessions {
     fn default() -> Sessions {
         let cache_dir = dirs::cache_dir().unwrap().join("oatmeal/sessions");
@@ -53,6 +103,22 @@ impl Sessions {
         return self.cache_dir.join(format!("{id}.yaml"));
     }
 
+    /// Locks the session for this Oatmeal, or returns `None` when it's
+    /// already open in another.
+    pub fn lock(&self, id: &str) -> Result<Option<SessionLock>> {
+        std::fs::create_dir_all(&self.cache_dir)?;
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .write(true)
+            .open(self.cache_dir.join(format!("{id}.lock")))?;
+
+        if !try_lock(&file)? {
+            return Ok(None);
+        }
+
+        return Ok(Some(SessionLock { _file: file }));
+    }
+
     /// Returns a list of sessions, but with only the first author message and
     /// context removed to save on memory.
     pub async fn list(&self) -> Result<Vec<Session>> {
@@ -63,6 +129,9 @@ impl Sessions {
 
         let mut dir = fs::read_dir(&self.cache_dir).await?;
         while let Some(file) = dir.next_entry().await? {
+            if !is_session_file(&file.path()) {
+                continue;
+            }
             let payload = fs::read_to_string(file.path()).await?;
             let mut session: Session = serde_yaml::from_str(&payload)?;
             let author_messages = session
@@ -88,24 +157,59 @@ impl Sessions {
         return Ok(sessions);
     }
 
+    /// Returns every session in full, oldest first.
+    pub async fn load_all(&self) -> Result<Vec<Session>> {
+        let mut sessions: Vec<Session> = vec![];
+        if !self.cache_dir.exists() {
+            return Ok(sessions);
+        }
+
+        let mut dir = fs::read_dir(&self.cache_dir).await?;
+        while let Some(file) = dir.next_entry().await? {
+            if !is_session_file(&file.path()) {
+                continue;
+            }
+            let payload = fs::read_to_string(file.path()).await?;
+            sessions.push(serde_yaml::from_str(&payload)?);
+        }
+
+        sessions.sort_by_cached_key(|session| {
+            return DateTime::parse_from_rfc3339(&session.timestamp).unwrap();
+        });
+
+        return Ok(sessions);
+    }
+
     pub async fn load(&self, id: &str) -> Result<Session> {
         let file_path = self.get_file_path(id);
         if !file_path.exists() {
-            bail!(format!("No session found for id {id}"));
+            bail!(SessionError::NotFound { id: id.to_string() });
         }
 
         let payload = fs::read_to_string(file_path).await?;
-        let session: Session = serde_yaml::from_str(&payload)?;
+        let session: Session = serde_yaml::from_str(&payload).map_err(|err| {
+            return SessionError::Corrupt {
+                id: id.to_string(),
+                reason: err.to_string(),
+            };
+        })?;
 
         return Ok(session);
     }
 
-    pub async fn save(
+    #[allow(clippy::too_many_arguments)]
+    /// Takes the messages as anything that serializes to them, so evicted
+    /// messages can be read back from the archive as they're written out.
+    pub async fn save<M: Serialize + ?Sized>(
         &self,
         id: &str,
         backend_context: &str,
         editor_context: &Option<EditorContext>,
-        messages: &[Message],
+        messages: &M,
+        few_shot: &[FewShotExample],
+        context_documents: &[ContextDocument],
+        variables: &PromptVariables,
+        draft: &str,
     ) -> Result<()> {
         let mut state = State {
             // TODO drop pulling this in from config.
@@ -113,7 +217,17 @@ impl Sessions {
             backend_model: Config::get(ConfigKey::Model),
             backend_context: backend_context.to_string(),
             editor_language: "".to_string(),
-            messages: messages.to_vec(),
+            project: env::current_dir()
+                .map(|e| return e.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            messages,
+            few_shot: few_shot.to_vec(),
+            context_documents: context_documents.to_vec(),
+            variables: variables.values.clone(),
+            sampling: SamplingParams::from_config(),
+            system_prompt: Some(Config::get(ConfigKey::SystemPrompt))
+                .filter(|e| return !e.trim().is_empty()),
+            draft: draft.to_string(),
         };
 
         if let Some(context) = editor_context {
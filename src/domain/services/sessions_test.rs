@@ -0,0 +1,44 @@
+use anyhow::Result;
+
+use super::Sessions;
+use crate::domain::models::Message;
+use crate::domain::models::PromptVariables;
+
+#[test]
+fn it_locks_sessions_for_one_instance() -> Result<()> {
+    let sessions = Sessions::new(tempfile::tempdir()?.into_path());
+
+    let lock = sessions.lock("abc")?;
+    assert!(lock.is_some());
+    #[cfg(unix)]
+    assert!(sessions.lock("abc")?.is_none());
+    assert!(sessions.lock("def")?.is_some());
+
+    drop(lock);
+    assert!(sessions.lock("abc")?.is_some());
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_lists_sessions_alongside_their_locks() -> Result<()> {
+    let sessions = Sessions::new(tempfile::tempdir()?.into_path());
+    let _lock = sessions.lock("abc")?;
+    sessions
+        .save(
+            "abc",
+            "",
+            &None,
+            &Vec::<Message>::new(),
+            &[],
+            &[],
+            &PromptVariables::default(),
+            "",
+        )
+        .await?;
+
+    assert_eq!(sessions.list().await?.len(), 1);
+    assert_eq!(sessions.load_all().await?.len(), 1);
+
+    return Ok(());
+}
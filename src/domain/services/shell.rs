@@ -0,0 +1,309 @@
+#[cfg(test)]
+#[path = "shell_test.rs"]
+mod tests;
+
+use std::path::Path;
+
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use tokio::process::Command;
+
+/// Maximum number of lines kept when condensing command output for the chat.
+const MAX_REPORT_LINES: usize = 60;
+
+/// Maximum number of referenced files included as source context.
+const MAX_SOURCE_FILES: usize = 5;
+
+/// Lines of source included either side of a referenced line.
+const SOURCE_CONTEXT_LINES: usize = 5;
+
+static FILE_REFERENCE_RES: Lazy<Vec<Regex>> = Lazy::new(|| {
+    return vec![
+        // Python tracebacks.
+        Regex::new(r#"File "(?P<file>[^"]+)", line (?P<line>\d+)"#).unwrap(),
+        // Rust, Go, Node, gcc, and most other tools.
+        Regex::new(r"(?P<file>[\w./-]+\.\w+):(?P<line>\d+)").unwrap(),
+    ];
+});
+
+static DIAGNOSTIC_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(r"^(?P<file>[^\s:][^:]*):(?P<line>\d+):(?:(?P<column>\d+):)?\s*(?:(?P<level>(?:error|warning|note|help|info)(?:\[[^\]]+\])?):)?\s*(?P<message>.+)$").unwrap();
+});
+
+/// A single problem reported by a linter or compiler.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub file: String,
+    pub line: usize,
+    pub column: Option<usize>,
+    /// Severity such as `error` or `warning`, empty when the tool doesn't
+    /// provide one.
+    pub level: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Formats the diagnostic as `FILE:LINE:COLUMN: LEVEL: MESSAGE`, keeping
+    /// the file and line reference intact.
+    pub fn format(&self) -> String {
+        let mut location = format!("{}:{}", self.file, self.line);
+        if let Some(column) = self.column {
+            location = format!("{location}:{column}");
+        }
+
+        if self.level.is_empty() {
+            return format!("{location}: {}", self.message);
+        }
+        return format!("{location}: {}: {}", self.level, self.message);
+    }
+}
+
+pub struct ShellOutput {
+    /// The command that was executed.
+    pub command: String,
+    pub success: bool,
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl ShellOutput {
+    /// Combined stdout and stderr.
+    pub fn output(&self) -> String {
+        return [self.stdout.trim_end(), self.stderr.trim_end()]
+            .iter()
+            .filter(|e| return !e.is_empty())
+            .copied()
+            .collect::<Vec<&str>>()
+            .join("\n");
+    }
+}
+
+pub struct Shell {}
+
+impl Shell {
+    /// Runs a command through the platform shell in the current directory.
+    pub async fn run(command: &str) -> Result<ShellOutput> {
+        let output = if cfg!(windows) {
+            Command::new("cmd").args(["/C", command]).output().await?
+        } else {
+            Command::new("sh").args(["-c", command]).output().await?
+        };
+
+        return Ok(ShellOutput {
+            command: command.to_string(),
+            success: output.status.success(),
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+
+    /// Runs a program directly with the provided arguments, without going
+    /// through a shell.
+    pub async fn run_args(args: &[String]) -> Result<ShellOutput> {
+        return Shell::run_args_in(args, Path::new(".")).await;
+    }
+
+    /// Runs a program directly with the provided arguments within `dir`.
+    pub async fn run_args_in(args: &[String], dir: &Path) -> Result<ShellOutput> {
+        let output = Command::new(&args[0])
+            .args(&args[1..])
+            .current_dir(dir)
+            .output()
+            .await?;
+
+        return Ok(ShellOutput {
+            command: args.join(" "),
+            success: output.status.success(),
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+
+    /// Finds files within `dir` referenced as `FILE:LINE` or Python traceback
+    /// entries in `output`, returning each file once with the first line
+    /// referenced.
+    pub fn referenced_files(output: &str, dir: &Path) -> Vec<(String, usize)> {
+        let mut res: Vec<(String, usize)> = vec![];
+        let root = dir.canonicalize().unwrap_or(dir.to_path_buf());
+        for line in output.lines() {
+            for re in FILE_REFERENCE_RES.iter() {
+                for captures in re.captures_iter(line) {
+                    let file = captures["file"].to_string();
+                    let line_number = captures["line"].parse::<usize>().unwrap_or(1);
+                    if res.iter().any(|e| return e.0 == file) {
+                        continue;
+                    }
+
+                    // Skip files outside of the project, such as standard libraries.
+                    let path = dir.join(&file).canonicalize();
+                    if path.is_err() || !path.as_ref().unwrap().is_file() {
+                        continue;
+                    }
+                    if !path.unwrap().starts_with(&root) {
+                        continue;
+                    }
+
+                    res.push((file, line_number));
+                }
+            }
+        }
+
+        res.truncate(MAX_SOURCE_FILES);
+        return res;
+    }
+
+    /// Builds markdown code blocks with the source surrounding each file
+    /// referenced in `output`.
+    pub fn source_context(output: &str, dir: &Path) -> String {
+        return Shell::referenced_files(output, dir)
+            .iter()
+            .filter_map(|(file, line)| {
+                let source = std::fs::read_to_string(dir.join(file)).ok()?;
+                let lines = source.lines().collect::<Vec<&str>>();
+                let start = line.saturating_sub(SOURCE_CONTEXT_LINES + 1);
+                let end = (line + SOURCE_CONTEXT_LINES).min(lines.len());
+                if start >= end {
+                    return None;
+                }
+
+                let language = Path::new(file)
+                    .extension()
+                    .map(|e| return e.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                return Some(format!(
+                    "{file} (lines {}-{end}):\n\n```{language}\n{}\n```",
+                    start + 1,
+                    lines[start..end].join("\n")
+                ));
+            })
+            .collect::<Vec<String>>()
+            .join("\n\n");
+    }
+
+    /// Guesses the test command for the project in `dir` from the files it
+    /// contains.
+    pub fn infer_test_command(dir: &Path) -> Option<String> {
+        if dir.join("Cargo.toml").exists() {
+            return Some("cargo test".to_string());
+        }
+        if dir.join("package.json").exists() {
+            return Some("npm test".to_string());
+        }
+        if ["pyproject.toml", "pytest.ini", "setup.py", "setup.cfg"]
+            .iter()
+            .any(|e| return dir.join(e).exists())
+        {
+            return Some("pytest".to_string());
+        }
+
+        return None;
+    }
+
+    /// Guesses the lint command for the project in `dir` from the files it
+    /// contains.
+    pub fn infer_check_command(dir: &Path) -> Option<String> {
+        if dir.join("Cargo.toml").exists() {
+            return Some("cargo clippy --message-format=short".to_string());
+        }
+        if dir.join("package.json").exists() {
+            return Some("npx eslint --format unix .".to_string());
+        }
+        if ["pyproject.toml", "setup.py", "setup.cfg"]
+            .iter()
+            .any(|e| return dir.join(e).exists())
+        {
+            return Some("ruff check .".to_string());
+        }
+
+        return None;
+    }
+
+    /// Extracts diagnostics printed as `FILE:LINE[:COLUMN]: [LEVEL:] MESSAGE`,
+    /// the format used by rustc's short messages, gcc, eslint's unix
+    /// formatter, flake8, and ruff.
+    pub fn parse_diagnostics(output: &str) -> Vec<Diagnostic> {
+        return output
+            .lines()
+            .filter_map(|line| {
+                let captures = DIAGNOSTIC_RE.captures(line.trim_end())?;
+                return Some(Diagnostic {
+                    file: captures["file"].to_string(),
+                    line: captures["line"].parse().ok()?,
+                    column: captures
+                        .name("column")
+                        .and_then(|e| return e.as_str().parse().ok()),
+                    level: captures
+                        .name("level")
+                        .map(|e| return e.as_str().to_string())
+                        .unwrap_or_default(),
+                    message: captures["message"].to_string(),
+                });
+            })
+            .collect();
+    }
+
+    /// Builds a prompt asking the model why a command failed, seeded with its
+    /// error output and the source of any files it references.
+    pub fn fix_prompt(output: &ShellOutput, dir: &Path) -> String {
+        let mut error = output.stderr.trim().to_string();
+        if error.is_empty() {
+            error = output.output();
+        }
+
+        let lines = error.lines().collect::<Vec<&str>>();
+        let start = lines.len().saturating_sub(MAX_REPORT_LINES);
+        let error = lines[start..].join("\n");
+
+        let exit_code = output
+            .exit_code
+            .map(|e| return e.to_string())
+            .unwrap_or("none".to_string());
+        let mut prompt = format!(
+            "I ran `{}` and it failed with exit code {exit_code}:\n\n```\n{error}\n```",
+            output.command
+        );
+
+        let context = Shell::source_context(&error, dir);
+        if !context.is_empty() {
+            prompt = format!("{prompt}\n\nHere is the relevant source.\n\n{context}");
+        }
+
+        return format!("{prompt}\n\nWhat's causing this, and how do I fix it?");
+    }
+
+    /// Reduces test output to the lines describing failures, falling back to
+    /// the tail of the output when nothing recognisable is found.
+    pub fn condense_test_output(output: &str) -> String {
+        let patterns = [
+            "FAIL",
+            "failed",
+            "panicked",
+            "error",
+            "Error",
+            "assert",
+            "expected",
+            "left:",
+            "right:",
+            "test result",
+        ];
+
+        let lines = output.lines().collect::<Vec<&str>>();
+        let mut matched = lines
+            .iter()
+            .filter(|line| return patterns.iter().any(|e| return line.contains(e)))
+            .copied()
+            .collect::<Vec<&str>>();
+
+        if matched.is_empty() {
+            matched = lines;
+        }
+
+        let start = matched.len().saturating_sub(MAX_REPORT_LINES);
+        return matched[start..].join("\n");
+    }
+}
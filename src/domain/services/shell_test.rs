@@ -0,0 +1,137 @@
+use anyhow::Result;
+
+use super::Shell;
+use super::ShellOutput;
+
+#[tokio::test]
+async fn it_runs_command() -> Result<()> {
+    let res = Shell::run("echo hello").await?;
+    assert!(res.success);
+    assert_eq!(res.exit_code, Some(0));
+    assert_eq!(res.output(), "hello");
+
+    let res = Shell::run("exit 3").await?;
+    assert!(!res.success);
+    assert_eq!(res.exit_code, Some(3));
+
+    return Ok(());
+}
+
+#[test]
+fn it_infers_test_command() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    assert_eq!(Shell::infer_test_command(dir.path()), None);
+
+    std::fs::write(dir.path().join("pyproject.toml"), "")?;
+    assert_eq!(
+        Shell::infer_test_command(dir.path()),
+        Some("pytest".to_string())
+    );
+
+    std::fs::write(dir.path().join("Cargo.toml"), "")?;
+    assert_eq!(
+        Shell::infer_test_command(dir.path()),
+        Some("cargo test".to_string())
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn it_condenses_test_output() {
+    let output = r#"
+running 2 tests
+test it_works ... ok
+test it_fails ... FAILED
+
+failures:
+
+---- it_fails stdout ----
+thread 'it_fails' panicked at src/lib.rs:10:5:
+assertion `left == right` failed
+  left: 1
+ right: 2
+
+test result: FAILED. 1 passed; 1 failed; 0 ignored
+"#;
+
+    insta::assert_snapshot!(Shell::condense_test_output(output), @r###"
+    test it_fails ... FAILED
+    thread 'it_fails' panicked at src/lib.rs:10:5:
+    assertion `left == right` failed
+      left: 1
+     right: 2
+    test result: FAILED. 1 passed; 1 failed; 0 ignored
+    "###);
+    assert_eq!(Shell::condense_test_output("all good"), "all good");
+}
+
+#[test]
+fn it_parses_diagnostics() {
+    let output = r#"
+    Checking oatmeal v0.13.0
+src/main.rs:3:9: warning: unused variable: `x`
+src/lib.rs:10:5: error[E0425]: cannot find value `y` in this scope
+app.py:1:1: F401 `os` imported but unused
+warning: `oatmeal` (bin "oatmeal") generated 1 warning
+"#;
+
+    let diagnostics = Shell::parse_diagnostics(output);
+    let formatted = diagnostics
+        .iter()
+        .map(|e| return e.format())
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    assert_eq!(diagnostics.len(), 3);
+    assert_eq!(diagnostics[1].line, 10);
+    assert_eq!(diagnostics[1].column, Some(5));
+    insta::assert_snapshot!(formatted, @r###"
+    src/main.rs:3:9: warning: unused variable: `x`
+    src/lib.rs:10:5: error[E0425]: cannot find value `y` in this scope
+    app.py:1:1: F401 `os` imported but unused
+    "###);
+}
+
+#[test]
+fn it_builds_fix_prompt_with_source_context() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir(dir.path().join("src"))?;
+    std::fs::write(
+        dir.path().join("src/main.rs"),
+        "fn main() {\n    let x: i32 = \"a\";\n}\n",
+    )?;
+
+    let output = ShellOutput {
+        command: "cargo build".to_string(),
+        success: false,
+        exit_code: Some(101),
+        stdout: "".to_string(),
+        stderr: "error[E0308]: mismatched types\n --> src/main.rs:2:18\n --> /rustc/lib.rs:1:1"
+            .to_string(),
+    };
+
+    insta::assert_snapshot!(Shell::fix_prompt(&output, dir.path()), @r###"
+    I ran `cargo build` and it failed with exit code 101:
+
+    ```
+    error[E0308]: mismatched types
+     --> src/main.rs:2:18
+     --> /rustc/lib.rs:1:1
+    ```
+
+    Here is the relevant source.
+
+    src/main.rs (lines 1-3):
+
+    ```rs
+    fn main() {
+        let x: i32 = "a";
+    }
+    ```
+
+    What's causing this, and how do I fix it?
+    "###);
+
+    return Ok(());
+}
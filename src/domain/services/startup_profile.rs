@@ -0,0 +1,78 @@
+#[cfg(test)]
+#[path = "startup_profile_test.rs"]
+mod tests;
+
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use once_cell::sync::Lazy;
+
+static STARTUP_PROFILE: Lazy<Mutex<StartupProfile>> =
+    Lazy::new(|| return Mutex::new(StartupProfile::new(Instant::now())));
+
+/// Times each phase of starting Oatmeal, printed with `--profile-startup`.
+pub struct StartupProfile {
+    start: Instant,
+    marks: Vec<(String, Duration)>,
+}
+
+impl StartupProfile {
+    pub fn new(start: Instant) -> StartupProfile {
+        return StartupProfile {
+            start,
+            marks: vec![],
+        };
+    }
+
+    /// Starts the clock for the global profile, which otherwise starts with
+    /// the first mark.
+    pub fn begin() {
+        Lazy::force(&STARTUP_PROFILE);
+    }
+
+    /// Records that `phase` finished in the global profile.
+    pub fn mark(phase: &str) {
+        let mut profile = STARTUP_PROFILE.lock().unwrap();
+        let elapsed = profile.start.elapsed();
+        profile.record(phase, elapsed);
+    }
+
+    pub fn report() -> String {
+        return STARTUP_PROFILE.lock().unwrap().format();
+    }
+
+    /// Records `phase` as having finished `at` after the start. Only the
+    /// first mark of a phase is kept, such as for the first frame.
+    pub fn record(&mut self, phase: &str, at: Duration) {
+        if self.marks.iter().any(|(name, _)| return name == phase) {
+            return;
+        }
+        self.marks.push((phase.to_string(), at));
+    }
+
+    /// Formats a table of when each phase finished, and how long it took
+    /// since the phase before it.
+    pub fn format(&self) -> String {
+        let width = self
+            .marks
+            .iter()
+            .map(|(phase, _)| return phase.len())
+            .chain(["phase".len()])
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec![format!("{:width$}  {:>8}  {:>8}", "phase", "at", "took")];
+        let mut previous = Duration::ZERO;
+        for (phase, at) in &self.marks {
+            lines.push(format!(
+                "{phase:width$}  {:>6}ms  {:>6}ms",
+                at.as_millis(),
+                at.saturating_sub(previous).as_millis()
+            ));
+            previous = *at;
+        }
+
+        return lines.join("\n");
+    }
+}
@@ -0,0 +1,31 @@
+use std::time::Duration;
+use std::time::Instant;
+
+use super::StartupProfile;
+
+#[test]
+fn it_formats_phases() {
+    let mut profile = StartupProfile::new(Instant::now());
+    profile.record("config loaded", Duration::from_millis(4));
+    profile.record("backend checked", Duration::from_millis(30));
+    profile.record("first frame", Duration::from_millis(42));
+
+    insta::assert_snapshot!(profile.format(), @r###"
+    phase                  at      took
+    config loaded         4ms       4ms
+    backend checked      30ms      26ms
+    first frame          42ms      12ms
+    "###);
+}
+
+#[test]
+fn it_keeps_the_first_mark_of_a_phase() {
+    let mut profile = StartupProfile::new(Instant::now());
+    profile.record("first frame", Duration::from_millis(10));
+    profile.record("first frame", Duration::from_millis(20));
+
+    insta::assert_snapshot!(profile.format(), @r###"
+    phase              at      took
+    first frame      10ms      10ms
+    "###);
+}
@@ -2,6 +2,10 @@ use once_cell::sync::Lazy;
 use ratatui::style::Color;
 use syntect::parsing::SyntaxReference;
 use syntect::parsing::SyntaxSet;
+use tokio::task;
+
+use super::StartupProfile;
+use crate::domain::models::ColorMode;
 
 pub static SYNTAX_SET: Lazy<SyntaxSet> = Lazy::new(Syntaxes::load);
 
@@ -14,7 +18,16 @@ impl Syntaxes {
         return syntax_set;
     }
 
-    pub fn get(name: &str) -> &SyntaxReference {
+    /// Loads the syntaxes on a blocking thread ahead of the first code block
+    /// being highlighted, rather than delaying the frame that needs them.
+    pub fn preload() {
+        task::spawn_blocking(|| {
+            Lazy::force(&SYNTAX_SET);
+            StartupProfile::mark("syntaxes loaded");
+        });
+    }
+
+    pub fn get(name: &str) -> &'static SyntaxReference {
         if let Some(syntax) = SYNTAX_SET.find_syntax_by_extension(name) {
             return syntax;
         }
@@ -30,6 +43,20 @@ impl Syntaxes {
         return SYNTAX_SET.find_syntax_plain_text();
     }
 
+    /// Whether both names are for the same syntax, such as `rs` and `rust`.
+    pub fn same_language(first: &str, second: &str) -> bool {
+        if first.is_empty() || second.is_empty() {
+            return false;
+        }
+        if first.eq_ignore_ascii_case(second) {
+            return true;
+        }
+
+        let syntax = Syntaxes::get(&first.to_lowercase());
+        return syntax.name != SYNTAX_SET.find_syntax_plain_text().name
+            && syntax.name == Syntaxes::get(&second.to_lowercase()).name;
+    }
+
     pub fn list() -> Vec<String> {
         let mut syntaxes = SYNTAX_SET
             .syntaxes()
@@ -43,7 +70,7 @@ impl Syntaxes {
     pub fn translate_colour(syntect_color: syntect::highlighting::Color) -> Option<Color> {
         match syntect_color {
             syntect::highlighting::Color { r, g, b, a } if a > 0 => {
-                return Some(Color::Rgb(r, g, b))
+                return ColorMode::current().apply(Color::Rgb(r, g, b))
             }
             _ => return None,
         }
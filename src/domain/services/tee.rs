@@ -0,0 +1,99 @@
+#[cfg(test)]
+#[path = "tee_test.rs"]
+mod tests;
+
+use std::fs;
+use std::io::Write;
+use std::path;
+
+use anyhow::Result;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+
+/// Transcript of prompts and answers appended to a file as they stream, kept
+/// apart from the session store. Files ending in `.md` get a heading for
+/// each message, anything else plain text.
+pub struct Tee {
+    pub path: path::PathBuf,
+    file: fs::File,
+    markdown: bool,
+    /// Whether an answer is being streamed, so its heading is only written
+    /// once.
+    answering: bool,
+}
+
+impl Tee {
+    pub fn open(path: path::PathBuf) -> Result<Tee> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let markdown = path
+            .extension()
+            .map(|e| return e == "md" || e == "markdown")
+            .unwrap_or(false);
+
+        return Ok(Tee {
+            path,
+            file,
+            markdown,
+            answering: false,
+        });
+    }
+
+    /// Opens the file set by `tee`, if any.
+    pub fn from_config() -> Result<Option<Tee>> {
+        let path = Config::get(ConfigKey::Tee);
+        if path.is_empty() {
+            return Ok(None);
+        }
+
+        return Ok(Some(Tee::open(path::PathBuf::from(path))?));
+    }
+
+    fn heading(&self, name: &str) -> String {
+        if self.markdown {
+            return format!("### {name}\n\n");
+        }
+        return format!("{name}:\n");
+    }
+
+    pub fn prompt(&mut self, name: &str, text: &str) -> Result<()> {
+        self.end_answer()?;
+        let text = format!("{}{}\n\n", self.heading(name), text.trim_end());
+        self.file.write_all(text.as_bytes())?;
+
+        return Ok(());
+    }
+
+    /// Appends the next chunk of an answer, ending it when `done`.
+    pub fn answer(&mut self, name: &str, text: &str, done: bool) -> Result<()> {
+        if !self.answering {
+            self.answering = true;
+            let heading = self.heading(name);
+            self.file.write_all(heading.as_bytes())?;
+        }
+        self.file.write_all(text.as_bytes())?;
+        if done {
+            self.end_answer()?;
+        }
+
+        return Ok(());
+    }
+
+    /// Closes off an answer, including one that was cut off before it was
+    /// done.
+    fn end_answer(&mut self) -> Result<()> {
+        if self.answering {
+            self.answering = false;
+            self.file.write_all(b"\n\n")?;
+        }
+
+        return Ok(());
+    }
+}
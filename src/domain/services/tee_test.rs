@@ -0,0 +1,48 @@
+use std::fs;
+
+use anyhow::Result;
+
+use super::Tee;
+
+#[test]
+fn it_appends_plain_text_transcripts() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("nested/chat.log");
+    fs::create_dir_all(path.parent().unwrap())?;
+    fs::write(&path, "Earlier:\nHi\n\n")?;
+
+    let mut tee = Tee::open(path.clone())?;
+    tee.prompt("dustin", "Say hi to the world\n")?;
+    tee.answer("llama2", "Hello ", false)?;
+    assert_eq!(
+        fs::read_to_string(&path)?,
+        "Earlier:\nHi\n\ndustin:\nSay hi to the world\n\nllama2:\nHello "
+    );
+
+    tee.answer("llama2", "World", true)?;
+    assert_eq!(
+        fs::read_to_string(&path)?,
+        "Earlier:\nHi\n\ndustin:\nSay hi to the world\n\nllama2:\nHello World\n\n"
+    );
+
+    return Ok(());
+}
+
+#[test]
+fn it_writes_markdown_headings() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("nested/chat.md");
+
+    let mut tee = Tee::open(path.clone())?;
+    tee.prompt("dustin", "Say hi")?;
+    tee.answer("llama2", "Hello", false)?;
+    // A prompt sent before the answer finished closes it off.
+    tee.prompt("dustin", "Go on")?;
+
+    assert_eq!(
+        fs::read_to_string(&path)?,
+        "### dustin\n\nSay hi\n\n### llama2\n\nHello\n\n### dustin\n\nGo on\n\n"
+    );
+
+    return Ok(());
+}
@@ -7,6 +7,8 @@ use anyhow::bail;
 use anyhow::Result;
 use syntect::highlighting::Theme;
 use syntect::highlighting::ThemeSet;
+use tokio::task;
+use tokio::task::JoinHandle;
 
 #[derive(Default)]
 pub struct Themes {}
@@ -53,4 +55,12 @@ impl Themes {
         }
         return Themes::get_from_memory(theme_name);
     }
+
+    /// Parses the theme on a blocking thread, so startup can wait on the
+    /// backend or session at the same time.
+    pub fn load_in_background(theme_name: &str, theme_file: &str) -> JoinHandle<Result<Theme>> {
+        let theme_name = theme_name.to_string();
+        let theme_file = theme_file.to_string();
+        return task::spawn_blocking(move || return Themes::get(&theme_name, &theme_file));
+    }
 }
@@ -0,0 +1,401 @@
+#[cfg(test)]
+#[path = "azure_test.rs"]
+mod tests;
+
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::TryStreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::mpsc;
+use tokio_util::io::StreamReader;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::ConfigError;
+use crate::domain::models::Event;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::StreamInterrupted;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::SendAuthorized;
+
+/// Last API version that lists a resource's deployments. Later versions only
+/// list them through Azure's management API.
+const DEPLOYMENTS_API_VERSION: &str = "2022-12-01";
+
+fn convert_err(err: reqwest::Error) -> std::io::Error {
+    let err_msg = err.to_string();
+    return std::io::Error::new(std::io::ErrorKind::Interrupted, err_msg);
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Deployment {
+    id: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct DeploymentListResponse {
+    data: Vec<Deployment>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct MessageRequest {
+    role: String,
+    content: String,
+}
+
+/// Azure picks the model from the deployment in the URL, so unlike OpenAI
+/// there's no model in the body.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CompletionRequest {
+    messages: Vec<MessageRequest>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CompletionDeltaResponse {
+    content: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CompletionChoiceResponse {
+    #[serde(default)]
+    delta: CompletionDeltaResponse,
+    finish_reason: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CompletionResponse {
+    choices: Vec<CompletionChoiceResponse>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct EmbeddingRequest {
+    input: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+/// Azure OpenAI, where models are served from deployments a resource names
+/// itself. The model oatmeal is configured with is the deployment's name.
+pub struct Azure {
+    url: String,
+    token: String,
+    api_version: String,
+    timeout: String,
+}
+
+impl Default for Azure {
+    fn default() -> Azure {
+        return Azure {
+            url: Config::get(ConfigKey::AzureURL),
+            token: Config::get(ConfigKey::AzureToken),
+            api_version: Config::get(ConfigKey::AzureApiVersion),
+            timeout: Config::get(ConfigKey::BackendHealthCheckTimeout),
+        };
+    }
+}
+
+impl Azure {
+    fn deployment_url(&self, deployment: &str, operation: &str) -> String {
+        return format!(
+            "{url}/openai/deployments/{deployment}/{operation}?api-version={version}",
+            url = self.url.trim_end_matches('/'),
+            version = self.api_version
+        );
+    }
+}
+
+#[async_trait]
+impl Backend for Azure {
+    fn name(&self) -> BackendName {
+        return BackendName::Azure;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        if self.url.is_empty() {
+            bail!(ConfigError::Missing {
+                key: ConfigKey::AzureURL
+            });
+        }
+        if self.token.is_empty() {
+            bail!(ConfigError::Missing {
+                key: ConfigKey::AzureToken
+            });
+        }
+        let deployment = Config::get(ConfigKey::Model);
+        if deployment.is_empty() {
+            bail!(ConfigError::Missing {
+                key: ConfigKey::Model
+            });
+        }
+
+        // A completion without messages is rejected with a 400 before it
+        // reaches the model, so costs nothing, but still 404s when the
+        // deployment doesn't exist.
+        let res = http_client()?
+            .post(self.deployment_url(&deployment, "chat/completions"))
+            .header("api-key", &self.token)
+            .json(&CompletionRequest::default())
+            .timeout(Duration::from_millis(self.timeout.parse::<u64>()?))
+            .send_authorized(BackendName::Azure)
+            .await;
+
+        if res.is_err() {
+            tracing::error!(error = ?res.unwrap_err(), "Azure OpenAI is not reachable");
+            bail!(BackendError::Unreachable {
+                backend: BackendName::Azure,
+                url: self.url.to_string(),
+            });
+        }
+
+        let status = res.unwrap().status().as_u16();
+        if status > 400 {
+            tracing::error!(status = status, "Azure OpenAI health check failed");
+            bail!(BackendError::from_status(
+                BackendName::Azure,
+                status,
+                &deployment
+            ));
+        }
+
+        return Ok(());
+    }
+
+    /// Falls back to the configured deployment when the resource doesn't list
+    /// them, as keys can be scoped to a single deployment.
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let res = http_client()?
+            .get(format!(
+                "{url}/openai/deployments?api-version={DEPLOYMENTS_API_VERSION}",
+                url = self.url.trim_end_matches('/')
+            ))
+            .header("api-key", &self.token)
+            .send_authorized(BackendName::Azure)
+            .await?;
+
+        let deployment = Config::get(ConfigKey::Model);
+        if !res.status().is_success() {
+            tracing::warn!(
+                status = res.status().as_u16(),
+                "Failed to list Azure OpenAI deployments"
+            );
+            if deployment.is_empty() {
+                bail!(BackendError::from_status(
+                    BackendName::Azure,
+                    res.status().as_u16(),
+                    ""
+                ));
+            }
+            return Ok(vec![deployment]);
+        }
+
+        let mut models: Vec<String> = res
+            .json::<DeploymentListResponse>()
+            .await?
+            .data
+            .iter()
+            .map(|deployment| {
+                return deployment.id.to_string();
+            })
+            .collect();
+
+        models.sort();
+
+        return Ok(models);
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let deployment = Config::get(ConfigKey::EmbeddingModel);
+        if deployment.is_empty() {
+            bail!(ConfigError::Missing {
+                key: ConfigKey::EmbeddingModel
+            });
+        }
+
+        let res = http_client()?
+            .post(self.deployment_url(&deployment, "embeddings"))
+            .header("api-key", &self.token)
+            .json(&EmbeddingRequest {
+                input: texts.to_vec(),
+            })
+            .send_authorized(BackendName::Azure)
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to make embeddings request to Azure OpenAI"
+            );
+            bail!("Failed to make embeddings request to Azure OpenAI");
+        }
+
+        let mut data = res.json::<EmbeddingResponse>().await?.data;
+        data.sort_by_key(|e| return e.index);
+
+        return Ok(data.into_iter().map(|e| return e.embedding).collect());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        let deployment = prompt.model();
+        let mut messages: Vec<MessageRequest> = vec![];
+        if !prompt.backend_context.is_empty() {
+            messages = serde_json::from_str(&prompt.backend_context)?;
+        }
+        messages.push(MessageRequest {
+            role: "user".to_string(),
+            content: prompt.text,
+        });
+
+        // The system prompt is sent with every request, so it's left out of
+        // the context.
+        let mut req_messages = vec![];
+        if let Some(system_prompt) = &prompt.system_prompt {
+            req_messages.push(MessageRequest {
+                role: "system".to_string(),
+                content: system_prompt.to_string(),
+            });
+        }
+        req_messages.extend(messages.iter().cloned());
+
+        let sampling = SamplingParams::from_config();
+        let req = CompletionRequest {
+            messages: req_messages,
+            stream: true,
+            seed: Config::get(ConfigKey::Seed).parse::<u64>().ok(),
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            max_tokens: sampling.max_tokens,
+            stop: sampling.stop_sequences(),
+        };
+
+        let res = http_client()?
+            .post(self.deployment_url(&deployment, "chat/completions"))
+            .header("api-key", &self.token)
+            .json(&req)
+            .send_authorized(BackendName::Azure)
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to make completion request to Azure OpenAI"
+            );
+            bail!(BackendError::from_status(
+                BackendName::Azure,
+                res.status().as_u16(),
+                &deployment
+            ));
+        }
+
+        let stream = res.bytes_stream().map_err(convert_err);
+        let mut lines_reader = StreamReader::new(stream).lines();
+
+        let mut last_message = "".to_string();
+        let mut truncated = false;
+        loop {
+            let line = match lines_reader.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(_) => {
+                    bail!(StreamInterrupted { text: last_message });
+                }
+            };
+
+            let mut cleaned_line = line.trim().to_string();
+            if cleaned_line.starts_with("data:") {
+                cleaned_line = cleaned_line.split_off(5).trim().to_string();
+            }
+            if cleaned_line.is_empty() {
+                continue;
+            }
+            if cleaned_line == "[DONE]" {
+                break;
+            }
+
+            let ores: CompletionResponse = serde_json::from_str(&cleaned_line)?;
+            tracing::debug!(body = ?ores, "Completion response");
+
+            // The first event only carries the prompt's content filter
+            // results, without any choices.
+            let choice = match ores.choices.first() {
+                Some(choice) => choice,
+                None => continue,
+            };
+            if let Some(finish_reason) = &choice.finish_reason {
+                truncated = finish_reason == "length";
+                break;
+            }
+
+            let text = match &choice.delta.content {
+                Some(text) if !text.is_empty() => text.to_string(),
+                _ => continue,
+            };
+
+            last_message += &text;
+            let msg = BackendResponse {
+                author: Author::Model,
+                text,
+                done: false,
+                context: None,
+            };
+
+            tx.send(Event::BackendPromptResponse(msg))?;
+        }
+
+        messages.push(MessageRequest {
+            role: "assistant".to_string(),
+            content: last_message.to_string(),
+        });
+
+        let msg = BackendResponse {
+            author: Author::Model,
+            text: "".to_string(),
+            done: true,
+            context: Some(serde_json::to_string(&messages)?),
+        };
+        tx.send(Event::BackendPromptResponse(msg))?;
+        if truncated {
+            tx.send(Event::BackendOutputTruncated())?;
+        }
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,225 @@
+use anyhow::bail;
+use anyhow::Result;
+use mockito::Matcher;
+use test_utils::insta_snapshot;
+use tokio::sync::mpsc;
+
+use super::Azure;
+use super::CompletionChoiceResponse;
+use super::CompletionDeltaResponse;
+use super::CompletionResponse;
+use super::Deployment;
+use super::DeploymentListResponse;
+use super::MessageRequest;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Event;
+
+impl Azure {
+    fn with_url(url: String) -> Azure {
+        return Azure {
+            url,
+            token: "abc".to_string(),
+            api_version: "2024-02-01".to_string(),
+            timeout: "200".to_string(),
+        };
+    }
+}
+
+fn to_res(action: Option<Event>) -> Result<BackendResponse> {
+    let act = match action.unwrap() {
+        Event::BackendPromptResponse(res) => res,
+        _ => bail!("Wrong type from recv"),
+    };
+
+    return Ok(act);
+}
+
+fn stream_line(content: Option<&str>, finish_reason: Option<&str>) -> Result<String> {
+    let res = serde_json::to_string(&CompletionResponse {
+        choices: vec![CompletionChoiceResponse {
+            delta: CompletionDeltaResponse {
+                content: content.map(|e| return e.to_string()),
+            },
+            finish_reason: finish_reason.map(|e| return e.to_string()),
+        }],
+    })?;
+
+    return Ok(format!("data: {res}\n"));
+}
+
+fn completions_mock(server: &mut mockito::Server, status: usize) -> mockito::Mock {
+    return server
+        .mock("POST", "/openai/deployments/model-1/chat/completions")
+        .match_query(Matcher::UrlEncoded(
+            "api-version".to_string(),
+            "2024-02-01".to_string(),
+        ))
+        .match_header("api-key", "abc")
+        .with_status(status);
+}
+
+#[tokio::test]
+async fn it_successfully_health_checks() {
+    Config::set(ConfigKey::Model, "model-1");
+    let mut server = mockito::Server::new();
+    let mock = completions_mock(&mut server, 400).create();
+
+    let backend = Azure::with_url(server.url());
+    let res = backend.health_check().await;
+
+    assert!(res.is_ok());
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_fails_health_checks_for_missing_deployments() {
+    Config::set(ConfigKey::Model, "model-1");
+    let mut server = mockito::Server::new();
+    let mock = completions_mock(&mut server, 404).create();
+
+    let backend = Azure::with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::ModelMissing {
+            backend: BackendName::Azure,
+            model: "model-1".to_string(),
+        })
+    );
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_fails_health_checks_with_an_invalid_token() {
+    Config::set(ConfigKey::Model, "model-1");
+    let mut server = mockito::Server::new();
+    let mock = completions_mock(&mut server, 401).create();
+
+    let backend = Azure::with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::InvalidToken {
+            backend: BackendName::Azure
+        })
+    );
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_lists_deployments() -> Result<()> {
+    let body = serde_json::to_string(&DeploymentListResponse {
+        data: vec![
+            Deployment {
+                id: "gpt4-prod".to_string(),
+            },
+            Deployment {
+                id: "gpt35-dev".to_string(),
+            },
+        ],
+    })?;
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/openai/deployments")
+        .match_query(Matcher::UrlEncoded(
+            "api-version".to_string(),
+            "2022-12-01".to_string(),
+        ))
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let backend = Azure::with_url(server.url());
+    let res = backend.list_models().await?;
+    mock.assert();
+
+    assert_eq!(res, vec!["gpt35-dev".to_string(), "gpt4-prod".to_string()]);
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_lists_the_configured_deployment_when_listing_fails() -> Result<()> {
+    Config::set(ConfigKey::Model, "model-1");
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/openai/deployments")
+        .match_query(Matcher::Any)
+        .with_status(404)
+        .create();
+
+    let backend = Azure::with_url(server.url());
+    let res = backend.list_models().await?;
+    mock.assert();
+
+    assert_eq!(res, vec!["model-1".to_string()]);
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_gets_completions() -> Result<()> {
+    Config::set(ConfigKey::Model, "model-1");
+    let body = [
+        r#"data: {"choices":[],"prompt_filter_results":[]}"#.to_string(),
+        stream_line(Some("Hello "), None)?,
+        stream_line(Some("World"), None)?,
+        stream_line(None, Some("stop"))?,
+        "data: [DONE]\n".to_string(),
+    ]
+    .join("\n");
+    let prompt = BackendPrompt {
+        text: "Say hi to the world".to_string(),
+        backend_context: serde_json::to_string(&vec![MessageRequest {
+            role: "assistant".to_string(),
+            content: "How may I help you?".to_string(),
+        }])?,
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
+    };
+
+    let mut server = mockito::Server::new();
+    let mock = completions_mock(&mut server, 200)
+        .match_body(Matcher::PartialJson(serde_json::json!({ "stream": true })))
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Azure::with_url(server.url());
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    let first_recv = to_res(rx.recv().await)?;
+    let second_recv = to_res(rx.recv().await)?;
+    let third_recv = to_res(rx.recv().await)?;
+
+    assert_eq!(first_recv.author, Author::Model);
+    assert_eq!(first_recv.text, "Hello ".to_string());
+    assert!(!first_recv.done);
+
+    assert_eq!(second_recv.text, "World".to_string());
+    assert!(!second_recv.done);
+
+    assert!(third_recv.text.is_empty());
+    assert!(third_recv.done);
+    insta_snapshot(|| {
+        insta::assert_toml_snapshot!(third_recv.context);
+    });
+
+    return Ok(());
+}
@@ -0,0 +1,298 @@
+#[cfg(test)]
+#[path = "balanced_test.rs"]
+mod tests;
+
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use strum::EnumIter;
+use strum::EnumVariantNames;
+use strum::IntoEnumIterator;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::domain::models::Backend;
+use crate::domain::models::BackendBox;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::Event;
+use crate::domain::models::ModelUsage;
+
+/// How long an endpoint that failed is skipped for, unless every other one
+/// is down too.
+const FAILOVER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// How long latencies measured for `least-latency` are trusted before the
+/// endpoints are probed again.
+const LATENCY_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumVariantNames, strum::Display)]
+#[strum(serialize_all = "kebab-case")]
+pub enum LoadBalancing {
+    RoundRobin,
+    LeastLatency,
+}
+
+impl LoadBalancing {
+    pub fn parse(text: &str) -> Option<LoadBalancing> {
+        return LoadBalancing::iter().find(|e| return e.to_string() == text);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct EndpointState {
+    latency: Option<Duration>,
+    measured_at: Option<Instant>,
+    down_until: Option<Instant>,
+}
+
+/// One backend served from several URLs, such as Ollama on two machines.
+/// Each request goes to the endpoint picked by the strategy, failing over to
+/// the others when it can't be reached.
+pub struct Balanced {
+    strategy: LoadBalancing,
+    endpoints: Vec<(String, BackendBox)>,
+    state: Mutex<(usize, Vec<EndpointState>)>,
+}
+
+impl Balanced {
+    pub fn new(strategy: LoadBalancing, endpoints: Vec<(String, BackendBox)>) -> Balanced {
+        let state = vec![EndpointState::default(); endpoints.len()];
+        return Balanced {
+            strategy,
+            endpoints,
+            state: Mutex::new((0, state)),
+        };
+    }
+
+    /// Endpoints in the order they should be tried. Ones that recently failed
+    /// come last, as a last resort.
+    fn order(&self, now: Instant) -> Vec<usize> {
+        let mut state = self.state.lock().unwrap();
+        let (next, endpoints) = &mut *state;
+        let count = endpoints.len();
+
+        let mut order: Vec<usize> = match self.strategy {
+            LoadBalancing::RoundRobin => {
+                let start = *next % count;
+                *next = (start + 1) % count;
+                (0..count).map(|e| return (start + e) % count).collect()
+            }
+            LoadBalancing::LeastLatency => {
+                let mut order: Vec<usize> = (0..count).collect();
+                order.sort_by_key(|e| return endpoints[*e].latency.unwrap_or(Duration::MAX));
+                order
+            }
+        };
+        order.sort_by_key(|e| {
+            return endpoints[*e]
+                .down_until
+                .map(|until| return until > now)
+                .unwrap_or(false);
+        });
+
+        return order;
+    }
+
+    fn needs_probe(&self, now: Instant) -> bool {
+        if self.strategy != LoadBalancing::LeastLatency {
+            return false;
+        }
+
+        let state = self.state.lock().unwrap();
+        return state.1.iter().any(|e| {
+            return e
+                .measured_at
+                .map(|measured_at| return now.duration_since(measured_at) > LATENCY_TTL)
+                .unwrap_or(true);
+        });
+    }
+
+    fn mark_down(&self, index: usize) {
+        let mut state = self.state.lock().unwrap();
+        state.1[index].down_until = Some(Instant::now() + FAILOVER_COOLDOWN);
+    }
+
+    /// Health checks every endpoint at once, recording how long each took to
+    /// answer and which are down.
+    async fn probe(&self) -> Vec<Result<()>> {
+        let checks = self.endpoints.iter().map(|(_, backend)| {
+            return async move {
+                let start = Instant::now();
+                let res = backend.health_check().await;
+                return (res, start.elapsed());
+            };
+        });
+        let results = futures::future::join_all(checks).await;
+
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        return results
+            .into_iter()
+            .enumerate()
+            .map(|(index, (res, latency))| {
+                let endpoint = &mut state.1[index];
+                endpoint.measured_at = Some(now);
+                if res.is_ok() {
+                    endpoint.latency = Some(latency);
+                    endpoint.down_until = None;
+                } else {
+                    endpoint.latency = None;
+                    endpoint.down_until = Some(now + FAILOVER_COOLDOWN);
+                }
+                return res;
+            })
+            .collect();
+    }
+}
+
+/// Whether a request failed before the endpoint answered, so it's safe to
+/// send it to another one without repeating part of an answer.
+fn can_fail_over(err: &anyhow::Error) -> bool {
+    if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+        return err.is_connect() || err.is_timeout();
+    }
+
+    return match err.downcast_ref::<BackendError>() {
+        Some(BackendError::Unreachable { .. })
+        | Some(BackendError::ModelMissing { .. })
+        | Some(BackendError::RateLimited { .. })
+        | Some(BackendError::NoModelLoaded { .. }) => true,
+        Some(BackendError::Status { status, .. }) => *status >= 500,
+        _ => false,
+    };
+}
+
+#[async_trait]
+impl Backend for Balanced {
+    fn name(&self) -> BackendName {
+        return self.endpoints[0].1.name();
+    }
+
+    /// Every endpoint is the same backend, so any of them can answer.
+    fn supports_tools(&self) -> bool {
+        return self
+            .endpoints
+            .iter()
+            .all(|(_, e)| return e.supports_tools());
+    }
+
+    /// Passes while any endpoint is up.
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        let mut results = self.probe().await;
+        if results.iter().any(|e| return e.is_ok()) {
+            return Ok(());
+        }
+
+        return results.remove(0);
+    }
+
+    /// Lists the models served by any endpoint that's up.
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let mut models = vec![];
+        let mut first_err = None;
+        for index in self.order(Instant::now()) {
+            match self.endpoints[index].1.list_models().await {
+                Ok(res) => models.extend(res),
+                Err(err) => {
+                    tracing::warn!(url = self.endpoints[index].0, error = ?err, "Failed to list models");
+                    self.mark_down(index);
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+
+        if models.is_empty() {
+            if let Some(err) = first_err {
+                return Err(err);
+            }
+        }
+
+        models.sort();
+        models.dedup();
+        return Ok(models);
+    }
+
+    /// Warms the model on every endpoint, so whichever gets the first prompt
+    /// is ready.
+    #[allow(clippy::implicit_return)]
+    async fn preload(&self) -> Result<()> {
+        for (url, backend) in self.endpoints.iter() {
+            if let Err(err) = backend.preload().await {
+                tracing::warn!(url = url, error = ?err, "Failed to preload model");
+            }
+        }
+
+        return Ok(());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn model_usage(&self) -> Result<Option<ModelUsage>> {
+        let index = self.order(Instant::now())[0];
+        return self.endpoints[index].1.model_usage().await;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let order = self.order(Instant::now());
+        let mut res = Err(anyhow::anyhow!("No endpoints are configured"));
+        for index in order {
+            res = self.endpoints[index].1.embed(texts).await;
+            match &res {
+                Err(err) if can_fail_over(err) => self.mark_down(index),
+                _ => return res,
+            }
+        }
+
+        return res;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        return self
+            .get_cancellable_completion(prompt, tx, CancellationToken::new())
+            .await;
+    }
+
+    /// Passes the whole prompt, with its tools, images and model, to the
+    /// endpoint, so it can stop generating on its own when cancelled.
+    #[allow(clippy::implicit_return)]
+    async fn get_cancellable_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        if self.needs_probe(Instant::now()) {
+            self.probe().await;
+        }
+
+        let order = self.order(Instant::now());
+        let mut res = Err(anyhow::anyhow!("No endpoints are configured"));
+        for index in order {
+            let (url, backend) = &self.endpoints[index];
+            res = backend
+                .get_cancellable_completion(prompt.clone(), tx, cancel.clone())
+                .await;
+            match &res {
+                Err(err) if can_fail_over(err) => {
+                    tracing::warn!(url = url, error = ?err, "Endpoint failed, trying the next one");
+                    self.mark_down(index);
+                }
+                _ => return res,
+            }
+        }
+
+        return res;
+    }
+}
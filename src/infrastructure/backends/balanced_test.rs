@@ -0,0 +1,200 @@
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use super::Balanced;
+use super::LoadBalancing;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendBox;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Event;
+use crate::domain::models::ImageAttachment;
+
+struct TestBackend {
+    url: String,
+    up: bool,
+    models: Vec<String>,
+    requests: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl Backend for TestBackend {
+    fn name(&self) -> BackendName {
+        return BackendName::Ollama;
+    }
+
+    fn supports_tools(&self) -> bool {
+        return true;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        if !self.up {
+            bail!(BackendError::Unreachable {
+                backend: BackendName::Ollama,
+                url: self.url.to_string(),
+            });
+        }
+        return Ok(());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        self.health_check().await?;
+        return Ok(self.models.clone());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        self.requests.fetch_add(1, Ordering::SeqCst);
+        self.health_check().await?;
+
+        let mut text = self.url.to_string();
+        if let Some(model) = prompt.model {
+            text = format!("{text} {model} with {} images", prompt.images.len());
+        }
+        tx.send(Event::BackendPromptResponse(BackendResponse {
+            author: Author::Model,
+            text,
+            done: true,
+            context: Some("[]".to_string()),
+        }))?;
+        return Ok(());
+    }
+}
+
+fn endpoint(url: &str, up: bool, models: &[&str]) -> ((String, BackendBox), Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let backend = TestBackend {
+        url: url.to_string(),
+        up,
+        models: models.iter().map(|e| return e.to_string()).collect(),
+        requests: requests.clone(),
+    };
+
+    return ((url.to_string(), Box::new(backend)), requests);
+}
+
+async fn answered_by(backend: &Balanced) -> Result<String> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    backend
+        .get_completion(BackendPrompt::new("Hi".to_string(), "".to_string()), &tx)
+        .await?;
+
+    return match rx.recv().await {
+        Some(Event::BackendPromptResponse(res)) => Ok(res.text),
+        _ => bail!("Wrong type from recv"),
+    };
+}
+
+#[tokio::test]
+async fn it_round_robins_between_endpoints() -> Result<()> {
+    let (first, _) = endpoint("http://one", true, &[]);
+    let (second, _) = endpoint("http://two", true, &[]);
+    let backend = Balanced::new(LoadBalancing::RoundRobin, vec![first, second]);
+
+    assert_eq!(answered_by(&backend).await?, "http://one");
+    assert_eq!(answered_by(&backend).await?, "http://two");
+    assert_eq!(answered_by(&backend).await?, "http://one");
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_forwards_the_whole_prompt() -> Result<()> {
+    let (first, _) = endpoint("http://one", true, &[]);
+    let backend = Balanced::new(LoadBalancing::RoundRobin, vec![first]);
+    assert!(backend.supports_tools());
+
+    let mut prompt = BackendPrompt::new("Hi".to_string(), "".to_string());
+    prompt.model = Some("llava".to_string());
+    prompt.images = vec![ImageAttachment {
+        source: "cat.png".to_string(),
+        media_type: "image/png".to_string(),
+        data: "aGk=".to_string(),
+        size: 2,
+    }];
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    backend.get_completion(prompt, &tx).await?;
+
+    match rx.recv().await {
+        Some(Event::BackendPromptResponse(res)) => {
+            assert_eq!(res.text, "http://one llava with 1 images");
+        }
+        _ => bail!("Wrong type from recv"),
+    }
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_fails_over_and_skips_endpoints_that_are_down() -> Result<()> {
+    let (first, first_requests) = endpoint("http://one", false, &[]);
+    let (second, _) = endpoint("http://two", true, &[]);
+    let backend = Balanced::new(LoadBalancing::RoundRobin, vec![first, second]);
+
+    assert_eq!(answered_by(&backend).await?, "http://two");
+    assert_eq!(answered_by(&backend).await?, "http://two");
+    assert_eq!(answered_by(&backend).await?, "http://two");
+    assert_eq!(first_requests.load(Ordering::SeqCst), 1);
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_prefers_the_fastest_endpoint_that_is_up() -> Result<()> {
+    let (first, _) = endpoint("http://one", false, &[]);
+    let (second, _) = endpoint("http://two", true, &[]);
+    let backend = Balanced::new(LoadBalancing::LeastLatency, vec![first, second]);
+
+    assert!(backend.health_check().await.is_ok());
+    assert_eq!(backend.order(Instant::now()), vec![1, 0]);
+    assert_eq!(answered_by(&backend).await?, "http://two");
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_fails_health_checks_when_every_endpoint_is_down() {
+    let (first, _) = endpoint("http://one", false, &[]);
+    let (second, _) = endpoint("http://two", false, &[]);
+    let backend = Balanced::new(LoadBalancing::RoundRobin, vec![first, second]);
+
+    let err = backend.health_check().await.unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::Unreachable {
+            backend: BackendName::Ollama,
+            url: "http://one".to_string(),
+        })
+    );
+}
+
+#[tokio::test]
+async fn it_lists_models_from_every_endpoint_that_is_up() -> Result<()> {
+    let (first, _) = endpoint("http://one", true, &["llama3", "mistral"]);
+    let (second, _) = endpoint("http://two", true, &["codellama", "llama3"]);
+    let (third, _) = endpoint("http://three", false, &["phi3"]);
+    let backend = Balanced::new(LoadBalancing::RoundRobin, vec![first, second, third]);
+
+    assert_eq!(
+        backend.list_models().await?,
+        vec!["codellama", "llama3", "mistral"]
+    );
+
+    return Ok(());
+}
@@ -0,0 +1,639 @@
+#[cfg(test)]
+#[path = "bedrock_test.rs"]
+mod tests;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::DateTime;
+use chrono::Utc;
+use futures::stream::StreamExt;
+use reqwest::RequestBuilder;
+use ring::digest;
+use ring::hmac;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::ConfigError;
+use crate::domain::models::Event;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::StreamInterrupted;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::BackendAuth;
+
+/// Name Bedrock's control plane and runtime both sign requests under.
+const SIGNING_SERVICE: &str = "bedrock";
+
+fn hex(bytes: &[u8]) -> String {
+    return bytes.iter().map(|e| return format!("{e:02x}")).collect();
+}
+
+fn sha256(data: &[u8]) -> String {
+    return hex(digest::digest(&digest::SHA256, data).as_ref());
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, key);
+    return hmac::sign(&key, data.as_bytes()).as_ref().to_vec();
+}
+
+/// Percent encodes everything but unreserved characters, as SigV4 expects.
+fn uri_encode(text: &str, encode_slash: bool) -> String {
+    let mut res = "".to_string();
+    for byte in text.bytes() {
+        let unreserved = byte.is_ascii_alphanumeric() || b"-_.~".contains(&byte);
+        if unreserved || (byte == b'/' && !encode_slash) {
+            res.push(byte as char);
+        } else {
+            res += &format!("%{byte:02X}");
+        }
+    }
+    return res;
+}
+
+/// AWS credentials, from the config or the standard `AWS_` environment
+/// variables.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: String,
+}
+
+impl Credentials {
+    fn from_config() -> Credentials {
+        let get = |key: ConfigKey, env_name: &str| {
+            let value = Config::get(key);
+            if value.is_empty() {
+                return std::env::var(env_name).unwrap_or_default();
+            }
+            return value;
+        };
+
+        return Credentials {
+            access_key_id: get(ConfigKey::AwsAccessKeyId, "AWS_ACCESS_KEY_ID"),
+            secret_access_key: get(ConfigKey::AwsSecretAccessKey, "AWS_SECRET_ACCESS_KEY"),
+            session_token: get(ConfigKey::AwsSessionToken, "AWS_SESSION_TOKEN"),
+        };
+    }
+}
+
+impl Credentials {
+    /// Signs a request with AWS Signature Version 4, covering every header
+    /// it already has besides the ones added here.
+    pub fn sign(
+        &self,
+        req: &mut reqwest::Request,
+        region: &str,
+        service: &str,
+        now: DateTime<Utc>,
+    ) -> Result<()> {
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+
+        let url = req.url().clone();
+        let mut host = url.host_str().unwrap_or_default().to_string();
+        if let Some(port) = url.port() {
+            host = format!("{host}:{port}");
+        }
+        req.headers_mut().insert("host", host.parse()?);
+        req.headers_mut().insert("x-amz-date", amz_date.parse()?);
+        if !self.session_token.is_empty() {
+            req.headers_mut()
+                .insert("x-amz-security-token", self.session_token.parse()?);
+        }
+
+        let mut headers: Vec<(String, String)> = vec![];
+        for (name, value) in req.headers() {
+            headers.push((
+                name.as_str().to_string(),
+                value.to_str()?.trim().to_string(),
+            ));
+        }
+        headers.sort();
+
+        // Paths are encoded once more on top of how they're sent, as every
+        // AWS service but S3 expects.
+        let canonical_uri = uri_encode(url.path(), false);
+        let mut query = url
+            .query_pairs()
+            .map(|(key, value)| return (uri_encode(&key, true), uri_encode(&value, true)))
+            .collect::<Vec<(String, String)>>();
+        query.sort();
+        let canonical_query = query
+            .iter()
+            .map(|(key, value)| return format!("{key}={value}"))
+            .collect::<Vec<String>>()
+            .join("&");
+        let canonical_headers = headers
+            .iter()
+            .map(|(name, value)| return format!("{name}:{value}\n"))
+            .collect::<String>();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| return name.as_str())
+            .collect::<Vec<&str>>()
+            .join(";");
+        let payload = req
+            .body()
+            .and_then(|e| return e.as_bytes())
+            .unwrap_or_default();
+
+        let canonical_request = [
+            req.method().as_str(),
+            &canonical_uri,
+            &canonical_query,
+            &canonical_headers,
+            &signed_headers,
+            &sha256(payload),
+        ]
+        .join("\n");
+        let scope = format!("{date}/{region}/{service}/aws4_request");
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+            sha256(canonical_request.as_bytes())
+        );
+
+        let mut key = hmac_sha256(format!("AWS4{}", self.secret_access_key).as_bytes(), &date);
+        for part in [region, service, "aws4_request"] {
+            key = hmac_sha256(&key, part);
+        }
+        let signature = hex(&hmac_sha256(&key, &string_to_sign));
+
+        req.headers_mut().insert(
+            "authorization",
+            format!(
+                "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, Signature={signature}",
+                self.access_key_id
+            )
+            .parse()?,
+        );
+
+        return Ok(());
+    }
+}
+
+/// One message from an `application/vnd.amazon.eventstream` response.
+#[derive(Default, Debug, Clone, PartialEq, Eq)]
+pub struct EventStreamMessage {
+    pub headers: HashMap<String, String>,
+    pub payload: Vec<u8>,
+}
+
+/// Splits Bedrock's binary event stream in to messages as its bytes arrive.
+/// Each message is a length prefixed prelude, typed headers, and a payload,
+/// with CRCs the TLS connection already makes redundant.
+#[derive(Default)]
+pub struct EventStreamDecoder {
+    buffer: Vec<u8>,
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> Result<usize> {
+    let raw = bytes
+        .get(at..at + 2)
+        .ok_or(anyhow::anyhow!("Truncated header"))?;
+    return Ok(u16::from_be_bytes([raw[0], raw[1]]) as usize);
+}
+
+impl EventStreamDecoder {
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Returns the next complete message, or None until more bytes arrive.
+    pub fn next_message(&mut self) -> Result<Option<EventStreamMessage>> {
+        if self.buffer.len() < 12 {
+            return Ok(None);
+        }
+        let total_len = u32::from_be_bytes(self.buffer[0..4].try_into()?) as usize;
+        let headers_len = u32::from_be_bytes(self.buffer[4..8].try_into()?) as usize;
+        if total_len < headers_len + 16 {
+            bail!("Malformed event stream message");
+        }
+        if self.buffer.len() < total_len {
+            return Ok(None);
+        }
+
+        let message = self.buffer.drain(..total_len).collect::<Vec<u8>>();
+        let raw_headers = &message[12..12 + headers_len];
+        let mut headers = HashMap::new();
+        let mut idx = 0;
+        while idx < raw_headers.len() {
+            let name_len = raw_headers[idx] as usize;
+            let name = String::from_utf8_lossy(
+                raw_headers
+                    .get(idx + 1..idx + 1 + name_len)
+                    .ok_or(anyhow::anyhow!("Truncated header"))?,
+            )
+            .to_string();
+            idx += 1 + name_len;
+
+            let value_type = *raw_headers
+                .get(idx)
+                .ok_or(anyhow::anyhow!("Truncated header"))?;
+            idx += 1;
+            let value_len = match value_type {
+                0 | 1 => 0,
+                2 => 1,
+                3 => 2,
+                4 => 4,
+                5 | 8 => 8,
+                9 => 16,
+                6 | 7 => {
+                    let len = read_u16(raw_headers, idx)?;
+                    idx += 2;
+                    len
+                }
+                _ => bail!("Unknown event stream header type {value_type}"),
+            };
+            let value = raw_headers
+                .get(idx..idx + value_len)
+                .ok_or(anyhow::anyhow!("Truncated header"))?;
+            idx += value_len;
+
+            // Bedrock only sends string headers.
+            if value_type == 7 {
+                headers.insert(name, String::from_utf8_lossy(value).to_string());
+            }
+        }
+
+        return Ok(Some(EventStreamMessage {
+            headers,
+            payload: message[12 + headers_len..total_len - 4].to_vec(),
+        }));
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelSummary {
+    model_id: String,
+    #[serde(default)]
+    inference_types_supported: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ModelListResponse {
+    model_summaries: Vec<ModelSummary>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ContentBlock {
+    text: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct MessageRequest {
+    role: String,
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InferenceConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CompletionRequest {
+    /// The Converse API takes the system prompt apart from the messages,
+    /// for every model family.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    system: Vec<ContentBlock>,
+    messages: Vec<MessageRequest>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    inference_config: Option<InferenceConfig>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ContentBlockDelta {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ContentBlockDeltaEvent {
+    delta: ContentBlockDelta,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MessageStopEvent {
+    stop_reason: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ExceptionEvent {
+    #[serde(default)]
+    message: String,
+}
+
+/// Models hosted on AWS Bedrock, such as Claude and Titan, called through the
+/// Converse API so every model takes the same request.
+pub struct Bedrock {
+    url: String,
+    runtime_url: String,
+    region: String,
+    credentials: Credentials,
+    /// Only its extra headers are sent, signed along with the rest of the
+    /// request.
+    auth: BackendAuth,
+    timeout: String,
+}
+
+impl Default for Bedrock {
+    fn default() -> Bedrock {
+        let region = Config::get(ConfigKey::AwsRegion);
+        return Bedrock {
+            url: format!("https://bedrock.{region}.amazonaws.com"),
+            runtime_url: format!("https://bedrock-runtime.{region}.amazonaws.com"),
+            region,
+            credentials: Credentials::from_config(),
+            auth: BackendAuth::from_config(&BackendName::Bedrock),
+            timeout: Config::get(ConfigKey::BackendHealthCheckTimeout),
+        };
+    }
+}
+
+impl Bedrock {
+    /// Requests are authorized by signing them with the AWS credentials, so
+    /// a bearer token from `backend-token-commands` can't stand in for them.
+    fn signed_request(&self, req: RequestBuilder) -> Result<reqwest::Request> {
+        if !self.auth.token_command.is_empty() {
+            bail!("Bedrock requests are signed with your AWS credentials, so it can't use a token command from `backend-token-commands`. Remove its entry, and set `aws-session-token` for temporary credentials instead.");
+        }
+
+        let mut req = req.build()?;
+        self.auth.add_headers(&mut req)?;
+        self.credentials
+            .sign(&mut req, &self.region, SIGNING_SERVICE, Utc::now())?;
+        return Ok(req);
+    }
+
+    fn models_url(&self) -> String {
+        return format!(
+            "{url}/foundation-models?byOutputModality=TEXT",
+            url = self.url
+        );
+    }
+}
+
+#[async_trait]
+impl Backend for Bedrock {
+    fn name(&self) -> BackendName {
+        return BackendName::Bedrock;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        if self.region.is_empty() {
+            bail!(ConfigError::Missing {
+                key: ConfigKey::AwsRegion
+            });
+        }
+        if self.credentials.access_key_id.is_empty() {
+            bail!(ConfigError::Missing {
+                key: ConfigKey::AwsAccessKeyId
+            });
+        }
+        if self.credentials.secret_access_key.is_empty() {
+            bail!(ConfigError::Missing {
+                key: ConfigKey::AwsSecretAccessKey
+            });
+        }
+
+        // Listing models is free, and fails when the credentials are invalid.
+        let client = http_client()?;
+        let req = self.signed_request(
+            client
+                .get(self.models_url())
+                .timeout(Duration::from_millis(self.timeout.parse::<u64>()?)),
+        )?;
+        let res = client.execute(req).await;
+
+        if res.is_err() {
+            tracing::error!(error = ?res.unwrap_err(), "Bedrock is not reachable");
+            bail!(BackendError::Unreachable {
+                backend: BackendName::Bedrock,
+                url: self.url.to_string(),
+            });
+        }
+
+        let status = res.unwrap().status().as_u16();
+        if status >= 400 {
+            tracing::error!(status = status, "Bedrock health check failed");
+            bail!(BackendError::from_status(BackendName::Bedrock, status, ""));
+        }
+
+        return Ok(());
+    }
+
+    /// Only text models that can be used on demand are listed, as the rest
+    /// need provisioned throughput bought ahead of time.
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let client = http_client()?;
+        let req = self.signed_request(client.get(self.models_url()))?;
+        let res = client.execute(req).await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to list Bedrock models"
+            );
+            bail!(BackendError::from_status(
+                BackendName::Bedrock,
+                res.status().as_u16(),
+                ""
+            ));
+        }
+
+        let mut models: Vec<String> = res
+            .json::<ModelListResponse>()
+            .await?
+            .model_summaries
+            .iter()
+            .filter(|model| {
+                return model.inference_types_supported.is_empty()
+                    || model
+                        .inference_types_supported
+                        .contains(&"ON_DEMAND".to_string());
+            })
+            .map(|model| {
+                return model.model_id.to_string();
+            })
+            .collect();
+
+        models.sort();
+
+        return Ok(models);
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        let model = prompt.model();
+        let mut messages: Vec<MessageRequest> = vec![];
+        if !prompt.backend_context.is_empty() {
+            messages = serde_json::from_str(&prompt.backend_context)?;
+        }
+        messages.push(MessageRequest {
+            role: "user".to_string(),
+            content: vec![ContentBlock { text: prompt.text }],
+        });
+
+        let sampling = SamplingParams::from_config();
+        let client = http_client()?;
+        let req = self.signed_request(
+            client
+                .post(format!(
+                    "{url}/model/{model}/converse-stream",
+                    url = self.runtime_url,
+                    model = uri_encode(&model, true)
+                ))
+                .json(&CompletionRequest {
+                    system: prompt
+                        .system_prompt
+                        .iter()
+                        .map(|e| {
+                            return ContentBlock {
+                                text: e.to_string(),
+                            };
+                        })
+                        .collect(),
+                    messages: messages.clone(),
+                    inference_config: Some(InferenceConfig {
+                        max_tokens: sampling.max_tokens,
+                        temperature: sampling.temperature,
+                        top_p: sampling.top_p,
+                        stop_sequences: sampling.stop_sequences(),
+                    })
+                    .filter(|e| return e != &InferenceConfig::default()),
+                }),
+        )?;
+        let res = client.execute(req).await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to make completion request to Bedrock"
+            );
+            bail!(BackendError::from_status(
+                BackendName::Bedrock,
+                res.status().as_u16(),
+                &model
+            ));
+        }
+
+        let mut stream = res.bytes_stream();
+        let mut decoder = EventStreamDecoder::default();
+        let mut last_message = "".to_string();
+        let mut truncated = false;
+        'stream: loop {
+            match stream.next().await {
+                Some(Ok(bytes)) => decoder.push(&bytes),
+                Some(Err(_)) => {
+                    bail!(StreamInterrupted { text: last_message });
+                }
+                None => break,
+            }
+
+            while let Some(message) = decoder.next_message()? {
+                let event_type = message
+                    .headers
+                    .get(":event-type")
+                    .map(|e| return e.as_str())
+                    .unwrap_or_default();
+                tracing::debug!(event_type = event_type, "Completion response");
+
+                if message
+                    .headers
+                    .get(":message-type")
+                    .map(|e| return e.as_str())
+                    == Some("exception")
+                {
+                    let exception = serde_json::from_slice::<ExceptionEvent>(&message.payload)
+                        .unwrap_or_default();
+                    let exception_type = message
+                        .headers
+                        .get(":exception-type")
+                        .cloned()
+                        .unwrap_or_default();
+                    bail!(
+                        "Bedrock failed to complete the answer: {exception_type} {}",
+                        exception.message
+                    );
+                }
+
+                match event_type {
+                    "contentBlockDelta" => {
+                        let event =
+                            serde_json::from_slice::<ContentBlockDeltaEvent>(&message.payload)?;
+                        if event.delta.text.is_empty() {
+                            continue;
+                        }
+
+                        last_message += &event.delta.text;
+                        let msg = BackendResponse {
+                            author: Author::Model,
+                            text: event.delta.text,
+                            done: false,
+                            context: None,
+                        };
+                        tx.send(Event::BackendPromptResponse(msg))?;
+                    }
+                    "messageStop" => {
+                        let event = serde_json::from_slice::<MessageStopEvent>(&message.payload)?;
+                        truncated = event.stop_reason == "max_tokens";
+                        break 'stream;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        messages.push(MessageRequest {
+            role: "assistant".to_string(),
+            content: vec![ContentBlock {
+                text: last_message.to_string(),
+            }],
+        });
+
+        let msg = BackendResponse {
+            author: Author::Model,
+            text: "".to_string(),
+            done: true,
+            context: Some(serde_json::to_string(&messages)?),
+        };
+        tx.send(Event::BackendPromptResponse(msg))?;
+        if truncated {
+            tx.send(Event::BackendOutputTruncated())?;
+        }
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,348 @@
+use anyhow::bail;
+use anyhow::Result;
+use chrono::TimeZone;
+use chrono::Utc;
+use mockito::Matcher;
+use test_utils::insta_snapshot;
+use tokio::sync::mpsc;
+
+use super::Bedrock;
+use super::ContentBlock;
+use super::Credentials;
+use super::EventStreamDecoder;
+use super::MessageRequest;
+use super::ModelListResponse;
+use super::ModelSummary;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Event;
+use crate::infrastructure::http::BackendAuth;
+
+impl Bedrock {
+    fn with_url(url: String) -> Bedrock {
+        return Bedrock {
+            url: url.to_string(),
+            runtime_url: url,
+            region: "us-east-1".to_string(),
+            credentials: Credentials {
+                access_key_id: "AKIDEXAMPLE".to_string(),
+                secret_access_key: "secret".to_string(),
+                session_token: "".to_string(),
+            },
+            auth: BackendAuth::default(),
+            timeout: "200".to_string(),
+        };
+    }
+}
+
+fn to_res(action: Option<Event>) -> Result<BackendResponse> {
+    let act = match action.unwrap() {
+        Event::BackendPromptResponse(res) => res,
+        _ => bail!("Wrong type from recv"),
+    };
+
+    return Ok(act);
+}
+
+/// Encodes a message the way Bedrock's event stream does, leaving the CRCs
+/// zeroed as they aren't checked.
+fn event_message(headers: &[(&str, &str)], payload: &str) -> Vec<u8> {
+    let mut raw_headers: Vec<u8> = vec![];
+    for (name, value) in headers {
+        raw_headers.push(name.len() as u8);
+        raw_headers.extend_from_slice(name.as_bytes());
+        raw_headers.push(7);
+        raw_headers.extend_from_slice(&(value.len() as u16).to_be_bytes());
+        raw_headers.extend_from_slice(value.as_bytes());
+    }
+
+    let total_len = 16 + raw_headers.len() + payload.len();
+    let mut res: Vec<u8> = vec![];
+    res.extend_from_slice(&(total_len as u32).to_be_bytes());
+    res.extend_from_slice(&(raw_headers.len() as u32).to_be_bytes());
+    res.extend_from_slice(&[0; 4]);
+    res.extend_from_slice(&raw_headers);
+    res.extend_from_slice(payload.as_bytes());
+    res.extend_from_slice(&[0; 4]);
+
+    return res;
+}
+
+fn event(event_type: &str, payload: &str) -> Vec<u8> {
+    return event_message(
+        &[(":event-type", event_type), (":message-type", "event")],
+        payload,
+    );
+}
+
+fn models_mock(server: &mut mockito::Server, status: usize) -> mockito::Mock {
+    return server
+        .mock("GET", "/foundation-models")
+        .match_query(Matcher::UrlEncoded(
+            "byOutputModality".to_string(),
+            "TEXT".to_string(),
+        ))
+        .match_header(
+            "authorization",
+            Matcher::Regex("^AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/".to_string()),
+        )
+        .with_status(status);
+}
+
+#[test]
+fn it_signs_requests_like_the_aws_example() -> Result<()> {
+    let credentials = Credentials {
+        access_key_id: "AKIDEXAMPLE".to_string(),
+        secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+        session_token: "".to_string(),
+    };
+    let mut req = reqwest::Client::new()
+        .get("https://iam.amazonaws.com/?Action=ListUsers&Version=2010-05-08")
+        .header(
+            "content-type",
+            "application/x-www-form-urlencoded; charset=utf-8",
+        )
+        .build()?;
+
+    let now = Utc.with_ymd_and_hms(2015, 8, 30, 12, 36, 0).unwrap();
+    credentials.sign(&mut req, "us-east-1", "iam", now)?;
+
+    assert_eq!(
+        req.headers()["authorization"],
+        "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, SignedHeaders=content-type;host;x-amz-date, Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+    );
+    assert_eq!(req.headers()["x-amz-date"], "20150830T123600Z");
+
+    return Ok(());
+}
+
+#[test]
+fn it_decodes_event_stream_messages_split_across_chunks() -> Result<()> {
+    let mut bytes = event("contentBlockDelta", r#"{"delta":{"text":"Hi"}}"#);
+    bytes.extend(event("messageStop", r#"{"stopReason":"end_turn"}"#));
+
+    let mut decoder = EventStreamDecoder::default();
+    decoder.push(&bytes[..10]);
+    assert_eq!(decoder.next_message()?, None);
+
+    decoder.push(&bytes[10..]);
+    let first = decoder.next_message()?.unwrap();
+    assert_eq!(first.headers[":event-type"], "contentBlockDelta");
+    assert_eq!(first.payload, br#"{"delta":{"text":"Hi"}}"#.to_vec());
+
+    let second = decoder.next_message()?.unwrap();
+    assert_eq!(second.headers[":event-type"], "messageStop");
+    assert_eq!(decoder.next_message()?, None);
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_successfully_health_checks() {
+    let mut server = mockito::Server::new();
+    let mock = models_mock(&mut server, 200).create();
+
+    let backend = Bedrock::with_url(server.url());
+    let res = backend.health_check().await;
+
+    assert!(res.is_ok());
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_fails_health_checks_with_invalid_credentials() {
+    let mut server = mockito::Server::new();
+    let mock = models_mock(&mut server, 403).create();
+
+    let backend = Bedrock::with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::InvalidToken {
+            backend: BackendName::Bedrock
+        })
+    );
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_signs_the_extra_headers() {
+    let mut server = mockito::Server::new();
+    let mock = models_mock(&mut server, 200)
+        .match_header("x-gateway", "oatmeal")
+        .match_header(
+            "authorization",
+            Matcher::Regex("SignedHeaders=host;x-amz-date;x-gateway,".to_string()),
+        )
+        .create();
+
+    let mut backend = Bedrock::with_url(server.url());
+    backend.auth = BackendAuth::parse(&BackendName::Bedrock, "bedrock=X-Gateway: oatmeal", "");
+    let res = backend.health_check().await;
+
+    assert!(res.is_ok());
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_rejects_token_commands() {
+    let mut server = mockito::Server::new();
+    let mock = models_mock(&mut server, 200).expect(0).create();
+
+    let mut backend = Bedrock::with_url(server.url());
+    backend.auth = BackendAuth::parse(&BackendName::Bedrock, "", "bedrock=echo token");
+    let err = backend.health_check().await.unwrap_err();
+
+    assert!(err.to_string().contains("`backend-token-commands`"));
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_lists_on_demand_models() -> Result<()> {
+    let body = serde_json::to_string(&ModelListResponse {
+        model_summaries: vec![
+            ModelSummary {
+                model_id: "anthropic.claude-v2:1".to_string(),
+                inference_types_supported: vec!["ON_DEMAND".to_string()],
+            },
+            ModelSummary {
+                model_id: "anthropic.claude-v2:1:18k".to_string(),
+                inference_types_supported: vec!["PROVISIONED".to_string()],
+            },
+            ModelSummary {
+                model_id: "amazon.titan-text-express-v1".to_string(),
+                inference_types_supported: vec!["ON_DEMAND".to_string()],
+            },
+        ],
+    })?;
+
+    let mut server = mockito::Server::new();
+    let mock = models_mock(&mut server, 200).with_body(body).create();
+
+    let backend = Bedrock::with_url(server.url());
+    let res = backend.list_models().await?;
+    mock.assert();
+
+    assert_eq!(
+        res,
+        vec![
+            "amazon.titan-text-express-v1".to_string(),
+            "anthropic.claude-v2:1".to_string(),
+        ]
+    );
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_gets_completions() -> Result<()> {
+    Config::set(ConfigKey::Model, "model-1");
+    let body = [
+        event("messageStart", r#"{"role":"assistant"}"#),
+        event("contentBlockDelta", r#"{"delta":{"text":"Hello "}}"#),
+        event("contentBlockDelta", r#"{"delta":{"text":"World"}}"#),
+        event("contentBlockStop", r#"{"contentBlockIndex":0}"#),
+        event("messageStop", r#"{"stopReason":"end_turn"}"#),
+    ]
+    .concat();
+    let prompt = BackendPrompt {
+        text: "Say hi to the world".to_string(),
+        backend_context: serde_json::to_string(&vec![MessageRequest {
+            role: "assistant".to_string(),
+            content: vec![ContentBlock {
+                text: "How may I help you?".to_string(),
+            }],
+        }])?,
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
+    };
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/model/model-1/converse-stream")
+        .match_header(
+            "authorization",
+            Matcher::Regex("SignedHeaders=content-type;host;x-amz-date,".to_string()),
+        )
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Bedrock::with_url(server.url());
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    let first_recv = to_res(rx.recv().await)?;
+    let second_recv = to_res(rx.recv().await)?;
+    let third_recv = to_res(rx.recv().await)?;
+
+    assert_eq!(first_recv.author, Author::Model);
+    assert_eq!(first_recv.text, "Hello ".to_string());
+    assert!(!first_recv.done);
+
+    assert_eq!(second_recv.text, "World".to_string());
+    assert!(!second_recv.done);
+
+    assert!(third_recv.text.is_empty());
+    assert!(third_recv.done);
+    insta_snapshot(|| {
+        insta::assert_toml_snapshot!(third_recv.context);
+    });
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_fails_completions_on_stream_exceptions() -> Result<()> {
+    Config::set(ConfigKey::Model, "model-1");
+    let body = [
+        event("contentBlockDelta", r#"{"delta":{"text":"Hello "}}"#),
+        event_message(
+            &[
+                (":exception-type", "throttlingException"),
+                (":message-type", "exception"),
+            ],
+            r#"{"message":"Too many requests"}"#,
+        ),
+    ]
+    .concat();
+    let prompt = BackendPrompt {
+        text: "Say hi to the world".to_string(),
+        backend_context: "".to_string(),
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
+    };
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/model/model-1/converse-stream")
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Bedrock::with_url(server.url());
+    let err = backend.get_completion(prompt, &tx).await.unwrap_err();
+    mock.assert();
+
+    assert!(err.to_string().contains("Too many requests"));
+
+    return Ok(());
+}
@@ -0,0 +1,524 @@
+#[cfg(test)]
+#[path = "candle_test.rs"]
+mod tests;
+
+use std::path::PathBuf;
+#[cfg(feature = "local-inference")]
+use std::sync::Arc;
+#[cfg(feature = "local-inference")]
+use std::sync::Mutex;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::fs;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Event;
+use crate::domain::models::SamplingParams;
+
+/// Where a plain transcript has the model's turn end and the user's begin.
+#[cfg_attr(not(feature = "local-inference"), allow(dead_code))]
+const TRANSCRIPT_STOP: &str = "\nUser:";
+
+#[cfg_attr(not(feature = "local-inference"), allow(dead_code))]
+const DEFAULT_TEMPERATURE: f64 = 0.8;
+
+#[cfg_attr(not(feature = "local-inference"), allow(dead_code))]
+const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+#[cfg_attr(feature = "local-inference", allow(dead_code))]
+const MISSING_FEATURE: &str = "Oatmeal was built without local inference. Rebuild it with `cargo install oatmeal --features local-inference`, or choose another backend.";
+
+/// Options for sampling each token of an answer.
+#[cfg_attr(not(feature = "local-inference"), allow(dead_code))]
+#[derive(Clone, Debug, PartialEq)]
+struct Generation {
+    temperature: Option<f64>,
+    top_p: Option<f64>,
+    max_tokens: usize,
+    seed: u64,
+    /// Ends the answer once generated, besides the start of the user's turn.
+    stop: Vec<String>,
+}
+
+#[cfg_attr(not(feature = "local-inference"), allow(dead_code))]
+impl Generation {
+    fn from_config() -> Result<Generation> {
+        let sampling = SamplingParams::from_config();
+        // Sampling without a temperature always picks the likeliest token.
+        let temperature = sampling.temperature.unwrap_or(DEFAULT_TEMPERATURE);
+        let seed = match Config::get(ConfigKey::Seed).parse::<u64>() {
+            Ok(seed) => seed,
+            Err(_) => {
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)?
+                    .as_nanos() as u64
+            }
+        };
+
+        return Ok(Generation {
+            temperature: (temperature > 0.0).then_some(temperature),
+            top_p: sampling.top_p,
+            max_tokens: sampling.max_tokens.unwrap_or(DEFAULT_MAX_TOKENS) as usize,
+            seed,
+            stop: sampling.stop,
+        });
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Context {
+    prompt: String,
+}
+
+/// Turns the text decoded so far into chunks to stream, holding back any
+/// ending that could be the start of a stop sequence, or of a character
+/// split across tokens.
+#[cfg_attr(not(feature = "local-inference"), allow(dead_code))]
+struct Streamer {
+    decoded: String,
+    emitted: usize,
+    stopped: bool,
+    /// The transcript's stop sequence, followed by any from `stop-sequences`.
+    stops: Vec<String>,
+}
+
+impl Default for Streamer {
+    fn default() -> Streamer {
+        return Streamer::new(&[]);
+    }
+}
+
+#[cfg_attr(not(feature = "local-inference"), allow(dead_code))]
+impl Streamer {
+    fn new(stop: &[String]) -> Streamer {
+        let mut stops = vec![TRANSCRIPT_STOP.to_string()];
+        stops.extend(stop.iter().filter(|e| return !e.is_empty()).cloned());
+        return Streamer {
+            decoded: "".to_string(),
+            emitted: 0,
+            stopped: false,
+            stops,
+        };
+    }
+
+    /// Returns the next chunk to stream, and whether the model has started
+    /// the user's turn or generated another stop sequence, ending its answer.
+    fn next(&mut self, decoded: &str) -> (String, bool) {
+        self.decoded = decoded.to_string();
+        if let Some(idx) = self
+            .stops
+            .iter()
+            .filter_map(|e| return decoded.find(e.as_str()))
+            .min()
+        {
+            let chunk = decoded[self.emitted.min(idx)..idx].to_string();
+            self.emitted = idx;
+            self.stopped = true;
+            return (chunk, true);
+        }
+
+        let mut held = self
+            .stops
+            .iter()
+            .filter_map(|stop| {
+                return (1..stop.len())
+                    .rev()
+                    .filter(|len| return stop.is_char_boundary(*len))
+                    .find(|len| return decoded.ends_with(&stop[..*len]));
+            })
+            .max()
+            .unwrap_or(0);
+        if decoded.ends_with(char::REPLACEMENT_CHARACTER) {
+            held = char::REPLACEMENT_CHARACTER.len_utf8();
+        }
+
+        let safe = decoded.len() - held;
+        if safe <= self.emitted {
+            return ("".to_string(), false);
+        }
+
+        let chunk = decoded[self.emitted..safe].to_string();
+        self.emitted = safe;
+        return (chunk, false);
+    }
+
+    /// Whatever was held back, once the answer ended without the stop
+    /// sequence.
+    fn rest(&self) -> String {
+        if self.stopped {
+            return "".to_string();
+        }
+
+        return self.decoded.get(self.emitted..).unwrap_or("").to_string();
+    }
+}
+
+/// Runs GGUF models in process with candle, so answers need neither a
+/// server nor a network connection. Models are `.gguf` files in
+/// `candle-models-dir`, each with a Hugging Face `tokenizer.json` beside
+/// it.
+pub struct Candle {
+    models_dir: PathBuf,
+    /// Preloaded and health checked, while prompts can ask for another.
+    model: String,
+    /// The last model answers came from, kept loaded for the next prompt.
+    #[cfg(feature = "local-inference")]
+    loaded: Arc<Mutex<Option<model::LoadedModel>>>,
+}
+
+impl Default for Candle {
+    fn default() -> Candle {
+        let mut models_dir = PathBuf::from(Config::get(ConfigKey::CandleModelsDir));
+        if models_dir.as_os_str().is_empty() {
+            models_dir = dirs::cache_dir().unwrap().join("oatmeal/models");
+        }
+
+        return Candle::new(models_dir, &Config::get(ConfigKey::Model));
+    }
+}
+
+#[cfg_attr(not(feature = "local-inference"), allow(dead_code))]
+impl Candle {
+    pub fn new(models_dir: PathBuf, model: &str) -> Candle {
+        return Candle {
+            models_dir,
+            model: model.to_string(),
+            #[cfg(feature = "local-inference")]
+            loaded: Arc::new(Mutex::new(None)),
+        };
+    }
+
+    /// The model's weights, and the tokenizer for them. A tokenizer named
+    /// after the model, such as `mistral.tokenizer.json`, is preferred over
+    /// one shared by the directory.
+    fn model_files(&self, model: &str) -> Result<(PathBuf, PathBuf)> {
+        if model.is_empty() {
+            bail!(
+                "Set `model` to one of the GGUF models in {}",
+                self.models_dir.display()
+            );
+        }
+
+        let name = model.trim_end_matches(".gguf");
+        let weights = self.models_dir.join(format!("{name}.gguf"));
+        if !weights.exists() {
+            bail!("Model {} doesn't exist", weights.display());
+        }
+
+        let tokenizer = [
+            format!("{name}.tokenizer.json"),
+            "tokenizer.json".to_string(),
+        ]
+        .iter()
+        .map(|e| return self.models_dir.join(e))
+        .find(|e| return e.exists());
+        return match tokenizer {
+            Some(tokenizer) => Ok((weights, tokenizer)),
+            None => bail!(
+                "Model {name} needs a Hugging Face tokenizer, saved as {name}.tokenizer.json or tokenizer.json in {}",
+                self.models_dir.display()
+            ),
+        };
+    }
+
+    /// Loads the model on a blocking thread, unless it's the one already
+    /// loaded.
+    #[cfg(feature = "local-inference")]
+    async fn load(&self, model: &str) -> Result<()> {
+        let (weights, tokenizer) = self.model_files(model)?;
+        let loaded = self.loaded.clone();
+
+        return tokio::task::spawn_blocking(move || {
+            model::load(&mut loaded.lock().unwrap(), &weights, &tokenizer)?;
+            return Ok(());
+        })
+        .await?;
+    }
+
+    /// Generates on a blocking thread, which stops at the next token once
+    /// `cancel` is cancelled.
+    #[cfg(feature = "local-inference")]
+    async fn generate(
+        &self,
+        model: &str,
+        prompt: String,
+        tx: &mpsc::UnboundedSender<Event>,
+        cancel: CancellationToken,
+    ) -> Result<(String, bool)> {
+        let (weights, tokenizer) = self.model_files(model)?;
+        let generation = Generation::from_config()?;
+        let loaded = self.loaded.clone();
+        let tx = tx.clone();
+
+        return tokio::task::spawn_blocking(move || {
+            let mut loaded = loaded.lock().unwrap();
+            let model = model::load(&mut loaded, &weights, &tokenizer)?;
+
+            let mut streamer = Streamer::new(&generation.stop);
+            let mut answer = "".to_string();
+            let mut send = |chunk: String| -> Result<()> {
+                if !chunk.is_empty() && !cancel.is_cancelled() {
+                    answer += &chunk;
+                    tx.send(Event::BackendPromptResponse(BackendResponse {
+                        author: Author::Model,
+                        text: chunk,
+                        done: false,
+                        context: None,
+                    }))?;
+                }
+                return Ok(());
+            };
+
+            // The answer starts after the space following `Assistant:`.
+            let truncated = model.generate(&prompt, &generation, |decoded| {
+                let (chunk, stopped) = streamer.next(decoded.trim_start());
+                send(chunk)?;
+                return Ok(stopped || cancel.is_cancelled());
+            })?;
+            send(streamer.rest())?;
+
+            return Ok((answer, truncated));
+        })
+        .await?;
+    }
+
+    #[cfg(not(feature = "local-inference"))]
+    async fn generate(
+        &self,
+        _model: &str,
+        _prompt: String,
+        _tx: &mpsc::UnboundedSender<Event>,
+        _cancel: CancellationToken,
+    ) -> Result<(String, bool)> {
+        bail!(MISSING_FEATURE);
+    }
+
+    async fn complete(
+        &self,
+        prompt: BackendPrompt,
+        tx: &mpsc::UnboundedSender<Event>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        let mut context = Context::default();
+        if !prompt.backend_context.is_empty() {
+            context = serde_json::from_str(&prompt.backend_context)?;
+        }
+        context.prompt = format!("{}User: {}\nAssistant:", context.prompt, prompt.text);
+
+        // The system prompt heads the transcript of each request, rather than
+        // being saved with it.
+        let text = match &prompt.system_prompt {
+            Some(system_prompt) => format!("{system_prompt}\n\n{}", context.prompt),
+            None => context.prompt.to_string(),
+        };
+        let (answer, truncated) = self
+            .generate(&prompt.model(), text, tx, cancel.clone())
+            .await?;
+        if cancel.is_cancelled() {
+            return Ok(());
+        }
+        context.prompt = format!("{} {}\n", context.prompt, answer.trim_end());
+
+        tx.send(Event::BackendPromptResponse(BackendResponse {
+            author: Author::Model,
+            text: "".to_string(),
+            done: true,
+            context: Some(serde_json::to_string(&context)?),
+        }))?;
+        if truncated {
+            tx.send(Event::BackendOutputTruncated())?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[async_trait]
+impl Backend for Candle {
+    fn name(&self) -> BackendName {
+        return BackendName::Candle;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        if cfg!(not(feature = "local-inference")) {
+            bail!(MISSING_FEATURE);
+        }
+
+        self.model_files(&self.model)?;
+        return Ok(());
+    }
+
+    /// Loads the model ahead of the first prompt, which would otherwise wait
+    /// for it.
+    #[cfg(feature = "local-inference")]
+    #[allow(clippy::implicit_return)]
+    async fn preload(&self) -> Result<()> {
+        return self.load(&self.model).await;
+    }
+
+    /// GGUF files in the models directory.
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let mut models = vec![];
+        let mut entries = fs::read_dir(&self.models_dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            if let Some(name) = file_name.strip_suffix(".gguf") {
+                models.push(name.to_string());
+            }
+        }
+        models.sort();
+
+        return Ok(models);
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        return self.complete(prompt, tx, CancellationToken::new()).await;
+    }
+
+    /// Candle generates on a blocking thread that keeps going after the
+    /// request is dropped, so it's told to stop instead.
+    #[allow(clippy::implicit_return)]
+    async fn get_cancellable_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+        cancel: CancellationToken,
+    ) -> Result<()> {
+        return self.complete(prompt, tx, cancel).await;
+    }
+}
+
+#[cfg(feature = "local-inference")]
+mod model {
+    use std::path::Path;
+    use std::path::PathBuf;
+
+    use anyhow::anyhow;
+    use anyhow::Result;
+    use candle_core::quantized::gguf_file;
+    use candle_core::Device;
+    use candle_core::Tensor;
+    use candle_transformers::generation::LogitsProcessor;
+    use candle_transformers::models::quantized_llama::ModelWeights;
+    use tokenizers::Tokenizer;
+
+    use super::Generation;
+
+    /// A model's weights and tokenizer, read once and reused for every
+    /// answer. Each answer starts over from the first position, which clears
+    /// what the model cached of the one before.
+    pub struct LoadedModel {
+        weights_path: PathBuf,
+        weights: ModelWeights,
+        tokenizer: Tokenizer,
+        eos_token: Option<u32>,
+    }
+
+    /// Loads the model into `loaded`, unless it's already the one there.
+    pub fn load<'a>(
+        loaded: &'a mut Option<LoadedModel>,
+        weights: &Path,
+        tokenizer: &Path,
+    ) -> Result<&'a mut LoadedModel> {
+        if let Some(model) = loaded.as_ref() {
+            if model.weights_path != weights {
+                // Frees the previous model before reading the next one.
+                *loaded = None;
+            }
+        }
+        if loaded.is_none() {
+            tracing::info!(model = %weights.display(), "Loading model");
+            *loaded = Some(LoadedModel::read(weights, tokenizer)?);
+        }
+
+        return Ok(loaded.as_mut().unwrap());
+    }
+
+    impl LoadedModel {
+        fn read(weights: &Path, tokenizer: &Path) -> Result<LoadedModel> {
+            let mut file = std::fs::File::open(weights)?;
+            let content = gguf_file::Content::read(&mut file)?;
+            let eos_token = content
+                .metadata
+                .get("tokenizer.ggml.eos_token_id")
+                .and_then(|e| return e.to_u32().ok());
+
+            return Ok(LoadedModel {
+                weights_path: weights.to_path_buf(),
+                weights: ModelWeights::from_gguf(content, &mut file, &Device::Cpu)?,
+                tokenizer: Tokenizer::from_file(tokenizer).map_err(|e| return anyhow!(e))?,
+                eos_token,
+            });
+        }
+
+        /// Generates an answer to `prompt` a token at a time, passing the
+        /// text decoded so far to `on_text` until it returns true, the model
+        /// ends its answer, or `max_tokens` is reached. Returns whether the
+        /// answer was cut off by `max_tokens`.
+        pub fn generate(
+            &mut self,
+            prompt: &str,
+            generation: &Generation,
+            mut on_text: impl FnMut(&str) -> Result<bool>,
+        ) -> Result<bool> {
+            let device = Device::Cpu;
+            let eos_token = self.eos_token;
+            let model = &mut self.weights;
+            let tokenizer = &self.tokenizer;
+            let prompt_tokens = tokenizer
+                .encode(prompt, true)
+                .map_err(|e| return anyhow!(e))?
+                .get_ids()
+                .to_vec();
+
+            let mut logits_processor =
+                LogitsProcessor::new(generation.seed, generation.temperature, generation.top_p);
+            let mut input = prompt_tokens.clone();
+            let mut answer_tokens = vec![];
+
+            for _ in 0..generation.max_tokens {
+                let position = prompt_tokens.len() + answer_tokens.len() - input.len();
+                let logits = model
+                    .forward(
+                        &Tensor::new(input.as_slice(), &device)?.unsqueeze(0)?,
+                        position,
+                    )?
+                    .squeeze(0)?;
+                let token = logits_processor.sample(&logits)?;
+                if Some(token) == eos_token {
+                    return Ok(false);
+                }
+
+                answer_tokens.push(token);
+                let decoded = tokenizer
+                    .decode(&answer_tokens, true)
+                    .map_err(|e| return anyhow!(e))?;
+                if on_text(&decoded)? {
+                    return Ok(false);
+                }
+                input = vec![token];
+            }
+
+            return Ok(true);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use anyhow::Result;
+#[cfg(feature = "local-inference")]
+use tokio::sync::mpsc;
+
+use super::Candle;
+use super::Streamer;
+use crate::domain::models::Backend;
+#[cfg(feature = "local-inference")]
+use crate::domain::models::BackendPrompt;
+#[cfg(feature = "local-inference")]
+use crate::domain::models::Event;
+
+#[tokio::test]
+async fn it_lists_gguf_models() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    for file in ["phi-2.Q4_K_M.gguf", "mistral.gguf", "tokenizer.json"] {
+        std::fs::write(dir.path().join(file), "")?;
+    }
+
+    let models = Candle::new(dir.path().to_path_buf(), "")
+        .list_models()
+        .await?;
+    assert_eq!(models, vec!["mistral", "phi-2.Q4_K_M"]);
+
+    return Ok(());
+}
+
+#[test]
+fn it_finds_the_tokenizer_for_a_model() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(dir.path().join("mistral.gguf"), "")?;
+
+    let err = Candle::new(dir.path().to_path_buf(), "")
+        .model_files("mistral")
+        .unwrap_err();
+    assert!(err.to_string().starts_with(
+        "Model mistral needs a Hugging Face tokenizer, saved as mistral.tokenizer.json or tokenizer.json"
+    ));
+
+    std::fs::write(dir.path().join("tokenizer.json"), "")?;
+    let (_, tokenizer) = Candle::new(dir.path().to_path_buf(), "").model_files("mistral.gguf")?;
+    assert_eq!(tokenizer, dir.path().join("tokenizer.json"));
+
+    std::fs::write(dir.path().join("mistral.tokenizer.json"), "")?;
+    let (weights, tokenizer) = Candle::new(dir.path().to_path_buf(), "").model_files("mistral")?;
+    assert_eq!(weights, dir.path().join("mistral.gguf"));
+    assert_eq!(tokenizer, dir.path().join("mistral.tokenizer.json"));
+
+    assert!(Candle::new(dir.path().to_path_buf(), "")
+        .model_files("missing")
+        .is_err());
+
+    return Ok(());
+}
+
+#[test]
+fn it_streams_until_the_users_turn() {
+    let mut streamer = Streamer::default();
+    assert_eq!(streamer.next("Hello"), ("Hello".to_string(), false));
+    assert_eq!(
+        streamer.next("Hello there\n"),
+        (" there".to_string(), false)
+    );
+    assert_eq!(streamer.next("Hello there\nUs"), ("".to_string(), false));
+    assert_eq!(streamer.next("Hello there\nUser:"), ("".to_string(), true));
+    assert_eq!(streamer.rest(), "");
+
+    let mut streamer = Streamer::default();
+    assert_eq!(streamer.next("Caf\u{FFFD}"), ("Caf".to_string(), false));
+    assert_eq!(streamer.next("Café!\n"), ("é!".to_string(), false));
+    assert_eq!(streamer.rest(), "\n");
+}
+
+#[test]
+fn it_streams_until_a_stop_sequence() {
+    let mut streamer = Streamer::new(&["###".to_string()]);
+    assert_eq!(streamer.next("Done #"), ("Done ".to_string(), false));
+    assert_eq!(streamer.next("Done ###"), ("".to_string(), true));
+    assert_eq!(streamer.rest(), "");
+}
+
+#[cfg(not(feature = "local-inference"))]
+#[tokio::test]
+async fn it_requires_the_local_inference_feature() {
+    let dir = tempfile::tempdir().unwrap();
+    let err = Candle::new(dir.path().to_path_buf(), "mistral")
+        .health_check()
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("--features local-inference"));
+}
+
+#[cfg(feature = "local-inference")]
+#[tokio::test]
+async fn it_answers_with_the_prompts_model() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    for file in ["mistral.gguf", "tokenizer.json"] {
+        std::fs::write(dir.path().join(file), "")?;
+    }
+
+    let mut prompt = BackendPrompt::new("Hello".to_string(), "".to_string());
+    prompt.model = Some("phi-2".to_string());
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+    let err = Candle::new(dir.path().to_path_buf(), "mistral")
+        .get_completion(prompt, &tx)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("phi-2.gguf doesn't exist"));
+
+    return Ok(());
+}
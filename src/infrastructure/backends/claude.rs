@@ -8,8 +8,6 @@ use anyhow::bail;
 use anyhow::Result;
 use async_trait::async_trait;
 use futures::stream::TryStreamExt;
-use itertools::Itertools;
-use regex::Regex;
 use serde::Deserialize;
 use serde::Serialize;
 use tokio::io::AsyncBufReadExt;
@@ -20,10 +18,16 @@ use crate::configuration::Config;
 use crate::configuration::ConfigKey;
 use crate::domain::models::Author;
 use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
 use crate::domain::models::BackendName;
 use crate::domain::models::BackendPrompt;
 use crate::domain::models::BackendResponse;
+use crate::domain::models::ConfigError;
 use crate::domain::models::Event;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::StreamInterrupted;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::SendAuthorized;
 
 fn convert_err(err: reqwest::Error) -> std::io::Error {
     let err_msg = err.to_string();
@@ -38,25 +42,61 @@ struct Model {
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct ModelListResponse {
     data: Vec<Model>,
+    #[serde(default)]
+    has_more: bool,
+    #[serde(default)]
+    last_id: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ImageSource {
+    #[serde(rename = "type")]
+    kind: String,
+    media_type: String,
+    data: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text { text: String },
+    Image { source: ImageSource },
+}
+
+/// Text, or text with images for vision models.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Blocks(Vec<ContentBlock>),
+}
+
+impl Default for MessageContent {
+    fn default() -> MessageContent {
+        return MessageContent::Text("".to_string());
+    }
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct MessageRequest {
     role: String,
-    content: String,
+    content: MessageContent,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct CompletionRequest {
     model: String,
     max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
     messages: Vec<MessageRequest>,
     stream: bool,
-}
-
-#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
-struct Healthcheck {
-    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -73,6 +113,9 @@ struct CompletionResponse {
     delta: CompletionDeltaResponse,
 }
 
+/// Version of the Anthropic API the requests are written against.
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
 pub struct Claude {
     url: String,
     token: String,
@@ -101,30 +144,34 @@ impl Backend for Claude {
             bail!("Claude URL is not defined");
         }
         if self.token.is_empty() {
-            bail!("Claude token is not defined");
+            bail!(ConfigError::Missing {
+                key: ConfigKey::ClaudeToken
+            });
         }
 
-        let res = reqwest::Client::new()
-            .get(format!("{url}/healthcheck", url = self.url))
+        // Listing a single model is the cheapest request that checks the API
+        // key is valid.
+        let res = http_client()?
+            .get(format!("{url}/v1/models", url = self.url))
+            .query(&[("limit", "1")])
+            .header("x-api-key", &self.token)
+            .header("anthropic-version", ANTHROPIC_VERSION)
             .timeout(Duration::from_millis(self.timeout.parse::<u64>()?))
-            .send()
+            .send_authorized(BackendName::Claude)
             .await;
 
         if res.is_err() {
             tracing::error!(error = ?res.unwrap_err(), "Claude is not reachable");
-            bail!("Claude is not reachable");
+            bail!(BackendError::Unreachable {
+                backend: BackendName::Claude,
+                url: self.url.to_string(),
+            });
         }
 
-        let result = res.unwrap();
-        let status = result.status().as_u16();
+        let status = res.unwrap().status().as_u16();
         if status >= 400 {
             tracing::error!(status = status, "Claude health check failed");
-            bail!("Claude health check failed");
-        }
-
-        let json = result.json::<Healthcheck>().await?;
-        if !json.message.contains("ok") {
-            bail!("Claude health check failed");
+            bail!(BackendError::from_status(BackendName::Claude, status, ""));
         }
 
         return Ok(());
@@ -132,39 +179,45 @@ impl Backend for Claude {
 
     #[allow(clippy::implicit_return)]
     async fn list_models(&self) -> Result<Vec<String>> {
-        let backup = vec![
-            "claude-3-haiku-20240307".to_string(),
-            "claude-3-sonnet-20240229".to_string(),
-            "claude-3-opus-20240229".to_string(),
-            "claude-2.1".to_string(),
-            "claude-2.0".to_string(),
-        ];
-        let res = reqwest::Client::new()
-            .get("https://raw.githubusercontent.com/anthropics/anthropic-sdk-typescript/main/src/resources/messages.ts")
-            .send()
-            .await;
+        let mut models: Vec<String> = vec![];
+        let mut after_id: Option<String> = None;
+        loop {
+            let mut query = vec![("limit", "1000".to_string())];
+            if let Some(id) = after_id {
+                query.push(("after_id", id));
+            }
 
-        let body = match res {
-            Ok(html) => html.text().await,
-            Err(_) => return Ok(backup),
-        };
+            let res = http_client()?
+                .get(format!("{url}/v1/models", url = self.url))
+                .query(&query)
+                .header("x-api-key", &self.token)
+                .header("anthropic-version", ANTHROPIC_VERSION)
+                .send_authorized(BackendName::Claude)
+                .await?;
+
+            if !res.status().is_success() {
+                tracing::error!(
+                    status = res.status().as_u16(),
+                    "Failed to list Claude models"
+                );
+                bail!(BackendError::from_status(
+                    BackendName::Claude,
+                    res.status().as_u16(),
+                    ""
+                ));
+            }
 
-        match body {
-            Ok(html) => {
-                let re = Regex::new(r#"['"](claude-.*)['"]"#).unwrap();
-                let mut results: Vec<String> = vec![];
-                for (_, [model]) in re.captures_iter(&html).map(|c| c.extract()) {
-                    let m = model.to_string();
-                    let cleaned = Regex::new(r"[^a-zA-Z0-9-\.]")
-                        .unwrap()
-                        .replace_all(&m, "")
-                        .to_string();
-                    results.push(cleaned);
-                }
-                return Ok(results.into_iter().unique().collect());
+            let page = res.json::<ModelListResponse>().await?;
+            models.extend(page.data.into_iter().map(|model| return model.id));
+            if !page.has_more || page.last_id.is_none() {
+                break;
             }
-            Err(_) => return Ok(backup),
-        };
+            after_id = page.last_id;
+        }
+
+        // Anthropic lists the newest models first, which is kept as the most
+        // useful order.
+        return Ok(models);
     }
 
     #[allow(clippy::implicit_return)]
@@ -173,30 +226,56 @@ impl Backend for Claude {
         prompt: BackendPrompt,
         tx: &'a mpsc::UnboundedSender<Event>,
     ) -> Result<()> {
+        let model = prompt.model();
         let mut messages: Vec<MessageRequest> = vec![];
         if !prompt.backend_context.is_empty() {
             messages = serde_json::from_str(&prompt.backend_context)?;
         }
+        let mut content = MessageContent::Text(prompt.text.to_string());
+        if !prompt.images.is_empty() {
+            // Claude reads images best ahead of the text about them.
+            let mut blocks = prompt
+                .images
+                .iter()
+                .map(|e| {
+                    return ContentBlock::Image {
+                        source: ImageSource {
+                            kind: "base64".to_string(),
+                            media_type: e.media_type.to_string(),
+                            data: e.data.to_string(),
+                        },
+                    };
+                })
+                .collect::<Vec<ContentBlock>>();
+            blocks.push(ContentBlock::Text { text: prompt.text });
+            content = MessageContent::Blocks(blocks);
+        }
         messages.push(MessageRequest {
             role: "user".to_string(),
-            content: prompt.text,
+            content,
         });
 
+        // Claude requires a limit, so it falls back to one when unset.
+        let sampling = SamplingParams::from_config();
         let req = CompletionRequest {
-            model: Config::get(ConfigKey::Model),
-            max_tokens: 1024,
+            model: model.to_string(),
+            max_tokens: sampling.max_tokens.unwrap_or(1024),
+            system: prompt.system_prompt,
             messages: messages.clone(),
             stream: true,
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            stop_sequences: sampling.stop_sequences(),
         };
 
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .post(format!("{url}/v1/messages", url = self.url))
             .header("x-api-key", &self.token)
             .header("content-type", "application/json")
-            .header("anthropic-version", "2023-06-01")
+            .header("anthropic-version", ANTHROPIC_VERSION)
             .header("anthropic-beta", "messages-2023-12-15")
             .json(&req)
-            .send()
+            .send_authorized(BackendName::Claude)
             .await?;
 
         if !res.status().is_success() {
@@ -204,19 +283,28 @@ impl Backend for Claude {
                 status = res.status().as_u16(),
                 "Failed to make completion request to Claude"
             );
-            bail!("Failed to make completion request to Claude");
+            bail!(BackendError::from_status(
+                BackendName::Claude,
+                res.status().as_u16(),
+                &model
+            ));
         }
 
         let stream = res.bytes_stream().map_err(convert_err);
         let mut lines_reader = StreamReader::new(stream).lines();
 
         let mut last_message = "".to_string();
-        while let Ok(line) = lines_reader.next_line().await {
-            if line.is_none() {
-                break;
-            }
+        let mut truncated = false;
+        loop {
+            let line = match lines_reader.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(_) => {
+                    bail!(StreamInterrupted { text: last_message });
+                }
+            };
 
-            let mut cleaned_line = line.unwrap().trim().to_string();
+            let mut cleaned_line = line.trim().to_string();
             if cleaned_line.starts_with("data:") {
                 cleaned_line = cleaned_line.split_off(5).trim().to_string();
             }
@@ -224,7 +312,12 @@ impl Backend for Claude {
                 continue;
             }
 
-            if cleaned_line.contains("content_block_stop") {
+            // The stop reason arrives after the content, once it's finished.
+            if cleaned_line.contains("\"message_delta\"") {
+                truncated = cleaned_line.contains("\"max_tokens\"");
+                continue;
+            }
+            if cleaned_line.contains("message_stop") {
                 break;
             }
             if !cleaned_line.contains("content_block_delta") {
@@ -252,7 +345,7 @@ impl Backend for Claude {
 
         messages.push(MessageRequest {
             role: "assistant".to_string(),
-            content: last_message.to_string(),
+            content: MessageContent::Text(last_message.to_string()),
         });
 
         let msg = BackendResponse {
@@ -262,6 +355,9 @@ impl Backend for Claude {
             context: Some(serde_json::to_string(&messages)?),
         };
         tx.send(Event::BackendPromptResponse(msg))?;
+        if truncated {
+            tx.send(Event::BackendOutputTruncated())?;
+        }
 
         return Ok(());
     }
@@ -6,13 +6,18 @@ use tokio::sync::mpsc;
 use super::Claude;
 use super::CompletionDeltaResponse;
 use super::CompletionResponse;
-use super::Healthcheck;
+use super::MessageContent;
 use super::MessageRequest;
+use super::Model;
+use super::ModelListResponse;
 use crate::domain::models::Author;
 use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
 use crate::domain::models::BackendPrompt;
 use crate::domain::models::BackendResponse;
 use crate::domain::models::Event;
+use crate::domain::models::ImageAttachment;
 
 impl Claude {
     fn with_url(url: String) -> Claude {
@@ -35,14 +40,19 @@ fn to_res(action: Option<Event>) -> Result<BackendResponse> {
 
 #[tokio::test]
 async fn it_successfully_health_checks() {
-    let body = serde_json::to_string(&Healthcheck {
-        message: "ok".to_string(),
+    let body = serde_json::to_string(&ModelListResponse {
+        data: vec![Model {
+            id: "claude-3-5-sonnet-20241022".to_string(),
+        }],
+        ..ModelListResponse::default()
     })
     .unwrap();
 
     let mut server = mockito::Server::new();
     let mock = server
-        .mock("GET", "/healthcheck")
+        .mock("GET", "/v1/models?limit=1")
+        .match_header("x-api-key", "abc")
+        .match_header("anthropic-version", "2023-06-01")
         .with_status(200)
         .with_body(body)
         .create();
@@ -56,7 +66,18 @@ async fn it_successfully_health_checks() {
 
 #[tokio::test]
 async fn it_successfully_health_checks_with_official_api() {
-    let backend = Claude::with_url("https://api.anthropic.com".to_string());
+    let token = match std::env::var("OATMEAL_CLAUDE_TOKEN") {
+        Ok(token) => token,
+        Err(_) => {
+            println!("There is no token in environment defined, skipping test");
+            return;
+        }
+    };
+    let backend = Claude {
+        url: "https://api.anthropic.com".to_string(),
+        token,
+        timeout: "500".to_string(),
+    };
     let res = backend.health_check().await;
 
     assert!(res.is_ok());
@@ -65,7 +86,10 @@ async fn it_successfully_health_checks_with_official_api() {
 #[tokio::test]
 async fn it_fails_health_checks() {
     let mut server = mockito::Server::new();
-    let mock = server.mock("GET", "/healthcheck").with_status(500).create();
+    let mock = server
+        .mock("GET", "/v1/models?limit=1")
+        .with_status(500)
+        .create();
 
     let backend = Claude::with_url(server.url());
     let res = backend.health_check().await;
@@ -74,12 +98,78 @@ async fn it_fails_health_checks() {
     mock.assert();
 }
 
+#[tokio::test]
+async fn it_fails_health_checks_with_an_invalid_token() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/v1/models?limit=1")
+        .with_status(401)
+        .create();
+
+    let backend = Claude::with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::InvalidToken {
+            backend: BackendName::Claude
+        })
+    );
+    mock.assert();
+}
+
 #[tokio::test]
 async fn it_lists_models() -> Result<()> {
-    let backend = Claude::with_url("https://api.anthropic.com".to_string());
+    let first_page = serde_json::to_string(&ModelListResponse {
+        data: vec![
+            Model {
+                id: "claude-3-5-sonnet-20241022".to_string(),
+            },
+            Model {
+                id: "claude-3-5-haiku-20241022".to_string(),
+            },
+        ],
+        has_more: true,
+        last_id: Some("claude-3-5-haiku-20241022".to_string()),
+    })?;
+    let second_page = serde_json::to_string(&ModelListResponse {
+        data: vec![Model {
+            id: "claude-3-opus-20240229".to_string(),
+        }],
+        has_more: false,
+        last_id: Some("claude-3-opus-20240229".to_string()),
+    })?;
+
+    let mut server = mockito::Server::new();
+    let first_mock = server
+        .mock("GET", "/v1/models?limit=1000")
+        .match_header("x-api-key", "abc")
+        .with_status(200)
+        .with_body(first_page)
+        .create();
+    let second_mock = server
+        .mock(
+            "GET",
+            "/v1/models?limit=1000&after_id=claude-3-5-haiku-20241022",
+        )
+        .with_status(200)
+        .with_body(second_page)
+        .create();
+
+    let backend = Claude::with_url(server.url());
     let res = backend.list_models().await?;
 
-    assert!(!res.is_empty());
+    first_mock.assert();
+    second_mock.assert();
+    assert_eq!(
+        res,
+        vec![
+            "claude-3-5-sonnet-20241022".to_string(),
+            "claude-3-5-haiku-20241022".to_string(),
+            "claude-3-opus-20240229".to_string(),
+        ]
+    );
+
     return Ok(());
 }
 
@@ -114,8 +204,13 @@ async fn it_gets_completions() -> Result<()> {
         text: "Say hi to the world".to_string(),
         backend_context: serde_json::to_string(&vec![MessageRequest {
             role: "assistant".to_string(),
-            content: "How may I help you?".to_string(),
+            content: MessageContent::Text("How may I help you?".to_string()),
         }])?,
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
     };
 
     let mut server = mockito::Server::new();
@@ -159,3 +254,64 @@ async fn it_gets_completions() -> Result<()> {
 
     return Ok(());
 }
+
+#[tokio::test]
+async fn it_sends_images_ahead_of_the_prompt() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/messages")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "messages": [{
+                "role": "user",
+                "content": [
+                    {
+                        "type": "image",
+                        "source": { "type": "base64", "media_type": "image/png", "data": "iVBORw0KGgo=" },
+                    },
+                    { "type": "text", "text": "What's this?" },
+                ],
+            }],
+        })))
+        .with_status(200)
+        .with_body("")
+        .create();
+
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Claude::with_url(server.url());
+    let mut prompt = BackendPrompt::new("What's this?".to_string(), "".to_string());
+    prompt.images = vec![ImageAttachment::from_bytes(
+        "screenshot.png",
+        b"\x89PNG\r\n\x1a\n",
+    )?];
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_sends_the_system_prompt_apart_from_the_messages() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/messages")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "system": "Answer tersely",
+            "messages": [{ "role": "user", "content": "Say hi to the world" }],
+        })))
+        .with_status(200)
+        .with_body("")
+        .create();
+
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Claude::with_url(server.url());
+    let mut prompt = BackendPrompt::new("Say hi to the world".to_string(), "".to_string());
+    prompt.system_prompt = Some("Answer tersely".to_string());
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    return Ok(());
+}
@@ -0,0 +1,308 @@
+#[cfg(test)]
+#[path = "cohere_test.rs"]
+mod tests;
+
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::TryStreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::mpsc;
+use tokio_util::io::StreamReader;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::ConfigError;
+use crate::domain::models::Event;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::StreamInterrupted;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::SendAuthorized;
+
+fn convert_err(err: reqwest::Error) -> std::io::Error {
+    let err_msg = err.to_string();
+    return std::io::Error::new(std::io::ErrorKind::Interrupted, err_msg);
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Model {
+    name: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ModelListResponse {
+    models: Vec<Model>,
+}
+
+/// An earlier turn of the conversation. Cohere names the roles `USER` and
+/// `CHATBOT`, and keeps the text under `message`.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ChatMessage {
+    role: String,
+    message: String,
+}
+
+/// Unlike OpenAI's `messages`, the new prompt is sent on its own with the
+/// turns before it in `chat_history`.
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CompletionRequest {
+    model: String,
+    message: String,
+    chat_history: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    preamble: Option<String>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CompletionResponse {
+    event_type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finish_reason: Option<String>,
+}
+
+pub struct Cohere {
+    url: String,
+    token: String,
+    timeout: String,
+}
+
+impl Default for Cohere {
+    fn default() -> Cohere {
+        return Cohere {
+            url: Config::get(ConfigKey::CohereURL),
+            token: Config::get(ConfigKey::CohereToken),
+            timeout: Config::get(ConfigKey::BackendHealthCheckTimeout),
+        };
+    }
+}
+
+#[async_trait]
+impl Backend for Cohere {
+    fn name(&self) -> BackendName {
+        return BackendName::Cohere;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        if self.url.is_empty() {
+            bail!(ConfigError::Missing {
+                key: ConfigKey::CohereURL
+            });
+        }
+        if self.token.is_empty() {
+            bail!(ConfigError::Missing {
+                key: ConfigKey::CohereToken
+            });
+        }
+
+        let res = http_client()?
+            .get(format!("{url}/v1/models", url = self.url))
+            .query(&[("endpoint", "chat")])
+            .header("Authorization", format!("Bearer {}", self.token))
+            .timeout(Duration::from_millis(self.timeout.parse::<u64>()?))
+            .send_authorized(BackendName::Cohere)
+            .await;
+
+        if res.is_err() {
+            tracing::error!(error = ?res.unwrap_err(), "Cohere is not reachable");
+            bail!(BackendError::Unreachable {
+                backend: BackendName::Cohere,
+                url: self.url.to_string(),
+            });
+        }
+
+        let status = res.unwrap().status().as_u16();
+        if status >= 400 {
+            tracing::error!(status = status, "Cohere health check failed");
+            bail!(BackendError::from_status(BackendName::Cohere, status, ""));
+        }
+
+        return Ok(());
+    }
+
+    /// Only models that work with the chat endpoint are listed.
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let res = http_client()?
+            .get(format!("{url}/v1/models", url = self.url))
+            .query(&[("endpoint", "chat")])
+            .header("Authorization", format!("Bearer {}", self.token))
+            .send_authorized(BackendName::Cohere)
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to list Cohere models"
+            );
+            bail!(BackendError::from_status(
+                BackendName::Cohere,
+                res.status().as_u16(),
+                ""
+            ));
+        }
+
+        let mut models: Vec<String> = res
+            .json::<ModelListResponse>()
+            .await?
+            .models
+            .iter()
+            .map(|model| {
+                return model.name.to_string();
+            })
+            .collect();
+
+        models.sort();
+
+        return Ok(models);
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        let model = prompt.model();
+        let mut chat_history: Vec<ChatMessage> = vec![];
+        if !prompt.backend_context.is_empty() {
+            chat_history = serde_json::from_str(&prompt.backend_context)?;
+        }
+
+        // Cohere takes a single preamble, so the system prompt follows the
+        // configured one.
+        let preamble = [
+            Some(Config::get(ConfigKey::CoherePreamble)),
+            prompt.system_prompt,
+        ]
+        .into_iter()
+        .flatten()
+        .filter(|e| return !e.is_empty())
+        .collect::<Vec<String>>()
+        .join("\n\n");
+
+        let sampling = SamplingParams::from_config();
+        let req = CompletionRequest {
+            model: model.to_string(),
+            message: prompt.text.to_string(),
+            chat_history: chat_history.clone(),
+            preamble: Some(preamble).filter(|e| return !e.is_empty()),
+            stream: true,
+            seed: Config::get(ConfigKey::Seed).parse::<u64>().ok(),
+            temperature: sampling.temperature,
+            p: sampling.top_p,
+            max_tokens: sampling.max_tokens,
+            stop_sequences: sampling.stop_sequences(),
+        };
+
+        let res = http_client()?
+            .post(format!("{url}/v1/chat", url = self.url))
+            .header("Authorization", format!("Bearer {}", self.token))
+            .json(&req)
+            .send_authorized(BackendName::Cohere)
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to make completion request to Cohere"
+            );
+            bail!(BackendError::from_status(
+                BackendName::Cohere,
+                res.status().as_u16(),
+                &model
+            ));
+        }
+
+        // Events are streamed as one JSON object per line, without SSE's
+        // `data:` prefix.
+        let stream = res.bytes_stream().map_err(convert_err);
+        let mut lines_reader = StreamReader::new(stream).lines();
+
+        let mut last_message = "".to_string();
+        let mut truncated = false;
+        loop {
+            let line = match lines_reader.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(_) => {
+                    bail!(StreamInterrupted { text: last_message });
+                }
+            };
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let ores: CompletionResponse = serde_json::from_str(&line)?;
+            tracing::debug!(body = ?ores, "Completion response");
+
+            if ores.event_type == "stream-end" {
+                let finish_reason = ores.finish_reason.unwrap_or_default();
+                if finish_reason == "ERROR" || finish_reason == "ERROR_TOXIC" {
+                    bail!("Cohere failed to complete the answer: {finish_reason}");
+                }
+                truncated = finish_reason == "MAX_TOKENS";
+                break;
+            }
+            if ores.event_type != "text-generation" || ores.text.is_empty() {
+                continue;
+            }
+
+            last_message += &ores.text;
+            let msg = BackendResponse {
+                author: Author::Model,
+                text: ores.text,
+                done: false,
+                context: None,
+            };
+
+            tx.send(Event::BackendPromptResponse(msg))?;
+        }
+
+        chat_history.push(ChatMessage {
+            role: "USER".to_string(),
+            message: prompt.text,
+        });
+        chat_history.push(ChatMessage {
+            role: "CHATBOT".to_string(),
+            message: last_message.to_string(),
+        });
+
+        let msg = BackendResponse {
+            author: Author::Model,
+            text: "".to_string(),
+            done: true,
+            context: Some(serde_json::to_string(&chat_history)?),
+        };
+        tx.send(Event::BackendPromptResponse(msg))?;
+        if truncated {
+            tx.send(Event::BackendOutputTruncated())?;
+        }
+
+        return Ok(());
+    }
+}
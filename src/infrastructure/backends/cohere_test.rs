@@ -0,0 +1,287 @@
+use anyhow::bail;
+use anyhow::Result;
+use mockito::Matcher;
+use test_utils::insta_snapshot;
+use tokio::sync::mpsc;
+
+use super::ChatMessage;
+use super::Cohere;
+use super::CompletionResponse;
+use super::Model;
+use super::ModelListResponse;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Event;
+
+impl Cohere {
+    fn with_url(url: String) -> Cohere {
+        return Cohere {
+            url,
+            token: "abc".to_string(),
+            timeout: "200".to_string(),
+        };
+    }
+}
+
+fn to_res(action: Option<Event>) -> Result<BackendResponse> {
+    let act = match action.unwrap() {
+        Event::BackendPromptResponse(res) => res,
+        _ => bail!("Wrong type from recv"),
+    };
+
+    return Ok(act);
+}
+
+fn stream_line(event_type: &str, text: &str, finish_reason: Option<&str>) -> Result<String> {
+    let res = serde_json::to_string(&CompletionResponse {
+        event_type: event_type.to_string(),
+        text: text.to_string(),
+        finish_reason: finish_reason.map(|e| return e.to_string()),
+    })?;
+
+    return Ok(format!("{res}\n"));
+}
+
+#[tokio::test]
+async fn it_successfully_health_checks() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/v1/models")
+        .match_query(Matcher::UrlEncoded(
+            "endpoint".to_string(),
+            "chat".to_string(),
+        ))
+        .match_header("Authorization", "Bearer abc")
+        .with_status(200)
+        .create();
+
+    let backend = Cohere::with_url(server.url());
+    let res = backend.health_check().await;
+
+    assert!(res.is_ok());
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_fails_health_checks_with_an_invalid_token() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/v1/models")
+        .match_query(Matcher::Any)
+        .with_status(401)
+        .create();
+
+    let backend = Cohere::with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::InvalidToken {
+            backend: BackendName::Cohere
+        })
+    );
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_lists_models() -> Result<()> {
+    let body = serde_json::to_string(&ModelListResponse {
+        models: vec![
+            Model {
+                name: "command-r-plus".to_string(),
+            },
+            Model {
+                name: "command-light".to_string(),
+            },
+        ],
+    })?;
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/v1/models")
+        .match_query(Matcher::Any)
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let backend = Cohere::with_url(server.url());
+    let res = backend.list_models().await?;
+    mock.assert();
+
+    assert_eq!(
+        res,
+        vec!["command-light".to_string(), "command-r-plus".to_string()]
+    );
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_gets_completions() -> Result<()> {
+    let body = [
+        stream_line("stream-start", "", None)?,
+        stream_line("text-generation", "Hello ", None)?,
+        stream_line("text-generation", "World", None)?,
+        stream_line("stream-end", "", Some("COMPLETE"))?,
+    ]
+    .join("");
+    let prompt = BackendPrompt {
+        text: "Say hi to the world".to_string(),
+        backend_context: serde_json::to_string(&vec![ChatMessage {
+            role: "CHATBOT".to_string(),
+            message: "How may I help you?".to_string(),
+        }])?,
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
+    };
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat")
+        .match_header("Authorization", "Bearer abc")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "message": "Say hi to the world",
+            "chat_history": [{ "role": "CHATBOT", "message": "How may I help you?" }],
+            "stream": true,
+        })))
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Cohere::with_url(server.url());
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    let first_recv = to_res(rx.recv().await)?;
+    let second_recv = to_res(rx.recv().await)?;
+    let third_recv = to_res(rx.recv().await)?;
+
+    assert_eq!(first_recv.author, Author::Model);
+    assert_eq!(first_recv.text, "Hello ".to_string());
+    assert!(!first_recv.done);
+
+    assert_eq!(second_recv.text, "World".to_string());
+    assert!(!second_recv.done);
+
+    assert!(third_recv.text.is_empty());
+    assert!(third_recv.done);
+    insta_snapshot(|| {
+        insta::assert_toml_snapshot!(third_recv.context);
+    });
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_reports_truncated_completions() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat")
+        .with_status(200)
+        .with_body(
+            [
+                stream_line("text-generation", "Hello", None)?,
+                stream_line("stream-end", "", Some("MAX_TOKENS"))?,
+            ]
+            .join(""),
+        )
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Cohere::with_url(server.url());
+    let prompt = BackendPrompt {
+        text: "Say hi to the world".to_string(),
+        backend_context: "".to_string(),
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
+    };
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    assert_eq!(to_res(rx.recv().await)?.text, "Hello");
+    assert!(to_res(rx.recv().await)?.done);
+    assert!(matches!(
+        rx.recv().await,
+        Some(Event::BackendOutputTruncated())
+    ));
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_sends_sampling_params() -> Result<()> {
+    Config::set(ConfigKey::TopP, "0.9");
+    Config::set(ConfigKey::StopSequences, "END,###");
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat")
+        .match_body(Matcher::PartialJson(
+            serde_json::json!({ "p": 0.9, "stop_sequences": ["END", "###"] }),
+        ))
+        .with_status(200)
+        .with_body(stream_line("stream-end", "", Some("COMPLETE"))?)
+        .create();
+
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Cohere::with_url(server.url());
+    let prompt = BackendPrompt {
+        text: "Say hi to the world".to_string(),
+        backend_context: "".to_string(),
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
+    };
+    backend.get_completion(prompt, &tx).await?;
+    Config::set(ConfigKey::TopP, "");
+    Config::set(ConfigKey::StopSequences, "");
+
+    mock.assert();
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_sends_the_system_prompt_after_the_preamble() -> Result<()> {
+    Config::set(ConfigKey::CoherePreamble, "You are Oatmeal.");
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat")
+        .match_body(Matcher::PartialJson(
+            serde_json::json!({ "preamble": "You are Oatmeal.\n\nAnswer tersely" }),
+        ))
+        .with_status(200)
+        .with_body(stream_line("stream-end", "", Some("COMPLETE"))?)
+        .create();
+
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Cohere::with_url(server.url());
+    let mut prompt = BackendPrompt::new("Say hi to the world".to_string(), "".to_string());
+    prompt.system_prompt = Some("Answer tersely".to_string());
+    backend.get_completion(prompt, &tx).await?;
+    Config::set(ConfigKey::CoherePreamble, "");
+
+    mock.assert();
+
+    return Ok(());
+}
@@ -18,10 +18,16 @@ use crate::configuration::Config;
 use crate::configuration::ConfigKey;
 use crate::domain::models::Author;
 use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
 use crate::domain::models::BackendName;
 use crate::domain::models::BackendPrompt;
 use crate::domain::models::BackendResponse;
+use crate::domain::models::ConfigError;
 use crate::domain::models::Event;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::StreamInterrupted;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::SendAuthorized;
 
 fn convert_err(err: reqwest::Error) -> std::io::Error {
     let err_msg = err.to_string();
@@ -60,15 +66,63 @@ struct Content {
     parts: Vec<ContentParts>,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+}
+
+/// Like `Content`, but without a role.
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SystemInstruction {
+    parts: Vec<ContentParts>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct CompletionRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<SystemInstruction>,
     contents: Vec<Content>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation_config: Option<GenerationConfig>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CandidateContent {
+    #[serde(default)]
+    parts: Vec<ContentParts>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: CandidateContent,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    finish_reason: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct GenerateContentResponse {
-    text: String,
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+}
+
+/// Gemini names models such as `models/gemini-1.5-pro`, though they're shown
+/// and can be picked without the prefix.
+fn model_path(model: &str) -> String {
+    if model.starts_with("models/") || model.starts_with("tunedModels/") {
+        return model.to_string();
+    }
+    return format!("models/{model}");
 }
 
 pub struct Gemini {
@@ -99,31 +153,36 @@ impl Backend for Gemini {
             bail!("Gemini URL is not defined");
         }
         if self.token.is_empty() {
-            bail!("Gemini token is not defined");
+            bail!(ConfigError::Missing {
+                key: ConfigKey::GeminiToken
+            });
         }
 
         let url = format!(
             "{url}/v1beta/{model}?key={key}",
             url = self.url,
-            model = Config::get(ConfigKey::Model),
+            model = model_path(&Config::get(ConfigKey::Model)),
             key = self.token
         );
 
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .get(&url)
             .timeout(Duration::from_millis(self.timeout.parse::<u64>()?))
-            .send()
+            .send_authorized(BackendName::Gemini)
             .await;
 
         if res.is_err() {
             tracing::error!(error = ?res.unwrap_err(), "Gemini is not reachable");
-            bail!("Gemini is not reachable");
+            bail!(BackendError::Unreachable {
+                backend: BackendName::Gemini,
+                url: self.url.to_string(),
+            });
         }
 
         let status = res.unwrap().status().as_u16();
         if status >= 400 {
             tracing::error!(status = status, "Gemini health check failed");
-            bail!("Gemini health check failed");
+            bail!(BackendError::from_status(BackendName::Gemini, status, ""));
         }
 
         return Ok(());
@@ -131,13 +190,13 @@ impl Backend for Gemini {
 
     #[allow(clippy::implicit_return)]
     async fn list_models(&self) -> Result<Vec<String>> {
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .get(format!(
                 "{url}/v1beta/models?key={key}",
                 url = self.url,
                 key = self.token
             ))
-            .send()
+            .send_authorized(BackendName::Gemini)
             .await?
             .json::<ModelListResponse>()
             .await?;
@@ -151,7 +210,11 @@ impl Backend for Gemini {
                     .contains(&"generateContent".to_string())
             })
             .map(|model| {
-                return model.name.to_string();
+                return model
+                    .name
+                    .strip_prefix("models/")
+                    .unwrap_or(&model.name)
+                    .to_string();
             })
             .collect();
 
@@ -166,28 +229,50 @@ impl Backend for Gemini {
         prompt: BackendPrompt,
         tx: &'a mpsc::UnboundedSender<Event>,
     ) -> Result<()> {
+        let model = prompt.model();
         let mut contents: Vec<Content> = vec![];
         if !prompt.backend_context.is_empty() {
             contents = serde_json::from_str(&prompt.backend_context)?;
         }
         contents.push(Content {
             role: "user".to_string(),
-            parts: vec![ContentParts::Text(prompt.text)],
+            parts: [ContentParts::Text(prompt.text)]
+                .into_iter()
+                .chain(prompt.images.iter().map(|e| {
+                    return ContentParts::InlineData(ContentPartsBlob {
+                        mime_type: e.media_type.to_string(),
+                        data: e.data.to_string(),
+                    });
+                }))
+                .collect(),
         });
 
+        let sampling = SamplingParams::from_config();
         let req = CompletionRequest {
+            system_instruction: prompt.system_prompt.map(|e| {
+                return SystemInstruction {
+                    parts: vec![ContentParts::Text(e)],
+                };
+            }),
             contents: contents.clone(),
+            generation_config: Some(GenerationConfig {
+                temperature: sampling.temperature,
+                top_p: sampling.top_p,
+                max_output_tokens: sampling.max_tokens,
+                stop_sequences: sampling.stop_sequences(),
+            })
+            .filter(|e| return e != &GenerationConfig::default()),
         };
 
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .post(format!(
-                "{url}/v1beta/{model}:streamGenerateContent?key={key}",
+                "{url}/v1beta/{model}:streamGenerateContent?alt=sse&key={key}",
                 url = self.url,
-                model = Config::get(ConfigKey::Model),
+                model = model_path(&model),
                 key = self.token,
             ))
             .json(&req)
-            .send()
+            .send_authorized(BackendName::Gemini)
             .await?;
 
         if !res.status().is_success() {
@@ -195,40 +280,63 @@ impl Backend for Gemini {
                 status = res.status().as_u16(),
                 "Failed to make completion request to Gemini"
             );
-            bail!(format!(
-                "Failed to make completion request to Gemini, {}",
-                res.status().as_u16()
+            bail!(BackendError::from_status(
+                BackendName::Gemini,
+                res.status().as_u16(),
+                &model
             ));
         }
         let stream = res.bytes_stream().map_err(convert_err);
         let mut lines_reader = StreamReader::new(stream).lines();
 
+        // Server sent events hold one response per line, each with the next
+        // part of the answer.
         let mut last_message = "".to_string();
-        while let Ok(line) = lines_reader.next_line().await {
-            if line.is_none() {
-                break;
-            }
+        let mut truncated = false;
+        loop {
+            let line = match lines_reader.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(_) => {
+                    bail!(StreamInterrupted { text: last_message });
+                }
+            };
 
-            let cleaned_line = line.unwrap().trim().to_string();
-            if !cleaned_line.starts_with("\"text\":") {
+            let cleaned_line = match line.trim().strip_prefix("data:") {
+                Some(data) => data.trim().to_string(),
+                None => continue,
+            };
+            if cleaned_line.is_empty() {
                 continue;
             }
 
-            let ores: GenerateContentResponse =
-                serde_json::from_str(&format!("{{ {text} }}", text = cleaned_line)).unwrap();
-
-            if ores.text.is_empty() || ores.text.is_empty() || ores.text == "\n" {
-                break;
+            let ores: GenerateContentResponse = serde_json::from_str(&cleaned_line)?;
+            tracing::debug!(body = ?ores, "Completion response");
+
+            for candidate in ores.candidates.iter().take(1) {
+                if candidate.finish_reason.as_deref() == Some("MAX_TOKENS") {
+                    truncated = true;
+                }
+
+                for part in &candidate.content.parts {
+                    let text = match part {
+                        ContentParts::Text(text) => text.to_string(),
+                        ContentParts::InlineData(_) => continue,
+                    };
+                    if text.is_empty() {
+                        continue;
+                    }
+
+                    last_message += &text;
+                    let msg = BackendResponse {
+                        author: Author::Model,
+                        text,
+                        done: false,
+                        context: None,
+                    };
+                    tx.send(Event::BackendPromptResponse(msg))?;
+                }
             }
-
-            last_message += &ores.text;
-            let msg = BackendResponse {
-                author: Author::Model,
-                text: ores.text,
-                done: false,
-                context: None,
-            };
-            tx.send(Event::BackendPromptResponse(msg))?;
         }
 
         contents.push(Content {
@@ -243,6 +351,9 @@ impl Backend for Gemini {
             context: Some(serde_json::to_string(&contents)?),
         };
         tx.send(Event::BackendPromptResponse(msg))?;
+        if truncated {
+            tx.send(Event::BackendOutputTruncated())?;
+        }
 
         return Ok(());
     }
@@ -3,10 +3,14 @@ use anyhow::Result;
 use test_utils::insta_snapshot;
 use tokio::sync::mpsc;
 
+use super::model_path;
+use super::Candidate;
+use super::CandidateContent;
 use super::Config;
 use super::Content;
 use super::ContentParts;
 use super::Gemini;
+use super::GenerateContentResponse;
 use super::Model;
 use super::ModelListResponse;
 use crate::configuration::ConfigKey;
@@ -35,12 +39,32 @@ fn to_res(action: Option<Event>) -> Result<BackendResponse> {
     return Ok(act);
 }
 
+fn stream_line(text: &str, finish_reason: Option<&str>) -> Result<String> {
+    let res = GenerateContentResponse {
+        candidates: vec![Candidate {
+            content: CandidateContent {
+                parts: vec![ContentParts::Text(text.to_string())],
+            },
+            finish_reason: finish_reason.map(|e| return e.to_string()),
+        }],
+    };
+
+    return Ok(format!("data: {}", serde_json::to_string(&res)?));
+}
+
+#[test]
+fn it_prefixes_model_paths() {
+    assert_eq!(model_path("gemini-1.5-pro"), "models/gemini-1.5-pro");
+    assert_eq!(model_path("models/gemini-1.5-pro"), "models/gemini-1.5-pro");
+    assert_eq!(model_path("tunedModels/mine"), "tunedModels/mine");
+}
+
 #[tokio::test]
 async fn it_successfully_health_checks() {
     Config::set(ConfigKey::Model, "model-1");
     let mut server = mockito::Server::new();
     let mock = server
-        .mock("GET", "/v1beta/model-1?key=abc")
+        .mock("GET", "/v1beta/models/model-1?key=abc")
         .with_status(200)
         .create();
 
@@ -53,7 +77,7 @@ async fn it_successfully_health_checks() {
 
 #[tokio::test]
 async fn it_successfully_health_checks_with_official_api() {
-    Config::set(ConfigKey::Model, "models/gemini-pro");
+    Config::set(ConfigKey::Model, "gemini-1.5-flash");
     let token = match std::env::var("OATMEAL_GEMINI_TOKEN") {
         Ok(token) => token,
         Err(_) => {
@@ -76,7 +100,7 @@ async fn it_fails_health_checks() {
     Config::set(ConfigKey::Model, "model-1");
     let mut server = mockito::Server::new();
     let mock = server
-        .mock("GET", "/v1beta/model-1?key=abc")
+        .mock("GET", "/v1beta/models/model-1?key=abc")
         .with_status(500)
         .create();
 
@@ -96,9 +120,13 @@ async fn it_lists_models() -> Result<()> {
                 supported_generation_methods: vec!["generateContent".to_string()],
             },
             Model {
-                name: "second".to_string(),
+                name: "models/second".to_string(),
                 supported_generation_methods: vec!["generateContent".to_string()],
             },
+            Model {
+                name: "models/embedding".to_string(),
+                supported_generation_methods: vec!["embedContent".to_string()],
+            },
         ],
     })?;
 
@@ -121,44 +149,31 @@ async fn it_lists_models() -> Result<()> {
 #[tokio::test]
 async fn it_gets_completions() -> Result<()> {
     Config::set(ConfigKey::Model, "model-1");
-    let body = r#"
-{
-  "contents": [
-    {
-      "parts": [
-        {
-          "text": "Hello "
-        }
-      ]
-    },
-    {
-      "parts": [
-        {
-          "text": "World"
-        }
-      ]
-    },
-    {
-      "parts": [
-        {
-          "text": ""
-        }
-      ]
-    }
-  ]
-}
-        "#;
+    let body = [
+        stream_line("Hello ", None)?,
+        stream_line("World", None)?,
+        stream_line("", Some("STOP"))?,
+    ]
+    .join("\n\n");
     let prompt = BackendPrompt {
         text: "Say hi to the world".to_string(),
         backend_context: serde_json::to_string(&vec![Content {
             role: "model".to_string(),
             parts: vec![ContentParts::Text("Hello".to_string())],
         }])?,
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
     };
 
     let mut server = mockito::Server::new();
     let mock = server
-        .mock("POST", "/v1beta/model-1:streamGenerateContent?key=abc")
+        .mock(
+            "POST",
+            "/v1beta/models/model-1:streamGenerateContent?alt=sse&key=abc",
+        )
         .with_status(200)
         .with_body(body)
         .create();
@@ -193,3 +208,46 @@ async fn it_gets_completions() -> Result<()> {
 
     return Ok(());
 }
+
+#[tokio::test]
+async fn it_keeps_streaming_after_newlines_and_reports_truncation() -> Result<()> {
+    Config::set(ConfigKey::Model, "model-2");
+    let body = [
+        stream_line("One\n", None)?,
+        stream_line("\n", None)?,
+        stream_line("Two", Some("MAX_TOKENS"))?,
+    ]
+    .join("\n\n");
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock(
+            "POST",
+            "/v1beta/models/model-2:streamGenerateContent?alt=sse&key=abc",
+        )
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    let backend = Gemini::with_url(server.url());
+    backend
+        .get_completion(BackendPrompt::new("Count".to_string(), "".to_string()), &tx)
+        .await?;
+    mock.assert();
+
+    let mut text = "".to_string();
+    let mut truncated = false;
+    while let Ok(event) = rx.try_recv() {
+        match event {
+            Event::BackendPromptResponse(msg) => text += &msg.text,
+            Event::BackendOutputTruncated() => truncated = true,
+            _ => bail!("Wrong type from recv"),
+        }
+    }
+
+    assert_eq!(text, "One\n\nTwo");
+    assert!(truncated);
+
+    return Ok(());
+}
@@ -21,10 +21,14 @@ use crate::configuration::Config;
 use crate::configuration::ConfigKey;
 use crate::domain::models::Author;
 use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
 use crate::domain::models::BackendName;
 use crate::domain::models::BackendPrompt;
 use crate::domain::models::BackendResponse;
+use crate::domain::models::ConfigError;
 use crate::domain::models::Event;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::SendAuthorized;
 
 fn convert_err(err: reqwest::Error) -> std::io::Error {
     let err_msg = err.to_string();
@@ -74,24 +78,33 @@ impl Backend for LangChain {
     #[allow(clippy::implicit_return)]
     async fn health_check(&self) -> Result<()> {
         if self.url.is_empty() {
-            bail!("LangChain URL is not defined");
+            bail!(ConfigError::Missing {
+                key: ConfigKey::LangChainURL
+            });
         }
 
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .get(format!("{url}/openapi.json", url = self.url))
             .timeout(Duration::from_millis(self.timeout.parse::<u64>()?))
-            .send()
+            .send_authorized(BackendName::LangChain)
             .await;
 
         if res.is_err() {
             tracing::error!(error = ?res.unwrap_err(), "LangChain is not reachable");
-            bail!("LangChain is not reachable");
+            bail!(BackendError::Unreachable {
+                backend: BackendName::LangChain,
+                url: self.url.to_string(),
+            });
         }
 
         let status = res.unwrap().status().as_u16();
         if status >= 400 {
             tracing::error!(status = status, "LangChain health check failed");
-            bail!("LangChain health check failed");
+            bail!(BackendError::from_status(
+                BackendName::LangChain,
+                status,
+                ""
+            ));
         }
 
         return Ok(());
@@ -99,9 +112,9 @@ impl Backend for LangChain {
 
     #[allow(clippy::implicit_return)]
     async fn list_models(&self) -> Result<Vec<String>> {
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .get(format!("{url}/openapi.json", url = self.url))
-            .send()
+            .send_authorized(BackendName::LangChain)
             .await?
             .json::<OpenAPIJSONResponse>()
             .await?;
@@ -128,23 +141,22 @@ impl Backend for LangChain {
     #[allow(clippy::implicit_return)]
     async fn get_completion<'a>(
         &self,
-        prompt: BackendPrompt,
+        mut prompt: BackendPrompt,
         tx: &'a mpsc::UnboundedSender<Event>,
     ) -> Result<()> {
+        // Chains only take a question.
+        prompt.inline_system_prompt();
+        let model = prompt.model();
         let mut input = HashMap::new();
         // TODO consider making the key configurable.
         input.insert("question".to_string(), prompt.text);
 
         let req = CompletionRequest { input };
 
-        let res = reqwest::Client::new()
-            .post(format!(
-                "{url}/{model}/stream",
-                url = self.url,
-                model = Config::get(ConfigKey::Model)
-            ))
+        let res = http_client()?
+            .post(format!("{url}/{model}/stream", url = self.url,))
             .json(&req)
-            .send()
+            .send_authorized(BackendName::LangChain)
             .await?;
 
         if !res.status().is_success() {
@@ -152,7 +164,11 @@ impl Backend for LangChain {
                 status = res.status().as_u16(),
                 "Failed to make completion request to LangChain"
             );
-            bail!("Failed to make completion request to LangChain");
+            bail!(BackendError::from_status(
+                BackendName::LangChain,
+                res.status().as_u16(),
+                &model
+            ));
         }
 
         let stream = res.bytes_stream().map_err(convert_err);
@@ -117,6 +117,11 @@ async fn it_gets_completions() -> Result<()> {
     let prompt = BackendPrompt {
         text: "Say hi to the world".to_string(),
         backend_context: "".to_string(),
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
     };
 
     let mut server = mockito::Server::new();
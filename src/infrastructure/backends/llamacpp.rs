@@ -0,0 +1,505 @@
+#[cfg(test)]
+#[path = "llamacpp_test.rs"]
+mod tests;
+
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::TryStreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use strum::EnumIter;
+use strum::EnumVariantNames;
+use strum::IntoEnumIterator;
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::mpsc;
+use tokio_util::io::StreamReader;
+
+use super::openai_compatible::MessageRequest;
+use super::openai_compatible::ModelListResponse;
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Event;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::StreamInterrupted;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::SendAuthorized;
+
+fn convert_err(err: reqwest::Error) -> std::io::Error {
+    let err_msg = err.to_string();
+    return std::io::Error::new(std::io::ErrorKind::Interrupted, err_msg);
+}
+
+/// Stops a raw completion before it writes the user's next turn.
+const TRANSCRIPT_STOP: &str = "\nUser:";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, EnumIter, EnumVariantNames, strum::Display)]
+#[strum(serialize_all = "lowercase")]
+pub enum LlamaCppEndpoint {
+    /// `/v1/chat/completions`, applying the model's chat template.
+    Chat,
+    /// The native `/completion`, continuing a plain text transcript, for
+    /// models without a chat template.
+    Completion,
+}
+
+impl LlamaCppEndpoint {
+    pub fn parse(text: &str) -> Option<LlamaCppEndpoint> {
+        return LlamaCppEndpoint::iter().find(|e| return e.to_string() == text);
+    }
+}
+
+/// What's kept in `backend_context` between prompts. Sending the whole
+/// conversation again to the same slot lets the server reuse its cache
+/// instead of processing it from scratch.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Context {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    messages: Vec<MessageRequest>,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    prompt: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id_slot: Option<i64>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<MessageRequest>,
+    stream: bool,
+    cache_prompt: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_slot: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ChatDeltaResponse {
+    content: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ChatChoiceResponse {
+    delta: ChatDeltaResponse,
+    finish_reason: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoiceResponse>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id_slot: Option<i64>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CompletionRequest {
+    prompt: String,
+    stream: bool,
+    cache_prompt: bool,
+    stop: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id_slot: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n_predict: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CompletionResponse {
+    content: String,
+    stop: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id_slot: Option<i64>,
+    /// Set when generation hit `n_predict` rather than finishing.
+    #[serde(default)]
+    stopped_limit: bool,
+}
+
+/// Strips the `data:` prefix from a server-sent event, returning `None` for
+/// lines without a payload.
+fn event_data(line: &str) -> Option<String> {
+    let mut cleaned_line = line.trim().to_string();
+    if cleaned_line.starts_with("data:") {
+        cleaned_line = cleaned_line.split_off(5).trim().to_string();
+    }
+    if cleaned_line.is_empty() {
+        return None;
+    }
+
+    return Some(cleaned_line);
+}
+
+/// llama.cpp's `llama-server`, serving the single model it was started with.
+pub struct LlamaCpp {
+    url: String,
+    token: String,
+    endpoint: String,
+    timeout: String,
+}
+
+impl Default for LlamaCpp {
+    fn default() -> LlamaCpp {
+        return LlamaCpp {
+            url: Config::get(ConfigKey::LlamaCppURL),
+            token: Config::get(ConfigKey::LlamaCppToken),
+            endpoint: Config::get(ConfigKey::LlamaCppEndpoint),
+            timeout: Config::get(ConfigKey::BackendHealthCheckTimeout),
+        };
+    }
+}
+
+impl LlamaCpp {
+    fn authorize(&self, builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if self.token.is_empty() {
+            return builder;
+        }
+
+        return builder.header("Authorization", format!("Bearer {}", self.token));
+    }
+
+    async fn chat(
+        &self,
+        prompt: BackendPrompt,
+        mut context: Context,
+        tx: &mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        let model = prompt.model();
+        context.messages.push(MessageRequest {
+            role: "user".to_string(),
+            content: prompt.text,
+        });
+
+        // The system prompt is sent with every request, so it's left out of
+        // the context.
+        let mut messages = vec![];
+        if let Some(system_prompt) = &prompt.system_prompt {
+            messages.push(MessageRequest {
+                role: "system".to_string(),
+                content: system_prompt.to_string(),
+            });
+        }
+        messages.extend(context.messages.iter().cloned());
+
+        let sampling = SamplingParams::from_config();
+        let req = ChatRequest {
+            model,
+            messages,
+            stream: true,
+            cache_prompt: true,
+            id_slot: context.id_slot,
+            seed: Config::get(ConfigKey::Seed).parse::<u64>().ok(),
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            max_tokens: sampling.max_tokens,
+            stop: sampling.stop_sequences(),
+        };
+
+        let res = self
+            .authorize(http_client()?.post(format!("{url}/v1/chat/completions", url = self.url)))
+            .json(&req)
+            .send_authorized(BackendName::LlamaCpp)
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to make completion request to llama.cpp"
+            );
+            bail!(BackendError::from_status(
+                BackendName::LlamaCpp,
+                res.status().as_u16(),
+                ""
+            ));
+        }
+
+        let stream = res.bytes_stream().map_err(convert_err);
+        let mut lines_reader = StreamReader::new(stream).lines();
+
+        let mut last_message = "".to_string();
+        let mut truncated = false;
+        loop {
+            let line = match lines_reader.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(_) => {
+                    bail!(StreamInterrupted { text: last_message });
+                }
+            };
+
+            let data = match event_data(&line) {
+                Some(data) => data,
+                None => continue,
+            };
+            if data == "[DONE]" {
+                break;
+            }
+
+            let ores: ChatResponse = serde_json::from_str(&data)?;
+            tracing::debug!(body = ?ores, "Completion response");
+            if ores.id_slot.is_some() {
+                context.id_slot = ores.id_slot;
+            }
+
+            let choice = match ores.choices.first() {
+                Some(choice) => choice,
+                None => continue,
+            };
+            if let Some(text) = &choice.delta.content {
+                if !text.is_empty() {
+                    last_message += text;
+                    tx.send(Event::BackendPromptResponse(BackendResponse {
+                        author: Author::Model,
+                        text: text.to_string(),
+                        done: false,
+                        context: None,
+                    }))?;
+                }
+            }
+            if let Some(finish_reason) = &choice.finish_reason {
+                truncated = finish_reason == "length";
+                break;
+            }
+        }
+
+        context.messages.push(MessageRequest {
+            role: "assistant".to_string(),
+            content: last_message,
+        });
+
+        tx.send(Event::BackendPromptResponse(BackendResponse {
+            author: Author::Model,
+            text: "".to_string(),
+            done: true,
+            context: Some(serde_json::to_string(&context)?),
+        }))?;
+        if truncated {
+            tx.send(Event::BackendOutputTruncated())?;
+        }
+
+        return Ok(());
+    }
+
+    async fn complete(
+        &self,
+        prompt: BackendPrompt,
+        mut context: Context,
+        tx: &mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        context.prompt = format!("{}User: {}\nAssistant:", context.prompt, prompt.text);
+
+        let sampling = SamplingParams::from_config();
+        let mut stop = vec![TRANSCRIPT_STOP.to_string()];
+        stop.extend(sampling.stop.iter().cloned());
+        // Raw completions have no system message, so the system prompt
+        // heads the transcript of each request.
+        let text = match &prompt.system_prompt {
+            Some(system_prompt) => format!("{system_prompt}\n\n{}", context.prompt),
+            None => context.prompt.to_string(),
+        };
+        let req = CompletionRequest {
+            prompt: text,
+            stream: true,
+            cache_prompt: true,
+            stop,
+            id_slot: context.id_slot,
+            seed: Config::get(ConfigKey::Seed).parse::<u64>().ok(),
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            n_predict: sampling.max_tokens,
+        };
+
+        let res = self
+            .authorize(http_client()?.post(format!("{url}/completion", url = self.url)))
+            .json(&req)
+            .send_authorized(BackendName::LlamaCpp)
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to make completion request to llama.cpp"
+            );
+            bail!(BackendError::from_status(
+                BackendName::LlamaCpp,
+                res.status().as_u16(),
+                ""
+            ));
+        }
+
+        let stream = res.bytes_stream().map_err(convert_err);
+        let mut lines_reader = StreamReader::new(stream).lines();
+
+        let mut last_message = "".to_string();
+        let truncated = loop {
+            let line = match lines_reader.next_line().await {
+                Ok(Some(line)) => line,
+                // The last event is marked stop, so ending without it means
+                // the connection dropped.
+                Ok(None) | Err(_) => {
+                    bail!(StreamInterrupted { text: last_message });
+                }
+            };
+
+            let data = match event_data(&line) {
+                Some(data) => data,
+                None => continue,
+            };
+
+            let ores: CompletionResponse = serde_json::from_str(&data)?;
+            tracing::debug!(body = ?ores, "Completion response");
+            // The answer starts after the space following `Assistant:`.
+            let text = if last_message.is_empty() {
+                ores.content.trim_start().to_string()
+            } else {
+                ores.content.to_string()
+            };
+            if !text.is_empty() {
+                last_message += &text;
+                tx.send(Event::BackendPromptResponse(BackendResponse {
+                    author: Author::Model,
+                    text,
+                    done: false,
+                    context: None,
+                }))?;
+            }
+
+            if ores.stop {
+                if ores.id_slot.is_some() {
+                    context.id_slot = ores.id_slot;
+                }
+                break ores.stopped_limit;
+            }
+        };
+
+        context.prompt = format!("{} {}\n", context.prompt, last_message.trim_end());
+
+        tx.send(Event::BackendPromptResponse(BackendResponse {
+            author: Author::Model,
+            text: "".to_string(),
+            done: true,
+            context: Some(serde_json::to_string(&context)?),
+        }))?;
+        if truncated {
+            tx.send(Event::BackendOutputTruncated())?;
+        }
+
+        return Ok(());
+    }
+}
+
+#[async_trait]
+impl Backend for LlamaCpp {
+    fn name(&self) -> BackendName {
+        return BackendName::LlamaCpp;
+    }
+
+    /// `/health` answers 503 until the server has loaded its model.
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        let res = self
+            .authorize(http_client()?.get(format!("{url}/health", url = self.url)))
+            .timeout(Duration::from_millis(self.timeout.parse::<u64>()?))
+            .send_authorized(BackendName::LlamaCpp)
+            .await;
+
+        if res.is_err() {
+            tracing::error!(error = ?res.unwrap_err(), "llama.cpp is not running");
+            bail!(BackendError::Unreachable {
+                backend: BackendName::LlamaCpp,
+                url: self.url.to_string(),
+            });
+        }
+
+        let status = res.unwrap().status().as_u16();
+        if status == 503 {
+            bail!("llama.cpp is still loading its model, try again once it's ready");
+        }
+        if status != 200 {
+            tracing::error!(status = status, "llama.cpp health check failed");
+            bail!(BackendError::from_status(BackendName::LlamaCpp, status, ""));
+        }
+
+        if LlamaCppEndpoint::parse(&self.endpoint).is_none() {
+            bail!(format!(
+                "llama-cpp-endpoint must be chat or completion, not '{}'",
+                self.endpoint
+            ));
+        }
+
+        return Ok(());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        let res = self
+            .authorize(http_client()?.get(format!("{url}/v1/models", url = self.url)))
+            .send_authorized(BackendName::LlamaCpp)
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to list llama.cpp models"
+            );
+            bail!(BackendError::from_status(
+                BackendName::LlamaCpp,
+                res.status().as_u16(),
+                ""
+            ));
+        }
+
+        let models = match res.json::<ModelListResponse>().await? {
+            ModelListResponse::Wrapped { data } => data,
+            ModelListResponse::Bare(models) => models,
+        };
+        let mut models: Vec<String> = models
+            .iter()
+            .map(|model| {
+                return model.id.to_string();
+            })
+            .collect();
+
+        models.sort();
+
+        return Ok(models);
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        let mut context = Context::default();
+        if !prompt.backend_context.is_empty() {
+            context = serde_json::from_str(&prompt.backend_context)?;
+        }
+
+        return match LlamaCppEndpoint::parse(&self.endpoint) {
+            Some(LlamaCppEndpoint::Completion) => self.complete(prompt, context, tx).await,
+            _ => self.chat(prompt, context, tx).await,
+        };
+    }
+}
@@ -0,0 +1,163 @@
+use anyhow::bail;
+use anyhow::Result;
+use mockito::Matcher;
+use tokio::sync::mpsc;
+
+use super::LlamaCpp;
+use super::LlamaCppEndpoint;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Event;
+
+impl LlamaCpp {
+    fn with_url(url: String, endpoint: LlamaCppEndpoint) -> LlamaCpp {
+        return LlamaCpp {
+            url,
+            token: "".to_string(),
+            endpoint: endpoint.to_string(),
+            timeout: "200".to_string(),
+        };
+    }
+}
+
+fn to_res(action: Option<Event>) -> Result<BackendResponse> {
+    let act = match action.unwrap() {
+        Event::BackendPromptResponse(res) => res,
+        _ => bail!("Wrong type from recv"),
+    };
+
+    return Ok(act);
+}
+
+#[tokio::test]
+async fn it_successfully_health_checks() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/health")
+        .with_status(200)
+        .with_body(r#"{"status":"ok"}"#)
+        .create();
+
+    let backend = LlamaCpp::with_url(server.url(), LlamaCppEndpoint::Chat);
+    let res = backend.health_check().await;
+
+    assert!(res.is_ok());
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_fails_health_checks_while_loading_the_model() {
+    let mut server = mockito::Server::new();
+    let mock = server.mock("GET", "/health").with_status(503).create();
+
+    let backend = LlamaCpp::with_url(server.url(), LlamaCppEndpoint::Chat);
+    let err = backend.health_check().await.unwrap_err();
+
+    insta::assert_snapshot!(err.to_string(), @"llama.cpp is still loading its model, try again once it's ready");
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_lists_models() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/v1/models")
+        .with_status(200)
+        .with_body(r#"{"object":"list","data":[{"id":"models/llama-3-8b.gguf","object":"model"}]}"#)
+        .create();
+
+    let backend = LlamaCpp::with_url(server.url(), LlamaCppEndpoint::Chat);
+    let res = backend.list_models().await?;
+    mock.assert();
+
+    assert_eq!(res, vec!["models/llama-3-8b.gguf".to_string()]);
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_gets_chat_completions_reusing_the_prompt_cache() -> Result<()> {
+    let body = [
+        r#"data: {"choices":[{"delta":{"content":"Hel"},"finish_reason":null}]}"#,
+        r#"data: {"choices":[{"delta":{"content":"lo"},"finish_reason":"stop"}]}"#,
+        "data: [DONE]",
+    ]
+    .join("\n\n");
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "cache_prompt": true,
+            "messages": [
+                {"role": "user", "content": "Hi"},
+                {"role": "assistant", "content": "Hey"},
+                {"role": "user", "content": "Say hello"},
+            ],
+        })))
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = LlamaCpp::with_url(server.url(), LlamaCppEndpoint::Chat);
+    let prompt = BackendPrompt::new(
+        "Say hello".to_string(),
+        r#"{"messages":[{"role":"user","content":"Hi"},{"role":"assistant","content":"Hey"}]}"#
+            .to_string(),
+    );
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    assert_eq!(to_res(rx.recv().await)?.text, "Hel");
+    assert_eq!(to_res(rx.recv().await)?.text, "lo");
+    let done = to_res(rx.recv().await)?;
+    assert!(done.done);
+    insta::assert_snapshot!(done.context.unwrap(), @r#"{"messages":[{"role":"user","content":"Hi"},{"role":"assistant","content":"Hey"},{"role":"user","content":"Say hello"},{"role":"assistant","content":"Hello"}]}"#);
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_gets_completions_from_the_same_slot() -> Result<()> {
+    let body = [
+        r#"data: {"content":" Hello","stop":false}"#,
+        r#"data: {"content":" there","stop":false}"#,
+        r#"data: {"content":"","stop":true,"id_slot":2,"stopped_limit":false}"#,
+    ]
+    .join("\n\n");
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/completion")
+        .match_body(Matcher::PartialJson(serde_json::json!({
+            "prompt": "User: Hi\nAssistant: Hey\nUser: Say hello\nAssistant:",
+            "cache_prompt": true,
+            "id_slot": 2,
+        })))
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = LlamaCpp::with_url(server.url(), LlamaCppEndpoint::Completion);
+    let prompt = BackendPrompt::new(
+        "Say hello".to_string(),
+        r#"{"prompt":"User: Hi\nAssistant: Hey\n","id_slot":2}"#.to_string(),
+    );
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    assert_eq!(to_res(rx.recv().await)?.text, "Hello");
+    assert_eq!(to_res(rx.recv().await)?.text, " there");
+    let done = to_res(rx.recv().await)?;
+    assert!(done.done);
+    insta::assert_snapshot!(done.context.unwrap(), @r#"{"prompt":"User: Hi\nAssistant: Hey\nUser: Say hello\nAssistant: Hello there\n","id_slot":2}"#);
+
+    return Ok(());
+}
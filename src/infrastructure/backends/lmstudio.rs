@@ -0,0 +1,112 @@
+#[cfg(test)]
+#[path = "lmstudio_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use super::openai_compatible::OpenAiCompatible;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::Event;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::SendAuthorized;
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Model {
+    id: String,
+    /// Either `loaded` or `not-loaded`.
+    state: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ModelListResponse {
+    data: Vec<Model>,
+}
+
+/// LM Studio's local server, through its OpenAI compatible API. Its own
+/// REST API tells which of the downloaded models are loaded.
+pub struct LMStudio {
+    client: OpenAiCompatible,
+}
+
+impl Default for LMStudio {
+    fn default() -> LMStudio {
+        return LMStudio {
+            client: OpenAiCompatible::from_config(BackendName::LMStudio, ""),
+        };
+    }
+}
+
+impl LMStudio {
+    /// Models loaded into memory, or `None` with versions of LM Studio
+    /// without the REST API.
+    async fn loaded_models(&self) -> Result<Option<Vec<String>>> {
+        let res = http_client()?
+            .get(format!("{url}/api/v0/models", url = self.client.url()))
+            .timeout(self.client.timeout()?)
+            .send_authorized(BackendName::LMStudio)
+            .await?;
+
+        if !res.status().is_success() {
+            return Ok(None);
+        }
+
+        let models = res
+            .json::<ModelListResponse>()
+            .await?
+            .data
+            .into_iter()
+            .filter(|e| return e.state == "loaded")
+            .map(|e| return e.id)
+            .collect();
+
+        return Ok(Some(models));
+    }
+}
+
+#[async_trait]
+impl Backend for LMStudio {
+    fn name(&self) -> BackendName {
+        return BackendName::LMStudio;
+    }
+
+    /// Fails when the server is up but no model is loaded, as LM Studio
+    /// would reject every prompt.
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        self.client.health_check().await?;
+
+        let loaded = match self.loaded_models().await? {
+            Some(loaded) => loaded,
+            None => self.client.list_models().await?,
+        };
+        if loaded.is_empty() {
+            bail!(BackendError::NoModelLoaded {
+                backend: BackendName::LMStudio
+            });
+        }
+
+        return Ok(());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        return self.client.list_models().await;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        return self.client.get_completion(prompt, tx).await;
+    }
+}
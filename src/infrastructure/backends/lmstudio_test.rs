@@ -0,0 +1,85 @@
+use anyhow::Result;
+
+use super::LMStudio;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::infrastructure::backends::openai_compatible::OpenAiCompatible;
+
+impl LMStudio {
+    fn with_url(url: String) -> LMStudio {
+        return LMStudio {
+            client: OpenAiCompatible::new(BackendName::LMStudio, &url, "", "200"),
+        };
+    }
+}
+
+#[tokio::test]
+async fn it_passes_health_checks_with_a_loaded_model() -> Result<()> {
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/v1/models")
+        .with_status(200)
+        .with_body(r#"{"data":[{"id":"qwen2.5-7b-instruct"}]}"#)
+        .create();
+    let mock = server
+        .mock("GET", "/api/v0/models")
+        .with_status(200)
+        .with_body(r#"{"data":[{"id":"qwen2.5-7b-instruct","state":"loaded"},{"id":"llama-3.2-1b","state":"not-loaded"}]}"#)
+        .create();
+
+    let backend = LMStudio::with_url(server.url());
+    backend.health_check().await?;
+    mock.assert();
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_fails_health_checks_without_a_loaded_model() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/v1/models")
+        .with_status(200)
+        .with_body(r#"{"data":[]}"#)
+        .create();
+    server
+        .mock("GET", "/api/v0/models")
+        .with_status(200)
+        .with_body(r#"{"data":[{"id":"llama-3.2-1b","state":"not-loaded"}]}"#)
+        .create();
+
+    let backend = LMStudio::with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::NoModelLoaded {
+            backend: BackendName::LMStudio
+        })
+    );
+}
+
+#[tokio::test]
+async fn it_falls_back_to_listed_models_without_the_rest_api() {
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/v1/models")
+        .with_status(200)
+        .with_body(r#"{"data":[]}"#)
+        .create();
+    server
+        .mock("GET", "/api/v0/models")
+        .with_status(404)
+        .create();
+
+    let backend = LMStudio::with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::NoModelLoaded {
+            backend: BackendName::LMStudio
+        })
+    );
+}
@@ -1,18 +1,76 @@
+pub mod azure;
+pub mod balanced;
+pub mod bedrock;
+pub mod candle;
 pub mod claude;
+pub mod cohere;
 pub mod gemini;
 pub mod langchain;
+pub mod llamacpp;
+pub mod lmstudio;
 pub mod ollama;
 pub mod openai;
+pub mod openai_compatible;
+pub mod openrouter;
+pub mod perplexity;
 use anyhow::bail;
 use anyhow::Result;
+use strum::IntoEnumIterator;
 
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::url_key;
 use crate::domain::models::BackendBox;
 use crate::domain::models::BackendName;
+use crate::domain::models::ConfigError;
 
 pub struct BackendManager {}
 
 impl BackendManager {
+    /// Balances requests between endpoints when the backend's URL lists
+    /// several, separated by commas.
     pub fn get(name: BackendName) -> Result<BackendBox> {
+        let key = match url_key(&name) {
+            Some(key) => key,
+            None => return BackendManager::single(name),
+        };
+        let urls_config = Config::get(key);
+        let urls: Vec<String> = urls_config
+            .split(',')
+            .map(|e| return e.trim().to_string())
+            .filter(|e| return !e.is_empty())
+            .collect();
+        if urls.len() < 2 {
+            return BackendManager::single(name);
+        }
+
+        // Backends read their URL from the config as they're created.
+        let mut endpoints = vec![];
+        for url in urls {
+            Config::set(key, &url);
+            let backend = BackendManager::single(name.clone());
+            Config::set(key, &urls_config);
+            endpoints.push((url, backend?));
+        }
+
+        let strategy_name = Config::get(ConfigKey::LoadBalancing);
+        let strategy = match balanced::LoadBalancing::parse(&strategy_name) {
+            Some(strategy) => strategy,
+            None => {
+                bail!(ConfigError::InvalidValue {
+                    key: ConfigKey::LoadBalancing.to_string(),
+                    value: strategy_name,
+                    possible_values: balanced::LoadBalancing::iter()
+                        .map(|e| return e.to_string())
+                        .collect(),
+                })
+            }
+        };
+
+        return Ok(Box::new(balanced::Balanced::new(strategy, endpoints)));
+    }
+
+    fn single(name: BackendName) -> Result<BackendBox> {
         if name == BackendName::LangChain {
             return Ok(Box::<langchain::LangChain>::default());
         }
@@ -33,6 +91,50 @@ impl BackendManager {
             return Ok(Box::<gemini::Gemini>::default());
         }
 
+        if name == BackendName::OpenRouter {
+            return Ok(Box::<openrouter::OpenRouter>::default());
+        }
+
+        if name == BackendName::Azure {
+            return Ok(Box::<azure::Azure>::default());
+        }
+
+        if name == BackendName::Bedrock {
+            return Ok(Box::<bedrock::Bedrock>::default());
+        }
+
+        if name == BackendName::Mistral {
+            return Ok(Box::new(openai_compatible::OpenAiCompatible::mistral()));
+        }
+
+        if name == BackendName::Groq {
+            return Ok(Box::new(openai_compatible::OpenAiCompatible::groq()));
+        }
+
+        if name == BackendName::Cohere {
+            return Ok(Box::<cohere::Cohere>::default());
+        }
+
+        if name == BackendName::Together {
+            return Ok(Box::new(openai_compatible::OpenAiCompatible::together()));
+        }
+
+        if name == BackendName::Perplexity {
+            return Ok(Box::<perplexity::Perplexity>::default());
+        }
+
+        if name == BackendName::LlamaCpp {
+            return Ok(Box::<llamacpp::LlamaCpp>::default());
+        }
+
+        if name == BackendName::LMStudio {
+            return Ok(Box::<lmstudio::LMStudio>::default());
+        }
+
+        if name == BackendName::Candle {
+            return Ok(Box::<candle::Candle>::default());
+        }
+
         bail!(format!("No backend implemented for {name}"))
     }
 }
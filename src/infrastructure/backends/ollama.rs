@@ -18,21 +18,51 @@ use crate::configuration::Config;
 use crate::configuration::ConfigKey;
 use crate::domain::models::Author;
 use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
 use crate::domain::models::BackendName;
 use crate::domain::models::BackendPrompt;
 use crate::domain::models::BackendResponse;
 use crate::domain::models::Event;
+use crate::domain::models::ModelUsage;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::StreamInterrupted;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::SendAuthorized;
 
 fn convert_err(err: reqwest::Error) -> std::io::Error {
     let err_msg = err.to_string();
     return std::io::Error::new(std::io::ErrorKind::Interrupted, err_msg);
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct CompletionRequest {
     model: String,
     prompt: String,
+    /// Overrides the system message in the model's template.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
     context: Option<Vec<i32>>,
+    /// Base64 encoded, for multimodal models such as LLaVA.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    options: Option<CompletionOptions>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CompletionOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -40,6 +70,10 @@ struct CompletionResponse {
     pub response: String,
     pub done: bool,
     pub context: Option<Vec<i32>>,
+    /// Why generation stopped, such as `length` when it reached
+    /// `num_predict`. Only set with newer versions of Ollama.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub done_reason: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -52,9 +86,38 @@ struct ModelListResponse {
     pub models: Vec<Model>,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct EmbeddingRequest {
+    model: String,
+    prompt: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EmbeddingResponse {
+    embedding: Vec<f32>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RunningModel {
+    name: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    size_vram: u64,
+    #[serde(default)]
+    expires_at: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct RunningModelsResponse {
+    pub models: Vec<RunningModel>,
+}
+
 pub struct Ollama {
     url: String,
     timeout: String,
+    keep_alive: String,
+    preload: bool,
 }
 
 impl Default for Ollama {
@@ -62,10 +125,52 @@ impl Default for Ollama {
         return Ollama {
             url: Config::get(ConfigKey::OllamaURL),
             timeout: Config::get(ConfigKey::BackendHealthCheckTimeout),
+            keep_alive: Config::get(ConfigKey::OllamaKeepAlive),
+            preload: Config::get(ConfigKey::OllamaPreload) == "true",
         };
     }
 }
 
+impl Ollama {
+    /// Ollama accepts either a duration string such as `10m`, or a number of
+    /// seconds where a negative value keeps the model loaded indefinitely.
+    fn keep_alive(&self) -> Option<serde_json::Value> {
+        let keep_alive = self.keep_alive.trim();
+        if keep_alive.is_empty() {
+            return None;
+        }
+        if let Ok(seconds) = keep_alive.parse::<i64>() {
+            return Some(serde_json::json!(seconds));
+        }
+
+        return Some(serde_json::json!(keep_alive));
+    }
+
+    /// Finds the model among those Ollama has loaded into memory. Names
+    /// without a tag match the `latest` tag.
+    async fn running_model(&self, model: &str) -> Result<Option<RunningModel>> {
+        let res = http_client()?
+            .get(format!("{url}/api/ps", url = self.url))
+            .timeout(Duration::from_millis(self.timeout.parse::<u64>()?))
+            .send_authorized(BackendName::Ollama)
+            .await?;
+
+        if !res.status().is_success() {
+            bail!("Failed to list running models from Ollama");
+        }
+
+        let tagged = format!("{model}:latest");
+        let running = res
+            .json::<RunningModelsResponse>()
+            .await?
+            .models
+            .into_iter()
+            .find(|e| return e.name == model || e.name == tagged);
+
+        return Ok(running);
+    }
+}
+
 #[async_trait]
 impl Backend for Ollama {
     fn name(&self) -> BackendName {
@@ -74,21 +179,24 @@ impl Backend for Ollama {
 
     #[allow(clippy::implicit_return)]
     async fn health_check(&self) -> Result<()> {
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .get(&self.url)
             .timeout(Duration::from_millis(self.timeout.parse::<u64>()?))
-            .send()
+            .send_authorized(BackendName::Ollama)
             .await;
 
         if res.is_err() {
             tracing::error!(error = ?res.unwrap_err(), "Ollama is not running");
-            bail!("Ollama is not running");
+            bail!(BackendError::Unreachable {
+                backend: BackendName::Ollama,
+                url: self.url.to_string(),
+            });
         }
 
-        let res = res.unwrap();
-        if res.status() != 200 {
-            tracing::error!(status = res.status().as_u16(), "Ollama health checkfailed");
-            bail!("Ollama health check failed");
+        let status = res.unwrap().status().as_u16();
+        if status != 200 {
+            tracing::error!(status = status, "Ollama health check failed");
+            bail!(BackendError::from_status(BackendName::Ollama, status, ""));
         }
 
         return Ok(());
@@ -96,9 +204,9 @@ impl Backend for Ollama {
 
     #[allow(clippy::implicit_return)]
     async fn list_models(&self) -> Result<Vec<String>> {
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .get(format!("{url}/api/tags", url = self.url))
-            .send()
+            .send_authorized(BackendName::Ollama)
             .await?
             .json::<ModelListResponse>()
             .await?;
@@ -116,26 +224,132 @@ impl Backend for Ollama {
         return Ok(models);
     }
 
+    #[allow(clippy::implicit_return)]
+    async fn preload(&self) -> Result<()> {
+        if !self.preload {
+            return Ok(());
+        }
+
+        // Generating with an empty prompt loads the model without a response.
+        let req = CompletionRequest {
+            model: Config::get(ConfigKey::Model),
+            keep_alive: self.keep_alive(),
+            ..CompletionRequest::default()
+        };
+
+        let res = http_client()?
+            .post(format!("{url}/api/generate", url = self.url))
+            .json(&req)
+            .send_authorized(BackendName::Ollama)
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to preload model with Ollama"
+            );
+            bail!("Failed to preload model with Ollama");
+        }
+
+        return Ok(());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn model_usage(&self) -> Result<Option<ModelUsage>> {
+        let running = self.running_model(&Config::get(ConfigKey::Model)).await?;
+        return Ok(running.map(|e| {
+            return ModelUsage {
+                size: e.size,
+                size_vram: e.size_vram,
+                expires_at: e.expires_at,
+            };
+        }));
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut model = Config::get(ConfigKey::EmbeddingModel);
+        if model.is_empty() {
+            model = Config::get(ConfigKey::Model);
+        }
+
+        // The embeddings endpoint takes a single prompt per request.
+        let client = http_client()?;
+        let mut embeddings = vec![];
+        for text in texts {
+            let res = client
+                .post(format!("{url}/api/embeddings", url = self.url))
+                .json(&EmbeddingRequest {
+                    model: model.to_string(),
+                    prompt: text.to_string(),
+                })
+                .send_authorized(BackendName::Ollama)
+                .await?;
+
+            if !res.status().is_success() {
+                tracing::error!(
+                    status = res.status().as_u16(),
+                    "Failed to make embeddings request to Ollama"
+                );
+                bail!("Failed to make embeddings request to Ollama");
+            }
+
+            embeddings.push(res.json::<EmbeddingResponse>().await?.embedding);
+        }
+
+        return Ok(embeddings);
+    }
+
     #[allow(clippy::implicit_return)]
     async fn get_completion<'a>(
         &self,
         prompt: BackendPrompt,
         tx: &'a mpsc::UnboundedSender<Event>,
     ) -> Result<()> {
+        let model = prompt.model();
         let mut req = CompletionRequest {
-            model: Config::get(ConfigKey::Model),
+            model: model.to_string(),
             prompt: prompt.text,
+            system: prompt.system_prompt,
             context: None,
+            images: prompt
+                .images
+                .iter()
+                .map(|e| return e.data.to_string())
+                .collect(),
+            keep_alive: self.keep_alive(),
+            options: None,
         };
 
+        let sampling = SamplingParams::from_config();
+        let options = CompletionOptions {
+            seed: Config::get(ConfigKey::Seed).parse::<u64>().ok(),
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            num_predict: sampling.max_tokens,
+            stop: sampling.stop_sequences(),
+        };
+        if options != CompletionOptions::default() {
+            req.options = Some(options);
+        }
+
         if !prompt.backend_context.is_empty() {
             req.context = Some(serde_json::from_str(&prompt.backend_context)?);
         }
 
-        let res = reqwest::Client::new()
+        // Loading a model can take a while, and Ollama doesn't report
+        // progress, so say what's happening until the first token arrives.
+        if let Ok(None) = self.running_model(&req.model).await {
+            tx.send(Event::BackendStatus(format!(
+                "Loading {} into memory...",
+                req.model
+            )))?;
+        }
+
+        let res = http_client()?
             .post(format!("{url}/api/generate", url = self.url))
             .json(&req)
-            .send()
+            .send_authorized(BackendName::Ollama)
             .await?;
 
         if !res.status().is_success() {
@@ -143,19 +357,30 @@ impl Backend for Ollama {
                 status = res.status().as_u16(),
                 "Failed to make completion request to Ollama"
             );
-            bail!("Failed to make completion request to Ollama");
+            bail!(BackendError::from_status(
+                BackendName::Ollama,
+                res.status().as_u16(),
+                &model
+            ));
         }
 
         let stream = res.bytes_stream().map_err(convert_err);
         let mut lines_reader = StreamReader::new(stream).lines();
 
-        while let Ok(line) = lines_reader.next_line().await {
-            if line.is_none() {
-                break;
-            }
+        let mut last_message = "".to_string();
+        loop {
+            let line = match lines_reader.next_line().await {
+                Ok(Some(line)) => line,
+                // Ollama's last line is marked done, so ending without it
+                // means the connection dropped.
+                Ok(None) | Err(_) => {
+                    bail!(StreamInterrupted { text: last_message });
+                }
+            };
 
-            let ores: CompletionResponse = serde_json::from_str(&line.unwrap()).unwrap();
+            let ores: CompletionResponse = serde_json::from_str(&line).unwrap();
             tracing::debug!(body = ?ores, "Completion response");
+            last_message += &ores.response;
             let mut msg = BackendResponse {
                 author: Author::Model,
                 text: ores.response,
@@ -167,6 +392,12 @@ impl Backend for Ollama {
             }
 
             tx.send(Event::BackendPromptResponse(msg))?;
+            if ores.done {
+                if ores.done_reason.as_deref() == Some("length") {
+                    tx.send(Event::BackendOutputTruncated())?;
+                }
+                break;
+            }
         }
 
         return Ok(());
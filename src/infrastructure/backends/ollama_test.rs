@@ -6,17 +6,22 @@ use super::CompletionResponse;
 use super::Model;
 use super::ModelListResponse;
 use super::Ollama;
+use super::RunningModel;
+use super::RunningModelsResponse;
 use crate::domain::models::Author;
 use crate::domain::models::Backend;
 use crate::domain::models::BackendPrompt;
 use crate::domain::models::BackendResponse;
 use crate::domain::models::Event;
+use crate::domain::models::StreamInterrupted;
 
 impl Ollama {
     fn with_url(url: String) -> Ollama {
         return Ollama {
             url,
             timeout: "200".to_string(),
+            keep_alive: "".to_string(),
+            preload: false,
         };
     }
 }
@@ -89,18 +94,25 @@ async fn it_gets_completions() -> Result<()> {
         response: "Hello ".to_string(),
         done: false,
         context: None,
+        done_reason: None,
     })?;
 
     let second_line = serde_json::to_string(&CompletionResponse {
         response: "World".to_string(),
         done: true,
         context: Some(vec![1, 2, 3]),
+        done_reason: None,
     })?;
 
     let body = [first_line, second_line].join("\n");
     let prompt = BackendPrompt {
         text: "Say hi to the world".to_string(),
         backend_context: serde_json::to_string(&vec![1])?,
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
     };
 
     let mut server = mockito::Server::new();
@@ -132,3 +144,220 @@ async fn it_gets_completions() -> Result<()> {
 
     return Ok(());
 }
+
+#[tokio::test]
+async fn it_sends_keep_alive_with_completions() -> Result<()> {
+    let body = serde_json::to_string(&CompletionResponse {
+        response: "Hello".to_string(),
+        done: true,
+        context: None,
+        done_reason: None,
+    })?;
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/api/generate")
+        .match_body(mockito::Matcher::PartialJson(
+            serde_json::json!({ "keep_alive": "30m" }),
+        ))
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Ollama {
+        keep_alive: "30m".to_string(),
+        ..Ollama::with_url(server.url())
+    };
+    let prompt = BackendPrompt {
+        text: "Say hi".to_string(),
+        backend_context: "".to_string(),
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
+    };
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_preloads_the_model() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/api/generate")
+        .match_body(mockito::Matcher::PartialJson(
+            serde_json::json!({ "prompt": "", "keep_alive": -1 }),
+        ))
+        .with_status(200)
+        .with_body(r#"{"response":"","done":true}"#)
+        .create();
+
+    let backend = Ollama {
+        keep_alive: "-1".to_string(),
+        preload: true,
+        ..Ollama::with_url(server.url())
+    };
+    backend.preload().await?;
+
+    mock.assert();
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_skips_preloading_when_disabled() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server.mock("POST", "/api/generate").expect(0).create();
+
+    let backend = Ollama::with_url(server.url());
+    backend.preload().await?;
+
+    mock.assert();
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_finds_running_models() -> Result<()> {
+    let body = serde_json::to_string(&RunningModelsResponse {
+        models: vec![RunningModel {
+            name: "llama2:latest".to_string(),
+            size: 5_000,
+            size_vram: 4_000,
+            expires_at: Some("2024-06-04T14:38:31Z".to_string()),
+        }],
+    })?;
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/api/ps")
+        .with_status(200)
+        .with_body(body)
+        .expect(2)
+        .create();
+
+    let backend = Ollama::with_url(server.url());
+    let running = backend.running_model("llama2").await?.unwrap();
+    assert_eq!(running.size_vram, 4_000);
+    assert!(backend.running_model("mistral").await?.is_none());
+
+    mock.assert();
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_reports_loading_the_model() -> Result<()> {
+    let body = serde_json::to_string(&CompletionResponse {
+        response: "Hello".to_string(),
+        done: true,
+        context: None,
+        done_reason: None,
+    })?;
+
+    let mut server = mockito::Server::new();
+    let ps_mock = server
+        .mock("GET", "/api/ps")
+        .with_status(200)
+        .with_body(r#"{"models":[]}"#)
+        .create();
+    let generate_mock = server
+        .mock("POST", "/api/generate")
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Ollama::with_url(server.url());
+    let prompt = BackendPrompt {
+        text: "Say hi".to_string(),
+        backend_context: "".to_string(),
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
+    };
+    backend.get_completion(prompt, &tx).await?;
+
+    ps_mock.assert();
+    generate_mock.assert();
+
+    match rx.recv().await.unwrap() {
+        Event::BackendStatus(status) => assert!(status.starts_with("Loading")),
+        _ => bail!("Wrong type from recv"),
+    }
+    assert_eq!(to_res(rx.recv().await)?.text, "Hello");
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_gets_embeddings() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/api/embeddings")
+        .with_status(200)
+        .with_body(r#"{"embedding":[0.5,0.25]}"#)
+        .expect(2)
+        .create();
+
+    let backend = Ollama::with_url(server.url());
+    let res = backend
+        .embed(&["first".to_string(), "second".to_string()])
+        .await?;
+
+    mock.assert();
+    assert_eq!(res, vec![vec![0.5, 0.25], vec![0.5, 0.25]]);
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_reports_interrupted_completions() -> Result<()> {
+    let body = serde_json::to_string(&CompletionResponse {
+        response: "Hello ".to_string(),
+        done: false,
+        context: None,
+        done_reason: None,
+    })?;
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/api/generate")
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Ollama::with_url(server.url());
+    let prompt = BackendPrompt {
+        text: "Say hi to the world".to_string(),
+        backend_context: "".to_string(),
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
+    };
+    let err = backend.get_completion(prompt, &tx).await.unwrap_err();
+
+    mock.assert();
+    assert_eq!(
+        err.downcast_ref::<StreamInterrupted>(),
+        Some(&StreamInterrupted {
+            text: "Hello ".to_string()
+        })
+    );
+    assert_eq!(to_res(rx.recv().await)?.text, "Hello ");
+
+    return Ok(());
+}
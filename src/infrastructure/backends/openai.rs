@@ -18,10 +18,17 @@ use crate::configuration::Config;
 use crate::configuration::ConfigKey;
 use crate::domain::models::Author;
 use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
 use crate::domain::models::BackendName;
 use crate::domain::models::BackendPrompt;
 use crate::domain::models::BackendResponse;
+use crate::domain::models::ConfigError;
 use crate::domain::models::Event;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::StreamInterrupted;
+use crate::domain::models::ToolInvocation;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::SendAuthorized;
 
 fn convert_err(err: reqwest::Error) -> std::io::Error {
     let err_msg = err.to_string();
@@ -38,22 +45,108 @@ struct ModelListResponse {
     data: Vec<Model>,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct FunctionCall {
+    name: String,
+    arguments: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ToolCallRequest {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    function: FunctionCall,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ImageUrl {
+    url: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentPart {
+    Text { text: String },
+    ImageUrl { image_url: ImageUrl },
+}
+
+/// Text, or text with images for vision models.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+impl Default for MessageContent {
+    fn default() -> MessageContent {
+        return MessageContent::Text("".to_string());
+    }
+}
+
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct MessageRequest {
     role: String,
-    content: String,
+    content: MessageContent,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ToolCallRequest>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct FunctionRequest {
+    name: String,
+    description: String,
+    parameters: serde_json::Value,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct ToolRequest {
+    #[serde(rename = "type")]
+    kind: String,
+    function: FunctionRequest,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct CompletionRequest {
     model: String,
     messages: Vec<MessageRequest>,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<ToolRequest>,
+}
+
+/// Part of a tool call, streamed in fragments keyed by `index`.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct ToolCallDeltaResponse {
+    index: usize,
+    id: Option<String>,
+    function: Option<FunctionCallDeltaResponse>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct FunctionCallDeltaResponse {
+    name: Option<String>,
+    arguments: Option<String>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 struct CompletionDeltaResponse {
     content: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<ToolCallDeltaResponse>,
 }
 
 #[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -67,6 +160,23 @@ struct CompletionResponse {
     choices: Vec<CompletionChoiceResponse>,
 }
 
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct EmbeddingRequest {
+    model: String,
+    input: Vec<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
 pub struct OpenAI {
     url: String,
     token: String,
@@ -89,13 +199,21 @@ impl Backend for OpenAI {
         return BackendName::OpenAI;
     }
 
+    fn supports_tools(&self) -> bool {
+        return true;
+    }
+
     #[allow(clippy::implicit_return)]
     async fn health_check(&self) -> Result<()> {
         if self.url.is_empty() {
-            bail!("OpenAI URL is not defined");
+            bail!(ConfigError::Missing {
+                key: ConfigKey::OpenAiURL
+            });
         }
         if self.token.is_empty() {
-            bail!("OpenAI token is not defined");
+            bail!(ConfigError::Missing {
+                key: ConfigKey::OpenAiToken
+            });
         }
 
         // OpenAI are trolls with their API where the index either returns a 404 or a
@@ -104,21 +222,24 @@ impl Backend for OpenAI {
             return Ok(());
         }
 
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .get(&self.url)
             .timeout(Duration::from_millis(self.timeout.parse::<u64>()?))
-            .send()
+            .send_authorized(BackendName::OpenAI)
             .await;
 
         if res.is_err() {
             tracing::error!(error = ?res.unwrap_err(), "OpenAI is not reachable");
-            bail!("OpenAI is not reachable");
+            bail!(BackendError::Unreachable {
+                backend: BackendName::OpenAI,
+                url: self.url.to_string(),
+            });
         }
 
         let status = res.unwrap().status().as_u16();
         if status >= 400 {
             tracing::error!(status = status, "OpenAI health check failed");
-            bail!("OpenAI health check failed");
+            bail!(BackendError::from_status(BackendName::OpenAI, status, ""));
         }
 
         return Ok(());
@@ -126,10 +247,10 @@ impl Backend for OpenAI {
 
     #[allow(clippy::implicit_return)]
     async fn list_models(&self) -> Result<Vec<String>> {
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .get(format!("{url}/v1/models", url = self.url))
             .header("Authorization", format!("Bearer {}", self.token))
-            .send()
+            .send_authorized(BackendName::OpenAI)
             .await?
             .json::<ModelListResponse>()
             .await?;
@@ -147,32 +268,119 @@ impl Backend for OpenAI {
         return Ok(models);
     }
 
+    #[allow(clippy::implicit_return)]
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let mut model = Config::get(ConfigKey::EmbeddingModel);
+        if model.is_empty() {
+            model = "text-embedding-3-small".to_string();
+        }
+
+        let res = http_client()?
+            .post(format!("{url}/v1/embeddings", url = self.url))
+            .header("Authorization", format!("Bearer {}", self.token))
+            .json(&EmbeddingRequest {
+                model,
+                input: texts.to_vec(),
+            })
+            .send_authorized(BackendName::OpenAI)
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to make embeddings request to OpenAI"
+            );
+            bail!("Failed to make embeddings request to OpenAI");
+        }
+
+        let mut data = res.json::<EmbeddingResponse>().await?.data;
+        data.sort_by_key(|e| return e.index);
+
+        return Ok(data.into_iter().map(|e| return e.embedding).collect());
+    }
+
     #[allow(clippy::implicit_return)]
     async fn get_completion<'a>(
         &self,
         prompt: BackendPrompt,
         tx: &'a mpsc::UnboundedSender<Event>,
     ) -> Result<()> {
+        let model = prompt.model();
         let mut messages: Vec<MessageRequest> = vec![];
         if !prompt.backend_context.is_empty() {
             messages = serde_json::from_str(&prompt.backend_context)?;
         }
-        messages.push(MessageRequest {
-            role: "user".to_string(),
-            content: prompt.text,
-        });
+        for output in &prompt.tool_outputs {
+            messages.push(MessageRequest {
+                role: "tool".to_string(),
+                content: MessageContent::Text(output.output.to_string()),
+                tool_calls: vec![],
+                tool_call_id: Some(output.id.to_string()),
+            });
+        }
+        if prompt.tool_outputs.is_empty() || !prompt.text.is_empty() {
+            let mut content = MessageContent::Text(prompt.text.to_string());
+            if !prompt.images.is_empty() {
+                let mut parts = vec![ContentPart::Text { text: prompt.text }];
+                parts.extend(prompt.images.iter().map(|e| {
+                    return ContentPart::ImageUrl {
+                        image_url: ImageUrl { url: e.data_url() },
+                    };
+                }));
+                content = MessageContent::Parts(parts);
+            }
+            messages.push(MessageRequest {
+                role: "user".to_string(),
+                content,
+                tool_calls: vec![],
+                tool_call_id: None,
+            });
+        }
 
+        // The system prompt is sent with every request, so it's left out of
+        // the context.
+        let mut req_messages = vec![];
+        if let Some(system_prompt) = &prompt.system_prompt {
+            req_messages.push(MessageRequest {
+                role: "system".to_string(),
+                content: MessageContent::Text(system_prompt.to_string()),
+                tool_calls: vec![],
+                tool_call_id: None,
+            });
+        }
+        req_messages.extend(messages.iter().cloned());
+
+        let sampling = SamplingParams::from_config();
         let req = CompletionRequest {
-            model: Config::get(ConfigKey::Model),
-            messages: messages.clone(),
+            model: model.to_string(),
+            messages: req_messages,
             stream: true,
+            seed: Config::get(ConfigKey::Seed).parse::<u64>().ok(),
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            max_tokens: sampling.max_tokens,
+            stop: sampling.stop_sequences(),
+            tools: prompt
+                .tools
+                .iter()
+                .map(|e| {
+                    return ToolRequest {
+                        kind: "function".to_string(),
+                        function: FunctionRequest {
+                            name: e.to_string(),
+                            description: e.description().to_string(),
+                            parameters: e.parameters(),
+                        },
+                    };
+                })
+                .collect(),
         };
 
-        let res = reqwest::Client::new()
+        let res = http_client()?
             .post(format!("{url}/v1/chat/completions", url = self.url))
             .header("Authorization", format!("Bearer {}", self.token))
             .json(&req)
-            .send()
+            .send_authorized(BackendName::OpenAI)
             .await?;
 
         if !res.status().is_success() {
@@ -180,19 +388,29 @@ impl Backend for OpenAI {
                 status = res.status().as_u16(),
                 "Failed to make completion request to OpenAI"
             );
-            bail!("Failed to make completion request to OpenAI");
+            bail!(BackendError::from_status(
+                BackendName::OpenAI,
+                res.status().as_u16(),
+                &model
+            ));
         }
 
         let stream = res.bytes_stream().map_err(convert_err);
         let mut lines_reader = StreamReader::new(stream).lines();
 
         let mut last_message = "".to_string();
-        while let Ok(line) = lines_reader.next_line().await {
-            if line.is_none() {
-                break;
-            }
+        let mut tool_calls: Vec<ToolCallRequest> = vec![];
+        let mut truncated = false;
+        loop {
+            let line = match lines_reader.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(_) => {
+                    bail!(StreamInterrupted { text: last_message });
+                }
+            };
 
-            let mut cleaned_line = line.unwrap().trim().to_string();
+            let mut cleaned_line = line.trim().to_string();
             if cleaned_line.starts_with("data:") {
                 cleaned_line = cleaned_line.split_off(5).trim().to_string();
             }
@@ -204,7 +422,22 @@ impl Backend for OpenAI {
             tracing::debug!(body = ?ores, "Completion response");
 
             let choice = &ores.choices[0];
-            if choice.finish_reason.is_some() {
+            for delta in &choice.delta.tool_calls {
+                if tool_calls.len() <= delta.index {
+                    tool_calls.resize(delta.index + 1, ToolCallRequest::default());
+                }
+                let call = &mut tool_calls[delta.index];
+                call.kind = "function".to_string();
+                if let Some(id) = &delta.id {
+                    call.id += id;
+                }
+                if let Some(function) = &delta.function {
+                    call.function.name += function.name.as_deref().unwrap_or_default();
+                    call.function.arguments += function.arguments.as_deref().unwrap_or_default();
+                }
+            }
+            if let Some(finish_reason) = &choice.finish_reason {
+                truncated = finish_reason == "length";
                 break;
             }
             if choice.delta.content.is_none() {
@@ -229,7 +462,9 @@ impl Backend for OpenAI {
 
         messages.push(MessageRequest {
             role: "assistant".to_string(),
-            content: last_message.to_string(),
+            content: MessageContent::Text(last_message.to_string()),
+            tool_calls: tool_calls.clone(),
+            tool_call_id: None,
         });
 
         let msg = BackendResponse {
@@ -239,6 +474,19 @@ impl Backend for OpenAI {
             context: Some(serde_json::to_string(&messages)?),
         };
         tx.send(Event::BackendPromptResponse(msg))?;
+        if truncated {
+            tx.send(Event::BackendOutputTruncated())?;
+        }
+        if !tool_calls.is_empty() {
+            tx.send(Event::ToolCallsRequested(
+                tool_calls
+                    .iter()
+                    .map(|e| {
+                        return ToolInvocation::new(&e.id, &e.function.name, &e.function.arguments);
+                    })
+                    .collect(),
+            ))?;
+        }
 
         return Ok(());
     }
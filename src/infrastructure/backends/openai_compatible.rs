@@ -0,0 +1,523 @@
+#[cfg(test)]
+#[path = "openai_compatible_test.rs"]
+mod tests;
+
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::stream::TryStreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::io::AsyncBufReadExt;
+use tokio::sync::mpsc;
+use tokio_util::io::StreamReader;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::token_key;
+use crate::domain::models::url_key;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::ConfigError;
+use crate::domain::models::Event;
+use crate::domain::models::SamplingParams;
+use crate::domain::models::StreamInterrupted;
+use crate::infrastructure::http::http_client;
+use crate::infrastructure::http::SendAuthorized;
+
+fn convert_err(err: reqwest::Error) -> std::io::Error {
+    let err_msg = err.to_string();
+    return std::io::Error::new(std::io::ErrorKind::Interrupted, err_msg);
+}
+
+/// Types of models that can chat, for services that list every kind of model
+/// they host.
+const CHAT_MODEL_TYPES: [&str; 3] = ["chat", "language", "code"];
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelCapabilities {
+    pub completion_chat: bool,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Model {
+    pub id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    /// What the model can do, from services such as Mistral that list it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<ModelCapabilities>,
+}
+
+/// Most services wrap the list in `data`, though some return it bare.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ModelListResponse {
+    Wrapped { data: Vec<Model> },
+    Bare(Vec<Model>),
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageRequest {
+    pub role: String,
+    pub content: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct CompletionRequest {
+    model: String,
+    messages: Vec<MessageRequest>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    random_seed: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletionDeltaResponse {
+    pub content: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletionChoiceResponse {
+    pub delta: CompletionDeltaResponse,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletionErrorResponse {
+    pub message: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompletionResponse {
+    #[serde(default)]
+    pub choices: Vec<CompletionChoiceResponse>,
+    /// Errors after the stream started, such as the provider going down,
+    /// arrive as an event instead of a status code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<CompletionErrorResponse>,
+    /// Sources the answer was based on, from services that search the web
+    /// such as Perplexity.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub citations: Vec<String>,
+}
+
+/// Lists the sources as a numbered list, matching the `[1]` style markers
+/// in the answer.
+fn format_citations(citations: &[String]) -> String {
+    let list = citations
+        .iter()
+        .enumerate()
+        .map(|(idx, url)| return format!("{}. <{url}>", idx + 1))
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    return format!("\n\n**Sources**\n\n{list}");
+}
+
+/// Backend for services that follow OpenAI's chat completions API, which
+/// only differ in where they're hosted. Those with more to them, like
+/// OpenRouter, wrap it.
+pub struct OpenAiCompatible {
+    name: BackendName,
+    url: String,
+    token: String,
+    timeout: String,
+    /// Prepended to each endpoint's path, as most services version their API
+    /// under `/v1`.
+    api_prefix: String,
+    /// Models to offer for services without a models endpoint.
+    static_models: Option<Vec<String>>,
+    /// Endpoint the health check hits, when the models endpoint isn't the
+    /// best sign the service is up.
+    health_path: Option<String>,
+    /// Sent along with every request, such as the app's name for services
+    /// that credit it.
+    headers: Vec<(String, String)>,
+    /// Mistral names the seed `random_seed`.
+    random_seed: bool,
+}
+
+impl OpenAiCompatible {
+    pub fn new(name: BackendName, url: &str, token: &str, timeout: &str) -> OpenAiCompatible {
+        return OpenAiCompatible {
+            name,
+            url: url.to_string(),
+            token: token.to_string(),
+            timeout: timeout.to_string(),
+            api_prefix: "/v1".to_string(),
+            static_models: None,
+            health_path: None,
+            headers: vec![],
+            random_seed: false,
+        };
+    }
+
+    pub fn with_api_prefix(mut self, api_prefix: &str) -> OpenAiCompatible {
+        self.api_prefix = api_prefix.to_string();
+        return self;
+    }
+
+    pub fn with_static_models(mut self, models: &[&str]) -> OpenAiCompatible {
+        self.static_models = Some(models.iter().map(|e| return e.to_string()).collect());
+        return self;
+    }
+
+    pub fn with_health_path(mut self, path: &str) -> OpenAiCompatible {
+        self.health_path = Some(path.to_string());
+        return self;
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> OpenAiCompatible {
+        self.headers.push((name.to_string(), value.to_string()));
+        return self;
+    }
+
+    pub fn with_random_seed(mut self) -> OpenAiCompatible {
+        self.random_seed = true;
+        return self;
+    }
+
+    /// Reads the backend's URL and token from the config, falling back to the
+    /// service's own environment variable for the token.
+    pub fn from_config(name: BackendName, token_env: &str) -> OpenAiCompatible {
+        let url = url_key(&name).map(Config::get).unwrap_or_default();
+        let mut token = token_key(&name).map(Config::get).unwrap_or_default();
+        if token.is_empty() {
+            token = std::env::var(token_env).unwrap_or_default();
+        }
+
+        return OpenAiCompatible::new(
+            name,
+            &url,
+            &token,
+            &Config::get(ConfigKey::BackendHealthCheckTimeout),
+        );
+    }
+
+    /// Groq's API. Answers stream fast enough that the UI batches chunks that
+    /// arrive between redraws, and models it has retired are left out.
+    pub fn groq() -> OpenAiCompatible {
+        return OpenAiCompatible::from_config(BackendName::Groq, "GROQ_API_KEY");
+    }
+
+    /// Mistral's La Plateforme, at api.mistral.ai. Embedding and moderation
+    /// models are left out, as they can't chat.
+    pub fn mistral() -> OpenAiCompatible {
+        return OpenAiCompatible::from_config(BackendName::Mistral, "MISTRAL_API_KEY")
+            .with_random_seed();
+    }
+
+    /// Open-weight models hosted by Together AI. It lists image and embedding
+    /// models too, which are left out.
+    pub fn together() -> OpenAiCompatible {
+        return OpenAiCompatible::from_config(BackendName::Together, "TOGETHER_API_KEY");
+    }
+
+    pub fn url(&self) -> &str {
+        return &self.url;
+    }
+
+    /// How long health checks wait for an answer.
+    pub fn timeout(&self) -> Result<Duration> {
+        return Ok(Duration::from_millis(self.timeout.parse::<u64>()?));
+    }
+
+    fn get(&self, path: &str) -> Result<reqwest::RequestBuilder> {
+        return Ok(self.with_headers(http_client()?.get(format!(
+            "{url}{prefix}{path}",
+            url = self.url,
+            prefix = self.api_prefix
+        ))));
+    }
+
+    fn with_headers(&self, mut req: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        req = req.header("Authorization", format!("Bearer {}", self.token));
+        for (name, value) in &self.headers {
+            req = req.header(name, value);
+        }
+
+        return req;
+    }
+}
+
+#[async_trait]
+impl Backend for OpenAiCompatible {
+    fn name(&self) -> BackendName {
+        return self.name.clone();
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        let name = self.name();
+        if self.url.is_empty() {
+            if let Some(key) = url_key(&name) {
+                bail!(ConfigError::Missing { key });
+            }
+        }
+        if self.token.is_empty() {
+            if let Some(key) = token_key(&name) {
+                bail!(ConfigError::Missing { key });
+            }
+        }
+
+        // Without a models endpoint, any answer from the host means it's up,
+        // short of the token being rejected.
+        let path = match (&self.health_path, &self.static_models) {
+            (Some(path), _) => path.as_str(),
+            (None, Some(_)) => "",
+            (None, None) => "/models",
+        };
+        let res = self
+            .get(path)?
+            .timeout(self.timeout()?)
+            .send_authorized(self.name.clone())
+            .await;
+
+        if res.is_err() {
+            tracing::error!(error = ?res.unwrap_err(), "{name} is not reachable");
+            bail!(BackendError::Unreachable {
+                backend: name,
+                url: self.url.to_string(),
+            });
+        }
+
+        let status = res.unwrap().status().as_u16();
+        let rejected = match self.static_models {
+            Some(_) => status == 401 || status == 403,
+            None => status >= 400,
+        };
+        if rejected {
+            tracing::error!(status = status, "{name} health check failed");
+            bail!(BackendError::from_status(name, status, ""));
+        }
+
+        return Ok(());
+    }
+
+    /// Models that were retired, or can't chat, such as image or embedding
+    /// models, are left out.
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        if let Some(models) = &self.static_models {
+            return Ok(models.clone());
+        }
+
+        let name = self.name();
+        let res = self
+            .get("/models")?
+            .send_authorized(self.name.clone())
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to list {name} models"
+            );
+            bail!(BackendError::from_status(name, res.status().as_u16(), ""));
+        }
+
+        let models = match res.json::<ModelListResponse>().await? {
+            ModelListResponse::Wrapped { data } => data,
+            ModelListResponse::Bare(models) => models,
+        };
+        let mut models: Vec<String> = models
+            .iter()
+            .filter(|model| {
+                return model.active.unwrap_or(true)
+                    && model
+                        .kind
+                        .as_ref()
+                        .map(|kind| return CHAT_MODEL_TYPES.contains(&kind.as_str()))
+                        .unwrap_or(true)
+                    && model
+                        .capabilities
+                        .as_ref()
+                        .map(|e| return e.completion_chat)
+                        .unwrap_or(true);
+            })
+            .map(|model| {
+                return model.id.to_string();
+            })
+            .collect();
+
+        models.sort();
+
+        return Ok(models);
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        let name = self.name();
+        let model = prompt.model();
+        let mut messages: Vec<MessageRequest> = vec![];
+        if !prompt.backend_context.is_empty() {
+            messages = serde_json::from_str(&prompt.backend_context)?;
+        }
+        messages.push(MessageRequest {
+            role: "user".to_string(),
+            content: prompt.text,
+        });
+
+        // The system prompt is sent with every request, so it's left out of
+        // the context.
+        let mut req_messages = vec![];
+        if let Some(system_prompt) = &prompt.system_prompt {
+            req_messages.push(MessageRequest {
+                role: "system".to_string(),
+                content: system_prompt.to_string(),
+            });
+        }
+        req_messages.extend(messages.iter().cloned());
+
+        let sampling = SamplingParams::from_config();
+        let seed = Config::get(ConfigKey::Seed).parse::<u64>().ok();
+        let req = CompletionRequest {
+            model: model.to_string(),
+            messages: req_messages,
+            stream: true,
+            seed: seed.filter(|_| return !self.random_seed),
+            random_seed: seed.filter(|_| return self.random_seed),
+            temperature: sampling.temperature,
+            top_p: sampling.top_p,
+            max_tokens: sampling.max_tokens,
+            stop: sampling.stop_sequences(),
+        };
+
+        let res = self
+            .with_headers(http_client()?.post(format!(
+                "{url}{prefix}/chat/completions",
+                url = self.url,
+                prefix = self.api_prefix
+            )))
+            .json(&req)
+            .send_authorized(self.name.clone())
+            .await?;
+
+        if !res.status().is_success() {
+            tracing::error!(
+                status = res.status().as_u16(),
+                "Failed to make completion request to {name}"
+            );
+            bail!(BackendError::from_status(
+                name,
+                res.status().as_u16(),
+                &model
+            ));
+        }
+
+        let stream = res.bytes_stream().map_err(convert_err);
+        let mut lines_reader = StreamReader::new(stream).lines();
+
+        let mut last_message = "".to_string();
+        let mut citations = vec![];
+        let mut truncated = false;
+        loop {
+            let line = match lines_reader.next_line().await {
+                Ok(Some(line)) => line,
+                Ok(None) => break,
+                Err(_) => {
+                    bail!(StreamInterrupted { text: last_message });
+                }
+            };
+
+            // Lines starting with a colon are comments, such as the ones
+            // OpenRouter sends to keep the connection open while queued.
+            let mut cleaned_line = line.trim().to_string();
+            if cleaned_line.starts_with(':') {
+                continue;
+            }
+            if cleaned_line.starts_with("data:") {
+                cleaned_line = cleaned_line.split_off(5).trim().to_string();
+            }
+            if cleaned_line.is_empty() {
+                continue;
+            }
+            if cleaned_line == "[DONE]" {
+                break;
+            }
+
+            let ores: CompletionResponse = serde_json::from_str(&cleaned_line)?;
+            tracing::debug!(body = ?ores, "Completion response");
+            if let Some(err) = ores.error {
+                bail!("{name} failed to complete the answer: {}", err.message);
+            }
+            if !ores.citations.is_empty() {
+                citations = ores.citations.clone();
+            }
+
+            let choice = match ores.choices.first() {
+                Some(choice) => choice,
+                None => continue,
+            };
+            if let Some(text) = &choice.delta.content {
+                if !text.is_empty() {
+                    last_message += text;
+                    let msg = BackendResponse {
+                        author: Author::Model,
+                        text: text.to_string(),
+                        done: false,
+                        context: None,
+                    };
+
+                    tx.send(Event::BackendPromptResponse(msg))?;
+                }
+            }
+            if let Some(finish_reason) = &choice.finish_reason {
+                truncated = finish_reason == "length";
+                break;
+            }
+        }
+
+        // Sources are only shown in the chat, keeping the context to what
+        // the model wrote.
+        if !citations.is_empty() {
+            tx.send(Event::BackendPromptResponse(BackendResponse {
+                author: Author::Model,
+                text: format_citations(&citations),
+                done: false,
+                context: None,
+            }))?;
+        }
+
+        messages.push(MessageRequest {
+            role: "assistant".to_string(),
+            content: last_message.to_string(),
+        });
+
+        let msg = BackendResponse {
+            author: Author::Model,
+            text: "".to_string(),
+            done: true,
+            context: Some(serde_json::to_string(&messages)?),
+        };
+        tx.send(Event::BackendPromptResponse(msg))?;
+        if truncated {
+            tx.send(Event::BackendOutputTruncated())?;
+        }
+
+        return Ok(());
+    }
+}
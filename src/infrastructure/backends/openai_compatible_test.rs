@@ -0,0 +1,382 @@
+use anyhow::bail;
+use anyhow::Result;
+use test_utils::insta_snapshot;
+use tokio::sync::mpsc;
+
+use super::CompletionChoiceResponse;
+use super::CompletionDeltaResponse;
+use super::CompletionResponse;
+use super::MessageRequest;
+use super::Model;
+use super::ModelCapabilities;
+use super::ModelListResponse;
+use super::OpenAiCompatible;
+use crate::domain::models::Author;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Event;
+
+fn with_url(url: String) -> OpenAiCompatible {
+    return OpenAiCompatible::new(BackendName::Groq, &url, "abc", "200");
+}
+
+fn to_res(action: Option<Event>) -> Result<BackendResponse> {
+    let act = match action.unwrap() {
+        Event::BackendPromptResponse(res) => res,
+        _ => bail!("Wrong type from recv"),
+    };
+
+    return Ok(act);
+}
+
+fn stream_line(content: Option<&str>, finish_reason: Option<&str>) -> Result<String> {
+    let res = serde_json::to_string(&CompletionResponse {
+        choices: vec![CompletionChoiceResponse {
+            delta: CompletionDeltaResponse {
+                content: content.map(|e| return e.to_string()),
+            },
+            finish_reason: finish_reason.map(|e| return e.to_string()),
+        }],
+        ..CompletionResponse::default()
+    })?;
+
+    return Ok(format!("data: {res}\n"));
+}
+
+#[test]
+fn it_configures_each_service() {
+    let services = [
+        (
+            OpenAiCompatible::groq(),
+            BackendName::Groq,
+            "https://api.groq.com/openai",
+        ),
+        (
+            OpenAiCompatible::mistral(),
+            BackendName::Mistral,
+            "https://api.mistral.ai",
+        ),
+        (
+            OpenAiCompatible::together(),
+            BackendName::Together,
+            "https://api.together.xyz",
+        ),
+    ];
+
+    for (client, name, url) in services {
+        assert_eq!(client.name(), name);
+        assert_eq!(client.url(), url);
+        assert_eq!(client.random_seed, name == BackendName::Mistral);
+    }
+}
+
+#[test]
+fn it_reads_the_token_from_the_services_env_var() {
+    std::env::set_var("OATMEAL_TEST_COMPATIBLE_API_KEY", "from env");
+    let client =
+        OpenAiCompatible::from_config(BackendName::Together, "OATMEAL_TEST_COMPATIBLE_API_KEY");
+
+    assert_eq!(client.token, "from env");
+}
+
+#[test]
+fn it_reads_wrapped_and_bare_model_lists() {
+    let wrapped: ModelListResponse =
+        serde_json::from_str(r#"{"object":"list","data":[{"id":"llama3-8b-8192","active":true}]}"#)
+            .unwrap();
+    let bare: ModelListResponse =
+        serde_json::from_str(r#"[{"id":"meta-llama/Llama-3-8b-chat-hf","type":"chat"}]"#).unwrap();
+
+    assert_eq!(
+        wrapped,
+        ModelListResponse::Wrapped {
+            data: vec![Model {
+                id: "llama3-8b-8192".to_string(),
+                active: Some(true),
+                kind: None,
+                capabilities: None,
+            }]
+        }
+    );
+    assert_eq!(
+        bare,
+        ModelListResponse::Bare(vec![Model {
+            id: "meta-llama/Llama-3-8b-chat-hf".to_string(),
+            active: None,
+            kind: Some("chat".to_string()),
+            capabilities: None,
+        }])
+    );
+}
+
+#[tokio::test]
+async fn it_successfully_health_checks() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/v1/models")
+        .match_header("Authorization", "Bearer abc")
+        .with_status(200)
+        .create();
+
+    let backend = with_url(server.url());
+    let res = backend.health_check().await;
+
+    assert!(res.is_ok());
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_fails_health_checks() {
+    let mut server = mockito::Server::new();
+    let mock = server.mock("GET", "/v1/models").with_status(500).create();
+
+    let backend = with_url(server.url());
+    let res = backend.health_check().await;
+
+    assert!(res.is_err());
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_fails_health_checks_with_an_invalid_token() {
+    let mut server = mockito::Server::new();
+    let mock = server.mock("GET", "/v1/models").with_status(401).create();
+
+    let backend = with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::InvalidToken {
+            backend: BackendName::Groq
+        })
+    );
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_lists_chat_models() -> Result<()> {
+    let model = |id: &str| {
+        return Model {
+            id: id.to_string(),
+            ..Model::default()
+        };
+    };
+    let body = serde_json::to_string(&ModelListResponse::Wrapped {
+        data: vec![
+            model("gemma-7b-it"),
+            Model {
+                active: Some(true),
+                ..model("llama3-70b-8192")
+            },
+            // Retired, as Groq lists them.
+            Model {
+                active: Some(false),
+                ..model("llama2-70b-4096")
+            },
+            // Typed, as Together lists them.
+            Model {
+                kind: Some("code".to_string()),
+                ..model("codellama/CodeLlama-34b-Instruct-hf")
+            },
+            Model {
+                kind: Some("image".to_string()),
+                ..model("stabilityai/stable-diffusion-xl-base-1.0")
+            },
+            // With capabilities, as Mistral lists them.
+            Model {
+                capabilities: Some(ModelCapabilities {
+                    completion_chat: true,
+                }),
+                ..model("mistral-large-latest")
+            },
+            Model {
+                capabilities: Some(ModelCapabilities {
+                    completion_chat: false,
+                }),
+                ..model("mistral-embed")
+            },
+        ],
+    })?;
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/v1/models")
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let backend = with_url(server.url());
+    let res = backend.list_models().await?;
+    mock.assert();
+
+    assert_eq!(
+        res,
+        vec![
+            "codellama/CodeLlama-34b-Instruct-hf".to_string(),
+            "gemma-7b-it".to_string(),
+            "llama3-70b-8192".to_string(),
+            "mistral-large-latest".to_string(),
+        ]
+    );
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_gets_completions() -> Result<()> {
+    let body = [
+        ": OPENROUTER PROCESSING\n".to_string(),
+        stream_line(Some("Hello "), None)?,
+        stream_line(Some("World"), None)?,
+        stream_line(None, Some("stop"))?,
+        "data: [DONE]\n".to_string(),
+    ]
+    .join("\n");
+    let prompt = BackendPrompt::new(
+        "Say hi to the world".to_string(),
+        serde_json::to_string(&vec![MessageRequest {
+            role: "assistant".to_string(),
+            content: "How may I help you?".to_string(),
+        }])?,
+    );
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_header("Authorization", "Bearer abc")
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = with_url(server.url());
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    let first_recv = to_res(rx.recv().await)?;
+    let second_recv = to_res(rx.recv().await)?;
+    let third_recv = to_res(rx.recv().await)?;
+
+    assert_eq!(first_recv.author, Author::Model);
+    assert_eq!(first_recv.text, "Hello ".to_string());
+    assert!(!first_recv.done);
+
+    assert_eq!(second_recv.text, "World".to_string());
+    assert!(!second_recv.done);
+
+    assert!(third_recv.text.is_empty());
+    assert!(third_recv.done);
+    insta_snapshot(|| {
+        insta::assert_toml_snapshot!(third_recv.context);
+    });
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_reports_truncated_completions() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .with_status(200)
+        .with_body(
+            [
+                stream_line(Some("Hello "), None)?,
+                stream_line(Some("there"), Some("length"))?,
+            ]
+            .join("\n"),
+        )
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = with_url(server.url());
+    let prompt = BackendPrompt::new("Say hi to the world".to_string(), "".to_string());
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    assert_eq!(to_res(rx.recv().await)?.text, "Hello ");
+    assert_eq!(to_res(rx.recv().await)?.text, "there");
+    assert!(to_res(rx.recv().await)?.done);
+    assert!(matches!(
+        rx.recv().await,
+        Some(Event::BackendOutputTruncated())
+    ));
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_sends_the_system_prompt_without_saving_it() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "messages": [
+                { "role": "system", "content": "Answer tersely" },
+                { "role": "user", "content": "Say hi to the world" },
+            ],
+        })))
+        .with_status(200)
+        .with_body(stream_line(Some("Hi"), Some("stop"))?)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = with_url(server.url());
+    let mut prompt = BackendPrompt::new("Say hi to the world".to_string(), "".to_string());
+    prompt.system_prompt = Some("Answer tersely".to_string());
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    assert_eq!(to_res(rx.recv().await)?.text, "Hi");
+    let context: Vec<MessageRequest> =
+        serde_json::from_str(&to_res(rx.recv().await)?.context.unwrap())?;
+    assert_eq!(
+        context
+            .iter()
+            .map(|e| return e.role.as_str())
+            .collect::<Vec<&str>>(),
+        vec!["user", "assistant"]
+    );
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_fails_on_stream_errors() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .with_status(200)
+        .with_body(
+            [
+                stream_line(Some("Hello "), None)?,
+                r#"data: {"error":{"message":"Provider returned error"}}"#.to_string(),
+            ]
+            .join("\n"),
+        )
+        .create();
+
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = with_url(server.url());
+    let prompt = BackendPrompt::new("Say hi to the world".to_string(), "".to_string());
+    let err = backend.get_completion(prompt, &tx).await.unwrap_err();
+
+    mock.assert();
+    assert_eq!(
+        err.to_string(),
+        "groq failed to complete the answer: Provider returned error"
+    );
+
+    return Ok(());
+}
@@ -6,15 +6,22 @@ use tokio::sync::mpsc;
 use super::CompletionChoiceResponse;
 use super::CompletionDeltaResponse;
 use super::CompletionResponse;
+use super::MessageContent;
 use super::MessageRequest;
 use super::Model;
 use super::ModelListResponse;
 use super::OpenAI;
 use crate::domain::models::Author;
 use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
 use crate::domain::models::BackendPrompt;
 use crate::domain::models::BackendResponse;
 use crate::domain::models::Event;
+use crate::domain::models::ImageAttachment;
+use crate::domain::models::ModelTool;
+use crate::domain::models::ToolInvocation;
+use crate::domain::models::ToolOutput;
 
 impl OpenAI {
     fn with_url(url: String) -> OpenAI {
@@ -67,6 +74,23 @@ async fn it_fails_health_checks() {
     mock.assert();
 }
 
+#[tokio::test]
+async fn it_fails_health_checks_with_an_invalid_token() {
+    let mut server = mockito::Server::new();
+    let mock = server.mock("GET", "/").with_status(401).create();
+
+    let backend = OpenAI::with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::InvalidToken {
+            backend: BackendName::OpenAI
+        })
+    );
+    mock.assert();
+}
+
 #[tokio::test]
 async fn it_lists_models() -> Result<()> {
     let body = serde_json::to_string(&ModelListResponse {
@@ -103,6 +127,7 @@ async fn it_gets_completions() -> Result<()> {
         choices: vec![CompletionChoiceResponse {
             delta: CompletionDeltaResponse {
                 content: Some("Hello ".to_string()),
+                tool_calls: vec![],
             },
             finish_reason: None,
         }],
@@ -112,6 +137,7 @@ async fn it_gets_completions() -> Result<()> {
         choices: vec![CompletionChoiceResponse {
             delta: CompletionDeltaResponse {
                 content: Some("World".to_string()),
+                tool_calls: vec![],
             },
             finish_reason: None,
         }],
@@ -119,7 +145,10 @@ async fn it_gets_completions() -> Result<()> {
 
     let third_line = serde_json::to_string(&CompletionResponse {
         choices: vec![CompletionChoiceResponse {
-            delta: CompletionDeltaResponse { content: None },
+            delta: CompletionDeltaResponse {
+                content: None,
+                tool_calls: vec![],
+            },
             finish_reason: Some("stop".to_string()),
         }],
     })?;
@@ -129,8 +158,15 @@ async fn it_gets_completions() -> Result<()> {
         text: "Say hi to the world".to_string(),
         backend_context: serde_json::to_string(&vec![MessageRequest {
             role: "assistant".to_string(),
-            content: "How may I help you?".to_string(),
+            content: MessageContent::Text("How may I help you?".to_string()),
+            tool_calls: vec![],
+            tool_call_id: None,
         }])?,
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
     };
 
     let mut server = mockito::Server::new();
@@ -171,3 +207,200 @@ async fn it_gets_completions() -> Result<()> {
 
     return Ok(());
 }
+
+#[tokio::test]
+async fn it_reports_truncated_completions() -> Result<()> {
+    let first_line = serde_json::to_string(&CompletionResponse {
+        choices: vec![CompletionChoiceResponse {
+            delta: CompletionDeltaResponse {
+                content: Some("Hello ".to_string()),
+                tool_calls: vec![],
+            },
+            finish_reason: None,
+        }],
+    })?;
+
+    let second_line = serde_json::to_string(&CompletionResponse {
+        choices: vec![CompletionChoiceResponse {
+            delta: CompletionDeltaResponse {
+                content: None,
+                tool_calls: vec![],
+            },
+            finish_reason: Some("length".to_string()),
+        }],
+    })?;
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .with_status(200)
+        .with_body([first_line, second_line].join("\n"))
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = OpenAI::with_url(server.url());
+    let prompt = BackendPrompt {
+        text: "Say hi to the world".to_string(),
+        backend_context: "".to_string(),
+        tools: vec![],
+        tool_outputs: vec![],
+        images: vec![],
+        model: None,
+        system_prompt: None,
+    };
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    assert!(!to_res(rx.recv().await)?.done);
+    assert!(to_res(rx.recv().await)?.done);
+    assert!(matches!(
+        rx.recv().await,
+        Some(Event::BackendOutputTruncated())
+    ));
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_gets_embeddings() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/embeddings")
+        .match_body(mockito::Matcher::PartialJson(
+            serde_json::json!({ "input": ["first", "second"] }),
+        ))
+        .with_status(200)
+        .with_body(
+            r#"{"data":[{"index":1,"embedding":[0.0,1.0]},{"index":0,"embedding":[1.0,0.0]}]}"#,
+        )
+        .create();
+
+    let backend = OpenAI::with_url(server.url());
+    let res = backend
+        .embed(&["first".to_string(), "second".to_string()])
+        .await?;
+
+    mock.assert();
+    assert_eq!(res, vec![vec![1.0, 0.0], vec![0.0, 1.0]]);
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_requests_tool_calls() -> Result<()> {
+    let body = [
+        r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"id":"call_1","function":{"name":"shell","arguments":"{\"comm"}}]},"finish_reason":null}]}"#,
+        r#"data: {"choices":[{"delta":{"tool_calls":[{"index":0,"function":{"arguments":"and\":\"ls\"}"}}]},"finish_reason":null}]}"#,
+        r#"data: {"choices":[{"delta":{},"finish_reason":"tool_calls"}]}"#,
+    ]
+    .join("\n");
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "messages": [
+                { "role": "tool", "content": "README.md", "tool_call_id": "call_0" },
+            ],
+            "tools": [
+                { "type": "function", "function": { "name": "shell" } },
+            ],
+        })))
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = OpenAI::with_url(server.url());
+    let mut prompt = BackendPrompt::new("".to_string(), "".to_string());
+    prompt.tools = vec![ModelTool::Shell];
+    prompt.tool_outputs = vec![ToolOutput::new(
+        &ToolInvocation::new("call_0", "shell", r#"{"command":"ls"}"#),
+        "README.md",
+    )];
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    let done = to_res(rx.recv().await)?;
+    assert!(done.done);
+    let context: Vec<MessageRequest> = serde_json::from_str(&done.context.unwrap())?;
+    assert_eq!(context.len(), 2);
+    assert_eq!(context[1].tool_calls[0].id, "call_1");
+
+    match rx.recv().await {
+        Some(Event::ToolCallsRequested(calls)) => {
+            assert_eq!(
+                calls,
+                vec![ToolInvocation::new(
+                    "call_1",
+                    "shell",
+                    r#"{"command":"ls"}"#
+                )]
+            );
+        }
+        _ => bail!("Wrong type from recv"),
+    }
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_sends_images_with_the_prompt() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "messages": [{
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": "What's this?" },
+                    { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } },
+                ],
+            }],
+        })))
+        .with_status(200)
+        .with_body("")
+        .create();
+
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = OpenAI::with_url(server.url());
+    let mut prompt = BackendPrompt::new("What's this?".to_string(), "".to_string());
+    prompt.images = vec![ImageAttachment::from_bytes(
+        "screenshot.png",
+        b"\x89PNG\r\n\x1a\n",
+    )?];
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_sends_the_prompts_model() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+            "model": "gpt-4o-mini",
+        })))
+        .with_status(200)
+        .with_body("")
+        .create();
+
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = OpenAI::with_url(server.url());
+    let mut prompt = BackendPrompt::new("Hello".to_string(), "".to_string());
+    prompt.model = Some("gpt-4o-mini".to_string());
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    return Ok(());
+}
@@ -0,0 +1,68 @@
+#[cfg(test)]
+#[path = "openrouter_test.rs"]
+mod tests;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use super::openai_compatible::OpenAiCompatible;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::Event;
+
+/// OpenRouter, which routes to models from many providers behind one OpenAI
+/// compatible API.
+pub struct OpenRouter {
+    client: OpenAiCompatible,
+}
+
+impl OpenRouter {
+    /// The health check describes the API key, so doubles as a check that
+    /// it's valid.
+    fn with_client(client: OpenAiCompatible) -> OpenRouter {
+        return OpenRouter {
+            client: client
+                .with_health_path("/auth/key")
+                .with_header("X-Title", "Oatmeal"),
+        };
+    }
+}
+
+impl Default for OpenRouter {
+    fn default() -> OpenRouter {
+        return OpenRouter::with_client(OpenAiCompatible::from_config(
+            BackendName::OpenRouter,
+            "OPENROUTER_API_KEY",
+        ));
+    }
+}
+
+#[async_trait]
+impl Backend for OpenRouter {
+    fn name(&self) -> BackendName {
+        return BackendName::OpenRouter;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        return self.client.health_check().await;
+    }
+
+    /// The catalog covers every model OpenRouter routes to, which runs in to
+    /// the hundreds.
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        return self.client.list_models().await;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        return self.client.get_completion(prompt, tx).await;
+    }
+}
@@ -0,0 +1,76 @@
+use anyhow::Result;
+use tokio::sync::mpsc;
+
+use super::OpenRouter;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::Event;
+use crate::infrastructure::backends::openai_compatible::OpenAiCompatible;
+
+impl OpenRouter {
+    fn with_url(url: String) -> OpenRouter {
+        return OpenRouter::with_client(OpenAiCompatible::new(
+            BackendName::OpenRouter,
+            &url,
+            "abc",
+            "200",
+        ));
+    }
+}
+
+#[tokio::test]
+async fn it_successfully_health_checks() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/v1/auth/key")
+        .match_header("Authorization", "Bearer abc")
+        .with_status(200)
+        .create();
+
+    let backend = OpenRouter::with_url(server.url());
+    let res = backend.health_check().await;
+
+    assert!(res.is_ok());
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_fails_health_checks_with_an_invalid_token() {
+    let mut server = mockito::Server::new();
+    let mock = server.mock("GET", "/v1/auth/key").with_status(401).create();
+
+    let backend = OpenRouter::with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::InvalidToken {
+            backend: BackendName::OpenRouter
+        })
+    );
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_credits_oatmeal_in_completions() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_header("Authorization", "Bearer abc")
+        .match_header("X-Title", "Oatmeal")
+        .with_status(200)
+        .with_body("data: [DONE]\n")
+        .create();
+
+    let (tx, _rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = OpenRouter::with_url(server.url());
+    let prompt = BackendPrompt::new("Say hi to the world".to_string(), "".to_string());
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    return Ok(());
+}
@@ -0,0 +1,64 @@
+#[cfg(test)]
+#[path = "perplexity_test.rs"]
+mod tests;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::mpsc;
+
+use super::openai_compatible::OpenAiCompatible;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::Event;
+
+/// Perplexity doesn't list its models, so the ones it documents are offered.
+const MODELS: [&str; 5] = [
+    "sonar",
+    "sonar-deep-research",
+    "sonar-pro",
+    "sonar-reasoning",
+    "sonar-reasoning-pro",
+];
+
+/// Perplexity's pplx-api, which searches the web to answer. The sources it
+/// cites are listed at the end of each answer.
+pub struct Perplexity {
+    client: OpenAiCompatible,
+}
+
+impl Default for Perplexity {
+    fn default() -> Perplexity {
+        return Perplexity {
+            client: OpenAiCompatible::from_config(BackendName::Perplexity, "PERPLEXITY_API_KEY")
+                .with_api_prefix("")
+                .with_static_models(&MODELS),
+        };
+    }
+}
+
+#[async_trait]
+impl Backend for Perplexity {
+    fn name(&self) -> BackendName {
+        return BackendName::Perplexity;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn health_check(&self) -> Result<()> {
+        return self.client.health_check().await;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn list_models(&self) -> Result<Vec<String>> {
+        return self.client.list_models().await;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_completion<'a>(
+        &self,
+        prompt: BackendPrompt,
+        tx: &'a mpsc::UnboundedSender<Event>,
+    ) -> Result<()> {
+        return self.client.get_completion(prompt, tx).await;
+    }
+}
@@ -0,0 +1,135 @@
+use anyhow::bail;
+use anyhow::Result;
+use tokio::sync::mpsc;
+
+use super::Perplexity;
+use super::MODELS;
+use crate::domain::models::Backend;
+use crate::domain::models::BackendError;
+use crate::domain::models::BackendName;
+use crate::domain::models::BackendPrompt;
+use crate::domain::models::BackendResponse;
+use crate::domain::models::Event;
+use crate::infrastructure::backends::openai_compatible::CompletionChoiceResponse;
+use crate::infrastructure::backends::openai_compatible::CompletionDeltaResponse;
+use crate::infrastructure::backends::openai_compatible::CompletionResponse;
+use crate::infrastructure::backends::openai_compatible::OpenAiCompatible;
+
+impl Perplexity {
+    fn with_url(url: String) -> Perplexity {
+        return Perplexity {
+            client: OpenAiCompatible::new(BackendName::Perplexity, &url, "abc", "200")
+                .with_api_prefix("")
+                .with_static_models(&MODELS),
+        };
+    }
+}
+
+fn to_res(action: Option<Event>) -> Result<BackendResponse> {
+    let act = match action.unwrap() {
+        Event::BackendPromptResponse(res) => res,
+        _ => bail!("Wrong type from recv"),
+    };
+
+    return Ok(act);
+}
+
+fn stream_line(content: &str, citations: &[&str], finish_reason: Option<&str>) -> Result<String> {
+    let res = serde_json::to_string(&CompletionResponse {
+        choices: vec![CompletionChoiceResponse {
+            delta: CompletionDeltaResponse {
+                content: Some(content.to_string()),
+            },
+            finish_reason: finish_reason.map(|e| return e.to_string()),
+        }],
+        citations: citations.iter().map(|e| return e.to_string()).collect(),
+        error: None,
+    })?;
+
+    return Ok(format!("data: {res}\n\n"));
+}
+
+#[tokio::test]
+async fn it_passes_health_checks_without_a_models_endpoint() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/")
+        .match_header("Authorization", "Bearer abc")
+        .with_status(404)
+        .create();
+
+    let backend = Perplexity::with_url(server.url());
+    backend.health_check().await?;
+    mock.assert();
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_fails_health_checks_with_an_invalid_token() {
+    let mut server = mockito::Server::new();
+    let mock = server.mock("GET", "/").with_status(401).create();
+
+    let backend = Perplexity::with_url(server.url());
+    let err = backend.health_check().await.unwrap_err();
+
+    assert_eq!(
+        err.downcast_ref::<BackendError>(),
+        Some(&BackendError::InvalidToken {
+            backend: BackendName::Perplexity
+        })
+    );
+    mock.assert();
+}
+
+#[tokio::test]
+async fn it_lists_documented_models() -> Result<()> {
+    let backend = Perplexity::with_url("http://localhost".to_string());
+    let res = backend.list_models().await?;
+
+    assert_eq!(res[0], "sonar");
+    assert_eq!(res.len(), MODELS.len());
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_lists_citations_after_the_answer() -> Result<()> {
+    let citations = ["https://example.com/a", "https://example.com/b"];
+    let body = [
+        stream_line("Oats are", &citations, None)?,
+        stream_line(" grains [1][2].", &citations, Some("stop"))?,
+    ]
+    .concat();
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/chat/completions")
+        .match_header("Authorization", "Bearer abc")
+        .with_status(200)
+        .with_body(body)
+        .create();
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+
+    let backend = Perplexity::with_url(server.url());
+    let prompt = BackendPrompt::new("What are oats?".to_string(), "".to_string());
+    backend.get_completion(prompt, &tx).await?;
+
+    mock.assert();
+
+    assert_eq!(to_res(rx.recv().await)?.text, "Oats are");
+    assert_eq!(to_res(rx.recv().await)?.text, " grains [1][2].");
+    assert_eq!(
+        to_res(rx.recv().await)?.text,
+        "\n\n**Sources**\n\n1. <https://example.com/a>\n2. <https://example.com/b>"
+    );
+    let done = to_res(rx.recv().await)?;
+    assert!(done.done);
+    assert_eq!(
+        done.context.unwrap(),
+        r#"[{"role":"user","content":"What are oats?"},{"role":"assistant","content":"Oats are grains [1][2]."}]"#
+    );
+
+    return Ok(());
+}
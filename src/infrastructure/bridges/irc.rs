@@ -0,0 +1,168 @@
+#[cfg(test)]
+#[path = "irc_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::io::Lines;
+use tokio::net::tcp::OwnedReadHalf;
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::TcpStream;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Bridge;
+use crate::domain::models::BridgeMessage;
+use crate::domain::models::BridgeName;
+
+/// IRC servers truncate lines longer than 512 bytes, including the command.
+const MAX_MESSAGE_LENGTH: usize = 400;
+
+/// Parses a `PRIVMSG` line such as `:alice!a@host PRIVMSG #rust :hello`.
+fn parse_privmsg(line: &str) -> Option<BridgeMessage> {
+    let line = line.strip_prefix(':')?;
+    let (prefix, rest) = line.split_once(' ')?;
+    let (command, rest) = rest.split_once(' ')?;
+    if command != "PRIVMSG" {
+        return None;
+    }
+
+    let (room, text) = rest.split_once(" :")?;
+    let sender = prefix.split('!').next()?;
+
+    return Some(BridgeMessage {
+        room: room.to_string(),
+        sender: sender.to_string(),
+        text: text.to_string(),
+    });
+}
+
+/// Splits a reply in to lines short enough to be sent as single messages.
+fn split_reply(text: &str) -> Vec<String> {
+    let mut res: Vec<String> = vec![];
+    for line in text.lines().filter(|e| return !e.trim().is_empty()) {
+        let mut current = String::new();
+        for c in line.chars() {
+            if current.len() + c.len_utf8() > MAX_MESSAGE_LENGTH {
+                res.push(std::mem::take(&mut current));
+            }
+            current.push(c);
+        }
+        res.push(current);
+    }
+
+    return res;
+}
+
+pub struct Irc {
+    server: String,
+    channel: String,
+    nick: String,
+    lines: Option<Lines<BufReader<OwnedReadHalf>>>,
+    writer: Option<OwnedWriteHalf>,
+}
+
+impl Default for Irc {
+    fn default() -> Irc {
+        return Irc {
+            server: Config::get(ConfigKey::IrcServer),
+            channel: Config::get(ConfigKey::IrcChannel),
+            nick: Config::get(ConfigKey::BridgeNick),
+            lines: None,
+            writer: None,
+        };
+    }
+}
+
+impl Irc {
+    async fn send(&mut self, line: &str) -> Result<()> {
+        if let Some(writer) = self.writer.as_mut() {
+            writer.write_all(format!("{line}\r\n").as_bytes()).await?;
+            return Ok(());
+        }
+
+        bail!("Not connected to IRC");
+    }
+
+    async fn next_line(&mut self) -> Result<Option<String>> {
+        if let Some(lines) = self.lines.as_mut() {
+            return Ok(lines.next_line().await?);
+        }
+
+        bail!("Not connected to IRC");
+    }
+}
+
+#[async_trait]
+impl Bridge for Irc {
+    fn name(&self) -> BridgeName {
+        return BridgeName::Irc;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn connect(&mut self) -> Result<()> {
+        if self.server.is_empty() || self.channel.is_empty() {
+            bail!("IRC server and channel are not defined");
+        }
+
+        let stream = TcpStream::connect(&self.server).await?;
+        let (reader, writer) = stream.into_split();
+        self.lines = Some(BufReader::new(reader).lines());
+        self.writer = Some(writer);
+
+        let nick = self.nick.to_string();
+        self.send(&format!("NICK {nick}")).await?;
+        self.send(&format!("USER {nick} 0 * :Oatmeal")).await?;
+
+        // Wait for the welcome message before joining.
+        while let Some(line) = self.next_line().await? {
+            if let Some(token) = line.strip_prefix("PING ") {
+                self.send(&format!("PONG {token}")).await?;
+                continue;
+            }
+
+            let command = line.split_whitespace().nth(1).unwrap_or_default();
+            if command == "001" {
+                let channel = self.channel.to_string();
+                self.send(&format!("JOIN {channel}")).await?;
+                return Ok(());
+            }
+            if command == "433" {
+                bail!(format!("The IRC nick {nick} is already in use"));
+            }
+        }
+
+        bail!("IRC server closed the connection");
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn next_message(&mut self) -> Result<Option<BridgeMessage>> {
+        while let Some(line) = self.next_line().await? {
+            if let Some(token) = line.strip_prefix("PING ") {
+                self.send(&format!("PONG {token}")).await?;
+                continue;
+            }
+
+            if let Some(msg) = parse_privmsg(&line) {
+                if msg.room == self.channel {
+                    return Ok(Some(msg));
+                }
+            }
+        }
+
+        return Ok(None);
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn reply(&mut self, room: &str, text: &str) -> Result<()> {
+        for line in split_reply(text) {
+            self.send(&format!("PRIVMSG {room} :{line}")).await?;
+        }
+
+        return Ok(());
+    }
+}
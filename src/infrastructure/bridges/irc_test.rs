@@ -0,0 +1,95 @@
+use anyhow::Result;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::net::TcpListener;
+
+use super::parse_privmsg;
+use super::split_reply;
+use super::Irc;
+use super::MAX_MESSAGE_LENGTH;
+use crate::domain::models::Bridge;
+use crate::domain::models::BridgeMessage;
+
+impl Irc {
+    fn with_server(server: String) -> Irc {
+        return Irc {
+            server,
+            channel: "#rust".to_string(),
+            nick: "oatmeal".to_string(),
+            lines: None,
+            writer: None,
+        };
+    }
+}
+
+#[test]
+fn it_parses_privmsg() {
+    assert_eq!(
+        parse_privmsg(":alice!a@example.com PRIVMSG #rust :oatmeal: hi there"),
+        Some(BridgeMessage {
+            room: "#rust".to_string(),
+            sender: "alice".to_string(),
+            text: "oatmeal: hi there".to_string(),
+        })
+    );
+    assert_eq!(parse_privmsg(":irc.example.com 001 oatmeal :Welcome"), None);
+}
+
+#[test]
+fn it_splits_replies() {
+    let long = "a".repeat(MAX_MESSAGE_LENGTH + 10);
+    let res = split_reply(&format!("first\n\n{long}"));
+    assert_eq!(res.len(), 3);
+    assert_eq!(res[0], "first");
+    assert_eq!(res[1].len(), MAX_MESSAGE_LENGTH);
+    assert_eq!(res[2].len(), 10);
+}
+
+#[tokio::test]
+async fn it_joins_and_replies() -> Result<()> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?.to_string();
+
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let mut received = vec![];
+
+        received.push(lines.next_line().await.unwrap().unwrap());
+        received.push(lines.next_line().await.unwrap().unwrap());
+        writer
+            .write_all(b"PING :abc\r\n:irc.example.com 001 oatmeal :Welcome\r\n")
+            .await
+            .unwrap();
+        received.push(lines.next_line().await.unwrap().unwrap());
+        received.push(lines.next_line().await.unwrap().unwrap());
+        writer
+            .write_all(b":alice!a@host PRIVMSG #other :ignored\r\n:alice!a@host PRIVMSG #rust :oatmeal: hi\r\n")
+            .await
+            .unwrap();
+        received.push(lines.next_line().await.unwrap().unwrap());
+
+        return received;
+    });
+
+    let mut irc = Irc::with_server(addr);
+    irc.connect().await?;
+    let msg = irc.next_message().await?.unwrap();
+    assert_eq!(msg.text, "oatmeal: hi");
+    irc.reply(&msg.room, "Hello!").await?;
+
+    assert_eq!(
+        server.await?,
+        vec![
+            "NICK oatmeal",
+            "USER oatmeal 0 * :Oatmeal",
+            "PONG :abc",
+            "JOIN #rust",
+            "PRIVMSG #rust :Hello!",
+        ]
+    );
+
+    return Ok(());
+}
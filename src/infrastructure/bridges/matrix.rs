@@ -0,0 +1,207 @@
+#[cfg(test)]
+#[path = "matrix_test.rs"]
+mod tests;
+
+use std::collections::HashMap;
+use std::collections::VecDeque;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Bridge;
+use crate::domain::models::BridgeMessage;
+use crate::domain::models::BridgeName;
+use crate::infrastructure::http::http_client;
+
+/// How long the homeserver holds a sync request open waiting for events.
+const SYNC_TIMEOUT_MS: u64 = 30000;
+
+#[derive(Debug, Deserialize)]
+struct WhoAmIResponse {
+    user_id: String,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct EventContent {
+    #[serde(default)]
+    msgtype: String,
+    #[serde(default)]
+    body: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RoomEvent {
+    #[serde(rename = "type")]
+    event_type: String,
+    sender: String,
+    #[serde(default)]
+    content: EventContent,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Timeline {
+    #[serde(default)]
+    events: Vec<RoomEvent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JoinedRoom {
+    #[serde(default)]
+    timeline: Timeline,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Rooms {
+    #[serde(default)]
+    join: HashMap<String, JoinedRoom>,
+}
+
+#[derive(Debug, Deserialize)]
+struct SyncResponse {
+    next_batch: String,
+    #[serde(default)]
+    rooms: Rooms,
+}
+
+pub struct Matrix {
+    url: String,
+    room: String,
+    token: String,
+    user_id: String,
+    since: Option<String>,
+    pending: VecDeque<BridgeMessage>,
+    transaction: u64,
+}
+
+impl Default for Matrix {
+    fn default() -> Matrix {
+        return Matrix {
+            url: Config::get(ConfigKey::MatrixHomeserver),
+            room: Config::get(ConfigKey::MatrixRoom),
+            token: Config::get(ConfigKey::MatrixToken),
+            user_id: "".to_string(),
+            since: None,
+            pending: VecDeque::new(),
+            transaction: 0,
+        };
+    }
+}
+
+impl Matrix {
+    fn endpoint(&self, path: &str) -> String {
+        return format!(
+            "{}/_matrix/client/v3/{path}",
+            self.url.trim_end_matches('/')
+        );
+    }
+
+    async fn sync(&mut self, timeout: u64) -> Result<SyncResponse> {
+        let mut query = vec![("timeout", timeout.to_string())];
+        if let Some(since) = &self.since {
+            query.push(("since", since.to_string()));
+        }
+
+        let res = http_client()?
+            .get(self.endpoint("sync"))
+            .bearer_auth(&self.token)
+            .query(&query)
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            bail!(format!("Matrix sync failed with status {}", res.status()));
+        }
+
+        let sync = res.json::<SyncResponse>().await?;
+        self.since = Some(sync.next_batch.to_string());
+
+        return Ok(sync);
+    }
+}
+
+#[async_trait]
+impl Bridge for Matrix {
+    fn name(&self) -> BridgeName {
+        return BridgeName::Matrix;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn connect(&mut self) -> Result<()> {
+        if self.url.is_empty() || self.room.is_empty() || self.token.is_empty() {
+            bail!("Matrix homeserver, room, and token are not defined");
+        }
+
+        let res = http_client()?
+            .get(self.endpoint("account/whoami"))
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            bail!(format!(
+                "Matrix authentication failed with status {}",
+                res.status()
+            ));
+        }
+        self.user_id = res.json::<WhoAmIResponse>().await?.user_id;
+
+        // Skip the room's history so only new messages are answered.
+        self.sync(0).await?;
+
+        return Ok(());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn next_message(&mut self) -> Result<Option<BridgeMessage>> {
+        while self.pending.is_empty() {
+            let sync = self.sync(SYNC_TIMEOUT_MS).await?;
+            if let Some(room) = sync.rooms.join.get(&self.room) {
+                for event in room.timeline.events.iter() {
+                    if event.event_type != "m.room.message"
+                        || event.content.msgtype != "m.text"
+                        || event.sender == self.user_id
+                    {
+                        continue;
+                    }
+
+                    self.pending.push_back(BridgeMessage {
+                        room: self.room.to_string(),
+                        sender: event.sender.to_string(),
+                        text: event.content.body.to_string(),
+                    });
+                }
+            }
+        }
+
+        return Ok(self.pending.pop_front());
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn reply(&mut self, room: &str, text: &str) -> Result<()> {
+        self.transaction += 1;
+        let txn_id = format!("oatmeal-{}-{}", std::process::id(), self.transaction);
+        let res = http_client()?
+            .put(self.endpoint(&format!("rooms/{room}/send/m.room.message/{txn_id}")))
+            .bearer_auth(&self.token)
+            .json(&json!({
+                "msgtype": "m.text",
+                "body": text,
+            }))
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            bail!(format!(
+                "Failed to send Matrix message with status {}",
+                res.status()
+            ));
+        }
+
+        return Ok(());
+    }
+}
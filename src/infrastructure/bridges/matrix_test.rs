@@ -0,0 +1,75 @@
+use std::collections::VecDeque;
+
+use anyhow::Result;
+use mockito::Matcher;
+
+use super::Matrix;
+use crate::domain::models::Bridge;
+
+impl Matrix {
+    fn with_url(url: String) -> Matrix {
+        return Matrix {
+            url,
+            room: "!room:example.com".to_string(),
+            token: "abc".to_string(),
+            user_id: "".to_string(),
+            since: None,
+            pending: VecDeque::new(),
+            transaction: 0,
+        };
+    }
+}
+
+#[tokio::test]
+async fn it_answers_new_messages() -> Result<()> {
+    let mut server = mockito::Server::new();
+    server
+        .mock("GET", "/_matrix/client/v3/account/whoami")
+        .match_header("Authorization", "Bearer abc")
+        .with_status(200)
+        .with_body(r#"{"user_id":"@oatmeal:example.com"}"#)
+        .create();
+
+    let initial_sync = server
+        .mock("GET", "/_matrix/client/v3/sync")
+        .match_query(Matcher::UrlEncoded("timeout".to_string(), "0".to_string()))
+        .with_status(200)
+        .with_body(r#"{"next_batch":"s1","rooms":{"join":{"!room:example.com":{"timeline":{"events":[{"type":"m.room.message","sender":"@alice:example.com","content":{"msgtype":"m.text","body":"old message"}}]}}}}}"#)
+        .create();
+
+    let sync = server
+        .mock("GET", "/_matrix/client/v3/sync")
+        .match_query(Matcher::UrlEncoded("since".to_string(), "s1".to_string()))
+        .with_status(200)
+        .with_body(r#"{"next_batch":"s2","rooms":{"join":{"!room:example.com":{"timeline":{"events":[{"type":"m.room.message","sender":"@oatmeal:example.com","content":{"msgtype":"m.text","body":"my own reply"}},{"type":"m.room.message","sender":"@alice:example.com","content":{"msgtype":"m.text","body":"oatmeal: hi"}}]}}}}}"#)
+        .create();
+
+    let send = server
+        .mock(
+            "PUT",
+            Matcher::Regex(
+                r"^/_matrix/client/v3/rooms/!room:example.com/send/m.room.message/.+$".to_string(),
+            ),
+        )
+        .match_body(Matcher::Json(serde_json::json!({
+            "msgtype": "m.text",
+            "body": "Hello!",
+        })))
+        .with_status(200)
+        .with_body(r#"{"event_id":"$1"}"#)
+        .create();
+
+    let mut matrix = Matrix::with_url(server.url());
+    matrix.connect().await?;
+    let msg = matrix.next_message().await?.unwrap();
+    assert_eq!(msg.sender, "@alice:example.com");
+    assert_eq!(msg.text, "oatmeal: hi");
+
+    matrix.reply(&msg.room, "Hello!").await?;
+
+    initial_sync.assert();
+    sync.assert();
+    send.assert();
+
+    return Ok(());
+}
@@ -0,0 +1,24 @@
+pub mod irc;
+pub mod matrix;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use crate::domain::models::BridgeBox;
+use crate::domain::models::BridgeName;
+
+pub struct BridgeManager {}
+
+impl BridgeManager {
+    pub fn get(name: BridgeName) -> Result<BridgeBox> {
+        if name == BridgeName::Irc {
+            return Ok(Box::<irc::Irc>::default());
+        }
+
+        if name == BridgeName::Matrix {
+            return Ok(Box::<matrix::Matrix>::default());
+        }
+
+        bail!(format!("No bridge implemented for {name}"))
+    }
+}
@@ -0,0 +1,233 @@
+#[cfg(test)]
+#[path = "docs_test.rs"]
+mod tests;
+
+use std::path::Path;
+use std::path::PathBuf;
+
+use anyhow::bail;
+use anyhow::Result;
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::infrastructure::http::http_client;
+
+/// Maximum number of lines of documentation attached as context.
+const MAX_LINES: usize = 300;
+
+/// Item kinds rustdoc prefixes page names with, used to find an item's page
+/// without knowing what it is.
+const ITEM_KINDS: [&str; 12] = [
+    "struct",
+    "enum",
+    "trait",
+    "fn",
+    "macro",
+    "type",
+    "constant",
+    "static",
+    "union",
+    "attr",
+    "derive",
+    "primitive",
+];
+
+static TAG_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(r"(?s)<[^>]*>").unwrap();
+});
+
+static IGNORED_ELEMENTS_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(r"(?s)<(script|style|nav|noscript)[^>]*>.*?</(script|style|nav|noscript)>")
+        .unwrap();
+});
+
+static BLOCK_END_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(r"(?i)<br\s*/?>|</(p|div|h\d|pre|li|tr|summary|details|section)>").unwrap();
+});
+
+/// A path to a documented Rust item, such as `tokio::sync::mpsc::channel`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DocsPath {
+    pub crate_name: String,
+    pub modules: Vec<String>,
+    /// The final segment, or none when the path is just the crate.
+    pub item: Option<String>,
+}
+
+impl DocsPath {
+    pub fn parse(text: &str) -> Option<DocsPath> {
+        let text = text.trim().trim_end_matches("::");
+        let segments = text.split("::").collect::<Vec<&str>>();
+        if segments.iter().any(|e| {
+            return e.is_empty()
+                || !e
+                    .chars()
+                    .all(|c| return c.is_alphanumeric() || c == '_' || c == '-');
+        }) {
+            return None;
+        }
+
+        let mut segments = segments.iter().map(|e| return e.to_string());
+        let crate_name = segments.next()?;
+        let mut modules = segments.collect::<Vec<String>>();
+        let item = modules.pop();
+
+        return Some(DocsPath {
+            crate_name,
+            modules,
+            item,
+        });
+    }
+
+    fn crate_dir(&self) -> String {
+        return self.crate_name.replace('-', "_");
+    }
+
+    /// Pages the item could be documented on, relative to the docs root,
+    /// such as `tokio/sync/mpsc/fn.channel.html`.
+    pub fn candidates(&self) -> Vec<String> {
+        let mut base = vec![self.crate_dir()];
+        base.extend(self.modules.iter().cloned());
+        let base = base.join("/");
+
+        if self.item.is_none() {
+            return vec![format!("{base}/index.html")];
+        }
+
+        let item = self.item.as_ref().unwrap();
+        let mut res = vec![format!("{base}/{item}/index.html")];
+        for kind in ITEM_KINDS {
+            res.push(format!("{base}/{kind}.{item}.html"));
+        }
+
+        return res;
+    }
+
+    /// Version of the crate locked in `Cargo.lock` within `dir`, so docs match
+    /// what the project builds against.
+    pub fn locked_version(&self, dir: &Path) -> Option<String> {
+        let lock = std::fs::read_to_string(dir.join("Cargo.lock")).ok()?;
+        let needle = format!("name = \"{}\"", self.crate_name);
+        let mut lines = lock.lines();
+        while let Some(line) = lines.next() {
+            if line.trim() == needle {
+                let version = lines.next()?.trim().strip_prefix("version = ")?;
+                return Some(version.trim_matches('"').to_string());
+            }
+        }
+
+        return None;
+    }
+}
+
+/// Converts a rustdoc page to plain text, keeping only the main content.
+fn html_to_text(html: &str) -> String {
+    let mut html = html;
+    if let Some(idx) = html.find("id=\"main-content\"") {
+        html = &html[idx..];
+        html = &html[html.find('>').map(|e| return e + 1).unwrap_or(0)..];
+    }
+
+    let lines = strip_html(html)
+        .lines()
+        .map(|e| return e.to_string())
+        .collect::<Vec<String>>();
+    let end = lines.len().min(MAX_LINES);
+    let mut res = lines[..end].join("\n").trim().to_string();
+    if lines.len() > MAX_LINES {
+        res = format!(
+            "{res}\n\n[truncated {} more lines]",
+            lines.len() - MAX_LINES
+        );
+    }
+
+    return res;
+}
+
+/// Converts HTML to plain text, dropping scripts, styles, and navigation,
+/// and collapsing runs of blank lines.
+pub fn strip_html(html: &str) -> String {
+    let text = IGNORED_ELEMENTS_RE.replace_all(html, "");
+    let text = BLOCK_END_RE.replace_all(&text, "\n");
+    let text = TAG_RE.replace_all(&text, "");
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+
+    let mut lines: Vec<&str> = vec![];
+    for line in text.lines().map(|e| return e.trim_end()) {
+        if line.trim().is_empty() && lines.last().map(|e| return e.is_empty()).unwrap_or(true) {
+            continue;
+        }
+        lines.push(if line.trim().is_empty() { "" } else { line });
+    }
+
+    return lines.join("\n").trim().to_string();
+}
+
+pub struct RustDocs {
+    url: String,
+    dir: PathBuf,
+}
+
+impl Default for RustDocs {
+    fn default() -> RustDocs {
+        return RustDocs {
+            url: "https://docs.rs".to_string(),
+            dir: std::env::current_dir().unwrap_or_default(),
+        };
+    }
+}
+
+impl RustDocs {
+    /// Fetches the documentation for an item, preferring local `cargo doc`
+    /// output in `target/doc` and otherwise docs.rs at the version in
+    /// `Cargo.lock`.
+    pub async fn lookup(&self, path: &DocsPath) -> Result<String> {
+        let name = [
+            vec![path.crate_name.to_string()],
+            path.modules.clone(),
+            path.item.iter().cloned().collect(),
+        ]
+        .concat()
+        .join("::");
+
+        let doc_dir = self.dir.join("target/doc");
+        for candidate in path.candidates() {
+            if let Ok(html) = tokio::fs::read_to_string(doc_dir.join(&candidate)).await {
+                return Ok(format!(
+                    "Documentation for {name} from local cargo doc output:\n\n{}",
+                    html_to_text(&html)
+                ));
+            }
+        }
+
+        let version = path
+            .locked_version(&self.dir)
+            .unwrap_or("latest".to_string());
+        let client = http_client()?;
+        for candidate in path.candidates() {
+            let url = format!("{}/{}/{version}/{candidate}", self.url, path.crate_name);
+            let res = client
+                .get(&url)
+                .header("User-Agent", "oatmeal")
+                .send()
+                .await?;
+
+            if res.status().is_success() {
+                return Ok(format!(
+                    "Documentation for {name} {version} from {url}:\n\n{}",
+                    html_to_text(&res.text().await?)
+                ));
+            }
+        }
+
+        bail!(format!(
+            "Couldn't find documentation for {name} locally or on docs.rs"
+        ));
+    }
+}
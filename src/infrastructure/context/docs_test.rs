@@ -0,0 +1,125 @@
+use anyhow::Result;
+
+use super::html_to_text;
+use super::DocsPath;
+use super::RustDocs;
+
+impl RustDocs {
+    fn with_url(url: String, dir: &std::path::Path) -> RustDocs {
+        return RustDocs {
+            url,
+            dir: dir.to_path_buf(),
+        };
+    }
+}
+
+#[test]
+fn it_parses_paths() {
+    assert_eq!(
+        DocsPath::parse("tokio::sync::mpsc::channel"),
+        Some(DocsPath {
+            crate_name: "tokio".to_string(),
+            modules: vec!["sync".to_string(), "mpsc".to_string()],
+            item: Some("channel".to_string()),
+        })
+    );
+    assert_eq!(
+        DocsPath::parse("serde_json"),
+        Some(DocsPath {
+            crate_name: "serde_json".to_string(),
+            modules: vec![],
+            item: None,
+        })
+    );
+    assert_eq!(DocsPath::parse("tokio::<T>"), None);
+    assert_eq!(DocsPath::parse("tokio::::sync"), None);
+}
+
+#[test]
+fn it_lists_candidates() {
+    let path = DocsPath::parse("async-trait::async_trait").unwrap();
+    let candidates = path.candidates();
+    assert_eq!(candidates[0], "async_trait/async_trait/index.html");
+    assert!(candidates.contains(&"async_trait/attr.async_trait.html".to_string()));
+
+    let path = DocsPath::parse("tokio").unwrap();
+    assert_eq!(path.candidates(), vec!["tokio/index.html".to_string()]);
+}
+
+#[test]
+fn it_reads_locked_version() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(
+        dir.path().join("Cargo.lock"),
+        "[[package]]\nname = \"tokio\"\nversion = \"1.35.1\"\n",
+    )?;
+
+    let path = DocsPath::parse("tokio::spawn").unwrap();
+    assert_eq!(path.locked_version(dir.path()), Some("1.35.1".to_string()));
+
+    let path = DocsPath::parse("serde::Serialize").unwrap();
+    assert_eq!(path.locked_version(dir.path()), None);
+
+    return Ok(());
+}
+
+#[test]
+fn it_converts_html_to_text() {
+    let html = r#"<nav>Sidebar</nav><section id="main-content" class="content"><h1>Function <a>channel</a></h1><pre class="rust item-decl"><code>pub fn channel&lt;T&gt;(buffer: usize) -&gt; (Sender&lt;T&gt;, Receiver&lt;T&gt;)</code></pre><script>var x = 1;</script><p>Creates a bounded channel.</p></section>"#;
+    assert_eq!(
+        html_to_text(html),
+        "Function channel\npub fn channel<T>(buffer: usize) -> (Sender<T>, Receiver<T>)\nCreates a bounded channel."
+    );
+}
+
+#[tokio::test]
+async fn it_prefers_local_docs() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir_all(dir.path().join("target/doc/oatmeal"))?;
+    std::fs::write(
+        dir.path().join("target/doc/oatmeal/struct.Config.html"),
+        "<section id=\"main-content\"><p>Local docs</p></section>",
+    )?;
+
+    let docs = RustDocs::with_url("http://localhost:1".to_string(), dir.path());
+    let res = docs
+        .lookup(&DocsPath::parse("oatmeal::Config").unwrap())
+        .await?;
+    assert_eq!(
+        res,
+        "Documentation for oatmeal::Config from local cargo doc output:\n\nLocal docs"
+    );
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_fetches_from_docs_rs() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::write(
+        dir.path().join("Cargo.lock"),
+        "[[package]]\nname = \"tokio\"\nversion = \"1.35.1\"\n",
+    )?;
+
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/tokio/1.35.1/tokio/fn.spawn.html")
+        .with_status(200)
+        .with_body("<section id=\"main-content\"><p>Spawns a new task.</p></section>")
+        .create();
+
+    let docs = RustDocs::with_url(server.url(), dir.path());
+    let res = docs
+        .lookup(&DocsPath::parse("tokio::spawn").unwrap())
+        .await?;
+    mock.assert();
+    assert_eq!(
+        res,
+        format!(
+            "Documentation for tokio::spawn 1.35.1 from {}/tokio/1.35.1/tokio/fn.spawn.html:\n\nSpawns a new task.",
+            server.url()
+        )
+    );
+
+    return Ok(());
+}
@@ -0,0 +1,44 @@
+#[cfg(test)]
+#[path = "document_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use tokio::fs;
+
+use super::docs::strip_html;
+use crate::infrastructure::http::http_client;
+
+pub struct Document {}
+
+impl Document {
+    /// Reads a file, or fetches an `http` or `https` URL, as text. Web pages
+    /// are converted from HTML.
+    pub async fn read(source: &str) -> Result<String> {
+        if !source.starts_with("http://") && !source.starts_with("https://") {
+            return Ok(fs::read_to_string(source).await?);
+        }
+
+        let res = http_client()?
+            .get(source)
+            .header("User-Agent", "oatmeal")
+            .send()
+            .await?;
+        if !res.status().is_success() {
+            bail!(format!("{source} responded with {}", res.status()));
+        }
+
+        let is_html = res
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|e| return e.to_str().ok())
+            .map(|e| return e.contains("text/html"))
+            .unwrap_or(false);
+        let text = res.text().await?;
+        if is_html {
+            return Ok(strip_html(&text));
+        }
+
+        return Ok(text);
+    }
+}
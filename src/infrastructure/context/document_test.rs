@@ -0,0 +1,44 @@
+use anyhow::Result;
+
+use super::Document;
+
+#[tokio::test]
+async fn it_reads_files() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("notes.md");
+    std::fs::write(&path, "# Notes")?;
+
+    let res = Document::read(&path.to_string_lossy()).await?;
+    assert_eq!(res, "# Notes");
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_fetches_pages_as_text() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/spec")
+        .with_status(200)
+        .with_header("content-type", "text/html; charset=utf-8")
+        .with_body("<style>p {}</style><h1>Spec</h1><p>Requests are &lt;1kB.</p>")
+        .create();
+
+    let res = Document::read(&format!("{}/spec", server.url())).await?;
+    mock.assert();
+    assert_eq!(res, "Spec\nRequests are <1kB.");
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_fails_on_error_responses() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server.mock("GET", "/missing").with_status(404).create();
+
+    let res = Document::read(&format!("{}/missing", server.url())).await;
+    mock.assert();
+    assert!(res.is_err());
+
+    return Ok(());
+}
@@ -0,0 +1,212 @@
+#[cfg(test)]
+#[path = "github_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::infrastructure::http::http_client;
+
+/// Number of the most recent comments included as context.
+const MAX_COMMENTS: usize = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitHubReference {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+    pub is_pull_request: bool,
+}
+
+impl GitHubReference {
+    /// Parses `https://github.com/OWNER/REPO/issues/N`,
+    /// `https://github.com/OWNER/REPO/pull/N`, or `OWNER/REPO#N`.
+    pub fn parse(text: &str) -> Option<GitHubReference> {
+        let text = text.trim().trim_end_matches('/');
+        if let Some((repo_path, number)) = text.split_once('#') {
+            let (owner, repo) = repo_path.split_once('/')?;
+            return Some(GitHubReference {
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+                number: number.parse().ok()?,
+                is_pull_request: false,
+            });
+        }
+
+        let path = text
+            .trim_start_matches("https://")
+            .trim_start_matches("http://")
+            .strip_prefix("github.com/")?;
+        let parts = path.split('/').collect::<Vec<&str>>();
+        if parts.len() < 4 || !["issues", "pull"].contains(&parts[2]) {
+            return None;
+        }
+
+        return Some(GitHubReference {
+            owner: parts[0].to_string(),
+            repo: parts[1].to_string(),
+            number: parts[3].parse().ok()?,
+            is_pull_request: parts[2] == "pull",
+        });
+    }
+
+    fn url(&self) -> String {
+        let kind = if self.is_pull_request {
+            "pull"
+        } else {
+            "issues"
+        };
+        return format!(
+            "https://github.com/{}/{}/{kind}/{}",
+            self.owner, self.repo, self.number
+        );
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Author {
+    login: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Comment {
+    #[serde(alias = "user")]
+    author: Author,
+    #[serde(default)]
+    body: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Thread {
+    title: String,
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    comments: Vec<Comment>,
+}
+
+pub struct GitHub {
+    url: String,
+    token: String,
+}
+
+impl Default for GitHub {
+    fn default() -> GitHub {
+        return GitHub {
+            url: "https://api.github.com".to_string(),
+            token: Config::get(ConfigKey::GithubToken),
+        };
+    }
+}
+
+impl GitHub {
+    /// Fetches the title, body, and recent comments of an issue or pull
+    /// request, formatted to be used as context for a prompt. The `gh` CLI
+    /// is used when installed, otherwise the REST API.
+    pub async fn fetch(&self, reference: &GitHubReference) -> Result<String> {
+        let thread = match self.fetch_with_cli(reference).await {
+            Ok(thread) => thread,
+            Err(err) => {
+                tracing::debug!(error = ?err, "Failed to fetch from GitHub with gh, falling back to the REST API");
+                self.fetch_with_api(reference).await?
+            }
+        };
+
+        return Ok(format_thread(reference, thread));
+    }
+
+    async fn fetch_with_cli(&self, reference: &GitHubReference) -> Result<Thread> {
+        let kind = if reference.is_pull_request {
+            "pr"
+        } else {
+            "issue"
+        };
+
+        let output = Command::new("gh")
+            .args([
+                kind,
+                "view",
+                &reference.url(),
+                "--json",
+                "title,body,comments",
+            ])
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            bail!(String::from_utf8_lossy(&output.stderr).to_string());
+        }
+
+        return Ok(serde_json::from_slice::<Thread>(&output.stdout)?);
+    }
+
+    async fn fetch_with_api(&self, reference: &GitHubReference) -> Result<Thread> {
+        let base = format!(
+            "{}/repos/{}/{}/issues/{}",
+            self.url, reference.owner, reference.repo, reference.number
+        );
+
+        let mut thread = self.get(&base).await?.json::<Thread>().await?;
+        thread.comments = self
+            .get(&format!("{base}/comments"))
+            .await?
+            .json::<Vec<Comment>>()
+            .await?;
+
+        return Ok(thread);
+    }
+
+    async fn get(&self, url: &str) -> Result<reqwest::Response> {
+        let mut req = http_client()?
+            .get(url)
+            .header("Accept", "application/vnd.github+json")
+            .header("User-Agent", "oatmeal");
+
+        if !self.token.is_empty() {
+            req = req.header("Authorization", format!("Bearer {}", self.token));
+        }
+
+        let res = req.send().await?;
+        if !res.status().is_success() {
+            bail!(format!(
+                "GitHub API request failed with status {}",
+                res.status()
+            ));
+        }
+
+        return Ok(res);
+    }
+}
+
+fn format_thread(reference: &GitHubReference, thread: Thread) -> String {
+    let kind = if reference.is_pull_request {
+        "pull request"
+    } else {
+        "issue"
+    };
+
+    let mut res = format!(
+        "GitHub {kind} {}/{}#{}: {}\n\n{}",
+        reference.owner,
+        reference.repo,
+        reference.number,
+        thread.title,
+        thread.body.unwrap_or_default().trim()
+    );
+
+    let start = thread.comments.len().saturating_sub(MAX_COMMENTS);
+    let comments = thread.comments[start..]
+        .iter()
+        .map(|e| return format!("@{}: {}", e.author.login, e.body.trim()))
+        .collect::<Vec<String>>();
+
+    if !comments.is_empty() {
+        res = format!("{res}\n\nRecent comments:\n\n{}", comments.join("\n\n"));
+    }
+
+    return res.trim().to_string();
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+
+use super::format_thread;
+use super::GitHub;
+use super::GitHubReference;
+
+impl GitHub {
+    fn with_url(url: String) -> GitHub {
+        return GitHub {
+            url,
+            token: "abc".to_string(),
+        };
+    }
+}
+
+#[test]
+fn it_parses_references() {
+    assert_eq!(
+        GitHubReference::parse("https://github.com/dustinblackman/oatmeal/pull/12"),
+        Some(GitHubReference {
+            owner: "dustinblackman".to_string(),
+            repo: "oatmeal".to_string(),
+            number: 12,
+            is_pull_request: true,
+        })
+    );
+    assert_eq!(
+        GitHubReference::parse("dustinblackman/oatmeal#3"),
+        Some(GitHubReference {
+            owner: "dustinblackman".to_string(),
+            repo: "oatmeal".to_string(),
+            number: 3,
+            is_pull_request: false,
+        })
+    );
+    assert_eq!(
+        GitHubReference::parse("https://github.com/dustinblackman/oatmeal"),
+        None
+    );
+}
+
+#[tokio::test]
+async fn it_fetches_with_api() -> Result<()> {
+    let reference = GitHubReference::parse("dustinblackman/oatmeal#3").unwrap();
+    let mut server = mockito::Server::new();
+    let issue_mock = server
+        .mock("GET", "/repos/dustinblackman/oatmeal/issues/3")
+        .match_header("Authorization", "Bearer abc")
+        .with_status(200)
+        .with_body(r#"{"title":"Crash on start","body":"It crashes.","user":{"login":"a"}}"#)
+        .create();
+    let comments_mock = server
+        .mock("GET", "/repos/dustinblackman/oatmeal/issues/3/comments")
+        .with_status(200)
+        .with_body(r#"[{"user":{"login":"dustinblackman"},"body":"Which version?"}]"#)
+        .create();
+
+    let github = GitHub::with_url(server.url());
+    let thread = github.fetch_with_api(&reference).await?;
+    issue_mock.assert();
+    comments_mock.assert();
+
+    insta::assert_snapshot!(format_thread(&reference, thread), @r###"
+    GitHub issue dustinblackman/oatmeal#3: Crash on start
+
+    It crashes.
+
+    Recent comments:
+
+    @dustinblackman: Which version?
+    "###);
+
+    return Ok(());
+}
@@ -0,0 +1,101 @@
+#[cfg(test)]
+#[path = "man_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use tokio::process::Command;
+
+/// Maximum number of lines of documentation attached as context.
+const MAX_LINES: usize = 400;
+
+pub struct ManPage {}
+
+/// Removes the backspace overstrikes `man` uses for bold and underlined text
+/// when it isn't writing to a terminal.
+fn strip_overstrike(text: &str) -> String {
+    let mut res = String::new();
+    for c in text.chars() {
+        if c == '\u{8}' {
+            res.pop();
+            continue;
+        }
+        res.push(c);
+    }
+
+    return res;
+}
+
+fn truncate(text: &str) -> String {
+    let lines = text.trim().lines().collect::<Vec<&str>>();
+    if lines.len() <= MAX_LINES {
+        return lines.join("\n");
+    }
+
+    return format!(
+        "{}\n\n[truncated {} more lines]",
+        lines[..MAX_LINES].join("\n"),
+        lines.len() - MAX_LINES
+    );
+}
+
+async fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .env("MANPAGER", "cat")
+        .env("MANWIDTH", "100")
+        .output()
+        .await
+        .ok()?;
+
+    // Many tools print their help to stderr, or exit non-zero after printing it.
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    if text.trim().is_empty() {
+        text = String::from_utf8_lossy(&output.stderr).to_string();
+    }
+    if text.trim().is_empty() || (!output.status.success() && program == "man") {
+        return None;
+    }
+
+    return Some(text);
+}
+
+impl ManPage {
+    /// Fetches the locally installed man page for `topic`, optionally from a
+    /// specific section, falling back to the command's `--help` output.
+    pub async fn lookup(topic: &str, section: Option<&str>) -> Result<String> {
+        if topic.is_empty()
+            || topic.starts_with('-')
+            || !topic
+                .chars()
+                .all(|c| return c.is_alphanumeric() || "_-.+:".contains(c))
+        {
+            bail!(format!("{topic} isn't a valid command name"));
+        }
+
+        let mut man_args = vec![topic];
+        if let Some(section) = section {
+            man_args.insert(0, section);
+        }
+
+        if let Some(text) = run("man", &man_args).await {
+            return Ok(format!(
+                "Man page for {topic}:\n\n{}",
+                truncate(&strip_overstrike(&text))
+            ));
+        }
+
+        if section.is_none() {
+            for flag in ["--help", "-h"] {
+                if let Some(text) = run(topic, &[flag]).await {
+                    return Ok(format!(
+                        "Output of `{topic} {flag}`:\n\n{}",
+                        truncate(&text)
+                    ));
+                }
+            }
+        }
+
+        bail!(format!("No man page or --help output found for {topic}"));
+    }
+}
@@ -0,0 +1,48 @@
+use anyhow::Result;
+
+use super::strip_overstrike;
+use super::truncate;
+use super::ManPage;
+use super::MAX_LINES;
+
+#[test]
+fn it_strips_overstrike() {
+    assert_eq!(
+        strip_overstrike("N\u{8}NA\u{8}AM\u{8}ME\u{8}E _\u{8}l_\u{8}s"),
+        "NAME ls"
+    );
+}
+
+#[test]
+fn it_truncates_long_output() {
+    let text = (0..(MAX_LINES + 3))
+        .map(|e| return e.to_string())
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let res = truncate(&text);
+    assert_eq!(res.lines().count(), MAX_LINES + 2);
+    assert!(res.ends_with("[truncated 3 more lines]"));
+}
+
+#[tokio::test]
+async fn it_rejects_invalid_topics() -> Result<()> {
+    let res = ManPage::lookup("ls; rm -rf /", None).await;
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "ls; rm -rf / isn't a valid command name"
+    );
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_errors_on_unknown_commands() -> Result<()> {
+    let res = ManPage::lookup("oatmeal-command-that-does-not-exist", None).await;
+    assert_eq!(
+        res.unwrap_err().to_string(),
+        "No man page or --help output found for oatmeal-command-that-does-not-exist"
+    );
+
+    return Ok(());
+}
@@ -0,0 +1,4 @@
+pub mod docs;
+pub mod document;
+pub mod github;
+pub mod man;
@@ -31,6 +31,11 @@ impl Editor for Clipboard {
         return Ok(None);
     }
 
+    #[allow(clippy::implicit_return)]
+    async fn get_buffer(&self, _file_path: &str) -> Result<String> {
+        return Err(anyhow!("Clipboard editor does not open files"));
+    }
+
     #[allow(clippy::implicit_return)]
     async fn clear_context(&self) -> Result<()> {
         return Ok(());
@@ -16,6 +16,7 @@ use tokio::process::Command;
 use crate::domain::models::AcceptType;
 use crate::domain::models::Editor;
 use crate::domain::models::EditorContext;
+use crate::domain::models::EditorError;
 use crate::domain::models::EditorName;
 
 fn base64_to_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
@@ -62,20 +63,29 @@ impl From<ContextResponse> for EditorContext {
 }
 
 async fn run_lua_command(func: &str) -> Result<String> {
+    return run_remote_expr(&format!("v:lua.{func}")).await;
+}
+
+async fn run_remote_expr(expr: &str) -> Result<String> {
     let nvim_server_path = env::var("NVIM")?;
-    let lua_func = format!("v:lua.{func}");
     let args = vec![
         "--headless",
         "--server",
         &nvim_server_path,
         "--remote-expr",
-        &lua_func,
+        expr,
     ];
 
     let stdout = Command::new("nvim")
         .args(args.clone())
         .output()
-        .await?
+        .await
+        .map_err(|err| {
+            return EditorError::RequestFailed {
+                editor: EditorName::Neovim,
+                reason: err.to_string(),
+            };
+        })?
         .stdout;
     let res = String::from_utf8(stdout)?;
 
@@ -96,7 +106,10 @@ impl Editor for Neovim {
     #[allow(clippy::implicit_return)]
     async fn health_check(&self) -> Result<()> {
         if env::var("NVIM").is_err() {
-            bail!("Not running within a Neovim terminal")
+            bail!(EditorError::Unavailable {
+                editor: EditorName::Neovim,
+                reason: "Not running within a Neovim terminal".to_string(),
+            })
         }
 
         return Ok(());
@@ -113,6 +126,20 @@ impl Editor for Neovim {
         return Ok(Some(ctx.into()));
     }
 
+    #[allow(clippy::implicit_return)]
+    async fn get_buffer(&self, file_path: &str) -> Result<String> {
+        let name = file_path.replace('\'', "''");
+        let json_str = run_remote_expr(&format!(
+            "json_encode(bufloaded('{name}') ? getbufline('{name}', 1, '$') : v:null)"
+        ))
+        .await?;
+        let lines: Option<Vec<String>> = serde_json::from_str(json_str.trim())?;
+        match lines {
+            Some(lines) => return Ok(lines.join("\n")),
+            None => bail!("{file_path} isn't open in Neovim"),
+        }
+    }
+
     #[allow(clippy::implicit_return)]
     async fn clear_context(&self) -> Result<()> {
         run_lua_command("oatmeal_clear_context()").await?;
@@ -29,6 +29,11 @@ impl Editor for NoopEditor {
         return Ok(None);
     }
 
+    #[allow(clippy::implicit_return)]
+    async fn get_buffer(&self, _file_path: &str) -> Result<String> {
+        return Err(anyhow!("None/noop editor does not open files"));
+    }
+
     #[allow(clippy::implicit_return)]
     async fn clear_context(&self) -> Result<()> {
         return Ok(());
@@ -18,6 +18,17 @@ async fn it_returns_no_context() -> Result<()> {
     return Ok(());
 }
 
+#[tokio::test]
+async fn it_returns_an_error_getting_buffers() -> Result<()> {
+    let err = NoopEditor::default()
+        .get_buffer("main.rs")
+        .await
+        .unwrap_err();
+
+    insta::assert_snapshot!(err.to_string(), @"None/noop editor does not open files");
+    return Ok(());
+}
+
 #[tokio::test]
 async fn it_clears_context() -> Result<()> {
     NoopEditor::default().get_context().await?;
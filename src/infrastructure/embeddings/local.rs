@@ -0,0 +1,158 @@
+#[cfg(test)]
+#[path = "local_test.rs"]
+mod tests;
+
+use std::path::PathBuf;
+
+#[cfg(not(feature = "local-embeddings"))]
+use anyhow::bail;
+use anyhow::Result;
+use tokio::fs;
+use tokio::io::AsyncWriteExt;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::infrastructure::http::http_client;
+
+/// Small sentence embedding model that runs quickly on a CPU.
+const DEFAULT_MODEL: &str = "sentence-transformers/all-MiniLM-L6-v2";
+
+/// Files loaded from a Hugging Face model repository.
+const MODEL_FILES: [&str; 3] = ["config.json", "tokenizer.json", "model.safetensors"];
+
+/// Embeds text on device with a BERT style model from Hugging Face, so
+/// `/similar` works without an embeddings API. The model is downloaded on
+/// first use and cached.
+#[cfg_attr(not(feature = "local-embeddings"), allow(dead_code))]
+pub struct LocalEmbeddings {
+    url: String,
+    /// Hugging Face repository, such as
+    /// `sentence-transformers/all-MiniLM-L6-v2`.
+    repo: String,
+    cache_dir: PathBuf,
+}
+
+impl Default for LocalEmbeddings {
+    fn default() -> LocalEmbeddings {
+        // `embedding-model` names a backend's model unless it's a repository.
+        let mut repo = Config::get(ConfigKey::EmbeddingModel);
+        if !repo.contains('/') {
+            repo = DEFAULT_MODEL.to_string();
+        }
+
+        return LocalEmbeddings {
+            url: "https://huggingface.co".to_string(),
+            repo,
+            cache_dir: dirs::cache_dir().unwrap().join("oatmeal/models"),
+        };
+    }
+}
+
+#[cfg_attr(not(feature = "local-embeddings"), allow(dead_code))]
+impl LocalEmbeddings {
+    /// Downloads any of the model's files missing from the cache, returning
+    /// the directory they're in.
+    async fn download(&self) -> Result<PathBuf> {
+        let dir = self.cache_dir.join(self.repo.replace('/', "--"));
+        fs::create_dir_all(&dir).await?;
+
+        for file in MODEL_FILES {
+            let path = dir.join(file);
+            if path.exists() {
+                continue;
+            }
+
+            tracing::info!(repo = self.repo, file, "Downloading embedding model");
+            let res = http_client()?
+                .get(format!(
+                    "{url}/{repo}/resolve/main/{file}",
+                    url = self.url,
+                    repo = self.repo
+                ))
+                .send()
+                .await?
+                .error_for_status()?;
+
+            // Write to a temporary file first so an interrupted download
+            // isn't mistaken for a cached one.
+            let partial = dir.join(format!("{file}.part"));
+            let mut out = fs::File::create(&partial).await?;
+            out.write_all(&res.bytes().await?).await?;
+            out.flush().await?;
+            drop(out);
+            fs::rename(&partial, &path).await?;
+        }
+
+        return Ok(dir);
+    }
+
+    #[cfg(feature = "local-embeddings")]
+    pub async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let dir = self.download().await?;
+        let texts = texts.to_vec();
+        return tokio::task::spawn_blocking(move || return model::embed(&dir, &texts)).await?;
+    }
+
+    #[cfg(not(feature = "local-embeddings"))]
+    pub async fn embed(&self, _texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        bail!("Oatmeal was built without local embeddings. Rebuild it with `cargo install oatmeal --features local-embeddings`, or set `embedding-provider` to `backend`.");
+    }
+}
+
+#[cfg(feature = "local-embeddings")]
+mod model {
+    use std::path::Path;
+
+    use anyhow::anyhow;
+    use anyhow::Result;
+    use candle_core::Device;
+    use candle_core::Tensor;
+    use candle_nn::VarBuilder;
+    use candle_transformers::models::bert::BertModel;
+    use candle_transformers::models::bert::Config;
+    use candle_transformers::models::bert::DTYPE;
+    use tokenizers::PaddingParams;
+    use tokenizers::Tokenizer;
+
+    /// Mean pools the model's output over each text's tokens, ignoring
+    /// padding, and normalizes the result.
+    pub fn embed(dir: &Path, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let device = Device::Cpu;
+        let config =
+            serde_json::from_str::<Config>(&std::fs::read_to_string(dir.join("config.json"))?)?;
+        let weights = std::fs::read(dir.join("model.safetensors"))?;
+        let model = BertModel::load(
+            VarBuilder::from_buffered_safetensors(weights, DTYPE, &device)?,
+            &config,
+        )?;
+
+        let mut tokenizer =
+            Tokenizer::from_file(dir.join("tokenizer.json")).map_err(|e| return anyhow!(e))?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        let encodings = tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| return anyhow!(e))?;
+
+        let ids = encodings
+            .iter()
+            .map(|e| return Tensor::new(e.get_ids(), &device))
+            .collect::<candle_core::Result<Vec<Tensor>>>()?;
+        let masks = encodings
+            .iter()
+            .map(|e| return Tensor::new(e.get_attention_mask(), &device))
+            .collect::<candle_core::Result<Vec<Tensor>>>()?;
+
+        // BERT in this version of candle doesn't take an attention mask, so
+        // texts are padded to the same length and padding is left out of
+        // pooling instead.
+        let ids = Tensor::stack(&ids, 0)?;
+        let mask = Tensor::stack(&masks, 0)?.to_dtype(DTYPE)?.unsqueeze(2)?;
+        let output = model.forward(&ids, &ids.zeros_like()?)?;
+
+        let summed = output.broadcast_mul(&mask)?.sum(1)?;
+        let pooled = summed.broadcast_div(&mask.sum(1)?)?;
+        let normalized = pooled.broadcast_div(&pooled.sqr()?.sum_keepdim(1)?.sqrt()?)?;
+
+        return Ok(normalized.to_vec2::<f32>()?);
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::Result;
+
+use super::LocalEmbeddings;
+use super::MODEL_FILES;
+
+#[tokio::test]
+async fn it_downloads_and_caches_model_files() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut server = mockito::Server::new();
+    let mocks = MODEL_FILES
+        .iter()
+        .map(|file| {
+            return server
+                .mock(
+                    "GET",
+                    format!("/sentence-transformers/all-MiniLM-L6-v2/resolve/main/{file}").as_str(),
+                )
+                .with_status(200)
+                .with_body(format!("contents of {file}"))
+                .expect(1)
+                .create();
+        })
+        .collect::<Vec<mockito::Mock>>();
+
+    let embeddings = LocalEmbeddings {
+        url: server.url(),
+        repo: "sentence-transformers/all-MiniLM-L6-v2".to_string(),
+        cache_dir: dir.path().to_path_buf(),
+    };
+    let model_dir = embeddings.download().await?;
+    embeddings.download().await?;
+
+    for mock in mocks {
+        mock.assert();
+    }
+    assert_eq!(
+        model_dir,
+        dir.path().join("sentence-transformers--all-MiniLM-L6-v2")
+    );
+    assert_eq!(
+        std::fs::read_to_string(model_dir.join("tokenizer.json"))?,
+        "contents of tokenizer.json"
+    );
+    assert!(!model_dir.join("model.safetensors.part").exists());
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_keeps_partial_downloads_out_of_the_cache() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let mut server = mockito::Server::new();
+    let _mock = server
+        .mock("GET", mockito::Matcher::Any)
+        .with_status(404)
+        .create();
+
+    let embeddings = LocalEmbeddings {
+        url: server.url(),
+        repo: "missing/model".to_string(),
+        cache_dir: dir.path().to_path_buf(),
+    };
+    assert!(embeddings.download().await.is_err());
+    assert!(!dir.path().join("missing--model/config.json").exists());
+
+    return Ok(());
+}
+
+#[cfg(not(feature = "local-embeddings"))]
+#[tokio::test]
+async fn it_requires_the_local_embeddings_feature() {
+    let err = LocalEmbeddings::default()
+        .embed(&["Hello".to_string()])
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("--features local-embeddings"));
+}
+
+#[cfg(feature = "local-embeddings")]
+#[test]
+fn it_embeds_with_a_bert_model() -> Result<()> {
+    use candle_core::Device;
+    use candle_nn::VarBuilder;
+    use candle_nn::VarMap;
+    use candle_transformers::models::bert::BertModel;
+    use candle_transformers::models::bert::Config;
+    use candle_transformers::models::bert::DTYPE;
+
+    // A tiny randomly initialised model, so the test runs offline.
+    let dir = tempfile::tempdir()?;
+    let config = r#"{
+        "vocab_size": 8,
+        "hidden_size": 8,
+        "num_hidden_layers": 1,
+        "num_attention_heads": 2,
+        "intermediate_size": 16,
+        "hidden_act": "gelu",
+        "hidden_dropout_prob": 0.0,
+        "max_position_embeddings": 16,
+        "type_vocab_size": 2,
+        "initializer_range": 0.02,
+        "layer_norm_eps": 1e-12,
+        "pad_token_id": 0,
+        "classifier_dropout": null,
+        "model_type": "bert"
+    }"#;
+    std::fs::write(dir.path().join("config.json"), config)?;
+    std::fs::write(
+        dir.path().join("tokenizer.json"),
+        r#"{
+            "version": "1.0",
+            "truncation": null,
+            "padding": null,
+            "added_tokens": [],
+            "normalizer": null,
+            "pre_tokenizer": { "type": "Whitespace" },
+            "post_processor": null,
+            "decoder": null,
+            "model": {
+                "type": "WordLevel",
+                "vocab": { "[PAD]": 0, "[UNK]": 1, "hello": 2, "world": 3, "retry": 4 },
+                "unk_token": "[UNK]"
+            }
+        }"#,
+    )?;
+
+    let varmap = VarMap::new();
+    BertModel::load(
+        VarBuilder::from_varmap(&varmap, DTYPE, &Device::Cpu),
+        &serde_json::from_str::<Config>(config)?,
+    )?;
+    varmap.save(dir.path().join("model.safetensors"))?;
+
+    let texts = vec![
+        "hello world".to_string(),
+        "hello world".to_string(),
+        "retry".to_string(),
+    ];
+    let embeddings = super::model::embed(dir.path(), &texts)?;
+
+    assert_eq!(embeddings.len(), 3);
+    assert_eq!(embeddings[0].len(), 8);
+    assert_eq!(embeddings[0], embeddings[1]);
+    for embedding in embeddings {
+        let norm = embedding.iter().map(|e| return e * e).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-4);
+    }
+
+    return Ok(());
+}
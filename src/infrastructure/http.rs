@@ -0,0 +1,223 @@
+#[cfg(test)]
+#[path = "http_test.rs"]
+mod tests;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use reqwest::header::HeaderName;
+use reqwest::header::HeaderValue;
+use reqwest::header::AUTHORIZATION;
+use tokio::process::Command;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::BackendName;
+use crate::domain::models::ConfigError;
+
+/// Tokens minted by each token command, reused until a backend rejects one.
+static TOKENS: Lazy<Mutex<HashMap<String, String>>> = Lazy::new(|| {
+    return Mutex::new(HashMap::new());
+});
+
+/// Clients built for each proxy setting, handed out as clones so requests
+/// share their connection pools.
+static CLIENTS: Lazy<Mutex<HashMap<String, reqwest::Client>>> = Lazy::new(|| {
+    return Mutex::new(HashMap::new());
+});
+
+/// HTTP client for the backends and every other service Oatmeal talks to.
+/// Requests go through `proxy` when it's set, otherwise through the proxy
+/// from `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY`. Either way hosts in
+/// `NO_PROXY` are reached directly.
+pub fn http_client() -> Result<reqwest::Client> {
+    return http_client_with_proxy(&Config::get(ConfigKey::Proxy));
+}
+
+pub fn http_client_with_proxy(proxy: &str) -> Result<reqwest::Client> {
+    let mut clients = CLIENTS.lock().unwrap();
+    if let Some(client) = clients.get(proxy) {
+        return Ok(client.clone());
+    }
+
+    let client = build_client(proxy)?;
+    clients.insert(proxy.to_string(), client.clone());
+    return Ok(client);
+}
+
+fn build_client(proxy: &str) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+    if !proxy.is_empty() {
+        let proxy = match reqwest::Proxy::all(proxy) {
+            Ok(proxy) => proxy.no_proxy(reqwest::NoProxy::from_env()),
+            Err(_) => {
+                bail!(ConfigError::InvalidValue {
+                    key: ConfigKey::Proxy.to_string(),
+                    value: proxy.to_string(),
+                    possible_values: vec![
+                        "http://HOST:PORT".to_string(),
+                        "https://HOST:PORT".to_string(),
+                        "socks5://HOST:PORT".to_string(),
+                        "socks5h://HOST:PORT".to_string(),
+                    ],
+                });
+            }
+        };
+        builder = builder.proxy(proxy);
+    }
+
+    return Ok(builder.build()?);
+}
+
+/// The backend's entry in a `BACKEND=VALUE` per line table.
+fn backend_entry(text: &str, backend: &BackendName) -> Option<String> {
+    return text.lines().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        if name.trim() != backend.to_string() {
+            return None;
+        }
+        return Some(value.trim().to_string());
+    });
+}
+
+/// Runs the token command, unless it minted a token already and `refresh`
+/// isn't set. The token is whatever the command prints.
+async fn mint_token(command: &str, refresh: bool) -> Result<String> {
+    if !refresh {
+        if let Some(token) = TOKENS.lock().unwrap().get(command) {
+            return Ok(token.to_string());
+        }
+    }
+
+    let output = Command::new("sh").args(["-c", command]).output().await?;
+    if !output.status.success() {
+        bail!(
+            "Token command `{command}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if token.is_empty() {
+        bail!("Token command `{command}` didn't print a token");
+    }
+
+    TOKENS
+        .lock()
+        .unwrap()
+        .insert(command.to_string(), token.to_string());
+    return Ok(token);
+}
+
+/// Extra headers and a token command for one backend, from
+/// `backend-headers` and `backend-token-commands`, for gateways in front of
+/// it that want more than an API key.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BackendAuth {
+    pub headers: Vec<(String, String)>,
+    /// Prints a bearer token, sent in place of the backend's own
+    /// `Authorization` header.
+    pub token_command: String,
+}
+
+impl BackendAuth {
+    /// Reads the backend's `Name: value` headers, separated by `;`, and its
+    /// token command.
+    pub fn parse(backend: &BackendName, headers: &str, token_commands: &str) -> BackendAuth {
+        let headers = backend_entry(headers, backend)
+            .unwrap_or_default()
+            .split(';')
+            .filter_map(|header| {
+                let (name, value) = header.split_once(':')?;
+                let name = name.trim();
+                if name.is_empty() {
+                    return None;
+                }
+                return Some((name.to_string(), value.trim().to_string()));
+            })
+            .collect();
+
+        return BackendAuth {
+            headers,
+            token_command: backend_entry(token_commands, backend).unwrap_or_default(),
+        };
+    }
+
+    pub fn from_config(backend: &BackendName) -> BackendAuth {
+        return BackendAuth::parse(
+            backend,
+            &Config::get(ConfigKey::BackendHeaders),
+            &Config::get(ConfigKey::BackendTokenCommands),
+        );
+    }
+
+    /// Adds just the extra headers, for requests that are signed before
+    /// they're sent rather than sent with [`BackendAuth::send`].
+    pub fn add_headers(&self, request: &mut reqwest::Request) -> Result<()> {
+        let headers = request.headers_mut();
+        for (name, value) in &self.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+
+        return Ok(());
+    }
+
+    async fn authorize(&self, request: &mut reqwest::Request, refresh: bool) -> Result<()> {
+        self.add_headers(request)?;
+        if !self.token_command.is_empty() {
+            let token = mint_token(&self.token_command, refresh).await?;
+            request.headers_mut().insert(
+                AUTHORIZATION,
+                HeaderValue::from_str(&format!("Bearer {token}"))?,
+            );
+        }
+
+        return Ok(());
+    }
+
+    /// Sends the request with the headers and token added. When the token
+    /// is rejected with a 401, a new one is minted and the request sent
+    /// again.
+    pub async fn send(&self, builder: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let (client, request) = builder.build_split();
+        let mut request = request?;
+        let retry = request.try_clone();
+
+        self.authorize(&mut request, false).await?;
+        let res = client.execute(request).await?;
+        if res.status() != reqwest::StatusCode::UNAUTHORIZED || self.token_command.is_empty() {
+            return Ok(res);
+        }
+        let mut retry = match retry {
+            Some(retry) => retry,
+            None => return Ok(res),
+        };
+
+        tracing::debug!(
+            command = self.token_command,
+            "token rejected, minting a new one"
+        );
+        self.authorize(&mut retry, true).await?;
+        return Ok(client.execute(retry).await?);
+    }
+}
+
+/// Sends backend requests with the auth configured for the backend.
+#[async_trait]
+pub trait SendAuthorized {
+    async fn send_authorized(self, backend: BackendName) -> Result<reqwest::Response>;
+}
+
+#[async_trait]
+impl SendAuthorized for reqwest::RequestBuilder {
+    #[allow(clippy::implicit_return)]
+    async fn send_authorized(self, backend: BackendName) -> Result<reqwest::Response> {
+        return BackendAuth::from_config(&backend).send(self).await;
+    }
+}
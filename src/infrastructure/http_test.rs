@@ -0,0 +1,146 @@
+use anyhow::Result;
+
+use super::http_client_with_proxy;
+use super::BackendAuth;
+use super::CLIENTS;
+use crate::domain::models::BackendName;
+
+#[tokio::test]
+async fn it_sends_requests_through_the_proxy() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", mockito::Matcher::Any)
+        .match_header("host", "oatmeal.invalid")
+        .with_status(200)
+        .with_body("Hello from the proxy")
+        .create();
+
+    let res = http_client_with_proxy(&server.url())?
+        .get("http://oatmeal.invalid/models")
+        .send()
+        .await?;
+    assert_eq!(res.text().await?, "Hello from the proxy");
+    mock.assert();
+
+    return Ok(());
+}
+
+#[test]
+fn it_accepts_socks_proxies() {
+    assert!(http_client_with_proxy("socks5://127.0.0.1:1080").is_ok());
+    assert!(http_client_with_proxy("").is_ok());
+}
+
+#[test]
+fn it_builds_one_client_per_proxy() -> Result<()> {
+    http_client_with_proxy("socks5://127.0.0.1:1081")?;
+    http_client_with_proxy("socks5://127.0.0.1:1081")?;
+    assert!(CLIENTS
+        .lock()
+        .unwrap()
+        .contains_key("socks5://127.0.0.1:1081"));
+
+    assert!(http_client_with_proxy("not a url either").is_err());
+    assert!(!CLIENTS.lock().unwrap().contains_key("not a url either"));
+
+    return Ok(());
+}
+
+#[test]
+fn it_rejects_invalid_proxies() {
+    let err = http_client_with_proxy("not a url").unwrap_err();
+    assert!(err.to_string().contains("not a url"));
+}
+
+#[test]
+fn it_parses_backend_auth() {
+    let auth = BackendAuth::parse(
+        &BackendName::OpenAI,
+        "ollama=X-Team: local\nopenai=X-Team: ml; X-Env: prod ;",
+        "openai=gateway-token --audience oatmeal",
+    );
+    assert_eq!(
+        auth.headers,
+        vec![
+            ("X-Team".to_string(), "ml".to_string()),
+            ("X-Env".to_string(), "prod".to_string()),
+        ]
+    );
+    assert_eq!(auth.token_command, "gateway-token --audience oatmeal");
+
+    assert_eq!(
+        BackendAuth::parse(&BackendName::Claude, "openai=X-Team: ml", ""),
+        BackendAuth::default()
+    );
+}
+
+#[tokio::test]
+async fn it_sends_the_extra_headers() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/v1/models")
+        .match_header("x-team", "ml")
+        .match_header("x-api-key", "override")
+        .with_status(200)
+        .create();
+
+    let auth = BackendAuth::parse(
+        &BackendName::Claude,
+        "claude=X-Team: ml; x-api-key: override",
+        "",
+    );
+    let res = auth
+        .send(
+            http_client_with_proxy("")?
+                .get(format!("{}/v1/models", server.url()))
+                .header("x-api-key", "abc"),
+        )
+        .await?;
+    assert_eq!(res.status(), 200);
+    mock.assert();
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_mints_a_new_token_when_rejected() -> Result<()> {
+    let dir = tempfile::tempdir()?;
+    let counter = dir.path().join("count");
+    let command = format!(
+        "n=$(($(cat {path} 2>/dev/null || echo 0) + 1)); echo $n > {path}; echo token-$n",
+        path = counter.display()
+    );
+
+    let mut server = mockito::Server::new();
+    let rejected = server
+        .mock("POST", "/v1/chat/completions")
+        .match_header("authorization", "Bearer token-1")
+        .with_status(401)
+        .create();
+    let accepted = server
+        .mock("POST", "/v1/chat/completions")
+        .match_header("authorization", "Bearer token-2")
+        .with_status(200)
+        .with_body("Hello")
+        .expect(2)
+        .create();
+
+    let auth = BackendAuth::parse(&BackendName::OpenAI, "", &format!("openai={command}"));
+    let request = || {
+        return http_client_with_proxy("")
+            .unwrap()
+            .post(format!("{}/v1/chat/completions", server.url()))
+            .header("Authorization", "Bearer abc")
+            .body("{}");
+    };
+    let res = auth.send(request()).await?;
+    assert_eq!(res.text().await?, "Hello");
+    // The new token is reused for the next request.
+    let res = auth.send(request()).await?;
+    assert_eq!(res.status(), 200);
+
+    rejected.assert();
+    accepted.assert();
+
+    return Ok(());
+}
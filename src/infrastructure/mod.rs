@@ -1,2 +1,8 @@
 pub mod backends;
+pub mod bridges;
+pub mod context;
 pub mod editors;
+pub mod embeddings;
+pub mod http;
+pub mod tickets;
+pub mod webhooks;
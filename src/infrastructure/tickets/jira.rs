@@ -0,0 +1,84 @@
+#[cfg(test)]
+#[path = "jira_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Ticket;
+use crate::domain::models::TicketProvider;
+use crate::domain::models::TicketProviderName;
+use crate::infrastructure::http::http_client;
+
+#[derive(Debug, Deserialize)]
+struct IssueFields {
+    summary: String,
+    #[serde(default)]
+    description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueResponse {
+    key: String,
+    fields: IssueFields,
+}
+
+pub struct Jira {
+    url: String,
+    email: String,
+    token: String,
+}
+
+impl Default for Jira {
+    fn default() -> Jira {
+        return Jira {
+            url: Config::get(ConfigKey::JiraURL),
+            email: Config::get(ConfigKey::JiraEmail),
+            token: Config::get(ConfigKey::JiraToken),
+        };
+    }
+}
+
+#[async_trait]
+impl TicketProvider for Jira {
+    fn name(&self) -> TicketProviderName {
+        return TicketProviderName::Jira;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_ticket(&self, key: &str) -> Result<Ticket> {
+        if self.url.is_empty() {
+            bail!("Jira URL is not defined");
+        }
+        if self.token.is_empty() {
+            bail!("Jira token is not defined");
+        }
+
+        let url = self.url.trim_end_matches('/');
+        let res = http_client()?
+            .get(format!(
+                "{url}/rest/api/2/issue/{key}?fields=summary,description"
+            ))
+            .basic_auth(&self.email, Some(&self.token))
+            .header("Accept", "application/json")
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            bail!(format!("Jira request failed with status {}", res.status()));
+        }
+
+        let issue = res.json::<IssueResponse>().await?;
+
+        return Ok(Ticket {
+            url: format!("{url}/browse/{}", issue.key),
+            key: issue.key,
+            title: issue.fields.summary,
+            description: issue.fields.description.unwrap_or_default(),
+        });
+    }
+}
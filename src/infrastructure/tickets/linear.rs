@@ -0,0 +1,94 @@
+#[cfg(test)]
+#[path = "linear_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::Ticket;
+use crate::domain::models::TicketProvider;
+use crate::domain::models::TicketProviderName;
+use crate::infrastructure::http::http_client;
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    identifier: String,
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct IssueData {
+    issue: Option<Issue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GraphQLResponse {
+    data: Option<IssueData>,
+}
+
+pub struct Linear {
+    url: String,
+    token: String,
+}
+
+impl Default for Linear {
+    fn default() -> Linear {
+        return Linear {
+            url: "https://api.linear.app".to_string(),
+            token: Config::get(ConfigKey::LinearToken),
+        };
+    }
+}
+
+#[async_trait]
+impl TicketProvider for Linear {
+    fn name(&self) -> TicketProviderName {
+        return TicketProviderName::Linear;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn get_ticket(&self, key: &str) -> Result<Ticket> {
+        if self.token.is_empty() {
+            bail!("Linear token is not defined");
+        }
+
+        let req = json!({
+            "query": "query Issue($id: String!) { issue(id: $id) { identifier title description url } }",
+            "variables": { "id": key }
+        });
+
+        let res = http_client()?
+            .post(format!("{url}/graphql", url = self.url))
+            .header("Authorization", &self.token)
+            .json(&req)
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            bail!(format!(
+                "Linear request failed with status {}",
+                res.status()
+            ));
+        }
+
+        let body = res.json::<GraphQLResponse>().await?;
+        if let Some(issue) = body.data.and_then(|e| return e.issue) {
+            return Ok(Ticket {
+                key: issue.identifier,
+                title: issue.title,
+                description: issue.description.unwrap_or_default(),
+                url: issue.url,
+            });
+        }
+
+        bail!(format!("Linear issue {key} not found"));
+    }
+}
@@ -0,0 +1,52 @@
+use anyhow::Result;
+
+use super::Linear;
+use crate::domain::models::TicketProvider;
+
+impl Linear {
+    fn with_url(url: String) -> Linear {
+        return Linear {
+            url,
+            token: "abc".to_string(),
+        };
+    }
+}
+
+#[tokio::test]
+async fn it_gets_ticket() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/graphql")
+        .match_header("Authorization", "abc")
+        .match_body(mockito::Matcher::PartialJsonString(
+            r#"{"variables":{"id":"ENG-1"}}"#.to_string(),
+        ))
+        .with_status(200)
+        .with_body(r#"{"data":{"issue":{"identifier":"ENG-1","title":"Add login","description":"Users need to log in.","url":"https://linear.app/t/issue/ENG-1"}}}"#)
+        .create();
+
+    let linear = Linear::with_url(server.url());
+    let ticket = linear.get_ticket("ENG-1").await?;
+    mock.assert();
+
+    assert_eq!(ticket.title, "Add login");
+    assert_eq!(ticket.url, "https://linear.app/t/issue/ENG-1");
+
+    return Ok(());
+}
+
+#[tokio::test]
+async fn it_fails_when_not_found() -> Result<()> {
+    let mut server = mockito::Server::new();
+    server
+        .mock("POST", "/graphql")
+        .with_status(200)
+        .with_body(r#"{"data":{"issue":null}}"#)
+        .create();
+
+    let linear = Linear::with_url(server.url());
+    let res = linear.get_ticket("ENG-1").await;
+    assert_eq!(res.unwrap_err().to_string(), "Linear issue ENG-1 not found");
+
+    return Ok(());
+}
@@ -0,0 +1,24 @@
+pub mod jira;
+pub mod linear;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use crate::domain::models::TicketProviderBox;
+use crate::domain::models::TicketProviderName;
+
+pub struct TicketProviderManager {}
+
+impl TicketProviderManager {
+    pub fn get(name: TicketProviderName) -> Result<TicketProviderBox> {
+        if name == TicketProviderName::Jira {
+            return Ok(Box::<jira::Jira>::default());
+        }
+
+        if name == TicketProviderName::Linear {
+            return Ok(Box::<linear::Linear>::default());
+        }
+
+        bail!(format!("No ticket provider implemented for {name}"))
+    }
+}
@@ -0,0 +1,119 @@
+#[cfg(test)]
+#[path = "discord_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::json;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::conversation_markdown;
+use crate::domain::models::Message;
+use crate::domain::models::Webhook;
+use crate::domain::models::WebhookName;
+use crate::infrastructure::http::http_client;
+
+/// Discord rejects messages over 2000 characters. Leaves room to close and
+/// reopen code blocks split across messages.
+const MAX_CONTENT_LENGTH: usize = 1900;
+
+/// Splits Markdown in to messages Discord accepts, breaking on lines and
+/// keeping code blocks fenced in each part.
+fn split_content(markdown: &str) -> Vec<String> {
+    let mut res: Vec<String> = vec![];
+    let mut current: Vec<String> = vec![];
+    let mut current_len = 0;
+    let mut fence: Option<String> = None;
+
+    for line in markdown.lines() {
+        if current_len + line.len() + 1 > MAX_CONTENT_LENGTH && !current.is_empty() {
+            if fence.is_some() {
+                current.push("```".to_string());
+            }
+            res.push(current.join("\n"));
+            current = vec![];
+            current_len = 0;
+            if let Some(open) = &fence {
+                current.push(open.to_string());
+                current_len += open.len() + 1;
+            }
+        }
+
+        if line.trim().starts_with("```") {
+            if fence.is_some() {
+                fence = None;
+            } else {
+                fence = Some(line.trim().to_string());
+            }
+        }
+
+        let mut line = line.to_string();
+        while line.len() > MAX_CONTENT_LENGTH {
+            let mut idx = MAX_CONTENT_LENGTH;
+            while !line.is_char_boundary(idx) {
+                idx -= 1;
+            }
+            let rest = line.split_off(idx);
+            res.push(line);
+            line = rest;
+        }
+
+        current_len += line.len() + 1;
+        current.push(line);
+    }
+
+    if !current.is_empty() {
+        res.push(current.join("\n"));
+    }
+
+    return res;
+}
+
+pub struct Discord {
+    url: String,
+}
+
+impl Default for Discord {
+    fn default() -> Discord {
+        return Discord {
+            url: Config::get(ConfigKey::DiscordWebhookURL),
+        };
+    }
+}
+
+#[async_trait]
+impl Webhook for Discord {
+    fn name(&self) -> WebhookName {
+        return WebhookName::Discord;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn post(&self, messages: &[Message]) -> Result<()> {
+        if self.url.is_empty() {
+            bail!("Discord webhook URL is not defined");
+        }
+
+        let client = http_client()?;
+        for content in split_content(&conversation_markdown(messages)) {
+            let res = client
+                .post(&self.url)
+                .json(&json!({
+                    "username": "Oatmeal",
+                    "content": content,
+                }))
+                .send()
+                .await?;
+
+            if !res.status().is_success() {
+                bail!(format!(
+                    "Discord webhook failed with status {}",
+                    res.status()
+                ));
+            }
+        }
+
+        return Ok(());
+    }
+}
@@ -0,0 +1,56 @@
+use anyhow::Result;
+
+use super::split_content;
+use super::Discord;
+use super::MAX_CONTENT_LENGTH;
+use crate::domain::models::Author;
+use crate::domain::models::Message;
+use crate::domain::models::Webhook;
+
+#[test]
+fn it_keeps_short_content_whole() {
+    assert_eq!(
+        split_content("Hello\nWorld"),
+        vec!["Hello\nWorld".to_string()]
+    );
+}
+
+#[test]
+fn it_splits_long_content_keeping_code_fenced() {
+    let code = (0..200)
+        .map(|e| return format!("let value_{e} = {e};"))
+        .collect::<Vec<String>>()
+        .join("\n");
+    let res = split_content(&format!("Here:\n```rust\n{code}\n```"));
+
+    assert!(res.len() > 1);
+    for part in res.iter() {
+        assert!(part.len() <= MAX_CONTENT_LENGTH + 4);
+        assert_eq!(part.matches("```").count() % 2, 0);
+    }
+    assert!(res[1].starts_with("```rust\n"));
+}
+
+#[tokio::test]
+async fn it_posts() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/hook")
+        .match_body(mockito::Matcher::Json(serde_json::json!({
+            "username": "Oatmeal",
+            "content": "**alice:**\nHello",
+        })))
+        .with_status(204)
+        .create();
+
+    let mut message = Message::new(Author::User, "Hello");
+    message.set_username("alice");
+
+    let discord = Discord {
+        url: format!("{}/hook", server.url()),
+    };
+    discord.post(&[message]).await?;
+    mock.assert();
+
+    return Ok(());
+}
@@ -0,0 +1,24 @@
+pub mod discord;
+pub mod slack;
+
+use anyhow::bail;
+use anyhow::Result;
+
+use crate::domain::models::WebhookBox;
+use crate::domain::models::WebhookName;
+
+pub struct WebhookManager {}
+
+impl WebhookManager {
+    pub fn get(name: WebhookName) -> Result<WebhookBox> {
+        if name == WebhookName::Discord {
+            return Ok(Box::<discord::Discord>::default());
+        }
+
+        if name == WebhookName::Slack {
+            return Ok(Box::<slack::Slack>::default());
+        }
+
+        bail!(format!("No webhook implemented for {name}"))
+    }
+}
@@ -0,0 +1,94 @@
+#[cfg(test)]
+#[path = "slack_test.rs"]
+mod tests;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::json;
+
+use crate::configuration::Config;
+use crate::configuration::ConfigKey;
+use crate::domain::models::conversation_markdown;
+use crate::domain::models::Message;
+use crate::domain::models::Webhook;
+use crate::domain::models::WebhookName;
+use crate::infrastructure::http::http_client;
+
+static BOLD_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(r"\*\*(?P<text>[^*\n]+)\*\*").unwrap();
+});
+
+static LINK_RE: Lazy<Regex> = Lazy::new(|| {
+    return Regex::new(r"\[(?P<text>[^\]\n]+)\]\((?P<url>[^)\s]+)\)").unwrap();
+});
+
+/// Converts Markdown to Slack's mrkdwn. Code blocks are kept as is, apart from
+/// dropping the language Slack doesn't support.
+fn to_mrkdwn(markdown: &str) -> String {
+    let mut in_codeblock = false;
+    return markdown
+        .lines()
+        .map(|line| {
+            if line.trim().starts_with("```") {
+                in_codeblock = !in_codeblock;
+                return "```".to_string();
+            }
+            if in_codeblock {
+                return line.to_string();
+            }
+
+            let mut line = line.to_string();
+            let trimmed = line.trim_start();
+            if trimmed.starts_with('#') {
+                line = format!("**{}**", trimmed.trim_start_matches('#').trim());
+            }
+
+            let line = BOLD_RE.replace_all(&line, "*$text*");
+            return LINK_RE.replace_all(&line, "<$url|$text>").to_string();
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+}
+
+pub struct Slack {
+    url: String,
+}
+
+impl Default for Slack {
+    fn default() -> Slack {
+        return Slack {
+            url: Config::get(ConfigKey::SlackWebhookURL),
+        };
+    }
+}
+
+#[async_trait]
+impl Webhook for Slack {
+    fn name(&self) -> WebhookName {
+        return WebhookName::Slack;
+    }
+
+    #[allow(clippy::implicit_return)]
+    async fn post(&self, messages: &[Message]) -> Result<()> {
+        if self.url.is_empty() {
+            bail!("Slack webhook URL is not defined");
+        }
+
+        let res = http_client()?
+            .post(&self.url)
+            .json(&json!({
+                "text": to_mrkdwn(&conversation_markdown(messages)),
+            }))
+            .send()
+            .await?;
+
+        if !res.status().is_success() {
+            bail!(format!("Slack webhook failed with status {}", res.status()));
+        }
+
+        return Ok(());
+    }
+}
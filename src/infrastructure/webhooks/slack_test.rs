@@ -0,0 +1,38 @@
+use anyhow::Result;
+
+use super::to_mrkdwn;
+use super::Slack;
+use crate::domain::models::Author;
+use crate::domain::models::Message;
+use crate::domain::models::Webhook;
+
+#[test]
+fn it_converts_to_mrkdwn() {
+    assert_eq!(
+        to_mrkdwn("## Fix\nSee **this** [doc](https://example.com).\n```rust\nlet x = **y;\n```"),
+        "*Fix*\nSee *this* <https://example.com|doc>.\n```\nlet x = **y;\n```"
+    );
+}
+
+#[tokio::test]
+async fn it_posts() -> Result<()> {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("POST", "/hook")
+        .match_body(mockito::Matcher::Json(serde_json::json!({
+            "text": "*Oatmeal:*\nHello",
+        })))
+        .with_status(200)
+        .create();
+
+    let mut message = Message::new(Author::Model, "Hello");
+    message.set_username("Oatmeal");
+
+    let slack = Slack {
+        url: format!("{}/hook", server.url()),
+    };
+    slack.post(&[message]).await?;
+    mock.assert();
+
+    return Ok(());
+}
@@ -12,6 +12,8 @@ use std::process;
 use anyhow::Error;
 use domain::models::Action;
 use domain::models::BackendName;
+use domain::models::ColorMode;
+use domain::models::ErrorReport;
 use domain::models::Event;
 use domain::services::clipboard::ClipboardService;
 use infrastructure::backends::BackendManager;
@@ -24,12 +26,21 @@ use crate::application::ui;
 use crate::configuration::Config;
 use crate::configuration::ConfigKey;
 use crate::domain::services::actions::ActionsService;
+use crate::domain::services::StartupProfile;
+use crate::domain::services::Syntaxes;
 
 #[cfg(feature = "dhat-heap")]
 #[global_allocator]
 static ALLOC: dhat::Alloc = dhat::Alloc;
 
 fn handle_error(err: Error) {
+    // Typed errors are something the user can fix, rather than a bug to
+    // report.
+    if let Some(report) = ErrorReport::from_error(&err) {
+        eprintln!("{}", Paint::red(report.render()));
+        process::exit(1);
+    }
+
     eprintln!(
             "{}",
             Paint::red(format!(
@@ -66,6 +77,11 @@ async fn main() {
         better_panic::Settings::auto().create_panic_handler()(panic_info);
     }));
 
+    StartupProfile::begin();
+    if ColorMode::from_env() == ColorMode::None {
+        Paint::disable();
+    }
+
     #[cfg(feature = "dhat-heap")]
     let _profiler = dhat::Profiler::new_heap();
 
@@ -98,6 +114,8 @@ async fn main() {
     if !ready_res.unwrap() {
         process::exit(0);
     }
+    StartupProfile::mark("config loaded");
+    Syntaxes::preload();
 
     let (action_tx, mut action_rx) = mpsc::unbounded_channel::<Action>();
     let (event_tx, event_rx) = mpsc::unbounded_channel::<Event>();
@@ -133,6 +151,10 @@ async fn main() {
         handle_error(res.unwrap_err());
     }
 
+    if Config::get(ConfigKey::ProfileStartup) == "true" {
+        eprintln!("{}", StartupProfile::report());
+    }
+
     #[cfg(feature = "dhat-heap")]
     drop(_profiler);
 